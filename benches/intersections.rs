@@ -0,0 +1,78 @@
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use ray_tracing_rust::math::{Float, Matrix4, Tuple4D};
+use ray_tracing_rust::{Bounds, Object, Ray, DEFAULT_BVH_LEAF_SIZE};
+
+fn ray() -> Ray {
+    Ray::new(
+        Tuple4D::new_point(0.1, 0.2, -5.0),
+        Tuple4D::new_vector(0.01, -0.02, 1.0).normalize(),
+    )
+}
+
+// a wavy sheet of 2 * size * size triangles in the xy plane around the origin
+fn sheet(size: usize) -> Vec<Object> {
+    let corner = |x: usize, y: usize| {
+        let (x, y) = (
+            x as Float / size as Float * 4.0 - 2.0,
+            y as Float / size as Float * 4.0 - 2.0,
+        );
+        Tuple4D::new_point(x, y, (x * 3.0).sin() * 0.1)
+    };
+    (0..size)
+        .flat_map(|y| (0..size).map(move |x| (x, y)))
+        .flat_map(|(x, y)| {
+            [
+                Object::triangle(corner(x, y), corner(x + 1, y), corner(x, y + 1)),
+                Object::triangle(corner(x + 1, y), corner(x + 1, y + 1), corner(x, y + 1)),
+            ]
+        })
+        .collect()
+}
+
+fn shapes(c: &mut Criterion) {
+    let ray = ray();
+    let mut sphere = Object::sphere();
+    sphere.transform = Matrix4::scaling(1.5, 1.0, 1.0).into();
+    c.bench_function("ray sphere", |bench| {
+        bench.iter(|| black_box(&sphere).intersect(black_box(&ray)))
+    });
+    let triangle = Object::triangle(
+        Tuple4D::new_point(-1.0, -1.0, 0.0),
+        Tuple4D::new_point(1.0, -1.0, 0.0),
+        Tuple4D::new_point(0.0, 1.0, 0.0),
+    );
+    c.bench_function("ray triangle", |bench| {
+        bench.iter(|| black_box(&triangle).intersect(black_box(&ray)))
+    });
+    let bounds = Bounds::new(
+        Tuple4D::new_point(-1.0, -1.0, -1.0),
+        Tuple4D::new_point(1.0, 1.0, 1.0),
+    );
+    c.bench_function("ray bounds", |bench| {
+        bench.iter(|| black_box(&bounds).intersects(black_box(&ray)))
+    });
+}
+
+fn hierarchies(c: &mut Criterion) {
+    let ray = ray();
+    // 20000 triangles
+    let flat = Object::group(sheet(100));
+    let bvh = Object::group(sheet(100)).into_bvh(DEFAULT_BVH_LEAF_SIZE);
+    c.bench_function("bvh traversal", |bench| {
+        bench.iter(|| black_box(&bvh).intersect(black_box(&ray)))
+    });
+    c.bench_function("flat group traversal", |bench| {
+        bench.iter(|| black_box(&flat).intersect(black_box(&ray)))
+    });
+    c.bench_function("bvh build", |bench| {
+        bench.iter_with_setup(
+            || Object::group(sheet(100)),
+            |group| group.into_bvh(DEFAULT_BVH_LEAF_SIZE),
+        )
+    });
+}
+
+criterion_group!(benches, shapes, hierarchies);
+criterion_main!(benches);
@@ -0,0 +1,38 @@
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use ray_tracing_rust::math::{Matrix4, Tuple4D};
+
+fn transform() -> Matrix4 {
+    Matrix4::translation(1.0, -2.0, 3.0)
+        * Matrix4::rotation_y(0.7)
+        * Matrix4::rotation_x(-0.3)
+        * Matrix4::scaling(2.0, 0.5, 1.5)
+}
+
+fn matrices(c: &mut Criterion) {
+    let (a, b) = (
+        transform(),
+        Matrix4::view_transform(
+            Tuple4D::new_point(0.0, 1.5, -5.0),
+            Tuple4D::new_point(0.0, 1.0, 0.0),
+            Tuple4D::new_vector(0.0, 1.0, 0.0),
+        ),
+    );
+    let point = Tuple4D::new_point(1.0, 2.0, 3.0);
+    c.bench_function("matrix4 multiply", |bench| {
+        bench.iter(|| black_box(a) * black_box(b))
+    });
+    c.bench_function("matrix4 times tuple", |bench| {
+        bench.iter(|| black_box(a) * black_box(point))
+    });
+    c.bench_function("matrix4 inverse", |bench| {
+        bench.iter(|| black_box(a).inverse())
+    });
+    c.bench_function("matrix4 transpose", |bench| {
+        bench.iter(|| black_box(a).transpose())
+    });
+}
+
+criterion_group!(benches, matrices);
+criterion_main!(benches);
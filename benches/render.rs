@@ -0,0 +1,22 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use ray_tracing_rust::scenes;
+
+fn render(c: &mut Criterion) {
+    let mut group = c.benchmark_group("render");
+    group.sample_size(10);
+    let (world, mut camera) = scenes::three_spheres(64, 48);
+    // a single thread so the numbers do not depend on the machine's core count
+    camera.threads = 1;
+    group.bench_function("three spheres 64x48", |bench| {
+        bench.iter(|| camera.render(&world))
+    });
+    let (world, mut camera) = scenes::cornell_box(32, 32);
+    camera.threads = 1;
+    group.bench_function("cornell box 32x32", |bench| {
+        bench.iter(|| camera.render(&world))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, render);
+criterion_main!(benches);
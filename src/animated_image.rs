@@ -0,0 +1,330 @@
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+use std::time::Duration;
+
+use crate::{Canvas, ColorEncoding};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AnimatedImageOptions {
+    // how long every frame is shown, gif rounds it to hundredths of a second
+    pub frame_delay: Duration,
+    // how often the animation is played, None repeats it forever. Some(0) is rejected, gif and
+    // apng disagree on what it means
+    pub plays: Option<u16>,
+    pub encoding: ColorEncoding,
+    // the following only apply to gif, which is limited to a palette of at most 256 colors
+    pub colors: usize,
+    // from 1 for the best palette to 30 for the fastest quantization
+    pub quantization_speed: i32,
+    // one palette for the whole animation instead of one per frame, so the same color does not
+    // flicker between frames
+    pub shared_palette: bool,
+}
+
+impl Default for AnimatedImageOptions {
+    fn default() -> Self {
+        AnimatedImageOptions {
+            frame_delay: Duration::from_millis(40),
+            plays: None,
+            encoding: ColorEncoding::Srgb,
+            colors: 256,
+            quantization_speed: 10,
+            shared_palette: true,
+        }
+    }
+}
+
+// writes the frames as an animated .gif or .png, which all have to be of the same size
+#[cfg_attr(not(any(feature = "gif", feature = "png")), allow(unused_variables))]
+pub fn save_animated(
+    frames: &[Canvas],
+    filename: &str,
+    options: &AnimatedImageOptions,
+) -> std::io::Result<()> {
+    let Some(first) = frames.first() else {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "an animation needs frames",
+        ));
+    };
+    if frames
+        .iter()
+        .any(|frame| (frame.width(), frame.height()) != (first.width(), first.height()))
+    {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "the frames of an animation need to have the same size",
+        ));
+    }
+    if options.plays == Some(0) {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "an animation has to be played at least once, None plays it forever",
+        ));
+    }
+    let extension = Path::new(filename)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.to_ascii_lowercase());
+    let frames: Vec<Canvas> = frames
+        .iter()
+        .map(|frame| frame.encode(options.encoding))
+        .collect();
+    match extension.as_deref() {
+        #[cfg(feature = "gif")]
+        Some("gif") => {
+            let file = std::fs::File::create(filename)?;
+            write_gif(std::io::BufWriter::new(file), &frames, options)
+        }
+        #[cfg(feature = "png")]
+        Some("png" | "apng") => {
+            let file = std::fs::File::create(filename)?;
+            write_apng(std::io::BufWriter::new(file), &frames, options)
+        }
+        _ => Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("unsupported animation format of {filename}"),
+        )),
+    }
+}
+
+// the frames are written as they are, save_animated encodes them first
+#[cfg(feature = "gif")]
+pub fn write_gif<W: std::io::Write>(
+    writer: W,
+    frames: &[Canvas],
+    options: &AnimatedImageOptions,
+) -> std::io::Result<()> {
+    let to_io_error = |err: gif::EncodingError| match err {
+        gif::EncodingError::Io(err) => err,
+        err => Error::new(ErrorKind::InvalidInput, err.to_string()),
+    };
+    let Some(first) = frames.first() else {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "an animation needs frames",
+        ));
+    };
+    let (Ok(width), Ok(height)) = (u16::try_from(first.width()), u16::try_from(first.height()))
+    else {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "gif images are at most 65535 pixels wide and high",
+        ));
+    };
+    let pixels: Vec<Vec<u8>> = frames.iter().map(rgba_bytes).collect();
+    let shared = options.shared_palette.then(|| {
+        quantizer(
+            &pixels.iter().map(Vec::as_slice).collect::<Vec<_>>(),
+            options,
+        )
+    });
+    let global_palette = shared
+        .as_ref()
+        .map_or(vec![], color_quant::NeuQuant::color_map_rgb);
+    let mut encoder =
+        gif::Encoder::new(writer, width, height, &global_palette).map_err(to_io_error)?;
+    match options.plays {
+        None => encoder
+            .set_repeat(gif::Repeat::Infinite)
+            .map_err(to_io_error)?,
+        // without the repeat extension the animation is played once
+        Some(0 | 1) => {}
+        Some(plays) => encoder
+            .set_repeat(gif::Repeat::Finite(plays - 1))
+            .map_err(to_io_error)?,
+    }
+    let delay = (options.frame_delay.as_millis() / 10).min(u16::MAX as u128) as u16;
+    for pixels in &pixels {
+        let local;
+        let (quantizer, palette) = match &shared {
+            Some(shared) => (shared, None),
+            None => {
+                local = quantizer(&[pixels], options);
+                (&local, Some(local.color_map_rgb()))
+            }
+        };
+        let indices: Vec<u8> = pixels
+            .chunks_exact(4)
+            .map(|pixel| quantizer.index_of(pixel) as u8)
+            .collect();
+        let frame = gif::Frame {
+            width,
+            height,
+            delay,
+            palette,
+            buffer: indices.into(),
+            ..gif::Frame::default()
+        };
+        encoder.write_frame(&frame).map_err(to_io_error)?;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "gif")]
+fn rgba_bytes(canvas: &Canvas) -> Vec<u8> {
+    canvas
+        .iter()
+        .flat_map(|color| {
+            let [r, g, b] = color.to_rgb8();
+            [r, g, b, 255]
+        })
+        .collect()
+}
+
+// palette for the pixels of all the given frames. Long animations are sampled, the quantizer only
+// looks at a fraction of the pixels anyway
+#[cfg(feature = "gif")]
+fn quantizer(frames: &[&[u8]], options: &AnimatedImageOptions) -> color_quant::NeuQuant {
+    const MAX_PIXELS: usize = 1 << 20;
+    let total = frames.iter().map(|pixels| pixels.len() / 4).sum::<usize>();
+    let step = total.div_ceil(MAX_PIXELS).max(1);
+    let samples: Vec<u8> = frames
+        .iter()
+        .flat_map(|pixels| pixels.chunks_exact(4).step_by(step))
+        .flatten()
+        .copied()
+        .collect();
+    color_quant::NeuQuant::new(
+        options.quantization_speed.clamp(1, 30),
+        options.colors.clamp(2, 256),
+        &samples,
+    )
+}
+
+// the frames are written as they are, save_animated encodes them first
+#[cfg(feature = "png")]
+pub fn write_apng<W: std::io::Write>(
+    writer: W,
+    frames: &[Canvas],
+    options: &AnimatedImageOptions,
+) -> std::io::Result<()> {
+    let to_io_error = |err: png::EncodingError| match err {
+        png::EncodingError::IoError(err) => err,
+        err => Error::new(ErrorKind::InvalidInput, err.to_string()),
+    };
+    let Some(first) = frames.first() else {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "an animation needs frames",
+        ));
+    };
+    let mut encoder = png::Encoder::new(writer, first.width() as u32, first.height() as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    // 0 plays forever
+    let plays = options.plays.map_or(0, u32::from);
+    encoder
+        .set_animated(frames.len() as u32, plays)
+        .map_err(to_io_error)?;
+    let delay = options.frame_delay.as_millis().min(u16::MAX as u128) as u16;
+    encoder.set_frame_delay(delay, 1000).map_err(to_io_error)?;
+    let mut writer = encoder.write_header().map_err(to_io_error)?;
+    for frame in frames {
+        writer
+            .write_image_data(&frame.to_rgb8_bytes())
+            .map_err(to_io_error)?;
+    }
+    writer.finish().map_err(to_io_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Color;
+
+    fn frames() -> Vec<Canvas> {
+        [Color::new(1.0, 0.0, 0.0), Color::new(0.0, 0.0, 1.0)]
+            .into_iter()
+            .map(|color| {
+                let mut canvas = Canvas::create_canvas(4, 3);
+                canvas.write_pixel(1, 1, color);
+                canvas
+            })
+            .collect()
+    }
+
+    #[test]
+    fn frames_have_to_match() {
+        let mut frames = frames();
+        frames.push(Canvas::create_canvas(2, 2));
+        let options = AnimatedImageOptions::default();
+        let err = save_animated(&frames, "animation.gif", &options).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        let err = save_animated(&[], "animation.gif", &options).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        let err = save_animated(&frames[..2], "animation.ppm", &options).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        let never = AnimatedImageOptions {
+            plays: Some(0),
+            ..options
+        };
+        let err = save_animated(&frames[..2], "animation.gif", &never).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    }
+
+    #[cfg(feature = "gif")]
+    #[test]
+    fn gif_frames() {
+        let options = AnimatedImageOptions {
+            frame_delay: Duration::from_millis(120),
+            ..AnimatedImageOptions::default()
+        };
+        let mut bytes = vec![];
+        write_gif(&mut bytes, &frames(), &options).unwrap();
+        assert!(bytes.starts_with(b"GIF89a"));
+        // graphic control extensions with the delay of 12 hundredths of a second
+        let delays = bytes
+            .windows(6)
+            .filter(|window| window[..3] == [0x21, 0xf9, 0x04] && window[4..] == [12, 0])
+            .count();
+        assert_eq!(delays, 2);
+        assert_eq!(bytes.last(), Some(&0x3b));
+    }
+
+    #[cfg(feature = "gif")]
+    #[test]
+    fn gif_with_a_palette_per_frame() {
+        let options = AnimatedImageOptions {
+            shared_palette: false,
+            colors: 16,
+            plays: Some(1),
+            ..AnimatedImageOptions::default()
+        };
+        let mut bytes = vec![];
+        write_gif(&mut bytes, &frames(), &options).unwrap();
+        // no netscape extension, the animation plays once
+        assert!(!bytes.windows(11).any(|window| window == b"NETSCAPE2.0"));
+        let mut decoder = gif::DecodeOptions::new()
+            .read_info(std::io::Cursor::new(bytes))
+            .unwrap();
+        let mut frames = 0;
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            assert!(frame.palette.is_some());
+            frames += 1;
+        }
+        assert_eq!(frames, 2);
+    }
+
+    #[cfg(feature = "png")]
+    #[test]
+    fn apng_frames() {
+        let options = AnimatedImageOptions {
+            plays: Some(3),
+            ..AnimatedImageOptions::default()
+        };
+        let mut bytes = vec![];
+        write_apng(&mut bytes, &frames(), &options).unwrap();
+        let decoder = png::Decoder::new(std::io::Cursor::new(bytes));
+        let mut reader = decoder.read_info().unwrap();
+        let control = reader.info().animation_control.unwrap();
+        assert_eq!((control.num_frames, control.num_plays), (2, 3));
+        let mut pixels = vec![0; reader.output_buffer_size().unwrap()];
+        reader.next_frame(&mut pixels).unwrap();
+        assert_eq!(pixels[15..18], [255, 0, 0]);
+        reader.next_frame(&mut pixels).unwrap();
+        assert_eq!(pixels[15..18], [0, 0, 255]);
+        let frame = reader.info().frame_control.unwrap();
+        assert_eq!((frame.delay_num, frame.delay_den), (40, 1000));
+    }
+}
@@ -0,0 +1,559 @@
+use std::fmt;
+use std::io;
+
+use crate::math::{lerp, smoothstep, Float, Matrix4, Quaternion, Spline, Tuple4D};
+use crate::{Camera, Canvas, Color, FrameSink, ImageOptions, World};
+
+// values that can be blended between keyframes, t goes from 0 at self to 1 at other
+pub trait Interpolate: Clone {
+    fn interpolate(&self, other: &Self, t: Float) -> Self;
+}
+
+impl Interpolate for Float {
+    fn interpolate(&self, other: &Self, t: Float) -> Self {
+        lerp(*self, *other, t)
+    }
+}
+
+impl Interpolate for Color {
+    fn interpolate(&self, other: &Self, t: Float) -> Self {
+        lerp(*self, *other, t)
+    }
+}
+
+impl Interpolate for Tuple4D {
+    fn interpolate(&self, other: &Self, t: Float) -> Self {
+        lerp(*self, *other, t)
+    }
+}
+
+// decomposed into translation, rotation and scale which are interpolated separately, so rotations
+// stay rigid in between. Shears are not kept
+impl Interpolate for Matrix4 {
+    fn interpolate(&self, other: &Self, t: Float) -> Self {
+        let (translation, rotation, scale) = decompose(self);
+        let (other_translation, other_rotation, other_scale) = decompose(other);
+        compose(
+            translation.interpolate(&other_translation, t),
+            rotation.slerp(other_rotation, t),
+            scale.interpolate(&other_scale, t),
+        )
+    }
+}
+
+// how the value changes from a key to the next one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Easing {
+    #[default]
+    Linear,
+    // starts slowly and speeds up
+    EaseIn,
+    // slows down towards the next key
+    EaseOut,
+    EaseInOut,
+    // catmull-rom spline through the neighboring keys, so the motion does not change direction
+    // abruptly at the keys
+    CubicSpline,
+}
+
+impl Easing {
+    // the fraction of the way to the next key at the fraction t of the time to it
+    fn ease(self, t: Float) -> Float {
+        match self {
+            Easing::Linear | Easing::CubicSpline => t,
+            Easing::EaseIn => t * t,
+            Easing::EaseOut => 1.0 - (1.0 - t) * (1.0 - t),
+            Easing::EaseInOut => smoothstep(0.0, 1.0, t),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keyframe<T> {
+    pub time: Float,
+    pub value: T,
+    // easing towards the next key
+    pub easing: Easing,
+}
+
+// values at points in time in seconds, blended in between. Before the first and after the last key
+// the value of that key is held
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keyframes<T> {
+    keys: Vec<Keyframe<T>>,
+}
+
+impl<T: Interpolate> Keyframes<T> {
+    // linear interpolation between the keys, which do not have to be sorted by time
+    pub fn new(keys: Vec<(Float, T)>) -> Keyframes<T> {
+        Keyframes::with_easing(
+            keys.into_iter()
+                .map(|(time, value)| (time, value, Easing::Linear))
+                .collect(),
+        )
+    }
+
+    // the easing of every key is used up to the next key
+    pub fn with_easing(keys: Vec<(Float, T, Easing)>) -> Keyframes<T> {
+        assert!(!keys.is_empty(), "keyframes need at least one key");
+        let mut keys: Vec<Keyframe<T>> = keys
+            .into_iter()
+            .map(|(time, value, easing)| Keyframe {
+                time,
+                value,
+                easing,
+            })
+            .collect();
+        keys.sort_by(|a, b| a.time.total_cmp(&b.time));
+        Keyframes { keys }
+    }
+
+    pub fn keys(&self) -> &[Keyframe<T>] {
+        &self.keys
+    }
+
+    pub fn value_at(&self, time: Float) -> T {
+        let next = self.keys.partition_point(|key| key.time <= time);
+        if next == 0 {
+            return self.keys[0].value.clone();
+        }
+        let start = &self.keys[next - 1];
+        let Some(end) = self.keys.get(next) else {
+            return start.value.clone();
+        };
+        if start.easing == Easing::CubicSpline {
+            return self.spline(next - 1, time);
+        }
+        let t = start
+            .easing
+            .ease((time - start.time) / (end.time - start.time));
+        start.value.interpolate(&end.value, t)
+    }
+
+    // catmull-rom spline between the keys idx and idx + 1 in the form of Barry and Goldman, which
+    // only blends values and so works for everything that can be interpolated. Missing neighbors
+    // at the ends repeat the end keys
+    fn spline(&self, idx: usize, time: Float) -> T {
+        let (k1, k2) = (&self.keys[idx], &self.keys[idx + 1]);
+        let (t0, p0) = match idx.checked_sub(1) {
+            Some(previous) => (self.keys[previous].time, &self.keys[previous].value),
+            None => (2.0 * k1.time - k2.time, &k1.value),
+        };
+        let (t3, p3) = match self.keys.get(idx + 2) {
+            Some(key) => (key.time, &key.value),
+            None => (2.0 * k2.time - k1.time, &k2.value),
+        };
+        let (t1, p1, t2, p2) = (k1.time, &k1.value, k2.time, &k2.value);
+        let blend = |a: &T, b: &T, start: Float, end: Float| {
+            a.interpolate(b, (time - start) / (end - start))
+        };
+        let a1 = blend(p0, p1, t0, t1);
+        let a2 = blend(p1, p2, t1, t2);
+        let a3 = blend(p2, p3, t2, t3);
+        let b1 = blend(&a1, &a2, t0, t2);
+        let b2 = blend(&a2, &a3, t1, t3);
+        blend(&b1, &b2, t1, t2)
+    }
+}
+
+// moves the camera along a path between two points in time while it keeps looking at the target.
+// Before the start and after the end the camera waits at the ends of the path
+#[derive(Debug, Clone, PartialEq)]
+pub struct CameraRig {
+    pub path: Spline,
+    pub target: Tuple4D,
+    pub up: Tuple4D,
+    pub start_time: Float,
+    pub end_time: Float,
+}
+
+impl CameraRig {
+    pub fn new(path: Spline, target: Tuple4D, start_time: Float, end_time: Float) -> CameraRig {
+        CameraRig {
+            path,
+            target,
+            up: Tuple4D::new_vector(0.0, 1.0, 0.0),
+            start_time,
+            end_time,
+        }
+    }
+
+    pub fn transform_at(&self, time: Float) -> Matrix4 {
+        let duration = self.end_time - self.start_time;
+        let t = if duration > 0.0 {
+            (time - self.start_time) / duration
+        } else {
+            1.0
+        };
+        Matrix4::view_transform(self.path.point_at(t), self.target, self.up)
+    }
+}
+
+type Track = Box<dyn Fn(&mut World, &mut Camera, Float) + Send + Sync>;
+
+// changes the world and the camera over time and renders the frames. Every track sets its values
+// from the time alone, so frames can be rendered in any order
+pub struct Animation {
+    pub frame_count: usize,
+    pub frames_per_second: Float,
+    tracks: Vec<Track>,
+}
+
+impl fmt::Debug for Animation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Animation")
+            .field("frame_count", &self.frame_count)
+            .field("frames_per_second", &self.frames_per_second)
+            .field("tracks", &self.tracks.len())
+            .finish()
+    }
+}
+
+impl Animation {
+    pub fn new(frame_count: usize, frames_per_second: Float) -> Animation {
+        Animation {
+            frame_count,
+            frames_per_second,
+            tracks: vec![],
+        }
+    }
+
+    // time of the frame in seconds, the first frame is at 0
+    pub fn time(&self, frame: usize) -> Float {
+        frame as Float / self.frames_per_second
+    }
+
+    // update is called with the time of every frame before it is rendered
+    pub fn add_track(
+        &mut self,
+        update: impl Fn(&mut World, &mut Camera, Float) + Send + Sync + 'static,
+    ) {
+        self.tracks.push(Box::new(update));
+    }
+
+    // object is the index into the objects of the world
+    pub fn animate_object_transform(&mut self, object: usize, keyframes: Keyframes<Matrix4>) {
+        self.add_track(move |world, _, time| {
+            world.objects[object].transform = keyframes.value_at(time).into();
+        });
+    }
+
+    pub fn animate_camera_transform(&mut self, keyframes: Keyframes<Matrix4>) {
+        self.add_track(move |_, camera, time| camera.transform = keyframes.value_at(time).into());
+    }
+
+    pub fn follow_camera_path(&mut self, rig: CameraRig) {
+        self.add_track(move |_, camera, time| camera.transform = rig.transform_at(time).into());
+    }
+
+    // light is the index into the lights of the world
+    pub fn animate_light_intensity(&mut self, light: usize, keyframes: Keyframes<Color>) {
+        self.add_track(move |world, _, time| {
+            world.lights[light].set_intensity(keyframes.value_at(time));
+        });
+    }
+
+    // sets the world and camera to the state at the frame
+    pub fn apply(&self, world: &mut World, camera: &mut Camera, frame: usize) {
+        let time = self.time(frame);
+        for track in &self.tracks {
+            track(world, camera, time);
+        }
+    }
+
+    // renders the frames in order and hands each one to on_frame, which stops the animation by
+    // returning an error. World and camera are left in the state of the last frame
+    pub fn render_each(
+        &self,
+        world: &mut World,
+        camera: &mut Camera,
+        mut on_frame: impl FnMut(usize, Canvas) -> io::Result<()>,
+    ) -> io::Result<()> {
+        for frame in 0..self.frame_count {
+            self.apply(world, camera, frame);
+            on_frame(frame, camera.render(world))?;
+        }
+        Ok(())
+    }
+
+    // streams the frames to a video, the sink is not finished afterwards
+    pub fn render_into(
+        &self,
+        world: &mut World,
+        camera: &mut Camera,
+        sink: &mut impl FrameSink,
+    ) -> io::Result<()> {
+        self.render_each(world, camera, |_, canvas| sink.write_frame(&canvas))
+    }
+
+    // saves every frame to the path given by frame_path and returns the paths
+    pub fn render_frames(
+        &self,
+        world: &mut World,
+        camera: &mut Camera,
+        pattern: &str,
+        options: &ImageOptions,
+    ) -> io::Result<Vec<String>> {
+        let mut paths = vec![];
+        self.render_each(world, camera, |frame, canvas| {
+            let path = frame_path(pattern, frame);
+            canvas.save_with_options(&path, options)?;
+            paths.push(path);
+            Ok(())
+        })?;
+        Ok(paths)
+    }
+}
+
+// translation, rotation and scale of an affine transform without shear, mirroring transforms get a
+// negative x scale
+fn decompose(matrix: &Matrix4) -> (Tuple4D, Quaternion, Tuple4D) {
+    let translation = Tuple4D::new_vector(matrix[[0, 3]], matrix[[1, 3]], matrix[[2, 3]]);
+    let column = |j: usize| Tuple4D::new_vector(matrix[[0, j]], matrix[[1, j]], matrix[[2, j]]);
+    let mut scale = Tuple4D::new_vector(
+        column(0).magnitude(),
+        column(1).magnitude(),
+        column(2).magnitude(),
+    );
+    if column(0).cross(column(1)).dot(column(2)) < 0.0 {
+        scale.x = -scale.x;
+    }
+    let divisor = |value: Float| if value == 0.0 { 1.0 } else { value };
+    let scales = [divisor(scale.x), divisor(scale.y), divisor(scale.z)];
+    let mut rotation = Matrix4::eye();
+    for i in 0..3 {
+        for j in 0..3 {
+            rotation[[i, j]] = matrix[[i, j]] / scales[j];
+        }
+    }
+    (translation, Quaternion::from_matrix(&rotation), scale)
+}
+
+fn compose(translation: Tuple4D, rotation: Quaternion, scale: Tuple4D) -> Matrix4 {
+    let scales = [scale.x, scale.y, scale.z];
+    let mut matrix = rotation.to_matrix();
+    for i in 0..3 {
+        for j in 0..3 {
+            matrix[[i, j]] *= scales[j];
+        }
+    }
+    matrix[[0, 3]] = translation.x;
+    matrix[[1, 3]] = translation.y;
+    matrix[[2, 3]] = translation.z;
+    matrix
+}
+
+// the last run of # in pattern replaced by the frame number padded with zeros to its length, so
+// "frame_####.png" becomes "frame_0012.png". Without a # the number is put before the extension
+pub fn frame_path(pattern: &str, frame: usize) -> String {
+    let Some(end) = pattern.rfind('#') else {
+        let extension_start = pattern
+            .rfind('.')
+            .filter(|&dot| !pattern[dot..].contains(['/', '\\']))
+            .unwrap_or(pattern.len());
+        let (stem, extension) = pattern.split_at(extension_start);
+        return format!("{stem}_{frame:04}{extension}");
+    };
+    let start = pattern[..end].trim_end_matches('#').len();
+    let width = end + 1 - start;
+    format!(
+        "{}{frame:0width$}{}",
+        &pattern[..start],
+        &pattern[end + 1..]
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::test_camera;
+    use crate::math::consts::PI;
+
+    #[test]
+    fn keyframes_are_blended_and_held() {
+        let keyframes = Keyframes::new(vec![(2.0, 10.0), (0.0, 0.0), (1.0, 4.0)]);
+        assert_eq!(keyframes.value_at(-1.0), 0.0);
+        assert_eq!(keyframes.value_at(0.5), 2.0);
+        assert_eq!(keyframes.value_at(1.0), 4.0);
+        assert_eq!(keyframes.value_at(1.5), 7.0);
+        assert_eq!(keyframes.value_at(3.0), 10.0);
+        assert_eq!(Keyframes::new(vec![(1.0, 5.0)]).value_at(0.0), 5.0);
+    }
+
+    #[test]
+    fn eased_keyframes() {
+        let keys =
+            |easing| Keyframes::with_easing(vec![(0.0, 0.0, easing), (2.0, 1.0, Easing::Linear)]);
+        assert_eq!(keys(Easing::EaseIn).value_at(1.0), 0.25);
+        assert_eq!(keys(Easing::EaseOut).value_at(1.0), 0.75);
+        assert_eq!(keys(Easing::EaseInOut).value_at(1.0), 0.5);
+        assert!(keys(Easing::EaseInOut).value_at(0.5) < 0.25);
+        assert_eq!(keys(Easing::EaseIn).value_at(2.0), 1.0);
+    }
+
+    #[test]
+    #[cfg_attr(feature = "f32", ignore = "exact only in double precision")]
+    fn cubic_spline_passes_through_the_keys_smoothly() {
+        let spline = |value: Float| (value, value * value, Easing::CubicSpline);
+        let keyframes =
+            Keyframes::with_easing(vec![spline(0.0), spline(1.0), spline(2.0), spline(3.0)]);
+        assert_eq!(keyframes.value_at(1.0), 1.0);
+        assert_eq!(keyframes.value_at(2.0), 4.0);
+        // catmull-rom reproduces quadratics between the inner keys
+        assert!((keyframes.value_at(1.5) - 2.25).abs() < 1e-9);
+        // the slope is continuous at the keys
+        let slope = |time: Float| {
+            (keyframes.value_at(time + 1e-6) - keyframes.value_at(time - 1e-6)) / 2e-6
+        };
+        assert!((slope(1.0) - 2.0).abs() < 1e-4);
+    }
+
+    #[test]
+    #[cfg_attr(feature = "f32", ignore = "exact only in double precision")]
+    fn transforms_are_decomposed() {
+        let transform = Matrix4::translation(1.0, 2.0, 3.0)
+            * Matrix4::rotation_y(1.0)
+            * Matrix4::rotation_x(0.5)
+            * Matrix4::scaling(2.0, 3.0, -4.0);
+        let (translation, rotation, scale) = decompose(&transform);
+        assert_eq!(compose(translation, rotation, scale), transform);
+        assert_eq!(translation, Tuple4D::new_vector(1.0, 2.0, 3.0));
+        assert_eq!(scale.x, -2.0);
+    }
+
+    #[test]
+    #[cfg_attr(feature = "f32", ignore = "exact only in double precision")]
+    fn rotations_stay_rigid_between_keys() {
+        let start = Matrix4::rotation_z(0.0);
+        let end = Matrix4::rotation_z(PI);
+        // blending the entries of the matrices would collapse the half turn to a flat transform
+        assert_eq!(
+            Interpolate::interpolate(&start, &end, 0.5),
+            Matrix4::rotation_z(PI / 2.0)
+        );
+        let keyframes = Keyframes::new(vec![
+            (0.0, Matrix4::scaling(1.0, 1.0, 1.0)),
+            (
+                1.0,
+                Matrix4::translation(0.0, 4.0, 0.0)
+                    * Matrix4::rotation_x(PI / 2.0)
+                    * Matrix4::scaling(3.0, 3.0, 3.0),
+            ),
+        ]);
+        assert_eq!(
+            keyframes.value_at(0.5),
+            Matrix4::translation(0.0, 2.0, 0.0)
+                * Matrix4::rotation_x(PI / 4.0)
+                * Matrix4::scaling(2.0, 2.0, 2.0)
+        );
+    }
+
+    #[test]
+    fn camera_rig_follows_the_path() {
+        let (mut world, mut camera) = scene();
+        let path = Spline::CatmullRom(vec![
+            Tuple4D::new_point(0.0, 0.0, -5.0),
+            Tuple4D::new_point(5.0, 0.0, 0.0),
+            Tuple4D::new_point(0.0, 0.0, 5.0),
+        ]);
+        let rig = CameraRig::new(path, Tuple4D::new_point(0.0, 0.0, 0.0), 1.0, 3.0);
+        let looking_from = |x, z| {
+            Matrix4::view_transform(
+                Tuple4D::new_point(x, 0.0, z),
+                Tuple4D::new_point(0.0, 0.0, 0.0),
+                Tuple4D::new_vector(0.0, 1.0, 0.0),
+            )
+        };
+        assert_eq!(rig.transform_at(0.0), looking_from(0.0, -5.0));
+        assert_eq!(rig.transform_at(2.0), looking_from(5.0, 0.0));
+        assert_eq!(rig.transform_at(3.5), looking_from(0.0, 5.0));
+        let mut animation = Animation::new(4, 1.0);
+        animation.follow_camera_path(rig);
+        animation.apply(&mut world, &mut camera, 2);
+        assert_eq!(camera.transform.matrix(), looking_from(5.0, 0.0));
+    }
+
+    #[test]
+    fn frame_paths() {
+        assert_eq!(frame_path("out/frame_####.png", 12), "out/frame_0012.png");
+        assert_eq!(frame_path("f#.ppm", 123), "f123.ppm");
+        assert_eq!(frame_path("out.v1/frame.png", 3), "out.v1/frame_0003.png");
+        assert_eq!(frame_path("out.v1/frame", 3), "out.v1/frame_0003");
+    }
+
+    fn scene() -> (World, Camera) {
+        let mut world = World::default_world();
+        world.objects.truncate(1);
+        (world, test_camera(11))
+    }
+
+    #[test]
+    fn tracks_are_applied_at_the_time_of_the_frame() {
+        let (mut world, mut camera) = scene();
+        let mut animation = Animation::new(5, 2.0);
+        animation.animate_object_transform(
+            0,
+            Keyframes::new(vec![
+                (0.0, Matrix4::eye()),
+                (2.0, Matrix4::translation(4.0, 0.0, 0.0)),
+            ]),
+        );
+        animation.animate_light_intensity(
+            0,
+            Keyframes::new(vec![
+                (0.0, Color::new(1.0, 1.0, 1.0)),
+                (1.0, Color::new(0.0, 0.0, 0.0)),
+            ]),
+        );
+        animation.add_track(|_, camera, time| camera.focal_distance = 1.0 + time);
+        animation.apply(&mut world, &mut camera, 1);
+        assert_eq!(
+            world.objects[0].transform.matrix(),
+            Matrix4::translation(1.0, 0.0, 0.0)
+        );
+        assert_eq!(world.lights[0].intensity(), Color::new(0.5, 0.5, 0.5));
+        assert_eq!(camera.focal_distance, 1.5);
+    }
+
+    #[test]
+    fn rendering_every_frame() {
+        let (mut world, mut camera) = scene();
+        let mut animation = Animation::new(3, 1.0);
+        let away = Matrix4::view_transform(
+            Tuple4D::new_point(0.0, 0.0, 5.0),
+            Tuple4D::new_point(0.0, 0.0, 10.0),
+            Tuple4D::new_vector(0.0, 1.0, 0.0),
+        );
+        animation.animate_camera_transform(Keyframes::new(vec![
+            (0.0, camera.transform.matrix()),
+            (2.0, away),
+        ]));
+        let mut centers = vec![];
+        animation
+            .render_each(&mut world, &mut camera, |frame, canvas| {
+                centers.push((frame, canvas.read_pixel(5, 5)));
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(centers.len(), 3);
+        assert_eq!(centers[0].0, 0);
+        // the camera looks away from the sphere in the last frame
+        assert_ne!(centers[0].1, Color::new(0.0, 0.0, 0.0));
+        assert_eq!(centers[2].1, Color::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn rendering_into_a_video() {
+        let (mut world, mut camera) = scene();
+        let mut video = crate::Y4mWriter::new(vec![], 24.0);
+        Animation::new(3, 24.0)
+            .render_into(&mut world, &mut camera, &mut video)
+            .unwrap();
+        let bytes = video.into_inner();
+        let frames = bytes
+            .windows(6)
+            .filter(|window| window == b"FRAME\n")
+            .count();
+        assert_eq!(frames, 3);
+    }
+}
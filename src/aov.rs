@@ -0,0 +1,319 @@
+use crate::math::{Float, Tuple4D};
+use crate::{hit, Camera, Canvas, Color, Computations, Intersection, Ray, Region, Vec2D, World};
+
+// arbitrary output variables, per pixel information about the first surface the camera sees
+// through the center of the pixel. They are rendered separately from the image
+
+impl Camera {
+    // distance from the camera to the first surface, infinite where nothing is hit
+    pub fn render_depth(&self, world: &World) -> Vec2D<Float> {
+        self.render_pass(Float::INFINITY, |x, y| {
+            if self.outside_of_image(x, y) {
+                return Float::INFINITY;
+            }
+            let ray = self.ray_for_pixel(x, y);
+            hit(&world.intersect_world(&ray))
+                .map_or(Float::INFINITY, |hit| hit.t * ray.direction.magnitude())
+        })
+    }
+
+    // index of the object in the objects of the world, children of groups have the id of the
+    // group. The ids only change when objects are added or removed before them
+    pub fn render_object_ids(&self, world: &World) -> Vec2D<Option<usize>> {
+        self.render_pass(None, |x, y| {
+            if self.outside_of_image(x, y) {
+                return None;
+            }
+            first_object(world, &self.ray_for_pixel(x, y))
+        })
+    }
+
+    // the fraction of the samples of each pixel that see the object with the index, for cutting
+    // out single objects in compositing. Uses the samples per pixel of the camera
+    pub fn render_object_mask(&self, world: &World, index: usize) -> Vec2D<Float> {
+        self.render_pass(0.0, |x, y| {
+            if self.outside_of_image(x, y) {
+                return 0.0;
+            }
+            let rays = self.pixel_rays(x, y);
+            let seen = rays
+                .iter()
+                .filter(|(_, ray)| first_object(world, ray) == Some(index))
+                .count();
+            seen as Float / rays.len() as Float
+        })
+    }
+
+    // render_object_mask for every object at once, which needs memory for all of the masks
+    pub fn render_object_masks(&self, world: &World) -> Vec<Vec2D<Float>> {
+        let coverage = self.render_pass(vec![], |x, y| {
+            let mut coverage = vec![0.0; world.objects.len()];
+            if self.outside_of_image(x, y) {
+                return coverage;
+            }
+            let rays = self.pixel_rays(x, y);
+            let weight = 1.0 / rays.len() as Float;
+            for (_, ray) in rays {
+                if let Some(id) = first_object(world, &ray) {
+                    coverage[id] += weight;
+                }
+            }
+            coverage
+        });
+        let mut masks = vec![Vec2D::new(self.hsize(), self.vsize(), 0.0); world.objects.len()];
+        for (idx, pixel) in coverage.iter().enumerate() {
+            let (x, y) = (idx % self.hsize(), idx / self.hsize());
+            for (mask, &value) in masks.iter_mut().zip(pixel) {
+                mask.write_pixel(x, y, value);
+            }
+        }
+        masks
+    }
+}
+
+impl Camera {
+    // surface normals facing the camera, the zero vector where nothing is hit
+    pub fn render_normals(&self, world: &World) -> Vec2D<Tuple4D> {
+        self.render_normals_region(world, Region::new(0, 0, self.hsize(), self.vsize()))
+    }
+
+    // render_normals for the pixels of the region only, as the guide for denoising a crop
+    pub fn render_normals_region(&self, world: &World, region: Region) -> Vec2D<Tuple4D> {
+        let none = Tuple4D::new_vector(0.0, 0.0, 0.0);
+        self.render_pass_region(region, none, |x, y| {
+            if self.outside_of_image(x, y) {
+                return none;
+            }
+            let ray = self.ray_for_pixel(x, y);
+            let xs = world.intersect_world(&ray);
+            primary_surface(&ray, &xs).map_or(none, |comps| comps.normalv)
+        })
+    }
+
+    // color of the surfaces without any lighting, black where nothing is hit
+    pub fn render_albedo(&self, world: &World) -> Canvas {
+        self.render_albedo_region(world, Region::new(0, 0, self.hsize(), self.vsize()))
+    }
+
+    // render_albedo for the pixels of the region only
+    pub fn render_albedo_region(&self, world: &World, region: Region) -> Canvas {
+        let black = Color::new(0.0, 0.0, 0.0);
+        self.render_pass_region(region, black, |x, y| {
+            if self.outside_of_image(x, y) {
+                return black;
+            }
+            let ray = self.ray_for_pixel(x, y);
+            let xs = world.intersect_world(&ray);
+            primary_surface(&ray, &xs).map_or(black, |comps| {
+                comps.material.color_at_filtered(
+                    comps.world_to_object,
+                    comps.point,
+                    comps.footprint,
+                )
+            })
+        })
+    }
+}
+
+fn primary_surface<'a>(ray: &Ray, xs: &[Intersection<'a>]) -> Option<Computations<'a>> {
+    Some(hit(xs)?.prepare_computations(ray, xs))
+}
+
+// the index of the top level object the ray hits first
+fn first_object(world: &World, ray: &Ray) -> Option<usize> {
+    world
+        .objects
+        .iter()
+        .enumerate()
+        .filter_map(|(id, object)| Some((id, hit(&object.intersect(ray))?.t)))
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(id, _)| id)
+}
+
+impl Vec2D<Option<usize>> {
+    // a distinct color for every id and black where there is none
+    pub fn to_id_colors(&self) -> Canvas {
+        let mut canvas = Canvas::create_canvas(self.width(), self.height());
+        for y in 0..self.height() {
+            for x in 0..self.width() {
+                if let Some(id) = self.read_pixel(x, y) {
+                    canvas.write_pixel(x, y, id_color(id));
+                }
+            }
+        }
+        canvas
+    }
+}
+
+impl Vec2D<Tuple4D> {
+    // the usual normal map coloring, the components mapped from [-1, 1] to [0, 1]
+    pub fn to_normal_colors(&self) -> Canvas {
+        let mut canvas = Canvas::create_canvas(self.width(), self.height());
+        for y in 0..self.height() {
+            for x in 0..self.width() {
+                let normal = self.read_pixel(x, y);
+                if normal.magnitude() > 0.0 {
+                    let color = Color::new(normal.x, normal.y, normal.z) * 0.5;
+                    canvas.write_pixel(x, y, color + Color::new(0.5, 0.5, 0.5));
+                }
+            }
+        }
+        canvas
+    }
+}
+
+// hues spread by the golden ratio, so neighboring ids get very different colors
+fn id_color(id: usize) -> Color {
+    let hue = (id as Float * 0.618_033_988_749_895).fract() * 6.0;
+    let (saturation, value) = (0.7, 0.95);
+    let chroma = value * saturation;
+    let second = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+    let (r, g, b) = match hue as usize {
+        0 => (chroma, second, 0.0),
+        1 => (second, chroma, 0.0),
+        2 => (0.0, chroma, second),
+        3 => (0.0, second, chroma),
+        4 => (second, 0.0, chroma),
+        _ => (chroma, 0.0, second),
+    };
+    let offset = value - chroma;
+    Color::new(r + offset, g + offset, b + offset)
+}
+
+impl Vec2D<Float> {
+    // values mapped linearly from black at low to white at high, values outside are clamped. For
+    // depth images low is the far and high the near distance, so close surfaces are bright
+    pub fn to_grayscale(&self, low: Float, high: Float) -> Canvas {
+        let mut canvas = Canvas::create_canvas(self.width(), self.height());
+        for y in 0..self.height() {
+            for x in 0..self.width() {
+                let value = (self.read_pixel(x, y) - low) / (high - low);
+                let value = if value.is_nan() {
+                    0.0
+                } else {
+                    value.clamp(0.0, 1.0)
+                };
+                canvas.write_pixel(x, y, Color::new(value, value, value));
+            }
+        }
+        canvas
+    }
+
+    // smallest and largest finite value, none if there is no finite value
+    pub fn finite_range(&self) -> Option<(Float, Float)> {
+        self.iter()
+            .filter(|value| value.is_finite())
+            .fold(None, |range, &value| match range {
+                None => Some((value, value)),
+                Some((min, max)) => Some((value.min(min), value.max(max))),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::test_camera;
+    use crate::math::{Matrix4, Tuple4D};
+
+    #[test]
+    fn depth_of_the_default_world() {
+        let depth = test_camera(11).render_depth(&World::default_world());
+        // the outer sphere has a radius of 1
+        assert_eq!(depth.read_pixel(5, 5), 4.0);
+        assert_eq!(depth.read_pixel(0, 0), Float::INFINITY);
+        let (near, far) = depth.finite_range().unwrap();
+        assert_eq!(near, 4.0);
+        assert!(far > near && far.is_finite());
+    }
+
+    #[test]
+    fn depth_as_grayscale() {
+        let depth = test_camera(11).render_depth(&World::default_world());
+        let canvas = depth.to_grayscale(6.0, 4.0);
+        assert_eq!(canvas.read_pixel(5, 5), Color::new(1.0, 1.0, 1.0));
+        assert_eq!(canvas.read_pixel(0, 0), Color::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn range_without_finite_values() {
+        assert_eq!(Vec2D::new(2, 2, Float::INFINITY).finite_range(), None);
+    }
+
+    #[test]
+    fn object_ids_of_the_default_world() {
+        let ids = test_camera(11).render_object_ids(&World::default_world());
+        // the inner sphere is inside the outer one
+        assert_eq!(ids.read_pixel(5, 5), Some(0));
+        assert_eq!(ids.read_pixel(0, 0), None);
+        let colors = ids.to_id_colors();
+        assert_eq!(colors.read_pixel(5, 5), id_color(0));
+        assert_eq!(colors.read_pixel(0, 0), Color::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn ids_are_the_closest_top_level_object() {
+        let mut world = World::new();
+        let mut back = crate::Object::sphere();
+        back.transform = Matrix4::translation(0.0, 0.0, 3.0).into();
+        world.objects.push(back);
+        world
+            .objects
+            .push(crate::Object::group(vec![crate::Object::sphere()]));
+        let ids = test_camera(3).render_object_ids(&world);
+        assert_eq!(ids.read_pixel(1, 1), Some(1));
+    }
+
+    #[test]
+    fn object_masks_add_up_to_the_coverage() {
+        let world = World::default_world();
+        let mut camera = test_camera(11);
+        camera.samples_per_pixel = 16;
+        let masks = camera.render_object_masks(&world);
+        assert_eq!(masks.len(), 2);
+        assert_eq!(masks[0].read_pixel(5, 5), 1.0);
+        assert_eq!(masks[0].read_pixel(0, 0), 0.0);
+        // the inner sphere is never seen
+        assert!(masks[1].iter().all(|&value| value == 0.0));
+        let edge = masks[0].read_pixel(4, 5);
+        assert!(edge > 0.0 && edge < 1.0);
+        let mask = camera.render_object_mask(&world, 0);
+        assert!(mask.iter().zip(masks[0].iter()).all(|(a, b)| a == b));
+        assert!(camera
+            .render_object_mask(&world, 2)
+            .iter()
+            .all(|&value| value == 0.0));
+    }
+
+    #[test]
+    fn normals_and_albedo_of_the_default_world() {
+        let world = World::default_world();
+        let camera = test_camera(11);
+        let normals = camera.render_normals(&world);
+        assert_eq!(
+            normals.read_pixel(5, 5),
+            Tuple4D::new_vector(0.0, 0.0, -1.0)
+        );
+        assert_eq!(normals.read_pixel(0, 0), Tuple4D::new_vector(0.0, 0.0, 0.0));
+        let colors = normals.to_normal_colors();
+        assert_eq!(colors.read_pixel(5, 5), Color::new(0.5, 0.5, 0.0));
+        let albedo = camera.render_albedo(&world);
+        assert_eq!(albedo.read_pixel(5, 5), world.objects[0].material.color);
+        assert_eq!(albedo.read_pixel(0, 0), Color::new(0.0, 0.0, 0.0));
+        // a region renders the same pixels as the crop of the whole image
+        let region = Region::new(3, 4, 5, 2);
+        let normals_region = camera.render_normals_region(&world, region);
+        assert_eq!(normals_region.width(), 5);
+        assert!(normals_region.iter().eq(normals.crop(region).iter()));
+        let albedo_region = camera.render_albedo_region(&world, region);
+        assert!(albedo_region.iter().eq(albedo.crop(region).iter()));
+    }
+
+    #[test]
+    fn id_colors_differ() {
+        let colors: Vec<Color> = (0..6).map(id_color).collect();
+        for (i, a) in colors.iter().enumerate() {
+            assert!(colors[i + 1..].iter().all(|b| a != b));
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use std::sync::Arc;
+
+use crate::math::Tuple4D;
+use crate::patterns::{spherical_map, UvPattern};
+use crate::Color;
+
+// what a ray sees when it doesn't hit any object
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Background {
+    Solid(Color),
+    // blends from bottom (looking straight down) to top (looking straight up)
+    Gradient {
+        bottom: Color,
+        top: Color,
+    },
+    // equirectangular environment map, e.g. an UvImage of a panorama
+    // can not be serialized
+    #[cfg_attr(feature = "serde", serde(skip))]
+    Environment(Arc<dyn UvPattern>),
+}
+
+impl Default for Background {
+    fn default() -> Self {
+        Background::Solid(Color::new(0.0, 0.0, 0.0))
+    }
+}
+
+impl Background {
+    pub fn environment(texture: impl UvPattern + 'static) -> Background {
+        Background::Environment(Arc::new(texture))
+    }
+
+    pub fn color_for(&self, direction: Tuple4D) -> Color {
+        match self {
+            Background::Solid(color) => *color,
+            Background::Gradient { bottom, top } => {
+                let t = 0.5 * (direction.normalize().y + 1.0);
+                *bottom * (1.0 - t) + *top * t
+            }
+            Background::Environment(texture) => {
+                let direction = direction.normalize();
+                let (u, v) =
+                    spherical_map(Tuple4D::new_point(direction.x, direction.y, direction.z));
+                texture.uv_pattern_at(u, v)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::patterns::UvCheckers;
+
+    use super::*;
+
+    #[test]
+    fn default_background_is_black() {
+        let background = Background::default();
+        assert_eq!(
+            background.color_for(Tuple4D::new_vector(0.0, 0.0, 1.0)),
+            Color::new(0.0, 0.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn gradient_background_blends_by_elevation() {
+        let background = Background::Gradient {
+            bottom: Color::new(1.0, 1.0, 1.0),
+            top: Color::new(0.0, 0.0, 1.0),
+        };
+        assert_eq!(
+            background.color_for(Tuple4D::new_vector(0.0, 1.0, 0.0)),
+            Color::new(0.0, 0.0, 1.0)
+        );
+        assert_eq!(
+            background.color_for(Tuple4D::new_vector(0.0, -2.0, 0.0)),
+            Color::new(1.0, 1.0, 1.0)
+        );
+        assert_eq!(
+            background.color_for(Tuple4D::new_vector(0.0, 0.0, -1.0)),
+            Color::new(0.5, 0.5, 1.0)
+        );
+    }
+
+    #[test]
+    fn environment_is_sampled_by_direction() {
+        let black = Color::new(0.0, 0.0, 0.0);
+        let white = Color::new(1.0, 1.0, 1.0);
+        let background = Background::environment(UvCheckers::new(2.0, 1.0, black, white));
+        // u in [0, 0.5) is black, [0.5, 1) is white
+        assert_eq!(
+            background.color_for(Tuple4D::new_vector(1.0, 0.0, 0.0)),
+            black
+        );
+        assert_eq!(
+            background.color_for(Tuple4D::new_vector(-1.0, 0.0, 0.0)),
+            white
+        );
+    }
+}
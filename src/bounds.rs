@@ -0,0 +1,213 @@
+use crate::math::{Float, Matrix4, Tuple4D, FLOAT_EQ_EPS};
+use crate::Ray;
+
+// axis aligned bounding box, used to skip the contents of groups a ray does not come close to
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Bounds {
+    pub min: Tuple4D,
+    pub max: Tuple4D,
+}
+
+impl Bounds {
+    pub fn new(min: Tuple4D, max: Tuple4D) -> Bounds {
+        Bounds { min, max }
+    }
+
+    // contains nothing, adding a point makes it the bounds of that point
+    pub fn empty() -> Bounds {
+        Bounds::new(
+            Tuple4D::new_point(Float::INFINITY, Float::INFINITY, Float::INFINITY),
+            Tuple4D::new_point(
+                Float::NEG_INFINITY,
+                Float::NEG_INFINITY,
+                Float::NEG_INFINITY,
+            ),
+        )
+    }
+
+    pub fn infinite() -> Bounds {
+        Bounds::new(
+            Tuple4D::new_point(
+                Float::NEG_INFINITY,
+                Float::NEG_INFINITY,
+                Float::NEG_INFINITY,
+            ),
+            Tuple4D::new_point(Float::INFINITY, Float::INFINITY, Float::INFINITY),
+        )
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min.x > self.max.x || self.min.y > self.max.y || self.min.z > self.max.z
+    }
+
+    pub fn is_infinite(&self) -> bool {
+        [
+            self.min.x, self.min.y, self.min.z, self.max.x, self.max.y, self.max.z,
+        ]
+        .iter()
+        .any(|value| value.is_infinite())
+            && !self.is_empty()
+    }
+
+    pub fn add_point(&mut self, point: Tuple4D) {
+        self.min = self.min.min(point);
+        self.max = self.max.max(point);
+    }
+
+    pub fn union(&self, other: &Bounds) -> Bounds {
+        let mut bounds = *self;
+        if !other.is_empty() {
+            bounds.add_point(other.min);
+            bounds.add_point(other.max);
+        }
+        bounds
+    }
+
+    pub fn contains_point(&self, point: Tuple4D) -> bool {
+        (self.min.x..=self.max.x).contains(&point.x)
+            && (self.min.y..=self.max.y).contains(&point.y)
+            && (self.min.z..=self.max.z).contains(&point.z)
+    }
+
+    // bounds of the box after the transformation, which contain all eight transformed corners
+    pub fn transform(&self, transformation: Matrix4) -> Bounds {
+        if self.is_empty() {
+            return *self;
+        }
+        // infinite coordinates would turn into nan when multiplied by zero entries
+        if self.is_infinite() {
+            return Bounds::infinite();
+        }
+        let mut bounds = Bounds::empty();
+        for x in [self.min.x, self.max.x] {
+            for y in [self.min.y, self.max.y] {
+                for z in [self.min.z, self.max.z] {
+                    bounds.add_point((transformation * Tuple4D::new_point(x, y, z)).project());
+                }
+            }
+        }
+        bounds
+    }
+
+    // slab test, true if the ray passes through the box at any t
+    pub fn intersects(&self, ray: &Ray) -> bool {
+        self.t_range(ray).is_some()
+    }
+
+    // values of t where the ray enters and leaves the box, None if it misses
+    pub fn t_range(&self, ray: &Ray) -> Option<(Float, Float)> {
+        if self.is_empty() {
+            return None;
+        }
+        let mut tmin = Float::NEG_INFINITY;
+        let mut tmax = Float::INFINITY;
+        let axes = [
+            (ray.origin.x, ray.direction.x, self.min.x, self.max.x),
+            (ray.origin.y, ray.direction.y, self.min.y, self.max.y),
+            (ray.origin.z, ray.direction.z, self.min.z, self.max.z),
+        ];
+        for (origin, direction, min, max) in axes {
+            if direction.abs() < FLOAT_EQ_EPS {
+                if origin < min || origin > max {
+                    return None;
+                }
+                continue;
+            }
+            let t1 = (min - origin) / direction;
+            let t2 = (max - origin) / direction;
+            tmin = tmin.max(t1.min(t2));
+            tmax = tmax.min(t1.max(t2));
+        }
+        (tmin <= tmax).then_some((tmin, tmax))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::consts::{FRAC_PI_4, SQRT_2};
+
+    use super::*;
+
+    #[test]
+    fn adding_points_grows_bounds() {
+        let mut bounds = Bounds::empty();
+        assert!(bounds.is_empty());
+        bounds.add_point(Tuple4D::new_point(-5.0, 2.0, 0.0));
+        bounds.add_point(Tuple4D::new_point(7.0, 0.0, -3.0));
+        assert_eq!(bounds.min, Tuple4D::new_point(-5.0, 0.0, -3.0));
+        assert_eq!(bounds.max, Tuple4D::new_point(7.0, 2.0, 0.0));
+        assert!(bounds.contains_point(Tuple4D::new_point(0.0, 1.0, -1.0)));
+        assert!(!bounds.contains_point(Tuple4D::new_point(0.0, 3.0, -1.0)));
+    }
+
+    #[test]
+    fn union_of_bounds() {
+        let a = Bounds::new(
+            Tuple4D::new_point(-5.0, -2.0, 0.0),
+            Tuple4D::new_point(7.0, 4.0, 4.0),
+        );
+        let b = Bounds::new(
+            Tuple4D::new_point(8.0, -7.0, -2.0),
+            Tuple4D::new_point(14.0, 2.0, 8.0),
+        );
+        let union = a.union(&b);
+        assert_eq!(union.min, Tuple4D::new_point(-5.0, -7.0, -2.0));
+        assert_eq!(union.max, Tuple4D::new_point(14.0, 4.0, 8.0));
+        assert_eq!(a.union(&Bounds::empty()), a);
+    }
+
+    #[test]
+    fn transforming_bounds() {
+        let bounds = Bounds::new(
+            Tuple4D::new_point(-1.0, -1.0, -1.0),
+            Tuple4D::new_point(1.0, 1.0, 1.0),
+        );
+        let transformed =
+            bounds.transform(Matrix4::rotation_x(FRAC_PI_4) * Matrix4::rotation_y(FRAC_PI_4));
+        assert_eq!(
+            transformed.min,
+            Tuple4D::new_point(-SQRT_2, -1.70710, -1.70710)
+        );
+        assert_eq!(
+            transformed.max,
+            Tuple4D::new_point(SQRT_2, 1.70710, 1.70710)
+        );
+        assert!(Bounds::infinite()
+            .transform(Matrix4::scaling(2.0, 2.0, 2.0))
+            .is_infinite());
+    }
+
+    #[test]
+    fn ray_intersects_bounds() {
+        let bounds = Bounds::new(
+            Tuple4D::new_point(5.0, -2.0, 0.0),
+            Tuple4D::new_point(11.0, 4.0, 7.0),
+        );
+        let cases = [
+            ((15.0, 1.0, 2.0), (-1.0, 0.0, 0.0), true),
+            ((8.0, 6.0, 2.0), (0.0, -1.0, 0.0), true),
+            ((8.0, 2.0, 12.0), (0.0, 0.0, -1.0), true),
+            ((8.0, 1.0, -5.0), (0.0, 0.2, 1.0), true),
+            ((15.0, 1.0, 2.0), (0.0, 0.0, -1.0), false),
+            ((8.0, 2.0, 12.0), (1.0, 0.0, 0.0), false),
+            ((12.0, 5.0, 4.0), (1.0, 0.0, 0.0), false),
+        ];
+        for ((ox, oy, oz), (dx, dy, dz), expected) in cases {
+            let ray = Ray::new(
+                Tuple4D::new_point(ox, oy, oz),
+                Tuple4D::new_vector(dx, dy, dz).normalize(),
+            );
+            assert_eq!(bounds.intersects(&ray), expected);
+        }
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 0.0, 2.0),
+            Tuple4D::new_vector(1.0, 0.0, 0.0),
+        );
+        assert_eq!(bounds.t_range(&ray), Some((5.0, 11.0)));
+        assert!(!Bounds::empty().intersects(&Ray::new(
+            Tuple4D::new_point(0.0, 0.0, 0.0),
+            Tuple4D::new_vector(1.0, 0.0, 0.0)
+        )));
+    }
+}
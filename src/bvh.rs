@@ -0,0 +1,407 @@
+use std::any::Any;
+use std::fs;
+use std::io::{Error, ErrorKind};
+use std::path::{Path, PathBuf};
+
+use crate::math::Float;
+use crate::shapes::{Group, Shape};
+use crate::{Bounds, Object};
+
+// most objects a group of the hierarchy holds before it is split again
+pub const DEFAULT_BVH_LEAF_SIZE: usize = 4;
+
+const MAGIC: &[u8; 4] = b"BVH1";
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    // indices of the objects in the group
+    Leaf(Vec<usize>),
+    // indices of the two nodes the objects were split into
+    Split(usize, usize),
+}
+
+// which objects of a group end up in which subgroup of its bounding volume hierarchy. It only
+// depends on the bounds of the objects, so it can be stored and applied to the same objects
+// loaded again. The first node is the root
+#[derive(Debug, Clone, PartialEq)]
+pub struct BvhLayout {
+    object_count: usize,
+    nodes: Vec<Node>,
+}
+
+impl BvhLayout {
+    // splits the objects at the median of their centers along the longest axis until at most
+    // leaf_size are left in every group
+    pub fn build(bounds: &[Bounds], leaf_size: usize) -> BvhLayout {
+        let mut layout = BvhLayout {
+            object_count: bounds.len(),
+            nodes: vec![],
+        };
+        let centers: Vec<[Float; 3]> = bounds
+            .iter()
+            .map(|bounds| {
+                let center = (bounds.min + bounds.max) / 2.0;
+                [center.x, center.y, center.z]
+            })
+            .collect();
+        layout.split((0..bounds.len()).collect(), &centers, leaf_size.max(2));
+        layout
+    }
+
+    fn split(
+        &mut self,
+        mut objects: Vec<usize>,
+        centers: &[[Float; 3]],
+        leaf_size: usize,
+    ) -> usize {
+        let idx = self.nodes.len();
+        if objects.len() <= leaf_size {
+            self.nodes.push(Node::Leaf(objects));
+            return idx;
+        }
+        let extent = |axis: usize| {
+            let values = objects.iter().map(|&object| centers[object][axis]);
+            values.clone().fold(Float::NEG_INFINITY, Float::max)
+                - values.fold(Float::INFINITY, Float::min)
+        };
+        let axis = (0..3)
+            .max_by(|&a, &b| extent(a).total_cmp(&extent(b)))
+            .unwrap_or(0);
+        // unbounded objects or objects that all share the same center can not be told apart
+        if !(extent(axis) > 0.0 && extent(axis).is_finite()) {
+            self.nodes.push(Node::Leaf(objects));
+            return idx;
+        }
+        objects.sort_by(|&a, &b| centers[a][axis].total_cmp(&centers[b][axis]));
+        let right = objects.split_off(objects.len() / 2);
+        self.nodes.push(Node::Split(0, 0));
+        let left = self.split(objects, centers, leaf_size);
+        let right = self.split(right, centers, leaf_size);
+        self.nodes[idx] = Node::Split(left, right);
+        idx
+    }
+
+    pub fn object_count(&self) -> usize {
+        self.object_count
+    }
+
+    // the hierarchy of groups holding objects, which have to be the ones the layout was built for
+    pub fn apply(&self, objects: Vec<Object>) -> Group {
+        assert_eq!(
+            objects.len(),
+            self.object_count,
+            "the layout was built for a different number of objects"
+        );
+        let mut objects: Vec<Option<Object>> = objects.into_iter().map(Some).collect();
+        Group::new(self.children(0, &mut objects))
+    }
+
+    // the objects of a leaf, the two subgroups of a split
+    fn children(&self, node: usize, objects: &mut [Option<Object>]) -> Vec<Object> {
+        match &self.nodes[node] {
+            Node::Leaf(indices) => indices
+                .iter()
+                .map(|&idx| objects[idx].take().expect("objects are in one leaf only"))
+                .collect(),
+            Node::Split(left, right) => vec![
+                Object::group(self.children(*left, objects)),
+                Object::group(self.children(*right, objects)),
+            ],
+        }
+    }
+
+    fn write(&self, bytes: &mut Vec<u8>) {
+        let mut push = |value: usize| bytes.extend_from_slice(&(value as u32).to_le_bytes());
+        push(self.object_count);
+        push(self.nodes.len());
+        for node in &self.nodes {
+            match node {
+                Node::Leaf(indices) => {
+                    push(0);
+                    push(indices.len());
+                    indices.iter().for_each(|&idx| push(idx));
+                }
+                Node::Split(left, right) => {
+                    push(1);
+                    push(*left);
+                    push(*right);
+                }
+            }
+        }
+    }
+
+    // checks that every object is in exactly one leaf and every node but the root is below exactly
+    // one earlier node, so applying a layout read from a broken file can not panic or loop
+    fn read(bytes: &mut &[u8]) -> Result<BvhLayout, String> {
+        let length = bytes.len();
+        let mut next = || -> Result<usize, String> {
+            let (value, rest) = bytes
+                .split_first_chunk::<4>()
+                .ok_or("bvh cache file ends early")?;
+            *bytes = rest;
+            Ok(u32::from_le_bytes(*value) as usize)
+        };
+        let object_count = next()?;
+        let node_count = next()?;
+        if node_count == 0 {
+            return Err("bvh layout without nodes".to_string());
+        }
+        // every object takes 4 bytes and every node at least 8, so a broken file can not make
+        // the checks below allocate more than the file is long
+        if object_count > length / 4 || node_count > length / 8 {
+            return Err("bvh cache file ends early".to_string());
+        }
+        let mut seen = vec![false; object_count];
+        let mut referenced = vec![false; node_count];
+        let mut nodes = Vec::with_capacity(node_count.min(object_count + 1));
+        for idx in 0..node_count {
+            let node = match next()? {
+                0 => {
+                    let len = next()?;
+                    let indices = (0..len).map(|_| next()).collect::<Result<Vec<_>, _>>()?;
+                    for &object in &indices {
+                        if object >= object_count || std::mem::replace(&mut seen[object], true) {
+                            return Err(format!("object {object} is not in exactly one leaf"));
+                        }
+                    }
+                    Node::Leaf(indices)
+                }
+                1 => {
+                    let (left, right) = (next()?, next()?);
+                    for child in [left, right] {
+                        if child <= idx
+                            || child >= node_count
+                            || std::mem::replace(&mut referenced[child], true)
+                        {
+                            return Err(format!("node {idx} points to an invalid node"));
+                        }
+                    }
+                    Node::Split(left, right)
+                }
+                tag => return Err(format!("unknown bvh node type {tag}")),
+            };
+            nodes.push(node);
+        }
+        if seen.contains(&false) || referenced[1..].contains(&false) {
+            return Err("bvh layout misses objects".to_string());
+        }
+        Ok(BvhLayout {
+            object_count,
+            nodes,
+        })
+    }
+}
+
+impl Object {
+    // the same object with the children of every group sorted into a bounding volume hierarchy,
+    // so rays only test the children whose bounds they hit instead of all of them
+    pub fn into_bvh(self, leaf_size: usize) -> Object {
+        divide(self, &mut |bounds| BvhLayout::build(bounds, leaf_size))
+    }
+}
+
+// applies the layout returned by next_layout to every group, the nested groups come first
+fn divide(object: Object, next_layout: &mut dyn FnMut(&[Bounds]) -> BvhLayout) -> Object {
+    let shape: &dyn Any = object.shape.as_ref();
+    if !shape.is::<Group>() {
+        return object;
+    }
+    let Object {
+        transform,
+        transform_end,
+        material,
+        shape,
+    } = object;
+    let shape: Box<dyn Any> = shape;
+    let group = shape.downcast::<Group>().expect("checked to be a group");
+    let children: Vec<Object> = group
+        .into_children()
+        .into_iter()
+        .map(|child| divide(child, next_layout))
+        .collect();
+    let bounds: Vec<Bounds> = children.iter().map(Object::bounds).collect();
+    let group: Box<dyn Shape> = Box::new(next_layout(&bounds).apply(children));
+    Object {
+        transform,
+        transform_end,
+        material,
+        shape: group,
+    }
+}
+
+// directory with the hierarchies built for mesh files. They are stored under a hash of the file
+// content, so changed files get a new hierarchy and unchanged ones are not split again
+#[derive(Debug, Clone, PartialEq)]
+pub struct BvhCache {
+    pub directory: PathBuf,
+    pub leaf_size: usize,
+}
+
+impl BvhCache {
+    pub fn new(directory: impl Into<PathBuf>) -> BvhCache {
+        BvhCache {
+            directory: directory.into(),
+            leaf_size: DEFAULT_BVH_LEAF_SIZE,
+        }
+    }
+
+    pub fn path_for(&self, content: &[u8]) -> PathBuf {
+        let hash = fnv1a(
+            content,
+            fnv1a(&(self.leaf_size as u64).to_le_bytes(), FNV_OFFSET),
+        );
+        self.directory.join(format!("{hash:016x}.bvh"))
+    }
+
+    // into_bvh with the layouts stored for content, the file the object was loaded from. Missing
+    // or unreadable cache files are replaced with the layouts built now, it fails when they can not
+    // be written
+    pub fn load_or_build(&self, content: &[u8], object: Object) -> std::io::Result<Object> {
+        let path = self.path_for(content);
+        let mut cached = read_layouts(&path).unwrap_or_default().into_iter();
+        let mut used = vec![];
+        let mut stale = false;
+        let object = divide(object, &mut |bounds| {
+            let layout = match cached.next() {
+                Some(layout) if layout.object_count() == bounds.len() => layout,
+                _ => {
+                    stale = true;
+                    BvhLayout::build(bounds, self.leaf_size)
+                }
+            };
+            used.push(layout.clone());
+            layout
+        });
+        if stale || cached.next().is_some() {
+            write_layouts(&path, &used).map_err(|err| {
+                Error::new(err.kind(), format!("bvh cache {}: {err}", path.display()))
+            })?;
+        }
+        Ok(object)
+    }
+}
+
+fn read_layouts(path: &Path) -> std::io::Result<Vec<BvhLayout>> {
+    let bytes = fs::read(path)?;
+    let mut rest = bytes
+        .strip_prefix(MAGIC.as_slice())
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, "not a bvh cache file"))?;
+    let mut layouts = vec![];
+    while !rest.is_empty() {
+        layouts.push(
+            BvhLayout::read(&mut rest).map_err(|err| Error::new(ErrorKind::InvalidData, err))?,
+        );
+    }
+    Ok(layouts)
+}
+
+fn write_layouts(path: &Path, layouts: &[BvhLayout]) -> std::io::Result<()> {
+    let mut bytes = MAGIC.to_vec();
+    for layout in layouts {
+        layout.write(&mut bytes);
+    }
+    if let Some(directory) = path.parent() {
+        fs::create_dir_all(directory)?;
+    }
+    fs::write(path, bytes)
+}
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+
+// stable across runs and compiler versions, unlike the hasher of the standard library
+fn fnv1a(bytes: &[u8], seed: u64) -> u64 {
+    bytes.iter().fold(seed, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::{Matrix4, Tuple4D};
+    use crate::Ray;
+
+    fn spheres(count: usize) -> Vec<Object> {
+        (0..count)
+            .map(|idx| {
+                let mut sphere = Object::sphere();
+                sphere.transform =
+                    (Matrix4::translation(idx as Float * 3.0, (idx % 3) as Float, 0.0)
+                        * Matrix4::scaling(0.5, 0.5, 0.5))
+                    .into();
+                sphere
+            })
+            .collect()
+    }
+
+    fn ts(object: &Object, ray: &Ray) -> Vec<Float> {
+        let mut ts: Vec<Float> = object.intersect(ray).iter().map(|x| x.t).collect();
+        ts.sort_by(Float::total_cmp);
+        ts
+    }
+
+    #[test]
+    fn hierarchy_finds_the_same_intersections() {
+        let flat = Object::group(spheres(20));
+        let bvh = Object::group(spheres(20)).into_bvh(2);
+        let shape: &dyn Any = bvh.shape.as_ref();
+        assert_eq!(shape.downcast_ref::<Group>().unwrap().children().len(), 2);
+        for (x, y) in [(0.0, 0.0), (9.0, 0.0), (30.0, 1.0), (57.0, 2.0), (1.5, 0.0)] {
+            let ray = Ray::new(
+                Tuple4D::new_point(x, y, -5.0),
+                Tuple4D::new_vector(0.0, 0.0, 1.0),
+            );
+            assert_eq!(ts(&bvh, &ray), ts(&flat, &ray));
+        }
+        assert_eq!(bvh.bounds(), flat.bounds());
+    }
+
+    #[test]
+    fn layouts_survive_writing_and_reading() {
+        let bounds: Vec<Bounds> = spheres(11).iter().map(Object::bounds).collect();
+        let layout = BvhLayout::build(&bounds, 3);
+        let mut bytes = vec![];
+        layout.write(&mut bytes);
+        assert_eq!(BvhLayout::read(&mut bytes.as_slice()), Ok(layout));
+        // the first leaf, behind two splits, claims an object that does not exist
+        let mut broken = bytes.clone();
+        broken[40..44].copy_from_slice(&99u32.to_le_bytes());
+        let err = BvhLayout::read(&mut broken.as_slice()).unwrap_err();
+        assert!(err.contains("exactly one leaf"), "{err}");
+        assert!(BvhLayout::read(&mut &bytes[..10]).is_err());
+        // counts far beyond the length of the file
+        let mut huge = bytes.clone();
+        huge[0..4].copy_from_slice(&u32::MAX.to_le_bytes());
+        let err = BvhLayout::read(&mut huge.as_slice()).unwrap_err();
+        assert!(err.contains("ends early"), "{err}");
+    }
+
+    #[test]
+    fn cached_layouts_are_reused() {
+        let directory = std::env::temp_dir().join(format!("bvh_cache_test_{}", std::process::id()));
+        let cache = BvhCache::new(&directory);
+        let nested = || Object::group(vec![Object::group(spheres(6)), Object::group(spheres(9))]);
+        let path = cache.path_for(b"mesh");
+        assert_ne!(path, cache.path_for(b"other mesh"));
+        let built = cache.load_or_build(b"mesh", nested()).unwrap();
+        let written = fs::read(&path).unwrap();
+        assert_eq!(read_layouts(&path).unwrap().len(), 3);
+        let loaded = cache.load_or_build(b"mesh", nested()).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), written);
+        assert_eq!(loaded.bounds(), built.bounds());
+        // a broken file is replaced
+        fs::write(&path, b"BVH1 broken").unwrap();
+        cache.load_or_build(b"mesh", nested()).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), written);
+        fs::remove_dir_all(&directory).unwrap();
+        // a cache that can not be written is an error that names the file
+        let blocked = directory.with_extension("file");
+        fs::write(&blocked, b"").unwrap();
+        let err = BvhCache::new(&blocked)
+            .load_or_build(b"mesh", nested())
+            .unwrap_err();
+        assert!(err.to_string().starts_with("bvh cache "), "{err}");
+        assert!(err.to_string().contains(".file"), "{err}");
+        fs::remove_file(&blocked).unwrap();
+    }
+}
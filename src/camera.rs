@@ -0,0 +1,982 @@
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::Instant;
+
+use crate::math::consts::{FRAC_PI_4, PI};
+use crate::math::{Float, Matrix4, Transform, Tuple4D};
+use crate::sampler::rng_for;
+use crate::{
+    hit, CancellationToken, Canvas, Color, Integrator, IntersectionBuffer, Progress, RandomSampler,
+    Ray, Region, RgbaCanvas, SampleDimension, Sampler, Vec2D, World, PACKET_SIZE,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Projection {
+    #[default]
+    Perspective,
+    // equidistant fisheye, the field of view spans the circle that fits into the image,
+    // pixels outside of it stay black
+    Fisheye,
+    // full 360 degree longitude and 180 degree latitude panorama, the field of view is ignored
+    Equirectangular,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Camera {
+    hsize: usize,
+    vsize: usize,
+    field_of_view: Float,
+    pub transform: Transform,
+    // camera transform at the end of the exposure, a moving camera blurs the whole image
+    pub transform_end: Option<Matrix4>,
+    pub projection: Projection,
+    pub integrator: Integrator,
+    // number of worker threads used by render
+    pub threads: usize,
+    // rays per pixel, with more than one the rays are jittered inside the pixel and averaged
+    pub samples_per_pixel: usize,
+    // not serialized, deserialized cameras use the default sampler
+    #[cfg_attr(feature = "serde", serde(skip, default = "default_sampler"))]
+    pub sampler: Arc<dyn Sampler>,
+    // thin lens model, with an aperture radius of 0 the camera is a pinhole and everything is sharp
+    pub aperture_radius: Float,
+    // distance from the camera to the plane that is in focus
+    pub focal_distance: Float,
+    half_width: Float,
+    half_height: Float,
+    pixel_size: Float,
+}
+
+impl Camera {
+    pub fn new(hsize: usize, vsize: usize, field_of_view: Float) -> Camera {
+        let half_view = (field_of_view / 2.0).tan();
+        let aspect = hsize as Float / vsize as Float;
+        let (half_width, half_height) = if aspect >= 1.0 {
+            (half_view, half_view / aspect)
+        } else {
+            (half_view * aspect, half_view)
+        };
+        Camera {
+            hsize,
+            vsize,
+            field_of_view,
+            transform: Transform::default(),
+            transform_end: None,
+            projection: Projection::Perspective,
+            integrator: Integrator::Whitted,
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+            samples_per_pixel: 1,
+            sampler: default_sampler(),
+            aperture_radius: 0.0,
+            focal_distance: 1.0,
+            half_width,
+            half_height,
+            pixel_size: half_width * 2.0 / hsize as Float,
+        }
+    }
+
+    pub fn hsize(&self) -> usize {
+        self.hsize
+    }
+
+    pub fn vsize(&self) -> usize {
+        self.vsize
+    }
+
+    pub fn field_of_view(&self) -> Float {
+        self.field_of_view
+    }
+
+    pub fn pixel_size(&self) -> Float {
+        self.pixel_size
+    }
+
+    pub fn ray_for_pixel(&self, x: usize, y: usize) -> Ray {
+        self.ray_for_pixel_offset(x, y, 0.5, 0.5)
+    }
+
+    // dx and dy in [0, 1) select the point inside the pixel the ray passes through
+    pub fn ray_for_pixel_offset(&self, x: usize, y: usize, dx: Float, dy: Float) -> Ray {
+        self.ray_for_sample(x, y, (dx, dy), (0.5, 0.5), 0.0)
+    }
+
+    // lens in [0, 1) x [0, 1) is mapped onto the aperture disk, (0.5, 0.5) is the center of the lens.
+    // time in [0, 1) is the point within the exposure the ray is cast at
+    pub fn ray_for_sample(
+        &self,
+        x: usize,
+        y: usize,
+        (dx, dy): (Float, Float),
+        lens: (Float, Float),
+        time: Float,
+    ) -> Ray {
+        let (u, v) = self.image_coordinates(x, y, dx, dy);
+        // every ray through this pixel meets the others at the focus point
+        let (focus_x, focus_y, focus_z) = match self.projection {
+            Projection::Perspective => {
+                // the camera looks toward -z, so +x is to the left
+                let world_x = self.half_width * u;
+                let world_y = self.half_height * v;
+                (world_x, world_y, -1.0)
+            }
+            Projection::Fisheye => {
+                let theta = u.hypot(v) * self.field_of_view / 2.0;
+                let phi = v.atan2(u);
+                (
+                    theta.sin() * phi.cos(),
+                    theta.sin() * phi.sin(),
+                    -theta.cos(),
+                )
+            }
+            Projection::Equirectangular => {
+                let longitude = (x as Float + dx) / self.hsize as Float * 2.0 * PI - PI;
+                let latitude = v * PI / 2.0;
+                (
+                    -latitude.cos() * longitude.sin(),
+                    latitude.sin(),
+                    -latitude.cos() * longitude.cos(),
+                )
+            }
+        };
+
+        let inverse = match self.transform_end {
+            Some(end) => self.transform.matrix().interpolate(end, time).inverse(),
+            None => self.transform.inverse(),
+        }
+        .expect("camera transform has to be invertible");
+        let focus = inverse
+            * Tuple4D::new_point(
+                focus_x * self.focal_distance,
+                focus_y * self.focal_distance,
+                focus_z * self.focal_distance,
+            );
+        let (lens_x, lens_y) = concentric_disk(lens);
+        let origin = inverse
+            * Tuple4D::new_point(
+                lens_x * self.aperture_radius,
+                lens_y * self.aperture_radius,
+                0.0,
+            );
+        let direction = (focus - origin).normalize();
+        Ray::new(origin, direction)
+            .with_time(time)
+            .with_cone(0.0, self.ray_spread())
+    }
+
+    // angle covered by a single pixel, the width of the ray cone grows by this per unit distance
+    fn ray_spread(&self) -> Float {
+        match self.projection {
+            Projection::Perspective => self.pixel_size,
+            Projection::Fisheye => self.field_of_view / self.hsize.min(self.vsize) as Float,
+            Projection::Equirectangular => 2.0 * PI / self.hsize as Float,
+        }
+    }
+
+    // position on the image from 1 at the left / top edge to -1 at the right / bottom edge, scaled
+    // so that the shorter side spans [-1, 1] for perspective and fisheye projections
+    fn image_coordinates(&self, x: usize, y: usize, dx: Float, dy: Float) -> (Float, Float) {
+        let u = 1.0 - 2.0 * (x as Float + dx) / self.hsize as Float;
+        let v = 1.0 - 2.0 * (y as Float + dy) / self.vsize as Float;
+        if self.projection != Projection::Fisheye {
+            return (u, v);
+        }
+        let shorter = self.hsize.min(self.vsize) as Float;
+        (
+            u * self.hsize as Float / shorter,
+            v * self.vsize as Float / shorter,
+        )
+    }
+
+    // a single sample without aperture traces the pixel center at time 0, motion blur and depth of field
+    // need more samples to not be noisy
+    pub fn pixel_color(&self, world: &World, x: usize, y: usize) -> Color {
+        if self.outside_of_image(x, y) {
+            return Color::new(0.0, 0.0, 0.0);
+        }
+        let rays = self.pixel_rays(x, y);
+        let count = rays.len();
+        let sum = rays
+            .into_iter()
+            .fold(Color::new(0.0, 0.0, 0.0), |sum, (index, ray)| {
+                let mut rng = rng_for(0, (x, y), index, SampleDimension::Light);
+                sum + self.integrator.radiance(world, &ray, &mut rng)
+            });
+        sum / count as Float
+    }
+
+    // the color averaged over the samples that hit an object and the fraction of those samples.
+    // Pixels that do not see any object are transparent black
+    pub fn pixel_color_alpha(&self, world: &World, x: usize, y: usize) -> (Color, Float) {
+        if self.outside_of_image(x, y) {
+            return (Color::new(0.0, 0.0, 0.0), 0.0);
+        }
+        let rays = self.pixel_rays(x, y);
+        let count = rays.len();
+        let (sum, hits) = rays.into_iter().fold(
+            (Color::new(0.0, 0.0, 0.0), 0),
+            |(sum, hits), (index, ray)| {
+                let intersections = world.intersect_world(&ray);
+                if hit(&intersections).is_none() {
+                    return (sum, hits);
+                }
+                let mut rng = rng_for(0, (x, y), index, SampleDimension::Light);
+                let color = self.integrator.radiance_of_intersections(
+                    world,
+                    &ray,
+                    &intersections,
+                    &mut rng,
+                );
+                (sum + color, hits + 1)
+            },
+        );
+        if hits == 0 {
+            return (sum, 0.0);
+        }
+        (sum / hits as Float, hits as Float / count as Float)
+    }
+
+    // the fisheye image is a circle, the corners around it stay black
+    pub(crate) fn outside_of_image(&self, x: usize, y: usize) -> bool {
+        if self.projection != Projection::Fisheye {
+            return false;
+        }
+        let (u, v) = self.image_coordinates(x, y, 0.5, 0.5);
+        u.hypot(v) > 1.0
+    }
+
+    // the sample index and ray of every sample of the pixel
+    pub(crate) fn pixel_rays(&self, x: usize, y: usize) -> Vec<(usize, Ray)> {
+        if self.single_ray_per_pixel() {
+            return vec![(0, self.ray_for_pixel(x, y))];
+        }
+        let count = self.samples_per_pixel.max(1);
+        (0..count)
+            .map(|index| {
+                let pixel = self
+                    .sampler
+                    .sample_2d((x, y), index, count, SampleDimension::Pixel);
+                let lens = self
+                    .sampler
+                    .sample_2d((x, y), index, count, SampleDimension::Lens);
+                let (time, _) = self
+                    .sampler
+                    .sample_2d((x, y), index, count, SampleDimension::Time);
+                (index, self.ray_for_sample(x, y, pixel, lens, time))
+            })
+            .collect()
+    }
+
+    fn single_ray_per_pixel(&self) -> bool {
+        self.samples_per_pixel <= 1
+            && self.aperture_radius == 0.0
+            && self.integrator == Integrator::Whitted
+    }
+
+    // pixel_color of the pixels x of row y. Pixels with a single ray are traced in packets, which
+    // find the first hits of several rays at once
+    fn row_colors(&self, world: &World, xs: Range<usize>, y: usize) -> Vec<Color> {
+        if !self.single_ray_per_pixel() || self.projection == Projection::Fisheye {
+            return xs.map(|x| self.pixel_color(world, x, y)).collect();
+        }
+        let rays: Vec<Ray> = xs.map(|x| self.ray_for_pixel(x, y)).collect();
+        let mut buffer = IntersectionBuffer::new();
+        rays.chunks(PACKET_SIZE)
+            .flat_map(|rays| world.color_at_packet_with(rays, &mut buffer))
+            .collect()
+    }
+
+    pub fn render(&self, world: &World) -> Canvas {
+        self.render_with(world, &CancellationToken::new(), |_, _| true)
+    }
+
+    // on_progress is called on the calling thread after every finished row. once the token is cancelled
+    // the workers stop picking up new rows and the partially rendered canvas is returned
+    pub fn render_with_progress(
+        &self,
+        world: &World,
+        token: &CancellationToken,
+        mut on_progress: impl FnMut(&Progress),
+    ) -> Canvas {
+        let start = Instant::now();
+        let mut rows_done = 0;
+        self.render_with(world, token, |_, _| {
+            rows_done += 1;
+            on_progress(&Progress {
+                rows_done,
+                rows_total: self.vsize,
+                elapsed: start.elapsed(),
+            });
+            true
+        })
+    }
+
+    // on_row is called on the calling thread with the canvas after every finished row,
+    // returning false stops the render and the partially filled canvas is returned
+    pub(crate) fn render_with(
+        &self,
+        world: &World,
+        token: &CancellationToken,
+        mut on_row: impl FnMut(&Canvas, usize) -> bool,
+    ) -> Canvas {
+        let mut image = Canvas::create_canvas(self.hsize, self.vsize);
+        self.render_rows(
+            self.vsize,
+            token,
+            |y| self.row_colors(world, 0..self.hsize, y),
+            |y, row| {
+                for (x, color) in row.into_iter().enumerate() {
+                    image.write_pixel(x, y, color);
+                }
+                on_row(&image, y)
+            },
+        );
+        image
+    }
+
+    // only the pixels in region of the image, clamped to its size. The pixels are the same as in
+    // the full render
+    pub fn render_region(&self, world: &World, region: Region) -> Canvas {
+        self.render_region_with_progress(world, region, &CancellationToken::new(), |_| ())
+    }
+
+    pub fn render_region_with_progress(
+        &self,
+        world: &World,
+        region: Region,
+        token: &CancellationToken,
+        mut on_progress: impl FnMut(&Progress),
+    ) -> Canvas {
+        let region = region.clamp_to(self.hsize, self.vsize);
+        let start = Instant::now();
+        let mut image = Canvas::create_canvas(region.width, region.height);
+        let mut rows_done = 0;
+        self.render_rows(
+            region.height,
+            token,
+            |row| self.row_colors(world, region.x..region.x + region.width, region.y + row),
+            |row, colors| {
+                for (x, color) in colors.into_iter().enumerate() {
+                    image.write_pixel(x, row, color);
+                }
+                rows_done += 1;
+                on_progress(&Progress {
+                    rows_done,
+                    rows_total: region.height,
+                    elapsed: start.elapsed(),
+                });
+                true
+            },
+        );
+        image
+    }
+
+    // re-renders region of a full size image and leaves the rest of it as it is
+    pub fn render_region_into(&self, world: &World, region: Region, image: &mut Canvas) {
+        let region = region.clamp_to(self.hsize, self.vsize);
+        image.paste(region.x, region.y, &self.render_region(world, region));
+    }
+
+    // the image with an alpha channel that is transparent where the camera sees no object, for
+    // compositing the objects over other images
+    pub fn render_rgba(&self, world: &World) -> RgbaCanvas {
+        self.render_rgba_with_progress(world, &CancellationToken::new(), |_| ())
+    }
+
+    pub fn render_rgba_with_progress(
+        &self,
+        world: &World,
+        token: &CancellationToken,
+        mut on_progress: impl FnMut(&Progress),
+    ) -> RgbaCanvas {
+        let start = Instant::now();
+        let mut image = RgbaCanvas::new(self.hsize, self.vsize);
+        let mut rows_done = 0;
+        self.render_rows(
+            self.vsize,
+            token,
+            |y| {
+                (0..self.hsize)
+                    .map(|x| self.pixel_color_alpha(world, x, y))
+                    .collect()
+            },
+            |y, row| {
+                for (x, (color, alpha)) in row.into_iter().enumerate() {
+                    image.write_pixel(x, y, color, alpha);
+                }
+                rows_done += 1;
+                on_progress(&Progress {
+                    rows_done,
+                    rows_total: self.vsize,
+                    elapsed: start.elapsed(),
+                });
+                true
+            },
+        );
+        image
+    }
+
+    // a value for every pixel, computed on the render threads
+    pub(crate) fn render_pass<T: Clone + Send>(
+        &self,
+        default: T,
+        pixel: impl Fn(usize, usize) -> T + Sync,
+    ) -> Vec2D<T> {
+        self.render_pass_region(Region::new(0, 0, self.hsize, self.vsize), default, pixel)
+    }
+
+    // render_pass for the pixels of the region only
+    pub(crate) fn render_pass_region<T: Clone + Send>(
+        &self,
+        region: Region,
+        default: T,
+        pixel: impl Fn(usize, usize) -> T + Sync,
+    ) -> Vec2D<T> {
+        let region = region.clamp_to(self.hsize, self.vsize);
+        let mut image = Vec2D::new(region.width, region.height, default);
+        self.render_rows(
+            region.height,
+            &CancellationToken::new(),
+            |row| {
+                (region.x..region.x + region.width)
+                    .map(|x| pixel(x, region.y + row))
+                    .collect()
+            },
+            |row, values| {
+                for (x, value) in values.into_iter().enumerate() {
+                    image.write_pixel(x, row, value);
+                }
+                true
+            },
+        );
+        image
+    }
+
+    // renders the image in passes of shrinking blocks, each pass traces one pixel per block and fills the
+    // whole block with it. on_pass gets every intermediate canvas, the last one equals the output of render
+    pub fn render_progressive(
+        &self,
+        world: &World,
+        initial_block_size: usize,
+        mut on_pass: impl FnMut(&Canvas),
+    ) -> Canvas {
+        let mut image = Canvas::create_canvas(self.hsize, self.vsize);
+        let mut previous_block_size = None;
+        let mut block_size = initial_block_size.max(1).next_power_of_two();
+        loop {
+            let rows = self.vsize.div_ceil(block_size);
+            let coarse = image.clone();
+            let mut rendered_rows = vec![];
+            let render_row = |row: usize| {
+                let y = row * block_size;
+                (0..self.hsize)
+                    .step_by(block_size)
+                    .map(|x| {
+                        // pixels that were traced in the previous pass are reused
+                        let traced = previous_block_size.is_some_and(|previous| {
+                            x.is_multiple_of(previous) && y.is_multiple_of(previous)
+                        });
+                        if traced {
+                            coarse.read_pixel(x, y)
+                        } else {
+                            self.pixel_color(world, x, y)
+                        }
+                    })
+                    .collect()
+            };
+            self.render_rows(
+                rows,
+                &CancellationToken::new(),
+                render_row,
+                |row, colors| {
+                    rendered_rows.push((row, colors));
+                    true
+                },
+            );
+
+            for (row, colors) in rendered_rows {
+                let y = row * block_size;
+                for (column, color) in colors.into_iter().enumerate() {
+                    let x = column * block_size;
+                    for block_y in y..(y + block_size).min(self.vsize) {
+                        for block_x in x..(x + block_size).min(self.hsize) {
+                            image.write_pixel(block_x, block_y, color);
+                        }
+                    }
+                }
+            }
+            on_pass(&image);
+
+            if block_size == 1 {
+                return image;
+            }
+            previous_block_size = Some(block_size);
+            block_size /= 2;
+        }
+    }
+
+    // calls render_row for every row index on the worker threads, the results are passed to on_row
+    // on the calling thread in the order they finish. returning false from on_row or cancelling the
+    // token stops the workers, pausing it holds them before their next row
+    fn render_rows<F, T>(
+        &self,
+        rows: usize,
+        token: &CancellationToken,
+        render_row: F,
+        mut on_row: impl FnMut(usize, Vec<T>) -> bool,
+    ) where
+        F: Fn(usize) -> Vec<T> + Sync,
+        T: Send,
+    {
+        // rows are handed out one by one so that threads which finish cheap rows early keep working
+        let next_row = AtomicUsize::new(0);
+        let cancelled = AtomicBool::new(false);
+        thread::scope(|scope| {
+            let (sender, receiver) = mpsc::channel();
+            for _ in 0..self.threads.max(1) {
+                let sender = sender.clone();
+                let (next_row, cancelled, render_row) = (&next_row, &cancelled, &render_row);
+                scope.spawn(move || loop {
+                    token.wait_while_paused();
+                    let row = next_row.fetch_add(1, Ordering::Relaxed);
+                    if row >= rows || cancelled.load(Ordering::Relaxed) || token.is_cancelled() {
+                        return;
+                    }
+                    if sender.send((row, render_row(row))).is_err() {
+                        return;
+                    }
+                });
+            }
+            // the receiver only finishes once every worker dropped its sender
+            drop(sender);
+            for (row, colors) in receiver {
+                if !on_row(row, colors) {
+                    cancelled.store(true, Ordering::Relaxed);
+                    break;
+                }
+            }
+        });
+    }
+}
+
+// maps the unit square onto the unit disk, keeping the distribution of the samples uniform
+fn concentric_disk((u, v): (Float, Float)) -> (Float, Float) {
+    let (a, b) = (2.0 * u - 1.0, 2.0 * v - 1.0);
+    if a == 0.0 && b == 0.0 {
+        return (0.0, 0.0);
+    }
+    let (radius, angle) = if a.abs() > b.abs() {
+        (a, FRAC_PI_4 * (b / a))
+    } else {
+        (b, 2.0 * FRAC_PI_4 - FRAC_PI_4 * (a / b))
+    };
+    (radius * angle.cos(), radius * angle.sin())
+}
+
+fn default_sampler() -> Arc<dyn Sampler> {
+    Arc::new(RandomSampler::default())
+}
+
+// square camera with a field of view of 90 degrees, looking at the origin from 5 units in front of
+// it, as used by the tests of the passes that render the default world
+#[cfg(test)]
+pub(crate) fn test_camera(size: usize) -> Camera {
+    let mut camera = Camera::new(size, size, PI / 2.0);
+    camera.transform = Matrix4::view_transform(
+        Tuple4D::new_point(0.0, 0.0, -5.0),
+        Tuple4D::new_point(0.0, 0.0, 0.0),
+        Tuple4D::new_vector(0.0, 1.0, 0.0),
+    )
+    .into();
+    camera
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::consts::PI;
+
+    use crate::math::{float_eq, FLOAT_EQ_EPS};
+    use crate::Object;
+
+    use super::*;
+
+    #[test]
+    fn constructing_camera() {
+        let camera = Camera::new(160, 120, PI / 2.0);
+        assert_eq!(camera.hsize(), 160);
+        assert_eq!(camera.vsize(), 120);
+        assert!(float_eq(camera.field_of_view(), PI / 2.0, FLOAT_EQ_EPS));
+        assert_eq!(camera.transform.matrix(), Matrix4::eye());
+    }
+
+    #[test]
+    fn pixel_size_for_horizontal_canvas() {
+        let camera = Camera::new(200, 125, PI / 2.0);
+        assert!(float_eq(camera.pixel_size(), 0.01, FLOAT_EQ_EPS));
+    }
+
+    #[test]
+    fn camera_rays_grow_by_one_pixel_per_unit_distance() {
+        let camera = Camera::new(200, 125, PI / 2.0);
+        let ray = camera.ray_for_pixel(100, 50);
+        assert_eq!(ray.footprint, 0.0);
+        assert!(float_eq(ray.spread, 0.01, FLOAT_EQ_EPS));
+    }
+
+    #[test]
+    fn pixel_size_for_vertical_canvas() {
+        let camera = Camera::new(125, 200, PI / 2.0);
+        assert!(float_eq(camera.pixel_size(), 0.01, FLOAT_EQ_EPS));
+    }
+
+    #[test]
+    fn ray_through_center_of_canvas() {
+        let camera = Camera::new(201, 101, PI / 2.0);
+        let ray = camera.ray_for_pixel(100, 50);
+        assert_eq!(ray.origin, Tuple4D::new_point(0.0, 0.0, 0.0));
+        assert_eq!(ray.direction, Tuple4D::new_vector(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn ray_through_corner_of_canvas() {
+        let camera = Camera::new(201, 101, PI / 2.0);
+        let ray = camera.ray_for_pixel(0, 0);
+        assert_eq!(ray.origin, Tuple4D::new_point(0.0, 0.0, 0.0));
+        assert_eq!(
+            ray.direction,
+            Tuple4D::new_vector(0.66519, 0.33259, -0.66851)
+        );
+    }
+
+    #[test]
+    fn ray_when_camera_is_transformed() {
+        let mut camera = Camera::new(201, 101, PI / 2.0);
+        camera.transform =
+            (Matrix4::rotation_y(PI / 4.0) * Matrix4::translation(0.0, -2.0, 5.0)).into();
+        let ray = camera.ray_for_pixel(100, 50);
+        let value = Float::sqrt(2.0) / 2.0;
+        assert_eq!(ray.origin, Tuple4D::new_point(0.0, 2.0, -5.0));
+        assert_eq!(ray.direction, Tuple4D::new_vector(value, 0.0, -value));
+    }
+
+    #[test]
+    fn rendering_world_with_camera() {
+        let world = World::default_world();
+        let mut camera = Camera::new(11, 11, PI / 2.0);
+        let from = Tuple4D::new_point(0.0, 0.0, -5.0);
+        let to = Tuple4D::new_point(0.0, 0.0, 0.0);
+        let up = Tuple4D::new_vector(0.0, 1.0, 0.0);
+        camera.transform = Matrix4::view_transform(from, to, up).into();
+        let image = camera.render(&world);
+        assert_eq!(image.read_pixel(5, 5), Color::new(0.38066, 0.47583, 0.2855));
+    }
+
+    #[test]
+    fn thread_count_does_not_change_image() {
+        let world = World::default_world();
+        let mut camera = Camera::new(16, 9, PI / 3.0);
+        camera.transform = Matrix4::view_transform(
+            Tuple4D::new_point(0.0, 0.5, -4.0),
+            Tuple4D::new_point(0.0, 0.0, 0.0),
+            Tuple4D::new_vector(0.0, 1.0, 0.0),
+        )
+        .into();
+        camera.threads = 1;
+        let single = camera.render(&world);
+        camera.threads = 4;
+        let multi = camera.render(&world);
+        for y in 0..9 {
+            for x in 0..16 {
+                assert_eq!(single.read_pixel(x, y), multi.read_pixel(x, y));
+            }
+        }
+    }
+
+    #[test]
+    fn rendering_a_region() {
+        let world = World::default_world();
+        let mut camera = Camera::new(16, 9, PI / 3.0);
+        camera.transform = Matrix4::view_transform(
+            Tuple4D::new_point(0.0, 0.5, -4.0),
+            Tuple4D::new_point(0.0, 0.0, 0.0),
+            Tuple4D::new_vector(0.0, 1.0, 0.0),
+        )
+        .into();
+        camera.samples_per_pixel = 4;
+        let full = camera.render(&world);
+        let region = camera.render_region(&world, Region::new(6, 3, 20, 4));
+        assert_eq!((region.width(), region.height()), (10, 4));
+        for y in 0..4 {
+            for x in 0..10 {
+                assert_eq!(region.read_pixel(x, y), full.read_pixel(x + 6, y + 3));
+            }
+        }
+        let mut image = Canvas::new(16, 9, Color::new(1.0, 0.0, 0.0));
+        camera.render_region_into(&world, Region::new(6, 3, 2, 2), &mut image);
+        assert_eq!(image.read_pixel(7, 4), full.read_pixel(7, 4));
+        assert_eq!(image.read_pixel(8, 4), Color::new(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn progressive_render_ends_with_full_image() {
+        let world = World::default_world();
+        let mut camera = Camera::new(11, 7, PI / 3.0);
+        camera.transform = Matrix4::view_transform(
+            Tuple4D::new_point(0.0, 0.5, -4.0),
+            Tuple4D::new_point(0.0, 0.0, 0.0),
+            Tuple4D::new_vector(0.0, 1.0, 0.0),
+        )
+        .into();
+        let mut passes = 0;
+        let progressive = camera.render_progressive(&world, 4, |_| passes += 1);
+        assert_eq!(passes, 3);
+        let full = camera.render(&world);
+        for y in 0..7 {
+            for x in 0..11 {
+                assert_eq!(progressive.read_pixel(x, y), full.read_pixel(x, y));
+            }
+        }
+    }
+
+    #[test]
+    fn first_progressive_pass_fills_blocks() {
+        let world = World::default_world();
+        let camera = Camera::new(4, 4, PI / 2.0);
+        let mut first_pass = None;
+        camera.render_progressive(&world, 2, |canvas| {
+            if first_pass.is_none() {
+                first_pass = Some(canvas.clone());
+            }
+        });
+        let first_pass = first_pass.unwrap();
+        let corner = first_pass.read_pixel(2, 2);
+        assert_eq!(corner, world.color_at(&camera.ray_for_pixel(2, 2)));
+        assert_eq!(first_pass.read_pixel(3, 2), corner);
+        assert_eq!(first_pass.read_pixel(2, 3), corner);
+        assert_eq!(first_pass.read_pixel(3, 3), corner);
+    }
+
+    #[test]
+    fn progress_is_reported_for_every_row() {
+        let world = World::default_world();
+        let camera = Camera::new(5, 4, PI / 2.0);
+        let mut reports = vec![];
+        camera.render_with_progress(&world, &CancellationToken::new(), |progress| {
+            reports.push(progress.rows_done)
+        });
+        assert_eq!(reports, vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn cancelled_render_stops_early() {
+        let world = World::default_world();
+        let camera = Camera::new(5, 4, PI / 2.0);
+        let token = CancellationToken::new();
+        token.cancel();
+        let mut rows_seen = 0;
+        let image = camera.render_with_progress(&world, &token, |progress| {
+            rows_seen = progress.rows_done;
+        });
+        assert_eq!(rows_seen, 0);
+        assert_eq!(image.width(), 5);
+        assert_eq!(image.read_pixel(2, 2), Color::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn single_sample_uses_pixel_center() {
+        let world = World::default_world();
+        let camera = Camera::new(11, 11, PI / 2.0);
+        assert_eq!(
+            camera.pixel_color(&world, 3, 7),
+            world.color_at(&camera.ray_for_pixel(3, 7))
+        );
+    }
+
+    #[test]
+    fn supersampling_averages_edge_pixels() {
+        // a pixel on the silhouette of the sphere lies between background and sphere color
+        let mut world = World::default_world();
+        world.objects.truncate(1);
+        world.objects[0].material.pattern = None;
+        world.objects[0].material.color = Color::new(1.0, 1.0, 1.0);
+        world.objects[0].material.ambient = 1.0;
+        world.objects[0].material.diffuse = 0.0;
+        world.objects[0].material.specular = 0.0;
+        let mut camera = Camera::new(8, 8, PI / 3.0);
+        camera.transform = Matrix4::view_transform(
+            Tuple4D::new_point(0.0, 0.0, -2.0),
+            Tuple4D::new_point(0.0, 0.0, 0.0),
+            Tuple4D::new_vector(0.0, 1.0, 0.0),
+        )
+        .into();
+        camera.samples_per_pixel = 64;
+        // sphere of radius 1 at distance 2 subtends exactly the field of view, the corner pixels are cut by it
+        let corner = camera.pixel_color(&world, 1, 1);
+        assert!(corner.r > 0.0 && corner.r < 1.0, "{corner:?}");
+        assert_eq!(camera.pixel_color(&world, 4, 4), Color::new(1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn ray_through_pixel_corner() {
+        let camera = Camera::new(201, 101, PI / 2.0);
+        let ray = camera.ray_for_pixel_offset(0, 0, 0.0, 0.0);
+        assert_eq!(ray.origin, Tuple4D::new_point(0.0, 0.0, 0.0));
+        let expected = Tuple4D::new_vector(1.0, 101.0 / 201.0, -1.0).normalize();
+        assert_eq!(ray.direction, expected);
+    }
+
+    #[test]
+    fn lens_rays_meet_on_focal_plane() {
+        let mut camera = Camera::new(11, 11, PI / 2.0);
+        camera.aperture_radius = 0.5;
+        camera.focal_distance = 4.0;
+        let center = camera.ray_for_sample(2, 3, (0.5, 0.5), (0.5, 0.5), 0.0);
+        let edge = camera.ray_for_sample(2, 3, (0.5, 0.5), (1.0, 0.5), 0.0);
+        assert_eq!(edge.origin, Tuple4D::new_point(0.5, 0.0, 0.0));
+        let t_center = -4.0 / center.direction.z;
+        let t_edge = -4.0 / edge.direction.z;
+        assert_eq!(center.position(t_center), edge.position(t_edge));
+    }
+
+    #[test]
+    fn closed_aperture_is_a_pinhole() {
+        let mut camera = Camera::new(11, 11, PI / 2.0);
+        camera.focal_distance = 3.0;
+        let ray = camera.ray_for_sample(4, 9, (0.5, 0.5), (0.9, 0.1), 0.0);
+        let pinhole = camera.ray_for_pixel(4, 9);
+        assert_eq!(ray.origin, pinhole.origin);
+        assert_eq!(ray.direction, pinhole.direction);
+    }
+
+    #[test]
+    fn concentric_disk_stays_inside_unit_circle() {
+        assert_eq!(concentric_disk((0.5, 0.5)), (0.0, 0.0));
+        for (u, v) in [(0.0, 0.0), (1.0, 0.3), (0.2, 0.9), (0.99, 0.99)] {
+            let (x, y) = concentric_disk((u, v));
+            assert!(x * x + y * y <= 1.0 + FLOAT_EQ_EPS);
+        }
+    }
+
+    #[test]
+    fn moving_camera_interpolates_transform() {
+        let mut camera = Camera::new(11, 11, PI / 2.0);
+        camera.transform_end = Some(Matrix4::translation(-4.0, 0.0, 0.0));
+        let ray = camera.ray_for_sample(5, 5, (0.5, 0.5), (0.5, 0.5), 0.5);
+        assert_eq!(ray.origin, Tuple4D::new_point(2.0, 0.0, 0.0));
+        assert_eq!(ray.time, 0.5);
+    }
+
+    #[test]
+    fn moving_object_is_blurred() {
+        let mut world = World::default_world();
+        world.objects.truncate(1);
+        world.objects[0].transform_end = Some(Matrix4::translation(3.0, 0.0, 0.0));
+        let mut camera = Camera::new(11, 11, PI / 2.0);
+        camera.transform = Matrix4::translation(0.0, 0.0, -5.0).into();
+        camera.samples_per_pixel = 16;
+        // the center pixel sees the sphere only during the first part of the exposure
+        let color = camera.pixel_color(&world, 5, 5);
+        let still = world.color_at(&camera.ray_for_pixel(5, 5));
+        assert!(color.g > 0.0 && color.g < still.g, "{color:?}");
+    }
+
+    #[test]
+    fn fisheye_center_looks_forward() {
+        let mut camera = Camera::new(101, 101, PI);
+        camera.projection = Projection::Fisheye;
+        let ray = camera.ray_for_pixel(50, 50);
+        assert_eq!(ray.direction, Tuple4D::new_vector(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn fisheye_edge_reaches_half_field_of_view() {
+        let mut camera = Camera::new(100, 100, PI);
+        camera.projection = Projection::Fisheye;
+        let ray = camera.ray_for_pixel_offset(0, 50, 0.0, 0.0);
+        // left edge of a 180 degree fisheye looks sideways, +x is to the left
+        assert_eq!(ray.direction, Tuple4D::new_vector(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn fisheye_masks_pixels_outside_the_circle() {
+        let world = World::default_world();
+        let mut camera = Camera::new(20, 20, PI);
+        camera.projection = Projection::Fisheye;
+        camera.transform = Matrix4::translation(0.0, 0.0, -5.0).into();
+        assert_eq!(camera.pixel_color(&world, 0, 0), Color::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn equirectangular_covers_full_sphere() {
+        let mut camera = Camera::new(200, 100, PI / 2.0);
+        camera.projection = Projection::Equirectangular;
+        let forward = camera.ray_for_pixel_offset(100, 50, 0.0, 0.0);
+        assert_eq!(forward.direction, Tuple4D::new_vector(0.0, 0.0, -1.0));
+        let behind = camera.ray_for_pixel_offset(0, 50, 0.0, 0.0);
+        assert_eq!(behind.direction, Tuple4D::new_vector(0.0, 0.0, 1.0));
+        let right = camera.ray_for_pixel_offset(150, 50, 0.0, 0.0);
+        assert_eq!(right.direction, Tuple4D::new_vector(-1.0, 0.0, 0.0));
+        let up = camera.ray_for_pixel_offset(100, 0, 0.0, 0.0);
+        assert_eq!(up.direction, Tuple4D::new_vector(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn rgba_render_is_transparent_where_nothing_is_hit() {
+        let world = World::default_world();
+        let camera = test_camera(11);
+        let image = camera.render_rgba(&world);
+        let (center, alpha) = image.read_pixel(5, 5);
+        assert_eq!(alpha, 1.0);
+        assert_eq!(center, camera.render(&world).read_pixel(5, 5));
+        assert_eq!(image.read_pixel(0, 0), (Color::new(0.0, 0.0, 0.0), 0.0));
+    }
+
+    #[test]
+    fn edge_pixels_are_partially_covered() {
+        let mut world = World::new();
+        world.objects.push(Object::sphere());
+        let mut camera = Camera::new(1, 1, PI / 2.0);
+        camera.samples_per_pixel = 64;
+        // the single pixel looks at the edge of the sphere
+        camera.transform = Matrix4::view_transform(
+            Tuple4D::new_point(-1.0, 0.0, -5.0),
+            Tuple4D::new_point(-1.0, 0.0, 0.0),
+            Tuple4D::new_vector(0.0, 1.0, 0.0),
+        )
+        .into();
+        let (_, alpha) = camera.pixel_color_alpha(&world, 0, 0);
+        assert!(alpha > 0.0 && alpha < 1.0);
+    }
+
+    #[test]
+    fn path_traced_pixels_are_deterministic() {
+        let world = World::default_world();
+        let mut camera = Camera::new(5, 5, PI / 2.0);
+        camera.transform = Matrix4::translation(0.0, 0.0, -5.0).into();
+        camera.integrator = Integrator::path_tracing(4);
+        camera.samples_per_pixel = 4;
+        assert_eq!(
+            camera.pixel_color(&world, 2, 2),
+            camera.pixel_color(&world, 2, 2)
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serializing_camera() {
+        let mut camera = Camera::new(160, 120, PI / 3.0);
+        camera.transform = Matrix4::translation(0.0, -1.0, 5.0).into();
+        camera.integrator = Integrator::path_tracing(3);
+        camera.samples_per_pixel = 16;
+        let json = serde_json::to_string(&camera).unwrap();
+        let restored: Camera = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.hsize(), 160);
+        assert_eq!(restored.pixel_size(), camera.pixel_size());
+        assert_eq!(restored.transform, camera.transform);
+        assert_eq!(restored.integrator, camera.integrator);
+        assert_eq!(restored.samples_per_pixel, 16);
+    }
+}
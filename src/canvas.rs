@@ -1,9 +1,12 @@
 use std::fs::File;
-use std::io::{BufWriter, Error, ErrorKind, Write};
+use std::io::{BufWriter, Error, ErrorKind, Read, Write};
+use std::path::Path;
+use std::str::FromStr;
 
-use crate::Color;
+use crate::math::Float;
+use crate::{Color, ColorEncoding};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Vec2D<T> {
     data: Vec<T>,
     width: usize,
@@ -12,25 +15,141 @@ pub struct Vec2D<T> {
 
 pub type Canvas = Vec2D<Color>;
 
-impl Canvas {
-    pub fn create_canvas(width: usize, height: usize) -> Canvas {
-        let data: Vec<Color> = vec![Color::new(0.0, 0.0, 0.0); width * height];
-        Canvas {
-            data,
+// how Canvas::save_with_options stores the colors. The encoding is not applied to hdr and exr, they
+// always store linear values
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ImageOptions {
+    // 16 instead of 8 bit per channel for ppm and png, avoids banding in smooth gradients
+    pub sixteen_bit: bool,
+    pub encoding: ColorEncoding,
+    // of the lossy jpeg and webp formats, from 1 to 100
+    pub quality: u8,
+}
+
+impl Default for ImageOptions {
+    fn default() -> Self {
+        ImageOptions {
+            sixteen_bit: false,
+            encoding: ColorEncoding::Srgb,
+            quality: 90,
+        }
+    }
+}
+
+// rectangle of pixels with its top left corner at x, y
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Region {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Region {
+    pub fn new(x: usize, y: usize, width: usize, height: usize) -> Region {
+        Region {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    // the part of the region inside of an image of the given size, empty if they do not overlap
+    pub fn clamp_to(&self, width: usize, height: usize) -> Region {
+        let (x, y) = (self.x.min(width), self.y.min(height));
+        Region::new(x, y, self.width.min(width - x), self.height.min(height - y))
+    }
+}
+
+// "x,y,width,height"
+impl FromStr for Region {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let values = text
+            .split(',')
+            .map(|value| value.trim().parse::<usize>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| format!("invalid region {text}: {err}"))?;
+        match values[..] {
+            [x, y, width, height] => Ok(Region::new(x, y, width, height)),
+            _ => Err(format!("invalid region {text}, expected x,y,width,height")),
+        }
+    }
+}
+
+impl<T: Clone> Vec2D<T> {
+    // every pixel set to value
+    pub fn new(width: usize, height: usize, value: T) -> Vec2D<T> {
+        Vec2D {
+            data: vec![value; width * height],
             width,
             height,
         }
     }
 
-    pub fn write_pixel(&mut self, width: usize, height: usize, color: Color) {
+    // copy of the pixels in region, which is clamped to the image
+    pub fn crop(&self, region: Region) -> Vec2D<T> {
+        let region = region.clamp_to(self.width, self.height);
+        let data = (region.y..region.y + region.height)
+            .flat_map(|y| {
+                let start = y * self.width + region.x;
+                self.data[start..start + region.width].iter().cloned()
+            })
+            .collect();
+        Vec2D {
+            data,
+            width: region.width,
+            height: region.height,
+        }
+    }
+
+    // writes other with its top left corner at x, y. Pixels that end up outside of the image are
+    // dropped
+    pub fn paste(&mut self, x: usize, y: usize, other: &Vec2D<T>) {
+        let region = Region::new(x, y, other.width, other.height).clamp_to(self.width, self.height);
+        for row in 0..region.height {
+            let start = (region.y + row) * self.width + region.x;
+            let source = row * other.width;
+            self.data[start..start + region.width]
+                .clone_from_slice(&other.data[source..source + region.width]);
+        }
+    }
+}
+
+impl<T> Vec2D<T> {
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn write_pixel(&mut self, width: usize, height: usize, value: T) {
         let idx = self.width * height;
-        self.data[idx + width] = color;
+        self.data[idx + width] = value;
+    }
+
+    // the pixels row by row
+    pub fn iter(&self) -> std::slice::Iter<'_, T> {
+        self.data.iter()
     }
+}
 
-    pub fn read_pixel(&self, width: usize, height: usize) -> Color {
+impl<T: Copy> Vec2D<T> {
+    pub fn read_pixel(&self, width: usize, height: usize) -> T {
         let idx = self.width * height;
         self.data[idx + width]
     }
+}
+
+impl Canvas {
+    pub fn create_canvas(width: usize, height: usize) -> Canvas {
+        Canvas::new(width, height, Color::new(0.0, 0.0, 0.0))
+    }
 
     pub fn to_ppm(&self, filename: &str) -> std::io::Result<()> {
         let file = File::create(filename)?;
@@ -47,6 +166,374 @@ impl Canvas {
         Ok(())
     }
 
+    // the format is chosen by the extension of the filename, with the default options
+    pub fn save(&self, filename: &str) -> std::io::Result<()> {
+        self.save_with_options(filename, &ImageOptions::default())
+    }
+
+    pub fn save_with_options(&self, filename: &str, options: &ImageOptions) -> std::io::Result<()> {
+        let extension = Path::new(filename)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_ascii_lowercase());
+        match extension.as_deref() {
+            Some("hdr") => return self.to_hdr(filename),
+            #[cfg(feature = "exr")]
+            Some("exr") => return self.to_exr(filename),
+            _ => (),
+        }
+        let canvas = self.encode(options.encoding);
+        match (extension.as_deref(), options.sixteen_bit) {
+            (Some("ppm"), false) => canvas.to_ppm_binary(filename),
+            (Some("ppm"), true) => canvas.to_ppm_16bit(filename),
+            #[cfg(feature = "png")]
+            (Some("png"), false) => canvas.to_png(filename),
+            #[cfg(feature = "png")]
+            (Some("png"), true) => canvas.to_png_16bit(filename),
+            #[cfg(feature = "jpeg")]
+            (Some("jpg" | "jpeg"), false) => canvas.to_jpeg(filename, options.quality),
+            #[cfg(feature = "webp")]
+            (Some("webp"), false) => canvas.to_webp(filename, options.quality as f32),
+            (_, true) => Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("no 16 bit support for the image format of {filename}"),
+            )),
+            _ => Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("unsupported image format of {filename}"),
+            )),
+        }
+    }
+
+    // the canvas with every pixel converted from linear values
+    pub fn encode(&self, encoding: ColorEncoding) -> Canvas {
+        Canvas {
+            data: self
+                .data
+                .iter()
+                .map(|&color| encoding.encode_color(color))
+                .collect(),
+            width: self.width,
+            height: self.height,
+        }
+    }
+
+    // the canvas with every pixel converted back to linear values
+    pub fn decode(&self, encoding: ColorEncoding) -> Canvas {
+        Canvas {
+            data: self
+                .data
+                .iter()
+                .map(|&color| encoding.decode_color(color))
+                .collect(),
+            width: self.width,
+            height: self.height,
+        }
+    }
+
+    // 16 bit per channel for the formats that support it, the colors are srgb encoded like with
+    // save
+    pub fn save_16bit(&self, filename: &str) -> std::io::Result<()> {
+        let options = ImageOptions {
+            sixteen_bit: true,
+            ..ImageOptions::default()
+        };
+        self.save_with_options(filename, &options)
+    }
+
+    // 8 bit rgb, a lot smaller than ppm and viewable almost everywhere
+    #[cfg(feature = "png")]
+    pub fn to_png(&self, filename: &str) -> std::io::Result<()> {
+        let file = File::create(filename)?;
+        self.write_png(BufWriter::new(file), png::BitDepth::Eight)
+    }
+
+    #[cfg(feature = "png")]
+    pub fn to_png_16bit(&self, filename: &str) -> std::io::Result<()> {
+        let file = File::create(filename)?;
+        self.write_png(BufWriter::new(file), png::BitDepth::Sixteen)
+    }
+
+    #[cfg(feature = "png")]
+    fn write_png<W: Write>(&self, writer: W, depth: png::BitDepth) -> std::io::Result<()> {
+        let data = match depth {
+            png::BitDepth::Sixteen => self.to_rgb16_bytes(),
+            _ => self.to_rgb8_bytes(),
+        };
+        let size = (self.width, self.height);
+        write_png(writer, size, png::ColorType::Rgb, depth, &data)
+    }
+
+    // lossy with a quality from 1 to 100. The size of jpeg files is limited to 65535 pixels per side
+    #[cfg(feature = "jpeg")]
+    pub fn to_jpeg(&self, filename: &str, quality: u8) -> std::io::Result<()> {
+        let file = File::create(filename)?;
+        self.write_jpeg(BufWriter::new(file), quality)
+    }
+
+    #[cfg(feature = "jpeg")]
+    fn write_jpeg<W: Write>(&self, writer: W, quality: u8) -> std::io::Result<()> {
+        let (Ok(width), Ok(height)) = (u16::try_from(self.width), u16::try_from(self.height))
+        else {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "canvas is too large for a jpeg file",
+            ));
+        };
+        let encoder = jpeg_encoder::Encoder::new(writer, quality.clamp(1, 100));
+        encoder
+            .encode(
+                &self.to_rgb8_bytes(),
+                width,
+                height,
+                jpeg_encoder::ColorType::Rgb,
+            )
+            .map_err(|err| match err {
+                jpeg_encoder::EncodingError::IoError(err) => err,
+                err => Error::new(ErrorKind::InvalidInput, err.to_string()),
+            })
+    }
+
+    // lossy with a quality from 0 to 100, usually smaller than a jpeg of the same quality
+    #[cfg(feature = "webp")]
+    pub fn to_webp(&self, filename: &str, quality: f32) -> std::io::Result<()> {
+        std::fs::write(filename, self.to_webp_bytes(quality)?)
+    }
+
+    #[cfg(feature = "webp")]
+    fn to_webp_bytes(&self, quality: f32) -> std::io::Result<Vec<u8>> {
+        let data = self.to_rgb8_bytes();
+        let encoder = webp::Encoder::from_rgb(&data, self.width as u32, self.height as u32);
+        encoder
+            .encode_simple(false, quality.clamp(0.0, 100.0))
+            .map(|memory| memory.to_vec())
+            .map_err(|err| Error::new(ErrorKind::InvalidInput, format!("{err:?}")))
+    }
+
+    // radiance rgbe with a shared exponent per pixel, keeps values above 1 with about 1% precision
+    pub fn to_hdr(&self, filename: &str) -> std::io::Result<()> {
+        let file = File::create(filename)?;
+        let mut writer = BufWriter::new(file);
+        self.write_hdr(&mut writer)?;
+        writer.flush()
+    }
+
+    fn write_hdr<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        write!(
+            writer,
+            "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n",
+            self.height, self.width
+        )?;
+        for row in self.data.chunks(self.width.max(1)) {
+            let pixels: Vec<[u8; 4]> = row.iter().map(|&color| rgbe(color)).collect();
+            // readers only accept run length encoded lines of this width. Flat lines of it
+            // could be mistaken as encoded ones, so they are encoded but without runs
+            if !(8..=0x7fff).contains(&self.width) {
+                writer.write_all(pixels.as_flattened())?;
+                continue;
+            }
+            writer.write_all(&[2, 2, (self.width >> 8) as u8, self.width as u8])?;
+            for component in 0..4 {
+                let values: Vec<u8> = pixels.iter().map(|pixel| pixel[component]).collect();
+                for chunk in values.chunks(128) {
+                    writer.write_all(&[chunk.len() as u8])?;
+                    writer.write_all(chunk)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // 32 bit float rgb without compression loss
+    #[cfg(feature = "exr")]
+    pub fn to_exr(&self, filename: &str) -> std::io::Result<()> {
+        let file = File::create(filename)?;
+        self.write_exr(BufWriter::new(file))
+    }
+
+    #[cfg(feature = "exr")]
+    fn write_exr<W: Write + std::io::Seek>(&self, writer: W) -> std::io::Result<()> {
+        use exr::prelude::{Image, SpecificChannels, Vec2, WritableImage};
+
+        let channels = SpecificChannels::rgb(|Vec2(x, y)| {
+            let color = self.read_pixel(x, y);
+            (color.r as f32, color.g as f32, color.b as f32)
+        });
+        Image::from_channels((self.width, self.height), channels)
+            .write()
+            .to_buffered(writer)
+            .map_err(|err| match err {
+                exr::error::Error::Io(err) => err,
+                err => Error::new(ErrorKind::InvalidInput, err.to_string()),
+            })
+    }
+
+    // the pixels row by row with 3 bytes per pixel
+    pub(crate) fn to_rgb8_bytes(&self) -> Vec<u8> {
+        self.data.iter().flat_map(|color| color.to_rgb8()).collect()
+    }
+
+    // the pixels row by row with 6 bytes per pixel, the most significant byte of a value first as
+    // ppm and png expect it
+    fn to_rgb16_bytes(&self) -> Vec<u8> {
+        self.data
+            .iter()
+            .flat_map(|color| color.to_rgb16())
+            .flat_map(u16::to_be_bytes)
+            .collect()
+    }
+
+    // P6 with one byte per value, about a quarter of the size of the plain P3 files
+    pub fn to_ppm_binary(&self, filename: &str) -> std::io::Result<()> {
+        let file = File::create(filename)?;
+        let mut writer = BufWriter::new(file);
+        self.write_ppm_binary(&mut writer)?;
+        writer.flush()
+    }
+
+    fn write_ppm_binary<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        write!(writer, "P6\n{} {}\n255\n", self.width, self.height)?;
+        writer.write_all(&self.to_rgb8_bytes())
+    }
+
+    // P6 with a max value of 65535 and two bytes per value
+    pub fn to_ppm_16bit(&self, filename: &str) -> std::io::Result<()> {
+        let file = File::create(filename)?;
+        let mut writer = BufWriter::new(file);
+        self.write_ppm_16bit(&mut writer)?;
+        writer.flush()
+    }
+
+    fn write_ppm_16bit<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        write!(writer, "P6\n{} {}\n65535\n", self.width, self.height)?;
+        writer.write_all(&self.to_rgb16_bytes())
+    }
+
+    // the format is chosen by the extension of the filename, everything apart from ppm needs the
+    // image feature. The colors are linear like the ones of renders, formats with integer values
+    // are taken to be srgb encoded like the images save writes
+    pub fn load(filename: &str) -> std::io::Result<Canvas> {
+        let extension = Path::new(filename)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_ascii_lowercase());
+        match extension.as_deref() {
+            Some("ppm") => Canvas::from_ppm(filename),
+            #[cfg(feature = "image")]
+            _ => Canvas::from_image(filename),
+            #[cfg(not(feature = "image"))]
+            _ => Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("unsupported image format of {filename}"),
+            )),
+        }
+    }
+
+    // png, jpeg, webp, bmp, tga as well as hdr and exr whose values are kept above 1. Integer
+    // values are converted from srgb, the floats of hdr and exr are already linear
+    #[cfg(feature = "image")]
+    pub fn from_image(filename: &str) -> std::io::Result<Canvas> {
+        let image = image::open(filename).map_err(|err| match err {
+            image::ImageError::IoError(err) => err,
+            err => Error::new(ErrorKind::InvalidData, err.to_string()),
+        })?;
+        Ok(Canvas::from_dynamic_image(&image))
+    }
+
+    #[cfg(feature = "image")]
+    fn from_dynamic_image(image: &image::DynamicImage) -> Canvas {
+        let encoding = match image {
+            image::DynamicImage::ImageRgb32F(_) | image::DynamicImage::ImageRgba32F(_) => {
+                ColorEncoding::Linear
+            }
+            _ => ColorEncoding::Srgb,
+        };
+        let image = image.to_rgb32f();
+        let (width, height) = image.dimensions();
+        let mut canvas = Canvas::create_canvas(width as usize, height as usize);
+        for (x, y, pixel) in image.enumerate_pixels() {
+            let [r, g, b] = pixel.0.map(Float::from);
+            let color = encoding.decode_color(Color::new(r, g, b));
+            canvas.write_pixel(x as usize, y as usize, color);
+        }
+        canvas
+    }
+
+    // plain P3 and binary P6 files with any max value up to 65535, converted from srgb
+    pub fn from_ppm(filename: &str) -> std::io::Result<Canvas> {
+        Canvas::from_ppm_reader(File::open(filename)?)
+    }
+
+    pub fn from_ppm_reader<R: Read>(mut reader: R) -> std::io::Result<Canvas> {
+        let mut bytes = vec![];
+        reader.read_to_end(&mut bytes)?;
+        let canvas = Canvas::from_ppm_bytes(&bytes)
+            .map_err(|err| Error::new(ErrorKind::InvalidData, err))?;
+        Ok(canvas.decode(ColorEncoding::Srgb))
+    }
+
+    fn from_ppm_bytes(bytes: &[u8]) -> Result<Canvas, String> {
+        let mut position = 0;
+        let magic = next_ppm_token(bytes, &mut position).ok_or("empty ppm file")?;
+        let binary = match magic {
+            b"P3" => false,
+            b"P6" => true,
+            _ => {
+                return Err(format!(
+                    "unsupported ppm format {}, only P3 and P6 are supported",
+                    String::from_utf8_lossy(magic)
+                ))
+            }
+        };
+        let width = next_ppm_number(bytes, &mut position)?;
+        let height = next_ppm_number(bytes, &mut position)?;
+        let max_value = next_ppm_number(bytes, &mut position)?;
+        if !(1..=65535).contains(&max_value) {
+            return Err(format!("invalid max value {max_value} in ppm file"));
+        }
+
+        // every value takes at least one byte, so a size that does not fit into the file is
+        // rejected before the canvas is allocated
+        let values = width
+            .checked_mul(height)
+            .and_then(|pixels| pixels.checked_mul(3))
+            .filter(|&values| values <= bytes.len() - position)
+            .ok_or_else(|| format!("ppm size {width}x{height} is larger than the file"))?;
+        let mut next_value: Box<dyn FnMut() -> Result<usize, String>> = if binary {
+            // a single whitespace separates the header from the raster, values above 255 take
+            // two bytes with the most significant one first
+            let raster = bytes.get(position + 1..).unwrap_or_default();
+            let bytes_per_value = if max_value > 255 { 2 } else { 1 };
+            if raster.len() < values * bytes_per_value {
+                return Err("unexpected end of ppm file".to_owned());
+            }
+            let mut raster = raster.chunks(bytes_per_value);
+            Box::new(move || {
+                let value = raster.next().unwrap_or_default();
+                Ok(value
+                    .iter()
+                    .fold(0, |acc, &byte| (acc << 8) | byte as usize))
+            })
+        } else {
+            Box::new(move || next_ppm_number(bytes, &mut position))
+        };
+        let mut canvas = Canvas::create_canvas(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                let mut channel = || -> Result<Float, String> {
+                    let value = next_value()?;
+                    if value > max_value {
+                        return Err(format!("value {value} exceeds max value {max_value}"));
+                    }
+                    Ok(value as Float / max_value as Float)
+                };
+                let color = Color::new(channel()?, channel()?, channel()?);
+                canvas.write_pixel(x, y, color);
+            }
+        }
+        Ok(canvas)
+    }
+
     fn to_ppm_str(&self) -> Result<String, std::fmt::Error> {
         use std::fmt::Write;
 
@@ -58,12 +545,7 @@ impl Canvas {
         // thus max 16*4 + 1*3 = 67 (spaces after numbers) aka 17 numbers possible per line
         let mut chars_written = 0;
         for (idx, color) in self.data.iter().enumerate() {
-            let r: u8 = (color.r * 255.0).round().clamp(0.0, 255.0) as u8;
-            let g: u8 = (color.g * 255.0).round().clamp(0.0, 255.0) as u8;
-            let b: u8 = (color.b * 255.0).round().clamp(0.0, 255.0) as u8;
-
-            let color_values = [r, g, b];
-            for value in color_values {
+            for value in color.to_rgb8() {
                 let mut chars_to_be_added = match value {
                     0..=9 => 1,
                     10..=99 => 2,
@@ -91,6 +573,76 @@ impl Canvas {
     }
 }
 
+// data holds the rows of the image with the channels of color_type in the given depth
+#[cfg(feature = "png")]
+pub(crate) fn write_png<W: Write>(
+    writer: W,
+    (width, height): (usize, usize),
+    color_type: png::ColorType,
+    depth: png::BitDepth,
+    data: &[u8],
+) -> std::io::Result<()> {
+    let to_io_error = |err: png::EncodingError| match err {
+        png::EncodingError::IoError(err) => err,
+        err => Error::new(ErrorKind::InvalidInput, err.to_string()),
+    };
+    let mut encoder = png::Encoder::new(writer, width as u32, height as u32);
+    encoder.set_color(color_type);
+    encoder.set_depth(depth);
+    let mut writer = encoder.write_header().map_err(to_io_error)?;
+    writer.write_image_data(data).map_err(to_io_error)?;
+    writer.finish().map_err(to_io_error)
+}
+
+// whitespace separated, comments reach from # to the end of the line
+fn next_ppm_token<'a>(bytes: &'a [u8], position: &mut usize) -> Option<&'a [u8]> {
+    loop {
+        match bytes.get(*position)? {
+            byte if byte.is_ascii_whitespace() => *position += 1,
+            b'#' => {
+                while bytes.get(*position).is_some_and(|&byte| byte != b'\n') {
+                    *position += 1;
+                }
+            }
+            _ => break,
+        }
+    }
+    let start = *position;
+    while bytes
+        .get(*position)
+        .is_some_and(|byte| !byte.is_ascii_whitespace() && *byte != b'#')
+    {
+        *position += 1;
+    }
+    Some(&bytes[start..*position])
+}
+
+fn next_ppm_number(bytes: &[u8], position: &mut usize) -> Result<usize, String> {
+    let token = next_ppm_token(bytes, position).ok_or("unexpected end of ppm file")?;
+    let token = String::from_utf8_lossy(token);
+    token
+        .parse::<usize>()
+        .map_err(|_| format!("invalid number {token} in ppm file"))
+}
+
+// mantissas of the color scaled to the largest component and their shared exponent
+fn rgbe(color: Color) -> [u8; 4] {
+    let [r, g, b] = [color.r, color.g, color.b].map(|value| value.max(0.0));
+    let max = r.max(g).max(b);
+    if max.is_nan() || max < 1e-32 {
+        return [0, 0, 0, 0];
+    }
+    let exponent = max.log2().floor() as i32 + 1;
+    let scale = 256.0 / Float::powi(2.0, exponent);
+    let mantissa = |value: Float| (value * scale).min(255.0) as u8;
+    [
+        mantissa(r),
+        mantissa(g),
+        mantissa(b),
+        (exponent + 128).clamp(0, 255) as u8,
+    ]
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -101,6 +653,49 @@ mod tests {
         assert_eq!(canvas.read_pixel(19, 7), Color::new(0.0, 0.0, 0.0))
     }
 
+    #[test]
+    fn vec2d_of_other_values() {
+        let mut alpha = Vec2D::new(3, 2, 1.0);
+        alpha.write_pixel(2, 1, 0.25);
+        assert_eq!(alpha.read_pixel(2, 1), 0.25);
+        assert_eq!(alpha.read_pixel(0, 1), 1.0);
+        assert_eq!((alpha.width(), alpha.height()), (3, 2));
+    }
+
+    #[test]
+    fn cropping_and_pasting() {
+        let mut values = Vec2D::new(4, 3, 0);
+        for y in 0..3 {
+            for x in 0..4 {
+                values.write_pixel(x, y, 10 * y + x);
+            }
+        }
+        let cropped = values.crop(Region::new(1, 1, 2, 5));
+        assert_eq!((cropped.width(), cropped.height()), (2, 2));
+        assert_eq!(
+            cropped.iter().copied().collect::<Vec<_>>(),
+            [11, 12, 21, 22]
+        );
+        let mut target = Vec2D::new(3, 3, 0);
+        target.paste(2, 1, &cropped);
+        assert_eq!(
+            target.iter().copied().collect::<Vec<_>>(),
+            [0, 0, 0, 0, 0, 11, 0, 0, 21]
+        );
+    }
+
+    #[test]
+    fn parsing_regions() {
+        assert_eq!("4, 2,10,20".parse(), Ok(Region::new(4, 2, 10, 20)));
+        assert!("4,2,10".parse::<Region>().is_err());
+        assert!("4,2,-1,3".parse::<Region>().is_err());
+        assert_eq!(
+            Region::new(8, 2, 10, 10).clamp_to(10, 5),
+            Region::new(8, 2, 2, 3)
+        );
+        assert_eq!(Region::new(12, 0, 3, 3).clamp_to(10, 5).width, 0);
+    }
+
     #[test]
     fn setting_pixel() {
         let mut canvas = Canvas::create_canvas(20, 8);
@@ -175,4 +770,310 @@ mod tests {
         let str = canvas.to_ppm_str().unwrap();
         assert_eq!(str.chars().nth_back(0), Some('\n'))
     }
+
+    #[test]
+    fn binary_ppm_format() {
+        let mut canvas = Canvas::create_canvas(2, 2);
+        canvas.write_pixel(1, 0, Color::new(1.5, 0.5, 0.0));
+        canvas.write_pixel(0, 1, Color::new(0.0, 0.0, 1.0));
+        let mut bytes = vec![];
+        canvas.write_ppm_binary(&mut bytes).unwrap();
+        let mut expected = b"P6\n2 2\n255\n".to_vec();
+        expected.extend([0, 0, 0, 255, 128, 0, 0, 0, 255, 0, 0, 0]);
+        assert_eq!(bytes, expected);
+    }
+
+    #[test]
+    fn reading_file_with_wrong_magic_number() {
+        let ppm_str = "P32\n1 1\n255\n0 0 0\n";
+        assert!(Canvas::from_ppm_bytes(ppm_str.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn reading_ppm_returns_canvas_of_right_size() {
+        let ppm_str = "P3\n10 2\n255\n".to_owned() + &"0 0 0\n".repeat(20);
+        let canvas = Canvas::from_ppm_bytes(ppm_str.as_bytes()).unwrap();
+        assert_eq!(canvas.width(), 10);
+        assert_eq!(canvas.height(), 2);
+    }
+
+    #[test]
+    fn reading_pixel_data_from_ppm() {
+        let ppm_str = "P3\n\
+        4 3\n\
+        255\n\
+        255 127 0  0 127 255  127 255 0  255 255 255\n\
+        0 0 0  255 0 0  0 255 0  0 0 255\n\
+        255 255 0  0 255 255  255 0 255  127 127 127\n";
+        let canvas = Canvas::from_ppm_bytes(ppm_str.as_bytes()).unwrap();
+        assert_eq!(canvas.read_pixel(0, 0), Color::new(1.0, 127.0 / 255.0, 0.0));
+        assert_eq!(canvas.read_pixel(3, 0), Color::new(1.0, 1.0, 1.0));
+        assert_eq!(canvas.read_pixel(1, 1), Color::new(1.0, 0.0, 0.0));
+        assert_eq!(canvas.read_pixel(2, 2), Color::new(1.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn ppm_roundtrip() {
+        let mut canvas = Canvas::create_canvas(3, 2);
+        canvas.write_pixel(1, 0, Color::new(1.0, 0.0, 0.0));
+        canvas.write_pixel(2, 1, Color::new(0.0, 0.0, 1.0));
+        let read = Canvas::from_ppm_bytes(canvas.to_ppm_str().unwrap().as_bytes()).unwrap();
+        assert_eq!(read.read_pixel(1, 0), Color::new(1.0, 0.0, 0.0));
+        assert_eq!(read.read_pixel(2, 1), Color::new(0.0, 0.0, 1.0));
+        assert_eq!(read.read_pixel(0, 0), Color::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn saving_with_unknown_extension() {
+        let canvas = Canvas::create_canvas(1, 1);
+        let err = canvas.save("image.xyz").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    }
+
+    #[cfg(feature = "png")]
+    #[test]
+    fn png_roundtrip() {
+        let mut canvas = Canvas::create_canvas(3, 2);
+        canvas.write_pixel(1, 0, Color::new(1.0, 0.5, 0.0));
+        canvas.write_pixel(2, 1, Color::new(0.0, 0.0, 2.0));
+        let mut bytes = vec![];
+        canvas.write_png(&mut bytes, png::BitDepth::Eight).unwrap();
+        let decoder = png::Decoder::new(std::io::Cursor::new(bytes));
+        let mut reader = decoder.read_info().unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size().unwrap()];
+        let info = reader.next_frame(&mut pixels).unwrap();
+        assert_eq!((info.width, info.height), (3, 2));
+        assert_eq!(info.color_type, png::ColorType::Rgb);
+        assert_eq!(&pixels[3..6], &[255, 128, 0]);
+        assert_eq!(&pixels[15..18], &[0, 0, 255]);
+        assert_eq!(&pixels[0..3], &[0, 0, 0]);
+    }
+
+    #[cfg(feature = "jpeg")]
+    #[test]
+    fn jpeg_quality_changes_size() {
+        let mut canvas = Canvas::create_canvas(16, 16);
+        for y in 0..16 {
+            for x in 0..16 {
+                canvas.write_pixel(
+                    x,
+                    y,
+                    Color::new(x as Float / 15.0, (x * y % 7) as Float / 6.0, 0.3),
+                );
+            }
+        }
+        let mut low = vec![];
+        canvas.write_jpeg(&mut low, 10).unwrap();
+        let mut high = vec![];
+        canvas.write_jpeg(&mut high, 100).unwrap();
+        // start of image marker
+        assert_eq!(&high[0..2], &[0xff, 0xd8]);
+        assert!(low.len() < high.len());
+    }
+
+    #[cfg(feature = "webp")]
+    #[test]
+    fn webp_roundtrip() {
+        let mut canvas = Canvas::create_canvas(4, 2);
+        canvas.write_pixel(1, 0, Color::new(1.0, 1.0, 1.0));
+        let bytes = canvas.to_webp_bytes(80.0).unwrap();
+        assert_eq!(&bytes[0..4], b"RIFF");
+        let image = webp::Decoder::new(&bytes).decode().unwrap();
+        assert_eq!((image.width(), image.height()), (4, 2));
+    }
+
+    #[test]
+    fn rgbe_encoding() {
+        assert_eq!(rgbe(Color::new(1.0, 0.5, 0.25)), [128, 64, 32, 129]);
+        assert_eq!(rgbe(Color::new(0.0, 6.0, -1.0)), [0, 192, 0, 131]);
+        assert_eq!(rgbe(Color::new(0.0, 0.0, 0.0)), [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn hdr_lines_are_run_length_encoded() {
+        let mut canvas = Canvas::create_canvas(10, 2);
+        canvas.write_pixel(0, 1, Color::new(1.0, 0.5, 0.25));
+        let mut bytes = vec![];
+        canvas.write_hdr(&mut bytes).unwrap();
+        let header = "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 2 +X 10\n";
+        assert!(bytes.starts_with(header.as_bytes()));
+        // per line a marker and 4 components with a length byte and the 10 values each
+        let line = 4 + 4 * 11;
+        assert_eq!(bytes.len(), header.len() + 2 * line);
+        let second = &bytes[header.len() + line..];
+        assert_eq!(&second[0..4], &[2, 2, 0, 10]);
+        assert_eq!(second[4..6], [10, 128]);
+        assert_eq!(second[4 + 3 * 11 + 1], 129);
+    }
+
+    #[cfg(feature = "exr")]
+    #[test]
+    fn exr_keeps_values_above_one() {
+        use exr::prelude::*;
+
+        let mut canvas = Canvas::create_canvas(3, 2);
+        canvas.write_pixel(2, 1, Color::new(4.5, 0.25, 100.0));
+        let mut bytes = std::io::Cursor::new(vec![]);
+        canvas.write_exr(&mut bytes).unwrap();
+        bytes.set_position(0);
+        let image = read()
+            .no_deep_data()
+            .largest_resolution_level()
+            .rgba_channels(
+                |resolution, _| vec![(0.0, 0.0, 0.0); resolution.area()],
+                |pixels: &mut Vec<(f32, f32, f32)>,
+                 Vec2(x, y),
+                 (r, g, b, _): (f32, f32, f32, f32)| {
+                    pixels[y * 3 + x] = (r, g, b)
+                },
+            )
+            .first_valid_layer()
+            .all_attributes()
+            .from_buffered(bytes)
+            .unwrap();
+        assert_eq!(image.layer_data.channel_data.pixels[5], (4.5, 0.25, 100.0));
+    }
+
+    #[test]
+    fn reading_ppm_with_comments_and_other_max_value() {
+        let ppm_str = "P3 # plain\n# size\n2 1\n15\n15 0 5 # first\n0\n15\n0\n";
+        let canvas = Canvas::from_ppm_bytes(ppm_str.as_bytes()).unwrap();
+        assert_eq!(canvas.read_pixel(0, 0), Color::new(1.0, 0.0, 1.0 / 3.0));
+        assert_eq!(canvas.read_pixel(1, 0), Color::new(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn reading_ppm_with_too_large_value() {
+        let ppm_str = "P3\n1 1\n10\n0 11 0\n";
+        assert!(Canvas::from_ppm_bytes(ppm_str.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn reading_ppm_larger_than_its_file() {
+        let huge = format!("P6\n{} 2\n255\n\0\0\0", usize::MAX / 2);
+        assert!(Canvas::from_ppm_bytes(huge.as_bytes()).is_err());
+        let ppm_str = "P3\n100000 100000\n255\n0 0 0\n";
+        assert!(Canvas::from_ppm_bytes(ppm_str.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn binary_ppm_roundtrip() {
+        let mut canvas = Canvas::create_canvas(3, 2);
+        canvas.write_pixel(1, 0, Color::new(1.0, 0.0, 0.0));
+        // whitespace values must not be taken as separators
+        canvas.write_pixel(2, 1, Color::new(10.0 / 255.0, 32.0 / 255.0, 1.0));
+        let mut bytes = vec![];
+        canvas.write_ppm_binary(&mut bytes).unwrap();
+        let read = Canvas::from_ppm_bytes(&bytes).unwrap();
+        assert_eq!(read.read_pixel(1, 0), Color::new(1.0, 0.0, 0.0));
+        assert_eq!(
+            read.read_pixel(2, 1),
+            Color::new(10.0 / 255.0, 32.0 / 255.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn loaded_ppm_files_are_linear() {
+        let read = Canvas::from_ppm_reader(b"P3\n1 1\n255\n128 255 0\n".as_slice()).unwrap();
+        assert_eq!(read.read_pixel(0, 0), Color::new(0.21586, 1.0, 0.0));
+    }
+
+    #[test]
+    fn reading_binary_ppm_with_two_bytes_per_value() {
+        let mut bytes = b"P6\n1 1\n# deep\n65535\n".to_vec();
+        bytes.extend([0xff, 0xff, 0x80, 0x00, 0x00, 0x01]);
+        let canvas = Canvas::from_ppm_bytes(&bytes).unwrap();
+        assert_eq!(
+            canvas.read_pixel(0, 0),
+            Color::new(1.0, 32768.0 / 65535.0, 1.0 / 65535.0)
+        );
+        assert!(Canvas::from_ppm_bytes(&bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[cfg(not(feature = "image"))]
+    #[test]
+    fn loading_other_formats_needs_image_feature() {
+        let err = Canvas::load("texture.png").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn canvas_from_image() {
+        let mut image = image::RgbImage::new(2, 3);
+        image.put_pixel(1, 2, image::Rgb([255, 128, 0]));
+        let canvas = Canvas::from_dynamic_image(&image.into());
+        assert_eq!((canvas.width(), canvas.height()), (2, 3));
+        // 8 bit images are srgb
+        assert_eq!(canvas.read_pixel(1, 2), Color::new(1.0, 0.21586, 0.0));
+        assert_eq!(canvas.read_pixel(0, 0), Color::new(0.0, 0.0, 0.0));
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn canvas_from_hdr_image_keeps_values_above_one() {
+        let mut image = image::Rgb32FImage::new(1, 1);
+        image.put_pixel(0, 0, image::Rgb([4.0, 0.5, 0.0]));
+        let canvas = Canvas::from_dynamic_image(&image.into());
+        assert_eq!(canvas.read_pixel(0, 0), Color::new(4.0, 0.5, 0.0));
+    }
+
+    #[test]
+    fn ppm_16bit_roundtrip() {
+        let mut canvas = Canvas::create_canvas(2, 1);
+        canvas.write_pixel(1, 0, Color::new(0.5, 1.0, 0.001));
+        let mut bytes = vec![];
+        canvas.write_ppm_16bit(&mut bytes).unwrap();
+        assert!(bytes.starts_with(b"P6\n2 1\n65535\n"));
+        assert_eq!(
+            &bytes[bytes.len() - 6..],
+            &[0x80, 0x00, 0xff, 0xff, 0x00, 0x42]
+        );
+        let read = Canvas::from_ppm_bytes(&bytes).unwrap();
+        // the 8 bit value would be 0
+        assert!((read.read_pixel(1, 0).b - 0.001).abs() < 1e-5);
+    }
+
+    #[cfg(feature = "png")]
+    #[test]
+    fn png_16bit() {
+        let mut canvas = Canvas::create_canvas(1, 1);
+        canvas.write_pixel(0, 0, Color::new(0.5, 1.0, 0.001));
+        let mut bytes = vec![];
+        canvas
+            .write_png(&mut bytes, png::BitDepth::Sixteen)
+            .unwrap();
+        let decoder = png::Decoder::new(std::io::Cursor::new(bytes));
+        let mut reader = decoder.read_info().unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size().unwrap()];
+        let info = reader.next_frame(&mut pixels).unwrap();
+        assert_eq!(info.bit_depth, png::BitDepth::Sixteen);
+        assert_eq!(pixels, [0x80, 0x00, 0xff, 0xff, 0x00, 0x42]);
+    }
+
+    #[test]
+    fn saving_16bit_with_unsupported_extension() {
+        let canvas = Canvas::create_canvas(1, 1);
+        let options = ImageOptions {
+            sixteen_bit: true,
+            ..ImageOptions::default()
+        };
+        let err = canvas.save_with_options("image.jpg", &options).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        let err = canvas.save_16bit("image.jpg").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn encoding_the_canvas() {
+        let mut canvas = Canvas::create_canvas(2, 1);
+        canvas.write_pixel(1, 0, Color::new(0.25, 1.0, 4.0));
+        let encoded = canvas.encode(ColorEncoding::Gamma(2.0));
+        assert_eq!(encoded.read_pixel(1, 0), Color::new(0.5, 1.0, 2.0));
+        assert_eq!(encoded.read_pixel(0, 0), Color::new(0.0, 0.0, 0.0));
+        assert_eq!(
+            canvas.encode(ColorEncoding::Linear).read_pixel(1, 0),
+            canvas.read_pixel(1, 0)
+        );
+    }
 }
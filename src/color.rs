@@ -1,15 +1,17 @@
-use crate::math::{float_eq, FLOAT_EQ_EPS};
+use crate::math::{default_tolerance, ApproxEq, Float, Tolerance};
 use std::ops::{Add, Div, Mul, Neg, Sub};
+use std::str::FromStr;
 
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Color {
-    pub r: f64,
-    pub g: f64,
-    pub b: f64,
+    pub r: Float,
+    pub g: Float,
+    pub b: Float,
 }
 
 impl Color {
-    pub fn new(r: f64, g: f64, b: f64) -> Color {
+    pub fn new(r: Float, g: Float, b: Float) -> Color {
         Color { r, g, b }
     }
 
@@ -20,14 +22,93 @@ impl Color {
             b: self.b * rhs.b,
         }
     }
+
+    // 8 bit per channel, values outside of [0, 1] are clamped
+    pub fn to_rgb8(self) -> [u8; 3] {
+        let to_byte = |value: Float| (value * 255.0).round().clamp(0.0, 255.0) as u8;
+        [to_byte(self.r), to_byte(self.g), to_byte(self.b)]
+    }
+
+    // 16 bit per channel, clamped like to_rgb8
+    pub fn to_rgb16(self) -> [u16; 3] {
+        let to_word = |value: Float| (value * 65535.0).round().clamp(0.0, 65535.0) as u16;
+        [to_word(self.r), to_word(self.g), to_word(self.b)]
+    }
+}
+
+// transfer function from the linear values the renderer computes to the values stored in an image.
+// Most viewers expect srgb, linear values look too dark in them
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColorEncoding {
+    Linear,
+    Srgb,
+    Gamma(Float),
+}
+
+impl ColorEncoding {
+    pub fn encode(self, value: Float) -> Float {
+        match self {
+            ColorEncoding::Linear => value,
+            ColorEncoding::Srgb if value <= 0.0031308 => value * 12.92,
+            ColorEncoding::Srgb => 1.055 * value.powf(1.0 / 2.4) - 0.055,
+            ColorEncoding::Gamma(gamma) => value.max(0.0).powf(1.0 / gamma),
+        }
+    }
+
+    pub fn encode_color(self, color: Color) -> Color {
+        Color::new(
+            self.encode(color.r),
+            self.encode(color.g),
+            self.encode(color.b),
+        )
+    }
+
+    // the inverse of encode, from stored values back to linear ones
+    pub fn decode(self, value: Float) -> Float {
+        match self {
+            ColorEncoding::Linear => value,
+            ColorEncoding::Srgb if value <= 0.04045 => value / 12.92,
+            ColorEncoding::Srgb => ((value + 0.055) / 1.055).powf(2.4),
+            ColorEncoding::Gamma(gamma) => value.max(0.0).powf(gamma),
+        }
+    }
+
+    pub fn decode_color(self, color: Color) -> Color {
+        Color::new(
+            self.decode(color.r),
+            self.decode(color.g),
+            self.decode(color.b),
+        )
+    }
+}
+
+// linear, srgb or a gamma value like 2.2
+impl FromStr for ColorEncoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "linear" => Ok(ColorEncoding::Linear),
+            "srgb" => Ok(ColorEncoding::Srgb),
+            _ => match s.parse::<Float>() {
+                Ok(gamma) if gamma > 0.0 => Ok(ColorEncoding::Gamma(gamma)),
+                _ => Err(format!(
+                    "invalid color encoding {s}, expected linear, srgb or a gamma value"
+                )),
+            },
+        }
+    }
+}
+
+impl ApproxEq for Color {
+    fn approx_eq(&self, other: &Self, tolerance: Tolerance) -> bool {
+        [self.r, self.g, self.b].approx_eq(&[other.r, other.g, other.b], tolerance)
+    }
 }
 
 impl PartialEq for Color {
     fn eq(&self, other: &Self) -> bool {
-        let eps = FLOAT_EQ_EPS;
-        float_eq(self.r, other.r, eps)
-            && float_eq(self.g, other.g, eps)
-            && float_eq(self.b, other.b, eps)
+        self.approx_eq(other, default_tolerance())
     }
 }
 
@@ -66,10 +147,10 @@ impl Neg for Color {
     }
 }
 
-impl Mul<f64> for Color {
+impl Mul<Float> for Color {
     type Output = Self;
 
-    fn mul(self, rhs: f64) -> Self::Output {
+    fn mul(self, rhs: Float) -> Self::Output {
         Self {
             r: self.r * rhs,
             g: self.g * rhs,
@@ -78,10 +159,10 @@ impl Mul<f64> for Color {
     }
 }
 
-impl Div<f64> for Color {
+impl Div<Float> for Color {
     type Output = Self;
 
-    fn div(self, rhs: f64) -> Self::Output {
+    fn div(self, rhs: Float) -> Self::Output {
         Self {
             r: self.r / rhs,
             g: self.g / rhs,
@@ -93,6 +174,7 @@ impl Div<f64> for Color {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::math::{float_eq, FLOAT_EQ_EPS};
 
     #[test]
     fn color_init() {
@@ -131,4 +213,62 @@ mod tests {
         let col2 = Color::new(0.9, 1.0, 0.1);
         assert_eq!(col.mul_color(col2), Color::new(0.9, 0.2, 0.04))
     }
+
+    #[test]
+    fn converting_to_bytes_clamps() {
+        let color = Color::new(-0.5, 0.5, 1.5);
+        assert_eq!(color.to_rgb8(), [0, 128, 255]);
+    }
+
+    #[test]
+    fn converting_to_words_clamps() {
+        let color = Color::new(-0.5, 0.5, 1.5);
+        assert_eq!(color.to_rgb16(), [0, 32768, 65535]);
+    }
+
+    #[test]
+    fn srgb_encoding() {
+        let encoding = ColorEncoding::Srgb;
+        assert_eq!(encoding.encode(0.0), 0.0);
+        assert!(float_eq(encoding.encode(1.0), 1.0, FLOAT_EQ_EPS));
+        assert!(float_eq(encoding.encode(0.002), 0.02584, FLOAT_EQ_EPS));
+        assert_eq!(
+            encoding.encode_color(Color::new(0.5, 0.2, 1.0)).to_rgb8(),
+            [188, 124, 255]
+        );
+    }
+
+    #[test]
+    fn gamma_encoding() {
+        let encoding = ColorEncoding::Gamma(2.0);
+        assert!(float_eq(encoding.encode(0.25), 0.5, FLOAT_EQ_EPS));
+        assert_eq!(encoding.encode(-1.0), 0.0);
+        assert!(float_eq(encoding.decode(0.5), 0.25, FLOAT_EQ_EPS));
+    }
+
+    #[test]
+    fn decoding_reverses_encoding() {
+        for value in [0.0, 0.002, 0.2, 0.5, 1.0] {
+            let encoding = ColorEncoding::Srgb;
+            assert!(float_eq(
+                encoding.decode(encoding.encode(value)),
+                value,
+                FLOAT_EQ_EPS
+            ));
+        }
+        assert!(float_eq(
+            ColorEncoding::Srgb.decode(128.0 / 255.0),
+            0.21586,
+            FLOAT_EQ_EPS
+        ));
+    }
+
+    #[test]
+    fn parsing_color_encodings() {
+        assert_eq!("srgb".parse(), Ok(ColorEncoding::Srgb));
+        assert_eq!("linear".parse(), Ok(ColorEncoding::Linear));
+        assert_eq!("2.2".parse(), Ok(ColorEncoding::Gamma(2.2)));
+        assert!("-1".parse::<ColorEncoding>().is_err());
+        assert!("rgb".parse::<ColorEncoding>().is_err());
+    }
 }
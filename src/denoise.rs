@@ -0,0 +1,190 @@
+use crate::math::{Float, Tuple4D};
+use crate::{Canvas, Color, Vec2D};
+
+// weights of the 5 taps of the b3 spline, the filter kernel of every iteration
+const KERNEL: [Float; 5] = [1.0 / 16.0, 1.0 / 4.0, 3.0 / 8.0, 1.0 / 4.0, 1.0 / 16.0];
+
+// edge avoiding a-trous wavelet filter. Every iteration blurs with a 5x5 kernel whose taps are
+// twice as far apart as in the previous one, so a few iterations cover a large area. Neighbors
+// count less the more their color, normal and albedo differ, which keeps the edges of objects and
+// textures sharp. The sigmas are the differences at which the weight has dropped to 1/e
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Denoiser {
+    pub iterations: usize,
+    // halved in every iteration, as the noise gets weaker
+    pub color_sigma: Float,
+    pub normal_sigma: Float,
+    pub albedo_sigma: Float,
+}
+
+impl Default for Denoiser {
+    fn default() -> Self {
+        Denoiser {
+            iterations: 5,
+            color_sigma: 0.6,
+            normal_sigma: 0.3,
+            albedo_sigma: 0.1,
+        }
+    }
+}
+
+impl Denoiser {
+    // normals and albedo are the ones Camera::render_normals and render_albedo return for the
+    // image, they have to be of the same size as it
+    pub fn denoise(&self, image: &Canvas, normals: &Vec2D<Tuple4D>, albedo: &Canvas) -> Canvas {
+        let (width, height) = (image.width(), image.height());
+        assert!(
+            (normals.width(), normals.height()) == (width, height)
+                && (albedo.width(), albedo.height()) == (width, height),
+            "normals and albedo have to be of the size of the image"
+        );
+        let mut current = image.clone();
+        for iteration in 0..self.iterations {
+            let step: isize = 1 << iteration;
+            // the taps of the later iterations are all outside of the image, they would leave it as
+            // it is. Stopping here also keeps the shift from overflowing
+            if step >= width.max(height) as isize {
+                break;
+            }
+            let color_sigma = self.color_sigma / (1 << iteration) as Float;
+            let mut next = Canvas::create_canvas(width, height);
+            for y in 0..height {
+                for x in 0..width {
+                    let color = current.read_pixel(x, y);
+                    let normal = normals.read_pixel(x, y);
+                    let surface = albedo.read_pixel(x, y);
+                    let mut sum = Color::new(0.0, 0.0, 0.0);
+                    let mut weights = 0.0;
+                    for (dy, kernel_y) in KERNEL.iter().enumerate() {
+                        for (dx, kernel_x) in KERNEL.iter().enumerate() {
+                            let qx = x as isize + (dx as isize - 2) * step;
+                            let qy = y as isize + (dy as isize - 2) * step;
+                            if qx < 0 || qy < 0 || qx >= width as isize || qy >= height as isize {
+                                continue;
+                            }
+                            let (qx, qy) = (qx as usize, qy as usize);
+                            let neighbor = current.read_pixel(qx, qy);
+                            let normal_distance = (normals.read_pixel(qx, qy) - normal).magnitude();
+                            let weight = kernel_x
+                                * kernel_y
+                                * edge_weight(color_distance(color, neighbor), color_sigma)
+                                * edge_weight(normal_distance, self.normal_sigma)
+                                * edge_weight(
+                                    color_distance(surface, albedo.read_pixel(qx, qy)),
+                                    self.albedo_sigma,
+                                );
+                            sum = sum + neighbor * weight;
+                            weights += weight;
+                        }
+                    }
+                    // the pixel itself always has a weight above 0
+                    next.write_pixel(x, y, sum / weights);
+                }
+            }
+            current = next;
+        }
+        current
+    }
+}
+
+fn color_distance(a: Color, b: Color) -> Float {
+    let difference = a - b;
+    (difference.r.powi(2) + difference.g.powi(2) + difference.b.powi(2)).sqrt()
+}
+
+fn edge_weight(distance: Float, sigma: Float) -> Float {
+    if sigma <= 0.0 {
+        return if distance == 0.0 { 1.0 } else { 0.0 };
+    }
+    (-(distance * distance) / (sigma * sigma)).exp()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn noisy(width: usize, height: usize, color: impl Fn(usize, usize) -> Color) -> Canvas {
+        let mut canvas = Canvas::create_canvas(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                // deterministic noise without bias
+                let noise = if (x * 7 + y * 13) % 3 == 0 {
+                    0.1
+                } else {
+                    -0.05
+                };
+                canvas.write_pixel(x, y, color(x, y) + Color::new(noise, noise, noise));
+            }
+        }
+        canvas
+    }
+
+    fn variance(canvas: &Canvas, xs: std::ops::Range<usize>) -> Float {
+        let values: Vec<Float> = (0..canvas.height())
+            .flat_map(|y| xs.clone().map(move |x| (x, y)))
+            .map(|(x, y)| canvas.read_pixel(x, y).r)
+            .collect();
+        let mean = values.iter().sum::<Float>() / values.len() as Float;
+        values
+            .iter()
+            .map(|value| (value - mean).powi(2))
+            .sum::<Float>()
+            / values.len() as Float
+    }
+
+    #[test]
+    fn noise_on_flat_surface_is_reduced() {
+        let image = noisy(16, 16, |_, _| Color::new(0.5, 0.5, 0.5));
+        let normals = Vec2D::new(16, 16, Tuple4D::new_vector(0.0, 0.0, -1.0));
+        let albedo = Canvas::new(16, 16, Color::new(0.8, 0.8, 0.8));
+        let denoised = Denoiser::default().denoise(&image, &normals, &albedo);
+        assert!(variance(&denoised, 0..16) < variance(&image, 0..16) / 10.0);
+    }
+
+    #[test]
+    fn edges_between_surfaces_are_kept() {
+        let dark = Color::new(0.1, 0.1, 0.1);
+        let bright = Color::new(0.9, 0.9, 0.9);
+        let image = noisy(16, 8, |x, _| if x < 8 { dark } else { bright });
+        let mut normals = Vec2D::new(16, 8, Tuple4D::new_vector(0.0, 0.0, -1.0));
+        for y in 0..8 {
+            for x in 8..16 {
+                normals.write_pixel(x, y, Tuple4D::new_vector(1.0, 0.0, 0.0));
+            }
+        }
+        let albedo = Canvas::new(16, 8, Color::new(0.8, 0.8, 0.8));
+        let denoised = Denoiser::default().denoise(&image, &normals, &albedo);
+        assert!((denoised.read_pixel(7, 4).r - 0.1).abs() < 0.06);
+        assert!((denoised.read_pixel(8, 4).r - 0.9).abs() < 0.06);
+    }
+
+    #[test]
+    fn no_iterations_keep_the_image() {
+        let image = noisy(4, 4, |_, _| Color::new(0.5, 0.5, 0.5));
+        let normals = Vec2D::new(4, 4, Tuple4D::new_vector(0.0, 0.0, -1.0));
+        let albedo = Canvas::create_canvas(4, 4);
+        let denoiser = Denoiser {
+            iterations: 0,
+            ..Denoiser::default()
+        };
+        let denoised = denoiser.denoise(&image, &normals, &albedo);
+        assert_eq!(denoised.read_pixel(1, 2), image.read_pixel(1, 2));
+    }
+
+    #[test]
+    fn iterations_beyond_the_image_size_change_nothing() {
+        let image = noisy(6, 5, |_, _| Color::new(0.5, 0.5, 0.5));
+        let normals = Vec2D::new(6, 5, Tuple4D::new_vector(0.0, 0.0, -1.0));
+        let albedo = Canvas::new(6, 5, Color::new(0.8, 0.8, 0.8));
+        let denoise = |iterations| {
+            let denoiser = Denoiser {
+                iterations,
+                ..Denoiser::default()
+            };
+            denoiser.denoise(&image, &normals, &albedo)
+        };
+        let fitting = denoise(3);
+        let many = denoise(100);
+        assert!(fitting.iter().eq(many.iter()));
+    }
+}
@@ -0,0 +1,61 @@
+use std::fmt;
+
+use crate::math::MathError;
+
+// error of the whole crate, for callers that want to pass failures of the math types and of
+// reading files on with a single type and ?
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    Math(MathError),
+    Io(std::io::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Math(err) => err.fmt(f),
+            Error::Io(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Math(err) => Some(err),
+            Error::Io(err) => Some(err),
+        }
+    }
+}
+
+impl From<MathError> for Error {
+    fn from(err: MathError) -> Self {
+        Error::Math(err)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::Tuple4D;
+
+    #[test]
+    fn math_and_io_errors_convert_into_the_crate_error() {
+        let normalized = || -> Result<Tuple4D, Error> {
+            Ok(Tuple4D::new_vector(0.0, 0.0, 0.0).try_normalize()?)
+        };
+        let err = normalized().unwrap_err();
+        assert!(matches!(err, Error::Math(MathError::ZeroLength)));
+        assert_eq!(err.to_string(), MathError::ZeroLength.to_string());
+        let err = Error::from(std::io::Error::other("disk full"));
+        assert_eq!(err.to_string(), "disk full");
+        assert!(std::error::Error::source(&err).is_some());
+    }
+}
@@ -0,0 +1,401 @@
+use std::io::{Error, ErrorKind};
+use std::sync::Arc;
+
+use gltf::image::Format;
+use gltf::material::AlphaMode;
+use gltf::mesh::Mode;
+use gltf::{buffer, image, Document, Node, Primitive};
+
+use crate::math::{Float, Matrix4, Tuple4D};
+use crate::patterns::{TextureFilter, TriangleTexturePattern, UvImage, UvPattern};
+use crate::shapes::Triangle;
+use crate::{Canvas, Color, Material, Object};
+
+// gltf 2.0 asset with its buffers and decoded images. Nodes become groups with the transform of the
+// node, every triangle primitive of a mesh becomes a group of triangles with a material derived
+// from the pbr parameters
+#[derive(Debug)]
+pub struct GltfFile {
+    pub document: Document,
+    pub buffers: Vec<buffer::Data>,
+    pub images: Vec<image::Data>,
+}
+
+impl GltfFile {
+    // .gltf with external or embedded buffers as well as binary .glb files
+    pub fn from_file(filename: &str) -> std::io::Result<GltfFile> {
+        let (document, buffers, images) = gltf::import(filename)
+            .map_err(|err| Error::new(ErrorKind::InvalidData, err.to_string()))?;
+        Ok(GltfFile {
+            document,
+            buffers,
+            images,
+        })
+    }
+
+    // the default scene, or the first one if none is marked as default. Fails for primitives whose
+    // indices or attributes do not match their positions
+    pub fn into_group(self) -> std::io::Result<Object> {
+        let textures: Vec<Option<Arc<dyn UvPattern>>> =
+            self.images.iter().map(image_texture).collect();
+        let scene = self
+            .document
+            .default_scene()
+            .or_else(|| self.document.scenes().next());
+        let children = match scene {
+            Some(scene) => scene
+                .nodes()
+                .map(|node| self.node_object(node, &textures))
+                .collect::<Result<_, _>>()
+                .map_err(|err| Error::new(ErrorKind::InvalidData, err))?,
+            None => vec![],
+        };
+        Ok(Object::group(children))
+    }
+
+    fn node_object(
+        &self,
+        node: Node,
+        textures: &[Option<Arc<dyn UvPattern>>],
+    ) -> Result<Object, String> {
+        let mut children: Vec<Object> = node
+            .children()
+            .map(|child| self.node_object(child, textures))
+            .collect::<Result<_, _>>()?;
+        if let Some(mesh) = node.mesh() {
+            for primitive in mesh.primitives() {
+                children.push(self.primitive_object(primitive, textures)?);
+            }
+        }
+        let mut group = Object::group(children);
+        group.transform = column_major_matrix(node.transform().matrix()).into();
+        Ok(group)
+    }
+
+    // points, lines and strips are skipped, they have no surface or are rarely used for meshes
+    fn primitive_object(
+        &self,
+        primitive: Primitive,
+        textures: &[Option<Arc<dyn UvPattern>>],
+    ) -> Result<Object, String> {
+        if primitive.mode() != Mode::Triangles {
+            return Ok(Object::group(vec![]));
+        }
+        let reader =
+            primitive.reader(|buffer| self.buffers.get(buffer.index()).map(|data| &data[..]));
+        let positions: Vec<Tuple4D> = match reader.read_positions() {
+            Some(positions) => positions
+                .map(|[x, y, z]| Tuple4D::new_point(x as Float, y as Float, z as Float))
+                .collect(),
+            None => return Ok(Object::group(vec![])),
+        };
+        let normals: Option<Vec<Tuple4D>> = reader.read_normals().map(|normals| {
+            normals
+                .map(|[x, y, z]| Tuple4D::new_vector(x as Float, y as Float, z as Float))
+                .collect()
+        });
+        // gltf puts the origin of the texture in the top left corner
+        let uvs: Option<Vec<(Float, Float)>> = reader.read_tex_coords(0).map(|uvs| {
+            uvs.into_f32()
+                .map(|[u, v]| (u as Float, 1.0 - v as Float))
+                .collect()
+        });
+        let indices: Vec<usize> = match reader.read_indices() {
+            Some(indices) => indices.into_u32().map(|idx| idx as usize).collect(),
+            None => (0..positions.len()).collect(),
+        };
+        let primitive_name = format!("primitive {}", primitive.index());
+        if let Some(normals) = normals
+            .as_ref()
+            .filter(|normals| normals.len() != positions.len())
+        {
+            return Err(format!(
+                "{primitive_name} has {} normals for {} positions",
+                normals.len(),
+                positions.len()
+            ));
+        }
+        if let Some(uvs) = uvs.as_ref().filter(|uvs| uvs.len() != positions.len()) {
+            return Err(format!(
+                "{primitive_name} has {} texture coordinates for {} positions",
+                uvs.len(),
+                positions.len()
+            ));
+        }
+        if !indices.len().is_multiple_of(3) {
+            return Err(format!(
+                "{primitive_name} has {} indices, which is not a multiple of 3",
+                indices.len()
+            ));
+        }
+        if let Some(idx) = indices.iter().find(|&&idx| idx >= positions.len()) {
+            return Err(format!(
+                "{primitive_name} has the index {idx}, but only {} positions",
+                positions.len()
+            ));
+        }
+        let gltf_material = primitive.material();
+        let material = match gltf_material.index() {
+            Some(_) => pbr_material(&gltf_material),
+            None => Material::default(),
+        };
+        let texture = gltf_material
+            .pbr_metallic_roughness()
+            .base_color_texture()
+            .filter(|info| info.tex_coord() == 0)
+            .and_then(|info| {
+                textures
+                    .get(info.texture().source().index())
+                    .cloned()
+                    .flatten()
+            });
+        let children = indices
+            .chunks_exact(3)
+            .map(|corners| {
+                let [i1, i2, i3] = [corners[0], corners[1], corners[2]];
+                let (p1, p2, p3) = (positions[i1], positions[i2], positions[i3]);
+                let triangle = match &normals {
+                    Some(normals) => {
+                        Triangle::smooth(p1, p2, p3, normals[i1], normals[i2], normals[i3])
+                    }
+                    None => Triangle::new(p1, p2, p3),
+                };
+                let mut object = Object::new(Box::new(triangle));
+                object.material = material.clone();
+                if let (Some(texture), Some(uvs)) = (&texture, &uvs) {
+                    object.material.pattern = Some(Arc::new(TriangleTexturePattern::new(
+                        texture.clone(),
+                        triangle,
+                        [uvs[i1], uvs[i2], uvs[i3]],
+                    )));
+                }
+                object
+            })
+            .collect();
+        Ok(Object::group(children))
+    }
+}
+
+pub fn parse_gltf_file(bytes: &[u8]) -> Result<GltfFile, String> {
+    let (document, buffers, images) = gltf::import_slice(bytes).map_err(|err| err.to_string())?;
+    Ok(GltfFile {
+        document,
+        buffers,
+        images,
+    })
+}
+
+// metallic surfaces become mirrors and the roughness blurs them and widens the highlight. Alpha
+// only makes the surface transparent in blend mode, as masks have no equivalent here
+fn pbr_material(gltf_material: &gltf::Material) -> Material {
+    let pbr = gltf_material.pbr_metallic_roughness();
+    let [r, g, b, alpha] = pbr.base_color_factor().map(|value| value as Float);
+    let [er, eg, eb] = gltf_material.emissive_factor().map(|value| value as Float);
+    let roughness = pbr.roughness_factor() as Float;
+    Material {
+        color: Color::new(r, g, b),
+        specular: 1.0 - roughness,
+        // phong exponent with a similar highlight width as the microfacet distribution
+        shininess: (2.0 / roughness.max(0.01).powi(4) - 2.0).clamp(1.0, 10000.0),
+        reflective: pbr.metallic_factor() as Float,
+        roughness,
+        transparency: match gltf_material.alpha_mode() {
+            AlphaMode::Blend => 1.0 - alpha,
+            _ => 0.0,
+        },
+        emissive: Color::new(er, eg, eb),
+        ..Material::default()
+    }
+}
+
+// only 8 bit images are supported, the others leave the surface untextured
+fn image_texture(data: &image::Data) -> Option<Arc<dyn UvPattern>> {
+    let channels = match data.format {
+        Format::R8 => 1,
+        Format::R8G8 => 2,
+        Format::R8G8B8 => 3,
+        Format::R8G8B8A8 => 4,
+        _ => return None,
+    };
+    let (width, height) = (data.width as usize, data.height as usize);
+    let mut canvas = Canvas::create_canvas(width, height);
+    for (idx, pixel) in data.pixels.chunks_exact(channels).enumerate() {
+        let value = |channel: usize| pixel[channel.min(channels - 1)] as Float / 255.0;
+        // grayscale images repeat the only channel, two channels are luminance and alpha
+        let color = match channels {
+            1 | 2 => Color::new(value(0), value(0), value(0)),
+            _ => Color::new(value(0), value(1), value(2)),
+        };
+        canvas.write_pixel(idx % width, idx / width, color);
+    }
+    Some(Arc::new(
+        UvImage::new(canvas).with_filter(TextureFilter::Trilinear),
+    ))
+}
+
+fn column_major_matrix(columns: [[f32; 4]; 4]) -> Matrix4 {
+    Matrix4::from_columns(columns.map(|column| column.map(|value| value as Float)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Ray;
+
+    // binary gltf with a single chunk of json and one of data
+    fn glb(json: &str, data: &[u8]) -> Vec<u8> {
+        let mut json = json.as_bytes().to_vec();
+        json.resize(json.len().next_multiple_of(4), b' ');
+        let mut data = data.to_vec();
+        data.resize(data.len().next_multiple_of(4), 0);
+        let length = 12 + 8 + json.len() + 8 + data.len();
+        let mut bytes = b"glTF".to_vec();
+        bytes.extend(2u32.to_le_bytes());
+        bytes.extend((length as u32).to_le_bytes());
+        bytes.extend((json.len() as u32).to_le_bytes());
+        bytes.extend(b"JSON");
+        bytes.extend(json);
+        bytes.extend((data.len() as u32).to_le_bytes());
+        bytes.extend(b"BIN\0");
+        bytes.extend(data);
+        bytes
+    }
+
+    fn triangle_glb(material: &str) -> Vec<u8> {
+        let json = format!(
+            r#"{{
+                "asset": {{"version": "2.0"}},
+                "scene": 0,
+                "scenes": [{{"nodes": [0]}}],
+                "nodes": [
+                    {{"translation": [0.0, 0.0, 2.0], "children": [1]}},
+                    {{"scale": [2.0, 2.0, 2.0], "mesh": 0}}
+                ],
+                "meshes": [{{"primitives": [{{"attributes": {{"POSITION": 0}}, "material": 0}}]}}],
+                "materials": [{material}],
+                "accessors": [{{
+                    "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+                    "min": [-1.0, 0.0, 0.0], "max": [1.0, 1.0, 0.0]
+                }}],
+                "bufferViews": [{{"buffer": 0, "byteLength": 36}}],
+                "buffers": [{{"byteLength": 36}}]
+            }}"#
+        );
+        let data: Vec<u8> = [0.0f32, 1.0, 0.0, -1.0, 0.0, 0.0, 1.0, 0.0, 0.0]
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect();
+        glb(&json, &data)
+    }
+
+    #[test]
+    fn importing_node_hierarchy() {
+        let gltf = parse_gltf_file(&triangle_glb("{}")).unwrap();
+        let group = gltf.into_group().unwrap();
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 1.5, -5.0),
+            Tuple4D::new_vector(0.0, 0.0, 1.0),
+        );
+        // the triangle is scaled to twice its size by the inner node and moved by the outer one
+        let xs = group.intersect(&ray);
+        assert_eq!(xs.len(), 1);
+        assert_eq!(xs[0].t, 7.0);
+        let missing = Ray::new(
+            Tuple4D::new_point(0.0, 2.5, -5.0),
+            Tuple4D::new_vector(0.0, 0.0, 1.0),
+        );
+        assert!(group.intersect(&missing).is_empty());
+    }
+
+    #[test]
+    fn pbr_parameters_map_to_material() {
+        let gltf = parse_gltf_file(&triangle_glb(
+            r#"{
+                "pbrMetallicRoughness": {
+                    "baseColorFactor": [1.0, 0.0, 0.0, 0.25],
+                    "metallicFactor": 0.5,
+                    "roughnessFactor": 0.5
+                },
+                "emissiveFactor": [0.0, 0.0, 1.0],
+                "alphaMode": "BLEND"
+            }"#,
+        ))
+        .unwrap();
+        let material = pbr_material(&gltf.document.materials().next().unwrap());
+        assert_eq!(material.color, Color::new(1.0, 0.0, 0.0));
+        assert_eq!(material.reflective, 0.5);
+        assert_eq!(material.roughness, 0.5);
+        assert_eq!(material.specular, 0.5);
+        assert_eq!(material.shininess, 30.0);
+        assert_eq!(material.transparency, 0.75);
+        assert_eq!(material.emissive, Color::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn converting_column_major_matrix() {
+        let mut columns = [[0.0; 4]; 4];
+        columns[0][0] = 1.0;
+        columns[1][1] = 1.0;
+        columns[2][2] = 1.0;
+        columns[3] = [1.0, 2.0, 3.0, 1.0];
+        assert_eq!(
+            column_major_matrix(columns),
+            Matrix4::translation(1.0, 2.0, 3.0)
+        );
+    }
+
+    // the triangle of triangle_glb with normals and indices that do not fit its 3 positions
+    fn broken_triangle_glb(normal_count: usize, index: u16) -> Vec<u8> {
+        let json = format!(
+            r#"{{
+                "asset": {{"version": "2.0"}},
+                "scenes": [{{"nodes": [0]}}],
+                "nodes": [{{"mesh": 0}}],
+                "meshes": [{{"primitives": [{{
+                    "attributes": {{"POSITION": 0, "NORMAL": 1}}, "indices": 2
+                }}]}}],
+                "accessors": [
+                    {{
+                        "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+                        "min": [-1.0, 0.0, 0.0], "max": [1.0, 1.0, 0.0]
+                    }},
+                    {{
+                        "bufferView": 0, "componentType": 5126, "count": {normal_count},
+                        "type": "VEC3"
+                    }},
+                    {{"bufferView": 1, "componentType": 5123, "count": 3, "type": "SCALAR"}}
+                ],
+                "bufferViews": [
+                    {{"buffer": 0, "byteLength": 36}},
+                    {{"buffer": 0, "byteOffset": 36, "byteLength": 6}}
+                ],
+                "buffers": [{{"byteLength": 42}}]
+            }}"#
+        );
+        let mut data: Vec<u8> = [0.0f32, 1.0, 0.0, -1.0, 0.0, 0.0, 1.0, 0.0, 0.0]
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect();
+        data.extend(
+            [0u16, 1, index]
+                .iter()
+                .flat_map(|value| value.to_le_bytes()),
+        );
+        glb(&json, &data)
+    }
+
+    #[test]
+    fn primitives_that_do_not_match_their_positions_are_errors() {
+        let valid = parse_gltf_file(&broken_triangle_glb(3, 2)).unwrap();
+        assert!(valid.into_group().is_ok());
+        let short_normals = parse_gltf_file(&broken_triangle_glb(2, 2)).unwrap();
+        assert!(short_normals.into_group().is_err());
+        let index_out_of_range = parse_gltf_file(&broken_triangle_glb(3, 3)).unwrap();
+        assert!(index_out_of_range.into_group().is_err());
+    }
+
+    #[test]
+    fn invalid_gltf_is_error() {
+        assert!(parse_gltf_file(b"not a gltf file").is_err());
+    }
+}
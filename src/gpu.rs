@@ -0,0 +1,591 @@
+use std::any::Any;
+
+use bytemuck::{Pod, Zeroable};
+use wgpu::util::DeviceExt;
+
+use crate::math::{Float, Matrix4, Tuple4D};
+use crate::shapes::{Cube, Group, Plane, Sphere, Triangle};
+use crate::{
+    Attenuation, Background, Camera, Canvas, Color, Integrator, Light, Material, Object,
+    Projection, World,
+};
+
+// rows rendered by one dispatch, long dispatches get the gpu reset by the driver
+const ROWS_PER_DISPATCH: usize = 64;
+const WORKGROUP_SIZE: usize = 8;
+
+// the structs below match the ones in gpu.wgsl, matrices are stored column by column
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+struct GpuUniforms {
+    inverse_view: [[f32; 4]; 4],
+    background_bottom: [f32; 4],
+    background_top: [f32; 4],
+    half_width: f32,
+    half_height: f32,
+    width: u32,
+    height: u32,
+    shape_count: u32,
+    triangle_count: u32,
+    light_count: u32,
+    max_depth: u32,
+    samples: u32,
+    first_row: u32,
+    pad: [u32; 2],
+}
+
+const SPHERE: u32 = 0;
+const PLANE: u32 = 1;
+const CUBE: u32 = 2;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+struct GpuShape {
+    inverse: [[f32; 4]; 4],
+    kind: u32,
+    material: u32,
+    pad: [u32; 2],
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+struct GpuTriangle {
+    points: [[f32; 4]; 3],
+    normals: [[f32; 4]; 3],
+    material: u32,
+    smooth_normals: u32,
+    pad: [u32; 2],
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+struct GpuMaterial {
+    color: [f32; 4],
+    emissive: [f32; 4],
+    ambient: f32,
+    diffuse: f32,
+    specular: f32,
+    shininess: f32,
+    reflective: f32,
+    pad: [f32; 3],
+}
+
+const POINT_LIGHT: u32 = 0;
+const DIRECTIONAL_LIGHT: u32 = 1;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+struct GpuLight {
+    position: [f32; 4],
+    direction: [f32; 4],
+    intensity: [f32; 4],
+    kind: u32,
+    attenuation: u32,
+    pad: [u32; 2],
+}
+
+// world and camera flattened into the buffers of the shader. Groups are resolved into their
+// children and triangles are moved into world space
+#[derive(Debug, Clone)]
+struct GpuScene {
+    uniforms: GpuUniforms,
+    shapes: Vec<GpuShape>,
+    triangles: Vec<GpuTriangle>,
+    materials: Vec<GpuMaterial>,
+    lights: Vec<GpuLight>,
+}
+
+impl GpuScene {
+    // fails for everything the shader can not render yet instead of rendering it differently
+    fn new(world: &World, camera: &Camera) -> Result<GpuScene, String> {
+        if camera.projection != Projection::Perspective {
+            return Err("the gpu only renders perspective cameras".to_string());
+        }
+        if camera.integrator != Integrator::Whitted {
+            return Err("the gpu only renders with the whitted integrator".to_string());
+        }
+        if camera.aperture_radius > 0.0 || camera.transform_end.is_some() {
+            return Err("the gpu renders neither depth of field nor motion blur".to_string());
+        }
+        if world.fog.is_some() {
+            return Err("the gpu does not render fog".to_string());
+        }
+        let (background_bottom, background_top) = match &world.background {
+            Background::Solid(color) => (*color, *color),
+            Background::Gradient { bottom, top } => (*bottom, *top),
+            Background::Environment(_) => {
+                return Err("the gpu does not render environment maps".to_string());
+            }
+        };
+        let inverse_view = camera
+            .transform
+            .inverse()
+            .ok_or("camera transform has to be invertible")?;
+        let mut scene = GpuScene {
+            uniforms: GpuUniforms {
+                inverse_view: columns(inverse_view),
+                background_bottom: color4(background_bottom),
+                background_top: color4(background_top),
+                half_width: (camera.pixel_size() * camera.hsize() as Float / 2.0) as f32,
+                half_height: (camera.pixel_size() * camera.vsize() as Float / 2.0) as f32,
+                width: camera.hsize() as u32,
+                height: camera.vsize() as u32,
+                shape_count: 0,
+                triangle_count: 0,
+                light_count: 0,
+                max_depth: world.max_depth as u32,
+                samples: camera.samples_per_pixel as u32,
+                first_row: 0,
+                pad: [0; 2],
+            },
+            shapes: vec![],
+            triangles: vec![],
+            materials: vec![],
+            lights: world
+                .lights
+                .iter()
+                .map(gpu_light)
+                .collect::<Result<_, _>>()?,
+        };
+        for object in &world.objects {
+            scene.add_object(object, Matrix4::eye())?;
+        }
+        scene.uniforms.shape_count = scene.shapes.len() as u32;
+        scene.uniforms.triangle_count = scene.triangles.len() as u32;
+        scene.uniforms.light_count = scene.lights.len() as u32;
+        Ok(scene)
+    }
+
+    fn add_object(&mut self, object: &Object, parent: Matrix4) -> Result<(), String> {
+        if object.transform_end.is_some() {
+            return Err("the gpu does not render motion blur".to_string());
+        }
+        let transform = parent * object.transform.matrix();
+        let shape: &dyn Any = object.shape.as_ref();
+        if let Some(group) = shape.downcast_ref::<Group>() {
+            for child in group.children() {
+                self.add_object(child, transform)?;
+            }
+            return Ok(());
+        }
+        let material = self.add_material(&object.material)?;
+        let kind = if shape.is::<Sphere>() {
+            SPHERE
+        } else if shape.is::<Plane>() {
+            PLANE
+        } else if shape.is::<Cube>() {
+            CUBE
+        } else if let Some(triangle) = shape.downcast_ref::<Triangle>() {
+            let normal_transform = transform
+                .inverse()
+                .ok_or("object transforms have to be invertible")?
+                .transpose();
+            let points = [triangle.p1(), triangle.p2(), triangle.p3()];
+            let normals = triangle.normals().unwrap_or([triangle.face_normal(); 3]);
+            self.triangles.push(GpuTriangle {
+                points: points.map(|point| tuple4(transform * point)),
+                normals: normals.map(|normal| tuple4((normal_transform * normal).normalize())),
+                material,
+                smooth_normals: triangle.normals().is_some() as u32,
+                pad: [0; 2],
+            });
+            return Ok(());
+        } else {
+            return Err(format!(
+                "the gpu can not render {} shapes",
+                object.shape.type_name()
+            ));
+        };
+        let inverse = transform
+            .inverse()
+            .ok_or("object transforms have to be invertible")?;
+        self.shapes.push(GpuShape {
+            inverse: columns(inverse),
+            kind,
+            material,
+            pad: [0; 2],
+        });
+        Ok(())
+    }
+
+    fn add_material(&mut self, material: &Material) -> Result<u32, String> {
+        if material.pattern.is_some() || material.normal_map.is_some() {
+            return Err("the gpu does not render patterns and normal maps".to_string());
+        }
+        if material.transparency > 0.0 || material.volume.is_some() {
+            return Err("the gpu does not render transparent objects and volumes".to_string());
+        }
+        if material.roughness > 0.0 {
+            return Err("the gpu does not render glossy reflections".to_string());
+        }
+        self.materials.push(GpuMaterial {
+            color: color4(material.color),
+            emissive: color4(material.emissive),
+            ambient: material.ambient as f32,
+            diffuse: material.diffuse as f32,
+            specular: material.specular as f32,
+            shininess: material.shininess as f32,
+            reflective: material.reflective as f32,
+            pad: [0.0; 3],
+        });
+        Ok(self.materials.len() as u32 - 1)
+    }
+}
+
+fn gpu_light(light: &Light) -> Result<GpuLight, String> {
+    let (kind, position, direction, attenuation) = match light {
+        Light::Point(light) => {
+            let attenuation = match light.attenuation {
+                Attenuation::None => 0,
+                Attenuation::Linear => 1,
+                Attenuation::Quadratic => 2,
+            };
+            let direction = Tuple4D::new_vector(0.0, 0.0, 0.0);
+            (POINT_LIGHT, light.position, direction, attenuation)
+        }
+        Light::Directional(light) => {
+            let position = Tuple4D::new_point(0.0, 0.0, 0.0);
+            (DIRECTIONAL_LIGHT, position, light.direction, 0)
+        }
+        Light::Spot(_) | Light::Area(_) => {
+            return Err("the gpu only renders point and directional lights".to_string());
+        }
+    };
+    Ok(GpuLight {
+        position: tuple4(position),
+        direction: tuple4(direction),
+        intensity: color4(light.intensity()),
+        kind,
+        attenuation,
+        pad: [0; 2],
+    })
+}
+
+fn columns(matrix: Matrix4) -> [[f32; 4]; 4] {
+    let mut columns = [[0.0; 4]; 4];
+    for (col, values) in columns.iter_mut().enumerate() {
+        for (row, value) in values.iter_mut().enumerate() {
+            *value = matrix[[row, col]] as f32;
+        }
+    }
+    columns
+}
+
+fn tuple4(tuple: Tuple4D) -> [f32; 4] {
+    [
+        tuple.x as f32,
+        tuple.y as f32,
+        tuple.z as f32,
+        tuple.w as f32,
+    ]
+}
+
+fn color4(color: Color) -> [f32; 4] {
+    [color.r as f32, color.g as f32, color.b as f32, 1.0]
+}
+
+// renders worlds with a compute shader instead of the cpu threads. Only the whitted integrator,
+// perspective cameras and the basic shapes, materials and lights are supported so far, render
+// returns an error for scenes with anything else. Colors are computed in single precision, so
+// they differ slightly from the cpu renders
+#[derive(Debug)]
+pub struct GpuRenderer {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    pipeline: wgpu::ComputePipeline,
+    adapter: String,
+}
+
+impl GpuRenderer {
+    // fails if there is no gpu that can run compute shaders
+    pub fn new() -> Result<GpuRenderer, String> {
+        pollster::block_on(GpuRenderer::create())
+    }
+
+    async fn create() -> Result<GpuRenderer, String> {
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::HighPerformance,
+                ..wgpu::RequestAdapterOptions::default()
+            })
+            .await
+            .map_err(|err| format!("no gpu found: {err}"))?;
+        let capabilities = adapter.get_downlevel_capabilities();
+        if !capabilities
+            .flags
+            .contains(wgpu::DownlevelFlags::COMPUTE_SHADERS)
+        {
+            return Err(format!(
+                "{} can not run compute shaders",
+                adapter.get_info().name
+            ));
+        }
+        let (device, queue) = adapter
+            .request_device(&wgpu::DeviceDescriptor {
+                label: Some("ray tracer"),
+                required_limits: adapter.limits(),
+                ..wgpu::DeviceDescriptor::default()
+            })
+            .await
+            .map_err(|err| err.to_string())?;
+        device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("ray tracer"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("gpu.wgsl").into()),
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("ray tracer"),
+            layout: None,
+            module: &module,
+            entry_point: Some("main"),
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+            cache: None,
+        });
+        if let Some(err) = device.pop_error_scope().await {
+            return Err(err.to_string());
+        }
+        let info = adapter.get_info();
+        Ok(GpuRenderer {
+            device,
+            queue,
+            pipeline,
+            adapter: format!("{} ({:?})", info.name, info.backend),
+        })
+    }
+
+    // name and backend of the gpu
+    pub fn adapter(&self) -> &str {
+        &self.adapter
+    }
+
+    pub fn render(&self, world: &World, camera: &Camera) -> Result<Canvas, String> {
+        let scene = GpuScene::new(world, camera)?;
+        let (width, height) = (camera.hsize(), camera.vsize());
+        let size = (width * height * std::mem::size_of::<[f32; 4]>()) as u64;
+        let limits = self.device.limits();
+        if size > limits.max_storage_buffer_binding_size as u64 || size > limits.max_buffer_size {
+            return Err(format!(
+                "{width}x{height} pixels do not fit into gpu memory"
+            ));
+        }
+        // storage buffers can not be empty
+        let storage = |label: &str, contents: &[u8]| {
+            self.device
+                .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some(label),
+                    contents: if contents.is_empty() {
+                        &[0; 256]
+                    } else {
+                        contents
+                    },
+                    usage: wgpu::BufferUsages::STORAGE,
+                })
+        };
+        let shapes = storage("shapes", bytemuck::cast_slice(&scene.shapes));
+        let triangles = storage("triangles", bytemuck::cast_slice(&scene.triangles));
+        let materials = storage("materials", bytemuck::cast_slice(&scene.materials));
+        let lights = storage("lights", bytemuck::cast_slice(&scene.lights));
+        let uniforms = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("uniforms"),
+            size: std::mem::size_of::<GpuUniforms>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let pixels = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("pixels"),
+            size,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let readback = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("readback"),
+            size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("scene"),
+            layout: &self.pipeline.get_bind_group_layout(0),
+            entries: &[&uniforms, &shapes, &triangles, &materials, &lights, &pixels]
+                .iter()
+                .enumerate()
+                .map(|(binding, buffer)| wgpu::BindGroupEntry {
+                    binding: binding as u32,
+                    resource: buffer.as_entire_binding(),
+                })
+                .collect::<Vec<_>>(),
+        });
+
+        for first_row in (0..height).step_by(ROWS_PER_DISPATCH) {
+            let rows = ROWS_PER_DISPATCH.min(height - first_row);
+            let uniforms_of_rows = GpuUniforms {
+                first_row: first_row as u32,
+                ..scene.uniforms
+            };
+            self.queue
+                .write_buffer(&uniforms, 0, bytemuck::bytes_of(&uniforms_of_rows));
+            let mut encoder = self
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+            {
+                let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor::default());
+                pass.set_pipeline(&self.pipeline);
+                pass.set_bind_group(0, &bind_group, &[]);
+                pass.dispatch_workgroups(
+                    width.div_ceil(WORKGROUP_SIZE) as u32,
+                    rows.div_ceil(WORKGROUP_SIZE) as u32,
+                    1,
+                );
+            }
+            self.queue.submit([encoder.finish()]);
+            self.wait()?;
+        }
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        encoder.copy_buffer_to_buffer(&pixels, 0, &readback, 0, size);
+        self.queue.submit([encoder.finish()]);
+        let slice = readback.slice(..);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        self.wait()?;
+        receiver
+            .recv()
+            .map_err(|err| err.to_string())?
+            .map_err(|err| err.to_string())?;
+        let mut canvas = Canvas::create_canvas(width, height);
+        {
+            let data = slice.get_mapped_range();
+            let values: &[[f32; 4]] = bytemuck::cast_slice(&data);
+            for (idx, [r, g, b, _]) in values.iter().enumerate() {
+                canvas.write_pixel(
+                    idx % width,
+                    idx / width,
+                    Color::new(*r as Float, *g as Float, *b as Float),
+                );
+            }
+        }
+        readback.unmap();
+        Ok(canvas)
+    }
+
+    fn wait(&self) -> Result<(), String> {
+        self.device
+            .poll(wgpu::PollType::Wait)
+            .map(|_| ())
+            .map_err(|err| err.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::consts::PI;
+
+    use super::*;
+    use crate::PointLight;
+
+    fn camera() -> Camera {
+        let mut camera = Camera::new(32, 24, PI / 3.0);
+        camera.transform = Matrix4::view_transform(
+            Tuple4D::new_point(0.0, 1.0, -5.0),
+            Tuple4D::new_point(0.0, 0.0, 0.0),
+            Tuple4D::new_vector(0.0, 1.0, 0.0),
+        )
+        .into();
+        camera
+    }
+
+    #[test]
+    fn groups_are_flattened() {
+        let mut world = World::new();
+        let mut child = Object::sphere();
+        child.transform = Matrix4::translation(1.0, 0.0, 0.0).into();
+        let triangle = Object::triangle(
+            Tuple4D::new_point(0.0, 1.0, 0.0),
+            Tuple4D::new_point(-1.0, 0.0, 0.0),
+            Tuple4D::new_point(1.0, 0.0, 0.0),
+        );
+        let mut group = Object::group(vec![child, triangle]);
+        group.transform = Matrix4::scaling(2.0, 2.0, 2.0).into();
+        world.objects.push(group);
+        world.objects.push(Object::plane());
+        let scene = GpuScene::new(&world, &camera()).unwrap();
+        assert_eq!(scene.shapes.len(), 2);
+        assert_eq!(
+            (scene.shapes[0].kind, scene.shapes[1].kind),
+            (SPHERE, PLANE)
+        );
+        let inverse = (Matrix4::scaling(2.0, 2.0, 2.0) * Matrix4::translation(1.0, 0.0, 0.0))
+            .inverse()
+            .unwrap();
+        assert_eq!(scene.shapes[0].inverse, columns(inverse));
+        assert_eq!(scene.triangles[0].points[0], [0.0, 2.0, 0.0, 1.0]);
+        assert_eq!(scene.triangles[0].normals[0], [0.0, 0.0, -1.0, 0.0]);
+        assert_eq!(scene.materials.len(), 3);
+        assert_eq!(scene.uniforms.shape_count, 2);
+        assert_eq!(scene.uniforms.triangle_count, 1);
+    }
+
+    #[test]
+    fn unsupported_scenes_are_errors() {
+        let world = World::default_world();
+        let mut camera = camera();
+        camera.integrator = Integrator::path_tracing(2);
+        assert!(GpuScene::new(&world, &camera).is_err());
+        let mut world = World::default_world();
+        world.objects.push(Object::torus(1.0, 0.25));
+        let err = GpuScene::new(&world, &self::camera()).unwrap_err();
+        assert_eq!(err, "the gpu can not render Torus shapes");
+        let mut world = World::default_world();
+        world.objects[0].material.transparency = 0.5;
+        assert!(GpuScene::new(&world, &self::camera()).is_err());
+    }
+
+    #[test]
+    fn buffer_layouts_match_the_shader() {
+        assert_eq!(std::mem::size_of::<GpuUniforms>(), 144);
+        assert_eq!(std::mem::size_of::<GpuShape>(), 80);
+        assert_eq!(std::mem::size_of::<GpuTriangle>(), 112);
+        assert_eq!(std::mem::size_of::<GpuMaterial>(), 64);
+        assert_eq!(std::mem::size_of::<GpuLight>(), 64);
+    }
+
+    // only runs where a gpu or a software adapter is available
+    #[test]
+    fn gpu_render_matches_the_cpu() {
+        let Ok(renderer) = GpuRenderer::new() else {
+            return;
+        };
+        let mut world = World::default_world();
+        world.objects[0].material.reflective = 0.3;
+        let mut floor = Object::plane();
+        floor.transform = Matrix4::translation(0.0, -1.0, 0.0).into();
+        world.objects.push(floor);
+        world.lights.push(
+            PointLight::new(
+                Tuple4D::new_point(5.0, 10.0, -5.0),
+                Color::new(0.3, 0.3, 0.3),
+            )
+            .into(),
+        );
+        let camera = camera();
+        let gpu = renderer.render(&world, &camera).unwrap();
+        let cpu = camera.render(&world);
+        let mut different = 0;
+        for y in 0..camera.vsize() {
+            for x in 0..camera.hsize() {
+                let (a, b) = (gpu.read_pixel(x, y), cpu.read_pixel(x, y));
+                if (a.r - b.r).abs() + (a.g - b.g).abs() + (a.b - b.b).abs() > 0.01 {
+                    different += 1;
+                }
+            }
+        }
+        // pixels right at the edges of shadows can flip because of the precision
+        assert!(different <= 4, "{different} pixels differ");
+    }
+}
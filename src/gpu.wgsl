@@ -0,0 +1,331 @@
+// whitted ray tracer for GpuRenderer, the layouts of the structs match the ones in gpu.rs
+
+struct Uniforms {
+    inverse_view: mat4x4<f32>,
+    background_bottom: vec4<f32>,
+    background_top: vec4<f32>,
+    half_width: f32,
+    half_height: f32,
+    width: u32,
+    height: u32,
+    shape_count: u32,
+    triangle_count: u32,
+    light_count: u32,
+    max_depth: u32,
+    samples: u32,
+    first_row: u32,
+    pad0: u32,
+    pad1: u32,
+}
+
+struct Shape {
+    inverse: mat4x4<f32>,
+    kind: u32,
+    material: u32,
+    pad0: u32,
+    pad1: u32,
+}
+
+// in world space, the normals are only used by smooth triangles
+struct Triangle {
+    p1: vec4<f32>,
+    p2: vec4<f32>,
+    p3: vec4<f32>,
+    n1: vec4<f32>,
+    n2: vec4<f32>,
+    n3: vec4<f32>,
+    material: u32,
+    smooth_normals: u32,
+    pad0: u32,
+    pad1: u32,
+}
+
+struct Material {
+    color: vec4<f32>,
+    emissive: vec4<f32>,
+    ambient: f32,
+    diffuse: f32,
+    specular: f32,
+    shininess: f32,
+    reflective: f32,
+    pad0: f32,
+    pad1: f32,
+    pad2: f32,
+}
+
+struct Light {
+    position: vec4<f32>,
+    direction: vec4<f32>,
+    intensity: vec4<f32>,
+    kind: u32,
+    attenuation: u32,
+    pad0: u32,
+    pad1: u32,
+}
+
+struct Hit {
+    t: f32,
+    normal: vec3<f32>,
+    material: u32,
+}
+
+@group(0) @binding(0) var<uniform> scene: Uniforms;
+@group(0) @binding(1) var<storage, read> shapes: array<Shape>;
+@group(0) @binding(2) var<storage, read> triangles: array<Triangle>;
+@group(0) @binding(3) var<storage, read> materials: array<Material>;
+@group(0) @binding(4) var<storage, read> lights: array<Light>;
+@group(0) @binding(5) var<storage, read_write> pixels: array<vec4<f32>>;
+
+const SPHERE: u32 = 0u;
+const PLANE: u32 = 1u;
+const CUBE: u32 = 2u;
+
+const POINT_LIGHT: u32 = 0u;
+
+const ATTENUATION_LINEAR: u32 = 1u;
+const ATTENUATION_QUADRATIC: u32 = 2u;
+
+const NO_HIT: f32 = 3.0e38;
+// larger than on the cpu, single precision needs a bigger offset against shadow acne
+const EPSILON: f32 = 1.0e-4;
+
+// the closest intersection in front of the origin
+fn nearest(t0: f32, t1: f32) -> f32 {
+    if t0 > 0.0 {
+        return t0;
+    }
+    if t1 > 0.0 {
+        return t1;
+    }
+    return NO_HIT;
+}
+
+// entry and exit of the ray into the slab between -1 and 1 along one axis
+fn slab(origin: f32, direction: f32) -> vec2<f32> {
+    if abs(direction) < 1.0e-9 {
+        if abs(origin) <= 1.0 {
+            return vec2<f32>(-NO_HIT, NO_HIT);
+        }
+        return vec2<f32>(NO_HIT, -NO_HIT);
+    }
+    let a = (-1.0 - origin) / direction;
+    let b = (1.0 - origin) / direction;
+    return vec2<f32>(min(a, b), max(a, b));
+}
+
+// t is the same for the local ray, its direction is not normalized
+fn shape_t(shape: Shape, origin: vec3<f32>, direction: vec3<f32>) -> f32 {
+    let o = (shape.inverse * vec4<f32>(origin, 1.0)).xyz;
+    let d = (shape.inverse * vec4<f32>(direction, 0.0)).xyz;
+    switch shape.kind {
+        case SPHERE: {
+            let a = dot(d, d);
+            let b = 2.0 * dot(d, o);
+            let c = dot(o, o) - 1.0;
+            let discriminant = b * b - 4.0 * a * c;
+            if discriminant < 0.0 {
+                return NO_HIT;
+            }
+            let root = sqrt(discriminant);
+            return nearest((-b - root) / (2.0 * a), (-b + root) / (2.0 * a));
+        }
+        case PLANE: {
+            if abs(d.y) < 1.0e-9 {
+                return NO_HIT;
+            }
+            return nearest(-o.y / d.y, -1.0);
+        }
+        default: {
+            let x = slab(o.x, d.x);
+            let y = slab(o.y, d.y);
+            let z = slab(o.z, d.z);
+            let t_min = max(x.x, max(y.x, z.x));
+            let t_max = min(x.y, min(y.y, z.y));
+            if t_min > t_max {
+                return NO_HIT;
+            }
+            return nearest(t_min, t_max);
+        }
+    }
+}
+
+fn shape_normal(shape: Shape, point: vec3<f32>) -> vec3<f32> {
+    let p = (shape.inverse * vec4<f32>(point, 1.0)).xyz;
+    var local = vec3<f32>(0.0, 1.0, 0.0);
+    if shape.kind == SPHERE {
+        local = p;
+    } else if shape.kind == CUBE {
+        let a = abs(p);
+        if a.x >= a.y && a.x >= a.z {
+            local = vec3<f32>(p.x, 0.0, 0.0);
+        } else if a.y >= a.z {
+            local = vec3<f32>(0.0, p.y, 0.0);
+        } else {
+            local = vec3<f32>(0.0, 0.0, p.z);
+        }
+    }
+    return normalize((transpose(shape.inverse) * vec4<f32>(local, 0.0)).xyz);
+}
+
+// möller-trumbore, returns t and the barycentric u and v
+fn triangle_t(triangle: Triangle, origin: vec3<f32>, direction: vec3<f32>) -> vec3<f32> {
+    let e1 = triangle.p2.xyz - triangle.p1.xyz;
+    let e2 = triangle.p3.xyz - triangle.p1.xyz;
+    let dir_cross_e2 = cross(direction, e2);
+    let det = dot(e1, dir_cross_e2);
+    if abs(det) < 1.0e-12 {
+        return vec3<f32>(NO_HIT, 0.0, 0.0);
+    }
+    let f = 1.0 / det;
+    let p1_to_origin = origin - triangle.p1.xyz;
+    let u = f * dot(p1_to_origin, dir_cross_e2);
+    if u < 0.0 || u > 1.0 {
+        return vec3<f32>(NO_HIT, 0.0, 0.0);
+    }
+    let origin_cross_e1 = cross(p1_to_origin, e1);
+    let v = f * dot(direction, origin_cross_e1);
+    if v < 0.0 || u + v > 1.0 {
+        return vec3<f32>(NO_HIT, 0.0, 0.0);
+    }
+    return vec3<f32>(nearest(f * dot(e2, origin_cross_e1), -1.0), u, v);
+}
+
+fn triangle_normal(triangle: Triangle, u: f32, v: f32) -> vec3<f32> {
+    if triangle.smooth_normals == 1u {
+        return normalize(triangle.n2.xyz * u + triangle.n3.xyz * v + triangle.n1.xyz * (1.0 - u - v));
+    }
+    let e1 = triangle.p2.xyz - triangle.p1.xyz;
+    let e2 = triangle.p3.xyz - triangle.p1.xyz;
+    return normalize(cross(e2, e1));
+}
+
+// every object is tested, there is no acceleration structure on the gpu yet
+fn closest_hit(origin: vec3<f32>, direction: vec3<f32>) -> Hit {
+    var hit = Hit(NO_HIT, vec3<f32>(0.0, 1.0, 0.0), 0u);
+    for (var i = 0u; i < scene.shape_count; i++) {
+        let shape = shapes[i];
+        let t = shape_t(shape, origin, direction);
+        if t < hit.t {
+            hit = Hit(t, shape_normal(shape, origin + direction * t), shape.material);
+        }
+    }
+    for (var i = 0u; i < scene.triangle_count; i++) {
+        let triangle = triangles[i];
+        let tuv = triangle_t(triangle, origin, direction);
+        if tuv.x < hit.t {
+            hit = Hit(tuv.x, triangle_normal(triangle, tuv.y, tuv.z), triangle.material);
+        }
+    }
+    return hit;
+}
+
+fn background(direction: vec3<f32>) -> vec3<f32> {
+    let t = 0.5 * (direction.y + 1.0);
+    return scene.background_bottom.xyz * (1.0 - t) + scene.background_top.xyz * t;
+}
+
+// phong reflection model like the cpu lighting function
+fn lighting(material: Material, light: Light, point: vec3<f32>, eye: vec3<f32>, normal: vec3<f32>) -> vec3<f32> {
+    let color = material.color.xyz;
+    let ambient = color * light.intensity.xyz * material.ambient;
+    var light_direction = -light.direction.xyz;
+    var distance = NO_HIT;
+    if light.kind == POINT_LIGHT {
+        let to_light = light.position.xyz - point;
+        distance = length(to_light);
+        light_direction = to_light / distance;
+    }
+    if closest_hit(point, light_direction).t < distance {
+        return ambient;
+    }
+    let light_dot_normal = dot(light_direction, normal);
+    if light_dot_normal < 0.0 {
+        return ambient;
+    }
+    var incoming = light.intensity.xyz;
+    if light.kind == POINT_LIGHT && light.attenuation == ATTENUATION_LINEAR {
+        incoming = incoming / distance;
+    } else if light.kind == POINT_LIGHT && light.attenuation == ATTENUATION_QUADRATIC {
+        incoming = incoming / (distance * distance);
+    }
+    let diffuse = color * incoming * material.diffuse * light_dot_normal;
+    let reflect_dot_eye = dot(reflect(-light_direction, normal), eye);
+    var specular = vec3<f32>(0.0);
+    if reflect_dot_eye > 0.0 {
+        specular = incoming * material.specular * pow(reflect_dot_eye, material.shininess);
+    }
+    return ambient + diffuse + specular;
+}
+
+// reflections are followed in a loop instead of recursively, throughput is the product of the
+// reflectivity of the surfaces so far
+fn trace(ray_origin: vec3<f32>, ray_direction: vec3<f32>) -> vec3<f32> {
+    var color = vec3<f32>(0.0);
+    var throughput = vec3<f32>(1.0);
+    var origin = ray_origin;
+    var direction = ray_direction;
+    for (var depth = 0u; depth <= scene.max_depth; depth++) {
+        let hit = closest_hit(origin, direction);
+        if hit.t >= NO_HIT {
+            color += throughput * background(direction);
+            break;
+        }
+        let material = materials[hit.material];
+        let point = origin + direction * hit.t;
+        let eye = -direction;
+        var normal = hit.normal;
+        if dot(normal, eye) < 0.0 {
+            normal = -normal;
+        }
+        let over_point = point + normal * EPSILON * max(1.0, max(abs(point.x), max(abs(point.y), abs(point.z))));
+        var surface = material.emissive.xyz;
+        for (var i = 0u; i < scene.light_count; i++) {
+            surface += lighting(material, lights[i], over_point, eye, normal);
+        }
+        color += throughput * surface;
+        if material.reflective <= 0.0 {
+            break;
+        }
+        throughput *= material.reflective;
+        origin = over_point;
+        direction = reflect(direction, normal);
+    }
+    return color;
+}
+
+// pcg hash, uniform in [0, 1)
+fn random(seed: u32) -> f32 {
+    let state = seed * 747796405u + 2891336453u;
+    let word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+    return f32((word >> 22u) ^ word) / 4294967296.0;
+}
+
+fn camera_ray_direction(x: u32, y: u32, offset: vec2<f32>, origin: vec3<f32>) -> vec3<f32> {
+    // the camera looks toward -z, so +x is to the left
+    let u = 1.0 - 2.0 * (f32(x) + offset.x) / f32(scene.width);
+    let v = 1.0 - 2.0 * (f32(y) + offset.y) / f32(scene.height);
+    let pixel = scene.inverse_view * vec4<f32>(scene.half_width * u, scene.half_height * v, -1.0, 1.0);
+    return normalize(pixel.xyz - origin);
+}
+
+@compute @workgroup_size(8, 8)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let x = id.x;
+    let y = id.y + scene.first_row;
+    if x >= scene.width || y >= scene.height {
+        return;
+    }
+    let origin = (scene.inverse_view * vec4<f32>(0.0, 0.0, 0.0, 1.0)).xyz;
+    let samples = max(scene.samples, 1u);
+    var sum = vec3<f32>(0.0);
+    for (var i = 0u; i < samples; i++) {
+        // a single sample goes through the center of the pixel like on the cpu
+        var offset = vec2<f32>(0.5, 0.5);
+        if samples > 1u {
+            let seed = ((y * scene.width + x) * samples + i) * 2u;
+            offset = vec2<f32>(random(seed), random(seed + 1u));
+        }
+        sum += trace(origin, camera_ray_direction(x, y, offset, origin));
+    }
+    pixels[y * scene.width + x] = vec4<f32>(sum / f32(samples), 1.0);
+}
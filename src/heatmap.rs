@@ -0,0 +1,238 @@
+use std::cell::Cell;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::math::Float;
+use crate::{Camera, Canvas, Color, Vec2D, World};
+
+// counters of the work done for the rays of the current thread, read by Camera::render_cost. They
+// are thread local, so counting does not slow down the threads of normal renders by sharing memory
+thread_local! {
+    static INTERSECTION_TESTS: Cell<usize> = const { Cell::new(0) };
+    static BOUNDS_TESTS: Cell<usize> = const { Cell::new(0) };
+}
+
+// heatmaps that are being rendered, the counters are only touched while there are any. Outside of
+// them every test costs a load of a value that never changes
+static COST_RENDERS: AtomicUsize = AtomicUsize::new(0);
+
+pub(crate) fn count_intersection_test() {
+    if COST_RENDERS.load(Ordering::Relaxed) > 0 {
+        INTERSECTION_TESTS.with(|count| count.set(count.get() + 1));
+    }
+}
+
+pub(crate) fn count_bounds_test() {
+    if COST_RENDERS.load(Ordering::Relaxed) > 0 {
+        BOUNDS_TESTS.with(|count| count.set(count.get() + 1));
+    }
+}
+
+// counting is switched on while it lives
+struct CostRender;
+
+impl CostRender {
+    fn start() -> CostRender {
+        COST_RENDERS.fetch_add(1, Ordering::Relaxed);
+        CostRender
+    }
+}
+
+impl Drop for CostRender {
+    fn drop(&mut self) {
+        COST_RENDERS.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+// what a heatmap shows for every pixel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cost {
+    // objects the ray was transformed into and tested against, including groups and instances
+    IntersectionTests,
+    // bounding boxes of groups the ray was tested against, the nodes of the hierarchy it visited
+    BoundsTests,
+}
+
+impl Camera {
+    // the work needed to find the surfaces hit by the ray through the center of every pixel, for
+    // finding the parts of a scene that need better grouping. Only the primary rays are counted,
+    // shadows and reflections are not traced
+    pub fn render_cost(&self, world: &World, cost: Cost) -> Vec2D<Float> {
+        let counter = match cost {
+            Cost::IntersectionTests => &INTERSECTION_TESTS,
+            Cost::BoundsTests => &BOUNDS_TESTS,
+        };
+        let _counting = CostRender::start();
+        self.render_pass(0.0, |x, y| {
+            if self.outside_of_image(x, y) {
+                return 0.0;
+            }
+            counter.with(|count| count.set(0));
+            world.intersect_world(&self.ray_for_pixel(x, y));
+            counter.with(|count| count.get()) as Float
+        })
+    }
+}
+
+// colors spaced evenly between 0 and 1 that are blended linearly
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorRamp {
+    pub stops: Vec<Color>,
+}
+
+impl ColorRamp {
+    pub fn new(stops: Vec<Color>) -> ColorRamp {
+        assert!(!stops.is_empty(), "a color ramp needs at least one color");
+        ColorRamp { stops }
+    }
+
+    // black over blue, green and yellow to red
+    pub fn heat() -> ColorRamp {
+        ColorRamp::new(vec![
+            Color::new(0.0, 0.0, 0.0),
+            Color::new(0.0, 0.0, 1.0),
+            Color::new(0.0, 1.0, 0.0),
+            Color::new(1.0, 1.0, 0.0),
+            Color::new(1.0, 0.0, 0.0),
+        ])
+    }
+
+    pub fn grayscale() -> ColorRamp {
+        ColorRamp::new(vec![Color::new(0.0, 0.0, 0.0), Color::new(1.0, 1.0, 1.0)])
+    }
+
+    // t is clamped to [0, 1]
+    pub fn color_at(&self, t: Float) -> Color {
+        let position = t.clamp(0.0, 1.0) * (self.stops.len() - 1) as Float;
+        let idx = (position.floor() as usize).min(self.stops.len() - 1);
+        let Some(&next) = self.stops.get(idx + 1) else {
+            return self.stops[idx];
+        };
+        let fraction = position - idx as Float;
+        self.stops[idx] * (1.0 - fraction) + next * fraction
+    }
+}
+
+impl Default for ColorRamp {
+    fn default() -> Self {
+        ColorRamp::heat()
+    }
+}
+
+// "heat", "gray" or a comma separated list of hex colors like "#000000,#ff8000"
+impl FromStr for ColorRamp {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text.to_ascii_lowercase().as_str() {
+            "heat" => Ok(ColorRamp::heat()),
+            "gray" | "grey" | "grayscale" => Ok(ColorRamp::grayscale()),
+            _ => {
+                let stops = text
+                    .split(',')
+                    .map(|color| parse_hex_color(color.trim()))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(ColorRamp::new(stops))
+            }
+        }
+    }
+}
+
+fn parse_hex_color(text: &str) -> Result<Color, String> {
+    let invalid = || format!("invalid color {text:?}, expected a ramp name or #rrggbb colors");
+    let digits = text.strip_prefix('#').ok_or_else(invalid)?;
+    if digits.len() != 6 || !digits.is_ascii() {
+        return Err(invalid());
+    }
+    let channel = |idx: usize| {
+        u8::from_str_radix(&digits[idx..idx + 2], 16)
+            .map(|value| value as Float / 255.0)
+            .map_err(|_| invalid())
+    };
+    Ok(Color::new(channel(0)?, channel(2)?, channel(4)?))
+}
+
+impl Vec2D<Float> {
+    // values from 0 to max mapped onto the ramp, larger ones get the last color
+    pub fn to_heatmap(&self, ramp: &ColorRamp, max: Float) -> Canvas {
+        let mut canvas = Canvas::create_canvas(self.width(), self.height());
+        for y in 0..self.height() {
+            for x in 0..self.width() {
+                let t = if max > 0.0 {
+                    self.read_pixel(x, y) / max
+                } else {
+                    0.0
+                };
+                canvas.write_pixel(x, y, ramp.color_at(t));
+            }
+        }
+        canvas
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::test_camera;
+    use crate::Object;
+
+    #[test]
+    fn every_object_is_tested_without_groups() {
+        let mut world = World::new();
+        world.objects = vec![Object::sphere(), Object::sphere(), Object::plane()];
+        let cost = test_camera(11).render_cost(&world, Cost::IntersectionTests);
+        assert_eq!(cost.read_pixel(0, 0), 3.0);
+        assert_eq!(cost.read_pixel(5, 5), 3.0);
+        let bounds = test_camera(11).render_cost(&world, Cost::BoundsTests);
+        assert_eq!(bounds.read_pixel(5, 5), 0.0);
+    }
+
+    #[test]
+    fn groups_skip_their_children_outside_of_the_bounds() {
+        let mut world = World::new();
+        world.objects = vec![Object::group(vec![Object::sphere(), Object::cube()])];
+        let cost = test_camera(11).render_cost(&world, Cost::IntersectionTests);
+        // the group and both children at the center, only the group in the corner
+        assert_eq!(cost.read_pixel(5, 5), 3.0);
+        assert_eq!(cost.read_pixel(0, 0), 1.0);
+        let bounds = test_camera(11).render_cost(&world, Cost::BoundsTests);
+        assert_eq!(bounds.read_pixel(0, 0), 1.0);
+    }
+
+    #[test]
+    fn colors_along_the_ramp() {
+        let ramp = ColorRamp::heat();
+        assert_eq!(ramp.color_at(0.0), Color::new(0.0, 0.0, 0.0));
+        assert_eq!(ramp.color_at(0.125), Color::new(0.0, 0.0, 0.5));
+        assert_eq!(ramp.color_at(1.0), Color::new(1.0, 0.0, 0.0));
+        assert_eq!(ramp.color_at(7.0), Color::new(1.0, 0.0, 0.0));
+        assert_eq!(
+            ColorRamp::new(vec![Color::new(0.2, 0.2, 0.2)]).color_at(0.5),
+            Color::new(0.2, 0.2, 0.2)
+        );
+    }
+
+    #[test]
+    fn parsing_ramps() {
+        assert_eq!("heat".parse(), Ok(ColorRamp::heat()));
+        assert_eq!("Gray".parse(), Ok(ColorRamp::grayscale()));
+        assert_eq!(
+            "#000000, #ff0000".parse(),
+            Ok(ColorRamp::new(vec![
+                Color::new(0.0, 0.0, 0.0),
+                Color::new(1.0, 0.0, 0.0)
+            ]))
+        );
+        assert!("#ff00".parse::<ColorRamp>().is_err());
+        assert!("rainbow".parse::<ColorRamp>().is_err());
+    }
+
+    #[test]
+    fn heatmap_scales_to_max() {
+        let mut cost = Vec2D::new(2, 1, 0.0);
+        cost.write_pixel(1, 0, 4.0);
+        let canvas = cost.to_heatmap(&ColorRamp::grayscale(), 8.0);
+        assert_eq!(canvas.read_pixel(0, 0), Color::new(0.0, 0.0, 0.0));
+        assert_eq!(canvas.read_pixel(1, 0), Color::new(0.5, 0.5, 0.5));
+    }
+}
@@ -0,0 +1,529 @@
+use rand::Rng;
+
+use crate::math::consts::PI;
+use crate::math::{Float, Tuple4D};
+use crate::normal_map::orthonormal_basis;
+use crate::pixel_debug::{self, TraceEvent};
+use crate::{
+    hit, AreaLight, Color, Computations, Intersection, IntersectionBuffer, Light, Ray, World,
+};
+
+// how the color seen along a camera ray is computed
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Integrator {
+    // phong shading with recursive mirror reflection and refraction, noise free
+    #[default]
+    Whitted,
+    // unbiased monte carlo path tracing with next event estimation, needs many samples per pixel.
+    // Ambient, specular and shininess of materials as well as volumes are ignored, area lights and
+    // emissive surfaces light the scene
+    PathTracing {
+        max_bounces: usize,
+        // upper bound for the brightest channel of light that reached the camera over more than one
+        // surface. Trades a little energy for the removal of fireflies from rarely sampled bright paths
+        indirect_clamp: Option<Float>,
+    },
+    // grayscale image of how much of the hemisphere above each hit point is open, checked with cosine
+    // distributed rays of the given length. Rays that hit nothing are white
+    AmbientOcclusion {
+        samples: usize,
+        distance: Float,
+    },
+}
+
+impl Integrator {
+    pub fn path_tracing(max_bounces: usize) -> Integrator {
+        Integrator::PathTracing {
+            max_bounces,
+            indirect_clamp: None,
+        }
+    }
+
+    pub fn radiance(&self, world: &World, ray: &Ray, rng: &mut impl Rng) -> Color {
+        self.radiance_of_intersections(world, ray, &world.intersect_world(ray), rng)
+    }
+
+    // radiance with the intersections of the camera ray already found
+    pub(crate) fn radiance_of_intersections(
+        &self,
+        world: &World,
+        ray: &Ray,
+        intersections: &[Intersection],
+        rng: &mut impl Rng,
+    ) -> Color {
+        match *self {
+            Integrator::Whitted => world.trace_intersections(
+                ray,
+                intersections,
+                world.max_depth,
+                &mut IntersectionBuffer::new(),
+            ),
+            Integrator::PathTracing {
+                max_bounces,
+                indirect_clamp,
+            } => trace_path(world, ray, intersections, max_bounces, indirect_clamp, rng),
+            Integrator::AmbientOcclusion { samples, distance } => {
+                ambient_occlusion(world, ray, intersections, samples, distance, rng)
+            }
+        }
+    }
+}
+
+fn ambient_occlusion(
+    world: &World,
+    ray: &Ray,
+    intersections: &[Intersection],
+    samples: usize,
+    distance: Float,
+    rng: &mut impl Rng,
+) -> Color {
+    pixel_debug::record_ray(0, ray);
+    let Some(surface_hit) = hit(intersections) else {
+        let background = Color::new(1.0, 1.0, 1.0);
+        pixel_debug::record(|| TraceEvent::Miss {
+            depth: 0,
+            background,
+        });
+        return background;
+    };
+    let comps = surface_hit.prepare_computations_with_offset(ray, intersections, world.hit_offset);
+    pixel_debug::record_hit(0, &comps);
+    let samples = samples.max(1);
+    let open = (0..samples)
+        .filter(|_| {
+            let (direction, _) = sample_cosine_hemisphere(comps.normalv, rng);
+            is_visible(world, comps.over_point, direction, distance, comps.time)
+        })
+        .count();
+    let value = open as Float / samples as Float;
+    Color::new(value, value, value)
+}
+
+// first_intersections are the intersections of the camera ray
+fn trace_path(
+    world: &World,
+    ray: &Ray,
+    first_intersections: &[Intersection],
+    max_bounces: usize,
+    indirect_clamp: Option<Float>,
+    rng: &mut impl Rng,
+) -> Color {
+    // surfaces is the number of surfaces the light was scattered by before reaching the camera
+    let clamp = |contribution: Color, surfaces: usize| match indirect_clamp {
+        Some(max) if surfaces > 1 => clamp_brightness(contribution, max),
+        _ => contribution,
+    };
+    let black = Color::new(0.0, 0.0, 0.0);
+    let mut radiance = black;
+    let mut throughput = Color::new(1.0, 1.0, 1.0);
+    let mut ray = *ray;
+    // pdf of the diffuse bounce that produced the ray, None for camera rays and specular bounces
+    // which can not be importance sampled by light sampling
+    let mut bsdf_pdf: Option<Float> = None;
+
+    for bounce in 0..=max_bounces {
+        pixel_debug::record_ray(bounce, &ray);
+        let traced;
+        let intersections = if bounce == 0 {
+            first_intersections
+        } else {
+            traced = world.intersect_world(&ray);
+            &traced
+        };
+        let surface_hit = hit(intersections);
+        let surface_t = surface_hit.map_or(Float::INFINITY, |hit| hit.t);
+
+        if let Some((light, t)) = nearest_area_light(world, &ray, surface_t) {
+            pixel_debug::record(|| TraceEvent::AreaLight { depth: bounce, t });
+            let weight = match bsdf_pdf {
+                Some(bsdf_pdf) => {
+                    let light_pdf = area_light_pdf(light, ray.position(t), ray.origin);
+                    power_heuristic(bsdf_pdf, light_pdf)
+                }
+                None => 1.0,
+            };
+            radiance = radiance + clamp(throughput.mul_color(light.intensity) * weight, bounce);
+            break;
+        }
+
+        let Some(surface_hit) = surface_hit else {
+            let background = world.background.color_for(ray.direction);
+            pixel_debug::record(|| TraceEvent::Miss {
+                depth: bounce,
+                background,
+            });
+            radiance = radiance + clamp(throughput.mul_color(background), bounce);
+            break;
+        };
+        let comps =
+            surface_hit.prepare_computations_with_offset(&ray, intersections, world.hit_offset);
+        pixel_debug::record_hit(bounce, &comps);
+        if let Some(medium) = comps.medium {
+            throughput = throughput.mul_color(medium.transmittance(comps.t));
+        }
+        let material = comps.material;
+        radiance = radiance + clamp(throughput.mul_color(material.emissive), bounce);
+        if bounce == max_bounces {
+            break;
+        }
+
+        // pick one of the lobes proportional to its weight, the division by the probability cancels out
+        let reflective = material.reflective.clamp(0.0, 1.0);
+        let transparency = material.transparency.clamp(0.0, 1.0 - reflective);
+        let choice: Float = rng.random();
+        if choice < reflective {
+            let direction = material.glossy_reflection(comps.reflectv, comps.normalv, rng);
+            ray = Ray::new(comps.over_point, direction).with_time(comps.time);
+            bsdf_pdf = None;
+        } else if choice < reflective + transparency {
+            ray = refracted_or_reflected_ray(&comps, rng);
+            bsdf_pdf = None;
+        } else {
+            let albedo = material.color_at_filtered(
+                comps.world_to_object,
+                comps.over_point,
+                comps.footprint,
+            ) * material.diffuse;
+            let direct = throughput
+                .mul_color(albedo)
+                .mul_color(sample_lights(world, &comps, rng));
+            radiance = radiance + clamp(direct, bounce + 1);
+            let (direction, pdf) = sample_cosine_hemisphere(comps.normalv, rng);
+            ray = Ray::new(comps.over_point, direction).with_time(comps.time);
+            // lambertian brdf albedo / pi times cos over the cosine weighted pdf leaves the albedo
+            throughput = throughput.mul_color(albedo);
+            bsdf_pdf = Some(pdf);
+        }
+    }
+    radiance
+}
+
+// scales the color down so that no channel is brighter than max, keeping its hue
+fn clamp_brightness(color: Color, max: Float) -> Color {
+    let brightest = color.r.max(color.g).max(color.b);
+    if brightest <= max {
+        return color;
+    }
+    color * (max / brightest)
+}
+
+// direct light arriving at the hit point, weighted by cos / pi like a lambertian surface
+fn sample_lights(world: &World, comps: &Computations, rng: &mut impl Rng) -> Color {
+    let point = comps.over_point;
+    world
+        .lights
+        .iter()
+        .map(|light| match light {
+            Light::Area(area) => {
+                let target = area.point_at(rng.random(), rng.random());
+                let to_light = target - point;
+                let distance = to_light.magnitude();
+                let direction = to_light / distance;
+                let cos = direction.dot(comps.normalv);
+                if cos <= 0.0 || !is_visible(world, point, direction, distance, comps.time) {
+                    return Color::new(0.0, 0.0, 0.0);
+                }
+                let light_pdf = area_light_pdf(area, target, point);
+                if !light_pdf.is_finite() {
+                    return Color::new(0.0, 0.0, 0.0);
+                }
+                let weight = power_heuristic(light_pdf, cos / PI);
+                area.intensity * (cos / PI * weight / light_pdf)
+            }
+            // delta lights can only be reached by light sampling, they keep the whitted brightness
+            _ => {
+                let (direction, distance) = light.direction_from(point);
+                let cos = direction.dot(comps.normalv);
+                if cos <= 0.0 || !is_visible(world, point, direction, distance, comps.time) {
+                    return Color::new(0.0, 0.0, 0.0);
+                }
+                light.intensity_at(point) * cos
+            }
+        })
+        .fold(Color::new(0.0, 0.0, 0.0), |sum, color| sum + color)
+}
+
+fn is_visible(
+    world: &World,
+    point: Tuple4D,
+    direction: Tuple4D,
+    distance: Float,
+    time: Float,
+) -> bool {
+    let ray = Ray::new(point, direction).with_time(time);
+    !world.is_occluded(&ray, distance)
+}
+
+fn nearest_area_light<'a>(
+    world: &'a World,
+    ray: &Ray,
+    max_t: Float,
+) -> Option<(&'a AreaLight, Float)> {
+    world
+        .lights
+        .iter()
+        .filter_map(|light| match light {
+            Light::Area(area) => area.intersect(ray).map(|t| (area, t)),
+            _ => None,
+        })
+        .filter(|&(_, t)| t >= 0.0 && t < max_t)
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+}
+
+// probability density of sampling light_point from point, per solid angle
+fn area_light_pdf(light: &AreaLight, light_point: Tuple4D, point: Tuple4D) -> Float {
+    let to_light = light_point - point;
+    let distance_squared = to_light.dot(to_light);
+    let cos_light = (to_light / distance_squared.sqrt())
+        .dot(light.normal())
+        .abs();
+    distance_squared / (cos_light * light.area())
+}
+
+// weight of a sample taken with the first strategy when both strategies could have produced it
+fn power_heuristic(pdf: Float, other_pdf: Float) -> Float {
+    let (a, b) = (pdf * pdf, other_pdf * other_pdf);
+    if a + b == 0.0 {
+        return 0.0;
+    }
+    a / (a + b)
+}
+
+// returns the direction and its pdf cos / pi
+fn sample_cosine_hemisphere(normal: Tuple4D, rng: &mut impl Rng) -> (Tuple4D, Float) {
+    let (u, v): (Float, Float) = (rng.random(), rng.random());
+    let radius = u.sqrt();
+    let angle = 2.0 * PI * v;
+    let (tangent, bitangent) = orthonormal_basis(normal);
+    let cos = (1.0 - u).max(0.0).sqrt();
+    let direction =
+        (tangent * (radius * angle.cos()) + bitangent * (radius * angle.sin()) + normal * cos)
+            .normalize();
+    (direction, cos / PI)
+}
+
+// chooses between reflection and refraction with the schlick approximation as probability
+fn refracted_or_reflected_ray(comps: &Computations, rng: &mut impl Rng) -> Ray {
+    let n_ratio = comps.n1 / comps.n2;
+    let cos_i = comps.eyev.dot(comps.normalv);
+    let sin2_t = n_ratio * n_ratio * (1.0 - cos_i * cos_i);
+    let reflect = sin2_t > 1.0 || rng.random::<Float>() < comps.schlick();
+    if reflect {
+        return Ray::new(comps.over_point, comps.reflectv).with_time(comps.time);
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    let direction = comps.normalv * (n_ratio * cos_i - cos_t) - comps.eyev * n_ratio;
+    Ray::new(comps.under_point, direction).with_time(comps.time)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::SmallRng;
+    use rand::SeedableRng;
+
+    use crate::math::Matrix4;
+    use crate::{Background, Object, PointLight};
+
+    use super::*;
+
+    #[test]
+    fn whitted_integrator_matches_color_at() {
+        let world = World::default_world();
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 0.0, -5.0),
+            Tuple4D::new_vector(0.0, 0.0, 1.0),
+        );
+        let mut rng = SmallRng::seed_from_u64(0);
+        assert_eq!(
+            Integrator::Whitted.radiance(&world, &ray, &mut rng),
+            world.color_at(&ray)
+        );
+    }
+
+    #[test]
+    fn path_tracing_sees_emissive_surfaces() {
+        let mut world = World::new();
+        let mut sphere = Object::sphere();
+        sphere.material.emissive = Color::new(0.5, 1.0, 2.0);
+        sphere.material.diffuse = 0.0;
+        world.objects.push(sphere);
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 0.0, -5.0),
+            Tuple4D::new_vector(0.0, 0.0, 1.0),
+        );
+        let integrator = Integrator::path_tracing(3);
+        let mut rng = SmallRng::seed_from_u64(0);
+        assert_eq!(
+            integrator.radiance(&world, &ray, &mut rng),
+            Color::new(0.5, 1.0, 2.0)
+        );
+    }
+
+    #[test]
+    fn area_light_is_visible_to_camera_rays() {
+        let mut world = World::new();
+        world.lights.push(
+            AreaLight::new(
+                Tuple4D::new_point(-1.0, -1.0, 0.0),
+                Tuple4D::new_vector(2.0, 0.0, 0.0),
+                Tuple4D::new_vector(0.0, 2.0, 0.0),
+                Color::new(3.0, 3.0, 3.0),
+            )
+            .into(),
+        );
+        let ray = Ray::new(
+            Tuple4D::new_point(0.5, 0.5, -5.0),
+            Tuple4D::new_vector(0.0, 0.0, 1.0),
+        );
+        let integrator = Integrator::path_tracing(3);
+        let mut rng = SmallRng::seed_from_u64(0);
+        assert_eq!(
+            integrator.radiance(&world, &ray, &mut rng),
+            Color::new(3.0, 3.0, 3.0)
+        );
+    }
+
+    #[test]
+    fn direct_light_from_point_light_matches_lambert() {
+        let mut world = World::new();
+        world.lights.push(
+            PointLight::new(
+                Tuple4D::new_point(0.0, 10.0, 0.0),
+                Color::new(1.0, 1.0, 1.0),
+            )
+            .into(),
+        );
+        world.objects.push(Object::plane());
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 1.0, -1.0),
+            Tuple4D::new_vector(0.0, -1.0, 1.0).normalize(),
+        );
+        // the light is straight above the hit point and the bounced ray escapes, only diffuse 0.9 is left
+        let integrator = Integrator::path_tracing(1);
+        let mut rng = SmallRng::seed_from_u64(0);
+        assert_eq!(
+            integrator.radiance(&world, &ray, &mut rng),
+            Color::new(0.9, 0.9, 0.9)
+        );
+    }
+
+    #[test]
+    fn light_and_bsdf_sampling_estimate_the_same_area_light() {
+        // a diffuse floor under a large area light, estimated with many paths
+        let mut world = World::new();
+        world.lights.push(
+            AreaLight::new(
+                Tuple4D::new_point(-2.0, 2.0, -2.0),
+                Tuple4D::new_vector(4.0, 0.0, 0.0),
+                Tuple4D::new_vector(0.0, 0.0, 4.0),
+                Color::new(1.0, 1.0, 1.0),
+            )
+            .into(),
+        );
+        let mut floor = Object::plane();
+        floor.material.diffuse = 1.0;
+        world.objects.push(floor);
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 1.0, -1.0),
+            Tuple4D::new_vector(0.0, -1.0, 1.0).normalize(),
+        );
+        let integrator = Integrator::path_tracing(1);
+        let mut rng = SmallRng::seed_from_u64(3);
+        let samples = 4000;
+        let sum = (0..samples).fold(Color::new(0.0, 0.0, 0.0), |sum, _| {
+            sum + integrator.radiance(&world, &ray, &mut rng)
+        });
+        let estimate = sum.r / samples as Float;
+        // irradiance of a square of half size 2 at distance 2 centered above a point divided by pi
+        let expected = {
+            let (a, b, c): (Float, Float, Float) = (2.0, 2.0, 2.0);
+            let corner = |x: Float, y: Float| {
+                (x / (x * x + c * c).sqrt()) * (y / (y * y + c * c).sqrt()).atan()
+                    + (y / (y * y + c * c).sqrt()) * (x / (x * x + c * c).sqrt()).atan()
+            };
+            4.0 * corner(a, b) / (2.0 * PI)
+        };
+        assert!(
+            (estimate - expected).abs() < 0.02,
+            "{estimate} vs {expected}"
+        );
+    }
+
+    #[test]
+    fn clamping_keeps_hue() {
+        let color = clamp_brightness(Color::new(8.0, 4.0, 2.0), 2.0);
+        assert_eq!(color, Color::new(2.0, 1.0, 0.5));
+        let dim = Color::new(0.5, 0.2, 0.1);
+        assert_eq!(clamp_brightness(dim, 2.0), dim);
+    }
+
+    #[test]
+    fn clamp_only_limits_indirect_light() {
+        // a bright sky seen through one mirror is direct light, seen through two mirrors it is indirect
+        let mut world = World::new();
+        world.background = Background::Solid(Color::new(100.0, 100.0, 100.0));
+        let mut floor = Object::plane();
+        floor.material.reflective = 1.0;
+        world.objects.push(floor);
+        let mut wall = Object::plane();
+        wall.transform =
+            (Matrix4::translation(1.0, 0.0, 0.0) * Matrix4::rotation_z(PI / 2.0)).into();
+        wall.material.reflective = 1.0;
+        world.objects.push(wall);
+        let integrator = Integrator::PathTracing {
+            max_bounces: 3,
+            indirect_clamp: Some(10.0),
+        };
+        let mut rng = SmallRng::seed_from_u64(0);
+
+        let one_mirror = Ray::new(
+            Tuple4D::new_point(0.0, 1.0, 0.0),
+            Tuple4D::new_vector(-0.5, -1.0, 0.0).normalize(),
+        );
+        assert_eq!(
+            integrator.radiance(&world, &one_mirror, &mut rng),
+            Color::new(100.0, 100.0, 100.0)
+        );
+        let two_mirrors = Ray::new(
+            Tuple4D::new_point(0.0, 1.0, 0.0),
+            Tuple4D::new_vector(0.5, -1.0, 0.0).normalize(),
+        );
+        assert_eq!(
+            integrator.radiance(&world, &two_mirrors, &mut rng),
+            Color::new(10.0, 10.0, 10.0)
+        );
+    }
+
+    #[test]
+    fn ambient_occlusion_of_open_and_covered_points() {
+        let mut world = World::new();
+        world.objects.push(Object::plane());
+        let integrator = Integrator::AmbientOcclusion {
+            samples: 16,
+            distance: 1.0,
+        };
+        let mut rng = SmallRng::seed_from_u64(0);
+        let down = Ray::new(
+            Tuple4D::new_point(0.0, 1.0, 0.0),
+            Tuple4D::new_vector(0.0, -1.0, 0.0),
+        );
+        assert_eq!(
+            integrator.radiance(&world, &down, &mut rng),
+            Color::new(1.0, 1.0, 1.0)
+        );
+
+        // a second plane right above the floor closes the hemisphere
+        let mut ceiling = Object::plane();
+        ceiling.transform = Matrix4::translation(0.0, 0.1, 0.0).into();
+        world.objects.push(ceiling);
+        let below_ceiling = Ray::new(
+            Tuple4D::new_point(0.0, 0.05, -1.0),
+            Tuple4D::new_vector(0.0, -0.05, 1.0).normalize(),
+        );
+        assert_eq!(
+            integrator.radiance(&world, &below_ceiling, &mut rng),
+            Color::new(0.0, 0.0, 0.0)
+        );
+    }
+}
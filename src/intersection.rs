@@ -0,0 +1,518 @@
+use crate::math::{ApproxEq, Float, Matrix4, Tolerance, Tuple4D, FLOAT_EQ_EPS};
+use crate::{Material, Object, Ray};
+
+// how far the points of Computations are moved off the surface, see World::hit_offset
+pub const DEFAULT_HIT_OFFSET: Float = FLOAT_EQ_EPS;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Intersection<'a> {
+    pub t: Float,
+    pub object: &'a Object,
+    // transforms world space into the space of the group object is in, None outside of groups
+    pub world_to_parent: Option<Matrix4>,
+    // material the surface is shaded with, the one of object unless an instance overrides it
+    pub material: &'a Material,
+}
+
+// lists of intersections that are reused for the rays traced through one world. A ray takes a list
+// and hands it back once it is shaded, so reflections and shadow rays cast in between get their
+// own lists and after the first few rays nothing has to be allocated anymore
+#[derive(Debug, Default)]
+pub struct IntersectionBuffer<'a> {
+    free: Vec<Vec<Intersection<'a>>>,
+}
+
+impl<'a> IntersectionBuffer<'a> {
+    pub fn new() -> IntersectionBuffer<'a> {
+        IntersectionBuffer::default()
+    }
+
+    pub(crate) fn take(&mut self) -> Vec<Intersection<'a>> {
+        self.free.pop().unwrap_or_default()
+    }
+
+    pub(crate) fn give_back(&mut self, mut intersections: Vec<Intersection<'a>>) {
+        intersections.clear();
+        self.free.push(intersections);
+    }
+}
+
+#[derive(Debug)]
+pub struct Computations<'a> {
+    pub t: Float,
+    pub object: &'a Object,
+    pub material: &'a Material,
+    // transforms world space into the space of object at the time of the ray
+    pub world_to_object: Matrix4,
+    pub point: Tuple4D,
+    pub over_point: Tuple4D,
+    pub under_point: Tuple4D,
+    pub eyev: Tuple4D,
+    pub normalv: Tuple4D,
+    pub reflectv: Tuple4D,
+    pub inside: bool,
+    // refractive indices of the materials the ray exits (n1) and enters (n2)
+    pub n1: Float,
+    pub n2: Float,
+    // material of the object the ray travelled through before the hit, None if it came through
+    // empty space
+    pub medium: Option<&'a Material>,
+    // time of the ray, secondary rays are cast at the same time
+    pub time: Float,
+    // width of the ray cone where it meets the surface and its growth per unit distance,
+    // secondary rays continue the cone from here
+    pub footprint: Float,
+    pub spread: Float,
+}
+
+impl<'a> Intersection<'a> {
+    pub fn new(t: Float, object: &'a Object) -> Intersection<'a> {
+        Intersection {
+            t,
+            object,
+            world_to_parent: None,
+            material: &object.material,
+        }
+    }
+
+    // the intersection seen from outside of a group whose inverse transform is group_inverse
+    pub(crate) fn inside_group(self, group_inverse: Matrix4) -> Intersection<'a> {
+        let world_to_parent = match self.world_to_parent {
+            Some(to_parent) => to_parent * group_inverse,
+            None => group_inverse,
+        };
+        Intersection {
+            world_to_parent: Some(world_to_parent),
+            ..self
+        }
+    }
+
+    // the intersection seen from outside of an instance that shades its geometry with material
+    pub(crate) fn with_material(self, material: &'a Material) -> Intersection<'a> {
+        Intersection { material, ..self }
+    }
+
+    // whether the surface is hit between the origin of the ray and distance, volumes let light through
+    pub(crate) fn blocks(&self, distance: Float) -> bool {
+        self.t >= 0.0 && self.t < distance && self.material.volume.is_none()
+    }
+
+    pub fn world_to_object(&self, time: Float) -> Matrix4 {
+        let inverse = self.object.inverse_transform(time);
+        match self.world_to_parent {
+            Some(to_parent) => inverse * to_parent,
+            None => inverse,
+        }
+    }
+
+    // intersections is the sorted list of all intersections along the ray,
+    // it is needed to find out which objects the hit is nested in
+    pub fn prepare_computations(
+        &self,
+        ray: &Ray,
+        intersections: &[Intersection<'a>],
+    ) -> Computations<'a> {
+        self.prepare_computations_with_offset(ray, intersections, DEFAULT_HIT_OFFSET)
+    }
+
+    // over_point and under_point are offset from the surface by offset instead of the default
+    pub fn prepare_computations_with_offset(
+        &self,
+        ray: &Ray,
+        intersections: &[Intersection<'a>],
+        offset: Float,
+    ) -> Computations<'a> {
+        let point = ray.position(self.t);
+        let eyev = -ray.direction;
+        let world_to_object = self.world_to_object(ray.time);
+        let mut normalv = self
+            .object
+            .normal_with_inverse(point, world_to_object, self.material);
+        let inside = normalv.dot(eyev) < 0.0;
+        if inside {
+            normalv = -normalv;
+        }
+        // nudge the point slightly above the surface to avoid self-shadowing (acne)
+        let over_point = point + normalv * offset;
+        let under_point = point - normalv * offset;
+        let reflectv = ray.direction.reflect(normalv);
+        let (n1, n2, medium) = self.refractive_indices(intersections);
+        // the cone is stretched along the surface at grazing angles, the geometric mean of the
+        // stretched and the unstretched width keeps the filtering isotropic without blurring too much
+        let cos = eyev.dot(normalv).max(FLOAT_EQ_EPS);
+        let footprint = ray.footprint_at(self.t) / cos.sqrt();
+        Computations {
+            t: self.t,
+            object: self.object,
+            material: self.material,
+            world_to_object,
+            point,
+            over_point,
+            under_point,
+            eyev,
+            normalv,
+            reflectv,
+            inside,
+            n1,
+            n2,
+            medium,
+            time: ray.time,
+            footprint,
+            spread: ray.spread,
+        }
+    }
+
+    fn refractive_indices(
+        &self,
+        intersections: &[Intersection<'a>],
+    ) -> (Float, Float, Option<&'a Material>) {
+        let mut containers: Vec<&Intersection<'a>> = vec![];
+        let mut n1 = 1.0;
+        let mut n2 = 1.0;
+        let mut medium = None;
+        for intersection in intersections {
+            let is_hit = self.t == intersection.t && self.is_same_object(intersection);
+            if is_hit {
+                medium = containers.last().map(|container| container.material);
+                n1 = medium.map_or(1.0, |material| material.refractive_index);
+            }
+
+            match containers
+                .iter()
+                .position(|container| container.is_same_object(intersection))
+            {
+                Some(idx) => {
+                    containers.remove(idx);
+                }
+                None => containers.push(intersection),
+            }
+
+            if is_hit {
+                n2 = containers
+                    .last()
+                    .map_or(1.0, |container| container.material.refractive_index);
+                break;
+            }
+        }
+        (n1, n2, medium)
+    }
+
+    // instances share their objects, so the same object in two instances differs in the transforms
+    // of the groups around it. Both hits of an object went through the same groups, so their
+    // transforms are bit for bit the same and are compared exactly
+    pub(crate) fn is_same_object(&self, other: &Intersection) -> bool {
+        std::ptr::eq(self.object, other.object)
+            && match (&self.world_to_parent, &other.world_to_parent) {
+                (Some(a), Some(b)) => a.approx_eq(b, Tolerance::Ulps(0)),
+                (None, None) => true,
+                _ => false,
+            }
+    }
+}
+
+impl Computations<'_> {
+    // Schlick's approximation of the Fresnel equations, returns the fraction of light that is reflected
+    pub fn schlick(&self) -> Float {
+        let mut cos = self.eyev.dot(self.normalv);
+        if self.n1 > self.n2 {
+            let n_ratio = self.n1 / self.n2;
+            let sin2_t = n_ratio * n_ratio * (1.0 - cos * cos);
+            // total internal reflection
+            if sin2_t > 1.0 {
+                return 1.0;
+            }
+            cos = (1.0 - sin2_t).sqrt();
+        }
+        let r0 = ((self.n1 - self.n2) / (self.n1 + self.n2)).powi(2);
+        r0 + (1.0 - r0) * (1.0 - cos).powi(5)
+    }
+}
+
+// the hit is the intersection with the lowest non-negative t value, boundaries of volumes are skipped
+pub fn hit<'a>(intersections: &[Intersection<'a>]) -> Option<Intersection<'a>> {
+    intersections
+        .iter()
+        .filter(|intersection| intersection.t >= 0.0 && intersection.material.volume.is_none())
+        .min_by(|a, b| a.t.total_cmp(&b.t))
+        .copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::consts::PI;
+
+    use crate::math::{float_eq, Matrix4};
+
+    use super::*;
+
+    #[test]
+    fn intersection_encapsulates_t_and_object() {
+        let sphere = Object::sphere();
+        let intersection = Intersection::new(3.5, &sphere);
+        assert_eq!(intersection.t, 3.5);
+        assert!(std::ptr::eq(intersection.object, &sphere));
+    }
+
+    #[test]
+    fn hit_when_all_intersections_positive() {
+        let sphere = Object::sphere();
+        let xs = [
+            Intersection::new(1.0, &sphere),
+            Intersection::new(2.0, &sphere),
+        ];
+        assert_eq!(hit(&xs).unwrap().t, 1.0);
+    }
+
+    #[test]
+    fn hit_when_some_intersections_negative() {
+        let sphere = Object::sphere();
+        let xs = [
+            Intersection::new(-1.0, &sphere),
+            Intersection::new(1.0, &sphere),
+        ];
+        assert_eq!(hit(&xs).unwrap().t, 1.0);
+    }
+
+    #[test]
+    fn hit_when_all_intersections_negative() {
+        let sphere = Object::sphere();
+        let xs = [
+            Intersection::new(-2.0, &sphere),
+            Intersection::new(-1.0, &sphere),
+        ];
+        assert!(hit(&xs).is_none());
+    }
+
+    #[test]
+    fn hit_is_lowest_nonnegative_intersection() {
+        let sphere = Object::sphere();
+        let xs = [
+            Intersection::new(5.0, &sphere),
+            Intersection::new(7.0, &sphere),
+            Intersection::new(-3.0, &sphere),
+            Intersection::new(2.0, &sphere),
+        ];
+        assert_eq!(hit(&xs).unwrap().t, 2.0);
+    }
+
+    #[test]
+    fn precomputing_state_of_intersection() {
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 0.0, -5.0),
+            Tuple4D::new_vector(0.0, 0.0, 1.0),
+        );
+        let sphere = Object::sphere();
+        let comps = Intersection::new(4.0, &sphere).prepare_computations(&ray, &[]);
+        assert_eq!(comps.t, 4.0);
+        assert!(std::ptr::eq(comps.object, &sphere));
+        assert_eq!(comps.point, Tuple4D::new_point(0.0, 0.0, -1.0));
+        assert_eq!(comps.eyev, Tuple4D::new_vector(0.0, 0.0, -1.0));
+        assert_eq!(comps.normalv, Tuple4D::new_vector(0.0, 0.0, -1.0));
+        assert!(!comps.inside);
+    }
+
+    #[test]
+    fn hit_when_intersection_occurs_inside() {
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 0.0, 0.0),
+            Tuple4D::new_vector(0.0, 0.0, 1.0),
+        );
+        let sphere = Object::sphere();
+        let comps = Intersection::new(1.0, &sphere).prepare_computations(&ray, &[]);
+        assert_eq!(comps.point, Tuple4D::new_point(0.0, 0.0, 1.0));
+        assert_eq!(comps.eyev, Tuple4D::new_vector(0.0, 0.0, -1.0));
+        assert_eq!(comps.normalv, Tuple4D::new_vector(0.0, 0.0, -1.0));
+        assert!(comps.inside);
+    }
+
+    #[test]
+    fn precomputing_reflection_vector() {
+        let plane = Object::plane();
+        let value = Float::sqrt(2.0) / 2.0;
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 1.0, -1.0),
+            Tuple4D::new_vector(0.0, -value, value),
+        );
+        let comps = Intersection::new(Float::sqrt(2.0), &plane).prepare_computations(&ray, &[]);
+        assert_eq!(comps.reflectv, Tuple4D::new_vector(0.0, value, value));
+    }
+
+    #[test]
+    fn hit_offsets_point() {
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 0.0, -5.0),
+            Tuple4D::new_vector(0.0, 0.0, 1.0),
+        );
+        let mut sphere = Object::sphere();
+        sphere.transform = Matrix4::translation(0.0, 0.0, 1.0).into();
+        let comps = Intersection::new(5.0, &sphere).prepare_computations(&ray, &[]);
+        assert!(comps.over_point.z < -FLOAT_EQ_EPS / 2.0);
+        assert!(comps.point.z > comps.over_point.z);
+    }
+
+    #[test]
+    fn hit_offsets_can_be_chosen() {
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 0.0, -5.0),
+            Tuple4D::new_vector(0.0, 0.0, 1.0),
+        );
+        let sphere = Object::sphere();
+        let comps =
+            Intersection::new(4.0, &sphere).prepare_computations_with_offset(&ray, &[], 0.1);
+        assert_eq!(comps.over_point, Tuple4D::new_point(0.0, 0.0, -1.1));
+        assert_eq!(comps.under_point, Tuple4D::new_point(0.0, 0.0, -0.9));
+    }
+
+    #[test]
+    fn tuples_and_matrices_take_a_tolerance() {
+        let mut almost = Tuple4D::new_vector(1.0, 2.0, 3.0);
+        almost.w = 1e-3;
+        assert!(!almost.is_vector());
+        assert!(almost.is_vector_within(Tolerance::Absolute(1e-2)));
+        assert!(Tuple4D::new_point(1.0, 2.0, 3.0).is_point_within(Tolerance::Ulps(0)));
+        let tiny = Matrix4::scaling(1e-3, 1e-3, 1e-3);
+        assert!(tiny.is_invertible_within(Tolerance::Relative(0.0)));
+        // the rows of the shear are 45 degrees apart
+        let shear = Matrix4::shearing(1.0, 0.0, 0.0, 0.0, 0.0, 0.0);
+        assert!(shear.inverse_within(Tolerance::Absolute(0.5)).is_some());
+        assert!(shear.inverse_within(Tolerance::Absolute(0.8)).is_none());
+    }
+
+    fn glass_sphere() -> Object {
+        let mut sphere = Object::sphere();
+        sphere.material.transparency = 1.0;
+        sphere.material.refractive_index = 1.5;
+        sphere
+    }
+
+    #[test]
+    fn finding_n1_and_n2_at_various_intersections() {
+        let mut a = glass_sphere();
+        a.transform = Matrix4::scaling(2.0, 2.0, 2.0).into();
+        a.material.refractive_index = 1.5;
+        let mut b = glass_sphere();
+        b.transform = Matrix4::translation(0.0, 0.0, -0.25).into();
+        b.material.refractive_index = 2.0;
+        let mut c = glass_sphere();
+        c.transform = Matrix4::translation(0.0, 0.0, 0.25).into();
+        c.material.refractive_index = 2.5;
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 0.0, -4.0),
+            Tuple4D::new_vector(0.0, 0.0, 1.0),
+        );
+        let xs = [
+            Intersection::new(2.0, &a),
+            Intersection::new(2.75, &b),
+            Intersection::new(3.25, &c),
+            Intersection::new(4.75, &b),
+            Intersection::new(5.25, &c),
+            Intersection::new(6.0, &a),
+        ];
+        let expected = [
+            (1.0, 1.5),
+            (1.5, 2.0),
+            (2.0, 2.5),
+            (2.5, 2.5),
+            (2.5, 1.5),
+            (1.5, 1.0),
+        ];
+        for (intersection, (n1, n2)) in xs.iter().zip(expected) {
+            let comps = intersection.prepare_computations(&ray, &xs);
+            assert_eq!(comps.n1, n1);
+            assert_eq!(comps.n2, n2);
+        }
+    }
+
+    #[test]
+    fn under_point_is_offset_below_surface() {
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 0.0, -5.0),
+            Tuple4D::new_vector(0.0, 0.0, 1.0),
+        );
+        let mut sphere = glass_sphere();
+        sphere.transform = Matrix4::translation(0.0, 0.0, 1.0).into();
+        let xs = [Intersection::new(5.0, &sphere)];
+        let comps = xs[0].prepare_computations(&ray, &xs);
+        assert!(comps.under_point.z > FLOAT_EQ_EPS / 2.0);
+        assert!(comps.point.z < comps.under_point.z);
+    }
+
+    #[test]
+    fn schlick_under_total_internal_reflection() {
+        let sphere = glass_sphere();
+        let value = Float::sqrt(2.0) / 2.0;
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 0.0, value),
+            Tuple4D::new_vector(0.0, 1.0, 0.0),
+        );
+        let xs = [
+            Intersection::new(-value, &sphere),
+            Intersection::new(value, &sphere),
+        ];
+        let comps = xs[1].prepare_computations(&ray, &xs);
+        assert_eq!(comps.schlick(), 1.0);
+    }
+
+    #[test]
+    fn schlick_with_perpendicular_viewing_angle() {
+        let sphere = glass_sphere();
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 0.0, 0.0),
+            Tuple4D::new_vector(0.0, 1.0, 0.0),
+        );
+        let xs = [
+            Intersection::new(-1.0, &sphere),
+            Intersection::new(1.0, &sphere),
+        ];
+        let comps = xs[1].prepare_computations(&ray, &xs);
+        assert!(float_eq(comps.schlick(), 0.04, FLOAT_EQ_EPS));
+    }
+
+    #[test]
+    fn schlick_with_small_angle_and_n2_greater_n1() {
+        let sphere = glass_sphere();
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 0.99, -2.0),
+            Tuple4D::new_vector(0.0, 0.0, 1.0),
+        );
+        let xs = [Intersection::new(1.8589, &sphere)];
+        let comps = xs[0].prepare_computations(&ray, &xs);
+        assert!(float_eq(comps.schlick(), 0.48873, FLOAT_EQ_EPS));
+    }
+
+    #[test]
+    fn normal_on_child_of_nested_groups() {
+        let mut sphere = Object::sphere();
+        sphere.transform = Matrix4::translation(5.0, 0.0, 0.0).into();
+        let outer_inverse = Matrix4::rotation_y(PI / 2.0).inverse().unwrap();
+        let inner_inverse = Matrix4::scaling(1.0, 2.0, 3.0).inverse().unwrap();
+        let intersection = Intersection::new(1.0, &sphere)
+            .inside_group(inner_inverse)
+            .inside_group(outer_inverse);
+        let point = Tuple4D::new_point(1.7321, 1.1547, -5.5774);
+        let normal =
+            sphere.normal_with_inverse(point, intersection.world_to_object(0.0), &sphere.material);
+        assert_eq!(normal, Tuple4D::new_vector(0.28570, 0.42854, -0.85716));
+    }
+
+    #[test]
+    fn hit_inside_transformed_group() {
+        let mut sphere = Object::sphere();
+        sphere.transform = Matrix4::translation(5.0, 0.0, 0.0).into();
+        let mut group = Object::group(vec![sphere]);
+        group.transform = Matrix4::scaling(2.0, 2.0, 2.0).into();
+        let ray = Ray::new(
+            Tuple4D::new_point(10.0, 0.0, -10.0),
+            Tuple4D::new_vector(0.0, 0.0, 1.0),
+        );
+        let xs = group.intersect(&ray);
+        assert_eq!(xs.len(), 2);
+        let comps = xs[0].prepare_computations(&ray, &xs);
+        assert_eq!(comps.point, Tuple4D::new_point(10.0, 0.0, -2.0));
+        assert_eq!(comps.normalv, Tuple4D::new_vector(0.0, 0.0, -1.0));
+        assert_eq!(
+            comps.world_to_object * comps.point,
+            Tuple4D::new_point(0.0, 0.0, -1.0)
+        );
+    }
+}
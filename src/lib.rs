@@ -1,5 +1,110 @@
+// literals and conversions are written for double precision and only shortened with the f32 feature
+#![cfg_attr(
+    feature = "f32",
+    allow(clippy::excessive_precision, clippy::unnecessary_cast)
+)]
+
 mod color;
 pub mod math;
-pub use color::Color;
+pub use color::{Color, ColorEncoding};
+mod error;
+pub use error::Error;
 mod canvas;
-pub use canvas::Canvas;
+pub use canvas::{Canvas, ImageOptions, Region, Vec2D};
+mod animated_image;
+#[cfg(feature = "png")]
+pub use animated_image::write_apng;
+#[cfg(feature = "gif")]
+pub use animated_image::write_gif;
+pub use animated_image::{save_animated, AnimatedImageOptions};
+mod rgba_canvas;
+pub use rgba_canvas::RgbaCanvas;
+mod bounds;
+pub use bounds::Bounds;
+mod bvh;
+pub use bvh::{BvhCache, BvhLayout, DEFAULT_BVH_LEAF_SIZE};
+mod ray;
+pub use ray::Ray;
+mod object;
+pub mod shapes;
+pub use object::Object;
+mod obj;
+pub use obj::{parse_mtl_file, parse_obj_file, ObjFile, ObjMaterial, ObjTriangle};
+mod mesh;
+pub use mesh::{smooth_normals, vertex_normals};
+mod stl;
+pub use stl::{parse_stl_ascii, parse_stl_binary, parse_stl_file, StlFile};
+mod ply;
+pub use ply::{parse_ply_file, PlyFile};
+#[cfg(feature = "gltf")]
+mod gltf;
+#[cfg(feature = "gltf")]
+pub use self::gltf::{parse_gltf_file, GltfFile};
+mod intersection;
+pub use intersection::{hit, Computations, Intersection, IntersectionBuffer, DEFAULT_HIT_OFFSET};
+mod material;
+mod normal_map;
+pub use normal_map::NormalMap;
+pub mod patterns;
+pub use material::Material;
+mod light;
+pub use light::{
+    lighting, AreaLight, Attenuation, DirectionalLight, Light, PointLight, SpotLight,
+    MIN_ATTENUATION_DISTANCE,
+};
+mod volume;
+pub use volume::{DensityGrid, Volume};
+mod background;
+pub use background::Background;
+mod world;
+pub use world::{World, DEFAULT_GLOSSY_SAMPLES, DEFAULT_MAX_DEPTH};
+mod validation;
+pub use validation::SceneWarning;
+mod sampler;
+pub use sampler::{
+    BlueNoiseSampler, HaltonSampler, RandomSampler, SampleDimension, Sampler, StratifiedSampler,
+};
+mod progress;
+pub use progress::{CancellationToken, Progress};
+mod render_job;
+pub use render_job::RenderJob;
+mod integrator;
+pub use integrator::Integrator;
+mod packet;
+pub use packet::{RayPacket, PACKET_SIZE};
+mod camera;
+pub use camera::{Camera, Projection};
+mod animation;
+mod aov;
+pub use animation::{frame_path, Animation, CameraRig, Easing, Interpolate, Keyframe, Keyframes};
+mod denoise;
+pub use denoise::Denoiser;
+mod heatmap;
+pub use heatmap::{ColorRamp, Cost};
+mod pixel_debug;
+pub use pixel_debug::{PixelTrace, TraceEvent};
+mod post_effects;
+pub use post_effects::{Bloom, ChromaticAberration, Vignette};
+mod video;
+pub use video::{FfmpegWriter, FrameSink, Y4mWriter};
+#[cfg(feature = "gpu")]
+mod gpu;
+#[cfg(feature = "gpu")]
+pub use gpu::GpuRenderer;
+#[cfg(feature = "network")]
+mod network;
+#[cfg(feature = "network")]
+pub use network::{NetworkRenderer, RenderWorker, DEFAULT_MAX_PIXELS, DEFAULT_TILE_SIZE};
+#[cfg(feature = "preview")]
+mod preview;
+#[cfg(feature = "scene")]
+mod scene_file;
+pub mod scenes;
+#[cfg(feature = "preview")]
+pub use preview::PreviewWindow;
+#[cfg(feature = "scene")]
+pub use scene_file::{
+    parse_scene_json, parse_scene_toml, AttenuationDescription, BackgroundDescription,
+    CameraDescription, IntegratorDescription, LightDescription, MaterialDescription,
+    ObjectDescription, SceneFile, ShapeDescription, TransformDescription, ViewDescription,
+};
@@ -0,0 +1,695 @@
+use crate::math::{Float, Matrix4, Tuple4D, FLOAT_EQ_EPS};
+use crate::{Color, Material, Ray};
+
+// how the intensity of a light decreases with the distance d to it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Attenuation {
+    #[default]
+    None,
+    // 1 / d
+    Linear,
+    // 1 / d^2, physically correct for point lights
+    Quadratic,
+}
+
+// points closer to a light than this are lit as if they were at this distance, so the light stays
+// finite at its own position
+pub const MIN_ATTENUATION_DISTANCE: Float = 0.01;
+
+impl Attenuation {
+    pub fn factor(&self, distance: Float) -> Float {
+        let distance = distance.max(MIN_ATTENUATION_DISTANCE);
+        match self {
+            Attenuation::None => 1.0,
+            Attenuation::Linear => 1.0 / distance,
+            Attenuation::Quadratic => 1.0 / (distance * distance),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PointLight {
+    pub position: Tuple4D,
+    pub intensity: Color,
+    pub attenuation: Attenuation,
+}
+
+impl PointLight {
+    pub fn new(position: Tuple4D, intensity: Color) -> PointLight {
+        PointLight {
+            position,
+            intensity,
+            attenuation: Attenuation::None,
+        }
+    }
+
+    fn intensity_at(&self, point: Tuple4D) -> Color {
+        let distance = (self.position - point).magnitude();
+        self.intensity * self.attenuation.factor(distance)
+    }
+}
+
+// light source shining from position into direction, with a cone half-angle in radians.
+// The intensity drops off towards the edge of the cone with cos(angle)^falloff
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SpotLight {
+    pub position: Tuple4D,
+    pub direction: Tuple4D,
+    pub cone_angle: Float,
+    pub falloff: Float,
+    pub intensity: Color,
+    pub attenuation: Attenuation,
+}
+
+impl SpotLight {
+    pub fn new(
+        position: Tuple4D,
+        direction: Tuple4D,
+        cone_angle: Float,
+        falloff: Float,
+        intensity: Color,
+    ) -> SpotLight {
+        SpotLight {
+            position,
+            direction: direction.normalize(),
+            cone_angle,
+            falloff,
+            intensity,
+            attenuation: Attenuation::None,
+        }
+    }
+
+    fn intensity_at(&self, point: Tuple4D) -> Color {
+        let to_point = point - self.position;
+        let distance = to_point.magnitude();
+        let cos_angle = (to_point / distance).dot(self.direction);
+        if cos_angle < self.cone_angle.cos() {
+            return Color::new(0.0, 0.0, 0.0);
+        }
+        self.intensity * cos_angle.powf(self.falloff) * self.attenuation.factor(distance)
+    }
+}
+
+// infinitely far away light source like the sun, all rays arrive parallel
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DirectionalLight {
+    // direction the light travels in
+    pub direction: Tuple4D,
+    pub intensity: Color,
+}
+
+impl DirectionalLight {
+    pub fn new(direction: Tuple4D, intensity: Color) -> DirectionalLight {
+        DirectionalLight {
+            direction: direction.normalize(),
+            intensity,
+        }
+    }
+}
+
+// emitting parallelogram spanned by uvec and vvec from corner, it is visible from both sides.
+// The whitted renderer treats it like a point light in its center, path tracing samples its area
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AreaLight {
+    pub corner: Tuple4D,
+    pub uvec: Tuple4D,
+    pub vvec: Tuple4D,
+    pub intensity: Color,
+}
+
+impl AreaLight {
+    pub fn new(corner: Tuple4D, uvec: Tuple4D, vvec: Tuple4D, intensity: Color) -> AreaLight {
+        AreaLight {
+            corner,
+            uvec,
+            vvec,
+            intensity,
+        }
+    }
+
+    // u and v in [0, 1] select the point on the light
+    pub fn point_at(&self, u: Float, v: Float) -> Tuple4D {
+        self.corner + self.uvec * u + self.vvec * v
+    }
+
+    pub fn center(&self) -> Tuple4D {
+        self.point_at(0.5, 0.5)
+    }
+
+    pub fn area(&self) -> Float {
+        self.uvec.cross(self.vvec).magnitude()
+    }
+
+    pub fn normal(&self) -> Tuple4D {
+        self.uvec.cross(self.vvec).normalize()
+    }
+
+    pub fn intersect(&self, ray: &Ray) -> Option<Float> {
+        let normal = self.normal();
+        let denominator = ray.direction.dot(normal);
+        if denominator.abs() < FLOAT_EQ_EPS {
+            return None;
+        }
+        let t = (self.corner - ray.origin).dot(normal) / denominator;
+        let offset = ray.position(t) - self.corner;
+        // coordinates along the (not necessarily orthogonal) edges
+        let (uu, uv, vv) = (
+            self.uvec.dot(self.uvec),
+            self.uvec.dot(self.vvec),
+            self.vvec.dot(self.vvec),
+        );
+        let (ou, ov) = (offset.dot(self.uvec), offset.dot(self.vvec));
+        let determinant = uu * vv - uv * uv;
+        let u = (ou * vv - ov * uv) / determinant;
+        let v = (ov * uu - ou * uv) / determinant;
+        if (0.0..=1.0).contains(&u) && (0.0..=1.0).contains(&v) {
+            Some(t)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Light {
+    Point(PointLight),
+    Spot(SpotLight),
+    Directional(DirectionalLight),
+    Area(AreaLight),
+}
+
+impl From<AreaLight> for Light {
+    fn from(light: AreaLight) -> Self {
+        Light::Area(light)
+    }
+}
+
+impl From<PointLight> for Light {
+    fn from(light: PointLight) -> Self {
+        Light::Point(light)
+    }
+}
+
+impl From<SpotLight> for Light {
+    fn from(light: SpotLight) -> Self {
+        Light::Spot(light)
+    }
+}
+
+impl From<DirectionalLight> for Light {
+    fn from(light: DirectionalLight) -> Self {
+        Light::Directional(light)
+    }
+}
+
+impl Light {
+    // intensity of the light source itself, used for ambient lighting
+    pub fn intensity(&self) -> Color {
+        match self {
+            Light::Point(light) => light.intensity,
+            Light::Spot(light) => light.intensity,
+            Light::Directional(light) => light.intensity,
+            Light::Area(light) => light.intensity,
+        }
+    }
+
+    pub fn set_intensity(&mut self, intensity: Color) {
+        match self {
+            Light::Point(light) => light.intensity = intensity,
+            Light::Spot(light) => light.intensity = intensity,
+            Light::Directional(light) => light.intensity = intensity,
+            Light::Area(light) => light.intensity = intensity,
+        }
+    }
+
+    // intensity of the light arriving at point, ignoring shadows
+    pub fn intensity_at(&self, point: Tuple4D) -> Color {
+        match self {
+            Light::Point(light) => light.intensity_at(point),
+            Light::Spot(light) => light.intensity_at(point),
+            Light::Directional(light) => light.intensity,
+            Light::Area(light) => light.intensity,
+        }
+    }
+
+    // unit vector pointing from point towards the light and the distance to the light
+    pub fn direction_from(&self, point: Tuple4D) -> (Tuple4D, Float) {
+        let position = match self {
+            Light::Point(light) => light.position,
+            Light::Spot(light) => light.position,
+            Light::Area(light) => light.center(),
+            Light::Directional(light) => return (-light.direction, Float::INFINITY),
+        };
+        let to_light = position - point;
+        let distance = to_light.magnitude();
+        (to_light / distance, distance)
+    }
+}
+
+// Phong reflection model
+// time selects the pose of a moving object for the pattern lookup
+#[allow(clippy::too_many_arguments)]
+pub fn lighting(
+    material: &Material,
+    world_to_object: Matrix4,
+    light: &Light,
+    point: Tuple4D,
+    eyev: Tuple4D,
+    normalv: Tuple4D,
+    in_shadow: bool,
+    footprint: Float,
+) -> Color {
+    let black = Color::new(0.0, 0.0, 0.0);
+    let color = material.color_at_filtered(world_to_object, point, footprint);
+    let ambient = color.mul_color(light.intensity()) * material.ambient;
+    if in_shadow {
+        return ambient;
+    }
+
+    let (lightv, _) = light.direction_from(point);
+    let light_dot_normal = lightv.dot(normalv);
+    // light is on the other side of the surface
+    if light_dot_normal < 0.0 {
+        return ambient;
+    }
+    let incoming = light.intensity_at(point);
+    let diffuse = color.mul_color(incoming) * material.diffuse * light_dot_normal;
+
+    let reflectv = (-lightv).reflect(normalv);
+    let reflect_dot_eye = reflectv.dot(eyev);
+    let specular = if reflect_dot_eye <= 0.0 {
+        black
+    } else {
+        let factor = reflect_dot_eye.powf(material.shininess);
+        incoming * material.specular * factor
+    };
+
+    ambient + diffuse + specular
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::consts::PI;
+    use std::sync::Arc;
+
+    use crate::patterns::StripePattern;
+    use crate::Object;
+
+    use super::*;
+
+    fn setup() -> (Material, Object, Tuple4D) {
+        (
+            Material::default(),
+            Object::sphere(),
+            Tuple4D::new_point(0.0, 0.0, 0.0),
+        )
+    }
+
+    #[test]
+    fn point_light_has_position_and_intensity() {
+        let intensity = Color::new(1.0, 1.0, 1.0);
+        let position = Tuple4D::new_point(0.0, 0.0, 0.0);
+        let light = PointLight::new(position, intensity);
+        assert_eq!(light.position, position);
+        assert_eq!(light.intensity, intensity);
+    }
+
+    #[test]
+    fn eye_between_light_and_surface() {
+        let (material, object, position) = setup();
+        let eyev = Tuple4D::new_vector(0.0, 0.0, -1.0);
+        let normalv = Tuple4D::new_vector(0.0, 0.0, -1.0);
+        let light = PointLight::new(
+            Tuple4D::new_point(0.0, 0.0, -10.0),
+            Color::new(1.0, 1.0, 1.0),
+        );
+        let result = lighting(
+            &material,
+            object.inverse_transform(0.0),
+            &Light::from(light),
+            position,
+            eyev,
+            normalv,
+            false,
+            0.0,
+        );
+        assert_eq!(result, Color::new(1.9, 1.9, 1.9));
+    }
+
+    #[test]
+    fn eye_offset_45_degrees() {
+        let (material, object, position) = setup();
+        let value = Float::sqrt(2.0) / 2.0;
+        let eyev = Tuple4D::new_vector(0.0, value, -value);
+        let normalv = Tuple4D::new_vector(0.0, 0.0, -1.0);
+        let light = PointLight::new(
+            Tuple4D::new_point(0.0, 0.0, -10.0),
+            Color::new(1.0, 1.0, 1.0),
+        );
+        let result = lighting(
+            &material,
+            object.inverse_transform(0.0),
+            &Light::from(light),
+            position,
+            eyev,
+            normalv,
+            false,
+            0.0,
+        );
+        assert_eq!(result, Color::new(1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn light_offset_45_degrees() {
+        let (material, object, position) = setup();
+        let eyev = Tuple4D::new_vector(0.0, 0.0, -1.0);
+        let normalv = Tuple4D::new_vector(0.0, 0.0, -1.0);
+        let light = PointLight::new(
+            Tuple4D::new_point(0.0, 10.0, -10.0),
+            Color::new(1.0, 1.0, 1.0),
+        );
+        let result = lighting(
+            &material,
+            object.inverse_transform(0.0),
+            &Light::from(light),
+            position,
+            eyev,
+            normalv,
+            false,
+            0.0,
+        );
+        assert_eq!(result, Color::new(0.7364, 0.7364, 0.7364));
+    }
+
+    #[test]
+    fn eye_in_path_of_reflection_vector() {
+        let (material, object, position) = setup();
+        let value = Float::sqrt(2.0) / 2.0;
+        let eyev = Tuple4D::new_vector(0.0, -value, -value);
+        let normalv = Tuple4D::new_vector(0.0, 0.0, -1.0);
+        let light = PointLight::new(
+            Tuple4D::new_point(0.0, 10.0, -10.0),
+            Color::new(1.0, 1.0, 1.0),
+        );
+        let result = lighting(
+            &material,
+            object.inverse_transform(0.0),
+            &Light::from(light),
+            position,
+            eyev,
+            normalv,
+            false,
+            0.0,
+        );
+        assert_eq!(result, Color::new(1.6364, 1.6364, 1.6364));
+    }
+
+    #[test]
+    fn light_behind_surface() {
+        let (material, object, position) = setup();
+        let eyev = Tuple4D::new_vector(0.0, 0.0, -1.0);
+        let normalv = Tuple4D::new_vector(0.0, 0.0, -1.0);
+        let light = PointLight::new(
+            Tuple4D::new_point(0.0, 0.0, 10.0),
+            Color::new(1.0, 1.0, 1.0),
+        );
+        let result = lighting(
+            &material,
+            object.inverse_transform(0.0),
+            &Light::from(light),
+            position,
+            eyev,
+            normalv,
+            false,
+            0.0,
+        );
+        assert_eq!(result, Color::new(0.1, 0.1, 0.1));
+    }
+
+    #[test]
+    fn surface_in_shadow() {
+        let (material, object, position) = setup();
+        let eyev = Tuple4D::new_vector(0.0, 0.0, -1.0);
+        let normalv = Tuple4D::new_vector(0.0, 0.0, -1.0);
+        let light = PointLight::new(
+            Tuple4D::new_point(0.0, 0.0, -10.0),
+            Color::new(1.0, 1.0, 1.0),
+        );
+        let result = lighting(
+            &material,
+            object.inverse_transform(0.0),
+            &Light::from(light),
+            position,
+            eyev,
+            normalv,
+            true,
+            0.0,
+        );
+        assert_eq!(result, Color::new(0.1, 0.1, 0.1));
+    }
+
+    #[test]
+    fn lighting_with_pattern_applied() {
+        let (mut material, object, _) = setup();
+        material.pattern = Some(Arc::new(StripePattern::new(
+            Color::new(1.0, 1.0, 1.0),
+            Color::new(0.0, 0.0, 0.0),
+        )));
+        material.ambient = 1.0;
+        material.diffuse = 0.0;
+        material.specular = 0.0;
+        let eyev = Tuple4D::new_vector(0.0, 0.0, -1.0);
+        let normalv = Tuple4D::new_vector(0.0, 0.0, -1.0);
+        let light = PointLight::new(
+            Tuple4D::new_point(0.0, 0.0, -10.0),
+            Color::new(1.0, 1.0, 1.0),
+        );
+        let c1 = lighting(
+            &material,
+            object.inverse_transform(0.0),
+            &Light::from(light),
+            Tuple4D::new_point(0.9, 0.0, 0.0),
+            eyev,
+            normalv,
+            false,
+            0.0,
+        );
+        let c2 = lighting(
+            &material,
+            object.inverse_transform(0.0),
+            &Light::from(light),
+            Tuple4D::new_point(1.1, 0.0, 0.0),
+            eyev,
+            normalv,
+            false,
+            0.0,
+        );
+        assert_eq!(c1, Color::new(1.0, 1.0, 1.0));
+        assert_eq!(c2, Color::new(0.0, 0.0, 0.0));
+    }
+
+    fn spot_light_pointing_down(cone_angle: Float, falloff: Float) -> SpotLight {
+        SpotLight::new(
+            Tuple4D::new_point(0.0, 10.0, 0.0),
+            Tuple4D::new_vector(0.0, -1.0, 0.0),
+            cone_angle,
+            falloff,
+            Color::new(1.0, 1.0, 1.0),
+        )
+    }
+
+    #[test]
+    fn spot_light_normalizes_direction() {
+        let light = SpotLight::new(
+            Tuple4D::new_point(0.0, 0.0, 0.0),
+            Tuple4D::new_vector(0.0, -3.0, 0.0),
+            0.5,
+            1.0,
+            Color::new(1.0, 1.0, 1.0),
+        );
+        assert_eq!(light.direction, Tuple4D::new_vector(0.0, -1.0, 0.0));
+    }
+
+    #[test]
+    fn spot_light_is_full_intensity_on_axis() {
+        let light = Light::from(spot_light_pointing_down(PI / 8.0, 10.0));
+        assert_eq!(
+            light.intensity_at(Tuple4D::new_point(0.0, 0.0, 0.0)),
+            Color::new(1.0, 1.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn spot_light_is_dark_outside_cone() {
+        let light = Light::from(spot_light_pointing_down(PI / 8.0, 1.0));
+        // 45 degrees off the axis
+        assert_eq!(
+            light.intensity_at(Tuple4D::new_point(10.0, 0.0, 0.0)),
+            Color::new(0.0, 0.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn spot_light_falls_off_towards_cone_edge() {
+        let light = Light::from(spot_light_pointing_down(PI / 3.0, 2.0));
+        // 45 degrees off the axis, cos^2 = 0.5
+        assert_eq!(
+            light.intensity_at(Tuple4D::new_point(10.0, 0.0, 0.0)),
+            Color::new(0.5, 0.5, 0.5)
+        );
+    }
+
+    #[test]
+    fn lighting_outside_spot_cone_is_ambient_only() {
+        let (material, object, _) = setup();
+        let light = Light::from(spot_light_pointing_down(PI / 8.0, 1.0));
+        let result = lighting(
+            &material,
+            object.inverse_transform(0.0),
+            &light,
+            Tuple4D::new_point(10.0, 0.0, 0.0),
+            Tuple4D::new_vector(0.0, 1.0, 0.0),
+            Tuple4D::new_vector(0.0, 1.0, 0.0),
+            false,
+            0.0,
+        );
+        assert_eq!(result, Color::new(0.1, 0.1, 0.1));
+    }
+
+    #[test]
+    fn directional_light_comes_from_infinity() {
+        let light = Light::from(DirectionalLight::new(
+            Tuple4D::new_vector(0.0, -2.0, 0.0),
+            Color::new(1.0, 1.0, 1.0),
+        ));
+        let (direction, distance) = light.direction_from(Tuple4D::new_point(5.0, -3.0, 100.0));
+        assert_eq!(direction, Tuple4D::new_vector(0.0, 1.0, 0.0));
+        assert_eq!(distance, Float::INFINITY);
+    }
+
+    #[test]
+    fn lighting_with_directional_light() {
+        let (material, object, position) = setup();
+        let light = Light::from(DirectionalLight::new(
+            Tuple4D::new_vector(0.0, 0.0, 1.0),
+            Color::new(1.0, 1.0, 1.0),
+        ));
+        let eyev = Tuple4D::new_vector(0.0, 0.0, -1.0);
+        let normalv = Tuple4D::new_vector(0.0, 0.0, -1.0);
+        let result = lighting(
+            &material,
+            object.inverse_transform(0.0),
+            &light,
+            position,
+            eyev,
+            normalv,
+            false,
+            0.0,
+        );
+        assert_eq!(result, Color::new(1.9, 1.9, 1.9));
+    }
+
+    #[test]
+    fn lights_are_not_attenuated_by_default() {
+        let light = PointLight::new(Tuple4D::new_point(0.0, 0.0, 0.0), Color::new(1.0, 1.0, 1.0));
+        assert_eq!(light.attenuation, Attenuation::None);
+        assert_eq!(
+            Light::from(light).intensity_at(Tuple4D::new_point(0.0, 0.0, 10.0)),
+            Color::new(1.0, 1.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn point_light_with_linear_attenuation() {
+        let mut light =
+            PointLight::new(Tuple4D::new_point(0.0, 0.0, 0.0), Color::new(1.0, 1.0, 1.0));
+        light.attenuation = Attenuation::Linear;
+        assert_eq!(
+            Light::from(light).intensity_at(Tuple4D::new_point(0.0, 0.0, 4.0)),
+            Color::new(0.25, 0.25, 0.25)
+        );
+    }
+
+    #[test]
+    fn point_light_with_quadratic_attenuation() {
+        let mut light =
+            PointLight::new(Tuple4D::new_point(0.0, 0.0, 0.0), Color::new(1.0, 1.0, 1.0));
+        light.attenuation = Attenuation::Quadratic;
+        assert_eq!(
+            Light::from(light).intensity_at(Tuple4D::new_point(0.0, 4.0, 0.0)),
+            Color::new(0.0625, 0.0625, 0.0625)
+        );
+    }
+
+    #[test]
+    fn attenuation_is_finite_at_the_light() {
+        assert_eq!(Attenuation::Linear.factor(0.0), 100.0);
+        assert_eq!(
+            Attenuation::Quadratic.factor(0.0),
+            Attenuation::Quadratic.factor(MIN_ATTENUATION_DISTANCE)
+        );
+        assert!(Attenuation::Quadratic.factor(0.0).is_finite());
+    }
+
+    #[test]
+    fn spot_light_with_quadratic_attenuation() {
+        let mut light = spot_light_pointing_down(PI / 3.0, 0.0);
+        light.attenuation = Attenuation::Quadratic;
+        assert_eq!(
+            Light::from(light).intensity_at(Tuple4D::new_point(0.0, 8.0, 0.0)),
+            Color::new(0.25, 0.25, 0.25)
+        );
+    }
+
+    #[test]
+    fn attenuation_applies_to_diffuse_but_not_ambient() {
+        let (material, object, position) = setup();
+        let mut light = PointLight::new(
+            Tuple4D::new_point(0.0, 0.0, -2.0),
+            Color::new(1.0, 1.0, 1.0),
+        );
+        light.attenuation = Attenuation::Quadratic;
+        let eyev = Tuple4D::new_vector(0.0, 1.0, 0.0);
+        let normalv = Tuple4D::new_vector(0.0, 0.0, -1.0);
+        let result = lighting(
+            &material,
+            object.inverse_transform(0.0),
+            &Light::from(light),
+            position,
+            eyev,
+            normalv,
+            false,
+            0.0,
+        );
+        // ambient 0.1 + diffuse 0.9 / 4
+        assert_eq!(result, Color::new(0.325, 0.325, 0.325));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serializing_lights() {
+        let lights: Vec<Light> = vec![
+            PointLight::new(
+                Tuple4D::new_point(0.0, 10.0, 0.0),
+                Color::new(1.0, 1.0, 1.0),
+            )
+            .into(),
+            SpotLight::new(
+                Tuple4D::new_point(0.0, 5.0, 0.0),
+                Tuple4D::new_vector(0.0, -1.0, 0.0),
+                PI / 8.0,
+                2.0,
+                Color::new(1.0, 0.5, 0.5),
+            )
+            .into(),
+        ];
+        let json = serde_json::to_string(&lights).unwrap();
+        assert_eq!(serde_json::from_str::<Vec<Light>>(&json).unwrap(), lights);
+    }
+}
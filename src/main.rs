@@ -1,47 +1,654 @@
-use ray_tracing_rust::{math::Tuple4D, Canvas, Color};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::Duration;
 
-#[derive(Debug)]
-struct Projectile {
-    position: Tuple4D,
-    velocity: Tuple4D,
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use ray_tracing_rust::math::Float;
+use ray_tracing_rust::{
+    save_animated, AnimatedImageOptions, Bloom, Camera, CancellationToken, Canvas,
+    ChromaticAberration, ColorEncoding, ColorRamp, Cost, Denoiser, ImageOptions, Progress, Region,
+    SceneFile, Vignette, World,
+};
+
+// renders json and toml scene files, see SceneFile for their format
+#[derive(Debug, Parser)]
+#[command(version, about = "Renders scene files with the ray tracer")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    // clap uses the doc comments as help texts
+    /// Render a scene to an image
+    Render(Box<RenderArgs>),
+    /// Check that a scene file can be loaded and built and report likely mistakes in it
+    Validate {
+        /// Scene file (.json or .toml)
+        scene: PathBuf,
+    },
+    /// Combine rendered frames into an animated .gif or .png
+    Assemble(AssembleArgs),
+    /// Trace a single pixel and print every ray, hit and shadow test on the way
+    DebugPixel {
+        /// Scene file (.json or .toml)
+        scene: PathBuf,
+        /// Column of the pixel, from the left
+        x: usize,
+        /// Row of the pixel, from the top
+        y: usize,
+    },
+    /// Render tiles for `render --workers` running on another machine
+    #[cfg(feature = "network")]
+    Worker {
+        /// Address to listen on, only this machine by default. The workers render any scene they
+        /// are sent, so only listen on networks where every host is trusted
+        #[arg(default_value = "127.0.0.1:7878")]
+        address: String,
+        /// Directory the mesh files of the scenes are looked up in, the same files have to be next
+        /// to the scene on the rendering machine
+        #[arg(short, long, default_value = ".")]
+        directory: PathBuf,
+        /// Number of render threads, all cores by default
+        #[arg(short, long)]
+        threads: Option<usize>,
+    },
+}
+
+#[derive(Debug, Args)]
+struct RenderArgs {
+    /// Scene file (.json or .toml)
+    scene: PathBuf,
+    /// Output image, the format is chosen by the extension (.ppm, .hdr, .png, .jpg, .webp or .exr
+    /// depending on the enabled features)
+    #[arg(short, long, default_value = "image.ppm")]
+    output: PathBuf,
+    /// Image width, overrides the camera of the scene
+    #[arg(long)]
+    width: Option<usize>,
+    /// Image height, overrides the camera of the scene
+    #[arg(long)]
+    height: Option<usize>,
+    /// Render the named cameras of the scene instead of its camera, the name is added to the
+    /// output file when there is more than one
+    #[arg(long, value_delimiter = ',')]
+    camera: Vec<String>,
+    /// Render all the named cameras of the scene
+    #[arg(long, conflicts_with = "camera")]
+    all_cameras: bool,
+    /// Samples per pixel, overrides the camera of the scene
+    #[arg(short, long)]
+    samples: Option<usize>,
+    /// Write 16 bits per channel, only for .ppm and .png
+    #[arg(long)]
+    sixteen_bit: bool,
+    /// Encoding of the written colors: srgb, linear or a gamma value like 2.2. Not used for .hdr
+    /// and .exr
+    #[arg(long, default_value = "srgb")]
+    encoding: ColorEncoding,
+    /// Quality of .jpg and .webp images, from 1 to 100
+    #[arg(long, default_value_t = 90)]
+    quality: u8,
+    /// What to write into the image
+    #[arg(long, value_enum, default_value_t = Pass::Color)]
+    pass: Pass,
+    /// Distance that is white in the depth pass, the closest surface by default
+    #[arg(long)]
+    near: Option<Float>,
+    /// Distance that is black in the depth pass, the farthest surface by default
+    #[arg(long)]
+    far: Option<Float>,
+    /// Colors of the cost passes: heat, gray or comma separated hex colors like #000000,#ff0000
+    #[arg(long, default_value = "heat")]
+    ramp: ColorRamp,
+    /// Cost that gets the last color of the ramp, the highest cost of the image by default
+    #[arg(long)]
+    max_cost: Option<Float>,
+    /// Index of the object in the scene for the mask pass
+    #[arg(long, default_value_t = 0)]
+    object: usize,
+    /// Remove the noise of low sample renders with a filter guided by the normals and albedo
+    #[arg(long)]
+    denoise: bool,
+    /// Add a glow around the parts of the image that are brighter than 1
+    #[arg(long)]
+    bloom: bool,
+    /// Offset the red and blue channels towards the edges of the image like a cheap lens
+    #[arg(long)]
+    chromatic_aberration: bool,
+    /// Darken the corners of the image
+    #[arg(long)]
+    vignette: bool,
+    /// Only render the pixels in x,y,width,height of the image, only for the color pass
+    #[arg(long)]
+    crop: Option<Region>,
+    /// Keep the size of the whole image when cropping, the pixels outside of the crop stay black
+    #[arg(long, requires = "crop")]
+    crop_full: bool,
+    /// Make the background transparent, only for .png
+    #[arg(long)]
+    transparent: bool,
+    /// Render on the gpu, only scenes with basic shapes, materials and lights and the whitted
+    /// integrator are supported
+    #[cfg(feature = "gpu")]
+    #[arg(long)]
+    gpu: bool,
+    /// Render the color pass in tiles on `worker` processes, given as comma separated host:port
+    #[cfg(feature = "network")]
+    #[arg(long, value_delimiter = ',')]
+    workers: Vec<String>,
+    /// Number of render threads, all cores by default
+    #[arg(short, long)]
+    threads: Option<usize>,
+    /// Do not print the progress
+    #[arg(short, long)]
+    quiet: bool,
+}
+
+#[derive(Debug, Args)]
+struct AssembleArgs {
+    /// Frames in the order they are shown
+    #[arg(required = true)]
+    frames: Vec<PathBuf>,
+    /// Animated image, .gif or .png depending on the enabled features
+    #[arg(short, long, default_value = "animation.gif")]
+    output: PathBuf,
+    /// Time every frame is shown in milliseconds
+    #[arg(long, default_value_t = 40)]
+    delay: u64,
+    /// How often the animation is played, at least once, forever by default
+    #[arg(long, value_parser = clap::value_parser!(u16).range(1..))]
+    plays: Option<u16>,
+    /// Number of colors of the gif palette, from 2 to 256
+    #[arg(long, default_value_t = 256)]
+    colors: usize,
+    /// Quantize every gif frame on its own instead of sharing one palette
+    #[arg(long)]
+    palette_per_frame: bool,
+    /// Encoding of the written frames, the loaded frames are converted to linear colors first
+    #[arg(long, default_value = "srgb")]
+    encoding: ColorEncoding,
 }
 
-struct Environment {
-    gravity: Tuple4D,
-    wind: Tuple4D,
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Pass {
+    /// The rendered image
+    Color,
+    /// Distance to the first surface as grayscale, written without color encoding
+    Depth,
+    /// A distinct color for every top level object of the scene
+    ObjectIds,
+    /// Coverage of the object selected with --object as grayscale
+    Mask,
+    /// Number of objects tested per primary ray as a heatmap
+    IntersectionTests,
+    /// Number of group bounds tested per primary ray as a heatmap
+    BoundsTests,
+    /// Surface normals as colors
+    Normals,
+    /// Surface colors without lighting
+    Albedo,
 }
 
-fn tick(env: &Environment, projectile: &Projectile) -> Projectile {
-    let new_position = projectile.position + projectile.velocity;
-    let new_velocity = projectile.velocity + env.gravity + env.wind;
-    Projectile {
-        position: new_position,
-        velocity: new_velocity,
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let result = match cli.command {
+        Command::Render(args) => render(&args),
+        Command::Validate { scene } => validate(&scene),
+        Command::Assemble(args) => assemble(&args),
+        Command::DebugPixel { scene, x, y } => debug_pixel(&scene, x, y),
+        #[cfg(feature = "network")]
+        Command::Worker {
+            address,
+            directory,
+            threads,
+        } => serve_worker(&address, directory, threads),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
     }
 }
 
-fn main() {
-    let mut p = Projectile {
-        position: Tuple4D::new_point(0.0, 1.0, 0.0),
-        velocity: Tuple4D::new_vector(1.0, 1.8, 0.0).normalize() * 11.25,
+fn load_scene(path: &Path) -> Result<SceneFile, String> {
+    SceneFile::from_file(&path.to_string_lossy()).map_err(|err| match err.kind() {
+        // parse errors already name the file
+        ErrorKind::InvalidData => err.to_string(),
+        _ => format!("{}: {err}", path.display()),
+    })
+}
+
+fn directory(path: &Path) -> &Path {
+    path.parent().unwrap_or(Path::new(""))
+}
+
+fn render(args: &RenderArgs) -> Result<(), String> {
+    let mut scene = load_scene(&args.scene)?;
+    apply_overrides(&mut scene, args);
+    let names: Vec<String> = if args.all_cameras {
+        if scene.cameras.is_empty() {
+            return Err("the scene has no named cameras".to_string());
+        }
+        scene.cameras.keys().cloned().collect()
+    } else {
+        args.camera.clone()
     };
+    // the cameras are checked before the world is built
+    let cameras = if names.is_empty() {
+        vec![(None, scene.camera.build().map_err(|err| err.to_string())?)]
+    } else {
+        names
+            .iter()
+            .map(|name| Ok((Some(name.as_str()), scene.build_camera(name)?)))
+            .collect::<Result<Vec<_>, String>>()?
+    };
+    // the world is shared by all cameras
+    let world = scene
+        .build_world(directory(&args.scene))
+        .map_err(|err| err.to_string())?;
+    let mut warnings = world.validate();
+    for (_, camera) in &cameras {
+        warnings.extend(camera.validate());
+    }
+    if !args.quiet {
+        for warning in &warnings {
+            eprintln!("warning: {warning}");
+        }
+    }
+    if let Some(warning) = warnings.iter().find(|warning| warning.prevents_rendering()) {
+        return Err(format!("can not render the scene, {warning}"));
+    }
+    let several = cameras.len() > 1;
+    for (name, mut camera) in cameras {
+        if let Some(threads) = args.threads {
+            camera.threads = threads.max(1);
+        }
+        let output = match name {
+            Some(name) if several => camera_output(&args.output, name),
+            _ => args.output.clone(),
+        };
+        if several && !args.quiet {
+            eprintln!("camera {}", name.unwrap_or_default());
+        }
+        render_camera(args, &scene, name, &world, &camera, &output)?;
+    }
+    Ok(())
+}
+
+// None without --gpu
+#[cfg_attr(not(feature = "gpu"), allow(unused_variables))]
+fn render_on_gpu(
+    args: &RenderArgs,
+    world: &World,
+    camera: &Camera,
+) -> Result<Option<Canvas>, String> {
+    #[cfg(feature = "gpu")]
+    if args.gpu {
+        let renderer = ray_tracing_rust::GpuRenderer::new()?;
+        if !args.quiet {
+            eprintln!("rendering on {}", renderer.adapter());
+        }
+        return renderer.render(world, camera).map(Some);
+    }
+    Ok(None)
+}
 
-    let e = Environment {
-        gravity: Tuple4D::new_vector(0.0, -0.1, 0.0),
-        wind: Tuple4D::new_vector(-0.01, 0.0, 0.0),
+// None without --workers
+#[cfg_attr(not(feature = "network"), allow(unused_variables))]
+fn render_on_workers(
+    args: &RenderArgs,
+    scene: &SceneFile,
+    name: Option<&str>,
+    region: Region,
+    on_progress: impl FnMut(&Progress),
+) -> Result<Option<Canvas>, String> {
+    #[cfg(feature = "network")]
+    if !args.workers.is_empty() {
+        let renderer = ray_tracing_rust::NetworkRenderer::new(args.workers.clone());
+        return renderer
+            .render_region(scene, name, region, on_progress)
+            .map(Some)
+            .map_err(|err| err.to_string());
+    }
+    Ok(None)
+}
+
+#[cfg(feature = "network")]
+fn serve_worker(address: &str, directory: PathBuf, threads: Option<usize>) -> Result<(), String> {
+    let listener =
+        std::net::TcpListener::bind(address).map_err(|err| format!("{address}: {err}"))?;
+    eprintln!("waiting for scenes on {address}");
+    let mut worker = ray_tracing_rust::RenderWorker::new(directory);
+    worker.threads = threads;
+    worker.serve(&listener).map_err(|err| err.to_string())
+}
+
+// image.png becomes image_name.png
+fn camera_output(output: &Path, name: &str) -> PathBuf {
+    let stem = output.file_stem().unwrap_or_default().to_string_lossy();
+    let file_name = match output.extension() {
+        Some(extension) => format!("{stem}_{name}.{}", extension.to_string_lossy()),
+        None => format!("{stem}_{name}"),
     };
+    output.with_file_name(file_name)
+}
 
-    println!("{p:?}");
-    let mut canvas = Canvas::create_canvas(900, 550);
-    while p.position.y > 0.0 {
-        canvas.write_pixel(
-            p.position.x.round() as usize,
-            (550.0 - p.position.y.round()) as usize,
-            Color::new(0.0, 0.0, 1.0),
+fn render_camera(
+    args: &RenderArgs,
+    scene: &SceneFile,
+    name: Option<&str>,
+    world: &World,
+    camera: &Camera,
+    output_path: &Path,
+) -> Result<(), String> {
+    #[cfg(feature = "gpu")]
+    if args.gpu && (args.pass != Pass::Color || args.transparent) {
+        return Err("--gpu only works for the color pass without transparency".to_string());
+    }
+    let crop = match args.crop {
+        Some(_) if args.pass != Pass::Color || args.transparent => {
+            return Err("--crop only works for the color pass without transparency".to_string());
+        }
+        Some(region) => {
+            let region = region.clamp_to(camera.hsize(), camera.vsize());
+            if region.width == 0 || region.height == 0 {
+                return Err(format!(
+                    "the crop is outside of the {}x{} image",
+                    camera.hsize(),
+                    camera.vsize()
+                ));
+            }
+            Some(region)
+        }
+        None => None,
+    };
+    let token = CancellationToken::new();
+    let on_progress = |progress: &Progress| {
+        if args.quiet {
+            return;
+        }
+        let eta = progress
+            .eta()
+            .map_or(String::new(), |eta| format!(", {}s left", eta.as_secs()));
+        eprint!("\rrendering: {:5.1}%{eta}    ", progress.fraction() * 100.0);
+        let _ = std::io::stderr().flush();
+    };
+    let options = ImageOptions {
+        sixteen_bit: args.sixteen_bit,
+        encoding: args.encoding,
+        quality: args.quality,
+    };
+    let output = output_path.to_string_lossy();
+    // the other passes hold data instead of colors
+    let save_linear = |canvas: Canvas| {
+        let options = ImageOptions {
+            encoding: ColorEncoding::Linear,
+            ..options
+        };
+        canvas.save_with_options(&output, &options)
+    };
+    let saved = match args.pass {
+        Pass::Color if args.transparent => {
+            let image = camera.render_rgba_with_progress(world, &token, on_progress);
+            finish_progress(args.quiet);
+            image.save_with_options(&output, &options)
+        }
+        Pass::Color => {
+            let whole = Region::new(0, 0, camera.hsize(), camera.vsize());
+            let remote = render_on_workers(args, scene, name, crop.unwrap_or(whole), on_progress)?;
+            let mut canvas = match remote {
+                // the workers only render the crop
+                Some(canvas) => canvas,
+                None => match (render_on_gpu(args, world, camera)?, crop) {
+                    (Some(canvas), Some(region)) => canvas.crop(region),
+                    (Some(canvas), None) => canvas,
+                    (None, Some(region)) => {
+                        camera.render_region_with_progress(world, region, &token, on_progress)
+                    }
+                    (None, None) => camera.render_with_progress(world, &token, on_progress),
+                },
+            };
+            finish_progress(args.quiet);
+            // the post effects are placed by the whole frame, not by the crop
+            let region = crop.unwrap_or(whole);
+            let (frame_width, frame_height) = (camera.hsize(), camera.vsize());
+            if args.denoise {
+                let normals = camera.render_normals_region(world, region);
+                let albedo = camera.render_albedo_region(world, region);
+                canvas = Denoiser::default().denoise(&canvas, &normals, &albedo);
+            }
+            if args.bloom {
+                canvas = Bloom::default().apply_to_crop(&canvas, frame_width);
+            }
+            if args.chromatic_aberration {
+                canvas = ChromaticAberration::default().apply_to_crop(
+                    &canvas,
+                    region,
+                    frame_width,
+                    frame_height,
+                );
+            }
+            if args.vignette {
+                canvas =
+                    Vignette::default().apply_to_crop(&canvas, region, frame_width, frame_height);
+            }
+            if let Some(region) = crop.filter(|_| args.crop_full) {
+                let mut full = Canvas::create_canvas(camera.hsize(), camera.vsize());
+                full.paste(region.x, region.y, &canvas);
+                canvas = full;
+            }
+            canvas.save_with_options(&output, &options)
+        }
+        Pass::Depth => {
+            let depth = camera.render_depth(world);
+            let (near, far) = depth.finite_range().unwrap_or((0.0, 1.0));
+            save_linear(depth.to_grayscale(args.far.unwrap_or(far), args.near.unwrap_or(near)))
+        }
+        Pass::ObjectIds => save_linear(camera.render_object_ids(world).to_id_colors()),
+        Pass::Mask => {
+            if args.object >= world.objects.len() {
+                return Err(format!("the scene has no object {}", args.object));
+            }
+            let mask = camera.render_object_mask(world, args.object);
+            save_linear(mask.to_grayscale(0.0, 1.0))
+        }
+        Pass::IntersectionTests | Pass::BoundsTests => {
+            let kind = if args.pass == Pass::IntersectionTests {
+                Cost::IntersectionTests
+            } else {
+                Cost::BoundsTests
+            };
+            let cost = camera.render_cost(world, kind);
+            let highest = cost.finite_range().map_or(0.0, |(_, high)| high);
+            if !args.quiet {
+                eprintln!("highest cost per pixel {highest}");
+            }
+            save_linear(cost.to_heatmap(&args.ramp, args.max_cost.unwrap_or(highest)))
+        }
+        Pass::Normals => save_linear(camera.render_normals(world).to_normal_colors()),
+        Pass::Albedo => camera
+            .render_albedo(world)
+            .save_with_options(&output, &options),
+    };
+    saved.map_err(|err| format!("{}: {err}", output_path.display()))?;
+    if !args.quiet {
+        eprintln!("written to {}", output_path.display());
+    }
+    Ok(())
+}
+
+fn finish_progress(quiet: bool) {
+    if !quiet {
+        eprintln!();
+    }
+}
+
+// the overrides are applied to the scene description, so the camera is built only once
+fn apply_overrides(scene: &mut SceneFile, args: &RenderArgs) {
+    for camera in std::iter::once(&mut scene.camera).chain(scene.cameras.values_mut()) {
+        if let Some(width) = args.width {
+            camera.width = width;
+        }
+        if let Some(height) = args.height {
+            camera.height = height;
+        }
+        if let Some(samples) = args.samples {
+            camera.samples_per_pixel = samples;
+        }
+    }
+}
+
+fn assemble(args: &AssembleArgs) -> Result<(), String> {
+    let frames = args
+        .frames
+        .iter()
+        .map(|path| {
+            Canvas::load(&path.to_string_lossy())
+                .map_err(|err| format!("{}: {err}", path.display()))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let options = AnimatedImageOptions {
+        frame_delay: Duration::from_millis(args.delay),
+        plays: args.plays,
+        encoding: args.encoding,
+        colors: args.colors,
+        shared_palette: !args.palette_per_frame,
+        ..AnimatedImageOptions::default()
+    };
+    save_animated(&frames, &args.output.to_string_lossy(), &options)
+        .map_err(|err| format!("{}: {err}", args.output.display()))?;
+    println!(
+        "{} frames written to {}",
+        frames.len(),
+        args.output.display()
+    );
+    Ok(())
+}
+
+fn debug_pixel(path: &Path, x: usize, y: usize) -> Result<(), String> {
+    let scene = load_scene(path)?;
+    let (world, camera) = scene
+        .build(directory(path))
+        .map_err(|err| err.to_string())?;
+    if x >= camera.hsize() || y >= camera.vsize() {
+        return Err(format!(
+            "pixel ({x}, {y}) is outside of the {}x{} image",
+            camera.hsize(),
+            camera.vsize()
+        ));
+    }
+    println!("{}", camera.debug_pixel(&world, x, y));
+    Ok(())
+}
+
+fn validate(path: &Path) -> Result<(), String> {
+    let scene = load_scene(path)?;
+    let (world, camera) = scene
+        .build(directory(path))
+        .map_err(|err| err.to_string())?;
+    println!(
+        "{}: {} objects, {} lights, {}x{} pixels",
+        path.display(),
+        world.objects.len(),
+        world.lights.len(),
+        camera.hsize(),
+        camera.vsize()
+    );
+    let mut warnings = world.validate();
+    warnings.extend(camera.validate());
+    for warning in &warnings {
+        println!("warning: {warning}");
+    }
+    if warnings.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("{} warnings", warnings.len()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parsing_render_arguments() {
+        let cli = Cli::try_parse_from([
+            "ray_tracing_rust",
+            "render",
+            "scene.toml",
+            "-o",
+            "out.ppm",
+            "--width",
+            "320",
+            "-s",
+            "16",
+        ])
+        .unwrap();
+        let Command::Render(args) = cli.command else {
+            panic!("expected render command");
+        };
+        assert_eq!(args.scene, PathBuf::from("scene.toml"));
+        assert_eq!(args.output, PathBuf::from("out.ppm"));
+        assert_eq!(args.width, Some(320));
+        assert_eq!(args.height, None);
+        assert_eq!(args.samples, Some(16));
+        assert!(!args.quiet);
+        assert!(!args.sixteen_bit);
+        assert_eq!(args.encoding, ColorEncoding::Srgb);
+        assert_eq!(args.pass, Pass::Color);
+    }
+
+    #[test]
+    fn parsing_color_encoding() {
+        let cli = Cli::try_parse_from([
+            "ray_tracing_rust",
+            "render",
+            "scene.toml",
+            "--encoding",
+            "2.2",
+        ])
+        .unwrap();
+        let Command::Render(args) = cli.command else {
+            panic!("expected render command");
+        };
+        assert_eq!(args.encoding, ColorEncoding::Gamma(2.2));
+        assert!(
+            Cli::try_parse_from(["ray_tracing_rust", "render", "a.json", "--encoding", "x"])
+                .is_err()
         );
-        p = tick(&e, &p);
-        println!("{p:?}");
     }
-    canvas.to_ppm("projectile.ppm").expect("Ahhh");
-    println!("File written!");
+
+    #[test]
+    fn scene_is_required() {
+        assert!(Cli::try_parse_from(["ray_tracing_rust", "render"]).is_err());
+        assert!(Cli::try_parse_from(["ray_tracing_rust", "validate"]).is_err());
+    }
+
+    #[test]
+    fn overrides_change_the_camera_description() {
+        let (world, camera) = ray_tracing_rust::scenes::three_spheres(100, 50);
+        let mut scene = SceneFile::from_world(&world, &camera).unwrap();
+        let cli = Cli::try_parse_from([
+            "ray_tracing_rust",
+            "render",
+            "scene.json",
+            "--height",
+            "10",
+            "--samples",
+            "4",
+        ])
+        .unwrap();
+        let Command::Render(args) = cli.command else {
+            panic!("expected render command");
+        };
+        apply_overrides(&mut scene, &args);
+        assert_eq!(scene.camera.width, 100);
+        assert_eq!(scene.camera.height, 10);
+        assert_eq!(scene.camera.samples_per_pixel, 4);
+    }
 }
@@ -0,0 +1,197 @@
+use std::sync::Arc;
+
+use rand::Rng;
+
+use crate::math::{Float, Matrix4, Tuple4D};
+use crate::patterns::{scale_footprint, Pattern};
+use crate::{Color, NormalMap, Object, Volume};
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Material {
+    pub color: Color,
+    // overrides color if set
+    // patterns, normal maps and volumes are not serialized
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub pattern: Option<Arc<dyn Pattern>>,
+    pub ambient: Float,
+    pub diffuse: Float,
+    pub specular: Float,
+    pub shininess: Float,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub normal_map: Option<NormalMap>,
+    pub reflective: Float,
+    pub transparency: Float,
+    pub refractive_index: Float,
+    // Beer's law absorption per unit distance travelled inside the object, a red glass absorbs
+    // green and blue. Black means the inside is perfectly clear
+    pub absorption: Color,
+    // blurs reflections, 0 is a perfect mirror and around 0.3 looks like brushed metal
+    pub roughness: Float,
+    // fills the object with a participating medium, its surface is not rendered then
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub volume: Option<Volume>,
+    // light given off by the surface itself, independent of any light source
+    pub emissive: Color,
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Material {
+            color: Color::new(1.0, 1.0, 1.0),
+            pattern: None,
+            ambient: 0.1,
+            diffuse: 0.9,
+            specular: 0.9,
+            shininess: 200.0,
+            normal_map: None,
+            reflective: 0.0,
+            transparency: 0.0,
+            refractive_index: 1.0,
+            absorption: Color::new(0.0, 0.0, 0.0),
+            roughness: 0.0,
+            emissive: Color::new(0.0, 0.0, 0.0),
+            volume: None,
+        }
+    }
+}
+
+impl Material {
+    // surface color at a world space point of object, time selects the pose of moving objects
+    pub fn color_at(&self, object: &Object, point: Tuple4D, time: Float) -> Color {
+        self.color_at_filtered(object.inverse_transform(time), point, 0.0)
+    }
+
+    // color averaged over a region of the surface that is footprint wide in world space,
+    // world_to_object includes the transforms of the groups the object is in
+    pub fn color_at_filtered(
+        &self,
+        world_to_object: Matrix4,
+        point: Tuple4D,
+        footprint: Float,
+    ) -> Color {
+        match &self.pattern {
+            Some(pattern) => pattern.pattern_at_nested_filtered(
+                world_to_object * point,
+                scale_footprint(world_to_object, footprint),
+            ),
+            None => self.color,
+        }
+    }
+
+    // fraction of the light that is left after travelling distance inside the object
+    pub fn transmittance(&self, distance: Float) -> Color {
+        Color::new(
+            (-self.absorption.r * distance).exp(),
+            (-self.absorption.g * distance).exp(),
+            (-self.absorption.b * distance).exp(),
+        )
+    }
+
+    // reflection direction offset by a random point in a sphere of radius roughness, directions that
+    // would end up below the surface fall back to the perfect reflection
+    pub fn glossy_reflection(
+        &self,
+        reflectv: Tuple4D,
+        normalv: Tuple4D,
+        rng: &mut impl Rng,
+    ) -> Tuple4D {
+        if self.roughness <= 0.0 {
+            return reflectv;
+        }
+        let offset = loop {
+            let candidate = Tuple4D::new_vector(
+                rng.random_range(-1.0..1.0),
+                rng.random_range(-1.0..1.0),
+                rng.random_range(-1.0..1.0),
+            );
+            if candidate.dot(candidate) <= 1.0 {
+                break candidate;
+            }
+        };
+        let direction = (reflectv + offset * self.roughness).normalize();
+        if direction.dot(normalv) <= 0.0 {
+            return reflectv;
+        }
+        direction
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::SmallRng;
+    use rand::SeedableRng;
+
+    use crate::math::FLOAT_EQ_EPS;
+
+    use super::*;
+
+    #[test]
+    fn default_material() {
+        let material = Material::default();
+        assert_eq!(material.color, Color::new(1.0, 1.0, 1.0));
+        assert!(material.pattern.is_none());
+        assert_eq!(material.ambient, 0.1);
+        assert_eq!(material.diffuse, 0.9);
+        assert_eq!(material.specular, 0.9);
+        assert_eq!(material.shininess, 200.0);
+        assert!(material.normal_map.is_none());
+        assert_eq!(material.reflective, 0.0);
+        assert_eq!(material.transparency, 0.0);
+        assert_eq!(material.refractive_index, 1.0);
+    }
+
+    #[test]
+    fn glossy_reflection_stays_within_roughness() {
+        let material = Material {
+            roughness: 0.2,
+            ..Material::default()
+        };
+        let reflectv = Tuple4D::new_vector(0.0, 1.0, 0.0);
+        let mut rng = SmallRng::seed_from_u64(1);
+        for _ in 0..100 {
+            let direction = material.glossy_reflection(reflectv, reflectv, &mut rng);
+            // offset of length 0.2 tilts the direction by at most asin(0.2)
+            assert!(direction.dot(reflectv) >= Float::sqrt(1.0 - 0.2 * 0.2) - FLOAT_EQ_EPS);
+        }
+    }
+
+    #[test]
+    fn smooth_material_reflects_perfectly() {
+        let reflectv = Tuple4D::new_vector(0.6, 0.8, 0.0);
+        let mut rng = SmallRng::seed_from_u64(1);
+        let normalv = Tuple4D::new_vector(0.0, 1.0, 0.0);
+        assert_eq!(
+            Material::default().glossy_reflection(reflectv, normalv, &mut rng),
+            reflectv
+        );
+    }
+
+    #[test]
+    fn transmittance_decays_exponentially() {
+        let material = Material {
+            absorption: Color::new(0.0, 1.0, 2.0),
+            ..Material::default()
+        };
+        assert_eq!(
+            material.transmittance(0.5),
+            Color::new(1.0, Float::exp(-0.5), Float::exp(-1.0))
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serializing_material_skips_patterns() {
+        let material = Material {
+            color: Color::new(0.2, 0.4, 0.6),
+            reflective: 0.5,
+            pattern: Some(Arc::new(Color::new(1.0, 0.0, 0.0))),
+            ..Material::default()
+        };
+        let json = serde_json::to_string(&material).unwrap();
+        let restored: Material = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.color, material.color);
+        assert_eq!(restored.reflective, 0.5);
+        assert!(restored.pattern.is_none());
+    }
+}
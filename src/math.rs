@@ -1,13 +1,149 @@
+// implements a binary operator for the combinations with values by forwarding to the
+// implementation for two references, so both can be used without copying
+macro_rules! forward_ref_binop {
+    (impl $imp:ident, $method:ident for $lhs:ty, $rhs:ty => $output:ty) => {
+        impl std::ops::$imp<$rhs> for $lhs {
+            type Output = $output;
+
+            fn $method(self, rhs: $rhs) -> $output {
+                std::ops::$imp::$method(&self, &rhs)
+            }
+        }
+
+        impl std::ops::$imp<&$rhs> for $lhs {
+            type Output = $output;
+
+            fn $method(self, rhs: &$rhs) -> $output {
+                std::ops::$imp::$method(&self, rhs)
+            }
+        }
+
+        impl std::ops::$imp<$rhs> for &$lhs {
+            type Output = $output;
+
+            fn $method(self, rhs: $rhs) -> $output {
+                std::ops::$imp::$method(self, &rhs)
+            }
+        }
+    };
+}
+
+mod error;
+pub use error::MathError;
 mod tuple;
 pub use tuple::Tuple4D;
+mod point;
+pub use point::{Point, Vector};
 mod matrix;
 pub use matrix::Matrix4;
 mod matrix2;
 pub use matrix2::Matrix2;
 mod matrix3;
 pub use matrix3::Matrix3;
+mod interpolation;
+pub use interpolation::{lerp, smoothstep};
+mod noise;
+mod tolerance;
+pub use tolerance::{default_tolerance, set_default_tolerance, ApproxEq, Tolerance};
+mod quaternion;
+pub use quaternion::Quaternion;
+mod roots;
+pub use roots::{solve_cubic, solve_polynomial, solve_quadratic, solve_quartic};
+mod spline;
+pub use spline::{catmull_rom, cubic_bezier, Spline};
+mod transform;
+mod transformation;
+pub use noise::{perlin_noise, turbulence};
+pub use transform::Transform;
+
+// the scalar of the whole renderer, the f32 feature halves the memory of large meshes and doubles
+// the width of the simd lanes at the cost of precision
+#[cfg(not(feature = "f32"))]
+pub type Float = f64;
+#[cfg(feature = "f32")]
+pub type Float = f32;
+#[cfg(feature = "f32")]
+pub use std::f32::consts;
+#[cfg(not(feature = "f32"))]
+pub use std::f64::consts;
+
+// the bits of a float as a key for hashing, in both precisions
+#[cfg(not(feature = "f32"))]
+pub(crate) fn float_bits(value: Float) -> u64 {
+    value.to_bits()
+}
+#[cfg(feature = "f32")]
+pub(crate) fn float_bits(value: Float) -> u64 {
+    u64::from(value.to_bits())
+}
 
-pub fn float_eq(a: f64, b: f64, eps: f64) -> bool {
+pub fn float_eq(a: Float, b: Float, eps: Float) -> bool {
     (a - b).abs() < eps
 }
-pub const FLOAT_EQ_EPS: f64 = 0.00001;
+#[cfg(not(feature = "f32"))]
+pub const FLOAT_EQ_EPS: Float = 0.00001;
+// single precision only has about seven digits, so surfaces need a larger offset against acne
+#[cfg(feature = "f32")]
+pub const FLOAT_EQ_EPS: Float = 0.0005;
+
+// the determinant of a matrix relative to the product of the lengths of its rows, which is the
+// largest determinant rows of these lengths can have. Scaling a matrix does not change it, so
+// matrices of tiny or huge objects are not taken to be singular. Compared to 0 with the
+// tolerance, a matrix whose rows have no length or that is not finite is always singular
+pub(crate) fn is_singular<const N: usize>(
+    determinant: Float,
+    rows: &[[Float; N]; N],
+    tolerance: Tolerance,
+) -> bool {
+    let scale: Float = rows
+        .iter()
+        .map(|row| row.iter().map(|value| value * value).sum::<Float>().sqrt())
+        .product();
+    let relative = determinant / scale;
+    !relative.is_finite() || tolerance.equal(relative, 0.0)
+}
+
+// how close to 0 the relative determinant of is_singular is for is_invertible and inverse, far
+// above the rounding errors of computing it in either precision
+pub(crate) const SINGULAR_TOLERANCE: Tolerance = Tolerance::Absolute(64.0 * Float::EPSILON);
+
+// a value with the precision of the formatter, three decimals by default. Values that round to
+// zero lose their sign, rotations are full of them
+pub(crate) fn format_entry(value: Float, f: &std::fmt::Formatter<'_>) -> String {
+    let text = format!("{:.*}", f.precision().unwrap_or(3), value);
+    match text.strip_prefix('-') {
+        Some(unsigned) if unsigned.chars().all(|c| c == '0' || c == '.') => unsigned.to_string(),
+        _ => text,
+    }
+}
+
+// one line per row between bars, every column right aligned to its widest entry or the width of
+// the formatter
+pub(crate) fn write_rows<const N: usize>(
+    f: &mut std::fmt::Formatter<'_>,
+    rows: &[[Float; N]],
+) -> std::fmt::Result {
+    let entries: Vec<Vec<String>> = rows
+        .iter()
+        .map(|row| row.iter().map(|&value| format_entry(value, f)).collect())
+        .collect();
+    let widths: Vec<usize> = (0..N)
+        .map(|col| {
+            entries
+                .iter()
+                .map(|row| row[col].len())
+                .fold(f.width().unwrap_or(0), usize::max)
+        })
+        .collect();
+    for (idx, row) in entries.iter().enumerate() {
+        if idx > 0 {
+            writeln!(f)?;
+        }
+        write!(f, "|")?;
+        for (entry, width) in row.iter().zip(&widths) {
+            write!(f, " {entry:>width$}")?;
+        }
+        write!(f, " |")?;
+    }
+    Ok(())
+}
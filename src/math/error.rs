@@ -0,0 +1,46 @@
+use std::fmt;
+
+use super::Tuple4D;
+
+// errors of the fallible operations on tuples and matrices, for callers that handle bad input
+// instead of aborting on it
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum MathError {
+    // a tuple with a w other than 0 where only directions make sense
+    NotAVector(Tuple4D),
+    // a tuple with a w other than 1 where only positions make sense
+    NotAPoint(Tuple4D),
+    // vectors without a length have no direction
+    ZeroLength,
+    // rows for a matrix of another size
+    RowCount {
+        expected: usize,
+        found: usize,
+    },
+    RowLength {
+        row: usize,
+        expected: usize,
+        found: usize,
+    },
+}
+
+impl fmt::Display for MathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MathError::NotAVector(tuple) => write!(f, "{tuple} is not a vector, w has to be 0"),
+            MathError::NotAPoint(tuple) => write!(f, "{tuple} is not a point, w has to be 1"),
+            MathError::ZeroLength => write!(f, "a vector of length zero can not be normalized"),
+            MathError::RowCount { expected, found } => {
+                write!(f, "expected {expected} rows, got {found}")
+            }
+            MathError::RowLength {
+                row,
+                expected,
+                found,
+            } => write!(f, "expected {expected} values in row {row}, got {found}"),
+        }
+    }
+}
+
+impl std::error::Error for MathError {}
@@ -0,0 +1,57 @@
+use std::ops::{Add, Mul, Sub};
+
+use super::Float;
+
+// the value the fraction t of the way from a to b, t is not clamped so values outside of [0, 1]
+// extrapolate. Works for everything that can be scaled and added, like floats, tuples and colors
+pub fn lerp<T>(a: T, b: T, t: Float) -> T
+where
+    T: Copy + Add<Output = T> + Sub<Output = T> + Mul<Float, Output = T>,
+{
+    a + (b - a) * t
+}
+
+// 0 up to edge0, 1 from edge1 on and an s-curve in between whose slope is 0 at both edges
+pub fn smoothstep(edge0: Float, edge1: Float, x: Float) -> Float {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::{float_eq, Tuple4D, FLOAT_EQ_EPS};
+    use crate::Color;
+
+    #[test]
+    fn lerp_floats_tuples_and_colors() {
+        assert!(float_eq(lerp(2.0, 4.0, 0.25), 2.5, FLOAT_EQ_EPS));
+        assert!(float_eq(lerp(2.0, 4.0, 1.5), 5.0, FLOAT_EQ_EPS));
+        assert_eq!(
+            lerp(
+                Tuple4D::new_point(0.0, 2.0, -4.0),
+                Tuple4D::new_point(1.0, 4.0, 4.0),
+                0.5
+            ),
+            Tuple4D::new_point(0.5, 3.0, 0.0)
+        );
+        assert_eq!(
+            lerp(Color::new(1.0, 0.0, 0.0), Color::new(0.0, 0.0, 1.0), 0.75),
+            Color::new(0.25, 0.0, 0.75)
+        );
+    }
+
+    #[test]
+    fn smoothstep_is_clamped_and_symmetric() {
+        assert_eq!(smoothstep(1.0, 3.0, 0.0), 0.0);
+        assert_eq!(smoothstep(1.0, 3.0, 4.0), 1.0);
+        assert!(float_eq(smoothstep(1.0, 3.0, 2.0), 0.5, FLOAT_EQ_EPS));
+        assert!(float_eq(
+            smoothstep(0.0, 1.0, 0.25) + smoothstep(0.0, 1.0, 0.75),
+            1.0,
+            FLOAT_EQ_EPS
+        ));
+        // reversed edges fall from 1 to 0
+        assert!(smoothstep(1.0, 0.0, 0.1) > 0.9);
+    }
+}
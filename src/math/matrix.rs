@@ -1,14 +1,20 @@
-use std::ops::{Index, IndexMut, Mul};
+use std::fmt;
+use std::ops::{Index, IndexMut, Mul, MulAssign};
 
-use super::{float_eq, Matrix3, Tuple4D, FLOAT_EQ_EPS};
+use super::{
+    default_tolerance, is_singular, write_rows, ApproxEq, Float, MathError, Tolerance, Tuple4D,
+    SINGULAR_TOLERANCE,
+};
 
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
 pub struct Matrix4 {
-    data: [[f64; 4]; 4],
+    data: [[Float; 4]; 4],
 }
 
 impl Index<[usize; 2]> for Matrix4 {
-    type Output = f64;
+    type Output = Float;
 
     fn index(&self, index: [usize; 2]) -> &Self::Output {
         &self.data[index[0]][index[1]]
@@ -21,23 +27,29 @@ impl IndexMut<[usize; 2]> for Matrix4 {
     }
 }
 
+// the rows aligned by column, e.g. println!("{matrix:.2}") for two decimals
+impl fmt::Display for Matrix4 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_rows(f, &self.data)
+    }
+}
+
+impl ApproxEq for Matrix4 {
+    fn approx_eq(&self, other: &Self, tolerance: Tolerance) -> bool {
+        self.data.approx_eq(&other.data, tolerance)
+    }
+}
+
 impl PartialEq for Matrix4 {
     fn eq(&self, other: &Self) -> bool {
-        for i in 0..=3 {
-            for j in 0..=3 {
-                if !float_eq(self[[i, j]], other[[i, j]], FLOAT_EQ_EPS) {
-                    return false;
-                }
-            }
-        }
-        return true;
+        self.approx_eq(other, default_tolerance())
     }
 }
 
-impl Mul for Matrix4 {
+impl Mul<&Matrix4> for &Matrix4 {
     type Output = Matrix4;
 
-    fn mul(self, rhs: Self) -> Self::Output {
+    fn mul(self, rhs: &Matrix4) -> Self::Output {
         let mut res = Matrix4::zeros();
         for i in 0..=3 {
             for j in 0..=3 {
@@ -50,10 +62,24 @@ impl Mul for Matrix4 {
     }
 }
 
-impl Mul<Tuple4D> for Matrix4 {
+forward_ref_binop!(impl Mul, mul for Matrix4, Matrix4 => Matrix4);
+
+impl MulAssign<&Matrix4> for Matrix4 {
+    fn mul_assign(&mut self, rhs: &Matrix4) {
+        *self = Mul::mul(&*self, rhs);
+    }
+}
+
+impl MulAssign for Matrix4 {
+    fn mul_assign(&mut self, rhs: Matrix4) {
+        *self *= &rhs;
+    }
+}
+
+impl Mul<&Tuple4D> for &Matrix4 {
     type Output = Tuple4D;
 
-    fn mul(self, rhs: Tuple4D) -> Self::Output {
+    fn mul(self, rhs: &Tuple4D) -> Self::Output {
         let mut res = Tuple4D::zeros();
         for i in 0..=3 {
             res[i] = self[[i, 0]] * rhs[0]
@@ -65,8 +91,55 @@ impl Mul<Tuple4D> for Matrix4 {
     }
 }
 
+forward_ref_binop!(impl Mul, mul for Matrix4, Tuple4D => Tuple4D);
+
+impl From<[[Float; 4]; 4]> for Matrix4 {
+    fn from(rows: [[Float; 4]; 4]) -> Self {
+        Matrix4::new(rows)
+    }
+}
+
+impl From<Matrix4> for [[Float; 4]; 4] {
+    fn from(matrix: Matrix4) -> Self {
+        matrix.data
+    }
+}
+
 impl Matrix4 {
-    pub fn create_and_fill(fill_value: f64) -> Matrix4 {
+    // the rows from top to bottom
+    pub fn new(rows: [[Float; 4]; 4]) -> Matrix4 {
+        Matrix4 { data: rows }
+    }
+
+    // for rows that are only known at runtime, fails unless there are 4 rows with 4 values each
+    pub fn from_rows<R: AsRef<[Float]>>(rows: &[R]) -> Result<Matrix4, MathError> {
+        if rows.len() != 4 {
+            return Err(MathError::RowCount {
+                expected: 4,
+                found: rows.len(),
+            });
+        }
+        let mut matrix = Matrix4::zeros();
+        for (row, values) in rows.iter().enumerate() {
+            let values = values.as_ref();
+            if values.len() != 4 {
+                return Err(MathError::RowLength {
+                    row,
+                    expected: 4,
+                    found: values.len(),
+                });
+            }
+            matrix.data[row].copy_from_slice(values);
+        }
+        Ok(matrix)
+    }
+
+    // the columns from left to right, the layout of opengl and gltf
+    pub fn from_columns(columns: [[Float; 4]; 4]) -> Matrix4 {
+        Matrix4::new(columns).transpose()
+    }
+
+    pub fn create_and_fill(fill_value: Float) -> Matrix4 {
         Matrix4 {
             data: [[fill_value; 4]; 4],
         }
@@ -94,67 +167,104 @@ impl Matrix4 {
         transposed_matrix
     }
 
-    fn submatrix(&self, row: usize, col: usize) -> Matrix3 {
-        let mut sub_matr = Matrix3::zeros();
-        let mut new_i = 0;
-        let mut new_j = 0;
+    // entry wise linear interpolation, exact for translations and scalings but rotations lose their
+    // rigidity in between
+    pub fn interpolate(&self, other: Matrix4, t: Float) -> Matrix4 {
+        let mut interpolated = Matrix4::zeros();
         for i in 0..=3 {
-            if i == row {
-                continue;
-            }
             for j in 0..=3 {
-                if j == col {
-                    continue;
-                }
-                sub_matr[[new_i, new_j]] = self[[i, j]];
-                new_j += 1;
+                interpolated[[i, j]] = self[[i, j]] + (other[[i, j]] - self[[i, j]]) * t;
             }
-            new_i += 1;
-            new_j = 0;
         }
-        sub_matr
+        interpolated
     }
 
-    fn minor(&self, row: usize, col: usize) -> f64 {
-        self.submatrix(row, col).determinant()
+    // determinants of the 2x2 blocks in the upper two rows (s) and the lower two rows (c), every
+    // cofactor of the matrix is built from them, so they are only computed once
+    fn block_determinants(&self) -> ([Float; 6], [Float; 6]) {
+        let m = &self.data;
+        let pair = |r: usize, i: usize, j: usize| m[r][i] * m[r + 1][j] - m[r + 1][i] * m[r][j];
+        let blocks = |r: usize| {
+            [
+                pair(r, 0, 1),
+                pair(r, 0, 2),
+                pair(r, 0, 3),
+                pair(r, 1, 2),
+                pair(r, 1, 3),
+                pair(r, 2, 3),
+            ]
+        };
+        (blocks(0), blocks(2))
     }
 
-    fn cofactor(&self, row: usize, col: usize) -> f64 {
-        let minor = self.minor(row, col);
-        if (row + col) % 2 != 0 {
-            return -minor;
-        }
-        minor
+    pub fn determinant(&self) -> Float {
+        let (s, c) = self.block_determinants();
+        determinant_from_blocks(&s, &c)
     }
 
-    pub fn determinant(&self) -> f64 {
-        self.cofactor(0, 0) * self[[0, 0]]
-            + self.cofactor(0, 1) * self[[0, 1]]
-            + self.cofactor(0, 2) * self[[0, 2]]
-            + self.cofactor(0, 3) * self[[0, 3]]
+    pub fn is_invertible(&self) -> bool {
+        self.is_invertible_within(SINGULAR_TOLERANCE)
     }
 
-    pub fn inverse(&self) -> Option<Matrix4> {
-        let det = self.determinant();
-        if float_eq(det, 0.0, FLOAT_EQ_EPS) {
-            return None;
-        }
+    // the matrix counts as singular if the determinant relative to the lengths of the rows equals
+    // 0 with the given tolerance
+    pub fn is_invertible_within(&self, tolerance: Tolerance) -> bool {
+        !is_singular(self.determinant(), &self.data, tolerance)
+    }
 
-        let mut inverse = Matrix4::zeros();
-        for i in 0..=3 {
-            for j in 0..=3 {
-                let cofactor = self.cofactor(i, j);
+    // the adjugate divided by the determinant
+    pub fn inverse(&self) -> Option<Matrix4> {
+        self.inverse_within(SINGULAR_TOLERANCE)
+    }
 
-                inverse[[j, i]] = cofactor / det;
-            }
+    // None if the matrix is singular with the given tolerance, see is_invertible_within
+    pub fn inverse_within(&self, tolerance: Tolerance) -> Option<Matrix4> {
+        let (s, c) = self.block_determinants();
+        let det = determinant_from_blocks(&s, &c);
+        if is_singular(det, &self.data, tolerance) {
+            return None;
         }
-        Option::Some(inverse)
+        let m = &self.data;
+        let adjugate = [
+            [
+                m[1][1] * c[5] - m[1][2] * c[4] + m[1][3] * c[3],
+                -m[0][1] * c[5] + m[0][2] * c[4] - m[0][3] * c[3],
+                m[3][1] * s[5] - m[3][2] * s[4] + m[3][3] * s[3],
+                -m[2][1] * s[5] + m[2][2] * s[4] - m[2][3] * s[3],
+            ],
+            [
+                -m[1][0] * c[5] + m[1][2] * c[2] - m[1][3] * c[1],
+                m[0][0] * c[5] - m[0][2] * c[2] + m[0][3] * c[1],
+                -m[3][0] * s[5] + m[3][2] * s[2] - m[3][3] * s[1],
+                m[2][0] * s[5] - m[2][2] * s[2] + m[2][3] * s[1],
+            ],
+            [
+                m[1][0] * c[4] - m[1][1] * c[2] + m[1][3] * c[0],
+                -m[0][0] * c[4] + m[0][1] * c[2] - m[0][3] * c[0],
+                m[3][0] * s[4] - m[3][1] * s[2] + m[3][3] * s[0],
+                -m[2][0] * s[4] + m[2][1] * s[2] - m[2][3] * s[0],
+            ],
+            [
+                -m[1][0] * c[3] + m[1][1] * c[1] - m[1][2] * c[0],
+                m[0][0] * c[3] - m[0][1] * c[1] + m[0][2] * c[0],
+                -m[3][0] * s[3] + m[3][1] * s[1] - m[3][2] * s[0],
+                m[2][0] * s[3] - m[2][1] * s[1] + m[2][2] * s[0],
+            ],
+        ];
+        Some(Matrix4 {
+            data: adjugate.map(|row| row.map(|value| value / det)),
+        })
     }
 }
 
+// laplace expansion along the upper two rows
+fn determinant_from_blocks(s: &[Float; 6], c: &[Float; 6]) -> Float {
+    s[0] * c[5] - s[1] * c[4] + s[2] * c[3] + s[3] * c[2] - s[4] * c[1] + s[5] * c[0]
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::math::Tuple4D;
+    use crate::math::{float_eq, Tuple4D, FLOAT_EQ_EPS};
 
     use super::*;
 
@@ -362,39 +472,6 @@ mod tests {
         assert_eq!(matrix.transpose(), expected)
     }
 
-    #[test]
-    fn submatrix() {
-        let mut matrix = Matrix4::zeros();
-        matrix[[0, 0]] = -2.0;
-        matrix[[0, 1]] = 1.0;
-        matrix[[0, 2]] = 2.0;
-        matrix[[0, 3]] = 3.0;
-        matrix[[1, 0]] = 3.0;
-        matrix[[1, 1]] = 2.0;
-        matrix[[1, 2]] = 1.0;
-        matrix[[1, 3]] = -1.0;
-        matrix[[2, 0]] = 4.0;
-        matrix[[2, 1]] = 3.0;
-        matrix[[2, 2]] = 6.0;
-        matrix[[2, 3]] = 5.0;
-        matrix[[3, 0]] = 1.0;
-        matrix[[3, 1]] = 2.0;
-        matrix[[3, 2]] = 7.0;
-        matrix[[3, 3]] = 8.0;
-
-        let mut expected_submatrix = Matrix3::zeros();
-        expected_submatrix[[0, 0]] = -2.0;
-        expected_submatrix[[0, 1]] = 2.0;
-        expected_submatrix[[0, 2]] = 3.0;
-        expected_submatrix[[1, 0]] = 3.0;
-        expected_submatrix[[1, 1]] = 1.0;
-        expected_submatrix[[1, 2]] = -1.0;
-        expected_submatrix[[2, 0]] = 4.0;
-        expected_submatrix[[2, 1]] = 6.0;
-        expected_submatrix[[2, 2]] = 5.0;
-        assert_eq!(matrix.submatrix(3, 1), expected_submatrix);
-    }
-
     #[test]
     fn determinant() {
         let mut matrix = Matrix4::zeros();
@@ -414,13 +491,15 @@ mod tests {
         matrix[[3, 1]] = 7.0;
         matrix[[3, 2]] = 7.0;
         matrix[[3, 3]] = -9.0;
-        println!("{}", matrix.cofactor(0, 1));
-
-        assert!(float_eq(matrix.cofactor(0, 0), 690.0, FLOAT_EQ_EPS));
-        assert!(float_eq(matrix.cofactor(0, 1), 447.0, FLOAT_EQ_EPS));
-        assert!(float_eq(matrix.cofactor(0, 2), 210.0, FLOAT_EQ_EPS));
-        assert!(float_eq(matrix.cofactor(0, 3), 51.0, FLOAT_EQ_EPS));
         assert!(float_eq(matrix.determinant(), -4071.0, FLOAT_EQ_EPS));
+        assert!(matrix.is_invertible());
+        // the transposed inverse times the determinant are the cofactors
+        let cofactor =
+            |i: usize, j: usize| matrix.inverse().unwrap()[[j, i]] * matrix.determinant();
+        assert!(float_eq(cofactor(0, 0), 690.0, FLOAT_EQ_EPS));
+        assert!(float_eq(cofactor(0, 1), 447.0, FLOAT_EQ_EPS));
+        assert!(float_eq(cofactor(0, 2), 210.0, FLOAT_EQ_EPS));
+        assert!(float_eq(cofactor(0, 3), 51.0, FLOAT_EQ_EPS));
     }
 
     #[test]
@@ -443,6 +522,7 @@ mod tests {
         matrix[[3, 2]] = 0.0;
         matrix[[3, 3]] = 0.0;
 
+        assert!(!matrix.is_invertible());
         assert_eq!(matrix.inverse(), None)
     }
 
@@ -467,7 +547,11 @@ mod tests {
         matrix[[3, 3]] = 4.0;
 
         assert!(float_eq(matrix.determinant(), 532.0, FLOAT_EQ_EPS));
-        assert!(float_eq(matrix.cofactor(2, 3), -160.0, FLOAT_EQ_EPS));
+        assert!(float_eq(
+            matrix.inverse().unwrap()[[3, 2]] * 532.0,
+            -160.0,
+            FLOAT_EQ_EPS
+        ));
 
         let mut expected_inv = Matrix4::zeros();
         expected_inv[[0, 0]] = 0.21805;
@@ -530,4 +614,154 @@ mod tests {
         let matrix3 = matrix * matrix2;
         assert_eq!(matrix, matrix3 * matrix2.inverse().unwrap())
     }
+
+    #[test]
+    fn inverse_of_chained_transforms() {
+        let transform = Matrix4::translation(1.0, -2.0, 3.0)
+            * Matrix4::rotation_y(0.7)
+            * Matrix4::shearing(0.5, 0.0, 0.2, 0.0, 0.0, 0.3)
+            * Matrix4::scaling(2.0, 0.5, 4.0);
+        let inverse = transform.inverse().unwrap();
+        assert_eq!(transform * inverse, Matrix4::eye());
+        assert_eq!(inverse * transform, Matrix4::eye());
+        assert!(!Matrix4::scaling(1.0, 0.0, 1.0).is_invertible());
+    }
+
+    #[test]
+    fn singularity_does_not_depend_on_the_scale() {
+        for scale in [1e-3, 0.05, 1.0, 1e4] {
+            let transform = Matrix4::scaling(scale, scale, scale) * Matrix4::rotation_x(0.3);
+            assert!(transform.is_invertible());
+            assert_eq!(transform * transform.inverse().unwrap(), Matrix4::eye());
+        }
+        // the rows of the shear are nearly parallel
+        let flat = Matrix4::new([
+            [1.0, 0.0, 0.0, 0.0],
+            [1.0, 1e-4, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ]);
+        assert!(flat.is_invertible());
+        assert!(!flat.is_invertible_within(Tolerance::Absolute(1e-3)));
+        assert!(!Matrix4::create_and_fill(Float::NAN).is_invertible());
+    }
+
+    #[test]
+    fn multiplying_references() {
+        let a = Matrix4::translation(1.0, 2.0, 3.0);
+        let b = Matrix4::rotation_x(0.5);
+        let point = Tuple4D::new_point(1.0, -1.0, 2.0);
+        let (ref_a, ref_b, ref_point) = (&a, &b, &point);
+        assert_eq!(ref_a * ref_b, a * b);
+        assert_eq!(ref_a * b, a * b);
+        assert_eq!(a * ref_b, a * b);
+        assert_eq!(ref_a * ref_point, a * point);
+        assert_eq!(a * ref_point, ref_a * point);
+        let mut c = a;
+        c *= ref_b;
+        c *= Matrix4::eye();
+        assert_eq!(c, a * b);
+    }
+
+    #[test]
+    fn interpolating_translations() {
+        let start = Matrix4::translation(0.0, 0.0, 0.0);
+        let end = Matrix4::translation(2.0, -4.0, 1.0);
+        assert_eq!(
+            start.interpolate(end, 0.25),
+            Matrix4::translation(0.5, -1.0, 0.25)
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn matrix_is_serialized_row_by_row() {
+        let matrix = Matrix4::translation(1.0, 2.0, 3.0);
+        let json = serde_json::to_string(&matrix).unwrap();
+        assert!(json.starts_with("[[1.0,0.0,0.0,1.0],[0.0,1.0,0.0,2.0]"));
+        assert_eq!(serde_json::from_str::<Matrix4>(&json).unwrap(), matrix);
+    }
+
+    #[test]
+    fn literal_constructors() {
+        let rows = [
+            [1.0, 2.0, 3.0, 4.0],
+            [5.0, 6.0, 7.0, 8.0],
+            [9.0, 8.0, 7.0, 6.0],
+            [5.0, 4.0, 3.0, 2.0],
+        ];
+        let matrix = Matrix4::new(rows);
+        assert_eq!(matrix[[1, 2]], 7.0);
+        assert_eq!(matrix[[2, 1]], 8.0);
+        assert_eq!(Matrix4::from(rows), matrix);
+        assert_eq!(<[[Float; 4]; 4]>::from(matrix), rows);
+        assert_eq!(Matrix4::from_columns(rows), matrix.transpose());
+        assert_eq!(Matrix4::from_rows(&rows), Ok(matrix));
+        assert_eq!(Matrix4::from_rows(&rows.map(Vec::from)), Ok(matrix));
+    }
+
+    #[test]
+    fn rows_of_the_wrong_size() {
+        assert_eq!(
+            Matrix4::from_rows(&[[1.0; 4]; 3]),
+            Err(MathError::RowCount {
+                expected: 4,
+                found: 3
+            })
+        );
+        let mut rows = vec![vec![0.0; 4]; 4];
+        rows[2].push(1.0);
+        assert_eq!(
+            Matrix4::from_rows(&rows).unwrap_err().to_string(),
+            "expected 4 values in row 2, got 5"
+        );
+    }
+
+    #[test]
+    fn display_aligns_columns() {
+        let matrix =
+            Matrix4::translation(-12.5, 0.0, 3.0) * Matrix4::rotation_z(crate::math::consts::PI);
+        assert_eq!(
+            matrix.to_string(),
+            "| -1.000  0.000 0.000 -12.500 |\n\
+             |  0.000 -1.000 0.000   0.000 |\n\
+             |  0.000  0.000 1.000   3.000 |\n\
+             |  0.000  0.000 0.000   1.000 |"
+        );
+        assert_eq!(
+            format!("{:.1}", Matrix4::eye()).lines().next(),
+            Some("| 1.0 0.0 0.0 0.0 |")
+        );
+    }
+
+    #[test]
+    fn homogeneous_tuples_keep_their_w() {
+        let tuple = Tuple4D {
+            x: 1.0,
+            y: 2.0,
+            z: 3.0,
+            w: 2.0,
+        };
+        let translated = Matrix4::translation(1.0, -1.0, 0.5) * tuple;
+        assert_eq!(translated.w, 2.0);
+        // the same point as translating the projected one
+        assert_eq!(
+            translated.project(),
+            Matrix4::translation(1.0, -1.0, 0.5) * tuple.project()
+        );
+    }
+
+    #[test]
+    fn comparing_with_explicit_tolerances() {
+        let matrix = Matrix4::rotation_x(0.5);
+        let mut close = matrix;
+        close[[1, 2]] += 1e-6;
+        assert_eq!(matrix, close);
+        assert!(!matrix.approx_eq(&close, Tolerance::Ulps(4)));
+        assert!(matrix.approx_eq(&close, Tolerance::Relative(1e-5)));
+        let mut far = Matrix4::scaling(1e6, 1.0, 1.0);
+        assert!(!far.approx_eq(&Matrix4::scaling(1e6 + 1.0, 1.0, 1.0), Tolerance::default()));
+        far[[0, 0]] += 1.0;
+        assert!(far.approx_eq(&Matrix4::scaling(1e6, 1.0, 1.0), Tolerance::Relative(1e-5)));
+    }
 }
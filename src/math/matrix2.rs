@@ -1,13 +1,17 @@
-use super::{float_eq, FLOAT_EQ_EPS};
-use std::ops::{Index, IndexMut};
+use super::{
+    default_tolerance, is_singular, write_rows, ApproxEq, Float, MathError, Tolerance,
+    SINGULAR_TOLERANCE,
+};
+use std::fmt;
+use std::ops::{Index, IndexMut, Mul};
 
 #[derive(Debug, Clone, Copy)]
 pub struct Matrix2 {
-    data: [[f64; 2]; 2],
+    data: [[Float; 2]; 2],
 }
 
 impl Index<[usize; 2]> for Matrix2 {
-    type Output = f64;
+    type Output = Float;
 
     fn index(&self, index: [usize; 2]) -> &Self::Output {
         &self.data[index[0]][index[1]]
@@ -20,21 +24,82 @@ impl IndexMut<[usize; 2]> for Matrix2 {
     }
 }
 
+impl fmt::Display for Matrix2 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_rows(f, &self.data)
+    }
+}
+
+impl ApproxEq for Matrix2 {
+    fn approx_eq(&self, other: &Self, tolerance: Tolerance) -> bool {
+        self.data.approx_eq(&other.data, tolerance)
+    }
+}
+
 impl PartialEq for Matrix2 {
     fn eq(&self, other: &Self) -> bool {
+        self.approx_eq(other, default_tolerance())
+    }
+}
+
+impl Mul<&Matrix2> for &Matrix2 {
+    type Output = Matrix2;
+
+    fn mul(self, rhs: &Matrix2) -> Self::Output {
+        let mut res = Matrix2::zeros();
         for i in 0..=1 {
             for j in 0..=1 {
-                if !float_eq(self[[i, j]], other[[i, j]], FLOAT_EQ_EPS) {
-                    return false;
-                }
+                res[[i, j]] = self[[i, 0]] * rhs[[0, j]] + self[[i, 1]] * rhs[[1, j]];
             }
         }
-        true
+        res
+    }
+}
+
+forward_ref_binop!(impl Mul, mul for Matrix2, Matrix2 => Matrix2);
+
+impl From<[[Float; 2]; 2]> for Matrix2 {
+    fn from(rows: [[Float; 2]; 2]) -> Self {
+        Matrix2::new(rows)
+    }
+}
+
+impl From<Matrix2> for [[Float; 2]; 2] {
+    fn from(matrix: Matrix2) -> Self {
+        matrix.data
     }
 }
 
 impl Matrix2 {
-    pub fn create_and_fill(fill_value: f64) -> Matrix2 {
+    // the rows from top to bottom
+    pub fn new(rows: [[Float; 2]; 2]) -> Matrix2 {
+        Matrix2 { data: rows }
+    }
+
+    // for rows that are only known at runtime, fails unless there are 2 rows with 2 values each
+    pub fn from_rows<R: AsRef<[Float]>>(rows: &[R]) -> Result<Matrix2, MathError> {
+        if rows.len() != 2 {
+            return Err(MathError::RowCount {
+                expected: 2,
+                found: rows.len(),
+            });
+        }
+        let mut matrix = Matrix2::zeros();
+        for (row, values) in rows.iter().enumerate() {
+            let values = values.as_ref();
+            if values.len() != 2 {
+                return Err(MathError::RowLength {
+                    row,
+                    expected: 2,
+                    found: values.len(),
+                });
+            }
+            matrix.data[row].copy_from_slice(values);
+        }
+        Ok(matrix)
+    }
+
+    pub fn create_and_fill(fill_value: Float) -> Matrix2 {
         Matrix2 {
             data: [[fill_value; 2]; 2],
         }
@@ -44,9 +109,35 @@ impl Matrix2 {
         Matrix2::create_and_fill(0.0)
     }
 
-    pub fn determinant(&self) -> f64 {
+    pub fn eye() -> Matrix2 {
+        let mut mat = Matrix2::zeros();
+        mat[[0, 0]] = 1.0;
+        mat[[1, 1]] = 1.0;
+        mat
+    }
+
+    pub fn determinant(&self) -> Float {
         self[[0, 0]] * self[[1, 1]] - self[[0, 1]] * self[[1, 0]]
     }
+
+    // the same rule as for Matrix4, see is_singular
+    pub fn is_invertible(&self) -> bool {
+        !is_singular(self.determinant(), &(*self).into(), SINGULAR_TOLERANCE)
+    }
+
+    // None for singular matrices
+    pub fn inverse(&self) -> Option<Matrix2> {
+        let det = self.determinant();
+        if is_singular(det, &(*self).into(), SINGULAR_TOLERANCE) {
+            return None;
+        }
+        let mut inverse = Matrix2::zeros();
+        inverse[[0, 0]] = self[[1, 1]] / det;
+        inverse[[0, 1]] = -self[[0, 1]] / det;
+        inverse[[1, 0]] = -self[[1, 0]] / det;
+        inverse[[1, 1]] = self[[0, 0]] / det;
+        Some(inverse)
+    }
 }
 
 #[cfg(test)]
@@ -64,4 +155,65 @@ mod tests {
         mat[[1, 1]] = 2.0;
         assert!(float_eq(mat.determinant(), 17.0, FLOAT_EQ_EPS))
     }
+
+    #[test]
+    fn matrix_equality() {
+        let mut mat = Matrix2::eye();
+        mat[[0, 1]] = 2.5;
+        let mut other = mat;
+        other[[0, 1]] += 0.000000001;
+        assert_eq!(mat, other);
+        other[[1, 0]] = 1.0;
+        assert_ne!(mat, other);
+    }
+
+    #[test]
+    fn inverse() {
+        let mut mat = Matrix2::zeros();
+        mat[[0, 0]] = 4.0;
+        mat[[0, 1]] = 7.0;
+        mat[[1, 0]] = 2.0;
+        mat[[1, 1]] = 6.0;
+        let inverse = mat.inverse().unwrap();
+        assert!(float_eq(inverse[[0, 0]], 0.6, FLOAT_EQ_EPS));
+        assert!(float_eq(inverse[[0, 1]], -0.7, FLOAT_EQ_EPS));
+        assert_eq!(mat * inverse, Matrix2::eye());
+        assert_eq!(inverse * mat, Matrix2::eye());
+    }
+
+    #[test]
+    fn singular_matrix_has_no_inverse() {
+        let mut mat = Matrix2::zeros();
+        mat[[0, 0]] = 1.0;
+        mat[[0, 1]] = 2.0;
+        mat[[1, 0]] = 2.0;
+        mat[[1, 1]] = 4.0;
+        assert!(!mat.is_invertible());
+        assert_eq!(mat.inverse(), None);
+    }
+
+    #[test]
+    fn literal_constructors() {
+        let matrix = Matrix2::new([[4.0, 7.0], [2.0, 6.0]]);
+        assert_eq!(matrix[[0, 1]], 7.0);
+        assert_eq!(Matrix2::from([[4.0, 7.0], [2.0, 6.0]]), matrix);
+        assert_eq!(Matrix2::from_rows(&[[4.0, 7.0], [2.0, 6.0]]), Ok(matrix));
+        assert_eq!(
+            Matrix2::from_rows(&[vec![4.0, 7.0], vec![2.0]]),
+            Err(MathError::RowLength {
+                row: 1,
+                expected: 2,
+                found: 1
+            })
+        );
+    }
+
+    #[test]
+    fn display() {
+        let matrix = Matrix2::new([[4.0, -7.24], [20.0, 6.0]]);
+        assert_eq!(
+            format!("{matrix:6.1}"),
+            "|    4.0   -7.2 |\n|   20.0    6.0 |"
+        );
+    }
 }
@@ -1,13 +1,17 @@
-use super::{float_eq, Matrix2, FLOAT_EQ_EPS};
-use std::ops::{Index, IndexMut};
+use super::{
+    default_tolerance, is_singular, write_rows, ApproxEq, Float, MathError, Matrix2, Matrix4,
+    Tolerance, Tuple4D, SINGULAR_TOLERANCE,
+};
+use std::fmt;
+use std::ops::{Index, IndexMut, Mul};
 
 #[derive(Debug, Clone, Copy)]
 pub struct Matrix3 {
-    data: [[f64; 3]; 3],
+    data: [[Float; 3]; 3],
 }
 
 impl Index<[usize; 2]> for Matrix3 {
-    type Output = f64;
+    type Output = Float;
 
     fn index(&self, index: [usize; 2]) -> &Self::Output {
         &self.data[index[0]][index[1]]
@@ -20,21 +24,99 @@ impl IndexMut<[usize; 2]> for Matrix3 {
     }
 }
 
+impl fmt::Display for Matrix3 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_rows(f, &self.data)
+    }
+}
+
+impl ApproxEq for Matrix3 {
+    fn approx_eq(&self, other: &Self, tolerance: Tolerance) -> bool {
+        self.data.approx_eq(&other.data, tolerance)
+    }
+}
+
 impl PartialEq for Matrix3 {
     fn eq(&self, other: &Self) -> bool {
+        self.approx_eq(other, default_tolerance())
+    }
+}
+
+impl Mul<&Matrix3> for &Matrix3 {
+    type Output = Matrix3;
+
+    fn mul(self, rhs: &Matrix3) -> Self::Output {
+        let mut res = Matrix3::zeros();
         for i in 0..=2 {
             for j in 0..=2 {
-                if !float_eq(self[[i, j]], other[[i, j]], FLOAT_EQ_EPS) {
-                    return false;
+                for k in 0..=2 {
+                    res[[i, j]] += self[[i, k]] * rhs[[k, j]];
                 }
             }
         }
-        true
+        res
+    }
+}
+
+forward_ref_binop!(impl Mul, mul for Matrix3, Matrix3 => Matrix3);
+
+// transforms x, y and z, w is kept so points stay points and vectors stay vectors
+impl Mul<&Tuple4D> for &Matrix3 {
+    type Output = Tuple4D;
+
+    fn mul(self, rhs: &Tuple4D) -> Self::Output {
+        let mut res = *rhs;
+        for i in 0..=2 {
+            res[i] = self[[i, 0]] * rhs[0] + self[[i, 1]] * rhs[1] + self[[i, 2]] * rhs[2];
+        }
+        res
+    }
+}
+
+forward_ref_binop!(impl Mul, mul for Matrix3, Tuple4D => Tuple4D);
+
+impl From<[[Float; 3]; 3]> for Matrix3 {
+    fn from(rows: [[Float; 3]; 3]) -> Self {
+        Matrix3::new(rows)
+    }
+}
+
+impl From<Matrix3> for [[Float; 3]; 3] {
+    fn from(matrix: Matrix3) -> Self {
+        matrix.data
     }
 }
 
 impl Matrix3 {
-    pub fn create_and_fill(fill_value: f64) -> Matrix3 {
+    // the rows from top to bottom
+    pub fn new(rows: [[Float; 3]; 3]) -> Matrix3 {
+        Matrix3 { data: rows }
+    }
+
+    // for rows that are only known at runtime, fails unless there are 3 rows with 3 values each
+    pub fn from_rows<R: AsRef<[Float]>>(rows: &[R]) -> Result<Matrix3, MathError> {
+        if rows.len() != 3 {
+            return Err(MathError::RowCount {
+                expected: 3,
+                found: rows.len(),
+            });
+        }
+        let mut matrix = Matrix3::zeros();
+        for (row, values) in rows.iter().enumerate() {
+            let values = values.as_ref();
+            if values.len() != 3 {
+                return Err(MathError::RowLength {
+                    row,
+                    expected: 3,
+                    found: values.len(),
+                });
+            }
+            matrix.data[row].copy_from_slice(values);
+        }
+        Ok(matrix)
+    }
+
+    pub fn create_and_fill(fill_value: Float) -> Matrix3 {
         Matrix3 {
             data: [[fill_value; 3]; 3],
         }
@@ -44,6 +126,36 @@ impl Matrix3 {
         Matrix3::create_and_fill(0.0)
     }
 
+    pub fn eye() -> Matrix3 {
+        let mut mat = Matrix3::zeros();
+        mat[[0, 0]] = 1.0;
+        mat[[1, 1]] = 1.0;
+        mat[[2, 2]] = 1.0;
+        mat
+    }
+
+    // the rotation, scaling and shearing part of a transform without its translation, which is
+    // all that normals and directions need
+    pub fn from_upper_left(matrix: &Matrix4) -> Matrix3 {
+        let mut mat = Matrix3::zeros();
+        for i in 0..=2 {
+            for j in 0..=2 {
+                mat[[i, j]] = matrix[[i, j]];
+            }
+        }
+        mat
+    }
+
+    pub fn transpose(&self) -> Matrix3 {
+        let mut transposed_matrix = Matrix3::zeros();
+        for i in 0..=2 {
+            for j in 0..=2 {
+                transposed_matrix[[i, j]] = self[[j, i]]
+            }
+        }
+        transposed_matrix
+    }
+
     fn submatrix(&self, row: usize, col: usize) -> Matrix2 {
         let mut sub_matr = Matrix2::zeros();
         let mut new_i = 0;
@@ -65,28 +177,48 @@ impl Matrix3 {
         sub_matr
     }
 
-    fn minor(&self, row: usize, col: usize) -> f64 {
+    fn minor(&self, row: usize, col: usize) -> Float {
         self.submatrix(row, col).determinant()
     }
 
-    fn cofactor(&self, row: usize, col: usize) -> f64 {
+    fn cofactor(&self, row: usize, col: usize) -> Float {
         let minor = self.minor(row, col);
-        if (row + col) % 2 != 0 {
+        if !(row + col).is_multiple_of(2) {
             return -minor;
         }
         minor
     }
 
-    pub fn determinant(&self) -> f64 {
+    pub fn determinant(&self) -> Float {
         self.cofactor(0, 0) * self[[0, 0]]
             + self.cofactor(0, 1) * self[[0, 1]]
             + self.cofactor(0, 2) * self[[0, 2]]
     }
+
+    // the same rule as for Matrix4, see is_singular
+    pub fn is_invertible(&self) -> bool {
+        !is_singular(self.determinant(), &(*self).into(), SINGULAR_TOLERANCE)
+    }
+
+    // the transposed cofactors divided by the determinant, None for singular matrices
+    pub fn inverse(&self) -> Option<Matrix3> {
+        let det = self.determinant();
+        if is_singular(det, &(*self).into(), SINGULAR_TOLERANCE) {
+            return None;
+        }
+        let mut inverse = Matrix3::zeros();
+        for i in 0..=2 {
+            for j in 0..=2 {
+                inverse[[j, i]] = self.cofactor(i, j) / det;
+            }
+        }
+        Some(inverse)
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::math::Matrix2;
+    use crate::math::{float_eq, Matrix2, FLOAT_EQ_EPS};
 
     use super::*;
 
@@ -200,4 +332,86 @@ mod tests {
         assert!(float_eq(matrix.cofactor(0, 2), -46.0, FLOAT_EQ_EPS));
         assert!(float_eq(matrix.determinant(), -196.0, FLOAT_EQ_EPS));
     }
+
+    fn example() -> Matrix3 {
+        Matrix3::new([[1.0, 2.0, 6.0], [-5.0, 8.0, -4.0], [2.0, 6.0, 4.0]])
+    }
+
+    #[test]
+    fn multiplication_and_transpose() {
+        let matrix = example();
+        let product = matrix * matrix.transpose();
+        assert!(float_eq(product[[0, 0]], 41.0, FLOAT_EQ_EPS));
+        assert!(float_eq(product[[0, 1]], -13.0, FLOAT_EQ_EPS));
+        assert!(float_eq(product[[2, 1]], 22.0, FLOAT_EQ_EPS));
+        assert_eq!(product, product.transpose());
+        assert_eq!(matrix * Matrix3::eye(), matrix);
+        assert_eq!(matrix.transpose()[[0, 1]], -5.0);
+    }
+
+    #[test]
+    fn inverse() {
+        let matrix = example();
+        let inverse = matrix.inverse().unwrap();
+        assert!(float_eq(inverse[[0, 0]], 56.0 / -196.0, FLOAT_EQ_EPS));
+        assert_eq!(matrix * inverse, Matrix3::eye());
+        assert_eq!(inverse * matrix, Matrix3::eye());
+        let mut singular = matrix;
+        singular[[2, 0]] = 2.0 * singular[[0, 0]];
+        singular[[2, 1]] = 2.0 * singular[[0, 1]];
+        singular[[2, 2]] = 2.0 * singular[[0, 2]];
+        assert!(!singular.is_invertible());
+        assert_eq!(singular.inverse(), None);
+    }
+
+    #[test]
+    fn sizes_agree_on_singularity() {
+        // singular, but rounding leaves a tiny determinant
+        let rows = [[0.1, 0.2, 0.3], [0.4, 0.5, 0.6], [0.7, 0.8, 0.9]];
+        let nearly_singular = Matrix3::new(rows);
+        let mut padded = Matrix4::eye();
+        for (i, row) in rows.iter().enumerate() {
+            for (j, &value) in row.iter().enumerate() {
+                padded[[i, j]] = value;
+            }
+        }
+        assert_eq!(nearly_singular.is_invertible(), padded.is_invertible());
+        assert!(!nearly_singular.is_invertible());
+        let scaled = Matrix3::new([[1e-3, 0.0, 0.0], [0.0, 1e-3, 0.0], [0.0, 0.0, 1e-3]]);
+        assert!(scaled.is_invertible());
+        assert!(Matrix2::new([[1e-3, 0.0], [0.0, 1e-3]]).is_invertible());
+        assert!(!Matrix2::new([[0.7, 0.1], [2.1, 0.3]]).is_invertible());
+    }
+
+    #[test]
+    fn multiplying_tuples_keeps_w() {
+        let transform = Matrix4::translation(5.0, 0.0, 0.0) * Matrix4::rotation_z(0.4);
+        let linear = Matrix3::from_upper_left(&transform);
+        let vector = Tuple4D::new_vector(1.0, 2.0, 3.0);
+        assert_eq!(linear * vector, transform * vector);
+        let point = Tuple4D::new_point(1.0, 2.0, 3.0);
+        assert_eq!(linear * point, Matrix4::rotation_z(0.4) * point);
+        // normals are transformed with the transposed inverse, the 4x4 version also gives the
+        // translation a w that has to be dropped
+        let normal = linear.inverse().unwrap().transpose() * vector;
+        let expected = transform.inverse().unwrap().transpose() * vector;
+        assert_eq!(
+            normal,
+            Tuple4D::new_vector(expected.x, expected.y, expected.z)
+        );
+    }
+
+    #[test]
+    fn literal_constructors() {
+        let matrix = example();
+        assert_eq!(matrix[[1, 0]], -5.0);
+        assert_eq!(Matrix3::from(<[[Float; 3]; 3]>::from(matrix)), matrix);
+        let rows = vec![
+            vec![1.0, 2.0, 6.0],
+            vec![-5.0, 8.0, -4.0],
+            vec![2.0, 6.0, 4.0],
+        ];
+        assert_eq!(Matrix3::from_rows(&rows), Ok(matrix));
+        assert!(Matrix3::from_rows(&rows[..2]).is_err());
+    }
 }
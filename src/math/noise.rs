@@ -0,0 +1,138 @@
+use super::{lerp, Float};
+// Ken Perlin's improved noise (2002), see https://mrl.cs.nyu.edu/~perlin/noise/
+const PERMUTATION: [u8; 256] = [
+    151, 160, 137, 91, 90, 15, 131, 13, 201, 95, 96, 53, 194, 233, 7, 225, 140, 36, 103, 30, 69,
+    142, 8, 99, 37, 240, 21, 10, 23, 190, 6, 148, 247, 120, 234, 75, 0, 26, 197, 62, 94, 252, 219,
+    203, 117, 35, 11, 32, 57, 177, 33, 88, 237, 149, 56, 87, 174, 20, 125, 136, 171, 168, 68, 175,
+    74, 165, 71, 134, 139, 48, 27, 166, 77, 146, 158, 231, 83, 111, 229, 122, 60, 211, 133, 230,
+    220, 105, 92, 41, 55, 46, 245, 40, 244, 102, 143, 54, 65, 25, 63, 161, 1, 216, 80, 73, 209, 76,
+    132, 187, 208, 89, 18, 169, 200, 196, 135, 130, 116, 188, 159, 86, 164, 100, 109, 198, 173,
+    186, 3, 64, 52, 217, 226, 250, 124, 123, 5, 202, 38, 147, 118, 126, 255, 82, 85, 212, 207, 206,
+    59, 227, 47, 16, 58, 17, 182, 189, 28, 42, 223, 183, 170, 213, 119, 248, 152, 2, 44, 154, 163,
+    70, 221, 153, 101, 155, 167, 43, 172, 9, 129, 22, 39, 253, 19, 98, 108, 110, 79, 113, 224, 232,
+    178, 185, 112, 104, 218, 246, 97, 228, 251, 34, 242, 193, 238, 210, 144, 12, 191, 179, 162,
+    241, 81, 51, 145, 235, 249, 14, 239, 107, 49, 192, 214, 31, 181, 199, 106, 157, 184, 84, 204,
+    176, 115, 121, 50, 45, 127, 4, 150, 254, 138, 236, 205, 93, 222, 114, 67, 29, 24, 72, 243, 141,
+    128, 195, 78, 66, 215, 61, 156, 180,
+];
+
+fn perm(idx: usize) -> usize {
+    PERMUTATION[idx & 255] as usize
+}
+
+fn fade(t: Float) -> Float {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+// dot product of the position with one of 12 gradient directions picked by the hash
+fn grad(hash: usize, x: Float, y: Float, z: Float) -> Float {
+    let h = hash & 15;
+    let u = if h < 8 { x } else { y };
+    let v = if h < 4 {
+        y
+    } else if h == 12 || h == 14 {
+        x
+    } else {
+        z
+    };
+    let u = if h & 1 == 0 { u } else { -u };
+    let v = if h & 2 == 0 { v } else { -v };
+    u + v
+}
+
+// smooth pseudo random value in roughly [-1, 1], zero on all integer lattice points
+pub fn perlin_noise(x: Float, y: Float, z: Float) -> Float {
+    let xi = x.floor().rem_euclid(256.0) as usize;
+    let yi = y.floor().rem_euclid(256.0) as usize;
+    let zi = z.floor().rem_euclid(256.0) as usize;
+    let x = x - x.floor();
+    let y = y - y.floor();
+    let z = z - z.floor();
+    let u = fade(x);
+    let v = fade(y);
+    let w = fade(z);
+
+    let a = perm(xi) + yi;
+    let aa = perm(a) + zi;
+    let ab = perm(a + 1) + zi;
+    let b = perm(xi + 1) + yi;
+    let ba = perm(b) + zi;
+    let bb = perm(b + 1) + zi;
+
+    lerp(
+        lerp(
+            lerp(grad(perm(aa), x, y, z), grad(perm(ba), x - 1.0, y, z), u),
+            lerp(
+                grad(perm(ab), x, y - 1.0, z),
+                grad(perm(bb), x - 1.0, y - 1.0, z),
+                u,
+            ),
+            v,
+        ),
+        lerp(
+            lerp(
+                grad(perm(aa + 1), x, y, z - 1.0),
+                grad(perm(ba + 1), x - 1.0, y, z - 1.0),
+                u,
+            ),
+            lerp(
+                grad(perm(ab + 1), x, y - 1.0, z - 1.0),
+                grad(perm(bb + 1), x - 1.0, y - 1.0, z - 1.0),
+                u,
+            ),
+            v,
+        ),
+        w,
+    )
+}
+
+// sum of octaves of noise with doubling frequency and halving amplitude
+pub fn turbulence(x: Float, y: Float, z: Float, octaves: usize) -> Float {
+    let mut sum = 0.0;
+    let mut frequency = 1.0;
+    let mut amplitude = 1.0;
+    for _ in 0..octaves {
+        sum += amplitude * perlin_noise(x * frequency, y * frequency, z * frequency);
+        frequency *= 2.0;
+        amplitude *= 0.5;
+    }
+    sum
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::{float_eq, FLOAT_EQ_EPS};
+
+    #[test]
+    fn noise_is_zero_on_lattice_points() {
+        assert!(float_eq(perlin_noise(0.0, 0.0, 0.0), 0.0, FLOAT_EQ_EPS));
+        assert!(float_eq(perlin_noise(1.0, 2.0, 3.0), 0.0, FLOAT_EQ_EPS));
+        assert!(float_eq(perlin_noise(-4.0, 7.0, -1.0), 0.0, FLOAT_EQ_EPS));
+    }
+
+    #[test]
+    fn noise_is_deterministic_and_bounded() {
+        for i in 0..100 {
+            let x = i as Float * 0.37 - 10.0;
+            let y = i as Float * 0.11 + 3.3;
+            let z = i as Float * -0.23;
+            let value = perlin_noise(x, y, z);
+            assert_eq!(value, perlin_noise(x, y, z));
+            assert!((-1.0..=1.0).contains(&value));
+        }
+    }
+
+    #[test]
+    fn noise_varies_between_lattice_points() {
+        let values: Vec<Float> = (0..10)
+            .map(|i| perlin_noise(0.5 + i as Float * 0.1, 0.3, 0.7))
+            .collect();
+        assert!(values.iter().any(|value| value.abs() > 0.01));
+    }
+
+    #[test]
+    fn turbulence_with_single_octave_is_noise() {
+        assert_eq!(turbulence(0.3, 0.6, 0.9, 1), perlin_noise(0.3, 0.6, 0.9));
+    }
+}
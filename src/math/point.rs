@@ -0,0 +1,358 @@
+use super::MathError;
+use crate::math::{
+    default_tolerance, format_entry, ApproxEq, Float, Matrix3, Matrix4, Tolerance, Tuple4D,
+};
+use std::fmt;
+use std::ops::{Add, AddAssign, Div, Mul, Neg, Sub, SubAssign};
+
+// a position in space. Points and vectors only allow the operations that make sense for them, so
+// adding two points or the dot product of a point do not compile, unlike with Tuple4D where they
+// panic at runtime. Both convert from and into Tuple4D for the code that works with tuples
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Point {
+    pub x: Float,
+    pub y: Float,
+    pub z: Float,
+}
+
+// a direction or an offset between two points
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Vector {
+    pub x: Float,
+    pub y: Float,
+    pub z: Float,
+}
+
+impl Point {
+    pub fn new(x: Float, y: Float, z: Float) -> Point {
+        Point { x, y, z }
+    }
+
+    pub fn origin() -> Point {
+        Point::new(0.0, 0.0, 0.0)
+    }
+
+    pub fn distance(self, other: Point) -> Float {
+        (self - other).magnitude()
+    }
+}
+
+impl Vector {
+    pub fn new(x: Float, y: Float, z: Float) -> Vector {
+        Vector { x, y, z }
+    }
+
+    pub fn zeros() -> Vector {
+        Vector::new(0.0, 0.0, 0.0)
+    }
+
+    pub fn magnitude(self) -> Float {
+        self.dot(self).sqrt()
+    }
+
+    pub fn normalize(self) -> Vector {
+        self / self.magnitude()
+    }
+
+    pub fn try_normalize(self) -> Result<Vector, MathError> {
+        let magnitude = self.magnitude();
+        if magnitude == 0.0 {
+            return Err(MathError::ZeroLength);
+        }
+        Ok(self / magnitude)
+    }
+
+    pub fn dot(self, other: Vector) -> Float {
+        self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    pub fn cross(self, other: Vector) -> Vector {
+        Vector::new(
+            self.y * other.z - self.z * other.y,
+            self.z * other.x - self.x * other.z,
+            self.x * other.y - self.y * other.x,
+        )
+    }
+
+    pub fn reflect(self, normal: Vector) -> Vector {
+        self - normal * 2.0 * self.dot(normal)
+    }
+}
+
+impl From<Point> for Tuple4D {
+    fn from(point: Point) -> Self {
+        Tuple4D::new_point(point.x, point.y, point.z)
+    }
+}
+
+impl From<Vector> for Tuple4D {
+    fn from(vector: Vector) -> Self {
+        Tuple4D::new_vector(vector.x, vector.y, vector.z)
+    }
+}
+
+impl TryFrom<Tuple4D> for Point {
+    type Error = MathError;
+
+    fn try_from(tuple: Tuple4D) -> Result<Self, Self::Error> {
+        if !tuple.is_point() {
+            return Err(MathError::NotAPoint(tuple));
+        }
+        Ok(Point::new(tuple.x, tuple.y, tuple.z))
+    }
+}
+
+impl TryFrom<Tuple4D> for Vector {
+    type Error = MathError;
+
+    fn try_from(tuple: Tuple4D) -> Result<Self, Self::Error> {
+        if !tuple.is_vector() {
+            return Err(MathError::NotAVector(tuple));
+        }
+        Ok(Vector::new(tuple.x, tuple.y, tuple.z))
+    }
+}
+
+impl fmt::Display for Point {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [x, y, z] = [self.x, self.y, self.z].map(|value| format_entry(value, f));
+        write!(f, "point({x}, {y}, {z})")
+    }
+}
+
+impl fmt::Display for Vector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [x, y, z] = [self.x, self.y, self.z].map(|value| format_entry(value, f));
+        write!(f, "vector({x}, {y}, {z})")
+    }
+}
+
+impl ApproxEq for Point {
+    fn approx_eq(&self, other: &Self, tolerance: Tolerance) -> bool {
+        [self.x, self.y, self.z].approx_eq(&[other.x, other.y, other.z], tolerance)
+    }
+}
+
+impl PartialEq for Point {
+    fn eq(&self, other: &Self) -> bool {
+        self.approx_eq(other, default_tolerance())
+    }
+}
+
+impl ApproxEq for Vector {
+    fn approx_eq(&self, other: &Self, tolerance: Tolerance) -> bool {
+        [self.x, self.y, self.z].approx_eq(&[other.x, other.y, other.z], tolerance)
+    }
+}
+
+impl PartialEq for Vector {
+    fn eq(&self, other: &Self) -> bool {
+        self.approx_eq(other, default_tolerance())
+    }
+}
+
+impl Add<&Vector> for &Point {
+    type Output = Point;
+
+    fn add(self, other: &Vector) -> Self::Output {
+        Point::new(self.x + other.x, self.y + other.y, self.z + other.z)
+    }
+}
+
+forward_ref_binop!(impl Add, add for Point, Vector => Point);
+
+impl Sub<&Vector> for &Point {
+    type Output = Point;
+
+    fn sub(self, other: &Vector) -> Self::Output {
+        Point::new(self.x - other.x, self.y - other.y, self.z - other.z)
+    }
+}
+
+forward_ref_binop!(impl Sub, sub for Point, Vector => Point);
+
+impl Sub<&Point> for &Point {
+    type Output = Vector;
+
+    fn sub(self, other: &Point) -> Self::Output {
+        Vector::new(self.x - other.x, self.y - other.y, self.z - other.z)
+    }
+}
+
+forward_ref_binop!(impl Sub, sub for Point, Point => Vector);
+
+impl Add<&Vector> for &Vector {
+    type Output = Vector;
+
+    fn add(self, other: &Vector) -> Self::Output {
+        Vector::new(self.x + other.x, self.y + other.y, self.z + other.z)
+    }
+}
+
+forward_ref_binop!(impl Add, add for Vector, Vector => Vector);
+
+impl Sub<&Vector> for &Vector {
+    type Output = Vector;
+
+    fn sub(self, other: &Vector) -> Self::Output {
+        Vector::new(self.x - other.x, self.y - other.y, self.z - other.z)
+    }
+}
+
+forward_ref_binop!(impl Sub, sub for Vector, Vector => Vector);
+
+impl Neg for Vector {
+    type Output = Vector;
+
+    fn neg(self) -> Self::Output {
+        Vector::new(-self.x, -self.y, -self.z)
+    }
+}
+
+impl Mul<Float> for Vector {
+    type Output = Vector;
+
+    fn mul(self, rhs: Float) -> Self::Output {
+        Vector::new(self.x * rhs, self.y * rhs, self.z * rhs)
+    }
+}
+
+impl Div<Float> for Vector {
+    type Output = Vector;
+
+    fn div(self, rhs: Float) -> Self::Output {
+        Vector::new(self.x / rhs, self.y / rhs, self.z / rhs)
+    }
+}
+
+impl AddAssign<Vector> for Point {
+    fn add_assign(&mut self, rhs: Vector) {
+        *self = *self + rhs;
+    }
+}
+
+impl SubAssign<Vector> for Point {
+    fn sub_assign(&mut self, rhs: Vector) {
+        *self = *self - rhs;
+    }
+}
+
+impl AddAssign for Vector {
+    fn add_assign(&mut self, rhs: Vector) {
+        *self = *self + rhs;
+    }
+}
+
+impl SubAssign for Vector {
+    fn sub_assign(&mut self, rhs: Vector) {
+        *self = *self - rhs;
+    }
+}
+
+// divided by w, so projective transforms work as well
+impl Mul<&Point> for &Matrix4 {
+    type Output = Point;
+
+    fn mul(self, rhs: &Point) -> Self::Output {
+        let tuple = (self * Tuple4D::from(*rhs)).project();
+        Point::new(tuple.x, tuple.y, tuple.z)
+    }
+}
+
+forward_ref_binop!(impl Mul, mul for Matrix4, Point => Point);
+
+// vectors are not translated
+impl Mul<&Vector> for &Matrix4 {
+    type Output = Vector;
+
+    fn mul(self, rhs: &Vector) -> Self::Output {
+        let tuple = self * Tuple4D::from(*rhs);
+        Vector::new(tuple.x, tuple.y, tuple.z)
+    }
+}
+
+forward_ref_binop!(impl Mul, mul for Matrix4, Vector => Vector);
+
+impl Mul<&Vector> for &Matrix3 {
+    type Output = Vector;
+
+    fn mul(self, rhs: &Vector) -> Self::Output {
+        let tuple = self * Tuple4D::from(*rhs);
+        Vector::new(tuple.x, tuple.y, tuple.z)
+    }
+}
+
+forward_ref_binop!(impl Mul, mul for Matrix3, Vector => Vector);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::{float_eq, FLOAT_EQ_EPS};
+
+    #[test]
+    fn points_and_vectors() {
+        let from = Point::new(3.0, 2.0, 1.0);
+        let to = Point::new(5.0, 6.0, 7.0);
+        let offset = to - from;
+        assert_eq!(offset, Vector::new(2.0, 4.0, 6.0));
+        assert_eq!(from + offset, to);
+        assert_eq!(to - offset, from);
+        assert!(float_eq(from.distance(to), Float::sqrt(56.0), FLOAT_EQ_EPS));
+        let mut point = from;
+        point += offset * 0.5;
+        assert_eq!(point, Point::new(4.0, 4.0, 4.0));
+    }
+
+    #[test]
+    fn vector_products_match_tuples() {
+        let a = Vector::new(1.0, 2.0, 3.0);
+        let b = Vector::new(2.0, 3.0, 4.0);
+        let (ta, tb) = (Tuple4D::from(a), Tuple4D::from(b));
+        assert_eq!(a.dot(b), ta.dot(tb));
+        assert_eq!(Tuple4D::from(a.cross(b)), ta.cross(tb));
+        assert_eq!(Tuple4D::from(a.normalize()), ta.normalize());
+        let normal = Vector::new(0.0, 1.0, 0.0);
+        assert_eq!(
+            Vector::new(1.0, -1.0, 0.0).reflect(normal),
+            Vector::new(1.0, 1.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn converting_tuples() {
+        let point = Tuple4D::new_point(1.0, 2.0, 3.0);
+        let vector = Tuple4D::new_vector(1.0, 2.0, 3.0);
+        assert_eq!(Point::try_from(point), Ok(Point::new(1.0, 2.0, 3.0)));
+        assert_eq!(Vector::try_from(vector), Ok(Vector::new(1.0, 2.0, 3.0)));
+        assert_eq!(Point::try_from(vector), Err(MathError::NotAPoint(vector)));
+        assert_eq!(Vector::try_from(point), Err(MathError::NotAVector(point)));
+        assert_eq!(Vector::zeros().try_normalize(), Err(MathError::ZeroLength));
+    }
+
+    #[test]
+    fn transforming_points_and_vectors() {
+        let transform = Matrix4::translation(5.0, -3.0, 2.0) * Matrix4::scaling(2.0, 2.0, 2.0);
+        assert_eq!(
+            transform * Point::new(1.0, 1.0, 1.0),
+            Point::new(7.0, -1.0, 4.0)
+        );
+        assert_eq!(
+            Matrix4::perspective(1.0, 1.0, 1.0, 3.0) * Point::new(0.0, 0.0, -3.0),
+            Point::new(0.0, 0.0, 1.0)
+        );
+        assert_eq!(
+            transform * Vector::new(1.0, 1.0, 1.0),
+            Vector::new(2.0, 2.0, 2.0)
+        );
+        assert_eq!(
+            Matrix3::from_upper_left(&transform) * Vector::new(1.0, 0.0, 0.0),
+            Vector::new(2.0, 0.0, 0.0)
+        );
+        assert_eq!(
+            Point::new(1.0, -0.5, 2.0).to_string(),
+            "point(1.000, -0.500, 2.000)"
+        );
+    }
+}
@@ -0,0 +1,247 @@
+use std::ops::Mul;
+
+use super::{default_tolerance, ApproxEq, Float, Matrix4, Tolerance, Tuple4D};
+
+// rotations as unit quaternions, which unlike matrices can be interpolated without losing their
+// rigidity and can be renormalized after many products. q and -q are the same rotation
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Quaternion {
+    pub w: Float,
+    pub x: Float,
+    pub y: Float,
+    pub z: Float,
+}
+
+impl Quaternion {
+    pub fn new(w: Float, x: Float, y: Float, z: Float) -> Quaternion {
+        Quaternion { w, x, y, z }
+    }
+
+    pub fn identity() -> Quaternion {
+        Quaternion::new(1.0, 0.0, 0.0, 0.0)
+    }
+
+    // counterclockwise by angle in radians around the axis when looking against its direction,
+    // like the rotation matrices. The axis does not have to be normalized
+    pub fn from_axis_angle(axis: Tuple4D, angle: Float) -> Quaternion {
+        let axis = Tuple4D::new_vector(axis.x, axis.y, axis.z);
+        let length = axis.magnitude();
+        if length == 0.0 {
+            return Quaternion::identity();
+        }
+        let (sin, cos) = (angle / 2.0).sin_cos();
+        let axis = axis * (sin / length);
+        Quaternion::new(cos, axis.x, axis.y, axis.z)
+    }
+
+    // the rotation of the upper left 3x3 block, which has to be a rotation without scale or shear.
+    // Shepperd's method, starting from the largest component for precision
+    pub fn from_matrix(matrix: &Matrix4) -> Quaternion {
+        let r = |i: usize, j: usize| matrix[[i, j]];
+        let trace = r(0, 0) + r(1, 1) + r(2, 2);
+        let quaternion = if trace > 0.0 {
+            let s = (trace + 1.0).sqrt() * 2.0;
+            Quaternion::new(
+                s / 4.0,
+                (r(2, 1) - r(1, 2)) / s,
+                (r(0, 2) - r(2, 0)) / s,
+                (r(1, 0) - r(0, 1)) / s,
+            )
+        } else if r(0, 0) > r(1, 1) && r(0, 0) > r(2, 2) {
+            let s = (1.0 + r(0, 0) - r(1, 1) - r(2, 2)).sqrt() * 2.0;
+            Quaternion::new(
+                (r(2, 1) - r(1, 2)) / s,
+                s / 4.0,
+                (r(0, 1) + r(1, 0)) / s,
+                (r(0, 2) + r(2, 0)) / s,
+            )
+        } else if r(1, 1) > r(2, 2) {
+            let s = (1.0 + r(1, 1) - r(0, 0) - r(2, 2)).sqrt() * 2.0;
+            Quaternion::new(
+                (r(0, 2) - r(2, 0)) / s,
+                (r(0, 1) + r(1, 0)) / s,
+                s / 4.0,
+                (r(1, 2) + r(2, 1)) / s,
+            )
+        } else {
+            let s = (1.0 + r(2, 2) - r(0, 0) - r(1, 1)).sqrt() * 2.0;
+            Quaternion::new(
+                (r(1, 0) - r(0, 1)) / s,
+                (r(0, 2) + r(2, 0)) / s,
+                (r(1, 2) + r(2, 1)) / s,
+                s / 4.0,
+            )
+        };
+        quaternion.normalize()
+    }
+
+    pub fn dot(self, other: Quaternion) -> Float {
+        self.w * other.w + self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    pub fn magnitude(self) -> Float {
+        self.dot(self).sqrt()
+    }
+
+    pub fn normalize(self) -> Quaternion {
+        let length = self.magnitude();
+        Quaternion::new(
+            self.w / length,
+            self.x / length,
+            self.y / length,
+            self.z / length,
+        )
+    }
+
+    // the opposite rotation for unit quaternions
+    pub fn conjugate(self) -> Quaternion {
+        Quaternion::new(self.w, -self.x, -self.y, -self.z)
+    }
+
+    // along the shorter of the two arcs between the rotations, t outside of [0, 1] continues the
+    // rotation, which the cubic splines of animations rely on
+    pub fn slerp(self, other: Quaternion, t: Float) -> Quaternion {
+        let (a, mut b) = (self, other);
+        let mut dot = a.dot(b);
+        if dot < 0.0 {
+            dot = -dot;
+            b = Quaternion::new(-b.w, -b.x, -b.y, -b.z);
+        }
+        let (weight_a, weight_b) = if dot > 0.9995 {
+            // nearly the same rotation, blending linearly avoids dividing by sin of almost 0
+            (1.0 - t, t)
+        } else {
+            let angle = dot.min(1.0).acos();
+            (
+                ((1.0 - t) * angle).sin() / angle.sin(),
+                (t * angle).sin() / angle.sin(),
+            )
+        };
+        Quaternion::new(
+            a.w * weight_a + b.w * weight_b,
+            a.x * weight_a + b.x * weight_b,
+            a.y * weight_a + b.y * weight_b,
+            a.z * weight_a + b.z * weight_b,
+        )
+    }
+
+    // the rotation matrix, the quaternion is normalized first
+    pub fn to_matrix(self) -> Matrix4 {
+        let Quaternion { w, x, y, z } = self.normalize();
+        Matrix4::new([
+            [
+                1.0 - 2.0 * (y * y + z * z),
+                2.0 * (x * y - w * z),
+                2.0 * (x * z + w * y),
+                0.0,
+            ],
+            [
+                2.0 * (x * y + w * z),
+                1.0 - 2.0 * (x * x + z * z),
+                2.0 * (y * z - w * x),
+                0.0,
+            ],
+            [
+                2.0 * (x * z - w * y),
+                2.0 * (y * z + w * x),
+                1.0 - 2.0 * (x * x + y * y),
+                0.0,
+            ],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+}
+
+impl From<Quaternion> for Matrix4 {
+    fn from(quaternion: Quaternion) -> Self {
+        quaternion.to_matrix()
+    }
+}
+
+impl ApproxEq for Quaternion {
+    fn approx_eq(&self, other: &Self, tolerance: Tolerance) -> bool {
+        [self.w, self.x, self.y, self.z].approx_eq(&[other.w, other.x, other.y, other.z], tolerance)
+    }
+}
+
+impl PartialEq for Quaternion {
+    fn eq(&self, other: &Self) -> bool {
+        self.approx_eq(other, default_tolerance())
+    }
+}
+
+// the rotation by rhs followed by self, like the product of their matrices
+impl Mul<&Quaternion> for &Quaternion {
+    type Output = Quaternion;
+
+    fn mul(self, rhs: &Quaternion) -> Self::Output {
+        Quaternion::new(
+            self.w * rhs.w - self.x * rhs.x - self.y * rhs.y - self.z * rhs.z,
+            self.w * rhs.x + self.x * rhs.w + self.y * rhs.z - self.z * rhs.y,
+            self.w * rhs.y - self.x * rhs.z + self.y * rhs.w + self.z * rhs.x,
+            self.w * rhs.z + self.x * rhs.y - self.y * rhs.x + self.z * rhs.w,
+        )
+    }
+}
+
+forward_ref_binop!(impl Mul, mul for Quaternion, Quaternion => Quaternion);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::consts::{FRAC_PI_2, PI};
+    use crate::math::{float_eq, FLOAT_EQ_EPS};
+
+    #[test]
+    fn axis_angle_matches_rotation_matrices() {
+        let x = Quaternion::from_axis_angle(Tuple4D::new_vector(2.0, 0.0, 0.0), 0.7);
+        assert_eq!(x.to_matrix(), Matrix4::rotation_x(0.7));
+        let y = Quaternion::from_axis_angle(Tuple4D::new_vector(0.0, 1.0, 0.0), -1.2);
+        assert_eq!(Matrix4::from(y), Matrix4::rotation_y(-1.2));
+        let z = Quaternion::from_axis_angle(Tuple4D::new_vector(0.0, 0.0, 1.0), PI);
+        assert_eq!(z.to_matrix(), Matrix4::rotation_z(PI));
+        assert_eq!((x * y).to_matrix(), x.to_matrix() * y.to_matrix());
+        assert_eq!(x * x.conjugate(), Quaternion::identity());
+    }
+
+    #[test]
+    fn matrices_are_converted_back() {
+        // every branch of Shepperd's method
+        for angle in [0.3, 2.0, PI, -2.5] {
+            for axis in [
+                Tuple4D::new_vector(1.0, 0.0, 0.0),
+                Tuple4D::new_vector(0.0, 1.0, 0.0),
+                Tuple4D::new_vector(0.0, 0.0, 1.0),
+                Tuple4D::new_vector(1.0, -2.0, 0.5),
+            ] {
+                let quaternion = Quaternion::from_axis_angle(axis, angle);
+                let restored = Quaternion::from_matrix(&quaternion.to_matrix());
+                assert!(float_eq(restored.dot(quaternion).abs(), 1.0, FLOAT_EQ_EPS));
+            }
+        }
+    }
+
+    #[test]
+    fn slerp_keeps_the_angular_speed() {
+        let axis = Tuple4D::new_vector(0.0, 1.0, 0.0);
+        let start = Quaternion::identity();
+        let end = Quaternion::from_axis_angle(axis, FRAC_PI_2);
+        assert_eq!(
+            start.slerp(end, 0.25),
+            Quaternion::from_axis_angle(axis, FRAC_PI_2 / 4.0)
+        );
+        assert_eq!(start.slerp(end, 1.0), end);
+        // the opposite sign is the same rotation and does not take the long way around
+        let flipped = Quaternion::new(-end.w, -end.x, -end.y, -end.z);
+        assert_eq!(
+            start.slerp(flipped, 0.5).to_matrix(),
+            Matrix4::rotation_y(FRAC_PI_2 / 2.0)
+        );
+        assert!(float_eq(
+            start.slerp(end, 0.6).magnitude(),
+            1.0,
+            FLOAT_EQ_EPS
+        ));
+    }
+}
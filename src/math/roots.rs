@@ -0,0 +1,317 @@
+use super::consts::PI;
+use super::Float;
+
+// coefficients go from the highest to the lowest power, the real roots are returned in ascending
+// order. Repeated roots may show up more than once
+
+// a x^2 + b x + c = 0, falls back to the linear equation if a is zero. Avoids the cancellation of
+// the textbook formula when b is much larger than the discriminant
+pub fn solve_quadratic(a: Float, b: Float, c: Float) -> Vec<Float> {
+    if a == 0.0 {
+        return if b == 0.0 { vec![] } else { vec![-c / b] };
+    }
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        return vec![];
+    }
+    // b + sign(b) sqrt(discriminant) adds two numbers of the same sign, -0 counts as positive so
+    // the vectorized sphere test of ray packets gives the same roots
+    let sign = if b < 0.0 { -1.0 } else { 1.0 };
+    let q = -0.5 * (b + sign * discriminant.sqrt());
+    let mut roots = if q == 0.0 {
+        // b and c are both zero
+        vec![0.0, 0.0]
+    } else {
+        vec![q / a, c / q]
+    };
+    roots.sort_by(Float::total_cmp);
+    roots
+}
+
+// a x^3 + b x^2 + c x + d = 0, with Cardano's formula for one real root and the trigonometric
+// solution for three, each refined with newton steps
+pub fn solve_cubic(a: Float, b: Float, c: Float, d: Float) -> Vec<Float> {
+    if a == 0.0 {
+        return solve_quadratic(b, c, d);
+    }
+    let (b, c, d) = (b / a, c / a, d / a);
+    // substituting x = t - b/3 removes the quadratic term: t^3 + p t + q = 0
+    let shift = b / 3.0;
+    let p = c - b * b / 3.0;
+    let q = 2.0 * b * b * b / 27.0 - b * c / 3.0 + d;
+    let discriminant = (q / 2.0).powi(2) + (p / 3.0).powi(3);
+    let mut roots = if p.abs() < 1e-12 {
+        vec![(-q).cbrt()]
+    } else if discriminant > 0.0 {
+        // the root is u + v with u v = -p/3. u is taken from the sum in which q/2 and the root of
+        // the discriminant do not cancel, v follows from the product
+        let sign = if q < 0.0 { -1.0 } else { 1.0 };
+        let u = (-(q / 2.0 + sign * discriminant.sqrt())).cbrt();
+        let v = if u == 0.0 { 0.0 } else { -p / (3.0 * u) };
+        vec![u + v]
+    } else {
+        let radius = 2.0 * (-p / 3.0).sqrt();
+        let angle = ((3.0 * q / (2.0 * p)) * (-3.0 / p).sqrt())
+            .clamp(-1.0, 1.0)
+            .acos()
+            / 3.0;
+        (0..3)
+            .map(|k| radius * (angle - 2.0 * PI * k as Float / 3.0).cos())
+            .collect()
+    };
+    for root in roots.iter_mut() {
+        *root = polish_root(&[1.0, b, c, d], *root - shift);
+    }
+    roots.sort_by(Float::total_cmp);
+    roots
+}
+
+// a x^4 + b x^3 + c x^2 + d x + e = 0 with Ferrari's method, the quartic is split into two
+// quadratics with the help of a root of the resolvent cubic. The roots are refined with a few newton
+// steps afterwards, as the method loses precision for the nearly repeated roots of grazing rays
+pub fn solve_quartic(a: Float, b: Float, c: Float, d: Float, e: Float) -> Vec<Float> {
+    if a == 0.0 {
+        return solve_cubic(b, c, d, e);
+    }
+    let (b, c, d, e) = (b / a, c / a, d / a, e / a);
+    // substituting x = y - b/4 removes the cubic term: y^4 + p y^2 + q y + r = 0
+    let shift = b / 4.0;
+    let b2 = b * b;
+    let p = c - 3.0 * b2 / 8.0;
+    let q = d - b * c / 2.0 + b2 * b / 8.0;
+    let r = e - b * d / 4.0 + b2 * c / 16.0 - 3.0 * b2 * b2 / 256.0;
+    let mut roots: Vec<Float> = if q.abs() < 1e-12 {
+        // biquadratic, solved for y^2
+        solve_quadratic(1.0, p, r)
+            .into_iter()
+            .filter(|&z| z >= 0.0)
+            .flat_map(|z| [-z.sqrt(), z.sqrt()])
+            .collect()
+    } else {
+        // for q != 0 the resolvent has a positive root
+        let m = solve_cubic(1.0, p, p * p / 4.0 - r, -q * q / 8.0)
+            .into_iter()
+            .fold(Float::NEG_INFINITY, Float::max);
+        if m <= 0.0 || !m.is_finite() {
+            // only possible through rounding, the general solver does not depend on the resolvent
+            return solve_polynomial(&[1.0, b, c, d, e]);
+        }
+        let sqrt_2m = (2.0 * m).sqrt();
+        let offset = sqrt_2m * q / (4.0 * m);
+        let mut roots = solve_quadratic(1.0, sqrt_2m, p / 2.0 + m - offset);
+        roots.extend(solve_quadratic(1.0, -sqrt_2m, p / 2.0 + m + offset));
+        roots
+    };
+    for root in roots.iter_mut() {
+        *root = polish_root(&[1.0, b, c, d, e], *root - shift);
+    }
+    roots.sort_by(Float::total_cmp);
+    roots
+}
+
+// the real roots of a polynomial of any degree, in ascending order and repeated roots only once.
+// Between two neighboring roots of the derivative the polynomial is monotonic, so every such
+// interval with a sign change holds exactly one root, which is found by bisection sped up with
+// newton steps. Slower than the closed forms but it does not lose roots to cancellation
+pub fn solve_polynomial(coefficients: &[Float]) -> Vec<Float> {
+    let leading = coefficients.iter().position(|&c| c != 0.0);
+    let Some(leading) = leading else {
+        return vec![];
+    };
+    let coefficients: Vec<Float> = coefficients[leading..]
+        .iter()
+        .map(|c| c / coefficients[leading])
+        .collect();
+    let degree = coefficients.len() - 1;
+    match degree {
+        0 => return vec![],
+        1 => return vec![-coefficients[1]],
+        2 => {
+            let mut roots = solve_quadratic(1.0, coefficients[1], coefficients[2]);
+            roots.dedup();
+            return roots;
+        }
+        _ => (),
+    }
+    let derivative: Vec<Float> = coefficients[..degree]
+        .iter()
+        .enumerate()
+        .map(|(i, c)| c * (degree - i) as Float)
+        .collect();
+    // all roots lie within the cauchy bound
+    let bound = 1.0
+        + coefficients[1..]
+            .iter()
+            .fold(0.0, |max: Float, c| max.max(c.abs()));
+    let mut ends = vec![-bound];
+    ends.extend(solve_polynomial(&derivative));
+    ends.push(bound);
+    let mut roots: Vec<Float> = vec![];
+    for pair in ends.windows(2) {
+        let (low, high) = (pair[0], pair[1]);
+        let (value_low, value_high) = (
+            evaluate(&coefficients, low).0,
+            evaluate(&coefficients, high).0,
+        );
+        let root = if is_zero(&coefficients, low) {
+            // a root at an extremum touches zero without changing the sign
+            Some(low)
+        } else if is_zero(&coefficients, high) {
+            // found as the start of the next interval
+            None
+        } else if value_low.signum() != value_high.signum() {
+            Some(bisect(&coefficients, low, high, value_low))
+        } else {
+            None
+        };
+        if let Some(root) = root {
+            if roots.last().is_none_or(|&last| last < root) {
+                roots.push(root);
+            }
+        }
+    }
+    roots
+}
+
+// value and derivative of the polynomial at x with horner's scheme
+fn evaluate(coefficients: &[Float], x: Float) -> (Float, Float) {
+    coefficients
+        .iter()
+        .fold((0.0, 0.0), |(value, derivative), coefficient| {
+            (value * x + coefficient, derivative * x + value)
+        })
+}
+
+// zero up to the rounding errors of evaluating it, horner loses at most two roundings per degree
+fn is_zero(coefficients: &[Float], x: Float) -> bool {
+    let magnitude = coefficients
+        .iter()
+        .fold(0.0, |sum: Float, c| sum * x.abs() + c.abs());
+    evaluate(coefficients, x).0.abs()
+        <= 2.0 * (coefficients.len() - 1) as Float * Float::EPSILON * magnitude
+}
+
+// the root in [low, high] where the polynomial changes its sign, newton steps that stay inside of
+// the interval replace the bisection steps
+fn bisect(coefficients: &[Float], mut low: Float, mut high: Float, value_low: Float) -> Float {
+    let mut x = (low + high) / 2.0;
+    for _ in 0..200 {
+        let (value, derivative) = evaluate(coefficients, x);
+        if value == 0.0 {
+            return x;
+        }
+        if value.signum() == value_low.signum() {
+            low = x;
+        } else {
+            high = x;
+        }
+        let newton = x - value / derivative;
+        let next = if newton > low && newton < high {
+            newton
+        } else {
+            (low + high) / 2.0
+        };
+        if next == x || high - low <= Float::EPSILON * x.abs() {
+            return next;
+        }
+        x = next;
+    }
+    x
+}
+
+// newton iterations on the polynomial with the given coefficients, stops once a step does not
+// bring the value closer to zero, which happens next to double roots where the derivative vanishes
+fn polish_root(coefficients: &[Float], root: Float) -> Float {
+    let mut x = root;
+    let (mut value, mut derivative) = evaluate(coefficients, x);
+    for _ in 0..4 {
+        let next = x - value / derivative;
+        let (next_value, next_derivative) = evaluate(coefficients, next);
+        if next_value.is_nan() || next_value.abs() >= value.abs() {
+            break;
+        }
+        (x, value, derivative) = (next, next_value, next_derivative);
+    }
+    x
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::{float_eq, FLOAT_EQ_EPS};
+
+    fn assert_roots(roots: Vec<Float>, expected: &[Float]) {
+        assert_eq!(roots.len(), expected.len(), "{roots:?} != {expected:?}");
+        for (root, expected) in roots.iter().zip(expected) {
+            assert!(
+                float_eq(*root, *expected, FLOAT_EQ_EPS),
+                "{roots:?} != {expected:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn quadratic_roots() {
+        assert_roots(solve_quadratic(1.0, -3.0, 2.0), &[1.0, 2.0]);
+        assert_roots(solve_quadratic(1.0, 0.0, 1.0), &[]);
+        assert_roots(solve_quadratic(0.0, 2.0, -4.0), &[2.0]);
+        // the smaller root would be lost to cancellation with the textbook formula
+        assert_roots(solve_quadratic(1.0, -1e8, 1.0), &[1e-8, 1e8]);
+    }
+
+    #[test]
+    fn cubic_roots() {
+        // (x - 1)(x - 2)(x - 3)
+        assert_roots(solve_cubic(1.0, -6.0, 11.0, -6.0), &[1.0, 2.0, 3.0]);
+        // (x - 2)(x^2 + 1)
+        assert_roots(solve_cubic(2.0, -4.0, 2.0, -4.0), &[2.0]);
+        assert_roots(solve_cubic(1.0, 0.0, 0.0, -8.0), &[2.0]);
+        // the two terms of cardano's formula nearly cancel for large q
+        assert_roots(solve_cubic(1.0, 0.0, 1.0, 1e8), &[-464.1581652163812]);
+    }
+
+    #[test]
+    fn quartic_roots() {
+        // (x - 1)(x - 2)(x - 3)(x - 4)
+        assert_roots(
+            solve_quartic(1.0, -10.0, 35.0, -50.0, 24.0),
+            &[1.0, 2.0, 3.0, 4.0],
+        );
+        // (x^2 - 1)(x^2 - 4)
+        assert_roots(
+            solve_quartic(1.0, 0.0, -5.0, 0.0, 4.0),
+            &[-2.0, -1.0, 1.0, 2.0],
+        );
+        // (x - 0.5)(x + 3)(x^2 + 1)
+        assert_roots(solve_quartic(2.0, 5.0, -1.0, 5.0, -3.0), &[-3.0, 0.5]);
+        assert_roots(solve_quartic(1.0, 0.0, 0.0, 0.0, 1.0), &[]);
+    }
+
+    #[test]
+    fn polynomial_roots() {
+        // (x - 1)(x - 2)(x - 3)(x - 4)(x - 5)
+        assert_roots(
+            solve_polynomial(&[1.0, -15.0, 85.0, -225.0, 274.0, -120.0]),
+            &[1.0, 2.0, 3.0, 4.0, 5.0],
+        );
+        // the double root (x - 1)^2 (x - 3) only touches zero
+        assert_roots(solve_polynomial(&[1.0, -5.0, 7.0, -3.0]), &[1.0, 3.0]);
+        assert_roots(solve_polynomial(&[2.0, 0.0, 0.0, 0.0]), &[0.0]);
+        assert_roots(solve_polynomial(&[0.0, 0.0, 1.0, -2.0]), &[2.0]);
+        assert_roots(solve_polynomial(&[1.0, 0.0, 0.0, 0.0, 1.0]), &[]);
+        assert_roots(solve_polynomial(&[3.0]), &[]);
+    }
+
+    #[test]
+    fn closed_forms_agree_with_the_general_solver() {
+        // a torus hit by a grazing ray has two nearly repeated roots
+        let coefficients = [1.0, -8.0, 22.0, -24.0, 9.0 - 1e-3];
+        let quartic = solve_quartic(1.0, -8.0, 22.0, -24.0, 9.0 - 1e-3);
+        let general = solve_polynomial(&coefficients);
+        assert_eq!(quartic.len(), 4);
+        assert_eq!(general.len(), 4);
+        for (a, b) in quartic.iter().zip(&general) {
+            assert!((a - b).abs() < 1e-3, "{quartic:?} != {general:?}");
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use crate::math::{Float, Tuple4D};
+
+// smooth curves through space for camera paths and other motion. t runs from 0 at the start to 1
+// at the end of the whole curve, every segment gets the same share of it no matter how long it is
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Spline {
+    // passes through all of the points
+    CatmullRom(Vec<Tuple4D>),
+    // cubic bezier segments where each one starts at the end of the previous one, so the points are
+    // start, control, control, end, control, control, end, ... Extra points at the end are ignored
+    Bezier(Vec<Tuple4D>),
+}
+
+impl Spline {
+    pub fn segment_count(&self) -> usize {
+        match self {
+            Spline::CatmullRom(points) => points.len().saturating_sub(1),
+            Spline::Bezier(points) => points.len().saturating_sub(1) / 3,
+        }
+    }
+
+    // t is clamped to [0, 1]. Curves with a single point stay at it
+    pub fn point_at(&self, t: Float) -> Tuple4D {
+        let points = match self {
+            Spline::CatmullRom(points) | Spline::Bezier(points) => points,
+        };
+        assert!(!points.is_empty(), "a spline needs at least one point");
+        let segments = self.segment_count();
+        if segments == 0 {
+            return points[0];
+        }
+        let position = t.clamp(0.0, 1.0) * segments as Float;
+        let segment = (position.floor() as usize).min(segments - 1);
+        let local = position - segment as Float;
+        match self {
+            Spline::CatmullRom(points) => {
+                let p1 = points[segment];
+                let p2 = points[segment + 1];
+                // the missing neighbors at the ends are mirrored, which keeps the ends straight
+                let p0 = segment
+                    .checked_sub(1)
+                    .map_or(p1 * 2.0 - p2, |previous| points[previous]);
+                let p3 = points.get(segment + 2).copied().unwrap_or(p2 * 2.0 - p1);
+                catmull_rom(p0, p1, p2, p3, local)
+            }
+            Spline::Bezier(points) => {
+                let start = segment * 3;
+                cubic_bezier(
+                    points[start],
+                    points[start + 1],
+                    points[start + 2],
+                    points[start + 3],
+                    local,
+                )
+            }
+        }
+    }
+}
+
+// uniform catmull-rom segment from p1 at t = 0 to p2 at t = 1, p0 and p3 set the tangents
+pub fn catmull_rom(p0: Tuple4D, p1: Tuple4D, p2: Tuple4D, p3: Tuple4D, t: Float) -> Tuple4D {
+    let (t2, t3) = (t * t, t * t * t);
+    // the weights add up to 1, so points stay points
+    p0 * (0.5 * (-t3 + 2.0 * t2 - t))
+        + p1 * (0.5 * (3.0 * t3 - 5.0 * t2 + 2.0))
+        + p2 * (0.5 * (-3.0 * t3 + 4.0 * t2 + t))
+        + p3 * (0.5 * (t3 - t2))
+}
+
+// from p0 at t = 0 to p3 at t = 1, pulled towards the control points p1 and p2
+pub fn cubic_bezier(p0: Tuple4D, p1: Tuple4D, p2: Tuple4D, p3: Tuple4D, t: Float) -> Tuple4D {
+    let s = 1.0 - t;
+    p0 * (s * s * s) + p1 * (3.0 * s * s * t) + p2 * (3.0 * s * t * t) + p3 * (t * t * t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(x: Float, y: Float) -> Tuple4D {
+        Tuple4D::new_point(x, y, 0.0)
+    }
+
+    #[test]
+    #[cfg_attr(feature = "f32", ignore = "exact only in double precision")]
+    fn catmull_rom_passes_through_the_points() {
+        let spline = Spline::CatmullRom(vec![point(0.0, 0.0), point(1.0, 1.0), point(2.0, 0.0)]);
+        assert_eq!(spline.segment_count(), 2);
+        assert_eq!(spline.point_at(0.0), point(0.0, 0.0));
+        assert_eq!(spline.point_at(0.5), point(1.0, 1.0));
+        assert_eq!(spline.point_at(1.0), point(2.0, 0.0));
+        assert_eq!(spline.point_at(7.0), point(2.0, 0.0));
+        assert!(spline.point_at(0.25).is_point());
+        // symmetric around the middle point
+        let (left, right) = (spline.point_at(0.3), spline.point_at(0.7));
+        assert!((left.y - right.y).abs() < 1e-9);
+        assert!((left.x + right.x - 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn catmull_rom_of_points_on_a_line_stays_on_it() {
+        let points = [
+            point(0.0, 0.0),
+            point(1.0, 0.0),
+            point(2.0, 0.0),
+            point(3.0, 0.0),
+        ];
+        let segment = catmull_rom(points[0], points[1], points[2], points[3], 0.5);
+        assert_eq!(segment, point(1.5, 0.0));
+    }
+
+    #[test]
+    fn bezier_segments() {
+        let spline = Spline::Bezier(vec![
+            point(0.0, 0.0),
+            point(0.0, 1.0),
+            point(1.0, 1.0),
+            point(1.0, 0.0),
+            point(1.0, -1.0),
+            point(2.0, -1.0),
+            point(2.0, 0.0),
+        ]);
+        assert_eq!(spline.segment_count(), 2);
+        assert_eq!(spline.point_at(0.0), point(0.0, 0.0));
+        assert_eq!(spline.point_at(0.25), point(0.5, 0.75));
+        assert_eq!(spline.point_at(0.5), point(1.0, 0.0));
+        assert_eq!(spline.point_at(0.75), point(1.5, -0.75));
+        assert_eq!(spline.point_at(1.0), point(2.0, 0.0));
+    }
+
+    #[test]
+    fn single_point_spline() {
+        let spline = Spline::Bezier(vec![point(3.0, 4.0)]);
+        assert_eq!(spline.point_at(0.6), point(3.0, 4.0));
+    }
+}
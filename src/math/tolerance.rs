@@ -0,0 +1,141 @@
+use std::sync::OnceLock;
+
+use super::{Float, FLOAT_EQ_EPS};
+
+// how close two floats have to be to count as equal
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Tolerance {
+    // |a - b| < epsilon, fits values around 1 but is too loose for tiny and too strict for large
+    // values
+    Absolute(Float),
+    // |a - b| <= epsilon * max(|a|, |b|), scales with the values but only accepts exactly 0 near 0
+    Relative(Float),
+    // at most this many representable floats apart, the same relative precision at every scale
+    Ulps(u32),
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Tolerance::Absolute(FLOAT_EQ_EPS)
+    }
+}
+
+impl Tolerance {
+    // nan is never equal to anything
+    pub fn equal(self, a: Float, b: Float) -> bool {
+        if a == b {
+            return true;
+        }
+        match self {
+            Tolerance::Absolute(epsilon) => (a - b).abs() < epsilon,
+            Tolerance::Relative(epsilon) => (a - b).abs() <= epsilon * a.abs().max(b.abs()),
+            Tolerance::Ulps(ulps) => {
+                !a.is_nan()
+                    && !b.is_nan()
+                    && (i128::from(ordered_bits(a)) - i128::from(ordered_bits(b))).unsigned_abs()
+                        <= u128::from(ulps)
+            }
+        }
+    }
+}
+
+// the bits as an integer that grows with the float, so the difference of two of them counts the
+// floats in between
+#[cfg(not(feature = "f32"))]
+fn ordered_bits(value: Float) -> i64 {
+    let bits = value.to_bits() as i64;
+    if bits < 0 {
+        i64::MIN - bits
+    } else {
+        bits
+    }
+}
+#[cfg(feature = "f32")]
+fn ordered_bits(value: Float) -> i64 {
+    let bits = value.to_bits() as i32;
+    i64::from(if bits < 0 { i32::MIN - bits } else { bits })
+}
+
+// written at most once, so reading it is a plain load that render threads do not contend on
+static DEFAULT_TOLERANCE: OnceLock<Tolerance> = OnceLock::new();
+
+// the tolerance of == for tuples, matrices, quaternions and colors
+pub fn default_tolerance() -> Tolerance {
+    DEFAULT_TOLERANCE.get().copied().unwrap_or_default()
+}
+
+// changes == of all math types and colors for the whole process. It can only be set once, best at
+// startup before anything is compared, later calls fail with the tolerance that is already set
+pub fn set_default_tolerance(tolerance: Tolerance) -> Result<(), Tolerance> {
+    DEFAULT_TOLERANCE
+        .set(tolerance)
+        .map_err(|_| default_tolerance())
+}
+
+// equality with an explicit tolerance instead of the default one of ==
+pub trait ApproxEq {
+    fn approx_eq(&self, other: &Self, tolerance: Tolerance) -> bool;
+}
+
+impl ApproxEq for Float {
+    fn approx_eq(&self, other: &Self, tolerance: Tolerance) -> bool {
+        tolerance.equal(*self, *other)
+    }
+}
+
+impl<T: ApproxEq, const N: usize> ApproxEq for [T; N] {
+    fn approx_eq(&self, other: &Self, tolerance: Tolerance) -> bool {
+        self.iter()
+            .zip(other)
+            .all(|(a, b)| a.approx_eq(b, tolerance))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn absolute_and_relative_tolerances() {
+        let absolute = Tolerance::Absolute(0.01);
+        assert!(absolute.equal(1.0, 1.005));
+        assert!(!absolute.equal(1000.0, 1000.5));
+        let relative = Tolerance::Relative(0.001);
+        assert!(relative.equal(1000.0, 1000.5));
+        assert!(!relative.equal(1.0, 1.005));
+        assert!(!relative.equal(0.0, 1e-30));
+        assert!(Tolerance::default().equal(0.0, -0.0));
+    }
+
+    #[test]
+    fn ulps_count_the_floats_in_between() {
+        let one: Float = 1.0;
+        let next = Float::from_bits(one.to_bits() + 2);
+        assert!(Tolerance::Ulps(2).equal(1.0, next));
+        assert!(!Tolerance::Ulps(1).equal(1.0, next));
+        // the floats around zero lie on both sides of it
+        let tiny = Float::from_bits(1);
+        assert!(Tolerance::Ulps(2).equal(tiny, -tiny));
+        assert!(!Tolerance::Ulps(2).equal(1.0, -1.0));
+        assert!(!Tolerance::Ulps(u32::MAX).equal(Float::NAN, Float::NAN));
+    }
+
+    #[test]
+    fn the_default_is_set_once() {
+        // the tests run in one process, so this keeps the default the other tests expect
+        assert_eq!(default_tolerance(), Tolerance::Absolute(FLOAT_EQ_EPS));
+        assert_eq!(set_default_tolerance(Tolerance::default()), Ok(()));
+        assert_eq!(
+            set_default_tolerance(Tolerance::Ulps(4)),
+            Err(Tolerance::default())
+        );
+        assert_eq!(default_tolerance(), Tolerance::default());
+    }
+
+    #[test]
+    fn arrays_compare_every_entry() {
+        let tolerance = Tolerance::Absolute(0.1);
+        assert!([1.0, 2.0].approx_eq(&[1.05, 1.95], tolerance));
+        assert!(![1.0, 2.0].approx_eq(&[1.05, 2.5], tolerance));
+    }
+}
@@ -0,0 +1,124 @@
+use super::{ApproxEq, Float, Matrix4, Tolerance};
+
+// a transformation matrix together with its inverse and the transposed inverse, which objects and
+// patterns need for every ray and normal. They are computed once when the transform is created
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(from = "Matrix4", into = "Matrix4"))]
+pub struct Transform {
+    matrix: Matrix4,
+    // None for singular matrices, the scene validation reports them before anything is rendered
+    inverse: Option<Matrix4>,
+    inverse_transpose: Option<Matrix4>,
+}
+
+impl Transform {
+    pub fn new(matrix: Matrix4) -> Transform {
+        let inverse = matrix.inverse();
+        Transform {
+            matrix,
+            inverse,
+            inverse_transpose: inverse.map(|inverse| inverse.transpose()),
+        }
+    }
+
+    pub fn matrix(&self) -> Matrix4 {
+        self.matrix
+    }
+
+    pub fn inverse(&self) -> Option<Matrix4> {
+        self.inverse
+    }
+
+    // transforms normals from object to world space
+    pub fn inverse_transpose(&self) -> Option<Matrix4> {
+        self.inverse_transpose
+    }
+
+    // blends the matrices, the inverse of the result has to be computed again
+    pub fn interpolate(&self, other: &Transform, t: Float) -> Transform {
+        Transform::new(self.matrix.interpolate(other.matrix, t))
+    }
+}
+
+// the identity is its own inverse, plain colors ask for it for every point they are shaded at
+impl Default for Transform {
+    fn default() -> Transform {
+        Transform {
+            matrix: Matrix4::eye(),
+            inverse: Some(Matrix4::eye()),
+            inverse_transpose: Some(Matrix4::eye()),
+        }
+    }
+}
+
+impl From<Matrix4> for Transform {
+    fn from(matrix: Matrix4) -> Transform {
+        Transform::new(matrix)
+    }
+}
+
+impl From<Transform> for Matrix4 {
+    fn from(transform: Transform) -> Matrix4 {
+        transform.matrix
+    }
+}
+
+// the inverses follow from the matrix
+impl ApproxEq for Transform {
+    fn approx_eq(&self, other: &Self, tolerance: Tolerance) -> bool {
+        self.matrix.approx_eq(&other.matrix, tolerance)
+    }
+}
+
+impl PartialEq for Transform {
+    fn eq(&self, other: &Transform) -> bool {
+        self.matrix == other.matrix
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::Tuple4D;
+
+    #[test]
+    fn inverses_are_computed_once() {
+        let matrix = Matrix4::translation(1.0, 2.0, 3.0) * Matrix4::scaling(2.0, 4.0, 0.5);
+        let transform = Transform::new(matrix);
+        assert_eq!(transform.matrix(), matrix);
+        assert_eq!(transform.inverse(), matrix.inverse());
+        assert_eq!(
+            transform.inverse_transpose(),
+            Some(matrix.inverse().unwrap().transpose())
+        );
+        let point = Tuple4D::new_point(1.0, -1.0, 2.0);
+        assert_eq!(transform.inverse().unwrap() * (matrix * point), point);
+    }
+
+    #[test]
+    fn singular_matrices_have_no_inverse() {
+        let transform = Transform::new(Matrix4::scaling(1.0, 0.0, 1.0));
+        assert!(transform.inverse().is_none());
+        assert!(transform.inverse_transpose().is_none());
+        assert_eq!(Transform::default().inverse(), Some(Matrix4::eye()));
+    }
+
+    #[test]
+    fn interpolated_transforms_are_inverted_again() {
+        let start = Transform::new(Matrix4::translation(0.0, 0.0, 0.0));
+        let end = Transform::new(Matrix4::translation(4.0, 0.0, 0.0));
+        let middle = start.interpolate(&end, 0.5);
+        assert_eq!(middle, Transform::new(Matrix4::translation(2.0, 0.0, 0.0)));
+        assert_eq!(middle.inverse(), Some(Matrix4::translation(-2.0, 0.0, 0.0)));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serialized_as_the_matrix() {
+        let transform = Transform::new(Matrix4::rotation_x(0.5));
+        let json = serde_json::to_string(&transform).unwrap();
+        assert_eq!(json, serde_json::to_string(&transform.matrix()).unwrap());
+        assert_eq!(serde_json::from_str::<Transform>(&json).unwrap(), transform);
+    }
+}
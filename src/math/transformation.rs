@@ -0,0 +1,375 @@
+use super::{Float, Matrix4, Quaternion, Tuple4D};
+
+impl Matrix4 {
+    pub fn translation(x: Float, y: Float, z: Float) -> Matrix4 {
+        let mut mat = Matrix4::eye();
+        mat[[0, 3]] = x;
+        mat[[1, 3]] = y;
+        mat[[2, 3]] = z;
+        mat
+    }
+
+    pub fn scaling(x: Float, y: Float, z: Float) -> Matrix4 {
+        let mut mat = Matrix4::eye();
+        mat[[0, 0]] = x;
+        mat[[1, 1]] = y;
+        mat[[2, 2]] = z;
+        mat
+    }
+
+    pub fn rotation_x(radians: Float) -> Matrix4 {
+        let (sin, cos) = radians.sin_cos();
+        let mut mat = Matrix4::eye();
+        mat[[1, 1]] = cos;
+        mat[[1, 2]] = -sin;
+        mat[[2, 1]] = sin;
+        mat[[2, 2]] = cos;
+        mat
+    }
+
+    pub fn rotation_y(radians: Float) -> Matrix4 {
+        let (sin, cos) = radians.sin_cos();
+        let mut mat = Matrix4::eye();
+        mat[[0, 0]] = cos;
+        mat[[0, 2]] = sin;
+        mat[[2, 0]] = -sin;
+        mat[[2, 2]] = cos;
+        mat
+    }
+
+    pub fn rotation_z(radians: Float) -> Matrix4 {
+        let (sin, cos) = radians.sin_cos();
+        let mut mat = Matrix4::eye();
+        mat[[0, 0]] = cos;
+        mat[[0, 1]] = -sin;
+        mat[[1, 0]] = sin;
+        mat[[1, 1]] = cos;
+        mat
+    }
+
+    // counterclockwise around an arbitrary axis through the origin when looking against its
+    // direction, like rotation_x, rotation_y and rotation_z
+    pub fn rotation_axis(axis: Tuple4D, radians: Float) -> Matrix4 {
+        Quaternion::from_axis_angle(axis, radians).to_matrix()
+    }
+
+    pub fn shearing(
+        x_y: Float,
+        x_z: Float,
+        y_x: Float,
+        y_z: Float,
+        z_x: Float,
+        z_y: Float,
+    ) -> Matrix4 {
+        let mut mat = Matrix4::eye();
+        mat[[0, 1]] = x_y;
+        mat[[0, 2]] = x_z;
+        mat[[1, 0]] = y_x;
+        mat[[1, 2]] = y_z;
+        mat[[2, 0]] = z_x;
+        mat[[2, 1]] = z_y;
+        mat
+    }
+
+    pub fn view_transform(from: Tuple4D, to: Tuple4D, up: Tuple4D) -> Matrix4 {
+        let forward = (to - from).normalize();
+        let left = forward.cross(up.normalize());
+        let true_up = left.cross(forward);
+
+        let mut orientation = Matrix4::eye();
+        orientation[[0, 0]] = left.x;
+        orientation[[0, 1]] = left.y;
+        orientation[[0, 2]] = left.z;
+        orientation[[1, 0]] = true_up.x;
+        orientation[[1, 1]] = true_up.y;
+        orientation[[1, 2]] = true_up.z;
+        orientation[[2, 0]] = -forward.x;
+        orientation[[2, 1]] = -forward.y;
+        orientation[[2, 2]] = -forward.z;
+
+        orientation * Matrix4::translation(-from.x, -from.y, -from.z)
+    }
+
+    // opengl style projection of the view space of view_transform, the frustum along -z between
+    // near and far is mapped to the cube [-1, 1]³ after dividing by w, see Tuple4D::project.
+    // field_of_view is the vertical angle in radians and aspect the width divided by the height
+    pub fn perspective(field_of_view: Float, aspect: Float, near: Float, far: Float) -> Matrix4 {
+        let focal = 1.0 / (field_of_view / 2.0).tan();
+        let mut mat = Matrix4::zeros();
+        mat[[0, 0]] = focal / aspect;
+        mat[[1, 1]] = focal;
+        mat[[2, 2]] = (far + near) / (near - far);
+        mat[[2, 3]] = 2.0 * far * near / (near - far);
+        mat[[3, 2]] = -1.0;
+        mat
+    }
+
+    // maps the box between the planes to the cube [-1, 1]³ like perspective, but keeps w at 1.
+    // near and far are distances along -z
+    pub fn orthographic(
+        left: Float,
+        right: Float,
+        bottom: Float,
+        top: Float,
+        near: Float,
+        far: Float,
+    ) -> Matrix4 {
+        Matrix4::scaling(
+            2.0 / (right - left),
+            2.0 / (top - bottom),
+            -2.0 / (far - near),
+        ) * Matrix4::translation(
+            -(right + left) / 2.0,
+            -(top + bottom) / 2.0,
+            (far + near) / 2.0,
+        )
+    }
+
+    // moves an object from the origin to from and turns it so its z axis points at target and its
+    // y axis is as close to up as possible, e.g. for spotlights and billboards. Unlike
+    // view_transform this goes from object to world space
+    pub fn look_at(from: Tuple4D, target: Tuple4D, up: Tuple4D) -> Matrix4 {
+        let forward = (target - from).normalize();
+        let side = up.cross(forward).normalize();
+        let true_up = forward.cross(side);
+
+        let mut matrix = Matrix4::translation(from.x, from.y, from.z);
+        for (col, axis) in [side, true_up, forward].into_iter().enumerate() {
+            matrix[[0, col]] = axis.x;
+            matrix[[1, col]] = axis.y;
+            matrix[[2, col]] = axis.z;
+        }
+        matrix
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::consts::PI;
+    use crate::math::FLOAT_EQ_EPS;
+
+    use super::*;
+
+    #[test]
+    fn translating_point() {
+        let transform = Matrix4::translation(5.0, -3.0, 2.0);
+        let point = Tuple4D::new_point(-3.0, 4.0, 5.0);
+        assert_eq!(transform * point, Tuple4D::new_point(2.0, 1.0, 7.0));
+    }
+
+    #[test]
+    fn translating_point_with_inverse() {
+        let transform = Matrix4::translation(5.0, -3.0, 2.0);
+        let inv = transform.inverse().unwrap();
+        let point = Tuple4D::new_point(-3.0, 4.0, 5.0);
+        assert_eq!(inv * point, Tuple4D::new_point(-8.0, 7.0, 3.0));
+    }
+
+    #[test]
+    fn translation_does_not_affect_vectors() {
+        let transform = Matrix4::translation(5.0, -3.0, 2.0);
+        let vector = Tuple4D::new_vector(-3.0, 4.0, 5.0);
+        assert_eq!(transform * vector, vector);
+    }
+
+    #[test]
+    fn scaling_point_and_vector() {
+        let transform = Matrix4::scaling(2.0, 3.0, 4.0);
+        let point = Tuple4D::new_point(-4.0, 6.0, 8.0);
+        assert_eq!(transform * point, Tuple4D::new_point(-8.0, 18.0, 32.0));
+        let vector = Tuple4D::new_vector(-4.0, 6.0, 8.0);
+        assert_eq!(transform * vector, Tuple4D::new_vector(-8.0, 18.0, 32.0));
+    }
+
+    #[test]
+    fn reflection_is_scaling_by_negative_value() {
+        let transform = Matrix4::scaling(-1.0, 1.0, 1.0);
+        let point = Tuple4D::new_point(2.0, 3.0, 4.0);
+        assert_eq!(transform * point, Tuple4D::new_point(-2.0, 3.0, 4.0));
+    }
+
+    #[test]
+    fn rotating_around_x_axis() {
+        let point = Tuple4D::new_point(0.0, 1.0, 0.0);
+        let half_quarter = Matrix4::rotation_x(PI / 4.0);
+        let full_quarter = Matrix4::rotation_x(PI / 2.0);
+        let sqrt2_half = Float::sqrt(2.0) / 2.0;
+        assert_eq!(
+            half_quarter * point,
+            Tuple4D::new_point(0.0, sqrt2_half, sqrt2_half)
+        );
+        assert_eq!(full_quarter * point, Tuple4D::new_point(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn rotating_around_y_axis() {
+        let point = Tuple4D::new_point(0.0, 0.0, 1.0);
+        let half_quarter = Matrix4::rotation_y(PI / 4.0);
+        let full_quarter = Matrix4::rotation_y(PI / 2.0);
+        let sqrt2_half = Float::sqrt(2.0) / 2.0;
+        assert_eq!(
+            half_quarter * point,
+            Tuple4D::new_point(sqrt2_half, 0.0, sqrt2_half)
+        );
+        assert_eq!(full_quarter * point, Tuple4D::new_point(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn rotating_around_z_axis() {
+        let point = Tuple4D::new_point(0.0, 1.0, 0.0);
+        let half_quarter = Matrix4::rotation_z(PI / 4.0);
+        let full_quarter = Matrix4::rotation_z(PI / 2.0);
+        let sqrt2_half = Float::sqrt(2.0) / 2.0;
+        assert_eq!(
+            half_quarter * point,
+            Tuple4D::new_point(-sqrt2_half, sqrt2_half, 0.0)
+        );
+        assert_eq!(full_quarter * point, Tuple4D::new_point(-1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn shearing_moves_components_in_proportion() {
+        let point = Tuple4D::new_point(2.0, 3.0, 4.0);
+        let transform = Matrix4::shearing(1.0, 0.0, 0.0, 0.0, 0.0, 0.0);
+        assert_eq!(transform * point, Tuple4D::new_point(5.0, 3.0, 4.0));
+        let transform = Matrix4::shearing(0.0, 0.0, 0.0, 0.0, 0.0, 1.0);
+        assert_eq!(transform * point, Tuple4D::new_point(2.0, 3.0, 7.0));
+    }
+
+    #[test]
+    fn chained_transformations_apply_in_reverse_order() {
+        let point = Tuple4D::new_point(1.0, 0.0, 1.0);
+        let rotation = Matrix4::rotation_x(PI / 2.0);
+        let scaling = Matrix4::scaling(5.0, 5.0, 5.0);
+        let translation = Matrix4::translation(10.0, 5.0, 7.0);
+        let transform = translation * scaling * rotation;
+        assert_eq!(transform * point, Tuple4D::new_point(15.0, 0.0, 7.0));
+    }
+
+    #[test]
+    fn view_transform_default_orientation() {
+        let from = Tuple4D::new_point(0.0, 0.0, 0.0);
+        let to = Tuple4D::new_point(0.0, 0.0, -1.0);
+        let up = Tuple4D::new_vector(0.0, 1.0, 0.0);
+        assert_eq!(Matrix4::view_transform(from, to, up), Matrix4::eye());
+    }
+
+    #[test]
+    fn view_transform_looking_in_positive_z() {
+        let from = Tuple4D::new_point(0.0, 0.0, 0.0);
+        let to = Tuple4D::new_point(0.0, 0.0, 1.0);
+        let up = Tuple4D::new_vector(0.0, 1.0, 0.0);
+        assert_eq!(
+            Matrix4::view_transform(from, to, up),
+            Matrix4::scaling(-1.0, 1.0, -1.0)
+        );
+    }
+
+    #[test]
+    fn view_transform_moves_the_world() {
+        let from = Tuple4D::new_point(0.0, 0.0, 8.0);
+        let to = Tuple4D::new_point(0.0, 0.0, 0.0);
+        let up = Tuple4D::new_vector(0.0, 1.0, 0.0);
+        assert_eq!(
+            Matrix4::view_transform(from, to, up),
+            Matrix4::translation(0.0, 0.0, -8.0)
+        );
+    }
+
+    #[test]
+    fn arbitrary_view_transform() {
+        let from = Tuple4D::new_point(1.0, 3.0, 2.0);
+        let to = Tuple4D::new_point(4.0, -2.0, 8.0);
+        let up = Tuple4D::new_vector(1.0, 1.0, 0.0);
+        let mut expected = Matrix4::zeros();
+        expected[[0, 0]] = -0.50709;
+        expected[[0, 1]] = 0.50709;
+        expected[[0, 2]] = 0.67612;
+        expected[[0, 3]] = -2.36643;
+        expected[[1, 0]] = 0.76772;
+        expected[[1, 1]] = 0.60609;
+        expected[[1, 2]] = 0.12122;
+        expected[[1, 3]] = -2.82843;
+        expected[[2, 0]] = -0.35857;
+        expected[[2, 1]] = 0.59761;
+        expected[[2, 2]] = -0.71714;
+        expected[[2, 3]] = 0.0;
+        expected[[3, 3]] = 1.0;
+        assert_eq!(Matrix4::view_transform(from, to, up), expected);
+    }
+
+    #[test]
+    fn rotating_around_arbitrary_axes() {
+        let x_axis = Tuple4D::new_vector(1.0, 0.0, 0.0);
+        assert_eq!(
+            Matrix4::rotation_axis(x_axis, 0.8),
+            Matrix4::rotation_x(0.8)
+        );
+        // a third of a turn around the diagonal cycles the axes
+        let diagonal = Tuple4D::new_vector(1.0, 1.0, 1.0);
+        let rotation = Matrix4::rotation_axis(diagonal, 2.0 * PI / 3.0);
+        assert_eq!(rotation * x_axis, Tuple4D::new_vector(0.0, 1.0, 0.0));
+        assert_eq!(rotation * diagonal, diagonal);
+    }
+
+    #[test]
+    fn look_at_points_z_at_the_target() {
+        let up = Tuple4D::new_vector(0.0, 1.0, 0.0);
+        let origin = Tuple4D::new_point(0.0, 0.0, 0.0);
+        assert_eq!(
+            Matrix4::look_at(origin, Tuple4D::new_point(0.0, 0.0, 3.0), up),
+            Matrix4::eye()
+        );
+        let from = Tuple4D::new_point(1.0, 3.0, 2.0);
+        let target = Tuple4D::new_point(4.0, -2.0, 8.0);
+        let transform = Matrix4::look_at(from, target, Tuple4D::new_vector(1.0, 1.0, 0.0));
+        assert_eq!(transform * origin, from);
+        assert_eq!(
+            transform * Tuple4D::new_vector(0.0, 0.0, 1.0),
+            (target - from).normalize()
+        );
+        assert!((transform.determinant() - 1.0).abs() < FLOAT_EQ_EPS);
+    }
+
+    #[test]
+    fn look_at_is_the_inverse_of_the_view_transform() {
+        // cameras look along -z, objects along +z
+        let from = Tuple4D::new_point(-2.0, 1.0, 4.0);
+        let to = Tuple4D::new_point(3.0, 1.0, 0.0);
+        let up = Tuple4D::new_vector(0.0, 1.0, 0.0);
+        assert_eq!(
+            Matrix4::look_at(from, to, up) * Matrix4::rotation_y(PI),
+            Matrix4::view_transform(from, to, up).inverse().unwrap()
+        );
+    }
+
+    #[test]
+    fn perspective_maps_the_frustum_to_a_cube() {
+        let projection = Matrix4::perspective(PI / 2.0, 2.0, 1.0, 10.0);
+        let project = |x, y, z| (projection * Tuple4D::new_point(x, y, z)).project();
+        assert_eq!(project(0.0, 0.0, -1.0), Tuple4D::new_point(0.0, 0.0, -1.0));
+        assert_eq!(project(0.0, 0.0, -10.0), Tuple4D::new_point(0.0, 0.0, 1.0));
+        // the corners of the far plane, 90° vertically and twice as wide
+        assert_eq!(
+            project(20.0, -10.0, -10.0),
+            Tuple4D::new_point(1.0, -1.0, 1.0)
+        );
+        // farther points of the same ray only move in depth
+        let (near, far) = (project(1.0, 1.0, -2.0), project(2.0, 2.0, -4.0));
+        assert_eq!((near.x, near.y), (far.x, far.y));
+        assert!(near.z < far.z);
+    }
+
+    #[test]
+    fn orthographic_keeps_w() {
+        let projection = Matrix4::orthographic(-2.0, 4.0, -1.0, 1.0, 1.0, 5.0);
+        assert_eq!(
+            projection * Tuple4D::new_point(-2.0, 1.0, -1.0),
+            Tuple4D::new_point(-1.0, 1.0, -1.0)
+        );
+        assert_eq!(
+            projection * Tuple4D::new_point(4.0, -1.0, -5.0),
+            Tuple4D::new_point(1.0, -1.0, 1.0)
+        );
+    }
+}
@@ -1,20 +1,25 @@
-use crate::math::{float_eq, FLOAT_EQ_EPS};
-use std::ops::{Add, Div, Index, IndexMut, Mul, Neg, Sub};
+use super::MathError;
+use crate::math::{default_tolerance, format_entry, ApproxEq, Float, Tolerance, FLOAT_EQ_EPS};
+use std::fmt;
+use std::ops::{
+    Add, AddAssign, Div, DivAssign, Index, IndexMut, Mul, MulAssign, Neg, Sub, SubAssign,
+};
 
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Tuple4D {
-    pub x: f64,
-    pub y: f64,
-    pub z: f64,
-    pub w: f64,
+    pub x: Float,
+    pub y: Float,
+    pub z: Float,
+    pub w: Float,
 }
 
 impl Tuple4D {
-    pub fn new_point(x: f64, y: f64, z: f64) -> Tuple4D {
+    pub fn new_point(x: Float, y: Float, z: Float) -> Tuple4D {
         Tuple4D { x, y, z, w: 1.0 }
     }
 
-    pub fn new_vector(x: f64, y: f64, z: f64) -> Tuple4D {
+    pub fn new_vector(x: Float, y: Float, z: Float) -> Tuple4D {
         Tuple4D { x, y, z, w: 0.0 }
     }
 
@@ -23,14 +28,23 @@ impl Tuple4D {
     }
 
     pub fn is_vector(self) -> bool {
-        float_eq(self.w, 0.0, FLOAT_EQ_EPS)
+        self.is_vector_within(Tolerance::Absolute(FLOAT_EQ_EPS))
     }
 
     pub fn is_point(self) -> bool {
-        float_eq(self.w, 1.0, FLOAT_EQ_EPS)
+        self.is_point_within(Tolerance::Absolute(FLOAT_EQ_EPS))
     }
 
-    pub fn magnitude(self) -> f64 {
+    // w compared with the given tolerance instead of the fixed one of is_vector
+    pub fn is_vector_within(self, tolerance: Tolerance) -> bool {
+        tolerance.equal(self.w, 0.0)
+    }
+
+    pub fn is_point_within(self, tolerance: Tolerance) -> bool {
+        tolerance.equal(self.w, 1.0)
+    }
+
+    pub fn magnitude(self) -> Float {
         (self.x * self.x + self.y * self.y + self.z * self.z + self.w * self.w).sqrt()
     }
 
@@ -38,39 +52,136 @@ impl Tuple4D {
         self / self.magnitude()
     }
 
-    pub fn dot(self, other: Self) -> f64 {
-        if !self.is_vector() || !other.is_vector() {
-            panic!("Called dot product on a tuple that is not a vector");
+    // the homogeneous divide, e.g. after a perspective projection. Points with w = 0 lie at
+    // infinity and get infinite or nan components
+    pub fn project(self) -> Self {
+        Tuple4D {
+            x: self.x / self.w,
+            y: self.y / self.w,
+            z: self.z / self.w,
+            w: 1.0,
         }
-        self.x * other.x + self.y * other.y + self.z * other.z
     }
 
-    pub fn cross(self, other: Self) -> Self {
-        if !self.is_vector() || !other.is_vector() {
-            panic!("Called cross product on a tuple that is not a vector");
+    // fails for tuples of length zero instead of dividing by zero
+    pub fn try_normalize(self) -> Result<Self, MathError> {
+        let magnitude = self.magnitude();
+        if magnitude == 0.0 {
+            return Err(MathError::ZeroLength);
         }
-        Self::new_vector(
+        Ok(self / magnitude)
+    }
+
+    // the products panic for points, the try_ versions return an error and Vector rejects them at
+    // compile time
+    pub fn dot(self, other: Self) -> Float {
+        self.try_dot(other).unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    pub fn try_dot(self, other: Self) -> Result<Float, MathError> {
+        self.check_vector()?;
+        other.check_vector()?;
+        Ok(self.x * other.x + self.y * other.y + self.z * other.z)
+    }
+
+    pub fn cross(self, other: Self) -> Self {
+        self.try_cross(other).unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    pub fn try_cross(self, other: Self) -> Result<Self, MathError> {
+        self.check_vector()?;
+        other.check_vector()?;
+        Ok(Self::new_vector(
             self.y * other.z - self.z * other.y,
             self.z * other.x - self.x * other.z,
             self.x * other.y - self.y * other.x,
-        )
+        ))
+    }
+
+    pub fn reflect(self, normal: Self) -> Self {
+        self.try_reflect(normal)
+            .unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    pub fn try_reflect(self, normal: Self) -> Result<Self, MathError> {
+        Ok(self - normal * 2.0 * self.try_dot(normal)?)
+    }
+
+    // the smaller of each pair of components
+    pub fn min(self, other: Self) -> Self {
+        self.zip_with(other, Float::min)
+    }
+
+    pub fn max(self, other: Self) -> Self {
+        self.zip_with(other, Float::max)
+    }
+
+    // every component clamped between the components of min and max
+    pub fn clamp(self, min: Self, max: Self) -> Self {
+        self.max(min).min(max)
+    }
+
+    pub fn abs(self) -> Self {
+        Tuple4D {
+            x: self.x.abs(),
+            y: self.y.abs(),
+            z: self.z.abs(),
+            w: self.w.abs(),
+        }
+    }
+
+    // the component-wise product, e.g. for scaling a vector per axis
+    pub fn hadamard(self, other: Self) -> Self {
+        self.zip_with(other, |a, b| a * b)
+    }
+
+    // all four components, w included
+    pub fn sum(self) -> Float {
+        self.x + self.y + self.z + self.w
+    }
+
+    fn zip_with(self, other: Self, f: impl Fn(Float, Float) -> Float) -> Self {
+        Tuple4D {
+            x: f(self.x, other.x),
+            y: f(self.y, other.y),
+            z: f(self.z, other.z),
+            w: f(self.w, other.w),
+        }
+    }
+
+    fn check_vector(self) -> Result<(), MathError> {
+        if !self.is_vector() {
+            return Err(MathError::NotAVector(self));
+        }
+        Ok(())
+    }
+}
+
+// (x, y, z, w) with the precision and the width of the formatter for every component
+impl fmt::Display for Tuple4D {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = f.width().unwrap_or(0);
+        let [x, y, z, w] = [self.x, self.y, self.z, self.w].map(|value| format_entry(value, f));
+        write!(f, "({x:>width$}, {y:>width$}, {z:>width$}, {w:>width$})")
+    }
+}
+
+impl ApproxEq for Tuple4D {
+    fn approx_eq(&self, other: &Self, tolerance: Tolerance) -> bool {
+        [self.x, self.y, self.z, self.w].approx_eq(&[other.x, other.y, other.z, other.w], tolerance)
     }
 }
 
 impl PartialEq for Tuple4D {
     fn eq(&self, other: &Self) -> bool {
-        let eps = FLOAT_EQ_EPS;
-        float_eq(self.x, other.x, eps)
-            && float_eq(self.y, other.y, eps)
-            && float_eq(self.z, other.z, eps)
-            && float_eq(self.w, other.w, eps)
+        self.approx_eq(other, default_tolerance())
     }
 }
 
-impl Add for Tuple4D {
-    type Output = Self;
-    fn add(self, other: Self) -> Self::Output {
-        Self {
+impl Add<&Tuple4D> for &Tuple4D {
+    type Output = Tuple4D;
+    fn add(self, other: &Tuple4D) -> Self::Output {
+        Tuple4D {
             x: self.x + other.x,
             y: self.y + other.y,
             z: self.z + other.z,
@@ -79,10 +190,12 @@ impl Add for Tuple4D {
     }
 }
 
-impl Sub for Tuple4D {
-    type Output = Self;
+forward_ref_binop!(impl Add, add for Tuple4D, Tuple4D => Tuple4D);
+
+impl Sub<&Tuple4D> for &Tuple4D {
+    type Output = Tuple4D;
 
-    fn sub(self, other: Self) -> Self::Output {
+    fn sub(self, other: &Tuple4D) -> Self::Output {
         Tuple4D {
             x: self.x - other.x,
             y: self.y - other.y,
@@ -92,8 +205,10 @@ impl Sub for Tuple4D {
     }
 }
 
-impl Neg for Tuple4D {
-    type Output = Self;
+forward_ref_binop!(impl Sub, sub for Tuple4D, Tuple4D => Tuple4D);
+
+impl Neg for &Tuple4D {
+    type Output = Tuple4D;
 
     fn neg(self) -> Self::Output {
         Tuple4D {
@@ -105,11 +220,19 @@ impl Neg for Tuple4D {
     }
 }
 
-impl Mul<f64> for Tuple4D {
+impl Neg for Tuple4D {
     type Output = Self;
 
-    fn mul(self, rhs: f64) -> Self::Output {
-        Self {
+    fn neg(self) -> Self::Output {
+        Neg::neg(&self)
+    }
+}
+
+impl Mul<Float> for &Tuple4D {
+    type Output = Tuple4D;
+
+    fn mul(self, rhs: Float) -> Self::Output {
+        Tuple4D {
             x: self.x * rhs,
             y: self.y * rhs,
             z: self.z * rhs,
@@ -118,11 +241,19 @@ impl Mul<f64> for Tuple4D {
     }
 }
 
-impl Div<f64> for Tuple4D {
+impl Mul<Float> for Tuple4D {
     type Output = Self;
 
-    fn div(self, rhs: f64) -> Self::Output {
-        Self {
+    fn mul(self, rhs: Float) -> Self::Output {
+        Mul::mul(&self, rhs)
+    }
+}
+
+impl Div<Float> for &Tuple4D {
+    type Output = Tuple4D;
+
+    fn div(self, rhs: Float) -> Self::Output {
+        Tuple4D {
             x: self.x / rhs,
             y: self.y / rhs,
             z: self.z / rhs,
@@ -131,8 +262,52 @@ impl Div<f64> for Tuple4D {
     }
 }
 
+impl Div<Float> for Tuple4D {
+    type Output = Self;
+
+    fn div(self, rhs: Float) -> Self::Output {
+        Div::div(&self, rhs)
+    }
+}
+
+impl AddAssign<&Tuple4D> for Tuple4D {
+    fn add_assign(&mut self, rhs: &Tuple4D) {
+        *self = Add::add(&*self, rhs);
+    }
+}
+
+impl AddAssign for Tuple4D {
+    fn add_assign(&mut self, rhs: Tuple4D) {
+        *self += &rhs;
+    }
+}
+
+impl SubAssign<&Tuple4D> for Tuple4D {
+    fn sub_assign(&mut self, rhs: &Tuple4D) {
+        *self = Sub::sub(&*self, rhs);
+    }
+}
+
+impl SubAssign for Tuple4D {
+    fn sub_assign(&mut self, rhs: Tuple4D) {
+        *self -= &rhs;
+    }
+}
+
+impl MulAssign<Float> for Tuple4D {
+    fn mul_assign(&mut self, rhs: Float) {
+        *self = Mul::mul(&*self, rhs);
+    }
+}
+
+impl DivAssign<Float> for Tuple4D {
+    fn div_assign(&mut self, rhs: Float) {
+        *self = Div::div(&*self, rhs);
+    }
+}
+
 impl Index<usize> for Tuple4D {
-    type Output = f64;
+    type Output = Float;
     fn index(&self, index: usize) -> &Self::Output {
         match index {
             0 => &self.x,
@@ -159,6 +334,7 @@ impl IndexMut<usize> for Tuple4D {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::math::float_eq;
 
     #[test]
     fn vector_is_not_point() {
@@ -334,6 +510,25 @@ mod tests {
         );
     }
 
+    #[test]
+    fn operators_on_references() {
+        let a = Tuple4D::new_point(3.0, -2.0, 5.0);
+        let b = Tuple4D::new_vector(-2.0, 3.0, 1.0);
+        let (ref_a, ref_b) = (&a, &b);
+        assert_eq!(ref_a + ref_b, a + b);
+        assert_eq!(ref_a - b, a - b);
+        assert_eq!(a - ref_b, a - b);
+        assert_eq!(-ref_b, -b);
+        assert_eq!(ref_b * 2.0, b * 2.0);
+        assert_eq!(ref_b / 2.0, b / 2.0);
+        let mut c = a;
+        c += ref_b;
+        c -= b;
+        c *= 4.0;
+        c /= 2.0;
+        assert_eq!(c, a * 2.0);
+    }
+
     #[test]
     fn check_magnitude() {
         let a = Tuple4D::new_vector(0.0, 1.0, 0.0);
@@ -341,12 +536,13 @@ mod tests {
         let b = Tuple4D::new_vector(0.0, 0.0, 1.0);
         assert_eq!(b.magnitude(), 1.0);
         let c = Tuple4D::new_vector(2.0, 3.0, 4.0);
-        assert_eq!(c.magnitude(), (29.0_f64).sqrt());
+        assert_eq!(c.magnitude(), Float::sqrt(29.0));
         let d = Tuple4D::new_point(1.0, 1.0, 1.0);
         assert_eq!(d.magnitude(), 2.0);
     }
 
     #[test]
+    #[cfg_attr(feature = "f32", ignore = "exact only in double precision")]
     fn check_normalizations() {
         let a = Tuple4D::new_vector(4.0, 0.0, 0.0);
         assert_eq!(a.normalize(), Tuple4D::new_vector(1.0, 0.0, 0.0));
@@ -356,9 +552,9 @@ mod tests {
         assert_eq!(
             b.normalize(),
             Tuple4D::new_vector(
-                1.0 / (14.0_f64).sqrt(),
-                2.0 / (14.0_f64).sqrt(),
-                3.0 / (14.0_f64).sqrt()
+                1.0 / Float::sqrt(14.0),
+                2.0 / Float::sqrt(14.0),
+                3.0 / Float::sqrt(14.0)
             )
         );
         assert_eq!(b.normalize().magnitude(), 1.0);
@@ -392,4 +588,90 @@ mod tests {
         let a = Tuple4D::new_point(1.0, 2.0, 3.0);
         a.cross(a);
     }
+
+    #[test]
+    fn reflecting_vector_approaching_at_45_degrees() {
+        let vec = Tuple4D::new_vector(1.0, -1.0, 0.0);
+        let normal = Tuple4D::new_vector(0.0, 1.0, 0.0);
+        assert_eq!(vec.reflect(normal), Tuple4D::new_vector(1.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn reflecting_vector_off_slanted_surface() {
+        let vec = Tuple4D::new_vector(0.0, -1.0, 0.0);
+        let value = Float::sqrt(2.0) / 2.0;
+        let normal = Tuple4D::new_vector(value, value, 0.0);
+        assert_eq!(vec.reflect(normal), Tuple4D::new_vector(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    #[should_panic]
+    fn reflect_on_point() {
+        let point = Tuple4D::new_point(1.0, -1.0, 0.0);
+        point.reflect(Tuple4D::new_vector(0.0, 1.0, 0.0));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serializing_tuple() {
+        let point = Tuple4D::new_point(1.0, -2.0, 3.5);
+        let json = serde_json::to_string(&point).unwrap();
+        assert_eq!(json, r#"{"x":1.0,"y":-2.0,"z":3.5,"w":1.0}"#);
+        assert_eq!(serde_json::from_str::<Tuple4D>(&json).unwrap(), point);
+    }
+
+    #[test]
+    fn display() {
+        let point = Tuple4D::new_point(1.0, -2.5, 1.0 / 3.0);
+        assert_eq!(point.to_string(), "(1.000, -2.500, 0.333, 1.000)");
+        assert_eq!(format!("{point:6.1}"), "(   1.0,   -2.5,    0.3,    1.0)");
+        assert_eq!(
+            format!("{:.2}", Tuple4D::new_vector(-0.001, 0.0, 0.0)),
+            "(0.00, 0.00, 0.00, 0.00)"
+        );
+    }
+
+    #[test]
+    fn fallible_operations() {
+        let point = Tuple4D::new_point(1.0, 2.0, 3.0);
+        let vector = Tuple4D::new_vector(1.0, 2.0, 3.0);
+        assert_eq!(vector.try_dot(vector), Ok(14.0));
+        assert_eq!(vector.try_dot(point), Err(MathError::NotAVector(point)));
+        assert_eq!(point.try_cross(vector), Err(MathError::NotAVector(point)));
+        assert_eq!(vector.try_cross(vector), Ok(Tuple4D::zeros()));
+        assert_eq!(point.try_reflect(vector), Err(MathError::NotAVector(point)));
+        assert_eq!(Tuple4D::zeros().try_normalize(), Err(MathError::ZeroLength));
+        assert_eq!(
+            Tuple4D::new_vector(0.0, 0.0, 2.0).try_normalize(),
+            Ok(Tuple4D::new_vector(0.0, 0.0, 1.0))
+        );
+    }
+
+    #[test]
+    fn component_wise_operations() {
+        let a = Tuple4D::new_point(1.0, -4.0, 3.0);
+        let b = Tuple4D::new_point(2.0, -5.0, -1.0);
+        assert_eq!(a.min(b), Tuple4D::new_point(1.0, -5.0, -1.0));
+        assert_eq!(a.max(b), Tuple4D::new_point(2.0, -4.0, 3.0));
+        assert_eq!(a.abs(), Tuple4D::new_point(1.0, 4.0, 3.0));
+        assert_eq!(a.hadamard(b), Tuple4D::new_point(2.0, 20.0, -3.0));
+        assert_eq!(a.sum(), 1.0);
+        let clamped = a.clamp(
+            Tuple4D::new_point(0.0, -2.0, 0.0),
+            Tuple4D::new_point(0.5, 2.0, 2.0),
+        );
+        assert_eq!(clamped, Tuple4D::new_point(0.5, -2.0, 2.0));
+    }
+
+    #[test]
+    fn projecting_homogeneous_points() {
+        let tuple = Tuple4D {
+            x: 2.0,
+            y: -4.0,
+            z: 1.0,
+            w: 2.0,
+        };
+        assert_eq!(tuple.project(), Tuple4D::new_point(1.0, -2.0, 0.5));
+        assert_eq!(tuple.project().project(), tuple.project());
+    }
 }
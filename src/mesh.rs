@@ -0,0 +1,146 @@
+use std::collections::HashMap;
+
+use crate::math::{float_bits, Float, Tuple4D, FLOAT_EQ_EPS};
+use crate::shapes::Triangle;
+
+// replaces flat triangles by smooth ones whose corner normals average the faces sharing the corner.
+// Faces meeting at an angle above crease_angle (in radians) keep a hard edge between them, so 0
+// leaves the mesh faceted. Triangles that already have normals are not changed
+pub fn smooth_normals(triangles: &[Triangle], crease_angle: Float) -> Vec<Triangle> {
+    // slack keeps the face itself in the average for a crease angle of 0
+    let min_cos = crease_angle.cos() - FLOAT_EQ_EPS;
+    let mut faces_at_corner: HashMap<[u64; 3], Vec<usize>> = HashMap::new();
+    for (idx, triangle) in triangles.iter().enumerate() {
+        for corner in [triangle.p1(), triangle.p2(), triangle.p3()] {
+            faces_at_corner
+                .entry(position_key(corner))
+                .or_default()
+                .push(idx);
+        }
+    }
+    triangles
+        .iter()
+        .map(|triangle| {
+            if triangle.normals().is_some() || area(triangle) == 0.0 {
+                return *triangle;
+            }
+            let normal = triangle.face_normal();
+            let [n1, n2, n3] = [triangle.p1(), triangle.p2(), triangle.p3()].map(|corner| {
+                faces_at_corner[&position_key(corner)]
+                    .iter()
+                    .map(|&idx| &triangles[idx])
+                    .filter(|other| other.face_normal().dot(normal) >= min_cos)
+                    .fold(Tuple4D::new_vector(0.0, 0.0, 0.0), |sum, other| {
+                        sum + other.face_normal() * area(other)
+                    })
+                    .normalize()
+            });
+            Triangle::smooth(triangle.p1(), triangle.p2(), triangle.p3(), n1, n2, n3)
+        })
+        .collect()
+}
+
+// normals at each vertex of an indexed mesh, averaged over all faces using the vertex and weighted
+// by their area. Vertices without faces get a zero vector
+pub fn vertex_normals(vertices: &[Tuple4D], faces: &[[usize; 3]]) -> Vec<Tuple4D> {
+    let mut normals = vec![Tuple4D::new_vector(0.0, 0.0, 0.0); vertices.len()];
+    for &[i1, i2, i3] in faces {
+        let triangle = Triangle::new(vertices[i1], vertices[i2], vertices[i3]);
+        if area(&triangle) == 0.0 {
+            continue;
+        }
+        let weighted = triangle.face_normal() * area(&triangle);
+        for idx in [i1, i2, i3] {
+            normals[idx] += weighted;
+        }
+    }
+    normals
+        .into_iter()
+        .map(|normal| {
+            if normal.magnitude() > 0.0 {
+                normal.normalize()
+            } else {
+                normal
+            }
+        })
+        .collect()
+}
+
+fn area(triangle: &Triangle) -> Float {
+    triangle.e1().cross(triangle.e2()).magnitude() / 2.0
+}
+
+// corners are only merged if they are at exactly the same position, as written by the exporter
+fn position_key(point: Tuple4D) -> [u64; 3] {
+    // +0.0 and -0.0 have different bits
+    [point.x, point.y, point.z].map(|value| float_bits(value + 0.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::consts::{FRAC_1_SQRT_2, FRAC_PI_4};
+
+    use super::*;
+
+    // two faces of a roof meeting at a right angle along the z axis
+    fn roof() -> Vec<Triangle> {
+        let ridge_front = Tuple4D::new_point(0.0, 1.0, 0.0);
+        let ridge_back = Tuple4D::new_point(0.0, 1.0, 1.0);
+        vec![
+            Triangle::new(ridge_front, Tuple4D::new_point(-1.0, 0.0, 0.0), ridge_back),
+            Triangle::new(ridge_front, ridge_back, Tuple4D::new_point(1.0, 0.0, 0.0)),
+        ]
+    }
+
+    #[test]
+    fn smoothing_averages_normals_of_shared_corners() {
+        let smooth = smooth_normals(&roof(), FRAC_PI_4 * 2.5);
+        let [ridge, side, _] = smooth[0].normals().unwrap();
+        assert_eq!(ridge, Tuple4D::new_vector(0.0, -1.0, 0.0));
+        assert_eq!(
+            side,
+            Tuple4D::new_vector(FRAC_1_SQRT_2, -FRAC_1_SQRT_2, 0.0)
+        );
+    }
+
+    #[test]
+    fn edges_sharper_than_crease_angle_stay_hard() {
+        let roof = roof();
+        let smooth = smooth_normals(&roof, FRAC_PI_4);
+        for (smooth, flat) in smooth.iter().zip(&roof) {
+            assert_eq!(smooth.normals(), Some([flat.face_normal(); 3]));
+        }
+    }
+
+    #[test]
+    fn existing_normals_are_kept() {
+        let normal = Tuple4D::new_vector(0.0, 0.0, 1.0);
+        let triangle = Triangle::smooth(
+            Tuple4D::new_point(0.0, 1.0, 0.0),
+            Tuple4D::new_point(-1.0, 0.0, 0.0),
+            Tuple4D::new_point(1.0, 0.0, 0.0),
+            normal,
+            normal,
+            normal,
+        );
+        assert_eq!(smooth_normals(&[triangle], 1.0), vec![triangle]);
+    }
+
+    #[test]
+    fn vertex_normals_of_indexed_mesh() {
+        let vertices = [
+            Tuple4D::new_point(0.0, 1.0, 0.0),
+            Tuple4D::new_point(0.0, 1.0, 1.0),
+            Tuple4D::new_point(-1.0, 0.0, 0.0),
+            Tuple4D::new_point(1.0, 0.0, 0.0),
+            Tuple4D::new_point(5.0, 5.0, 5.0),
+        ];
+        let normals = vertex_normals(&vertices, &[[0, 2, 1], [0, 1, 3]]);
+        assert_eq!(normals[0], Tuple4D::new_vector(0.0, -1.0, 0.0));
+        assert_eq!(
+            normals[3],
+            Tuple4D::new_vector(-FRAC_1_SQRT_2, -FRAC_1_SQRT_2, 0.0)
+        );
+        assert_eq!(normals[4], Tuple4D::new_vector(0.0, 0.0, 0.0));
+    }
+}
@@ -0,0 +1,608 @@
+use std::io::{BufRead, BufReader, Error, ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{mpsc, Condvar, Mutex};
+use std::thread;
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+
+use crate::scene_file::transform_matrix;
+use crate::{
+    Camera, CameraDescription, Canvas, Color, ObjectDescription, Progress, Region, SceneFile,
+    ShapeDescription, World,
+};
+
+// side length of the square tiles the image is split into
+pub const DEFAULT_TILE_SIZE: usize = 64;
+
+// largest image a worker renders tiles of, 8192x8192 pixels
+pub const DEFAULT_MAX_PIXELS: usize = 1 << 26;
+
+// longer messages are rejected before they are read completely, a tile of the default size is
+// far below it
+const MAX_MESSAGE_LENGTH: u64 = 64 << 20;
+
+// what the coordinator and the workers send each other, one json object per line. A connection
+// starts with the scene, followed by any number of tiles which are answered with their pixels
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Message {
+    // camera None renders the camera of the scene instead of one of the named ones
+    Scene {
+        scene: Box<SceneFile>,
+        camera: Option<String>,
+    },
+    Tile {
+        region: Region,
+    },
+    // the colors of the region row by row
+    Pixels {
+        region: Region,
+        colors: Vec<Color>,
+    },
+    Error {
+        message: String,
+    },
+}
+
+struct Connection {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl Connection {
+    fn new(stream: TcpStream) -> std::io::Result<Connection> {
+        Ok(Connection {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: stream,
+        })
+    }
+
+    fn send(&mut self, message: &Message) -> std::io::Result<()> {
+        let mut line = serde_json::to_string(message).map_err(Error::other)?;
+        line.push('\n');
+        self.writer.write_all(line.as_bytes())?;
+        self.writer.flush()
+    }
+
+    // None once the other side closed the connection
+    fn receive(&mut self) -> std::io::Result<Option<Message>> {
+        let mut line = String::new();
+        let length = (&mut self.reader)
+            .take(MAX_MESSAGE_LENGTH)
+            .read_line(&mut line)?;
+        if length == 0 {
+            return Ok(None);
+        }
+        if length as u64 == MAX_MESSAGE_LENGTH && !line.ends_with('\n') {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("message longer than {MAX_MESSAGE_LENGTH} bytes"),
+            ));
+        }
+        serde_json::from_str(&line)
+            .map(Some)
+            .map_err(|err| Error::new(ErrorKind::InvalidData, err))
+    }
+}
+
+// renders tiles for coordinators that connect to it. Mesh files of the scenes are looked up in
+// directory, so it has to hold the same files as the directory of the scene on the coordinator.
+// Anyone who can connect can send scenes, so only meshes inside of directory are read, nothing is
+// written and the image size is limited
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderWorker {
+    pub directory: PathBuf,
+    // render threads, all cores by default
+    pub threads: Option<usize>,
+    // width times height of the largest image that is rendered
+    pub max_pixels: usize,
+}
+
+impl RenderWorker {
+    pub fn new(directory: impl Into<PathBuf>) -> RenderWorker {
+        RenderWorker {
+            directory: directory.into(),
+            threads: None,
+            max_pixels: DEFAULT_MAX_PIXELS,
+        }
+    }
+
+    // serves one coordinator after the other until the listener fails, a render uses all threads
+    pub fn serve(&self, listener: &TcpListener) -> std::io::Result<()> {
+        for stream in listener.incoming() {
+            // a coordinator that goes away only ends its own connection
+            let _ = self.handle(stream?);
+        }
+        Ok(())
+    }
+
+    // renders the tiles of a single coordinator until it closes the connection. A scene that
+    // makes the worker panic is answered with an error and dropped
+    pub fn handle(&self, stream: TcpStream) -> std::io::Result<()> {
+        let mut connection = Connection::new(stream)?;
+        let mut scene: Option<(World, Camera)> = None;
+        while let Some(message) = connection.receive()? {
+            let reply =
+                match panic::catch_unwind(AssertUnwindSafe(|| self.reply(message, &mut scene))) {
+                    Ok(Some(reply)) => reply,
+                    Ok(None) => continue,
+                    Err(payload) => {
+                        scene = None;
+                        let reason = payload
+                            .downcast_ref::<&str>()
+                            .copied()
+                            .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+                            .unwrap_or("unknown reason");
+                        Message::Error {
+                            message: format!("the worker panicked: {reason}"),
+                        }
+                    }
+                };
+            connection.send(&reply)?;
+        }
+        Ok(())
+    }
+}
+
+impl RenderWorker {
+    // None for scenes, which are only answered if they can not be rendered
+    fn reply(&self, message: Message, scene: &mut Option<(World, Camera)>) -> Option<Message> {
+        let reply = match message {
+            Message::Scene {
+                scene: file,
+                camera,
+            } => match self
+                .check(&file, camera.as_deref())
+                .and_then(|()| build(&file, camera.as_deref(), &self.directory))
+            {
+                Ok((world, mut camera)) => {
+                    if let Some(threads) = self.threads {
+                        camera.threads = threads.max(1);
+                    }
+                    *scene = Some((world, camera));
+                    return None;
+                }
+                Err(message) => Message::Error { message },
+            },
+            Message::Tile { region } => match scene {
+                Some((world, camera)) => {
+                    let region = region.clamp_to(camera.hsize(), camera.vsize());
+                    let canvas = camera.render_region(world, region);
+                    Message::Pixels {
+                        region,
+                        colors: canvas.iter().copied().collect(),
+                    }
+                }
+                None => Message::Error {
+                    message: "tile sent before the scene".to_string(),
+                },
+            },
+            _ => Message::Error {
+                message: "workers only take scenes and tiles".to_string(),
+            },
+        };
+        Some(reply)
+    }
+
+    // the parts of a scene from the network that are not rendered, and the views and transforms
+    // that could not be rendered
+    fn check(&self, scene: &SceneFile, camera: Option<&str>) -> Result<(), String> {
+        if scene.bvh_cache.is_some() {
+            return Err("workers do not write bvh caches".to_string());
+        }
+        let description = camera_description(scene, camera).map_err(|err| err.to_string())?;
+        description.build().map_err(|err| err.to_string())?;
+        let (width, height) = (description.width, description.height);
+        if width
+            .checked_mul(height)
+            .is_none_or(|pixels| pixels > self.max_pixels)
+        {
+            return Err(format!(
+                "the image of {width}x{height} pixels is larger than the {} pixels of the worker",
+                self.max_pixels
+            ));
+        }
+        scene.objects.iter().try_for_each(check_object)
+    }
+}
+
+// transforms have to be invertible and meshes inside of the directory of the worker
+fn check_object(object: &ObjectDescription) -> Result<(), String> {
+    transform_matrix(&object.transform).map_err(|err| err.to_string())?;
+    if let Some(transform_end) = &object.transform_end {
+        transform_matrix(transform_end).map_err(|err| err.to_string())?;
+    }
+    match &object.shape {
+        ShapeDescription::Mesh { file } => {
+            let inside = Path::new(file)
+                .components()
+                .all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+            if !inside {
+                return Err(format!(
+                    "mesh file '{file}' is outside of the directory of the worker"
+                ));
+            }
+            Ok(())
+        }
+        ShapeDescription::Group { children } => children.iter().try_for_each(check_object),
+        _ => Ok(()),
+    }
+}
+
+fn build(
+    scene: &SceneFile,
+    camera: Option<&str>,
+    directory: &Path,
+) -> Result<(World, Camera), String> {
+    let camera = match camera {
+        Some(name) => scene.build_camera(name)?,
+        None => scene.camera.build().map_err(|err| err.to_string())?,
+    };
+    let world = scene
+        .build_world(directory)
+        .map_err(|err| err.to_string())?;
+    Ok((world, camera))
+}
+
+// splits renders into tiles that are handed out to workers on other machines, every worker gets
+// the next tile as soon as it sent back the last one. Tiles of workers that fail are given to the
+// remaining ones
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetworkRenderer {
+    // host:port of every worker
+    pub workers: Vec<String>,
+    pub tile_size: usize,
+}
+
+// tiles that still have to be rendered and the number of tiles workers are busy with
+struct TileQueue {
+    tiles: Vec<Region>,
+    in_progress: usize,
+}
+
+impl NetworkRenderer {
+    pub fn new(workers: Vec<String>) -> NetworkRenderer {
+        NetworkRenderer {
+            workers,
+            tile_size: DEFAULT_TILE_SIZE,
+        }
+    }
+
+    // the image of the camera of the scene or of the named camera
+    pub fn render(&self, scene: &SceneFile, camera: Option<&str>) -> std::io::Result<Canvas> {
+        let (width, height) = image_size(scene, camera)?;
+        self.render_region(scene, camera, Region::new(0, 0, width, height), |_| ())
+    }
+
+    // the pixels of region, which is clamped to the image. on_progress is called on the calling
+    // thread after every tile, the tiles count as rows
+    pub fn render_region(
+        &self,
+        scene: &SceneFile,
+        camera: Option<&str>,
+        region: Region,
+        mut on_progress: impl FnMut(&Progress),
+    ) -> std::io::Result<Canvas> {
+        let (width, height) = image_size(scene, camera)?;
+        let region = region.clamp_to(width, height);
+        let tiles = self.tiles(region);
+        let total = tiles.len();
+        // the tiles are taken from the back
+        let queue = Mutex::new(TileQueue {
+            tiles: tiles.into_iter().rev().collect(),
+            in_progress: 0,
+        });
+        let changed = Condvar::new();
+        let mut canvas = Canvas::create_canvas(region.width, region.height);
+        let mut done = 0;
+        let mut errors = vec![];
+        let start = Instant::now();
+        thread::scope(|scope| {
+            let (sender, receiver) = mpsc::channel();
+            for worker in &self.workers {
+                let sender = sender.clone();
+                let (queue, changed) = (&queue, &changed);
+                scope.spawn(move || {
+                    let result = render_tiles(worker, scene, camera, queue, changed, |tile| {
+                        let _ = sender.send(Ok(tile));
+                    });
+                    if let Err(err) = result {
+                        let _ = sender.send(Err(format!("{worker}: {err}")));
+                    }
+                });
+            }
+            drop(sender);
+            for result in receiver {
+                match result {
+                    Ok((tile, pixels)) => {
+                        canvas.paste(tile.x - region.x, tile.y - region.y, &pixels);
+                        done += 1;
+                        on_progress(&Progress {
+                            rows_done: done,
+                            rows_total: total,
+                            elapsed: start.elapsed(),
+                        });
+                    }
+                    Err(err) => errors.push(err),
+                }
+            }
+        });
+        if done < total {
+            let reason = if errors.is_empty() {
+                "no workers".to_string()
+            } else {
+                errors.join(", ")
+            };
+            return Err(Error::other(format!(
+                "{} of {total} tiles were not rendered: {reason}",
+                total - done
+            )));
+        }
+        Ok(canvas)
+    }
+
+    fn tiles(&self, region: Region) -> Vec<Region> {
+        let size = self.tile_size.max(1);
+        (region.y..region.y + region.height)
+            .step_by(size)
+            .flat_map(|y| {
+                (region.x..region.x + region.width)
+                    .step_by(size)
+                    .map(move |x| Region::new(x, y, size, size))
+            })
+            .map(|tile| {
+                let end = (region.x + region.width, region.y + region.height);
+                Region::new(
+                    tile.x,
+                    tile.y,
+                    tile.width.min(end.0 - tile.x),
+                    tile.height.min(end.1 - tile.y),
+                )
+            })
+            .collect()
+    }
+}
+
+fn image_size(scene: &SceneFile, camera: Option<&str>) -> std::io::Result<(usize, usize)> {
+    let description = camera_description(scene, camera)?;
+    Ok((description.width, description.height))
+}
+
+fn camera_description<'a>(
+    scene: &'a SceneFile,
+    camera: Option<&str>,
+) -> std::io::Result<&'a CameraDescription> {
+    match camera {
+        Some(name) => scene.cameras.get(name).ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!("the scene has no camera named '{name}'"),
+            )
+        }),
+        None => Ok(&scene.camera),
+    }
+}
+
+// sends the scene to one worker and then tiles from the queue until it is empty. The tile a
+// failing worker was busy with goes back into the queue
+fn render_tiles(
+    worker: &str,
+    scene: &SceneFile,
+    camera: Option<&str>,
+    queue: &Mutex<TileQueue>,
+    changed: &Condvar,
+    mut on_tile: impl FnMut((Region, Canvas)),
+) -> std::io::Result<()> {
+    let address = worker
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "address without host"))?;
+    let mut connection = Connection::new(TcpStream::connect(address)?)?;
+    connection.send(&Message::Scene {
+        scene: Box::new(scene.clone()),
+        camera: camera.map(str::to_string),
+    })?;
+    loop {
+        let tile = {
+            let mut queue = queue.lock().expect("tile queue poisoned");
+            loop {
+                if let Some(tile) = queue.tiles.pop() {
+                    queue.in_progress += 1;
+                    break Some(tile);
+                }
+                // tiles of other workers come back if they fail
+                if queue.in_progress == 0 {
+                    break None;
+                }
+                queue = changed.wait(queue).expect("tile queue poisoned");
+            }
+        };
+        let Some(tile) = tile else {
+            return Ok(());
+        };
+        let result = render_tile(&mut connection, tile);
+        let mut queue = queue.lock().expect("tile queue poisoned");
+        queue.in_progress -= 1;
+        if result.is_err() {
+            queue.tiles.push(tile);
+        }
+        changed.notify_all();
+        drop(queue);
+        on_tile((tile, result?));
+    }
+}
+
+fn render_tile(connection: &mut Connection, tile: Region) -> std::io::Result<Canvas> {
+    connection.send(&Message::Tile { region: tile })?;
+    match connection.receive()? {
+        Some(Message::Pixels { region, colors })
+            if region == tile && colors.len() == tile.width * tile.height =>
+        {
+            let mut canvas = Canvas::create_canvas(tile.width, tile.height);
+            for (idx, color) in colors.into_iter().enumerate() {
+                canvas.write_pixel(idx % tile.width, idx / tile.width, color);
+            }
+            Ok(canvas)
+        }
+        Some(Message::Error { message }) => Err(Error::other(message)),
+        Some(_) => Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("unexpected answer to tile {tile:?}"),
+        )),
+        None => Err(Error::new(
+            ErrorKind::UnexpectedEof,
+            "the worker closed the connection",
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_scene_toml;
+
+    const SCENE: &str = r#"
+        [camera]
+        width = 21
+        height = 13
+        field_of_view = 1.0
+        view = { from = [0, 1.5, -5], to = [0, 1, 0], up = [0, 1, 0] }
+
+        [[lights]]
+        type = "point"
+        position = [-10, 10, -10]
+        intensity = [1, 1, 1]
+
+        [[objects]]
+        type = "sphere"
+        transform = [{ translate = [0, 1, 0] }]
+        material = { color = [0.2, 0.4, 0.8], reflective = 0.3 }
+
+        [[objects]]
+        type = "plane"
+    "#;
+
+    fn start_worker() -> String {
+        start(RenderWorker::new(""))
+    }
+
+    fn start(worker: RenderWorker) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        thread::spawn(move || worker.serve(&listener));
+        address
+    }
+
+    #[test]
+    fn tiles_cover_the_region() {
+        let mut renderer = NetworkRenderer::new(vec![]);
+        renderer.tile_size = 8;
+        let tiles = renderer.tiles(Region::new(3, 2, 20, 10));
+        assert_eq!(tiles.len(), 6);
+        assert_eq!(tiles[0], Region::new(3, 2, 8, 8));
+        assert_eq!(tiles[5], Region::new(19, 10, 4, 2));
+        let area: usize = tiles.iter().map(|tile| tile.width * tile.height).sum();
+        assert_eq!(area, 200);
+    }
+
+    #[test]
+    fn workers_render_the_same_image() {
+        let scene = parse_scene_toml(SCENE).unwrap();
+        let (world, camera) = scene.build(Path::new("")).unwrap();
+        let mut renderer = NetworkRenderer::new(vec![start_worker(), start_worker()]);
+        renderer.tile_size = 5;
+        let mut calls = 0;
+        let region = Region::new(4, 2, 30, 30);
+        let canvas = renderer
+            .render_region(&scene, None, region, |_| calls += 1)
+            .unwrap();
+        assert_eq!(calls, 12);
+        let expected = camera.render_region(&world, region.clamp_to(21, 13));
+        assert_eq!(
+            canvas.iter().collect::<Vec<_>>(),
+            expected.iter().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn tiles_of_unreachable_workers_go_to_the_others() {
+        let scene = parse_scene_toml(SCENE).unwrap();
+        // nothing listens on the port of a dropped listener
+        let closed = TcpListener::bind("127.0.0.1:0").unwrap();
+        let closed_address = closed.local_addr().unwrap().to_string();
+        drop(closed);
+        let renderer = NetworkRenderer::new(vec![closed_address.clone(), start_worker()]);
+        assert!(renderer.render(&scene, None).is_ok());
+        let err = NetworkRenderer::new(vec![closed_address])
+            .render(&scene, None)
+            .unwrap_err();
+        assert!(err.to_string().contains("tiles were not rendered"), "{err}");
+        let err = NetworkRenderer::new(vec![start_worker()])
+            .render(&scene, Some("top"))
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn workers_only_render_safe_scenes() {
+        let mut worker = RenderWorker::new("");
+        worker.max_pixels = 21 * 13;
+        let render = |scene: &str| {
+            let scene = parse_scene_toml(scene).unwrap();
+            NetworkRenderer::new(vec![start(worker.clone())])
+                .render(&scene, None)
+                .unwrap_err()
+                .to_string()
+        };
+        let err = render(&format!("bvh_cache = \"cache\"\n{SCENE}"));
+        assert!(err.contains("bvh caches"), "{err}");
+        for file in ["/etc/passwd", "../mesh.obj", "meshes/../../mesh.obj"] {
+            let err = render(&format!(
+                "{SCENE}\n[[objects]]\ntype = \"mesh\"\nfile = \"{file}\""
+            ));
+            assert!(err.contains("outside of the directory"), "{err}");
+        }
+        let err = render(&SCENE.replace("width = 21", "width = 22"));
+        assert!(err.contains("larger than"), "{err}");
+    }
+
+    #[test]
+    fn workers_survive_scenes_that_can_not_be_rendered() {
+        let address = start_worker();
+        let render = |scene: &str| {
+            let scene = parse_scene_toml(scene).unwrap();
+            NetworkRenderer::new(vec![address.clone()]).render(&scene, None)
+        };
+        let err = render(&SCENE.replace("from = [0, 1.5, -5]", "from = [0, 1, 0]"))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("camera view"), "{err}");
+        let err = render(&SCENE.replace("translate = [0, 1, 0]", "scale = [0, 0, 0]"))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("can not be inverted"), "{err}");
+        assert!(render(SCENE).is_ok());
+    }
+
+    #[test]
+    fn long_messages_are_rejected() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (server, _) = listener.accept().unwrap();
+        let writer = thread::spawn(move || {
+            let chunk = vec![b' '; 1 << 20];
+            // the worker stops reading, which ends the writes with an error
+            for _ in 0..=(MAX_MESSAGE_LENGTH >> 20) {
+                if stream.write_all(&chunk).is_err() {
+                    break;
+                }
+            }
+        });
+        let err = RenderWorker::new("").handle(server).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        writer.join().unwrap();
+    }
+}
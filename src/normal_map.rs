@@ -0,0 +1,163 @@
+use std::sync::Arc;
+
+use crate::math::{Float, Tuple4D};
+use crate::patterns::{Pattern, UvMap, UvPattern};
+use crate::Color;
+
+// step size for the finite differences, in object space
+const DELTA: Float = 0.0001;
+
+// perturbs the object space normal of a surface to fake fine detail
+#[derive(Debug, Clone)]
+pub enum NormalMap {
+    // the brightness of the pattern is used as height field, the normal is tilted along its gradient
+    Bump {
+        height: Arc<dyn Pattern>,
+        scale: Float,
+    },
+    // the rgb values of the texture encode a tangent space normal, like common normal map images
+    Texture {
+        texture: Arc<dyn UvPattern>,
+        mapping: UvMap,
+    },
+}
+
+impl NormalMap {
+    pub fn bump(height: impl Pattern + 'static, scale: Float) -> NormalMap {
+        NormalMap::Bump {
+            height: Arc::new(height),
+            scale,
+        }
+    }
+
+    pub fn texture(texture: impl UvPattern + 'static, mapping: UvMap) -> NormalMap {
+        NormalMap::Texture {
+            texture: Arc::new(texture),
+            mapping,
+        }
+    }
+
+    // point and normal are given in object space
+    pub fn perturb(&self, point: Tuple4D, normal: Tuple4D) -> Tuple4D {
+        let normal = normal.normalize();
+        match self {
+            NormalMap::Bump { height, scale } => {
+                let height_at =
+                    |offset: Tuple4D| brightness(height.pattern_at_nested(point + offset));
+                let dx = Tuple4D::new_vector(DELTA, 0.0, 0.0);
+                let dy = Tuple4D::new_vector(0.0, DELTA, 0.0);
+                let dz = Tuple4D::new_vector(0.0, 0.0, DELTA);
+                let gradient = Tuple4D::new_vector(
+                    height_at(dx) - height_at(-dx),
+                    height_at(dy) - height_at(-dy),
+                    height_at(dz) - height_at(-dz),
+                ) / (2.0 * DELTA);
+                // only the part of the gradient along the surface tilts the normal
+                let tangential = gradient - normal * gradient.dot(normal);
+                (normal - tangential * *scale).normalize()
+            }
+            NormalMap::Texture { texture, mapping } => {
+                let (u, v) = mapping(point);
+                let (tangent, bitangent) = tangent_frame(*mapping, point, normal);
+                let encoded = texture.uv_pattern_at(u, v);
+                let x = encoded.r * 2.0 - 1.0;
+                let y = encoded.g * 2.0 - 1.0;
+                let z = encoded.b * 2.0 - 1.0;
+                (tangent * x + bitangent * y + normal * z).normalize()
+            }
+        }
+    }
+}
+
+fn brightness(color: Color) -> Float {
+    (color.r + color.g + color.b) / 3.0
+}
+
+// any two unit vectors perpendicular to the normal and to each other
+pub(crate) fn orthonormal_basis(normal: Tuple4D) -> (Tuple4D, Tuple4D) {
+    let helper = if normal.x.abs() > 0.9 {
+        Tuple4D::new_vector(0.0, 1.0, 0.0)
+    } else {
+        Tuple4D::new_vector(1.0, 0.0, 0.0)
+    };
+    let first = normal.cross(helper).normalize();
+    let second = normal.cross(first);
+    (first, second)
+}
+
+// tangent points in the direction of increasing u along the surface, bitangent completes the frame
+fn tangent_frame(mapping: UvMap, point: Tuple4D, normal: Tuple4D) -> (Tuple4D, Tuple4D) {
+    let (first, second) = orthonormal_basis(normal);
+    let (u, _) = mapping(point);
+    let du = |direction: Tuple4D| {
+        let (shifted_u, _) = mapping(point + direction * DELTA);
+        // u wraps around for most mappings
+        let mut diff = shifted_u - u;
+        if diff > 0.5 {
+            diff -= 1.0;
+        } else if diff < -0.5 {
+            diff += 1.0;
+        }
+        diff / DELTA
+    };
+    let gradient_u = first * du(first) + second * du(second);
+    let tangent = if gradient_u.magnitude() > DELTA {
+        gradient_u.normalize()
+    } else {
+        first
+    };
+    (tangent, normal.cross(tangent))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::Matrix4;
+    use crate::patterns::{planar_map, GradientPattern};
+
+    use super::*;
+
+    #[test]
+    fn constant_height_does_not_change_normal() {
+        let normal_map = NormalMap::bump(Color::new(0.5, 0.5, 0.5), 1.0);
+        let normal = Tuple4D::new_vector(0.0, 1.0, 0.0);
+        assert_eq!(
+            normal_map.perturb(Tuple4D::new_point(0.3, 0.0, 0.2), normal),
+            normal
+        );
+    }
+
+    #[test]
+    fn bump_tilts_normal_away_from_rising_height() {
+        let mut height = GradientPattern::new(Color::new(0.0, 0.0, 0.0), Color::new(1.0, 1.0, 1.0));
+        height.transform = Matrix4::scaling(10.0, 1.0, 1.0).into();
+        let normal_map = NormalMap::bump(height, 10.0);
+        let normal = normal_map.perturb(
+            Tuple4D::new_point(2.0, 0.0, 0.0),
+            Tuple4D::new_vector(0.0, 1.0, 0.0),
+        );
+        // height grows by 0.1 per unit along x, scaled by 10 this tilts the normal by 45 degrees
+        let value = Float::sqrt(2.0) / 2.0;
+        assert_eq!(normal, Tuple4D::new_vector(-value, value, 0.0));
+    }
+
+    #[test]
+    fn flat_normal_map_texture_keeps_normal() {
+        let normal_map = NormalMap::texture(Color::new(0.5, 0.5, 1.0), planar_map);
+        let normal = Tuple4D::new_vector(0.0, 1.0, 0.0);
+        assert_eq!(
+            normal_map.perturb(Tuple4D::new_point(0.3, 0.0, 0.7), normal),
+            normal
+        );
+    }
+
+    #[test]
+    fn normal_map_texture_tilts_normal_along_tangent() {
+        // planar mapping has u increasing along x
+        let normal_map = NormalMap::texture(Color::new(1.0, 0.5, 0.5), planar_map);
+        let normal = normal_map.perturb(
+            Tuple4D::new_point(0.3, 0.0, 0.7),
+            Tuple4D::new_vector(0.0, 1.0, 0.0),
+        );
+        assert_eq!(normal, Tuple4D::new_vector(1.0, 0.0, 0.0));
+    }
+}
@@ -0,0 +1,573 @@
+use std::fs;
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::math::{Float, Tuple4D};
+use crate::mesh::smooth_normals;
+use crate::patterns::{TextureFilter, TriangleTexturePattern, UvImage, UvPattern};
+use crate::shapes::Triangle;
+use crate::{Color, Material, Object};
+
+// triangles read from a wavefront obj file. Faces with more than three vertices are split into a
+// fan of triangles, faces with normals for all vertices become smooth triangles
+#[derive(Debug, Default)]
+pub struct ObjFile {
+    pub vertices: Vec<Tuple4D>,
+    pub normals: Vec<Tuple4D>,
+    pub texture_coords: Vec<(Float, Float)>,
+    // number of lines that were not understood
+    pub ignored: usize,
+    // mtl files referenced by mtllib statements
+    pub material_libraries: Vec<String>,
+    // names given to usemtl in the order they first appear, triangles refer to them by index
+    pub material_names: Vec<String>,
+    // materials available to usemtl, filled from the material libraries by from_file
+    pub materials: Vec<ObjMaterial>,
+    // triangles before the first group statement
+    pub default_group: Vec<ObjTriangle>,
+    // named groups in the order they first appear in the file
+    pub groups: Vec<(String, Vec<ObjTriangle>)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ObjTriangle {
+    pub triangle: Triangle,
+    // texture coordinates at the corners, if all of them have one
+    pub uvs: Option<[(Float, Float); 3]>,
+    // index into material_names of the material active for the face
+    pub material: Option<usize>,
+}
+
+// material of an mtl file, the diffuse texture is only loaded by ObjFile::from_file
+#[derive(Debug, Clone)]
+pub struct ObjMaterial {
+    pub name: String,
+    pub material: Material,
+    // file name given by map_Kd, relative to the mtl file
+    pub diffuse_map: Option<String>,
+    pub diffuse_texture: Option<Arc<dyn UvPattern>>,
+}
+
+impl ObjFile {
+    // also loads the material libraries and their textures, which are looked up relative to the obj file
+    pub fn from_file(filename: &str) -> std::io::Result<ObjFile> {
+        let invalid = |err| Error::new(ErrorKind::InvalidData, err);
+        let obj_str = fs::read_to_string(filename)?;
+        let mut obj = parse_obj_file(&obj_str).map_err(invalid)?;
+        let directory = Path::new(filename).parent().unwrap_or(Path::new(""));
+        for library in &obj.material_libraries {
+            let mtl_path = directory.join(library);
+            let mtl_str = fs::read_to_string(&mtl_path)?;
+            for mut material in parse_mtl_file(&mtl_str).map_err(invalid)? {
+                if let Some(diffuse_map) = &material.diffuse_map {
+                    let texture_path = mtl_path.parent().unwrap_or(Path::new("")).join(diffuse_map);
+                    let texture = UvImage::from_file(&texture_path.to_string_lossy())?
+                        .with_filter(TextureFilter::Trilinear);
+                    material.diffuse_texture = Some(Arc::new(texture));
+                }
+                obj.materials.push(material);
+            }
+        }
+        Ok(obj)
+    }
+
+    pub fn group(&self, name: &str) -> Option<&[ObjTriangle]> {
+        self.groups
+            .iter()
+            .find(|(group_name, _)| group_name == name)
+            .map(|(_, triangles)| triangles.as_slice())
+    }
+
+    pub fn material(&self, name: &str) -> Option<&ObjMaterial> {
+        self.materials.iter().find(|material| material.name == name)
+    }
+
+    // smooth normals for the triangles of each group that have none in the file, see smooth_normals
+    pub fn smooth_normals(&mut self, crease_angle: Float) {
+        let groups = std::iter::once(&mut self.default_group)
+            .chain(self.groups.iter_mut().map(|(_, triangles)| triangles));
+        for group in groups {
+            let triangles: Vec<Triangle> = group.iter().map(|t| t.triangle).collect();
+            let smoothed = smooth_normals(&triangles, crease_angle);
+            for (obj_triangle, triangle) in group.iter_mut().zip(smoothed) {
+                obj_triangle.triangle = triangle;
+            }
+        }
+    }
+
+    // a single group with the triangles of the default group and one subgroup per named group.
+    // Triangles without a known material get the default one
+    pub fn into_group(self) -> Object {
+        let mut children = self.triangle_objects(&self.default_group);
+        children.extend(
+            self.groups
+                .iter()
+                .map(|(_, triangles)| Object::group(self.triangle_objects(triangles))),
+        );
+        Object::group(children)
+    }
+
+    fn triangle_objects(&self, triangles: &[ObjTriangle]) -> Vec<Object> {
+        triangles
+            .iter()
+            .map(|obj_triangle| {
+                let mut object = Object::new(Box::new(obj_triangle.triangle));
+                let material = obj_triangle
+                    .material
+                    .and_then(|idx| self.material(&self.material_names[idx]));
+                if let Some(material) = material {
+                    object.material = material.material.clone();
+                    if let (Some(texture), Some(uvs)) =
+                        (&material.diffuse_texture, obj_triangle.uvs)
+                    {
+                        object.material.pattern = Some(Arc::new(TriangleTexturePattern::new(
+                            texture.clone(),
+                            obj_triangle.triangle,
+                            uvs,
+                        )));
+                    }
+                }
+                object
+            })
+            .collect()
+    }
+}
+
+pub fn parse_obj_file(obj_str: &str) -> Result<ObjFile, String> {
+    let mut obj = ObjFile::default();
+    // None while faces go to the default group
+    let mut current_group: Option<usize> = None;
+    let mut current_material: Option<usize> = None;
+    for (line_idx, line) in obj_str.lines().enumerate() {
+        let line_number = line_idx + 1;
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            Some("v") => obj.vertices.push(parse_point(tokens, line_number)?),
+            Some("vn") => {
+                let point = parse_point(tokens, line_number)?;
+                obj.normals
+                    .push(Tuple4D::new_vector(point.x, point.y, point.z));
+            }
+            Some("vt") => {
+                let u = parse_number(tokens.next(), line_number)?;
+                // the second coordinate is optional for 1d textures
+                let v = match tokens.next() {
+                    Some(token) => parse_number(Some(token), line_number)?,
+                    None => 0.0,
+                };
+                obj.texture_coords.push((u, v));
+            }
+            Some("f") => {
+                let triangles = parse_face(&obj, tokens, line_number)?;
+                let group = match current_group {
+                    Some(idx) => &mut obj.groups[idx].1,
+                    None => &mut obj.default_group,
+                };
+                group.extend(triangles.into_iter().map(|triangle| ObjTriangle {
+                    material: current_material,
+                    ..triangle
+                }));
+            }
+            Some("mtllib") => obj
+                .material_libraries
+                .push(tokens.collect::<Vec<_>>().join(" ")),
+            Some("usemtl") => {
+                let name = tokens.collect::<Vec<_>>().join(" ");
+                current_material = Some(
+                    match obj.material_names.iter().position(|known| *known == name) {
+                        Some(idx) => idx,
+                        None => {
+                            obj.material_names.push(name);
+                            obj.material_names.len() - 1
+                        }
+                    },
+                );
+            }
+            Some("g") | Some("o") => {
+                let name = tokens.collect::<Vec<_>>().join(" ");
+                current_group = Some(
+                    match obj
+                        .groups
+                        .iter()
+                        .position(|(group_name, _)| *group_name == name)
+                    {
+                        Some(idx) => idx,
+                        None => {
+                            obj.groups.push((name, vec![]));
+                            obj.groups.len() - 1
+                        }
+                    },
+                );
+            }
+            Some(token) if token.starts_with('#') => {}
+            None => {}
+            Some(_) => obj.ignored += 1,
+        }
+    }
+    Ok(obj)
+}
+
+fn parse_number(token: Option<&str>, line_number: usize) -> Result<Float, String> {
+    let token = token.ok_or(format!("missing number on line {line_number}"))?;
+    token
+        .parse::<Float>()
+        .map_err(|_| format!("invalid number {token} on line {line_number}"))
+}
+
+pub(crate) fn parse_point<'a>(
+    mut tokens: impl Iterator<Item = &'a str>,
+    line_number: usize,
+) -> Result<Tuple4D, String> {
+    let x = parse_number(tokens.next(), line_number)?;
+    let y = parse_number(tokens.next(), line_number)?;
+    let z = parse_number(tokens.next(), line_number)?;
+    Ok(Tuple4D::new_point(x, y, z))
+}
+
+// indices start at 1, negative ones count back from the last element read so far
+fn resolve_index(token: &str, len: usize, line_number: usize) -> Result<usize, String> {
+    let index = token
+        .parse::<i64>()
+        .map_err(|_| format!("invalid index {token} on line {line_number}"))?;
+    let resolved = if index < 0 {
+        len as i64 + index
+    } else {
+        index - 1
+    };
+    if resolved < 0 || resolved >= len as i64 {
+        return Err(format!("index {token} out of range on line {line_number}"));
+    }
+    Ok(resolved as usize)
+}
+
+// face vertices are given as v, v/vt, v//vn or v/vt/vn. The material is left for the caller
+fn parse_face<'a>(
+    obj: &ObjFile,
+    tokens: impl Iterator<Item = &'a str>,
+    line_number: usize,
+) -> Result<Vec<ObjTriangle>, String> {
+    let mut points = vec![];
+    let mut texture_coords = vec![];
+    let mut normals = vec![];
+    for token in tokens {
+        let mut parts = token.split('/');
+        let vertex = parts.next().unwrap_or_default();
+        points.push(obj.vertices[resolve_index(vertex, obj.vertices.len(), line_number)?]);
+        if let Some(texture) = parts.next().filter(|texture| !texture.is_empty()) {
+            let idx = resolve_index(texture, obj.texture_coords.len(), line_number)?;
+            texture_coords.push(obj.texture_coords[idx]);
+        }
+        if let Some(normal) = parts.next().filter(|normal| !normal.is_empty()) {
+            normals.push(obj.normals[resolve_index(normal, obj.normals.len(), line_number)?]);
+        }
+    }
+    if points.len() < 3 {
+        return Err(format!(
+            "face with less than 3 vertices on line {line_number}"
+        ));
+    }
+    let smooth = normals.len() == points.len();
+    let textured = texture_coords.len() == points.len();
+    Ok((1..points.len() - 1)
+        .map(|idx| {
+            let (p1, p2, p3) = (points[0], points[idx], points[idx + 1]);
+            let triangle = if smooth {
+                Triangle::smooth(p1, p2, p3, normals[0], normals[idx], normals[idx + 1])
+            } else {
+                Triangle::new(p1, p2, p3)
+            };
+            let uvs = textured.then(|| {
+                [
+                    texture_coords[0],
+                    texture_coords[idx],
+                    texture_coords[idx + 1],
+                ]
+            });
+            ObjTriangle {
+                triangle,
+                uvs,
+                material: None,
+            }
+        })
+        .collect())
+}
+
+// materials of an mtl file. Kd is the color, Ks the specular highlight, Ns the shininess, d the
+// opacity (Tr its inverse), Ni the refractive index and Ke the emitted light
+pub fn parse_mtl_file(mtl_str: &str) -> Result<Vec<ObjMaterial>, String> {
+    let mut materials: Vec<ObjMaterial> = vec![];
+    for (line_idx, line) in mtl_str.lines().enumerate() {
+        let line_number = line_idx + 1;
+        let mut tokens = line.split_whitespace();
+        let Some(statement) = tokens.next() else {
+            continue;
+        };
+        if statement == "newmtl" {
+            materials.push(ObjMaterial {
+                name: tokens.collect::<Vec<_>>().join(" "),
+                material: Material::default(),
+                diffuse_map: None,
+                diffuse_texture: None,
+            });
+            continue;
+        }
+        if statement.starts_with('#') {
+            continue;
+        }
+        let current = materials
+            .last_mut()
+            .ok_or(format!("{statement} before newmtl on line {line_number}"))?;
+        let material = &mut current.material;
+        match statement {
+            "Kd" => material.color = parse_color(tokens, line_number)?,
+            "Ks" => {
+                let specular = parse_color(tokens, line_number)?;
+                material.specular = (specular.r + specular.g + specular.b) / 3.0;
+            }
+            "Ns" => material.shininess = parse_number(tokens.next(), line_number)?,
+            "d" => material.transparency = 1.0 - parse_number(tokens.next(), line_number)?,
+            "Tr" => material.transparency = parse_number(tokens.next(), line_number)?,
+            "Ni" => material.refractive_index = parse_number(tokens.next(), line_number)?,
+            "Ke" => material.emissive = parse_color(tokens, line_number)?,
+            // options like -s come before the file name
+            "map_Kd" => current.diffuse_map = tokens.last().map(str::to_string),
+            _ => {}
+        }
+    }
+    Ok(materials)
+}
+
+fn parse_color<'a>(
+    tokens: impl Iterator<Item = &'a str>,
+    line_number: usize,
+) -> Result<Color, String> {
+    let point = parse_point(tokens, line_number)?;
+    Ok(Color::new(point.x, point.y, point.z))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Ray;
+
+    const POLYGON: &str = "v -1 1 0\nv -1 0 0\nv 1 0 0\nv 1 1 0\nv 0 2 0\n";
+
+    fn triangles(group: &[ObjTriangle]) -> Vec<Triangle> {
+        group
+            .iter()
+            .map(|obj_triangle| obj_triangle.triangle)
+            .collect()
+    }
+
+    #[test]
+    fn ignoring_unrecognized_lines() {
+        let gibberish = "There was a young lady named Bright\n\
+                         who traveled much faster than light.\n\
+                         She set out one day\n\
+                         in a relative way,\n\
+                         and came back the previous night.";
+        let obj = parse_obj_file(gibberish).unwrap();
+        assert_eq!(obj.ignored, 5);
+    }
+
+    #[test]
+    fn vertex_records() {
+        let obj = parse_obj_file("v -1 1 0\nv -1.0000 0.5000 0.0000\nv 1 0 0\nv 1 1 0").unwrap();
+        assert_eq!(obj.vertices[0], Tuple4D::new_point(-1.0, 1.0, 0.0));
+        assert_eq!(obj.vertices[1], Tuple4D::new_point(-1.0, 0.5, 0.0));
+        assert_eq!(obj.vertices[2], Tuple4D::new_point(1.0, 0.0, 0.0));
+        assert_eq!(obj.vertices[3], Tuple4D::new_point(1.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn parsing_triangle_faces() {
+        let obj = parse_obj_file(&format!("{POLYGON}\nf 1 2 3\nf 1 3 4")).unwrap();
+        let v = &obj.vertices;
+        assert_eq!(obj.default_group.len(), 2);
+        assert_eq!(
+            obj.default_group[0].triangle,
+            Triangle::new(v[0], v[1], v[2])
+        );
+        assert_eq!(
+            obj.default_group[1].triangle,
+            Triangle::new(v[0], v[2], v[3])
+        );
+    }
+
+    #[test]
+    fn triangulating_polygons() {
+        let obj = parse_obj_file(&format!("{POLYGON}\nf 1 2 3 4 5")).unwrap();
+        let v = &obj.vertices;
+        assert_eq!(
+            triangles(&obj.default_group),
+            vec![
+                Triangle::new(v[0], v[1], v[2]),
+                Triangle::new(v[0], v[2], v[3]),
+                Triangle::new(v[0], v[3], v[4]),
+            ]
+        );
+    }
+
+    #[test]
+    fn triangles_in_named_groups() {
+        let obj = parse_obj_file(&format!(
+            "{POLYGON}g FirstGroup\nf 1 2 3\ng SecondGroup\nf 1 3 4"
+        ))
+        .unwrap();
+        let v = &obj.vertices;
+        assert!(obj.default_group.is_empty());
+        assert_eq!(
+            triangles(obj.group("FirstGroup").unwrap()),
+            [Triangle::new(v[0], v[1], v[2])]
+        );
+        assert_eq!(
+            triangles(obj.group("SecondGroup").unwrap()),
+            [Triangle::new(v[0], v[2], v[3])]
+        );
+        assert!(obj.group("ThirdGroup").is_none());
+    }
+
+    #[test]
+    fn converting_obj_file_to_group() {
+        let obj = parse_obj_file(&format!(
+            "{POLYGON}f 1 2 3\ng FirstGroup\nf 1 3 4\ng Second\nf 1 4 5"
+        ))
+        .unwrap();
+        let group = obj.into_group();
+        let ray = Ray::new(
+            Tuple4D::new_point(0.5, 0.6, -5.0),
+            Tuple4D::new_vector(0.0, 0.0, 1.0),
+        );
+        let xs = group.intersect(&ray);
+        assert_eq!(xs.len(), 1);
+        assert_eq!(xs[0].t, 5.0);
+    }
+
+    #[test]
+    fn vertex_normal_records() {
+        let obj = parse_obj_file("vn 0 0 1\nvn 0.707 0 -0.707\nvn 1 2 3").unwrap();
+        assert_eq!(obj.normals[0], Tuple4D::new_vector(0.0, 0.0, 1.0));
+        assert_eq!(obj.normals[1], Tuple4D::new_vector(0.707, 0.0, -0.707));
+        assert_eq!(obj.normals[2], Tuple4D::new_vector(1.0, 2.0, 3.0));
+    }
+
+    #[test]
+    fn faces_with_normals() {
+        let obj = parse_obj_file(
+            "v 0 1 0\nv -1 0 0\nv 1 0 0\n\nvn -1 0 0\nvn 1 0 0\nvn 0 1 0\nvt 0 0\n\nf 1//3 2//1 3//2\nf 1/1/3 2/1/1 3/1/2",
+        )
+        .unwrap();
+        let (v, n) = (&obj.vertices, &obj.normals);
+        let expected = Triangle::smooth(v[0], v[1], v[2], n[2], n[0], n[1]);
+        assert_eq!(triangles(&obj.default_group), vec![expected, expected]);
+    }
+
+    #[test]
+    fn negative_indices_count_from_the_end() {
+        let obj = parse_obj_file(&format!("{POLYGON}f -3 -2 -1")).unwrap();
+        let v = &obj.vertices;
+        assert_eq!(
+            triangles(&obj.default_group),
+            vec![Triangle::new(v[2], v[3], v[4])]
+        );
+    }
+
+    #[test]
+    fn invalid_faces_are_errors() {
+        assert!(parse_obj_file(&format!("{POLYGON}f 1 2 9")).is_err());
+        assert!(parse_obj_file(&format!("{POLYGON}f 1 2")).is_err());
+        assert!(parse_obj_file("v 1 x 0").is_err());
+    }
+
+    #[test]
+    fn smoothing_normals_keeps_uvs_and_materials() {
+        let mut obj = parse_obj_file(&format!(
+            "{POLYGON}vt 0 0\nusemtl red\nf 1/1 2/1 3/1\ng FirstGroup\nf 1 3 4"
+        ))
+        .unwrap();
+        obj.smooth_normals(0.5);
+        let face_normal = Tuple4D::new_vector(0.0, 0.0, -1.0);
+        assert_eq!(
+            obj.default_group[0].triangle.normals(),
+            Some([face_normal; 3])
+        );
+        assert_eq!(obj.default_group[0].material, Some(0));
+        assert!(obj.default_group[0].uvs.is_some());
+        assert!(obj.group("FirstGroup").unwrap()[0]
+            .triangle
+            .normals()
+            .is_some());
+    }
+
+    #[test]
+    fn texture_coordinates_of_faces() {
+        let obj = parse_obj_file(&format!(
+            "{POLYGON}vt 0 0\nvt 1 0\nvt 1 1\nvt 0 1\nf 1/1 2/2 3/3 4/4\nf 1 2 3"
+        ))
+        .unwrap();
+        assert_eq!(obj.texture_coords[2], (1.0, 1.0));
+        assert_eq!(
+            obj.default_group[0].uvs,
+            Some([(0.0, 0.0), (1.0, 0.0), (1.0, 1.0)])
+        );
+        assert_eq!(
+            obj.default_group[1].uvs,
+            Some([(0.0, 0.0), (1.0, 1.0), (0.0, 1.0)])
+        );
+        assert_eq!(obj.default_group[2].uvs, None);
+    }
+
+    #[test]
+    fn faces_use_the_current_material() {
+        let obj = parse_obj_file(&format!(
+            "mtllib scene.mtl\n{POLYGON}f 1 2 3\nusemtl red\nf 1 3 4\nusemtl blue\nf 1 4 5\nusemtl red\nf 1 2 3"
+        ))
+        .unwrap();
+        assert_eq!(obj.material_libraries, vec!["scene.mtl"]);
+        assert_eq!(obj.material_names, vec!["red", "blue"]);
+        let materials: Vec<_> = obj.default_group.iter().map(|t| t.material).collect();
+        assert_eq!(materials, vec![None, Some(0), Some(1), Some(0)]);
+    }
+
+    #[test]
+    fn parsing_mtl_file() {
+        let materials = parse_mtl_file(
+            "# two materials\nnewmtl red\nKd 0.8 0.1 0.1\nKs 0.5 0.5 0.5\nNs 50\nillum 2\n\n\
+             newmtl glass\nd 0.25\nNi 1.5\nmap_Kd -s 2 2 1 textures/glass.ppm",
+        )
+        .unwrap();
+        assert_eq!(materials.len(), 2);
+        let red = &materials[0];
+        assert_eq!(red.name, "red");
+        assert_eq!(red.material.color, Color::new(0.8, 0.1, 0.1));
+        assert_eq!(red.material.specular, 0.5);
+        assert_eq!(red.material.shininess, 50.0);
+        assert!(red.diffuse_map.is_none());
+        let glass = &materials[1];
+        assert_eq!(glass.material.transparency, 0.75);
+        assert_eq!(glass.material.refractive_index, 1.5);
+        assert_eq!(glass.diffuse_map.as_deref(), Some("textures/glass.ppm"));
+        assert!(parse_mtl_file("Kd 1 1 1").is_err());
+        assert!(parse_mtl_file("newmtl a\nNs shiny").is_err());
+    }
+
+    #[test]
+    fn group_uses_materials_of_triangles() {
+        let mut obj = parse_obj_file(&format!(
+            "{POLYGON}usemtl red\nf 1 2 3\nusemtl missing\nf 1 3 4"
+        ))
+        .unwrap();
+        obj.materials = parse_mtl_file("newmtl red\nKd 1 0 0").unwrap();
+        let group = obj.into_group();
+        let color_at = |x, y| {
+            let ray = Ray::new(
+                Tuple4D::new_point(x, y, -5.0),
+                Tuple4D::new_vector(0.0, 0.0, 1.0),
+            );
+            group.intersect(&ray)[0].object.material.color
+        };
+        assert_eq!(color_at(-0.5, 0.4), Color::new(1.0, 0.0, 0.0));
+        assert_eq!(color_at(0.5, 0.9), Color::new(1.0, 1.0, 1.0));
+    }
+}
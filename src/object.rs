@@ -0,0 +1,305 @@
+use std::sync::Arc;
+
+use crate::math::{Float, Matrix4, Transform, Tuple4D};
+use crate::shapes::{Cube, Group, Instance, Plane, SdfShape, Shape, Sphere, Torus, Triangle};
+use crate::{Bounds, Intersection, Material, Ray};
+
+#[derive(Debug)]
+pub struct Object {
+    pub transform: Transform,
+    // transform at the end of the exposure, the object moves from transform to this one for motion blur
+    pub transform_end: Option<Matrix4>,
+    pub material: Material,
+    pub shape: Box<dyn Shape>,
+}
+
+impl Object {
+    pub fn new(shape: Box<dyn Shape>) -> Object {
+        Object {
+            transform: Transform::default(),
+            transform_end: None,
+            material: Material::default(),
+            shape,
+        }
+    }
+
+    pub fn sphere() -> Object {
+        Object::new(Box::new(Sphere))
+    }
+
+    pub fn plane() -> Object {
+        Object::new(Box::new(Plane))
+    }
+
+    pub fn cube() -> Object {
+        Object::new(Box::new(Cube))
+    }
+
+    // see SdfShape::mandelbulb
+    pub fn mandelbulb(power: Float, iterations: usize) -> Object {
+        Object::new(Box::new(SdfShape::mandelbulb(power, iterations)))
+    }
+
+    pub fn torus(major_radius: Float, minor_radius: Float) -> Object {
+        Object::new(Box::new(Torus::new(major_radius, minor_radius)))
+    }
+
+    pub fn triangle(p1: Tuple4D, p2: Tuple4D, p3: Tuple4D) -> Object {
+        Object::new(Box::new(Triangle::new(p1, p2, p3)))
+    }
+
+    pub fn group(children: Vec<Object>) -> Object {
+        Object::new(Box::new(Group::new(children)))
+    }
+
+    // shares the geometry of prototype instead of copying it, see Instance
+    pub fn instance(prototype: Arc<Object>) -> Object {
+        Object::new(Box::new(Instance::new(prototype)))
+    }
+
+    pub fn intersect(&self, ray: &Ray) -> Vec<Intersection<'_>> {
+        let mut intersections = vec![];
+        self.intersect_into(ray, &mut intersections);
+        intersections
+    }
+
+    // intersect that adds to the intersections found so far instead of allocating a new list
+    pub fn intersect_into<'a>(&'a self, ray: &Ray, intersections: &mut Vec<Intersection<'a>>) {
+        crate::heatmap::count_intersection_test();
+        let inverse = self.inverse_transform(ray.time);
+        let local_ray = ray.transform(inverse);
+        intersections.extend(
+            self.shape
+                .local_intersect(&local_ray)
+                .into_iter()
+                .map(|t| Intersection::new(t, self)),
+        );
+        // t is the same for the local ray, only the transforms of the enclosing groups have to be recorded
+        let children_start = intersections.len();
+        self.shape
+            .local_intersect_children(&local_ray, intersections);
+        for intersection in &mut intersections[children_start..] {
+            *intersection = intersection.inside_group(inverse);
+        }
+    }
+
+    // any intersection that blocks the ray before distance, see World::is_occluded
+    pub fn occluder(&self, ray: &Ray, distance: Float) -> Option<Intersection<'_>> {
+        crate::heatmap::count_intersection_test();
+        let inverse = self.inverse_transform(ray.time);
+        let local_ray = ray.transform(inverse);
+        let blocking = self
+            .shape
+            .local_intersect(&local_ray)
+            .into_iter()
+            .map(|t| Intersection::new(t, self))
+            .find(|intersection| intersection.blocks(distance));
+        blocking.or_else(|| {
+            self.shape
+                .local_occluding_child(&local_ray, distance)
+                .map(|intersection| intersection.inside_group(inverse))
+        })
+    }
+
+    // bounds in the space of the parent, covering the whole motion of moving objects
+    pub fn bounds(&self) -> Bounds {
+        let local = self.shape.bounds();
+        let bounds = local.transform(self.transform.matrix());
+        match self.transform_end {
+            Some(end) => bounds.union(&local.transform(end)),
+            None => bounds,
+        }
+    }
+
+    pub fn transform_at(&self, time: Float) -> Matrix4 {
+        match self.transform_end {
+            Some(end) => self.transform.matrix().interpolate(end, time),
+            None => self.transform.matrix(),
+        }
+    }
+
+    pub fn world_to_object(&self, world_point: Tuple4D) -> Tuple4D {
+        self.world_to_object_at(world_point, 0.0)
+    }
+
+    pub fn world_to_object_at(&self, world_point: Tuple4D, time: Float) -> Tuple4D {
+        self.inverse_transform(time) * world_point
+    }
+
+    pub fn normal_at(&self, world_point: Tuple4D) -> Tuple4D {
+        self.normal_at_time(world_point, 0.0)
+    }
+
+    pub fn normal_at_time(&self, world_point: Tuple4D, time: Float) -> Tuple4D {
+        self.normal_with_inverse(world_point, self.inverse_transform(time), &self.material)
+    }
+
+    // inverse transforms from world to object space, including the transforms of enclosing groups
+    // the normal map is taken from material, which differs from the own one inside of instances
+    pub(crate) fn normal_with_inverse(
+        &self,
+        world_point: Tuple4D,
+        inverse: Matrix4,
+        material: &Material,
+    ) -> Tuple4D {
+        let local_point = inverse * world_point;
+        let mut local_normal = self.shape.local_normal_at(local_point);
+        if let Some(normal_map) = &material.normal_map {
+            local_normal = normal_map.perturb(local_point, local_normal);
+        }
+        let mut world_normal = inverse.transpose() * local_normal;
+        world_normal.w = 0.0;
+        world_normal.normalize()
+    }
+
+    // only moving objects have to invert their transform again for every ray
+    pub fn inverse_transform(&self, time: Float) -> Matrix4 {
+        match self.transform_end {
+            Some(_) => self.transform_at(time).inverse(),
+            None => self.transform.inverse(),
+        }
+        .expect("object transform has to be invertible")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::consts::{FRAC_1_SQRT_2, PI};
+
+    use crate::patterns::GradientPattern;
+    use crate::{Color, NormalMap};
+
+    use super::*;
+
+    #[test]
+    fn default_transformation_and_material() {
+        let object = Object::sphere();
+        assert_eq!(object.transform.matrix(), Matrix4::eye());
+        assert_eq!(object.material.color, Material::default().color);
+    }
+
+    #[test]
+    fn intersect_sets_object() {
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 0.0, -5.0),
+            Tuple4D::new_vector(0.0, 0.0, 1.0),
+        );
+        let sphere = Object::sphere();
+        let xs = sphere.intersect(&ray);
+        assert_eq!(xs.len(), 2);
+        assert!(std::ptr::eq(xs[0].object, &sphere));
+        assert!(std::ptr::eq(xs[1].object, &sphere));
+    }
+
+    #[test]
+    fn intersect_into_appends_to_earlier_intersections() {
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 0.0, -5.0),
+            Tuple4D::new_vector(0.0, 0.0, 1.0),
+        );
+        let sphere = Object::sphere();
+        let mut inner = Object::sphere();
+        inner.transform = Matrix4::scaling(0.5, 0.5, 0.5).into();
+        let mut group = Object::group(vec![inner]);
+        group.transform = Matrix4::translation(0.0, 0.0, 1.0).into();
+        let mut xs = sphere.intersect(&ray);
+        group.intersect_into(&ray, &mut xs);
+        let ts: Vec<_> = xs.iter().map(|x| x.t).collect();
+        assert_eq!(ts, [4.0, 6.0, 5.5, 6.5]);
+        assert!(std::ptr::eq(xs[0].object, &sphere));
+        assert_eq!(
+            xs[2].world_to_object(0.0),
+            group.intersect(&ray)[0].world_to_object(0.0)
+        );
+    }
+
+    #[test]
+    fn intersecting_scaled_sphere() {
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 0.0, -5.0),
+            Tuple4D::new_vector(0.0, 0.0, 1.0),
+        );
+        let mut sphere = Object::sphere();
+        sphere.transform = Matrix4::scaling(2.0, 2.0, 2.0).into();
+        let xs = sphere.intersect(&ray);
+        assert_eq!(xs.len(), 2);
+        assert_eq!(xs[0].t, 3.0);
+        assert_eq!(xs[1].t, 7.0);
+    }
+
+    #[test]
+    fn intersecting_translated_sphere() {
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 0.0, -5.0),
+            Tuple4D::new_vector(0.0, 0.0, 1.0),
+        );
+        let mut sphere = Object::sphere();
+        sphere.transform = Matrix4::translation(5.0, 0.0, 0.0).into();
+        assert!(sphere.intersect(&ray).is_empty());
+    }
+
+    #[test]
+    fn normal_is_normalized() {
+        let sphere = Object::sphere();
+        let value = Float::sqrt(3.0) / 3.0;
+        let normal = sphere.normal_at(Tuple4D::new_point(value, value, value));
+        assert_eq!(normal, normal.normalize());
+    }
+
+    #[test]
+    fn normal_on_translated_sphere() {
+        let mut sphere = Object::sphere();
+        sphere.transform = Matrix4::translation(0.0, 1.0, 0.0).into();
+        let normal = sphere.normal_at(Tuple4D::new_point(0.0, 1.0 + FRAC_1_SQRT_2, -FRAC_1_SQRT_2));
+        assert_eq!(
+            normal,
+            Tuple4D::new_vector(0.0, FRAC_1_SQRT_2, -FRAC_1_SQRT_2)
+        );
+    }
+
+    #[test]
+    fn normal_on_transformed_sphere() {
+        let mut sphere = Object::sphere();
+        sphere.transform = (Matrix4::scaling(1.0, 0.5, 1.0) * Matrix4::rotation_z(PI / 5.0)).into();
+        let value = Float::sqrt(2.0) / 2.0;
+        let normal = sphere.normal_at(Tuple4D::new_point(0.0, value, -value));
+        assert_eq!(normal, Tuple4D::new_vector(0.0, 0.97014, -0.24254));
+    }
+
+    #[test]
+    fn normal_map_is_applied_before_world_conversion() {
+        let mut plane = Object::plane();
+        plane.transform = Matrix4::rotation_z(PI / 2.0).into();
+        let mut height = GradientPattern::new(Color::new(0.0, 0.0, 0.0), Color::new(1.0, 1.0, 1.0));
+        height.transform = Matrix4::scaling(10.0, 1.0, 1.0).into();
+        plane.material.normal_map = Some(NormalMap::bump(height, 10.0));
+        // object space point (2, 0, 0) with tilted normal (-1, 1, 0), rotated around z
+        let normal = plane.normal_at(Tuple4D::new_point(0.0, 2.0, 0.0));
+        assert_eq!(
+            normal,
+            Tuple4D::new_vector(-FRAC_1_SQRT_2, -FRAC_1_SQRT_2, 0.0)
+        );
+    }
+
+    #[test]
+    fn moving_sphere_is_hit_where_it_is_at_ray_time() {
+        let mut sphere = Object::sphere();
+        sphere.transform_end = Some(Matrix4::translation(4.0, 0.0, 0.0));
+        let ray = Ray::new(
+            Tuple4D::new_point(2.0, 0.0, -5.0),
+            Tuple4D::new_vector(0.0, 0.0, 1.0),
+        );
+        assert!(sphere.intersect(&ray).is_empty());
+        let xs = sphere.intersect(&ray.with_time(0.5));
+        assert_eq!(xs.len(), 2);
+        assert_eq!(xs[0].t, 4.0);
+    }
+
+    #[test]
+    fn normal_of_moving_sphere() {
+        let mut sphere = Object::sphere();
+        sphere.transform_end = Some(Matrix4::translation(0.0, 2.0, 0.0));
+        let normal = sphere.normal_at_time(Tuple4D::new_point(0.0, 0.0, 0.0), 0.5);
+        assert_eq!(normal, Tuple4D::new_vector(0.0, -1.0, 0.0));
+    }
+}
@@ -0,0 +1,293 @@
+use std::any::Any;
+
+use wide::{CmpEq, CmpGe, CmpLe, CmpLt};
+
+use crate::math::{Float, Matrix4, FLOAT_EQ_EPS};
+use crate::shapes::{Group, Shape, Sphere};
+use crate::{Bounds, Intersection, IntersectionBuffer, Object, Ray, World};
+
+pub const PACKET_SIZE: usize = 4;
+
+#[cfg(not(feature = "f32"))]
+type Lanes = wide::f64x4;
+#[cfg(feature = "f32")]
+type Lanes = wide::f32x4;
+
+// up to four rays with their coordinates side by side, so that one instruction works on all of
+// them. Packets with fewer rays repeat the last one, only the first len lanes are used
+#[derive(Debug, Clone, Copy)]
+pub struct RayPacket {
+    pub rays: [Ray; PACKET_SIZE],
+    pub len: usize,
+    origin: [Lanes; 3],
+    direction: [Lanes; 3],
+}
+
+impl RayPacket {
+    pub fn new(rays: &[Ray]) -> RayPacket {
+        assert!(
+            (1..=PACKET_SIZE).contains(&rays.len()),
+            "a packet holds 1 to {PACKET_SIZE} rays"
+        );
+        let padded: [Ray; PACKET_SIZE] = std::array::from_fn(|lane| rays[lane.min(rays.len() - 1)]);
+        let lanes = |value: fn(&Ray) -> Float| Lanes::new(padded.map(|ray| value(&ray)));
+        RayPacket {
+            rays: padded,
+            len: rays.len(),
+            origin: [
+                lanes(|ray| ray.origin.x),
+                lanes(|ray| ray.origin.y),
+                lanes(|ray| ray.origin.z),
+            ],
+            direction: [
+                lanes(|ray| ray.direction.x),
+                lanes(|ray| ray.direction.y),
+                lanes(|ray| ray.direction.z),
+            ],
+        }
+    }
+
+    fn transform(&self, matrix: Matrix4) -> RayPacket {
+        let row = |i: usize, [x, y, z]: [Lanes; 3]| {
+            x * matrix[[i, 0]] + y * matrix[[i, 1]] + z * matrix[[i, 2]]
+        };
+        RayPacket {
+            // for the shapes that fall back to single rays
+            rays: self.rays.map(|ray| ray.transform(matrix)),
+            len: self.len,
+            origin: std::array::from_fn(|i| row(i, self.origin) + matrix[[i, 3]]),
+            direction: std::array::from_fn(|i| row(i, self.direction)),
+        }
+    }
+
+    // the rays share the time, so they see moving objects in the same place
+    fn same_time(&self) -> bool {
+        self.rays.iter().all(|ray| ray.time == self.rays[0].time)
+    }
+}
+
+impl Bounds {
+    // slab test of Bounds::intersects for all rays of the packet at once
+    pub fn intersects_packet(&self, packet: &RayPacket) -> [bool; PACKET_SIZE] {
+        if self.is_empty() {
+            return [false; PACKET_SIZE];
+        }
+        let mut tmin = Lanes::splat(Float::NEG_INFINITY);
+        let mut tmax = Lanes::splat(Float::INFINITY);
+        let mut hit = Lanes::splat(0.0).cmp_eq(0.0);
+        let axes = [
+            (self.min.x, self.max.x),
+            (self.min.y, self.max.y),
+            (self.min.z, self.max.z),
+        ];
+        for (axis, (min, max)) in axes.into_iter().enumerate() {
+            let (origin, direction) = (packet.origin[axis], packet.direction[axis]);
+            // rays parallel to the slab have to start inside of it
+            let parallel = direction.abs().cmp_lt(FLOAT_EQ_EPS);
+            let inside = origin.cmp_ge(min) & origin.cmp_le(max);
+            hit &= !parallel | inside;
+            let t1 = (Lanes::splat(min) - origin) / direction;
+            let t2 = (Lanes::splat(max) - origin) / direction;
+            tmin = parallel.blend(tmin, tmin.max(t1.min(t2)));
+            tmax = parallel.blend(tmax, tmax.min(t1.max(t2)));
+        }
+        let hit = (hit & tmin.cmp_le(tmax)).to_array();
+        hit.map(|lane| lane != 0.0)
+    }
+}
+
+// Sphere::local_intersect for all rays of the packet, in the same order of operations so the
+// results are the same
+fn intersect_unit_spheres(packet: &RayPacket) -> [Option<(Float, Float)>; PACKET_SIZE] {
+    let [ox, oy, oz] = packet.origin;
+    let [dx, dy, dz] = packet.direction;
+    let a = dx * dx + dy * dy + dz * dz;
+    let b = (dx * ox + dy * oy + dz * oz) * 2.0;
+    let c = ox * ox + oy * oy + oz * oz - 1.0;
+    let discriminant = b * b - a * 4.0 * c;
+    let miss = discriminant.cmp_lt(0.0).to_array();
+    let root = discriminant.max(Lanes::splat(0.0)).sqrt();
+    // the stable formula of solve_quadratic
+    let sign = b.cmp_lt(0.0).blend(Lanes::splat(-1.0), Lanes::splat(1.0));
+    let q = (b + sign * root) * -0.5;
+    let tangent = q.cmp_eq(0.0);
+    let near = tangent.blend(Lanes::splat(0.0), q / a);
+    let far = tangent.blend(Lanes::splat(0.0), c / q);
+    let t1 = near.min(far).to_array();
+    let t2 = near.max(far).to_array();
+    std::array::from_fn(|lane| (miss[lane] == 0.0).then_some((t1[lane], t2[lane])))
+}
+
+impl Object {
+    // Object::intersect for every ray of the packet. Spheres and the bounds of groups are tested
+    // on all rays at once, everything else falls back to one ray at a time. The tests are not
+    // counted for the cost heatmaps
+    pub fn intersect_packet(&self, packet: &RayPacket) -> [Vec<Intersection<'_>>; PACKET_SIZE] {
+        let shape: &dyn Any = self.shape.as_ref();
+        let is_sphere = shape.is::<Sphere>();
+        let group = shape.downcast_ref::<Group>();
+        if !packet.same_time() || !(is_sphere || group.is_some()) {
+            return std::array::from_fn(|lane| {
+                if lane < packet.len {
+                    self.intersect(&packet.rays[lane])
+                } else {
+                    vec![]
+                }
+            });
+        }
+        let inverse = self.inverse_transform(packet.rays[0].time);
+        let local = packet.transform(inverse);
+        if is_sphere {
+            let hits = intersect_unit_spheres(&local);
+            return std::array::from_fn(|lane| match hits[lane] {
+                Some((t1, t2)) if lane < packet.len => {
+                    vec![Intersection::new(t1, self), Intersection::new(t2, self)]
+                }
+                _ => vec![],
+            });
+        }
+        let group = group.expect("only spheres and groups are intersected as packets");
+        let active = group.bounds().intersects_packet(&local);
+        let mut intersections: [Vec<Intersection>; PACKET_SIZE] = Default::default();
+        if !active[..packet.len].contains(&true) {
+            return intersections;
+        }
+        for child in group.children() {
+            for (lane, child_intersections) in
+                child.intersect_packet(&local).into_iter().enumerate()
+            {
+                if active[lane] && lane < packet.len {
+                    intersections[lane].extend(
+                        child_intersections
+                            .into_iter()
+                            .map(|intersection| intersection.inside_group(inverse)),
+                    );
+                }
+            }
+        }
+        intersections
+    }
+}
+
+impl World {
+    // World::intersect_world for every ray of the packet
+    pub fn intersect_world_packet(
+        &self,
+        packet: &RayPacket,
+    ) -> [Vec<Intersection<'_>>; PACKET_SIZE] {
+        let mut intersections: [Vec<Intersection>; PACKET_SIZE] = Default::default();
+        for object in &self.objects {
+            for (lane, object_intersections) in
+                object.intersect_packet(packet).into_iter().enumerate()
+            {
+                intersections[lane].extend(object_intersections);
+            }
+        }
+        for lane_intersections in &mut intersections {
+            lane_intersections.sort_by(|a, b| a.t.total_cmp(&b.t));
+        }
+        intersections
+    }
+
+    // World::color_at for up to PACKET_SIZE rays, the first hit of all of them is found together
+    pub fn color_at_packet(&self, rays: &[Ray]) -> Vec<crate::Color> {
+        self.color_at_packet_with(rays, &mut IntersectionBuffer::new())
+    }
+
+    // color_at_packet with the lists for reflected and shadow rays taken from the buffer
+    pub fn color_at_packet_with<'a>(
+        &'a self,
+        rays: &[Ray],
+        buffer: &mut IntersectionBuffer<'a>,
+    ) -> Vec<crate::Color> {
+        let packet = RayPacket::new(rays);
+        self.intersect_world_packet(&packet)
+            .into_iter()
+            .zip(rays)
+            .map(|(intersections, ray)| {
+                self.trace_intersections(ray, &intersections, self.max_depth, buffer)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::Tuple4D;
+
+    fn rays() -> Vec<Ray> {
+        [(0.0, 0.0), (0.5, 0.2), (0.99, 0.0), (3.0, 0.0)]
+            .into_iter()
+            .map(|(x, y)| {
+                Ray::new(
+                    Tuple4D::new_point(x, y, -5.0),
+                    Tuple4D::new_vector(0.01, -0.02, 1.0).normalize(),
+                )
+            })
+            .collect()
+    }
+
+    fn ts(intersections: &[Intersection]) -> Vec<Float> {
+        intersections
+            .iter()
+            .map(|intersection| intersection.t)
+            .collect()
+    }
+
+    #[test]
+    fn packets_of_spheres_match_single_rays() {
+        let mut sphere = Object::sphere();
+        sphere.transform =
+            (Matrix4::translation(0.1, 0.0, 1.0) * Matrix4::scaling(1.0, 1.5, 1.0)).into();
+        let rays = rays();
+        let packet = sphere.intersect_packet(&RayPacket::new(&rays));
+        for (lane, ray) in rays.iter().enumerate() {
+            assert_eq!(ts(&packet[lane]), ts(&sphere.intersect(ray)));
+        }
+        assert!(packet[3].is_empty());
+    }
+
+    #[test]
+    fn bounds_of_packets() {
+        let bounds = Bounds::new(
+            Tuple4D::new_point(-1.0, -1.0, -1.0),
+            Tuple4D::new_point(1.0, 1.0, 1.0),
+        );
+        let mut rays = rays();
+        // parallel to the x and y slabs, outside of the x slab
+        rays[2] = Ray::new(
+            Tuple4D::new_point(2.0, 0.0, -5.0),
+            Tuple4D::new_vector(0.0, 0.0, 1.0),
+        );
+        let packet = RayPacket::new(&rays);
+        let expected: Vec<bool> = rays.iter().map(|ray| bounds.intersects(ray)).collect();
+        assert_eq!(bounds.intersects_packet(&packet).to_vec(), expected);
+        assert_eq!(expected, [true, true, false, false]);
+        assert_eq!(Bounds::empty().intersects_packet(&packet), [false; 4]);
+    }
+
+    #[test]
+    fn packets_of_the_world_match_single_rays() {
+        let mut world = World::default_world();
+        let mut children = std::mem::take(&mut world.objects);
+        // falls back to single rays inside of the group
+        let mut cube = Object::cube();
+        cube.transform =
+            (Matrix4::translation(0.5, 0.0, -1.5) * Matrix4::scaling(0.3, 0.3, 0.3)).into();
+        children.push(cube);
+        let mut group = Object::group(children);
+        group.transform = Matrix4::rotation_y(0.3).into();
+        world.objects.push(group);
+        world.objects.push(Object::plane());
+        let rays = &rays()[..3];
+        let packet = world.intersect_world_packet(&RayPacket::new(rays));
+        for (lane, ray) in rays.iter().enumerate() {
+            assert_eq!(ts(&packet[lane]), ts(&world.intersect_world(ray)));
+        }
+        assert!(packet[3].is_empty());
+        let colors = world.color_at_packet(rays);
+        let expected: Vec<_> = rays.iter().map(|ray| world.color_at(ray)).collect();
+        assert_eq!(colors, expected);
+    }
+}
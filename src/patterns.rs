@@ -0,0 +1,163 @@
+use std::fmt::Debug;
+
+use crate::math::{Float, Matrix4, Transform, Tuple4D};
+use crate::{Color, Object};
+
+mod blended;
+pub use blended::BlendedPattern;
+mod checker;
+pub use checker::CheckerPattern;
+mod gradient;
+pub use gradient::GradientPattern;
+mod perturbed;
+pub use perturbed::PerturbedPattern;
+mod ring;
+pub use ring::RingPattern;
+mod stripe;
+pub use stripe::StripePattern;
+mod texture_map;
+pub use texture_map::{CubeMapPattern, TextureMapPattern, TriangleTexturePattern};
+mod uv;
+pub use uv::{
+    cube_map, cylindrical_map, face_from_point, planar_map, spherical_map, CubeFace, TextureFilter,
+    UvAlignCheck, UvCheckers, UvImage, UvMap, UvPattern,
+};
+mod vertex_color;
+pub use vertex_color::VertexColorPattern;
+
+pub trait Pattern: Debug + Send + Sync {
+    fn transform(&self) -> Transform;
+    // point is given in pattern space
+    fn pattern_at(&self, point: Tuple4D) -> Color;
+
+    // point is given in the space of the enclosing pattern (or object space for the outermost one)
+    fn pattern_at_nested(&self, point: Tuple4D) -> Color {
+        let pattern_point = self
+            .transform()
+            .inverse()
+            .expect("pattern transform has to be invertible")
+            * point;
+        self.pattern_at(pattern_point)
+    }
+
+    fn pattern_at_shape(&self, object: &Object, world_point: Tuple4D) -> Color {
+        self.pattern_at_nested(object.world_to_object(world_point))
+    }
+
+    // pattern averaged over a region that is footprint wide in pattern space, patterns that can
+    // alias (like image textures) override this, the others ignore the footprint
+    fn pattern_at_filtered(&self, point: Tuple4D, _footprint: Float) -> Color {
+        self.pattern_at(point)
+    }
+
+    fn pattern_at_nested_filtered(&self, point: Tuple4D, footprint: Float) -> Color {
+        let inverse = self
+            .transform()
+            .inverse()
+            .expect("pattern transform has to be invertible");
+        self.pattern_at_filtered(inverse * point, scale_footprint(inverse, footprint))
+    }
+}
+
+// width of a footprint after the transformation, the largest stretch along the axes is used
+pub(crate) fn scale_footprint(transform: Matrix4, footprint: Float) -> Float {
+    if footprint == 0.0 {
+        return 0.0;
+    }
+    let stretch = [
+        Tuple4D::new_vector(1.0, 0.0, 0.0),
+        Tuple4D::new_vector(0.0, 1.0, 0.0),
+        Tuple4D::new_vector(0.0, 0.0, 1.0),
+    ]
+    .into_iter()
+    .map(|axis| (transform * axis).magnitude())
+    .fold(0.0, Float::max);
+    footprint * stretch
+}
+
+// a plain color is a pattern that is the same everywhere
+impl Pattern for Color {
+    fn transform(&self) -> Transform {
+        Transform::default()
+    }
+
+    fn pattern_at(&self, _point: Tuple4D) -> Color {
+        *self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // returns the pattern space point as color, to check the transformations
+    #[derive(Debug)]
+    struct TestPattern {
+        transform: Transform,
+    }
+
+    impl Pattern for TestPattern {
+        fn transform(&self) -> Transform {
+            self.transform
+        }
+
+        fn pattern_at(&self, point: Tuple4D) -> Color {
+            Color::new(point.x, point.y, point.z)
+        }
+    }
+
+    #[test]
+    fn color_is_solid_pattern() {
+        let color = Color::new(0.2, 0.4, 0.6);
+        assert_eq!(color.pattern_at(Tuple4D::new_point(0.0, 0.0, 0.0)), color);
+        assert_eq!(color.pattern_at(Tuple4D::new_point(-3.5, 2.0, 7.1)), color);
+    }
+
+    #[test]
+    fn nested_pattern_applies_its_own_transformation() {
+        let white = Color::new(1.0, 1.0, 1.0);
+        let black = Color::new(0.0, 0.0, 0.0);
+        let mut inner = StripePattern::new(white, black);
+        inner.transform = Matrix4::scaling(0.5, 0.5, 0.5).into();
+        let outer = CheckerPattern::new(inner, Color::new(1.0, 0.0, 0.0));
+        // inside the first checker cell the scaled stripes alternate every 0.5 units
+        assert_eq!(outer.pattern_at(Tuple4D::new_point(0.25, 0.0, 0.0)), white);
+        assert_eq!(outer.pattern_at(Tuple4D::new_point(0.75, 0.0, 0.0)), black);
+        assert_eq!(
+            outer.pattern_at(Tuple4D::new_point(1.25, 0.0, 0.0)),
+            Color::new(1.0, 0.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn pattern_with_object_transformation() {
+        let mut object = Object::sphere();
+        object.transform = Matrix4::scaling(2.0, 2.0, 2.0).into();
+        let pattern = TestPattern {
+            transform: Transform::default(),
+        };
+        let color = pattern.pattern_at_shape(&object, Tuple4D::new_point(2.0, 3.0, 4.0));
+        assert_eq!(color, Color::new(1.0, 1.5, 2.0));
+    }
+
+    #[test]
+    fn pattern_with_pattern_transformation() {
+        let object = Object::sphere();
+        let pattern = TestPattern {
+            transform: Matrix4::scaling(2.0, 2.0, 2.0).into(),
+        };
+        let color = pattern.pattern_at_shape(&object, Tuple4D::new_point(2.0, 3.0, 4.0));
+        assert_eq!(color, Color::new(1.0, 1.5, 2.0));
+    }
+
+    #[test]
+    fn pattern_with_object_and_pattern_transformation() {
+        let mut object = Object::sphere();
+        object.transform = Matrix4::scaling(2.0, 2.0, 2.0).into();
+        let pattern = TestPattern {
+            transform: Matrix4::translation(0.5, 1.0, 1.5).into(),
+        };
+        let color = pattern.pattern_at_shape(&object, Tuple4D::new_point(2.5, 3.0, 3.5));
+        assert_eq!(color, Color::new(0.75, 0.5, 0.25));
+    }
+}
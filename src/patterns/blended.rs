@@ -0,0 +1,71 @@
+use std::sync::Arc;
+
+use crate::math::{Transform, Tuple4D};
+use crate::patterns::Pattern;
+use crate::Color;
+
+// averages the colors of two patterns at every point
+#[derive(Debug, Clone)]
+pub struct BlendedPattern {
+    pub a: Arc<dyn Pattern>,
+    pub b: Arc<dyn Pattern>,
+    pub transform: Transform,
+}
+
+impl BlendedPattern {
+    pub fn new(a: impl Pattern + 'static, b: impl Pattern + 'static) -> BlendedPattern {
+        BlendedPattern {
+            a: Arc::new(a),
+            b: Arc::new(b),
+            transform: Transform::default(),
+        }
+    }
+}
+
+impl Pattern for BlendedPattern {
+    fn transform(&self) -> Transform {
+        self.transform
+    }
+
+    fn pattern_at(&self, point: Tuple4D) -> Color {
+        (self.a.pattern_at_nested(point) + self.b.pattern_at_nested(point)) / 2.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::consts::PI;
+    use crate::math::Matrix4;
+
+    use crate::patterns::StripePattern;
+
+    use super::*;
+
+    #[test]
+    fn blending_solid_colors_averages_them() {
+        let pattern = BlendedPattern::new(Color::new(1.0, 0.0, 0.2), Color::new(0.0, 1.0, 0.4));
+        assert_eq!(
+            pattern.pattern_at(Tuple4D::new_point(0.3, 1.0, -2.0)),
+            Color::new(0.5, 0.5, 0.3)
+        );
+    }
+
+    #[test]
+    fn blending_crossed_stripes() {
+        let white = Color::new(1.0, 1.0, 1.0);
+        let black = Color::new(0.0, 0.0, 0.0);
+        let along_x = StripePattern::new(white, black);
+        let mut along_z = StripePattern::new(white, black);
+        along_z.transform = Matrix4::rotation_y(PI / 2.0).into();
+        let pattern = BlendedPattern::new(along_x, along_z);
+        assert_eq!(
+            pattern.pattern_at(Tuple4D::new_point(0.5, 0.0, -0.5)),
+            white
+        );
+        assert_eq!(
+            pattern.pattern_at(Tuple4D::new_point(1.5, 0.0, -0.5)),
+            Color::new(0.5, 0.5, 0.5)
+        );
+        assert_eq!(pattern.pattern_at(Tuple4D::new_point(1.5, 0.0, 0.5)), black);
+    }
+}
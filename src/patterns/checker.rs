@@ -0,0 +1,91 @@
+use std::sync::Arc;
+
+use crate::math::{Transform, Tuple4D};
+use crate::patterns::Pattern;
+use crate::Color;
+
+// 3D checkerboard of unit cubes
+#[derive(Debug, Clone)]
+pub struct CheckerPattern {
+    pub a: Arc<dyn Pattern>,
+    pub b: Arc<dyn Pattern>,
+    pub transform: Transform,
+}
+
+impl CheckerPattern {
+    pub fn new(a: impl Pattern + 'static, b: impl Pattern + 'static) -> CheckerPattern {
+        CheckerPattern {
+            a: Arc::new(a),
+            b: Arc::new(b),
+            transform: Transform::default(),
+        }
+    }
+}
+
+impl Pattern for CheckerPattern {
+    fn transform(&self) -> Transform {
+        self.transform
+    }
+
+    fn pattern_at(&self, point: Tuple4D) -> Color {
+        let sum = point.x.floor() + point.y.floor() + point.z.floor();
+        if sum.rem_euclid(2.0) == 0.0 {
+            self.a.pattern_at_nested(point)
+        } else {
+            self.b.pattern_at_nested(point)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup() -> (CheckerPattern, Color, Color) {
+        let white = Color::new(1.0, 1.0, 1.0);
+        let black = Color::new(0.0, 0.0, 0.0);
+        (CheckerPattern::new(white, black), white, black)
+    }
+
+    #[test]
+    fn checkers_repeat_in_x() {
+        let (pattern, white, black) = setup();
+        assert_eq!(pattern.pattern_at(Tuple4D::new_point(0.0, 0.0, 0.0)), white);
+        assert_eq!(
+            pattern.pattern_at(Tuple4D::new_point(0.99, 0.0, 0.0)),
+            white
+        );
+        assert_eq!(
+            pattern.pattern_at(Tuple4D::new_point(1.01, 0.0, 0.0)),
+            black
+        );
+    }
+
+    #[test]
+    fn checkers_repeat_in_y() {
+        let (pattern, white, black) = setup();
+        assert_eq!(pattern.pattern_at(Tuple4D::new_point(0.0, 0.0, 0.0)), white);
+        assert_eq!(
+            pattern.pattern_at(Tuple4D::new_point(0.0, 0.99, 0.0)),
+            white
+        );
+        assert_eq!(
+            pattern.pattern_at(Tuple4D::new_point(0.0, 1.01, 0.0)),
+            black
+        );
+    }
+
+    #[test]
+    fn checkers_repeat_in_z() {
+        let (pattern, white, black) = setup();
+        assert_eq!(pattern.pattern_at(Tuple4D::new_point(0.0, 0.0, 0.0)), white);
+        assert_eq!(
+            pattern.pattern_at(Tuple4D::new_point(0.0, 0.0, 0.99)),
+            white
+        );
+        assert_eq!(
+            pattern.pattern_at(Tuple4D::new_point(0.0, 0.0, 1.01)),
+            black
+        );
+    }
+}
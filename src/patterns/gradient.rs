@@ -0,0 +1,62 @@
+use std::sync::Arc;
+
+use crate::math::{Transform, Tuple4D};
+use crate::patterns::Pattern;
+use crate::Color;
+
+// linear blend from a to b along the x axis, repeating every unit
+#[derive(Debug, Clone)]
+pub struct GradientPattern {
+    pub a: Arc<dyn Pattern>,
+    pub b: Arc<dyn Pattern>,
+    pub transform: Transform,
+}
+
+impl GradientPattern {
+    pub fn new(a: impl Pattern + 'static, b: impl Pattern + 'static) -> GradientPattern {
+        GradientPattern {
+            a: Arc::new(a),
+            b: Arc::new(b),
+            transform: Transform::default(),
+        }
+    }
+}
+
+impl Pattern for GradientPattern {
+    fn transform(&self) -> Transform {
+        self.transform
+    }
+
+    fn pattern_at(&self, point: Tuple4D) -> Color {
+        let a = self.a.pattern_at_nested(point);
+        let b = self.b.pattern_at_nested(point);
+        let fraction = point.x - point.x.floor();
+        a + (b - a) * fraction
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gradient_linearly_interpolates_between_colors() {
+        let pattern = GradientPattern::new(Color::new(1.0, 1.0, 1.0), Color::new(0.0, 0.0, 0.0));
+        assert_eq!(
+            pattern.pattern_at(Tuple4D::new_point(0.0, 0.0, 0.0)),
+            Color::new(1.0, 1.0, 1.0)
+        );
+        assert_eq!(
+            pattern.pattern_at(Tuple4D::new_point(0.25, 0.0, 0.0)),
+            Color::new(0.75, 0.75, 0.75)
+        );
+        assert_eq!(
+            pattern.pattern_at(Tuple4D::new_point(0.5, 0.0, 0.0)),
+            Color::new(0.5, 0.5, 0.5)
+        );
+        assert_eq!(
+            pattern.pattern_at(Tuple4D::new_point(0.75, 0.0, 0.0)),
+            Color::new(0.25, 0.25, 0.25)
+        );
+    }
+}
@@ -0,0 +1,68 @@
+use std::sync::Arc;
+
+use crate::math::{perlin_noise, Float, Transform, Tuple4D};
+use crate::patterns::Pattern;
+use crate::Color;
+
+// jitters the lookup point of another pattern with perlin noise
+#[derive(Debug, Clone)]
+pub struct PerturbedPattern {
+    pub pattern: Arc<dyn Pattern>,
+    // maximum displacement of the lookup point
+    pub scale: Float,
+    pub transform: Transform,
+}
+
+impl PerturbedPattern {
+    pub fn new(pattern: impl Pattern + 'static, scale: Float) -> PerturbedPattern {
+        PerturbedPattern {
+            pattern: Arc::new(pattern),
+            scale,
+            transform: Transform::default(),
+        }
+    }
+}
+
+impl Pattern for PerturbedPattern {
+    fn transform(&self) -> Transform {
+        self.transform
+    }
+
+    fn pattern_at(&self, point: Tuple4D) -> Color {
+        // sample the noise at offset positions so the three axes are uncorrelated
+        let dx = perlin_noise(point.x, point.y, point.z);
+        let dy = perlin_noise(point.x + 31.4, point.y + 15.9, point.z + 26.5);
+        let dz = perlin_noise(point.x + 53.5, point.y + 89.7, point.z + 93.2);
+        let jittered = point + Tuple4D::new_vector(dx, dy, dz) * self.scale;
+        self.pattern.pattern_at_nested(jittered)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::patterns::StripePattern;
+
+    use super::*;
+
+    #[test]
+    fn zero_scale_does_not_perturb() {
+        let white = Color::new(1.0, 1.0, 1.0);
+        let black = Color::new(0.0, 0.0, 0.0);
+        let pattern = PerturbedPattern::new(StripePattern::new(white, black), 0.0);
+        assert_eq!(pattern.pattern_at(Tuple4D::new_point(0.9, 0.3, 0.2)), white);
+        assert_eq!(pattern.pattern_at(Tuple4D::new_point(1.1, 0.3, 0.2)), black);
+    }
+
+    #[test]
+    fn perturbation_moves_stripe_boundaries() {
+        let white = Color::new(1.0, 1.0, 1.0);
+        let black = Color::new(0.0, 0.0, 0.0);
+        let plain = StripePattern::new(white, black);
+        let perturbed = PerturbedPattern::new(plain.clone(), 0.8);
+        let differs = (0..100).any(|i| {
+            let point = Tuple4D::new_point(i as Float * 0.13, i as Float * 0.07, i as Float * 0.05);
+            plain.pattern_at(point) != perturbed.pattern_at(point)
+        });
+        assert!(differs);
+    }
+}
@@ -0,0 +1,58 @@
+use std::sync::Arc;
+
+use crate::math::{Transform, Tuple4D};
+use crate::patterns::Pattern;
+use crate::Color;
+
+// concentric rings around the y axis
+#[derive(Debug, Clone)]
+pub struct RingPattern {
+    pub a: Arc<dyn Pattern>,
+    pub b: Arc<dyn Pattern>,
+    pub transform: Transform,
+}
+
+impl RingPattern {
+    pub fn new(a: impl Pattern + 'static, b: impl Pattern + 'static) -> RingPattern {
+        RingPattern {
+            a: Arc::new(a),
+            b: Arc::new(b),
+            transform: Transform::default(),
+        }
+    }
+}
+
+impl Pattern for RingPattern {
+    fn transform(&self) -> Transform {
+        self.transform
+    }
+
+    fn pattern_at(&self, point: Tuple4D) -> Color {
+        let distance = (point.x * point.x + point.z * point.z).sqrt();
+        if distance.floor().rem_euclid(2.0) == 0.0 {
+            self.a.pattern_at_nested(point)
+        } else {
+            self.b.pattern_at_nested(point)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ring_extends_in_x_and_z() {
+        let white = Color::new(1.0, 1.0, 1.0);
+        let black = Color::new(0.0, 0.0, 0.0);
+        let pattern = RingPattern::new(white, black);
+        assert_eq!(pattern.pattern_at(Tuple4D::new_point(0.0, 0.0, 0.0)), white);
+        assert_eq!(pattern.pattern_at(Tuple4D::new_point(1.0, 0.0, 0.0)), black);
+        assert_eq!(pattern.pattern_at(Tuple4D::new_point(0.0, 0.0, 1.0)), black);
+        // 0.708 = just slightly more than sqrt(2)/2
+        assert_eq!(
+            pattern.pattern_at(Tuple4D::new_point(0.708, 0.0, 0.708)),
+            black
+        );
+    }
+}
@@ -0,0 +1,143 @@
+use std::sync::Arc;
+
+use crate::math::{Transform, Tuple4D};
+use crate::patterns::Pattern;
+use crate::Color;
+
+// alternates between two colors along the x axis
+#[derive(Debug, Clone)]
+pub struct StripePattern {
+    pub a: Arc<dyn Pattern>,
+    pub b: Arc<dyn Pattern>,
+    pub transform: Transform,
+}
+
+impl StripePattern {
+    pub fn new(a: impl Pattern + 'static, b: impl Pattern + 'static) -> StripePattern {
+        StripePattern {
+            a: Arc::new(a),
+            b: Arc::new(b),
+            transform: Transform::default(),
+        }
+    }
+}
+
+impl Pattern for StripePattern {
+    fn transform(&self) -> Transform {
+        self.transform
+    }
+
+    fn pattern_at(&self, point: Tuple4D) -> Color {
+        if point.x.floor().rem_euclid(2.0) == 0.0 {
+            self.a.pattern_at_nested(point)
+        } else {
+            self.b.pattern_at_nested(point)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::Matrix4;
+    use crate::Object;
+
+    use super::*;
+
+    fn white() -> Color {
+        Color::new(1.0, 1.0, 1.0)
+    }
+
+    fn black() -> Color {
+        Color::new(0.0, 0.0, 0.0)
+    }
+
+    #[test]
+    fn creating_stripe_pattern() {
+        let pattern = StripePattern::new(white(), black());
+        let origin = Tuple4D::new_point(0.0, 0.0, 0.0);
+        assert_eq!(pattern.a.pattern_at(origin), white());
+        assert_eq!(pattern.b.pattern_at(origin), black());
+    }
+
+    #[test]
+    fn stripe_pattern_is_constant_in_y_and_z() {
+        let pattern = StripePattern::new(white(), black());
+        assert_eq!(
+            pattern.pattern_at(Tuple4D::new_point(0.0, 0.0, 0.0)),
+            white()
+        );
+        assert_eq!(
+            pattern.pattern_at(Tuple4D::new_point(0.0, 1.0, 0.0)),
+            white()
+        );
+        assert_eq!(
+            pattern.pattern_at(Tuple4D::new_point(0.0, 2.0, 0.0)),
+            white()
+        );
+        assert_eq!(
+            pattern.pattern_at(Tuple4D::new_point(0.0, 0.0, 1.0)),
+            white()
+        );
+        assert_eq!(
+            pattern.pattern_at(Tuple4D::new_point(0.0, 0.0, 2.0)),
+            white()
+        );
+    }
+
+    #[test]
+    fn stripe_pattern_alternates_in_x() {
+        let pattern = StripePattern::new(white(), black());
+        assert_eq!(
+            pattern.pattern_at(Tuple4D::new_point(0.0, 0.0, 0.0)),
+            white()
+        );
+        assert_eq!(
+            pattern.pattern_at(Tuple4D::new_point(0.9, 0.0, 0.0)),
+            white()
+        );
+        assert_eq!(
+            pattern.pattern_at(Tuple4D::new_point(1.0, 0.0, 0.0)),
+            black()
+        );
+        assert_eq!(
+            pattern.pattern_at(Tuple4D::new_point(-0.1, 0.0, 0.0)),
+            black()
+        );
+        assert_eq!(
+            pattern.pattern_at(Tuple4D::new_point(-1.0, 0.0, 0.0)),
+            black()
+        );
+        assert_eq!(
+            pattern.pattern_at(Tuple4D::new_point(-1.1, 0.0, 0.0)),
+            white()
+        );
+    }
+
+    #[test]
+    fn stripes_with_object_transformation() {
+        let mut object = Object::sphere();
+        object.transform = Matrix4::scaling(2.0, 2.0, 2.0).into();
+        let pattern = StripePattern::new(white(), black());
+        let color = pattern.pattern_at_shape(&object, Tuple4D::new_point(1.5, 0.0, 0.0));
+        assert_eq!(color, white());
+    }
+
+    #[test]
+    fn stripes_with_pattern_transformation() {
+        let object = Object::sphere();
+        let mut pattern = StripePattern::new(white(), black());
+        pattern.transform = Matrix4::scaling(2.0, 2.0, 2.0).into();
+        let color = pattern.pattern_at_shape(&object, Tuple4D::new_point(1.5, 0.0, 0.0));
+        assert_eq!(color, white());
+    }
+
+    #[test]
+    fn stripes_with_object_and_pattern_transformation() {
+        let mut object = Object::sphere();
+        object.transform = Matrix4::scaling(2.0, 2.0, 2.0).into();
+        let mut pattern = StripePattern::new(white(), black());
+        pattern.transform = Matrix4::translation(0.5, 0.0, 0.0).into();
+        let color = pattern.pattern_at_shape(&object, Tuple4D::new_point(2.5, 0.0, 0.0));
+        assert_eq!(color, white());
+    }
+}
@@ -0,0 +1,330 @@
+use std::sync::Arc;
+
+use crate::math::{Float, Transform, Tuple4D};
+use crate::patterns::uv::{cube_map, CubeFace, UvMap, UvPattern};
+use crate::patterns::Pattern;
+use crate::shapes::Triangle;
+use crate::Color;
+
+// wraps a 2D uv pattern onto a surface using the given mapping
+#[derive(Debug, Clone)]
+pub struct TextureMapPattern {
+    pub uv_pattern: Arc<dyn UvPattern>,
+    pub mapping: UvMap,
+    pub transform: Transform,
+}
+
+impl TextureMapPattern {
+    pub fn new(uv_pattern: impl UvPattern + 'static, mapping: UvMap) -> TextureMapPattern {
+        TextureMapPattern {
+            uv_pattern: Arc::new(uv_pattern),
+            mapping,
+            transform: Transform::default(),
+        }
+    }
+}
+
+impl Pattern for TextureMapPattern {
+    fn transform(&self) -> Transform {
+        self.transform
+    }
+
+    fn pattern_at(&self, point: Tuple4D) -> Color {
+        let (u, v) = (self.mapping)(point);
+        self.uv_pattern.uv_pattern_at(u, v)
+    }
+
+    fn pattern_at_filtered(&self, point: Tuple4D, footprint: Float) -> Color {
+        let (u, v) = (self.mapping)(point);
+        let uv_footprint = uv_footprint(self.mapping, point, (u, v), footprint);
+        self.uv_pattern.uv_pattern_at_filtered(u, v, uv_footprint)
+    }
+}
+
+// size of the footprint in uv space, estimated by moving the point by the footprint along every
+// axis and taking the largest change in uv. Mappings wrap around, so no change is larger than 0.5
+fn uv_footprint(mapping: UvMap, point: Tuple4D, (u, v): (Float, Float), footprint: Float) -> Float {
+    if footprint <= 0.0 {
+        return 0.0;
+    }
+    let wrapped = |delta: Float| {
+        let delta = delta.abs().rem_euclid(1.0);
+        delta.min(1.0 - delta)
+    };
+    [
+        Tuple4D::new_vector(footprint, 0.0, 0.0),
+        Tuple4D::new_vector(0.0, footprint, 0.0),
+        Tuple4D::new_vector(0.0, 0.0, footprint),
+    ]
+    .into_iter()
+    .map(|offset| {
+        let (offset_u, offset_v) = mapping(point + offset);
+        wrapped(offset_u - u).max(wrapped(offset_v - v))
+    })
+    .fold(0.0, Float::max)
+}
+
+// one uv pattern per face of the cube from -1 to 1
+#[derive(Debug, Clone)]
+pub struct CubeMapPattern {
+    pub left: Arc<dyn UvPattern>,
+    pub front: Arc<dyn UvPattern>,
+    pub right: Arc<dyn UvPattern>,
+    pub back: Arc<dyn UvPattern>,
+    pub up: Arc<dyn UvPattern>,
+    pub down: Arc<dyn UvPattern>,
+    pub transform: Transform,
+}
+
+impl CubeMapPattern {
+    pub fn new(
+        left: impl UvPattern + 'static,
+        front: impl UvPattern + 'static,
+        right: impl UvPattern + 'static,
+        back: impl UvPattern + 'static,
+        up: impl UvPattern + 'static,
+        down: impl UvPattern + 'static,
+    ) -> CubeMapPattern {
+        CubeMapPattern {
+            left: Arc::new(left),
+            front: Arc::new(front),
+            right: Arc::new(right),
+            back: Arc::new(back),
+            up: Arc::new(up),
+            down: Arc::new(down),
+            transform: Transform::default(),
+        }
+    }
+
+    fn face_pattern(&self, face: CubeFace) -> &Arc<dyn UvPattern> {
+        match face {
+            CubeFace::Left => &self.left,
+            CubeFace::Front => &self.front,
+            CubeFace::Right => &self.right,
+            CubeFace::Back => &self.back,
+            CubeFace::Up => &self.up,
+            CubeFace::Down => &self.down,
+        }
+    }
+}
+
+impl Pattern for CubeMapPattern {
+    fn transform(&self) -> Transform {
+        self.transform
+    }
+
+    fn pattern_at(&self, point: Tuple4D) -> Color {
+        let (face, u, v) = cube_map(point);
+        self.face_pattern(face).uv_pattern_at(u, v)
+    }
+
+    fn pattern_at_filtered(&self, point: Tuple4D, footprint: Float) -> Color {
+        let (face, u, v) = cube_map(point);
+        // every face spans 2 units of the cube
+        self.face_pattern(face)
+            .uv_pattern_at_filtered(u, v, footprint / 2.0)
+    }
+}
+
+// maps a uv pattern onto a triangle of a mesh with texture coordinates given at its corners,
+// used as the pattern of the object holding the triangle
+#[derive(Debug, Clone)]
+pub struct TriangleTexturePattern {
+    pub uv_pattern: Arc<dyn UvPattern>,
+    pub triangle: Triangle,
+    // uv coordinates at p1, p2 and p3 of the triangle
+    pub uvs: [(Float, Float); 3],
+    pub transform: Transform,
+}
+
+impl TriangleTexturePattern {
+    pub fn new(
+        uv_pattern: Arc<dyn UvPattern>,
+        triangle: Triangle,
+        uvs: [(Float, Float); 3],
+    ) -> TriangleTexturePattern {
+        TriangleTexturePattern {
+            uv_pattern,
+            triangle,
+            uvs,
+            transform: Transform::default(),
+        }
+    }
+
+    fn uv_at(&self, point: Tuple4D) -> (Float, Float) {
+        let (b2, b3) = self.triangle.barycentric(point);
+        let b1 = 1.0 - b2 - b3;
+        let [uv1, uv2, uv3] = self.uvs;
+        (
+            uv1.0 * b1 + uv2.0 * b2 + uv3.0 * b3,
+            uv1.1 * b1 + uv2.1 * b2 + uv3.1 * b3,
+        )
+    }
+}
+
+impl Pattern for TriangleTexturePattern {
+    fn transform(&self) -> Transform {
+        self.transform
+    }
+
+    fn pattern_at(&self, point: Tuple4D) -> Color {
+        let (u, v) = self.uv_at(point);
+        self.uv_pattern.uv_pattern_at(u, v)
+    }
+
+    fn pattern_at_filtered(&self, point: Tuple4D, footprint: Float) -> Color {
+        let (u, v) = self.uv_at(point);
+        // uv units per unit of length, from the ratio of the areas of the triangle in both spaces
+        let [uv1, uv2, uv3] = self.uvs;
+        let uv_area = ((uv2.0 - uv1.0) * (uv3.1 - uv1.1) - (uv3.0 - uv1.0) * (uv2.1 - uv1.1)).abs();
+        let area = self.triangle.e1().cross(self.triangle.e2()).magnitude();
+        let scale = if area > 0.0 {
+            (uv_area / area).sqrt()
+        } else {
+            0.0
+        };
+        self.uv_pattern
+            .uv_pattern_at_filtered(u, v, footprint * scale)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::FLOAT_EQ_EPS;
+    use crate::patterns::uv::{planar_map, spherical_map, UvAlignCheck, UvCheckers};
+
+    use super::*;
+
+    #[test]
+    fn texture_map_with_spherical_map() {
+        let black = Color::new(0.0, 0.0, 0.0);
+        let white = Color::new(1.0, 1.0, 1.0);
+        let pattern =
+            TextureMapPattern::new(UvCheckers::new(16.0, 8.0, black, white), spherical_map);
+        let cases = [
+            (Tuple4D::new_point(0.4315, 0.4670, 0.7719), white),
+            (Tuple4D::new_point(-0.9654, 0.2552, -0.0534), black),
+            (Tuple4D::new_point(0.1039, 0.7090, 0.6975), white),
+            (Tuple4D::new_point(-0.4986, -0.7856, -0.3663), black),
+            (Tuple4D::new_point(-0.0317, -0.9395, 0.3411), black),
+            (Tuple4D::new_point(0.4809, -0.7721, 0.4154), black),
+            (Tuple4D::new_point(0.0285, -0.9612, -0.2745), black),
+            (Tuple4D::new_point(-0.5734, -0.2162, -0.7903), white),
+            (Tuple4D::new_point(0.7688, -0.1470, 0.6223), black),
+            (Tuple4D::new_point(-0.7652, 0.2175, 0.6060), black),
+        ];
+        for (point, color) in cases {
+            assert_eq!(pattern.pattern_at(point), color);
+        }
+    }
+
+    #[test]
+    fn finding_colors_on_mapped_cube() {
+        let red = Color::new(1.0, 0.0, 0.0);
+        let yellow = Color::new(1.0, 1.0, 0.0);
+        let brown = Color::new(1.0, 0.5, 0.0);
+        let green = Color::new(0.0, 1.0, 0.0);
+        let cyan = Color::new(0.0, 1.0, 1.0);
+        let blue = Color::new(0.0, 0.0, 1.0);
+        let purple = Color::new(1.0, 0.0, 1.0);
+        let white = Color::new(1.0, 1.0, 1.0);
+        let align = |main, upper_left, upper_right, bottom_left, bottom_right| UvAlignCheck {
+            main,
+            upper_left,
+            upper_right,
+            bottom_left,
+            bottom_right,
+        };
+        let pattern = CubeMapPattern::new(
+            align(yellow, cyan, red, blue, brown),
+            align(cyan, red, yellow, brown, green),
+            align(red, yellow, purple, green, white),
+            align(green, purple, cyan, white, blue),
+            align(brown, cyan, purple, red, yellow),
+            align(purple, brown, green, blue, white),
+        );
+        let cases = [
+            (Tuple4D::new_point(-1.0, 0.0, 0.0), yellow),
+            (Tuple4D::new_point(-1.0, 0.9, -0.9), cyan),
+            (Tuple4D::new_point(-1.0, 0.9, 0.9), red),
+            (Tuple4D::new_point(-1.0, -0.9, -0.9), blue),
+            (Tuple4D::new_point(-1.0, -0.9, 0.9), brown),
+            (Tuple4D::new_point(0.0, 0.0, 1.0), cyan),
+            (Tuple4D::new_point(-0.9, 0.9, 1.0), red),
+            (Tuple4D::new_point(0.9, 0.9, 1.0), yellow),
+            (Tuple4D::new_point(-0.9, -0.9, 1.0), brown),
+            (Tuple4D::new_point(0.9, -0.9, 1.0), green),
+            (Tuple4D::new_point(1.0, 0.0, 0.0), red),
+            (Tuple4D::new_point(1.0, 0.9, 0.9), yellow),
+            (Tuple4D::new_point(1.0, 0.9, -0.9), purple),
+            (Tuple4D::new_point(1.0, -0.9, 0.9), green),
+            (Tuple4D::new_point(1.0, -0.9, -0.9), white),
+            (Tuple4D::new_point(0.0, 0.0, -1.0), green),
+            (Tuple4D::new_point(0.9, 0.9, -1.0), purple),
+            (Tuple4D::new_point(-0.9, 0.9, -1.0), cyan),
+            (Tuple4D::new_point(0.9, -0.9, -1.0), white),
+            (Tuple4D::new_point(-0.9, -0.9, -1.0), blue),
+            (Tuple4D::new_point(0.0, 1.0, 0.0), brown),
+            (Tuple4D::new_point(-0.9, 1.0, -0.9), cyan),
+            (Tuple4D::new_point(0.9, 1.0, -0.9), purple),
+            (Tuple4D::new_point(-0.9, 1.0, 0.9), red),
+            (Tuple4D::new_point(0.9, 1.0, 0.9), yellow),
+            (Tuple4D::new_point(0.0, -1.0, 0.0), purple),
+            (Tuple4D::new_point(-0.9, -1.0, 0.9), brown),
+            (Tuple4D::new_point(0.9, -1.0, 0.9), green),
+            (Tuple4D::new_point(-0.9, -1.0, -0.9), blue),
+            (Tuple4D::new_point(0.9, -1.0, -0.9), white),
+        ];
+        for (point, color) in cases {
+            assert_eq!(pattern.pattern_at(point), color, "{point:?}");
+        }
+    }
+
+    #[test]
+    fn uv_footprint_of_planar_map() {
+        let point = Tuple4D::new_point(0.25, 0.0, 0.5);
+        let uv = planar_map(point);
+        assert!((uv_footprint(planar_map, point, uv, 0.1) - 0.1).abs() < FLOAT_EQ_EPS);
+        assert_eq!(uv_footprint(planar_map, point, uv, 0.0), 0.0);
+    }
+
+    #[test]
+    fn texture_map_filters_with_footprint() {
+        let black = Color::new(0.0, 0.0, 0.0);
+        let white = Color::new(1.0, 1.0, 1.0);
+        let pattern = TextureMapPattern::new(UvCheckers::new(2.0, 2.0, black, white), planar_map);
+        let point = Tuple4D::new_point(0.5, 0.0, 0.25);
+        assert_eq!(pattern.pattern_at_filtered(point, 0.0), white);
+        assert_eq!(
+            pattern.pattern_at_filtered(point, 0.0),
+            pattern.pattern_at(point)
+        );
+        let blurred = pattern.pattern_at_filtered(point, 0.5);
+        assert!((blurred.r - 0.5).abs() < FLOAT_EQ_EPS);
+    }
+
+    #[test]
+    fn triangle_texture_interpolates_uv_coordinates() {
+        let black = Color::new(0.0, 0.0, 0.0);
+        let white = Color::new(1.0, 1.0, 1.0);
+        let triangle = Triangle::new(
+            Tuple4D::new_point(0.0, 0.0, 0.0),
+            Tuple4D::new_point(2.0, 0.0, 0.0),
+            Tuple4D::new_point(0.0, 2.0, 0.0),
+        );
+        let pattern = TriangleTexturePattern::new(
+            Arc::new(UvCheckers::new(2.0, 2.0, black, white)),
+            triangle,
+            [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)],
+        );
+        assert_eq!(
+            pattern.uv_at(Tuple4D::new_point(1.0, 0.5, 0.0)),
+            (0.5, 0.25)
+        );
+        assert_eq!(pattern.pattern_at(Tuple4D::new_point(0.2, 0.2, 0.0)), black);
+        assert_eq!(pattern.pattern_at(Tuple4D::new_point(1.2, 0.2, 0.0)), white);
+        // the triangle is twice as large as its uv triangle
+        let blurred = pattern.pattern_at_filtered(Tuple4D::new_point(1.0, 0.25, 0.0), 1.0);
+        assert!((blurred.r - 0.5).abs() < FLOAT_EQ_EPS);
+    }
+}
@@ -0,0 +1,591 @@
+use std::fmt::Debug;
+
+use crate::math::consts::PI;
+use crate::math::{Float, Tuple4D};
+use crate::{Canvas, Color};
+
+// a 2D pattern, u and v are expected to be in [0, 1]
+pub trait UvPattern: Debug + Send + Sync {
+    fn uv_pattern_at(&self, u: Float, v: Float) -> Color;
+
+    // pattern averaged over a square around (u, v) that is footprint wide in uv space
+    fn uv_pattern_at_filtered(&self, u: Float, v: Float, _footprint: Float) -> Color {
+        self.uv_pattern_at(u, v)
+    }
+}
+
+// maps a point on the surface of an object (in object space) to uv coordinates
+pub type UvMap = fn(Tuple4D) -> (Float, Float);
+
+impl UvPattern for Color {
+    fn uv_pattern_at(&self, _u: Float, _v: Float) -> Color {
+        *self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UvCheckers {
+    pub width: Float,
+    pub height: Float,
+    pub a: Color,
+    pub b: Color,
+}
+
+impl UvCheckers {
+    pub fn new(width: Float, height: Float, a: Color, b: Color) -> UvCheckers {
+        UvCheckers {
+            width,
+            height,
+            a,
+            b,
+        }
+    }
+}
+
+impl UvPattern for UvCheckers {
+    fn uv_pattern_at(&self, u: Float, v: Float) -> Color {
+        let u2 = (u * self.width).floor();
+        let v2 = (v * self.height).floor();
+        if (u2 + v2).rem_euclid(2.0) == 0.0 {
+            self.a
+        } else {
+            self.b
+        }
+    }
+
+    // box filtered analytically, the checkers fade to the average color once a cell gets
+    // smaller than the footprint instead of flickering
+    fn uv_pattern_at_filtered(&self, u: Float, v: Float, footprint: Float) -> Color {
+        if footprint <= 0.0 {
+            return self.uv_pattern_at(u, v);
+        }
+        let odd_u = odd_fraction(u * self.width, footprint * self.width);
+        let odd_v = odd_fraction(v * self.height, footprint * self.height);
+        let fraction_b = odd_u * (1.0 - odd_v) + odd_v * (1.0 - odd_u);
+        self.a * (1.0 - fraction_b) + self.b * fraction_b
+    }
+}
+
+// fraction of [x - width / 2, x + width / 2] that lies in cells with an odd index
+fn odd_fraction(x: Float, width: Float) -> Float {
+    // integral of the function that is 1 in odd cells and 0 in even ones
+    let integral = |x: Float| {
+        let pairs = (x / 2.0).floor();
+        pairs + (x - 2.0 * pairs - 1.0).max(0.0)
+    };
+    (integral(x + width / 2.0) - integral(x - width / 2.0)) / width
+}
+
+// marks the corners of the uv square with distinct colors, useful to check the orientation of a mapping
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UvAlignCheck {
+    pub main: Color,
+    pub upper_left: Color,
+    pub upper_right: Color,
+    pub bottom_left: Color,
+    pub bottom_right: Color,
+}
+
+impl UvPattern for UvAlignCheck {
+    fn uv_pattern_at(&self, u: Float, v: Float) -> Color {
+        if v > 0.8 {
+            if u < 0.2 {
+                return self.upper_left;
+            }
+            if u > 0.8 {
+                return self.upper_right;
+            }
+        } else if v < 0.2 {
+            if u < 0.2 {
+                return self.bottom_left;
+            }
+            if u > 0.8 {
+                return self.bottom_right;
+            }
+        }
+        self.main
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextureFilter {
+    // the closest pixel
+    #[default]
+    Nearest,
+    // interpolates between the four closest pixels
+    Bilinear,
+    // bilinear on the two mipmap levels that match the footprint of the ray best, blended
+    Trilinear,
+}
+
+// samples an image, u = 0 is the left and v = 0 the bottom edge of the canvas. Empty canvases are
+// black everywhere
+#[derive(Debug)]
+pub struct UvImage {
+    pub canvas: Canvas,
+    pub filter: TextureFilter,
+    // downsampled copies of the canvas, each half the size of the previous one down to a single
+    // pixel. Built once in new, so changes to the canvas afterwards are not reflected
+    mipmaps: Vec<Canvas>,
+}
+
+impl UvImage {
+    pub fn new(canvas: Canvas) -> UvImage {
+        let mut mipmaps: Vec<Canvas> = vec![];
+        let mut level = &canvas;
+        while !is_empty(level) && (level.width() > 1 || level.height() > 1) {
+            mipmaps.push(downsample(level));
+            level = mipmaps.last().expect("a level was just pushed");
+        }
+        UvImage {
+            canvas,
+            filter: TextureFilter::Nearest,
+            mipmaps,
+        }
+    }
+
+    pub fn with_filter(self, filter: TextureFilter) -> UvImage {
+        UvImage { filter, ..self }
+    }
+
+    pub fn from_ppm(filename: &str) -> std::io::Result<UvImage> {
+        Ok(UvImage::new(Canvas::from_ppm(filename)?))
+    }
+
+    // any format Canvas::load supports
+    pub fn from_file(filename: &str) -> std::io::Result<UvImage> {
+        Ok(UvImage::new(Canvas::load(filename)?))
+    }
+
+    // level 0 is the canvas itself, levels beyond the last one give the last one
+    pub fn mipmap_level(&self, level: usize) -> &Canvas {
+        match level {
+            0 => &self.canvas,
+            _ => self
+                .mipmaps
+                .get(level - 1)
+                .or(self.mipmaps.last())
+                .unwrap_or(&self.canvas),
+        }
+    }
+
+    pub fn mipmap_levels(&self) -> usize {
+        self.mipmaps.len() + 1
+    }
+}
+
+// position in pixels, u and v map to the centers of the border pixels at 0 and 1
+fn pixel_position(canvas: &Canvas, u: Float, v: Float) -> (Float, Float) {
+    let x = u.clamp(0.0, 1.0) * (canvas.width() - 1) as Float;
+    let y = (1.0 - v).clamp(0.0, 1.0) * (canvas.height() - 1) as Float;
+    (x, y)
+}
+
+fn is_empty(canvas: &Canvas) -> bool {
+    canvas.width() == 0 || canvas.height() == 0
+}
+
+fn sample_nearest(canvas: &Canvas, u: Float, v: Float) -> Color {
+    if is_empty(canvas) {
+        return Color::new(0.0, 0.0, 0.0);
+    }
+    let (x, y) = pixel_position(canvas, u, v);
+    canvas.read_pixel(x.round() as usize, y.round() as usize)
+}
+
+fn sample_bilinear(canvas: &Canvas, u: Float, v: Float) -> Color {
+    if is_empty(canvas) {
+        return Color::new(0.0, 0.0, 0.0);
+    }
+    let (x, y) = pixel_position(canvas, u, v);
+    let (x0, y0) = (x.floor() as usize, y.floor() as usize);
+    let x1 = (x0 + 1).min(canvas.width() - 1);
+    let y1 = (y0 + 1).min(canvas.height() - 1);
+    let (tx, ty) = (x - x0 as Float, y - y0 as Float);
+    let top = canvas.read_pixel(x0, y0) * (1.0 - tx) + canvas.read_pixel(x1, y0) * tx;
+    let bottom = canvas.read_pixel(x0, y1) * (1.0 - tx) + canvas.read_pixel(x1, y1) * tx;
+    top * (1.0 - ty) + bottom * ty
+}
+
+// averages blocks of 2x2 pixels, a trailing row or column of odd sized canvases is folded into
+// the last block
+fn downsample(canvas: &Canvas) -> Canvas {
+    let width = (canvas.width() / 2).max(1);
+    let height = (canvas.height() / 2).max(1);
+    let mut result = Canvas::create_canvas(width, height);
+    for y in 0..height {
+        let y_end = if y == height - 1 {
+            canvas.height()
+        } else {
+            2 * y + 2
+        };
+        for x in 0..width {
+            let x_end = if x == width - 1 {
+                canvas.width()
+            } else {
+                2 * x + 2
+            };
+            let mut sum = Color::new(0.0, 0.0, 0.0);
+            for source_y in 2 * y..y_end {
+                for source_x in 2 * x..x_end {
+                    sum = sum + canvas.read_pixel(source_x, source_y);
+                }
+            }
+            let count = (y_end - 2 * y) * (x_end - 2 * x);
+            result.write_pixel(x, y, sum / count as Float);
+        }
+    }
+    result
+}
+
+impl UvPattern for UvImage {
+    fn uv_pattern_at(&self, u: Float, v: Float) -> Color {
+        self.uv_pattern_at_filtered(u, v, 0.0)
+    }
+
+    fn uv_pattern_at_filtered(&self, u: Float, v: Float, footprint: Float) -> Color {
+        match self.filter {
+            TextureFilter::Nearest => sample_nearest(&self.canvas, u, v),
+            TextureFilter::Bilinear => sample_bilinear(&self.canvas, u, v),
+            TextureFilter::Trilinear => {
+                let size = self.canvas.width().max(self.canvas.height()) as Float;
+                // every level halves the resolution, so the level at which one pixel covers the
+                // footprint is its base 2 logarithm in pixels of the full canvas
+                let level = (footprint * size)
+                    .log2()
+                    .clamp(0.0, (self.mipmap_levels() - 1) as Float);
+                let lower = level.floor() as usize;
+                let blend = level - lower as Float;
+                let color = sample_bilinear(self.mipmap_level(lower), u, v);
+                if blend == 0.0 {
+                    return color;
+                }
+                color * (1.0 - blend) + sample_bilinear(self.mipmap_level(lower + 1), u, v) * blend
+            }
+        }
+    }
+}
+
+pub fn spherical_map(point: Tuple4D) -> (Float, Float) {
+    // azimuthal angle in (-pi, pi]
+    let theta = point.x.atan2(point.z);
+    let radius = Tuple4D::new_vector(point.x, point.y, point.z).magnitude();
+    // polar angle in [0, pi]
+    let phi = (point.y / radius).acos();
+    let raw_u = theta / (2.0 * PI);
+    // flip u so it increases counter-clockwise when viewed from above
+    let u = 1.0 - (raw_u + 0.5);
+    let v = 1.0 - phi / PI;
+    (u, v)
+}
+
+pub fn planar_map(point: Tuple4D) -> (Float, Float) {
+    (point.x.rem_euclid(1.0), point.z.rem_euclid(1.0))
+}
+
+pub fn cylindrical_map(point: Tuple4D) -> (Float, Float) {
+    let theta = point.x.atan2(point.z);
+    let raw_u = theta / (2.0 * PI);
+    let u = 1.0 - (raw_u + 0.5);
+    (u, point.y.rem_euclid(1.0))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CubeFace {
+    Left,
+    Right,
+    Front,
+    Back,
+    Up,
+    Down,
+}
+
+// faces of the axis aligned cube from -1 to 1
+pub fn face_from_point(point: Tuple4D) -> CubeFace {
+    let coord = point.x.abs().max(point.y.abs()).max(point.z.abs());
+    if coord == point.x {
+        CubeFace::Right
+    } else if coord == -point.x {
+        CubeFace::Left
+    } else if coord == point.y {
+        CubeFace::Up
+    } else if coord == -point.y {
+        CubeFace::Down
+    } else if coord == point.z {
+        CubeFace::Front
+    } else {
+        CubeFace::Back
+    }
+}
+
+pub fn cube_map(point: Tuple4D) -> (CubeFace, Float, Float) {
+    let face = face_from_point(point);
+    let (u, v) = match face {
+        CubeFace::Front => (point.x + 1.0, point.y + 1.0),
+        CubeFace::Back => (1.0 - point.x, point.y + 1.0),
+        CubeFace::Left => (point.z + 1.0, point.y + 1.0),
+        CubeFace::Right => (1.0 - point.z, point.y + 1.0),
+        CubeFace::Up => (point.x + 1.0, 1.0 - point.z),
+        CubeFace::Down => (point.x + 1.0, point.z + 1.0),
+    };
+    (face, u.rem_euclid(2.0) / 2.0, v.rem_euclid(2.0) / 2.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::consts::FRAC_1_SQRT_2;
+
+    use crate::math::{float_eq, FLOAT_EQ_EPS};
+
+    use super::*;
+
+    fn assert_uv(actual: (Float, Float), expected: (Float, Float)) {
+        assert!(
+            float_eq(actual.0, expected.0, FLOAT_EQ_EPS)
+                && float_eq(actual.1, expected.1, FLOAT_EQ_EPS),
+            "{actual:?} != {expected:?}"
+        );
+    }
+
+    #[test]
+    fn checker_pattern_in_2d() {
+        let black = Color::new(0.0, 0.0, 0.0);
+        let white = Color::new(1.0, 1.0, 1.0);
+        let checkers = UvCheckers::new(2.0, 2.0, black, white);
+        assert_eq!(checkers.uv_pattern_at(0.0, 0.0), black);
+        assert_eq!(checkers.uv_pattern_at(0.5, 0.0), white);
+        assert_eq!(checkers.uv_pattern_at(0.0, 0.5), white);
+        assert_eq!(checkers.uv_pattern_at(0.5, 0.5), black);
+        assert_eq!(checkers.uv_pattern_at(1.0, 1.0), black);
+    }
+
+    #[test]
+    fn uv_image_pattern_samples_canvas() {
+        let mut canvas = Canvas::create_canvas(10, 10);
+        for y in 0..10 {
+            for x in 0..10 {
+                let value = (x + y * 10) as Float / 100.0;
+                canvas.write_pixel(x, y, Color::new(value, value, value));
+            }
+        }
+        let pattern = UvImage::new(canvas);
+        let cases = [
+            (0.0, 0.0, 0.9),
+            (0.3, 0.0, 0.93),
+            (0.6, 0.3, 0.65),
+            (1.0, 1.0, 0.09),
+        ];
+        for (u, v, value) in cases {
+            assert_eq!(pattern.uv_pattern_at(u, v), Color::new(value, value, value));
+        }
+    }
+
+    #[test]
+    fn spherical_mapping_on_3d_point() {
+        let cases = [
+            (Tuple4D::new_point(0.0, 0.0, -1.0), (0.0, 0.5)),
+            (Tuple4D::new_point(1.0, 0.0, 0.0), (0.25, 0.5)),
+            (Tuple4D::new_point(0.0, 0.0, 1.0), (0.5, 0.5)),
+            (Tuple4D::new_point(-1.0, 0.0, 0.0), (0.75, 0.5)),
+            (Tuple4D::new_point(0.0, 1.0, 0.0), (0.5, 1.0)),
+            (Tuple4D::new_point(0.0, -1.0, 0.0), (0.5, 0.0)),
+            (
+                Tuple4D::new_point(FRAC_1_SQRT_2, FRAC_1_SQRT_2, 0.0),
+                (0.25, 0.75),
+            ),
+        ];
+        for (point, expected) in cases {
+            assert_uv(spherical_map(point), expected);
+        }
+    }
+
+    #[test]
+    fn planar_mapping_on_3d_point() {
+        let cases = [
+            (Tuple4D::new_point(0.25, 0.0, 0.5), (0.25, 0.5)),
+            (Tuple4D::new_point(0.25, 0.0, -0.25), (0.25, 0.75)),
+            (Tuple4D::new_point(0.25, 0.5, -0.25), (0.25, 0.75)),
+            (Tuple4D::new_point(1.25, 0.0, 0.5), (0.25, 0.5)),
+            (Tuple4D::new_point(0.25, 0.0, -1.75), (0.25, 0.25)),
+            (Tuple4D::new_point(1.0, 0.0, -1.0), (0.0, 0.0)),
+            (Tuple4D::new_point(0.0, 0.0, 0.0), (0.0, 0.0)),
+        ];
+        for (point, expected) in cases {
+            assert_uv(planar_map(point), expected);
+        }
+    }
+
+    #[test]
+    fn cylindrical_mapping_on_3d_point() {
+        let cases = [
+            (Tuple4D::new_point(0.0, 0.0, -1.0), (0.0, 0.0)),
+            (Tuple4D::new_point(0.0, 0.5, -1.0), (0.0, 0.5)),
+            (Tuple4D::new_point(0.0, 1.0, -1.0), (0.0, 0.0)),
+            (
+                Tuple4D::new_point(FRAC_1_SQRT_2, 0.5, -FRAC_1_SQRT_2),
+                (0.125, 0.5),
+            ),
+            (Tuple4D::new_point(1.0, 0.5, 0.0), (0.25, 0.5)),
+            (
+                Tuple4D::new_point(FRAC_1_SQRT_2, 0.5, FRAC_1_SQRT_2),
+                (0.375, 0.5),
+            ),
+            (Tuple4D::new_point(0.0, -0.25, 1.0), (0.5, 0.75)),
+            (
+                Tuple4D::new_point(-FRAC_1_SQRT_2, 0.5, FRAC_1_SQRT_2),
+                (0.625, 0.5),
+            ),
+            (Tuple4D::new_point(-1.0, 1.25, 0.0), (0.75, 0.25)),
+            (
+                Tuple4D::new_point(-FRAC_1_SQRT_2, 0.5, -FRAC_1_SQRT_2),
+                (0.875, 0.5),
+            ),
+        ];
+        for (point, expected) in cases {
+            assert_uv(cylindrical_map(point), expected);
+        }
+    }
+
+    #[test]
+    fn layout_of_align_check_pattern() {
+        let pattern = UvAlignCheck {
+            main: Color::new(1.0, 1.0, 1.0),
+            upper_left: Color::new(1.0, 0.0, 0.0),
+            upper_right: Color::new(1.0, 1.0, 0.0),
+            bottom_left: Color::new(0.0, 1.0, 0.0),
+            bottom_right: Color::new(0.0, 1.0, 1.0),
+        };
+        assert_eq!(pattern.uv_pattern_at(0.5, 0.5), pattern.main);
+        assert_eq!(pattern.uv_pattern_at(0.1, 0.9), pattern.upper_left);
+        assert_eq!(pattern.uv_pattern_at(0.9, 0.9), pattern.upper_right);
+        assert_eq!(pattern.uv_pattern_at(0.1, 0.1), pattern.bottom_left);
+        assert_eq!(pattern.uv_pattern_at(0.9, 0.1), pattern.bottom_right);
+    }
+
+    #[test]
+    fn identifying_face_of_cube_from_point() {
+        let cases = [
+            (Tuple4D::new_point(-1.0, 0.5, -0.25), CubeFace::Left),
+            (Tuple4D::new_point(1.1, -0.75, 0.8), CubeFace::Right),
+            (Tuple4D::new_point(0.1, 0.6, 0.9), CubeFace::Front),
+            (Tuple4D::new_point(-0.7, 0.0, -2.0), CubeFace::Back),
+            (Tuple4D::new_point(0.5, 1.0, 0.9), CubeFace::Up),
+            (Tuple4D::new_point(-0.2, -1.3, 1.1), CubeFace::Down),
+        ];
+        for (point, face) in cases {
+            assert_eq!(face_from_point(point), face);
+        }
+    }
+
+    #[test]
+    fn uv_mapping_of_cube_faces() {
+        let cases = [
+            (Tuple4D::new_point(-0.5, 0.5, 1.0), (0.25, 0.75)),
+            (Tuple4D::new_point(0.5, -0.5, 1.0), (0.75, 0.25)),
+            (Tuple4D::new_point(0.5, 0.5, -1.0), (0.25, 0.75)),
+            (Tuple4D::new_point(-0.5, -0.5, -1.0), (0.75, 0.25)),
+            (Tuple4D::new_point(-1.0, 0.5, -0.5), (0.25, 0.75)),
+            (Tuple4D::new_point(-1.0, -0.5, 0.5), (0.75, 0.25)),
+            (Tuple4D::new_point(1.0, 0.5, 0.5), (0.25, 0.75)),
+            (Tuple4D::new_point(1.0, -0.5, -0.5), (0.75, 0.25)),
+            (Tuple4D::new_point(-0.5, 1.0, -0.5), (0.25, 0.75)),
+            (Tuple4D::new_point(0.5, 1.0, 0.5), (0.75, 0.25)),
+            (Tuple4D::new_point(-0.5, -1.0, 0.5), (0.25, 0.75)),
+            (Tuple4D::new_point(0.5, -1.0, -0.5), (0.75, 0.25)),
+        ];
+        for (point, expected) in cases {
+            let (_, u, v) = cube_map(point);
+            assert_uv((u, v), expected);
+        }
+    }
+
+    fn gradient_canvas() -> Canvas {
+        let mut canvas = Canvas::create_canvas(4, 2);
+        for y in 0..2 {
+            for x in 0..4 {
+                let value = x as Float / 3.0;
+                canvas.write_pixel(x, y, Color::new(value, y as Float, 0.0));
+            }
+        }
+        canvas
+    }
+
+    #[test]
+    fn bilinear_filter_interpolates_between_pixels() {
+        let pattern = UvImage::new(gradient_canvas()).with_filter(TextureFilter::Bilinear);
+        assert_eq!(pattern.uv_pattern_at(0.0, 1.0), Color::new(0.0, 0.0, 0.0));
+        assert_eq!(pattern.uv_pattern_at(0.5, 0.5), Color::new(0.5, 0.5, 0.0));
+        assert_eq!(pattern.uv_pattern_at(1.0, 0.0), Color::new(1.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn mipmap_levels_halve_resolution() {
+        let pattern = UvImage::new(gradient_canvas());
+        assert_eq!(pattern.mipmap_levels(), 3);
+        let level_1 = pattern.mipmap_level(1);
+        assert_eq!((level_1.width(), level_1.height()), (2, 1));
+        assert_eq!(level_1.read_pixel(0, 0), Color::new(1.0 / 6.0, 0.5, 0.0));
+        let level_2 = pattern.mipmap_level(2);
+        assert_eq!((level_2.width(), level_2.height()), (1, 1));
+        assert_eq!(level_2.read_pixel(0, 0), Color::new(0.5, 0.5, 0.0));
+    }
+
+    #[test]
+    fn odd_sized_canvas_keeps_every_pixel_in_mipmaps() {
+        let mut canvas = Canvas::create_canvas(3, 1);
+        canvas.write_pixel(2, 0, Color::new(3.0, 3.0, 3.0));
+        let pattern = UvImage::new(canvas);
+        assert_eq!(pattern.mipmap_levels(), 2);
+        assert_eq!(
+            pattern.mipmap_level(1).read_pixel(0, 0),
+            Color::new(1.0, 1.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn empty_images_are_black() {
+        let black = Color::new(0.0, 0.0, 0.0);
+        for (width, height) in [(0, 0), (0, 4), (3, 0)] {
+            for filter in [
+                TextureFilter::Nearest,
+                TextureFilter::Bilinear,
+                TextureFilter::Trilinear,
+            ] {
+                let pattern =
+                    UvImage::new(Canvas::create_canvas(width, height)).with_filter(filter);
+                assert_eq!(pattern.mipmap_levels(), 1);
+                assert_eq!(pattern.uv_pattern_at_filtered(0.3, 0.6, 0.5), black);
+            }
+        }
+        let single = UvImage::new(Canvas::create_canvas(1, 1));
+        assert_eq!(single.mipmap_level(3).width(), 1);
+    }
+
+    #[test]
+    fn trilinear_filter_picks_level_from_footprint() {
+        let pattern = UvImage::new(gradient_canvas()).with_filter(TextureFilter::Trilinear);
+        // a footprint smaller than a pixel uses the full resolution
+        assert_eq!(
+            pattern.uv_pattern_at_filtered(0.0, 1.0, 0.01),
+            Color::new(0.0, 0.0, 0.0)
+        );
+        // the whole texture fits into the footprint
+        assert_eq!(
+            pattern.uv_pattern_at_filtered(0.0, 1.0, 1.0),
+            Color::new(0.5, 0.5, 0.0)
+        );
+        let between = pattern.uv_pattern_at_filtered(0.0, 1.0, 0.35);
+        assert!(between.r > 0.0 && between.r < 0.5);
+    }
+
+    #[test]
+    fn filtered_checkers_fade_to_average() {
+        let black = Color::new(0.0, 0.0, 0.0);
+        let white = Color::new(1.0, 1.0, 1.0);
+        let checkers = UvCheckers::new(8.0, 8.0, black, white);
+        assert_eq!(checkers.uv_pattern_at_filtered(0.05, 0.05, 0.0), black);
+        assert_eq!(checkers.uv_pattern_at_filtered(0.05, 0.05, 0.01), black);
+        let average = checkers.uv_pattern_at_filtered(0.3, 0.7, 0.5);
+        assert!((average.r - 0.5).abs() < FLOAT_EQ_EPS);
+    }
+}
@@ -0,0 +1,62 @@
+use crate::math::{Transform, Tuple4D};
+use crate::patterns::Pattern;
+use crate::shapes::Triangle;
+use crate::Color;
+
+// colors given at the corners of a triangle, blended across its surface like smooth normals
+#[derive(Debug, Clone)]
+pub struct VertexColorPattern {
+    pub triangle: Triangle,
+    // colors at p1, p2 and p3 of the triangle
+    pub colors: [Color; 3],
+    pub transform: Transform,
+}
+
+impl VertexColorPattern {
+    pub fn new(triangle: Triangle, colors: [Color; 3]) -> VertexColorPattern {
+        VertexColorPattern {
+            triangle,
+            colors,
+            transform: Transform::default(),
+        }
+    }
+}
+
+impl Pattern for VertexColorPattern {
+    fn transform(&self) -> Transform {
+        self.transform
+    }
+
+    fn pattern_at(&self, point: Tuple4D) -> Color {
+        let (b2, b3) = self.triangle.barycentric(point);
+        let [c1, c2, c3] = self.colors;
+        c1 * (1.0 - b2 - b3) + c2 * b2 + c3 * b3
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vertex_colors_are_interpolated() {
+        let triangle = Triangle::new(
+            Tuple4D::new_point(0.0, 1.0, 0.0),
+            Tuple4D::new_point(-1.0, 0.0, 0.0),
+            Tuple4D::new_point(1.0, 0.0, 0.0),
+        );
+        let pattern = VertexColorPattern::new(
+            triangle,
+            [
+                Color::new(1.0, 0.0, 0.0),
+                Color::new(0.0, 1.0, 0.0),
+                Color::new(0.0, 0.0, 1.0),
+            ],
+        );
+        assert_eq!(pattern.pattern_at(triangle.p2()), Color::new(0.0, 1.0, 0.0));
+        assert_eq!(
+            pattern.pattern_at(Tuple4D::new_point(0.0, 0.5, 0.0)),
+            Color::new(0.5, 0.25, 0.25)
+        );
+    }
+}
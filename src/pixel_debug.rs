@@ -0,0 +1,355 @@
+use std::cell::RefCell;
+use std::fmt;
+
+use crate::math::{Float, Tuple4D};
+use crate::sampler::rng_for;
+use crate::{Camera, Color, Computations, Intersection, Ray, SampleDimension, World};
+
+// the events of the pixel traced by Camera::debug_pixel on this thread, None while no pixel is
+// traced so normal renders only pay for checking it
+thread_local! {
+    static EVENTS: RefCell<Option<Vec<TraceEvent>>> = const { RefCell::new(None) };
+}
+
+// something that happened while tracing a pixel. Depth is the number of bounces before the ray,
+// 0 for the rays from the camera
+#[derive(Debug, Clone, PartialEq)]
+pub enum TraceEvent {
+    // start of a sample of the pixel, the events up to its SampleColor belong to it
+    Sample {
+        index: usize,
+    },
+    Ray {
+        depth: usize,
+        origin: Tuple4D,
+        direction: Tuple4D,
+    },
+    // the closest surface the ray hits, shape is the name of the type of the shape
+    Hit {
+        depth: usize,
+        shape: &'static str,
+        t: Float,
+        point: Tuple4D,
+        normal: Tuple4D,
+        inside: bool,
+    },
+    Miss {
+        depth: usize,
+        background: Color,
+    },
+    // the ray reached an area light before any surface, only with path tracing
+    AreaLight {
+        depth: usize,
+        t: Float,
+    },
+    // the light was blocked by the surface of shape at t along direction
+    ShadowTest {
+        point: Tuple4D,
+        direction: Tuple4D,
+        light_distance: Float,
+        blocker: Option<(&'static str, Float)>,
+    },
+    TotalInternalReflection {
+        depth: usize,
+    },
+    // color of the ray at depth, including everything traced from its surface
+    Shaded {
+        depth: usize,
+        color: Color,
+    },
+    SampleColor {
+        index: usize,
+        color: Color,
+    },
+}
+
+impl fmt::Display for TraceEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let indent = |depth: usize| "  ".repeat(depth + 1);
+        match self {
+            TraceEvent::Sample { index } => write!(f, "sample {index}"),
+            TraceEvent::Ray {
+                depth,
+                origin,
+                direction,
+            } => write!(
+                f,
+                "{}ray from {} towards {}",
+                indent(*depth),
+                point(origin),
+                point(direction)
+            ),
+            TraceEvent::Hit {
+                depth,
+                shape,
+                t,
+                point: hit_point,
+                normal,
+                inside,
+            } => write!(
+                f,
+                "{}hit {shape} at t = {t:.5}, point {}, normal {}{}",
+                indent(*depth),
+                point(hit_point),
+                point(normal),
+                if *inside { ", from inside" } else { "" }
+            ),
+            TraceEvent::Miss { depth, background } => {
+                write!(
+                    f,
+                    "{}miss, background {}",
+                    indent(*depth),
+                    color(background)
+                )
+            }
+            TraceEvent::AreaLight { depth, t } => {
+                write!(f, "{}area light at t = {t:.5}", indent(*depth))
+            }
+            TraceEvent::ShadowTest {
+                point: start,
+                direction,
+                light_distance,
+                blocker,
+            } => {
+                write!(
+                    f,
+                    "    shadow ray from {} towards {}, light at {light_distance:.5}: ",
+                    point(start),
+                    point(direction)
+                )?;
+                match blocker {
+                    Some((shape, t)) => write!(f, "blocked by {shape} at t = {t:.5}"),
+                    None => write!(f, "lit"),
+                }
+            }
+            TraceEvent::TotalInternalReflection { depth } => {
+                write!(f, "{}total internal reflection", indent(*depth))
+            }
+            TraceEvent::Shaded { depth, color: c } => {
+                write!(f, "{}color {}", indent(*depth), color(c))
+            }
+            TraceEvent::SampleColor { index, color: c } => {
+                write!(f, "sample {index} color {}", color(c))
+            }
+        }
+    }
+}
+
+fn point(tuple: &Tuple4D) -> String {
+    format!("({:.5}, {:.5}, {:.5})", tuple.x, tuple.y, tuple.z)
+}
+
+fn color(color: &Color) -> String {
+    format!("({:.5}, {:.5}, {:.5})", color.r, color.g, color.b)
+}
+
+// everything that happened while tracing one pixel, color is the one Camera::pixel_color returns
+#[derive(Debug, Clone, PartialEq)]
+pub struct PixelTrace {
+    pub x: usize,
+    pub y: usize,
+    pub events: Vec<TraceEvent>,
+    pub color: Color,
+}
+
+impl fmt::Display for PixelTrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "pixel ({}, {})", self.x, self.y)?;
+        for event in &self.events {
+            writeln!(f, "{event}")?;
+        }
+        write!(f, "pixel color {}", color(&self.color))
+    }
+}
+
+impl Camera {
+    // traces the pixel like the renders do and records every ray, hit and shadow test on the way.
+    // The pixel is traced on the calling thread
+    pub fn debug_pixel(&self, world: &World, x: usize, y: usize) -> PixelTrace {
+        let rays = if self.outside_of_image(x, y) {
+            vec![]
+        } else {
+            self.pixel_rays(x, y)
+        };
+        EVENTS.set(Some(vec![]));
+        let count = rays.len();
+        let sum = rays
+            .into_iter()
+            .fold(Color::new(0.0, 0.0, 0.0), |sum, (index, ray)| {
+                record(|| TraceEvent::Sample { index });
+                let mut rng = rng_for(0, (x, y), index, SampleDimension::Light);
+                let color = self.integrator.radiance(world, &ray, &mut rng);
+                record(|| TraceEvent::SampleColor { index, color });
+                sum + color
+            });
+        let events = EVENTS.take().unwrap_or_default();
+        PixelTrace {
+            x,
+            y,
+            events,
+            color: if count == 0 {
+                sum
+            } else {
+                sum / count as Float
+            },
+        }
+    }
+}
+
+// event is only built while a pixel is debugged
+pub(crate) fn record(event: impl FnOnce() -> TraceEvent) {
+    EVENTS.with_borrow_mut(|events| {
+        if let Some(events) = events {
+            events.push(event());
+        }
+    });
+}
+
+pub(crate) fn record_ray(depth: usize, ray: &Ray) {
+    record(|| TraceEvent::Ray {
+        depth,
+        origin: ray.origin,
+        direction: ray.direction,
+    });
+}
+
+pub(crate) fn record_hit(depth: usize, comps: &Computations) {
+    record(|| TraceEvent::Hit {
+        depth,
+        shape: comps.object.shape.type_name(),
+        t: comps.t,
+        point: comps.point,
+        normal: comps.normalv,
+        inside: comps.inside,
+    });
+}
+
+// hit is the closest surface along the shadow ray, it only blocks the light in front of it
+pub(crate) fn record_shadow_test(ray: &Ray, light_distance: Float, hit: Option<Intersection>) {
+    record(|| TraceEvent::ShadowTest {
+        point: ray.origin,
+        direction: ray.direction,
+        light_distance,
+        blocker: hit
+            .filter(|hit| hit.t < light_distance)
+            .map(|hit| (hit.object.shape.type_name(), hit.t)),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::test_camera;
+    use crate::math::consts::PI;
+    use crate::math::Matrix4;
+    use crate::{Integrator, Object, PointLight};
+
+    #[test]
+    fn tracing_a_hit_of_the_default_world() {
+        let world = World::default_world();
+        let camera = test_camera(11);
+        let trace = camera.debug_pixel(&world, 5, 5);
+        assert_eq!(trace.color, camera.pixel_color(&world, 5, 5));
+        assert_eq!(trace.events[0], TraceEvent::Sample { index: 0 });
+        assert_eq!(
+            trace.events[1],
+            TraceEvent::Ray {
+                depth: 0,
+                origin: Tuple4D::new_point(0.0, 0.0, -5.0),
+                direction: Tuple4D::new_vector(0.0, 0.0, 1.0),
+            }
+        );
+        let TraceEvent::Hit { shape, t, .. } = trace.events[2] else {
+            panic!("expected a hit, got {:?}", trace.events[2]);
+        };
+        assert_eq!((shape, t), ("Sphere", 4.0));
+        assert!(matches!(
+            trace.events[3],
+            TraceEvent::ShadowTest { blocker: None, .. }
+        ));
+        assert_eq!(
+            trace.events.last(),
+            Some(&TraceEvent::SampleColor {
+                index: 0,
+                color: trace.color
+            })
+        );
+    }
+
+    #[test]
+    fn shadow_blockers_are_named() {
+        let mut world = World::new();
+        world.lights.push(
+            PointLight::new(
+                Tuple4D::new_point(0.0, 0.0, -10.0),
+                Color::new(1.0, 1.0, 1.0),
+            )
+            .into(),
+        );
+        let mut wall = Object::plane();
+        wall.transform = Matrix4::rotation_x(PI / 2.0).into();
+        world.objects.push(wall);
+        let mut blocker = Object::cube();
+        blocker.transform =
+            (Matrix4::translation(0.7, 0.0, -3.0) * Matrix4::scaling(0.2, 0.2, 0.2)).into();
+        world.objects.push(blocker);
+        let mut camera = test_camera(11);
+        camera.transform = Matrix4::view_transform(
+            Tuple4D::new_point(1.0, 0.0, -5.0),
+            Tuple4D::new_point(1.0, 0.0, 0.0),
+            Tuple4D::new_vector(0.0, 1.0, 0.0),
+        )
+        .into();
+        // the cube is between the center of the image on the wall and the light
+        let trace = camera.debug_pixel(&world, 5, 5);
+        let blockers: Vec<_> = trace
+            .events
+            .iter()
+            .filter_map(|event| match event {
+                TraceEvent::ShadowTest { blocker, .. } => Some(*blocker),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(blockers.len(), 1);
+        assert_eq!(blockers[0].map(|(shape, _)| shape), Some("Cube"));
+    }
+
+    #[test]
+    fn recursion_depth_of_reflections() {
+        let mut world = World::default_world();
+        world.objects[0].material.reflective = 0.5;
+        world.max_depth = 2;
+        let trace = test_camera(11).debug_pixel(&world, 5, 5);
+        let depths: Vec<usize> = trace
+            .events
+            .iter()
+            .filter_map(|event| match event {
+                TraceEvent::Ray { depth, .. } => Some(*depth),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(depths, vec![0, 1]);
+        assert!(trace
+            .to_string()
+            .starts_with("pixel (5, 5)\nsample 0\n  ray from"));
+    }
+
+    #[test]
+    fn path_tracing_records_bounces() {
+        let mut camera = test_camera(11);
+        camera.integrator = Integrator::path_tracing(3);
+        let trace = camera.debug_pixel(&World::default_world(), 0, 0);
+        assert!(matches!(trace.events[2], TraceEvent::Miss { depth: 0, .. }));
+        assert_eq!(
+            trace.color,
+            camera.pixel_color(&World::default_world(), 0, 0)
+        );
+    }
+
+    #[test]
+    fn normal_renders_record_nothing() {
+        record(|| panic!("events must not be built outside of debug_pixel"));
+        test_camera(11).debug_pixel(&World::default_world(), 20, 20);
+        record(|| panic!("events must not be built outside of debug_pixel"));
+    }
+}
@@ -0,0 +1,536 @@
+use std::fs;
+use std::io::{Error, ErrorKind};
+use std::str::SplitWhitespace;
+use std::sync::Arc;
+
+use crate::math::{Float, Tuple4D};
+use crate::mesh::vertex_normals;
+use crate::patterns::VertexColorPattern;
+use crate::shapes::Triangle;
+use crate::{Color, Object};
+
+// mesh of a stanford ply file in ascii or binary little endian format. Normals and colors are empty
+// if the vertices do not have them, faces with more than three vertices are split into a fan
+#[derive(Debug, Default)]
+pub struct PlyFile {
+    pub vertices: Vec<Tuple4D>,
+    pub normals: Vec<Tuple4D>,
+    pub colors: Vec<Color>,
+    // indices into the vertices for each triangle
+    pub faces: Vec<[usize; 3]>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PlyFormat {
+    Ascii,
+    BinaryLittleEndian,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PlyType {
+    Char,
+    UChar,
+    Short,
+    UShort,
+    Int,
+    UInt,
+    Float,
+    Double,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum PlyProperty {
+    Scalar(String, PlyType),
+    // type of the length and of the items of the list
+    List(String, PlyType, PlyType),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct PlyElement {
+    name: String,
+    count: usize,
+    properties: Vec<PlyProperty>,
+}
+
+// values of the body, in ascii files they are separated by whitespace while binary files store
+// them back to back
+enum PlyReader<'a> {
+    Ascii(SplitWhitespace<'a>),
+    Binary(&'a [u8]),
+}
+
+impl PlyFile {
+    pub fn from_file(filename: &str) -> std::io::Result<PlyFile> {
+        let bytes = fs::read(filename)?;
+        parse_ply_file(&bytes).map_err(|err| Error::new(ErrorKind::InvalidData, err))
+    }
+
+    // area weighted vertex normals for meshes without them, the vertices of a ply file are shared
+    // between faces already, so all edges end up smooth
+    pub fn generate_normals(&mut self) {
+        if self.normals.is_empty() {
+            self.normals = vertex_normals(&self.vertices, &self.faces);
+        }
+    }
+
+    pub fn triangles(&self) -> Vec<Triangle> {
+        self.faces
+            .iter()
+            .map(|&[i1, i2, i3]| {
+                let (p1, p2, p3) = (self.vertices[i1], self.vertices[i2], self.vertices[i3]);
+                if self.normals.is_empty() {
+                    Triangle::new(p1, p2, p3)
+                } else {
+                    Triangle::smooth(
+                        p1,
+                        p2,
+                        p3,
+                        self.normals[i1],
+                        self.normals[i2],
+                        self.normals[i3],
+                    )
+                }
+            })
+            .collect()
+    }
+
+    // vertex colors are blended across the triangles by a pattern on each of them
+    pub fn into_group(self) -> Object {
+        let children = self
+            .triangles()
+            .into_iter()
+            .zip(&self.faces)
+            .map(|(triangle, &[i1, i2, i3])| {
+                let mut object = Object::new(Box::new(triangle));
+                if !self.colors.is_empty() {
+                    let colors = [self.colors[i1], self.colors[i2], self.colors[i3]];
+                    object.material.pattern =
+                        Some(Arc::new(VertexColorPattern::new(triangle, colors)));
+                }
+                object
+            })
+            .collect();
+        Object::group(children)
+    }
+}
+
+pub fn parse_ply_file(bytes: &[u8]) -> Result<PlyFile, String> {
+    let (format, elements, body) = parse_header(bytes)?;
+    let mut reader = match format {
+        PlyFormat::Ascii => PlyReader::Ascii(
+            std::str::from_utf8(body)
+                .map_err(|_| "ascii ply body is not valid utf-8")?
+                .split_whitespace(),
+        ),
+        PlyFormat::BinaryLittleEndian => PlyReader::Binary(body),
+    };
+    let mut ply = PlyFile::default();
+    for element in &elements {
+        for _ in 0..element.count {
+            match element.name.as_str() {
+                "vertex" => read_vertex(&mut ply, element, &mut reader)?,
+                "face" => read_face(&mut ply, element, &mut reader)?,
+                // other elements like edges are skipped
+                _ => {
+                    for property in &element.properties {
+                        reader.read_property(property)?;
+                    }
+                }
+            }
+        }
+    }
+    let vertex_count = ply.vertices.len();
+    if let Some(face) = ply
+        .faces
+        .iter()
+        .find(|face| face.iter().any(|&idx| idx >= vertex_count))
+    {
+        return Err(format!(
+            "face {face:?} refers to missing vertex, there are {vertex_count} vertices"
+        ));
+    }
+    Ok(ply)
+}
+
+// returns the format, the elements in the order their data appears and the bytes after the header
+fn parse_header(bytes: &[u8]) -> Result<(PlyFormat, Vec<PlyElement>, &[u8]), String> {
+    let mut format = None;
+    let mut elements: Vec<PlyElement> = vec![];
+    let mut rest = bytes;
+    let mut line_number = 0;
+    loop {
+        let line_end = rest
+            .iter()
+            .position(|&byte| byte == b'\n')
+            .ok_or("ply header without end_header")?;
+        let line = std::str::from_utf8(&rest[..line_end])
+            .map_err(|_| "ply header is not valid utf-8")?
+            .trim();
+        rest = &rest[line_end + 1..];
+        line_number += 1;
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            Some("ply") if line_number == 1 => {}
+            _ if line_number == 1 => return Err("missing ply magic number".to_string()),
+            Some("format") => {
+                format = Some(match tokens.next() {
+                    Some("ascii") => PlyFormat::Ascii,
+                    Some("binary_little_endian") => PlyFormat::BinaryLittleEndian,
+                    other => return Err(format!("unsupported ply format {other:?}")),
+                })
+            }
+            Some("element") => {
+                let name = tokens.next().ok_or("element without name")?.to_string();
+                let count = tokens
+                    .next()
+                    .and_then(|count| count.parse().ok())
+                    .ok_or(format!("invalid count for element {name}"))?;
+                elements.push(PlyElement {
+                    name,
+                    count,
+                    properties: vec![],
+                });
+            }
+            Some("property") => {
+                let property = parse_property(tokens)
+                    .ok_or(format!("invalid property on header line {line_number}"))?;
+                elements
+                    .last_mut()
+                    .ok_or("property before first element")?
+                    .properties
+                    .push(property);
+            }
+            Some("end_header") => break,
+            // comments, obj_info and empty lines
+            _ => {}
+        }
+    }
+    let format = format.ok_or("ply header without format")?;
+    Ok((format, elements, rest))
+}
+
+fn parse_property<'a>(mut tokens: impl Iterator<Item = &'a str>) -> Option<PlyProperty> {
+    match tokens.next()? {
+        "list" => {
+            let count_kind = PlyType::from_name(tokens.next()?)?;
+            let item_kind = PlyType::from_name(tokens.next()?)?;
+            Some(PlyProperty::List(
+                tokens.next()?.to_string(),
+                count_kind,
+                item_kind,
+            ))
+        }
+        kind => Some(PlyProperty::Scalar(
+            tokens.next()?.to_string(),
+            PlyType::from_name(kind)?,
+        )),
+    }
+}
+
+fn read_vertex(
+    ply: &mut PlyFile,
+    element: &PlyElement,
+    reader: &mut PlyReader,
+) -> Result<(), String> {
+    let mut position = [0.0; 3];
+    let mut normal = [0.0; 3];
+    let mut color = [0.0; 3];
+    let (mut has_normal, mut has_color) = (false, false);
+    for property in &element.properties {
+        let values = reader.read_property(property)?;
+        let (PlyProperty::Scalar(name, kind), [value]) = (property, values.as_slice()) else {
+            continue;
+        };
+        match name.as_str() {
+            "x" => position[0] = *value,
+            "y" => position[1] = *value,
+            "z" => position[2] = *value,
+            "nx" => (normal[0], has_normal) = (*value, true),
+            "ny" => (normal[1], has_normal) = (*value, true),
+            "nz" => (normal[2], has_normal) = (*value, true),
+            "red" => (color[0], has_color) = (kind.normalize_color(*value), true),
+            "green" => (color[1], has_color) = (kind.normalize_color(*value), true),
+            "blue" => (color[2], has_color) = (kind.normalize_color(*value), true),
+            _ => {}
+        }
+    }
+    ply.vertices
+        .push(Tuple4D::new_point(position[0], position[1], position[2]));
+    if has_normal {
+        ply.normals
+            .push(Tuple4D::new_vector(normal[0], normal[1], normal[2]));
+    }
+    if has_color {
+        ply.colors.push(Color::new(color[0], color[1], color[2]));
+    }
+    Ok(())
+}
+
+fn read_face(
+    ply: &mut PlyFile,
+    element: &PlyElement,
+    reader: &mut PlyReader,
+) -> Result<(), String> {
+    for property in &element.properties {
+        let values = reader.read_property(property)?;
+        let PlyProperty::List(name, _, _) = property else {
+            continue;
+        };
+        if name != "vertex_indices" && name != "vertex_index" {
+            continue;
+        }
+        if values.len() < 3 {
+            return Err(format!("face with {} vertices", values.len()));
+        }
+        // the casts would turn negative indices into 0 and cut off fractions
+        if let Some(idx) = values
+            .iter()
+            .find(|idx| !(idx.is_finite() && **idx >= 0.0 && idx.fract() == 0.0))
+        {
+            return Err(format!("invalid vertex index {idx} in face"));
+        }
+        let indices: Vec<usize> = values.iter().map(|&idx| idx as usize).collect();
+        ply.faces
+            .extend((1..indices.len() - 1).map(|idx| [indices[0], indices[idx], indices[idx + 1]]));
+    }
+    Ok(())
+}
+
+impl PlyType {
+    fn from_name(name: &str) -> Option<PlyType> {
+        match name {
+            "char" | "int8" => Some(PlyType::Char),
+            "uchar" | "uint8" => Some(PlyType::UChar),
+            "short" | "int16" => Some(PlyType::Short),
+            "ushort" | "uint16" => Some(PlyType::UShort),
+            "int" | "int32" => Some(PlyType::Int),
+            "uint" | "uint32" => Some(PlyType::UInt),
+            "float" | "float32" => Some(PlyType::Float),
+            "double" | "float64" => Some(PlyType::Double),
+            _ => None,
+        }
+    }
+
+    fn size(&self) -> usize {
+        match self {
+            PlyType::Char | PlyType::UChar => 1,
+            PlyType::Short | PlyType::UShort => 2,
+            PlyType::Int | PlyType::UInt | PlyType::Float => 4,
+            PlyType::Double => 8,
+        }
+    }
+
+    // integer colors use the full range of their type, float colors are already in 0..1
+    fn normalize_color(&self, value: Float) -> Float {
+        match self {
+            PlyType::UChar => value / u8::MAX as Float,
+            PlyType::UShort => value / u16::MAX as Float,
+            _ => value,
+        }
+    }
+
+    fn decode_le(&self, bytes: &[u8]) -> Float {
+        match self {
+            PlyType::Char => i8::from_le_bytes([bytes[0]]) as Float,
+            PlyType::UChar => bytes[0] as Float,
+            PlyType::Short => i16::from_le_bytes(bytes.try_into().unwrap()) as Float,
+            PlyType::UShort => u16::from_le_bytes(bytes.try_into().unwrap()) as Float,
+            PlyType::Int => i32::from_le_bytes(bytes.try_into().unwrap()) as Float,
+            PlyType::UInt => u32::from_le_bytes(bytes.try_into().unwrap()) as Float,
+            PlyType::Float => f32::from_le_bytes(bytes.try_into().unwrap()) as Float,
+            PlyType::Double => f64::from_le_bytes(bytes.try_into().unwrap()) as Float,
+        }
+    }
+}
+
+impl PlyReader<'_> {
+    fn read(&mut self, kind: PlyType) -> Result<Float, String> {
+        match self {
+            PlyReader::Ascii(tokens) => {
+                let token = tokens.next().ok_or("unexpected end of ply data")?;
+                token
+                    .parse::<Float>()
+                    .map_err(|_| format!("invalid ply value {token}"))
+            }
+            PlyReader::Binary(bytes) => {
+                if bytes.len() < kind.size() {
+                    return Err("unexpected end of ply data".to_string());
+                }
+                let (value, rest) = bytes.split_at(kind.size());
+                *bytes = rest;
+                Ok(kind.decode_le(value))
+            }
+        }
+    }
+
+    // a single value for scalars, the items for lists
+    fn read_property(&mut self, property: &PlyProperty) -> Result<Vec<Float>, String> {
+        match property {
+            PlyProperty::Scalar(_, kind) => Ok(vec![self.read(*kind)?]),
+            PlyProperty::List(_, count_kind, item_kind) => {
+                let count = self.read(*count_kind)? as usize;
+                (0..count).map(|_| self.read(*item_kind)).collect()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Ray;
+
+    const HEADER: &str = "element vertex 4\n\
+        property float x\n\
+        property float y\n\
+        property float z\n\
+        property uchar red\n\
+        property uchar green\n\
+        property uchar blue\n\
+        element face 1\n\
+        property list uchar int vertex_indices\n\
+        end_header\n";
+
+    #[test]
+    fn parsing_ascii_ply() {
+        let ply = parse_ply_file(
+            format!(
+                "ply\nformat ascii 1.0\ncomment a quad\n{HEADER}\
+                 -1 1 0 255 0 0\n-1 0 0 0 255 0\n1 0 0 0 0 255\n1 1 0 255 255 255\n\
+                 4 0 1 2 3\n"
+            )
+            .as_bytes(),
+        )
+        .unwrap();
+        assert_eq!(ply.vertices.len(), 4);
+        assert_eq!(ply.vertices[3], Tuple4D::new_point(1.0, 1.0, 0.0));
+        assert_eq!(ply.colors[1], Color::new(0.0, 1.0, 0.0));
+        assert!(ply.normals.is_empty());
+        assert_eq!(ply.faces, vec![[0, 1, 2], [0, 2, 3]]);
+    }
+
+    #[test]
+    fn parsing_binary_ply() {
+        let mut bytes = format!("ply\nformat binary_little_endian 1.0\n{HEADER}").into_bytes();
+        for (position, color) in [
+            ([-1.0f32, 1.0, 0.0], [255u8, 0, 0]),
+            ([-1.0, 0.0, 0.0], [0, 255, 0]),
+            ([1.0, 0.0, 0.0], [0, 0, 255]),
+            ([1.0, 1.0, 0.0], [255, 255, 255]),
+        ] {
+            for value in position {
+                bytes.extend(value.to_le_bytes());
+            }
+            bytes.extend(color);
+        }
+        bytes.push(3);
+        for idx in [0i32, 2, 3] {
+            bytes.extend(idx.to_le_bytes());
+        }
+        let ply = parse_ply_file(&bytes).unwrap();
+        assert_eq!(ply.vertices[1], Tuple4D::new_point(-1.0, 0.0, 0.0));
+        assert_eq!(ply.colors[2], Color::new(0.0, 0.0, 1.0));
+        assert_eq!(ply.faces, vec![[0, 2, 3]]);
+    }
+
+    #[test]
+    fn vertex_normals_make_smooth_triangles() {
+        let ply = parse_ply_file(
+            b"ply\nformat ascii 1.0\nelement vertex 3\nproperty float x\nproperty float y\n\
+              property float z\nproperty float nx\nproperty float ny\nproperty float nz\n\
+              element face 1\nproperty list uchar uint vertex_index\nend_header\n\
+              0 1 0 0 1 0\n-1 0 0 -1 0 0\n1 0 0 1 0 0\n3 0 1 2\n",
+        )
+        .unwrap();
+        assert_eq!(ply.normals[1], Tuple4D::new_vector(-1.0, 0.0, 0.0));
+        assert_eq!(
+            ply.triangles()[0].normals(),
+            Some([
+                Tuple4D::new_vector(0.0, 1.0, 0.0),
+                Tuple4D::new_vector(-1.0, 0.0, 0.0),
+                Tuple4D::new_vector(1.0, 0.0, 0.0),
+            ])
+        );
+    }
+
+    #[test]
+    fn generating_missing_vertex_normals() {
+        let mut ply = parse_ply_file(
+            format!(
+                "ply\nformat ascii 1.0\n{HEADER}\
+                 -1 1 0 255 0 0\n-1 0 0 0 255 0\n1 0 0 0 0 255\n1 1 0 255 255 255\n\
+                 4 0 1 2 3\n"
+            )
+            .as_bytes(),
+        )
+        .unwrap();
+        ply.generate_normals();
+        assert_eq!(ply.normals, vec![Tuple4D::new_vector(0.0, 0.0, -1.0); 4]);
+        assert!(ply.triangles()[0].normals().is_some());
+    }
+
+    #[test]
+    fn unknown_elements_are_skipped() {
+        let ply = parse_ply_file(
+            b"ply\nformat ascii 1.0\nelement vertex 3\nproperty float x\nproperty float y\n\
+              property float z\nelement edge 1\nproperty int vertex1\nproperty int vertex2\n\
+              element face 1\nproperty list uchar int vertex_indices\nend_header\n\
+              0 1 0\n-1 0 0\n1 0 0\n0 1\n3 0 1 2\n",
+        )
+        .unwrap();
+        assert_eq!(ply.faces, vec![[0, 1, 2]]);
+    }
+
+    #[test]
+    fn invalid_ply_files_are_errors() {
+        assert!(parse_ply_file(b"obj\nformat ascii 1.0\nend_header\n").is_err());
+        assert!(parse_ply_file(b"ply\nformat binary_big_endian 1.0\nend_header\n").is_err());
+        assert!(
+            parse_ply_file(format!("ply\nformat ascii 1.0\n{HEADER}0 0 0").as_bytes()).is_err()
+        );
+        assert!(parse_ply_file(
+            format!(
+                "ply\nformat ascii 1.0\n{HEADER}\
+                 -1 1 0 255 0 0\n-1 0 0 0 255 0\n1 0 0 0 0 255\n1 1 0 255 255 255\n3 0 1 7\n"
+            )
+            .as_bytes()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn face_indices_have_to_be_vertex_numbers() {
+        for face in ["3 0 -1 2", "3 0 1.5 2"] {
+            let ply = format!(
+                "ply\nformat ascii 1.0\n{HEADER}\
+                 -1 1 0 255 0 0\n-1 0 0 0 255 0\n1 0 0 0 0 255\n1 1 0 255 255 255\n{face}\n"
+            );
+            assert!(parse_ply_file(ply.as_bytes()).is_err(), "{face}");
+        }
+    }
+
+    #[test]
+    fn converting_ply_file_to_group_with_vertex_colors() {
+        let ply = parse_ply_file(
+            format!(
+                "ply\nformat ascii 1.0\n{HEADER}\
+                 -1 1 0 255 0 0\n-1 0 0 0 255 0\n1 0 0 0 0 255\n1 1 0 255 255 255\n\
+                 3 0 1 2\n"
+            )
+            .as_bytes(),
+        )
+        .unwrap();
+        let group = ply.into_group();
+        let ray = Ray::new(
+            Tuple4D::new_point(-1.0, 0.0, -5.0),
+            Tuple4D::new_vector(0.0, 0.0, 1.0),
+        );
+        let xs = group.intersect(&ray);
+        assert_eq!(xs.len(), 1);
+        let point = ray.position(xs[0].t);
+        assert_eq!(
+            xs[0].object.material.color_at(xs[0].object, point, 0.0),
+            Color::new(0.0, 1.0, 0.0)
+        );
+    }
+}
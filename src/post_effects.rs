@@ -0,0 +1,353 @@
+use crate::math::Float;
+use crate::{Canvas, Color, Region};
+
+// effects applied to the rendered image before it is saved. They work on the linear values of the
+// canvas, which are not limited to 1
+
+// glow around bright lights and emissive objects. The parts of the image that are brighter than
+// the threshold are blurred and added on top of the image
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bloom {
+    // luminance above which pixels start to glow
+    pub threshold: Float,
+    // standard deviation of the blur as a fraction of the image width, so the glow does not
+    // change with the resolution
+    pub radius: Float,
+    pub intensity: Float,
+}
+
+impl Default for Bloom {
+    fn default() -> Self {
+        Bloom {
+            threshold: 1.0,
+            radius: 0.01,
+            intensity: 0.5,
+        }
+    }
+}
+
+impl Bloom {
+    pub fn apply(&self, image: &Canvas) -> Canvas {
+        self.apply_to_crop(image, image.width())
+    }
+
+    // apply for an image that was cropped from a frame that is frame_width wide, the glow has the
+    // size it has in the whole frame
+    pub fn apply_to_crop(&self, image: &Canvas, frame_width: usize) -> Canvas {
+        let mut bright = Canvas::create_canvas(image.width(), image.height());
+        for y in 0..image.height() {
+            for x in 0..image.width() {
+                let color = image.read_pixel(x, y);
+                let luminance = luminance(color);
+                if luminance > self.threshold {
+                    // only the part above the threshold glows, so the glow fades in smoothly
+                    bright.write_pixel(x, y, color * ((luminance - self.threshold) / luminance));
+                }
+            }
+        }
+        let glow = bright.gaussian_blur(self.radius * frame_width as Float);
+        let mut result = image.clone();
+        for y in 0..image.height() {
+            for x in 0..image.width() {
+                let color = image.read_pixel(x, y) + glow.read_pixel(x, y) * self.intensity;
+                result.write_pixel(x, y, color);
+            }
+        }
+        result
+    }
+}
+
+// darkens the image towards its corners like the falloff of a real lens
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Vignette {
+    // how much of the brightness is lost in the corners
+    pub strength: Float,
+    // distance from the center where the darkening starts, 1 is the distance of the corners, so
+    // radii of 1 and more leave the image as it is
+    pub radius: Float,
+}
+
+impl Default for Vignette {
+    fn default() -> Self {
+        Vignette {
+            strength: 0.4,
+            radius: 0.3,
+        }
+    }
+}
+
+impl Vignette {
+    pub fn apply(&self, image: &Canvas) -> Canvas {
+        self.apply_to_crop(image, whole(image), image.width(), image.height())
+    }
+
+    // apply for an image that was cropped from a frame of frame_width x frame_height, the corners
+    // are the ones of the frame
+    pub fn apply_to_crop(
+        &self,
+        image: &Canvas,
+        crop: Region,
+        frame_width: usize,
+        frame_height: usize,
+    ) -> Canvas {
+        let mut result = image.clone();
+        // the pixels are all closer to the center than the corners, none of them is darkened
+        if self.radius >= 1.0 {
+            return result;
+        }
+        for y in 0..image.height() {
+            for x in 0..image.width() {
+                let distance = center_offset(frame_width, frame_height, crop.x + x, crop.y + y);
+                let t = ((distance - self.radius) / (1.0 - self.radius)).clamp(0.0, 1.0);
+                // smoothstep, so there is no visible ring where the darkening starts
+                let falloff = t * t * (3.0 - 2.0 * t);
+                let factor = 1.0 - self.strength * falloff;
+                result.write_pixel(x, y, image.read_pixel(x, y) * factor);
+            }
+        }
+        result
+    }
+}
+
+// lenses bend the colors by different amounts, which shows as red and blue fringes that grow
+// towards the edges of the image. Red is scaled away from the center and blue towards it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChromaticAberration {
+    // offset of the red and blue channels in the corners as a fraction of the distance to the
+    // center
+    pub strength: Float,
+}
+
+impl Default for ChromaticAberration {
+    fn default() -> Self {
+        ChromaticAberration { strength: 0.005 }
+    }
+}
+
+impl ChromaticAberration {
+    pub fn apply(&self, image: &Canvas) -> Canvas {
+        self.apply_to_crop(image, whole(image), image.width(), image.height())
+    }
+
+    // apply for an image that was cropped from a frame of frame_width x frame_height, the channels
+    // are scaled around the center of the frame
+    pub fn apply_to_crop(
+        &self,
+        image: &Canvas,
+        crop: Region,
+        frame_width: usize,
+        frame_height: usize,
+    ) -> Canvas {
+        // the center of the frame in the pixels of the crop
+        let (center_x, center_y) = (
+            frame_width as Float / 2.0 - crop.x as Float,
+            frame_height as Float / 2.0 - crop.y as Float,
+        );
+        let mut result = image.clone();
+        for y in 0..image.height() {
+            for x in 0..image.width() {
+                let (px, py) = (x as Float + 0.5 - center_x, y as Float + 0.5 - center_y);
+                let red = image.sample(
+                    center_x + px * (1.0 - self.strength),
+                    center_y + py * (1.0 - self.strength),
+                );
+                let blue = image.sample(
+                    center_x + px * (1.0 + self.strength),
+                    center_y + py * (1.0 + self.strength),
+                );
+                let green = image.read_pixel(x, y).g;
+                result.write_pixel(x, y, Color::new(red.r, green, blue.b));
+            }
+        }
+        result
+    }
+}
+
+fn whole(image: &Canvas) -> Region {
+    Region::new(0, 0, image.width(), image.height())
+}
+
+// distance of the pixel center from the center of a frame of the size, 1 in the corners
+fn center_offset(width: usize, height: usize, x: usize, y: usize) -> Float {
+    let (half_width, half_height) = (width as Float / 2.0, height as Float / 2.0);
+    let (dx, dy) = (
+        x as Float + 0.5 - half_width,
+        y as Float + 0.5 - half_height,
+    );
+    (dx * dx + dy * dy).sqrt() / (half_width * half_width + half_height * half_height).sqrt()
+}
+
+impl Canvas {
+    // bilinear interpolation between the pixel centers at a position in pixels, with the edge
+    // pixels repeated outside of the image
+    pub(crate) fn sample(&self, x: Float, y: Float) -> Color {
+        if self.width() == 0 || self.height() == 0 {
+            return Color::new(0.0, 0.0, 0.0);
+        }
+        let x = (x - 0.5).clamp(0.0, (self.width() - 1) as Float);
+        let y = (y - 0.5).clamp(0.0, (self.height() - 1) as Float);
+        let (x0, y0) = (x.floor() as usize, y.floor() as usize);
+        let (x1, y1) = (
+            (x0 + 1).min(self.width() - 1),
+            (y0 + 1).min(self.height() - 1),
+        );
+        let (fx, fy) = (x - x0 as Float, y - y0 as Float);
+        let top = self.read_pixel(x0, y0) * (1.0 - fx) + self.read_pixel(x1, y0) * fx;
+        let bottom = self.read_pixel(x0, y1) * (1.0 - fx) + self.read_pixel(x1, y1) * fx;
+        top * (1.0 - fy) + bottom * fy
+    }
+
+    // blurred with a gaussian of the standard deviation sigma in pixels. The image is extended
+    // beyond its borders by repeating the edge pixels
+    pub fn gaussian_blur(&self, sigma: Float) -> Canvas {
+        if sigma <= 0.0 {
+            return self.clone();
+        }
+        let radius = (3.0 * sigma).ceil() as isize;
+        let kernel: Vec<Float> = (-radius..=radius)
+            .map(|offset| (-(offset * offset) as Float / (2.0 * sigma * sigma)).exp())
+            .collect();
+        let total: Float = kernel.iter().sum();
+        let kernel: Vec<Float> = kernel.iter().map(|weight| weight / total).collect();
+        // the gaussian is separable, blurring the rows and then the columns is the same as
+        // blurring with the 2d kernel
+        let horizontal = self.convolve(&kernel, (1, 0));
+        horizontal.convolve(&kernel, (0, 1))
+    }
+
+    fn convolve(&self, kernel: &[Float], (step_x, step_y): (isize, isize)) -> Canvas {
+        let (width, height) = (self.width() as isize, self.height() as isize);
+        let radius = (kernel.len() / 2) as isize;
+        let mut result = Canvas::create_canvas(self.width(), self.height());
+        for y in 0..height {
+            for x in 0..width {
+                let mut sum = Color::new(0.0, 0.0, 0.0);
+                for (idx, weight) in kernel.iter().enumerate() {
+                    let offset = idx as isize - radius;
+                    let sample_x = (x + offset * step_x).clamp(0, width - 1);
+                    let sample_y = (y + offset * step_y).clamp(0, height - 1);
+                    sum = sum + self.read_pixel(sample_x as usize, sample_y as usize) * *weight;
+                }
+                result.write_pixel(x as usize, y as usize, sum);
+            }
+        }
+        result
+    }
+}
+
+// relative luminance of linear rec. 709 colors
+pub(crate) fn luminance(color: Color) -> Float {
+    0.2126 * color.r + 0.7152 * color.g + 0.0722 * color.b
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg_attr(feature = "f32", ignore = "exact only in double precision")]
+    fn blur_keeps_the_total_brightness() {
+        let mut canvas = Canvas::create_canvas(21, 21);
+        canvas.write_pixel(10, 10, Color::new(1.0, 1.0, 1.0));
+        let blurred = canvas.gaussian_blur(2.0);
+        let total: Float = blurred.iter().map(|color| color.r).sum();
+        assert!((total - 1.0).abs() < 1e-9);
+        assert!(blurred.read_pixel(10, 10).r < 1.0);
+        assert!(blurred.read_pixel(12, 10).r > 0.0);
+        assert_eq!(blurred.read_pixel(12, 10), blurred.read_pixel(10, 8));
+    }
+
+    #[test]
+    fn blur_of_a_flat_image_is_the_same() {
+        let canvas = Canvas::new(5, 4, Color::new(0.3, 0.2, 0.1));
+        let blurred = canvas.gaussian_blur(3.0);
+        assert_eq!(blurred.read_pixel(0, 0), Color::new(0.3, 0.2, 0.1));
+        assert_eq!(blurred.read_pixel(4, 3), Color::new(0.3, 0.2, 0.1));
+    }
+
+    #[test]
+    fn bloom_spreads_only_bright_pixels() {
+        let mut canvas = Canvas::new(50, 50, Color::new(0.5, 0.5, 0.5));
+        canvas.write_pixel(10, 10, Color::new(50.0, 50.0, 50.0));
+        canvas.write_pixel(40, 40, Color::new(0.9, 0.9, 0.9));
+        let bloom = Bloom {
+            radius: 0.04,
+            ..Bloom::default()
+        };
+        let result = bloom.apply(&canvas);
+        assert!(result.read_pixel(12, 10).r > 0.5);
+        // dim pixels do not glow
+        assert_eq!(result.read_pixel(41, 40), Color::new(0.5, 0.5, 0.5));
+        assert_eq!(result.read_pixel(30, 30), Color::new(0.5, 0.5, 0.5));
+    }
+
+    #[test]
+    fn vignette_darkens_the_corners() {
+        let canvas = Canvas::new(21, 11, Color::new(1.0, 1.0, 1.0));
+        let result = Vignette::default().apply(&canvas);
+        assert_eq!(result.read_pixel(10, 5), Color::new(1.0, 1.0, 1.0));
+        let corner = result.read_pixel(0, 0).r;
+        assert!(corner < result.read_pixel(5, 3).r);
+        assert!(corner > 0.6 && corner < 0.7);
+        let no_darkening = Vignette {
+            radius: 1.0,
+            ..Vignette::default()
+        };
+        assert_eq!(
+            no_darkening.apply(&canvas).read_pixel(0, 0),
+            Color::new(1.0, 1.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn chromatic_aberration_shifts_red_and_blue_apart() {
+        let mut canvas = Canvas::create_canvas(41, 41);
+        for y in 0..41 {
+            canvas.write_pixel(35, y, Color::new(1.0, 1.0, 1.0));
+        }
+        let aberration = ChromaticAberration { strength: 0.05 };
+        let result = aberration.apply(&canvas);
+        // the white line is 15 pixels right of the center, the channels move by 0.75 pixels
+        assert!(result.read_pixel(36, 20).r > 0.5);
+        assert!(result.read_pixel(34, 20).b > 0.5);
+        assert_eq!(result.read_pixel(35, 20).g, 1.0);
+        // nothing moves at the center
+        let flat = aberration.apply(&Canvas::new(3, 3, Color::new(0.2, 0.4, 0.6)));
+        assert_eq!(flat.read_pixel(1, 1), Color::new(0.2, 0.4, 0.6));
+    }
+
+    #[test]
+    fn effects_on_a_crop_match_the_whole_frame() {
+        let mut canvas = Canvas::create_canvas(41, 31);
+        for y in 0..31 {
+            canvas.write_pixel(35, y, Color::new(1.0, 1.0, 1.0));
+            canvas.write_pixel(36, y, Color::new(0.5, 0.5, 0.5));
+        }
+        let crop = Region::new(30, 10, 11, 8);
+        let part = canvas.crop(crop);
+        let vignette = Vignette::default();
+        let aberration = ChromaticAberration { strength: 0.05 };
+        let whole = aberration.apply(&vignette.apply(&canvas)).crop(crop);
+        let cropped =
+            aberration.apply_to_crop(&vignette.apply_to_crop(&part, crop, 41, 31), crop, 41, 31);
+        // the crop only misses the pixels beyond its borders
+        for y in 1..7 {
+            for x in 1..10 {
+                assert_eq!(cropped.read_pixel(x, y), whole.read_pixel(x, y));
+            }
+        }
+    }
+
+    #[test]
+    fn sampling_between_pixels() {
+        let mut canvas = Canvas::create_canvas(2, 1);
+        canvas.write_pixel(1, 0, Color::new(1.0, 1.0, 1.0));
+        assert_eq!(canvas.sample(1.0, 0.5), Color::new(0.5, 0.5, 0.5));
+        assert_eq!(canvas.sample(-3.0, 0.5), Color::new(0.0, 0.0, 0.0));
+        assert_eq!(canvas.sample(5.0, 9.0), Color::new(1.0, 1.0, 1.0));
+        let empty = Canvas::create_canvas(0, 0);
+        assert_eq!(empty.sample(0.5, 0.5), Color::new(0.0, 0.0, 0.0));
+        let aberration = ChromaticAberration::default();
+        assert_eq!(aberration.apply(&empty).width(), 0);
+    }
+}
@@ -0,0 +1,74 @@
+use std::time::{Duration, Instant};
+
+use minifb::{Key, Window, WindowOptions};
+
+use crate::{Camera, CancellationToken, Canvas, World};
+
+// minimum time between two window updates while rendering
+const REFRESH_INTERVAL: Duration = Duration::from_millis(50);
+
+// a window that shows a canvas while it is being rendered, closing it or pressing escape aborts the render
+pub struct PreviewWindow {
+    window: Window,
+    buffer: Vec<u32>,
+}
+
+impl PreviewWindow {
+    pub fn new(title: &str, width: usize, height: usize) -> Result<PreviewWindow, minifb::Error> {
+        let window = Window::new(title, width, height, WindowOptions::default())?;
+        Ok(PreviewWindow {
+            window,
+            buffer: vec![0; width * height],
+        })
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.window.is_open() && !self.window.is_key_down(Key::Escape)
+    }
+
+    pub fn show(&mut self, canvas: &Canvas) -> Result<(), minifb::Error> {
+        let (width, height) = (canvas.width(), canvas.height());
+        self.buffer.resize(width * height, 0);
+        for y in 0..height {
+            for x in 0..width {
+                self.buffer[y * width + x] = to_pixel(canvas, x, y);
+            }
+        }
+        self.window.update_with_buffer(&self.buffer, width, height)
+    }
+
+    // keeps the window responsive until the user closes it
+    pub fn wait_until_closed(&mut self) {
+        while self.is_open() {
+            self.window.update();
+            std::thread::sleep(REFRESH_INTERVAL);
+        }
+    }
+}
+
+fn to_pixel(canvas: &Canvas, x: usize, y: usize) -> u32 {
+    let [r, g, b] = canvas.read_pixel(x, y).to_rgb8();
+    (r as u32) << 16 | (g as u32) << 8 | b as u32
+}
+
+impl Camera {
+    // renders like render, showing finished rows in the window. if the window is closed
+    // before the render is done, the partial canvas is returned
+    pub fn render_with_preview(&self, world: &World, window: &mut PreviewWindow) -> Canvas {
+        let mut last_refresh = Instant::now();
+        let image = self.render_with(world, &CancellationToken::new(), |canvas, _| {
+            if last_refresh.elapsed() >= REFRESH_INTERVAL {
+                last_refresh = Instant::now();
+                if window.show(canvas).is_err() {
+                    return false;
+                }
+            }
+            window.is_open()
+        });
+        if window.is_open() {
+            // errors only mean that the window is gone, the image is still complete
+            let _ = window.show(&image);
+        }
+        image
+    }
+}
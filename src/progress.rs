@@ -0,0 +1,140 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+
+use crate::math::Float;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Progress {
+    pub rows_done: usize,
+    pub rows_total: usize,
+    pub elapsed: Duration,
+}
+
+impl Progress {
+    pub fn fraction(&self) -> Float {
+        if self.rows_total == 0 {
+            return 1.0;
+        }
+        self.rows_done as Float / self.rows_total as Float
+    }
+
+    // estimated time until the render is done, assuming the remaining rows take as long as the finished ones
+    pub fn eta(&self) -> Option<Duration> {
+        if self.rows_done == 0 {
+            return None;
+        }
+        let remaining = self.rows_total - self.rows_done;
+        Some(
+            self.elapsed
+                .mul_f64(remaining as f64 / self.rows_done as f64),
+        )
+    }
+}
+
+// shared flags to abort or pause a running render from another thread, clones refer to the same
+// flags
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+    paused: Arc<(Mutex<bool>, Condvar)>,
+}
+
+impl CancellationToken {
+    pub fn new() -> CancellationToken {
+        CancellationToken::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+        // paused workers have to wake up to notice
+        let (flag, changed) = &*self.paused;
+        let _paused = flag.lock().expect("pause flag poisoned");
+        changed.notify_all();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    // the workers finish the rows they are on and wait before they start the next ones
+    pub fn pause(&self) {
+        self.set_paused(true);
+    }
+
+    pub fn resume(&self) {
+        self.set_paused(false);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        *self.paused.0.lock().expect("pause flag poisoned")
+    }
+
+    fn set_paused(&self, paused: bool) {
+        let (flag, changed) = &*self.paused;
+        *flag.lock().expect("pause flag poisoned") = paused;
+        changed.notify_all();
+    }
+
+    // blocks while the render is paused, cancelling ends the wait
+    pub(crate) fn wait_while_paused(&self) {
+        let (flag, changed) = &*self.paused;
+        let mut paused = flag.lock().expect("pause flag poisoned");
+        while *paused && !self.is_cancelled() {
+            paused = changed.wait(paused).expect("pause flag poisoned");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn eta_scales_elapsed_time() {
+        let progress = Progress {
+            rows_done: 25,
+            rows_total: 100,
+            elapsed: Duration::from_secs(10),
+        };
+        assert_eq!(progress.fraction(), 0.25);
+        assert_eq!(progress.eta(), Some(Duration::from_secs(30)));
+    }
+
+    #[test]
+    fn no_eta_before_first_row() {
+        let progress = Progress {
+            rows_done: 0,
+            rows_total: 100,
+            elapsed: Duration::from_secs(1),
+        };
+        assert_eq!(progress.eta(), None);
+    }
+
+    #[test]
+    fn cloned_tokens_share_state() {
+        let token = CancellationToken::new();
+        let clone = token.clone();
+        assert!(!token.is_cancelled());
+        clone.cancel();
+        assert!(token.is_cancelled());
+    }
+
+    #[test]
+    fn paused_workers_wait_for_resume_or_cancel() {
+        let token = CancellationToken::new();
+        token.pause();
+        assert!(token.is_paused());
+        let waiting = {
+            let token = token.clone();
+            std::thread::spawn(move || token.wait_while_paused())
+        };
+        token.resume();
+        waiting.join().unwrap();
+        token.pause();
+        token.cancel();
+        // returns at once, the render is over
+        token.wait_while_paused();
+        assert!(token.is_paused());
+    }
+}
@@ -0,0 +1,123 @@
+use crate::math::{Float, Matrix4, Tuple4D};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ray {
+    pub origin: Tuple4D,
+    pub direction: Tuple4D,
+    // point in time within the exposure, from 0 to 1, used for motion blur
+    pub time: Float,
+    // the ray stands for a cone that is footprint wide at the origin and grows by spread per unit
+    // distance, it is used to pick how much textures are filtered
+    pub footprint: Float,
+    pub spread: Float,
+}
+
+impl Ray {
+    pub fn new(origin: Tuple4D, direction: Tuple4D) -> Ray {
+        Ray {
+            origin,
+            direction,
+            time: 0.0,
+            footprint: 0.0,
+            spread: 0.0,
+        }
+    }
+
+    pub fn with_time(self, time: Float) -> Ray {
+        Ray { time, ..self }
+    }
+
+    pub fn with_cone(self, footprint: Float, spread: Float) -> Ray {
+        Ray {
+            footprint,
+            spread,
+            ..self
+        }
+    }
+
+    // width of the ray cone at distance t
+    pub fn footprint_at(&self, t: Float) -> Float {
+        self.footprint + self.spread * t.abs()
+    }
+
+    pub fn position(&self, t: Float) -> Tuple4D {
+        self.origin + self.direction * t
+    }
+
+    pub fn transform(&self, transformation: Matrix4) -> Ray {
+        Ray {
+            origin: transformation * self.origin,
+            direction: transformation * self.direction,
+            ..*self
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn creating_ray() {
+        let origin = Tuple4D::new_point(1.0, 2.0, 3.0);
+        let direction = Tuple4D::new_vector(4.0, 5.0, 6.0);
+        let ray = Ray::new(origin, direction);
+        assert_eq!(ray.origin, origin);
+        assert_eq!(ray.direction, direction);
+    }
+
+    #[test]
+    fn computing_point_from_distance() {
+        let ray = Ray::new(
+            Tuple4D::new_point(2.0, 3.0, 4.0),
+            Tuple4D::new_vector(1.0, 0.0, 0.0),
+        );
+        assert_eq!(ray.position(0.0), Tuple4D::new_point(2.0, 3.0, 4.0));
+        assert_eq!(ray.position(1.0), Tuple4D::new_point(3.0, 3.0, 4.0));
+        assert_eq!(ray.position(-1.0), Tuple4D::new_point(1.0, 3.0, 4.0));
+        assert_eq!(ray.position(2.5), Tuple4D::new_point(4.5, 3.0, 4.0));
+    }
+
+    #[test]
+    fn translating_ray() {
+        let ray = Ray::new(
+            Tuple4D::new_point(1.0, 2.0, 3.0),
+            Tuple4D::new_vector(0.0, 1.0, 0.0),
+        );
+        let transformed = ray.transform(Matrix4::translation(3.0, 4.0, 5.0));
+        assert_eq!(transformed.origin, Tuple4D::new_point(4.0, 6.0, 8.0));
+        assert_eq!(transformed.direction, Tuple4D::new_vector(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn scaling_ray() {
+        let ray = Ray::new(
+            Tuple4D::new_point(1.0, 2.0, 3.0),
+            Tuple4D::new_vector(0.0, 1.0, 0.0),
+        );
+        let transformed = ray.transform(Matrix4::scaling(2.0, 3.0, 4.0));
+        assert_eq!(transformed.origin, Tuple4D::new_point(2.0, 6.0, 12.0));
+        assert_eq!(transformed.direction, Tuple4D::new_vector(0.0, 3.0, 0.0));
+    }
+
+    #[test]
+    fn transforming_keeps_time() {
+        let ray = Ray::new(
+            Tuple4D::new_point(1.0, 2.0, 3.0),
+            Tuple4D::new_vector(0.0, 1.0, 0.0),
+        )
+        .with_time(0.3);
+        assert_eq!(ray.transform(Matrix4::translation(3.0, 4.0, 5.0)).time, 0.3);
+    }
+
+    #[test]
+    fn ray_cone_grows_with_distance() {
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 0.0, 0.0),
+            Tuple4D::new_vector(0.0, 0.0, 1.0),
+        )
+        .with_cone(0.5, 0.1);
+        assert_eq!(ray.footprint_at(0.0), 0.5);
+        assert_eq!(ray.footprint_at(10.0), 1.5);
+    }
+}
@@ -0,0 +1,285 @@
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::task::{Context, Poll, Waker};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::{Camera, CancellationToken, Canvas, Progress, Region, World};
+
+// what the render thread shares with the job
+#[derive(Debug)]
+struct JobState {
+    image: Canvas,
+    progress: Progress,
+    finished: bool,
+    // what the render thread panicked with, handed to whoever waits for the job
+    panic: Option<Box<dyn std::any::Any + Send>>,
+    // the task that awaits the job, woken after every row
+    waker: Option<Waker>,
+}
+
+// a render running on a background thread, for applications with an event loop that can not block
+// on Camera::render. The image can be looked at while it is filled row by row, and the job can be
+// polled, awaited or waited for. Dropping an unfinished job cancels it. A panic of the render is
+// caught, it finishes the job and is returned as the error of wait and poll
+#[derive(Debug)]
+pub struct RenderJob {
+    state: Arc<Mutex<JobState>>,
+    token: CancellationToken,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Camera {
+    // renders a copy of the camera on a new thread, see RenderJob
+    pub fn render_job(&self, world: Arc<World>) -> RenderJob {
+        let camera = self.clone();
+        let token = CancellationToken::new();
+        let state = Arc::new(Mutex::new(JobState {
+            image: Canvas::create_canvas(self.hsize(), self.vsize()),
+            progress: Progress {
+                rows_done: 0,
+                rows_total: self.vsize(),
+                elapsed: Duration::ZERO,
+            },
+            finished: false,
+            panic: None,
+            waker: None,
+        }));
+        let thread = {
+            let (state, token) = (state.clone(), token.clone());
+            thread::spawn(move || {
+                let start = Instant::now();
+                let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                    camera.render_with(&world, &token, |image, y| {
+                        // only the finished row is copied, the rest of the image did not change
+                        let row = image.crop(Region::new(0, y, image.width(), 1));
+                        let mut state = lock(&state);
+                        state.image.paste(0, y, &row);
+                        state.progress.rows_done += 1;
+                        state.progress.elapsed = start.elapsed();
+                        if let Some(waker) = &state.waker {
+                            waker.wake_by_ref();
+                        }
+                        true
+                    })
+                }));
+                let mut state = lock(&state);
+                state.finished = true;
+                state.panic = result.err();
+                if let Some(waker) = state.waker.take() {
+                    waker.wake();
+                }
+            })
+        };
+        RenderJob {
+            state,
+            token,
+            thread: Some(thread),
+        }
+    }
+}
+
+impl RenderJob {
+    pub fn progress(&self) -> Progress {
+        self.lock().progress
+    }
+
+    // true once every row is rendered or the job stopped after being cancelled or a panic
+    pub fn is_finished(&self) -> bool {
+        self.lock().finished
+    }
+
+    // copy of the image so far, the rows that are not rendered yet are black
+    pub fn partial_image(&self) -> Canvas {
+        self.lock().image.clone()
+    }
+
+    // the rows that are being rendered are finished, no new ones are started until resume
+    pub fn pause(&self) {
+        self.token.pause();
+    }
+
+    pub fn resume(&self) {
+        self.token.resume();
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.token.is_paused()
+    }
+
+    // stops the render, waiting for the job gives the rows that were finished
+    pub fn cancel(&self) {
+        self.token.cancel();
+    }
+
+    // blocks until the job is finished, fails with the payload of the panic if the render panicked
+    pub fn wait(mut self) -> thread::Result<Canvas> {
+        self.join();
+        self.take_result()
+    }
+
+    fn join(&mut self) {
+        if let Some(thread) = self.thread.take() {
+            // the render thread catches the panics of the render, there is nothing left to report
+            let _ = thread.join();
+        }
+    }
+
+    fn take_result(&self) -> thread::Result<Canvas> {
+        let mut state = self.lock();
+        match state.panic.take() {
+            Some(payload) => Err(payload),
+            None => Ok(std::mem::replace(
+                &mut state.image,
+                Canvas::create_canvas(0, 0),
+            )),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, JobState> {
+        lock(&self.state)
+    }
+}
+
+// the state is still usable after a panic while it was locked, the panic is reported on its own
+fn lock(state: &Mutex<JobState>) -> std::sync::MutexGuard<'_, JobState> {
+    state.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+impl Future for RenderJob {
+    type Output = thread::Result<Canvas>;
+
+    fn poll(mut self: Pin<&mut Self>, context: &mut Context<'_>) -> Poll<Self::Output> {
+        {
+            let mut state = self.lock();
+            if !state.finished {
+                state.waker = Some(context.waker().clone());
+                return Poll::Pending;
+            }
+        }
+        // the thread is done once it marked the job as finished
+        self.join();
+        Poll::Ready(self.take_result())
+    }
+}
+
+impl Drop for RenderJob {
+    fn drop(&mut self) {
+        if self.thread.is_some() {
+            self.token.cancel();
+            self.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::task::Wake;
+    use std::thread::Thread;
+
+    use super::*;
+    use crate::math::{Float, Tuple4D};
+    use crate::shapes::Shape;
+    use crate::{scenes, Bounds, Object, Ray};
+
+    // unparks the thread that polls, the smallest executor for a single future
+    struct Unpark(Thread);
+
+    impl Wake for Unpark {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    fn block_on<F: Future + Unpin>(mut future: F) -> F::Output {
+        let waker = Waker::from(Arc::new(Unpark(thread::current())));
+        let mut context = Context::from_waker(&waker);
+        loop {
+            if let Poll::Ready(output) = Pin::new(&mut future).poll(&mut context) {
+                return output;
+            }
+            thread::park();
+        }
+    }
+
+    fn pixels(canvas: &Canvas) -> Vec<crate::Color> {
+        canvas.iter().copied().collect()
+    }
+
+    #[test]
+    fn jobs_render_the_same_image() {
+        let (world, camera) = scenes::three_spheres(24, 16);
+        let world = Arc::new(world);
+        let expected = pixels(&camera.render(&world));
+        let image = camera.render_job(world.clone()).wait().unwrap();
+        assert_eq!(pixels(&image), expected);
+        let job = camera.render_job(world);
+        let image = block_on(job).unwrap();
+        assert_eq!(pixels(&image), expected);
+    }
+
+    #[test]
+    fn paused_jobs_do_not_start_new_rows() {
+        // far more rows than can be rendered before the job is paused
+        let (world, mut camera) = scenes::three_spheres(100, 200);
+        camera.threads = 2;
+        let job = camera.render_job(Arc::new(world));
+        job.pause();
+        assert!(job.is_paused());
+        // the rows that were already started are finished
+        thread::sleep(Duration::from_millis(100));
+        let paused = job.progress().rows_done;
+        thread::sleep(Duration::from_millis(100));
+        assert_eq!(job.progress().rows_done, paused);
+        assert!(!job.is_finished());
+        job.resume();
+        let image = job.wait().unwrap();
+        assert_eq!(image.height(), 200);
+    }
+
+    #[test]
+    fn cancelled_jobs_keep_the_finished_rows() {
+        let (world, camera) = scenes::three_spheres(100, 200);
+        let job = camera.render_job(Arc::new(world));
+        job.pause();
+        // cancelling wakes the paused workers
+        job.cancel();
+        while !job.is_finished() {
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert!(job.progress().rows_done < 200);
+        assert_eq!(job.wait().unwrap().width(), 100);
+    }
+
+    #[derive(Debug)]
+    struct Panicking;
+
+    impl Shape for Panicking {
+        fn local_intersect(&self, _ray: &Ray) -> Vec<Float> {
+            panic!("broken shape")
+        }
+
+        fn local_normal_at(&self, point: Tuple4D) -> Tuple4D {
+            point - Tuple4D::new_point(0.0, 0.0, 0.0)
+        }
+
+        fn bounds(&self) -> Bounds {
+            Bounds::new(
+                Tuple4D::new_point(-1.0, -1.0, -1.0),
+                Tuple4D::new_point(1.0, 1.0, 1.0),
+            )
+        }
+    }
+
+    #[test]
+    fn panics_of_the_render_finish_the_job() {
+        let (mut world, camera) = scenes::three_spheres(8, 8);
+        world.objects.push(Object::new(Box::new(Panicking)));
+        let world = Arc::new(world);
+        let job = camera.render_job(world.clone());
+        assert!(job.wait().is_err());
+        assert!(block_on(camera.render_job(world)).is_err());
+    }
+}
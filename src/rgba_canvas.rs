@@ -0,0 +1,159 @@
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+
+use crate::canvas::Vec2D;
+use crate::math::Float;
+use crate::{Canvas, Color, ImageOptions};
+
+// a canvas with the coverage of every pixel, from 0 where nothing was hit to 1 where the pixel is
+// fully covered by objects. The colors are not premultiplied with the alpha, so pixels at the edges
+// of objects keep the color of the objects
+#[derive(Debug, Clone)]
+pub struct RgbaCanvas {
+    pub color: Canvas,
+    pub alpha: Vec2D<Float>,
+}
+
+impl RgbaCanvas {
+    // transparent black
+    pub fn new(width: usize, height: usize) -> RgbaCanvas {
+        RgbaCanvas {
+            color: Canvas::create_canvas(width, height),
+            alpha: Vec2D::new(width, height, 0.0),
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.color.width()
+    }
+
+    pub fn height(&self) -> usize {
+        self.color.height()
+    }
+
+    pub fn write_pixel(&mut self, x: usize, y: usize, color: Color, alpha: Float) {
+        self.color.write_pixel(x, y, color);
+        self.alpha.write_pixel(x, y, alpha);
+    }
+
+    pub fn read_pixel(&self, x: usize, y: usize) -> (Color, Float) {
+        (self.color.read_pixel(x, y), self.alpha.read_pixel(x, y))
+    }
+
+    // composited over a background of a single color
+    pub fn over(&self, background: Color) -> Canvas {
+        let mut canvas = Canvas::create_canvas(self.width(), self.height());
+        for y in 0..self.height() {
+            for x in 0..self.width() {
+                let (color, alpha) = self.read_pixel(x, y);
+                canvas.write_pixel(x, y, color * alpha + background * (1.0 - alpha));
+            }
+        }
+        canvas
+    }
+
+    pub fn save(&self, filename: &str) -> std::io::Result<()> {
+        self.save_with_options(filename, &ImageOptions::default())
+    }
+
+    // only png stores the alpha channel, other formats are refused instead of dropping it
+    #[cfg_attr(not(feature = "png"), allow(unused_variables))]
+    pub fn save_with_options(&self, filename: &str, options: &ImageOptions) -> std::io::Result<()> {
+        let extension = Path::new(filename)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_ascii_lowercase());
+        match extension.as_deref() {
+            #[cfg(feature = "png")]
+            Some("png") => {
+                let image = RgbaCanvas {
+                    color: self.color.encode(options.encoding),
+                    alpha: self.alpha.clone(),
+                };
+                let file = std::fs::File::create(filename)?;
+                let depth = if options.sixteen_bit {
+                    png::BitDepth::Sixteen
+                } else {
+                    png::BitDepth::Eight
+                };
+                image.write_png(std::io::BufWriter::new(file), depth)
+            }
+            _ => Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("no alpha channel support for the image format of {filename}"),
+            )),
+        }
+    }
+
+    // the values are written as they are, save encodes them first
+    #[cfg(feature = "png")]
+    pub fn to_png(&self, filename: &str) -> std::io::Result<()> {
+        let file = std::fs::File::create(filename)?;
+        self.write_png(std::io::BufWriter::new(file), png::BitDepth::Eight)
+    }
+
+    #[cfg(feature = "png")]
+    fn write_png<W: std::io::Write>(&self, writer: W, depth: png::BitDepth) -> std::io::Result<()> {
+        let mut data = vec![];
+        for y in 0..self.height() {
+            for x in 0..self.width() {
+                let (color, alpha) = self.read_pixel(x, y);
+                let alpha = alpha.clamp(0.0, 1.0);
+                if depth == png::BitDepth::Sixteen {
+                    let alpha = (alpha * 65535.0).round() as u16;
+                    for value in color.to_rgb16().into_iter().chain([alpha]) {
+                        data.extend(value.to_be_bytes());
+                    }
+                } else {
+                    let alpha = (alpha * 255.0).round() as u8;
+                    data.extend(color.to_rgb8().into_iter().chain([alpha]));
+                }
+            }
+        }
+        let size = (self.width(), self.height());
+        crate::canvas::write_png(writer, size, png::ColorType::Rgba, depth, &data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_canvas_is_transparent() {
+        let canvas = RgbaCanvas::new(4, 3);
+        assert_eq!(canvas.read_pixel(3, 2), (Color::new(0.0, 0.0, 0.0), 0.0));
+        assert_eq!((canvas.width(), canvas.height()), (4, 3));
+    }
+
+    #[test]
+    fn compositing_over_background() {
+        let mut canvas = RgbaCanvas::new(2, 1);
+        canvas.write_pixel(0, 0, Color::new(1.0, 0.0, 0.0), 0.25);
+        let composite = canvas.over(Color::new(0.0, 0.0, 1.0));
+        assert_eq!(composite.read_pixel(0, 0), Color::new(0.25, 0.0, 0.75));
+        assert_eq!(composite.read_pixel(1, 0), Color::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn saving_without_alpha_support() {
+        let canvas = RgbaCanvas::new(1, 1);
+        let err = canvas.save("image.ppm").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    }
+
+    #[cfg(feature = "png")]
+    #[test]
+    fn png_with_alpha() {
+        let mut canvas = RgbaCanvas::new(2, 1);
+        canvas.write_pixel(1, 0, Color::new(1.0, 0.5, 0.0), 0.5);
+        let mut bytes = vec![];
+        canvas.write_png(&mut bytes, png::BitDepth::Eight).unwrap();
+        let decoder = png::Decoder::new(std::io::Cursor::new(bytes));
+        let mut reader = decoder.read_info().unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size().unwrap()];
+        let info = reader.next_frame(&mut pixels).unwrap();
+        assert_eq!(info.color_type, png::ColorType::Rgba);
+        assert_eq!(pixels, [0, 0, 0, 0, 255, 128, 0, 128]);
+    }
+}
@@ -0,0 +1,259 @@
+use std::fmt::Debug;
+
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+
+use crate::math::{float_bits, Float, Tuple4D};
+
+// what a sample is used for, every use gets its own independent sequence
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SampleDimension {
+    Pixel,
+    Lens,
+    Light,
+    // only the first coordinate is used, it is the time within the exposure
+    Time,
+}
+
+// produces the 2d sample points in [0, 1) x [0, 1) that are used to place rays inside a pixel, on the
+// lens or on a light. samples only depend on their arguments, so renders are deterministic no matter
+// how the work is split between threads
+pub trait Sampler: Debug + Send + Sync {
+    fn sample_2d(
+        &self,
+        pixel: (usize, usize),
+        index: usize,
+        count: usize,
+        dimension: SampleDimension,
+    ) -> (Float, Float);
+}
+
+// independent uniform samples
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RandomSampler {
+    pub seed: u64,
+}
+
+impl RandomSampler {
+    pub fn new(seed: u64) -> RandomSampler {
+        RandomSampler { seed }
+    }
+}
+
+impl Sampler for RandomSampler {
+    fn sample_2d(
+        &self,
+        pixel: (usize, usize),
+        index: usize,
+        _count: usize,
+        dimension: SampleDimension,
+    ) -> (Float, Float) {
+        let mut rng = rng_for(self.seed, pixel, index, dimension);
+        (rng.random(), rng.random())
+    }
+}
+
+// splits the square into a grid with one jittered sample per cell, samples beyond the largest
+// square grid that fits into count fall back to uniform ones
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StratifiedSampler {
+    pub seed: u64,
+}
+
+impl StratifiedSampler {
+    pub fn new(seed: u64) -> StratifiedSampler {
+        StratifiedSampler { seed }
+    }
+}
+
+impl Sampler for StratifiedSampler {
+    fn sample_2d(
+        &self,
+        pixel: (usize, usize),
+        index: usize,
+        count: usize,
+        dimension: SampleDimension,
+    ) -> (Float, Float) {
+        let mut rng = rng_for(self.seed, pixel, index, dimension);
+        let (jitter_x, jitter_y): (Float, Float) = (rng.random(), rng.random());
+        let cells = count.isqrt();
+        if index >= cells * cells {
+            return (jitter_x, jitter_y);
+        }
+        let (cell_x, cell_y) = (index % cells, index / cells);
+        (
+            (cell_x as Float + jitter_x) / cells as Float,
+            (cell_y as Float + jitter_y) / cells as Float,
+        )
+    }
+}
+
+// low discrepancy halton sequence, randomly shifted per pixel so neighbouring pixels do not
+// share the same pattern
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HaltonSampler {
+    pub seed: u64,
+}
+
+impl HaltonSampler {
+    pub fn new(seed: u64) -> HaltonSampler {
+        HaltonSampler { seed }
+    }
+}
+
+impl Sampler for HaltonSampler {
+    fn sample_2d(
+        &self,
+        pixel: (usize, usize),
+        index: usize,
+        _count: usize,
+        dimension: SampleDimension,
+    ) -> (Float, Float) {
+        const BASES: [(u64, u64); 4] = [(2, 3), (5, 7), (11, 13), (17, 19)];
+        let (base_x, base_y) = BASES[dimension as usize];
+        let mut rng = rng_for(self.seed, pixel, 0, dimension);
+        let (shift_x, shift_y): (Float, Float) = (rng.random(), rng.random());
+        // index 0 of every halton sequence is 0, it is skipped
+        let index = index as u64 + 1;
+        (
+            (radical_inverse(index, base_x) + shift_x).fract(),
+            (radical_inverse(index, base_y) + shift_y).fract(),
+        )
+    }
+}
+
+// the r2 sequence offset by interleaved gradient noise. the offsets of neighbouring pixels differ as much
+// as possible, which spreads the error as high frequency noise in screen space similar to blue noise
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BlueNoiseSampler;
+
+impl Sampler for BlueNoiseSampler {
+    fn sample_2d(
+        &self,
+        pixel: (usize, usize),
+        index: usize,
+        _count: usize,
+        dimension: SampleDimension,
+    ) -> (Float, Float) {
+        // inverse of the plastic number and its square
+        const R2_X: Float = 0.754_877_666_246_692_8;
+        const R2_Y: Float = 0.569_840_290_998_053_2;
+        let (x, y) = (pixel.0 as Float, pixel.1 as Float);
+        let dimension_offset = dimension as usize as Float * 17.0;
+        let noise = interleaved_gradient_noise(x + dimension_offset, y);
+        let noise_2 = interleaved_gradient_noise(y + dimension_offset, x + 5.0);
+        let index = index as Float;
+        (
+            (noise + index * R2_X).fract(),
+            (noise_2 + index * R2_Y).fract(),
+        )
+    }
+}
+
+fn interleaved_gradient_noise(x: Float, y: Float) -> Float {
+    (52.982_918 * (0.067_110_56 * x + 0.005_837_15 * y).fract()).fract()
+}
+
+fn radical_inverse(mut index: u64, base: u64) -> Float {
+    let inverse_base = 1.0 / base as Float;
+    let mut factor = inverse_base;
+    let mut result = 0.0;
+    while index > 0 {
+        result += (index % base) as Float * factor;
+        index /= base;
+        factor *= inverse_base;
+    }
+    result
+}
+
+// random number generator for the sample index of a pixel, e.g. for the bounces of a path
+pub(crate) fn rng_for(
+    seed: u64,
+    pixel: (usize, usize),
+    index: usize,
+    dimension: SampleDimension,
+) -> SmallRng {
+    let mut hash = seed;
+    for value in [pixel.0, pixel.1, index, dimension as usize] {
+        hash = splitmix64(hash ^ value as u64);
+    }
+    SmallRng::seed_from_u64(hash)
+}
+
+// random number generator that only depends on the position, used where no pixel is known
+pub(crate) fn rng_for_point(point: Tuple4D) -> SmallRng {
+    let mut hash = 0;
+    for value in [point.x, point.y, point.z] {
+        hash = splitmix64(hash ^ float_bits(value));
+    }
+    SmallRng::seed_from_u64(hash)
+}
+
+fn splitmix64(value: u64) -> u64 {
+    let mut z = value.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn all_samplers() -> Vec<Box<dyn Sampler>> {
+        vec![
+            Box::new(RandomSampler::new(1)),
+            Box::new(StratifiedSampler::new(1)),
+            Box::new(HaltonSampler::new(1)),
+            Box::new(BlueNoiseSampler),
+        ]
+    }
+
+    #[test]
+    fn samples_lie_in_unit_square() {
+        for sampler in all_samplers() {
+            for index in 0..50 {
+                let (x, y) = sampler.sample_2d((3, 8), index, 50, SampleDimension::Pixel);
+                assert!((0.0..1.0).contains(&x) && (0.0..1.0).contains(&y));
+            }
+        }
+    }
+
+    #[test]
+    fn samples_are_deterministic() {
+        for sampler in all_samplers() {
+            assert_eq!(
+                sampler.sample_2d((4, 2), 3, 16, SampleDimension::Lens),
+                sampler.sample_2d((4, 2), 3, 16, SampleDimension::Lens)
+            );
+        }
+    }
+
+    #[test]
+    fn stratified_samples_fill_every_cell() {
+        let sampler = StratifiedSampler::new(7);
+        let mut cells = [false; 16];
+        for index in 0..16 {
+            let (x, y) = sampler.sample_2d((0, 0), index, 16, SampleDimension::Pixel);
+            cells[(y * 4.0) as usize * 4 + (x * 4.0) as usize] = true;
+        }
+        assert!(cells.iter().all(|&cell| cell));
+    }
+
+    #[test]
+    #[cfg_attr(feature = "f32", ignore = "exact only in double precision")]
+    fn radical_inverse_mirrors_digits() {
+        assert_eq!(radical_inverse(1, 2), 0.5);
+        assert_eq!(radical_inverse(3, 2), 0.75);
+        assert_eq!(radical_inverse(4, 3), 1.0 / 3.0 + 1.0 / 9.0);
+    }
+
+    #[test]
+    fn dimensions_are_independent() {
+        let sampler = RandomSampler::new(0);
+        assert_ne!(
+            sampler.sample_2d((1, 1), 0, 1, SampleDimension::Pixel),
+            sampler.sample_2d((1, 1), 0, 1, SampleDimension::Light)
+        );
+    }
+}
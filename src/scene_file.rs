@@ -0,0 +1,1092 @@
+use std::any::Any;
+use std::collections::BTreeMap;
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::math::{Float, Matrix4, Tuple4D, FLOAT_EQ_EPS};
+use crate::shapes::{Cube, Group, Plane, Quadric, Sphere, Torus, Triangle};
+use crate::{
+    AreaLight, Attenuation, Background, Bounds, BvhCache, Camera, Color, DirectionalLight,
+    Integrator, Light, Material, ObjFile, Object, PlyFile, PointLight, SpotLight, StlFile, World,
+    DEFAULT_BVH_LEAF_SIZE, DEFAULT_GLOSSY_SAMPLES, DEFAULT_MAX_DEPTH,
+};
+
+// scene in a plain data form that is read from and written to json or toml files. Points, vectors
+// and colors are arrays of three numbers, angles are in radians. Everything but the camera and the
+// shapes of the objects can be left out and gets the same default as in code
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SceneFile {
+    pub camera: CameraDescription,
+    // more views of the same scene by name, for rendering several shots of it at once
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub cameras: BTreeMap<String, CameraDescription>,
+    #[serde(default)]
+    pub background: BackgroundDescription,
+    #[serde(default = "default_max_depth")]
+    pub max_depth: usize,
+    #[serde(default = "default_glossy_samples")]
+    pub glossy_samples: usize,
+    #[serde(default)]
+    pub lights: Vec<LightDescription>,
+    #[serde(default)]
+    pub objects: Vec<ObjectDescription>,
+    // directory relative to the scene file where the bounding volume hierarchies of meshes are
+    // stored, so they are only built the first time a mesh is loaded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bvh_cache: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CameraDescription {
+    pub width: usize,
+    pub height: usize,
+    pub field_of_view: Float,
+    pub view: ViewDescription,
+    #[serde(default = "default_one")]
+    pub samples_per_pixel: usize,
+    #[serde(default)]
+    pub aperture_radius: Float,
+    #[serde(default = "default_one_float")]
+    pub focal_distance: Float,
+    #[serde(default)]
+    pub integrator: IntegratorDescription,
+}
+
+// either the points the camera looks from and at, or the view transform itself
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ViewDescription {
+    LookAt {
+        from: [Float; 3],
+        to: [Float; 3],
+        up: [Float; 3],
+    },
+    Matrix([[Float; 4]; 4]),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum IntegratorDescription {
+    #[default]
+    Whitted,
+    PathTracing {
+        max_bounces: usize,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        indirect_clamp: Option<Float>,
+    },
+    AmbientOcclusion {
+        samples: usize,
+        distance: Float,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BackgroundDescription {
+    Solid { color: [Float; 3] },
+    Gradient { bottom: [Float; 3], top: [Float; 3] },
+}
+
+impl Default for BackgroundDescription {
+    fn default() -> Self {
+        BackgroundDescription::Solid {
+            color: [0.0, 0.0, 0.0],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LightDescription {
+    Point {
+        position: [Float; 3],
+        intensity: [Float; 3],
+        #[serde(default)]
+        attenuation: AttenuationDescription,
+    },
+    Spot {
+        position: [Float; 3],
+        direction: [Float; 3],
+        cone_angle: Float,
+        falloff: Float,
+        intensity: [Float; 3],
+        #[serde(default)]
+        attenuation: AttenuationDescription,
+    },
+    Directional {
+        direction: [Float; 3],
+        intensity: [Float; 3],
+    },
+    Area {
+        corner: [Float; 3],
+        uvec: [Float; 3],
+        vvec: [Float; 3],
+        intensity: [Float; 3],
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AttenuationDescription {
+    #[default]
+    None,
+    Linear,
+    Quadratic,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ObjectDescription {
+    #[serde(flatten)]
+    pub shape: ShapeDescription,
+    // applied in order, the first transform in the list is applied first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub transform: Vec<TransformDescription>,
+    // transform at the end of the exposure for motion blur
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transform_end: Option<Vec<TransformDescription>>,
+    #[serde(default)]
+    pub material: MaterialDescription,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ShapeDescription {
+    Sphere,
+    Plane,
+    Cube,
+    Torus {
+        major_radius: Float,
+        minor_radius: Float,
+    },
+    Quadric {
+        coefficients: [Float; 10],
+        // min and max corner of the box the surface is clipped to, unbounded if left out
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        bounds: Option<[[Float; 3]; 2]>,
+    },
+    Triangle {
+        points: [[Float; 3]; 3],
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        normals: Option<[[Float; 3]; 3]>,
+    },
+    Group {
+        children: Vec<ObjectDescription>,
+    },
+    // obj, stl, ply or (with the gltf feature) gltf file, relative to the directory of the scene
+    // file
+    Mesh {
+        file: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransformDescription {
+    Translate([Float; 3]),
+    Scale([Float; 3]),
+    RotateX(Float),
+    RotateY(Float),
+    RotateZ(Float),
+    // radians counterclockwise around the axis, as in Matrix4::rotation_axis
+    RotateAxis {
+        axis: [Float; 3],
+        angle: Float,
+    },
+    // moves the object to from and points its z axis at to, as in Matrix4::look_at. Loading fails
+    // when from equals to or up is parallel to the direction between them
+    LookAt {
+        from: [Float; 3],
+        to: [Float; 3],
+        up: [Float; 3],
+    },
+    // x_y, x_z, y_x, y_z, z_x, z_y as in Matrix4::shearing
+    Shear([Float; 6]),
+    // row by row
+    Matrix([[Float; 4]; 4]),
+}
+
+// same fields and defaults as Material, patterns, normal maps and volumes can not be described
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MaterialDescription {
+    pub color: [Float; 3],
+    pub ambient: Float,
+    pub diffuse: Float,
+    pub specular: Float,
+    pub shininess: Float,
+    pub reflective: Float,
+    pub transparency: Float,
+    pub refractive_index: Float,
+    pub absorption: [Float; 3],
+    pub roughness: Float,
+    pub emissive: [Float; 3],
+}
+
+impl Default for MaterialDescription {
+    fn default() -> Self {
+        MaterialDescription::from_material(&Material::default())
+            .expect("the default material has no pattern")
+    }
+}
+
+impl SceneFile {
+    // the format is chosen by the extension, .json or .toml
+    pub fn from_file(filename: &str) -> std::io::Result<SceneFile> {
+        let text = std::fs::read_to_string(filename)?;
+        let scene = match extension(filename).as_str() {
+            "json" => parse_scene_json(&text),
+            "toml" => parse_scene_toml(&text),
+            other => Err(format!("unknown scene file extension '{other}'")),
+        };
+        scene.map_err(|err| Error::new(ErrorKind::InvalidData, format!("{filename}: {err}")))
+    }
+
+    pub fn to_file(&self, filename: &str) -> std::io::Result<()> {
+        let text = match extension(filename).as_str() {
+            "json" => self.to_json(),
+            "toml" => self.to_toml(),
+            other => Err(format!("unknown scene file extension '{other}'")),
+        };
+        let text = text.map_err(|err| Error::new(ErrorKind::InvalidData, err))?;
+        std::fs::write(filename, text)
+    }
+
+    pub fn to_json(&self) -> Result<String, String> {
+        serde_json::to_string_pretty(self).map_err(|err| err.to_string())
+    }
+
+    pub fn to_toml(&self) -> Result<String, String> {
+        toml::to_string_pretty(self).map_err(|err| err.to_string())
+    }
+
+    // loads the scene file and builds it, meshes are looked up next to it
+    pub fn load(filename: &str) -> std::io::Result<(World, Camera)> {
+        let scene = SceneFile::from_file(filename)?;
+        let directory = Path::new(filename).parent().unwrap_or(Path::new(""));
+        scene.build(directory)
+    }
+
+    // mesh files are looked up relative to directory
+    pub fn build(&self, directory: &Path) -> std::io::Result<(World, Camera)> {
+        Ok((self.build_world(directory)?, self.camera.build()?))
+    }
+
+    // the world without any camera, it can be rendered with all the cameras of the scene
+    pub fn build_world(&self, directory: &Path) -> std::io::Result<World> {
+        let bvh_cache = self
+            .bvh_cache
+            .as_ref()
+            .map(|cache| BvhCache::new(directory.join(cache)));
+        let objects = self
+            .objects
+            .iter()
+            .map(|object| object.build(directory, bvh_cache.as_ref()))
+            .collect::<std::io::Result<Vec<Object>>>()?;
+        let world = World {
+            objects,
+            lights: self.lights.iter().map(LightDescription::build).collect(),
+            background: self.background.build(),
+            max_depth: self.max_depth,
+            glossy_samples: self.glossy_samples,
+            ..World::default()
+        };
+        Ok(world)
+    }
+
+    // one of the named cameras
+    pub fn build_camera(&self, name: &str) -> Result<Camera, String> {
+        match self.cameras.get(name) {
+            Some(camera) => camera
+                .build()
+                .map_err(|err| format!("camera '{name}': {err}")),
+            None if self.cameras.is_empty() => {
+                Err(format!("the scene has no camera named '{name}'"))
+            }
+            None => Err(format!(
+                "the scene has no camera named '{name}', it has {}",
+                self.cameras
+                    .keys()
+                    .map(String::as_str)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )),
+        }
+    }
+
+    // the description of a built world, for saving scenes that were put together in code. Fails for
+    // everything that has no description, like patterns, volumes and shapes without parameters
+    // that can be written down
+    pub fn from_world(world: &World, camera: &Camera) -> Result<SceneFile, String> {
+        if world.fog.is_some() {
+            return Err("fog can not be saved".to_string());
+        }
+        Ok(SceneFile {
+            camera: CameraDescription::from_camera(camera),
+            cameras: BTreeMap::new(),
+            background: BackgroundDescription::from_background(&world.background)?,
+            max_depth: world.max_depth,
+            glossy_samples: world.glossy_samples,
+            lights: world
+                .lights
+                .iter()
+                .map(LightDescription::from_light)
+                .collect(),
+            objects: world
+                .objects
+                .iter()
+                .map(ObjectDescription::from_object)
+                .collect::<Result<_, _>>()?,
+            bvh_cache: None,
+        })
+    }
+}
+
+pub fn parse_scene_json(text: &str) -> Result<SceneFile, String> {
+    serde_json::from_str(text).map_err(|err| err.to_string())
+}
+
+pub fn parse_scene_toml(text: &str) -> Result<SceneFile, String> {
+    toml::from_str(text).map_err(|err| err.to_string())
+}
+
+impl CameraDescription {
+    // fails for views without an orientation, the camera could not turn pixels into rays
+    pub fn build(&self) -> std::io::Result<Camera> {
+        let mut camera = Camera::new(self.width, self.height, self.field_of_view);
+        let view = match self.view {
+            ViewDescription::LookAt { from, to, up } => {
+                let (from, to, up) = orientation("the camera view", from, to, up)?;
+                Matrix4::view_transform(from, to, up)
+            }
+            ViewDescription::Matrix(rows) => Matrix4::new(rows),
+        };
+        if !view.is_invertible() {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "the camera view can not be inverted",
+            ));
+        }
+        camera.transform = view.into();
+        camera.samples_per_pixel = self.samples_per_pixel;
+        camera.aperture_radius = self.aperture_radius;
+        camera.focal_distance = self.focal_distance;
+        camera.integrator = match self.integrator {
+            IntegratorDescription::Whitted => Integrator::Whitted,
+            IntegratorDescription::PathTracing {
+                max_bounces,
+                indirect_clamp,
+            } => Integrator::PathTracing {
+                max_bounces,
+                indirect_clamp,
+            },
+            IntegratorDescription::AmbientOcclusion { samples, distance } => {
+                Integrator::AmbientOcclusion { samples, distance }
+            }
+        };
+        Ok(camera)
+    }
+
+    fn from_camera(camera: &Camera) -> CameraDescription {
+        CameraDescription {
+            width: camera.hsize(),
+            height: camera.vsize(),
+            field_of_view: camera.field_of_view(),
+            view: ViewDescription::Matrix(camera.transform.matrix().into()),
+            samples_per_pixel: camera.samples_per_pixel,
+            aperture_radius: camera.aperture_radius,
+            focal_distance: camera.focal_distance,
+            integrator: match camera.integrator {
+                Integrator::Whitted => IntegratorDescription::Whitted,
+                Integrator::PathTracing {
+                    max_bounces,
+                    indirect_clamp,
+                } => IntegratorDescription::PathTracing {
+                    max_bounces,
+                    indirect_clamp,
+                },
+                Integrator::AmbientOcclusion { samples, distance } => {
+                    IntegratorDescription::AmbientOcclusion { samples, distance }
+                }
+            },
+        }
+    }
+}
+
+impl BackgroundDescription {
+    fn build(&self) -> Background {
+        match *self {
+            BackgroundDescription::Solid { color: rgb } => Background::Solid(color(rgb)),
+            BackgroundDescription::Gradient { bottom, top } => Background::Gradient {
+                bottom: color(bottom),
+                top: color(top),
+            },
+        }
+    }
+
+    fn from_background(background: &Background) -> Result<BackgroundDescription, String> {
+        match background {
+            Background::Solid(color) => Ok(BackgroundDescription::Solid { color: rgb(*color) }),
+            Background::Gradient { bottom, top } => Ok(BackgroundDescription::Gradient {
+                bottom: rgb(*bottom),
+                top: rgb(*top),
+            }),
+            Background::Environment(_) => Err("environment maps can not be saved".to_string()),
+        }
+    }
+}
+
+impl LightDescription {
+    fn build(&self) -> Light {
+        match *self {
+            LightDescription::Point {
+                position,
+                intensity,
+                attenuation,
+            } => PointLight {
+                attenuation: attenuation.build(),
+                ..PointLight::new(point(position), color(intensity))
+            }
+            .into(),
+            LightDescription::Spot {
+                position,
+                direction,
+                cone_angle,
+                falloff,
+                intensity,
+                attenuation,
+            } => SpotLight {
+                attenuation: attenuation.build(),
+                ..SpotLight::new(
+                    point(position),
+                    vector(direction),
+                    cone_angle,
+                    falloff,
+                    color(intensity),
+                )
+            }
+            .into(),
+            LightDescription::Directional {
+                direction,
+                intensity,
+            } => DirectionalLight::new(vector(direction), color(intensity)).into(),
+            LightDescription::Area {
+                corner,
+                uvec,
+                vvec,
+                intensity,
+            } => AreaLight::new(point(corner), vector(uvec), vector(vvec), color(intensity)).into(),
+        }
+    }
+
+    fn from_light(light: &Light) -> LightDescription {
+        match light {
+            Light::Point(light) => LightDescription::Point {
+                position: xyz(light.position),
+                intensity: rgb(light.intensity),
+                attenuation: AttenuationDescription::from_attenuation(light.attenuation),
+            },
+            Light::Spot(light) => LightDescription::Spot {
+                position: xyz(light.position),
+                direction: xyz(light.direction),
+                cone_angle: light.cone_angle,
+                falloff: light.falloff,
+                intensity: rgb(light.intensity),
+                attenuation: AttenuationDescription::from_attenuation(light.attenuation),
+            },
+            Light::Directional(light) => LightDescription::Directional {
+                direction: xyz(light.direction),
+                intensity: rgb(light.intensity),
+            },
+            Light::Area(light) => LightDescription::Area {
+                corner: xyz(light.corner),
+                uvec: xyz(light.uvec),
+                vvec: xyz(light.vvec),
+                intensity: rgb(light.intensity),
+            },
+        }
+    }
+}
+
+impl AttenuationDescription {
+    fn build(self) -> Attenuation {
+        match self {
+            AttenuationDescription::None => Attenuation::None,
+            AttenuationDescription::Linear => Attenuation::Linear,
+            AttenuationDescription::Quadratic => Attenuation::Quadratic,
+        }
+    }
+
+    fn from_attenuation(attenuation: Attenuation) -> AttenuationDescription {
+        match attenuation {
+            Attenuation::None => AttenuationDescription::None,
+            Attenuation::Linear => AttenuationDescription::Linear,
+            Attenuation::Quadratic => AttenuationDescription::Quadratic,
+        }
+    }
+}
+
+impl ObjectDescription {
+    fn build(&self, directory: &Path, bvh_cache: Option<&BvhCache>) -> std::io::Result<Object> {
+        let mut object = match &self.shape {
+            ShapeDescription::Sphere => Object::sphere(),
+            ShapeDescription::Plane => Object::plane(),
+            ShapeDescription::Cube => Object::cube(),
+            ShapeDescription::Torus {
+                major_radius,
+                minor_radius,
+            } => Object::torus(*major_radius, *minor_radius),
+            ShapeDescription::Quadric {
+                coefficients,
+                bounds,
+            } => {
+                let mut quadric = Quadric::new(*coefficients);
+                if let Some([min, max]) = bounds {
+                    quadric = quadric.with_bounds(Bounds::new(point(*min), point(*max)));
+                }
+                Object::new(Box::new(quadric))
+            }
+            ShapeDescription::Triangle { points, normals } => {
+                let [p1, p2, p3] = points.map(point);
+                let triangle = match normals {
+                    Some(normals) => {
+                        let [n1, n2, n3] = normals.map(vector);
+                        Triangle::smooth(p1, p2, p3, n1, n2, n3)
+                    }
+                    None => Triangle::new(p1, p2, p3),
+                };
+                Object::new(Box::new(triangle))
+            }
+            ShapeDescription::Group { children } => Object::group(
+                children
+                    .iter()
+                    .map(|child| child.build(directory, bvh_cache))
+                    .collect::<std::io::Result<_>>()?,
+            ),
+            ShapeDescription::Mesh { file } => {
+                let path = directory.join(file);
+                let mesh = load_mesh(&path)?;
+                match bvh_cache {
+                    Some(cache) => cache.load_or_build(&std::fs::read(&path)?, mesh)?,
+                    None => mesh.into_bvh(DEFAULT_BVH_LEAF_SIZE),
+                }
+            }
+        };
+        object.transform = transform_matrix(&self.transform)?.into();
+        object.transform_end = self
+            .transform_end
+            .as_deref()
+            .map(transform_matrix)
+            .transpose()?;
+        // meshes keep the materials of their file unless one is given
+        if !matches!(self.shape, ShapeDescription::Mesh { .. })
+            || self.material != MaterialDescription::default()
+        {
+            object.material = self.material.build();
+        }
+        Ok(object)
+    }
+
+    // groups are written out with their children, meshes loaded from files included
+    fn from_object(object: &Object) -> Result<ObjectDescription, String> {
+        let shape: &dyn Any = object.shape.as_ref();
+        let shape = if shape.is::<Sphere>() {
+            ShapeDescription::Sphere
+        } else if shape.is::<Plane>() {
+            ShapeDescription::Plane
+        } else if shape.is::<Cube>() {
+            ShapeDescription::Cube
+        } else if let Some(torus) = shape.downcast_ref::<Torus>() {
+            ShapeDescription::Torus {
+                major_radius: torus.major_radius,
+                minor_radius: torus.minor_radius,
+            }
+        } else if let Some(quadric) = shape.downcast_ref::<Quadric>() {
+            ShapeDescription::Quadric {
+                coefficients: quadric.coefficients,
+                bounds: (!quadric.bounds.is_infinite())
+                    .then(|| [xyz(quadric.bounds.min), xyz(quadric.bounds.max)]),
+            }
+        } else if let Some(triangle) = shape.downcast_ref::<Triangle>() {
+            ShapeDescription::Triangle {
+                points: [triangle.p1(), triangle.p2(), triangle.p3()].map(xyz),
+                normals: triangle.normals().map(|normals| normals.map(xyz)),
+            }
+        } else if let Some(group) = shape.downcast_ref::<Group>() {
+            ShapeDescription::Group {
+                children: group
+                    .children()
+                    .iter()
+                    .map(ObjectDescription::from_object)
+                    .collect::<Result<_, _>>()?,
+            }
+        } else {
+            return Err(format!("{:?} can not be saved", object.shape));
+        };
+        let transform = if object.transform.matrix() == Matrix4::eye() {
+            vec![]
+        } else {
+            vec![TransformDescription::Matrix(
+                object.transform.matrix().into(),
+            )]
+        };
+        Ok(ObjectDescription {
+            shape,
+            transform,
+            transform_end: object
+                .transform_end
+                .map(|transform| vec![TransformDescription::Matrix(transform.into())]),
+            material: MaterialDescription::from_material(&object.material)?,
+        })
+    }
+}
+
+impl TransformDescription {
+    fn matrix(&self) -> std::io::Result<Matrix4> {
+        let matrix = match *self {
+            TransformDescription::Translate([x, y, z]) => Matrix4::translation(x, y, z),
+            TransformDescription::Scale([x, y, z]) => Matrix4::scaling(x, y, z),
+            TransformDescription::RotateX(radians) => Matrix4::rotation_x(radians),
+            TransformDescription::RotateY(radians) => Matrix4::rotation_y(radians),
+            TransformDescription::RotateZ(radians) => Matrix4::rotation_z(radians),
+            TransformDescription::RotateAxis { axis, angle } => {
+                Matrix4::rotation_axis(vector(axis), angle)
+            }
+            TransformDescription::LookAt { from, to, up } => {
+                let (from, to, up) = orientation("look_at", from, to, up)?;
+                Matrix4::look_at(from, to, up)
+            }
+            TransformDescription::Shear([x_y, x_z, y_x, y_z, z_x, z_y]) => {
+                Matrix4::shearing(x_y, x_z, y_x, y_z, z_x, z_y)
+            }
+            TransformDescription::Matrix(values) => Matrix4::new(values),
+        };
+        Ok(matrix)
+    }
+}
+
+impl MaterialDescription {
+    fn build(&self) -> Material {
+        Material {
+            color: color(self.color),
+            ambient: self.ambient,
+            diffuse: self.diffuse,
+            specular: self.specular,
+            shininess: self.shininess,
+            reflective: self.reflective,
+            transparency: self.transparency,
+            refractive_index: self.refractive_index,
+            absorption: color(self.absorption),
+            roughness: self.roughness,
+            emissive: color(self.emissive),
+            ..Material::default()
+        }
+    }
+
+    fn from_material(material: &Material) -> Result<MaterialDescription, String> {
+        if material.pattern.is_some() || material.normal_map.is_some() || material.volume.is_some()
+        {
+            return Err("patterns, normal maps and volumes can not be saved".to_string());
+        }
+        Ok(MaterialDescription {
+            color: rgb(material.color),
+            ambient: material.ambient,
+            diffuse: material.diffuse,
+            specular: material.specular,
+            shininess: material.shininess,
+            reflective: material.reflective,
+            transparency: material.transparency,
+            refractive_index: material.refractive_index,
+            absorption: rgb(material.absorption),
+            roughness: material.roughness,
+            emissive: rgb(material.emissive),
+        })
+    }
+}
+
+fn load_mesh(path: &Path) -> std::io::Result<Object> {
+    let filename = path.to_string_lossy();
+    match extension(&filename).as_str() {
+        "obj" => Ok(ObjFile::from_file(&filename)?.into_group()),
+        "stl" => Ok(StlFile::from_file(&filename)?.into_group()),
+        "ply" => Ok(PlyFile::from_file(&filename)?.into_group()),
+        #[cfg(feature = "gltf")]
+        "gltf" | "glb" => crate::GltfFile::from_file(&filename)?.into_group(),
+        other => Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("unknown mesh file extension '{other}'"),
+        )),
+    }
+}
+
+// from, to and up as points and vector, if they describe an orientation. Without a direction, or
+// with an up vector along it, there is none
+fn orientation(
+    what: &str,
+    from: [Float; 3],
+    to: [Float; 3],
+    up: [Float; 3],
+) -> std::io::Result<(Tuple4D, Tuple4D, Tuple4D)> {
+    let (from, to, up) = (point(from), point(to), vector(up));
+    let forward = to - from;
+    if forward.magnitude() == 0.0
+        || up.cross(forward.normalize()).magnitude() <= FLOAT_EQ_EPS * up.magnitude()
+    {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "{what} needs from and to to differ and an up vector that is not parallel to \
+                 the direction between them"
+            ),
+        ));
+    }
+    Ok((from, to, up))
+}
+
+// the first transform of the list is applied first, so it is the rightmost factor. Rays are
+// moved into object space with the inverse, so a transform that squashes the object flat fails
+pub(crate) fn transform_matrix(transforms: &[TransformDescription]) -> std::io::Result<Matrix4> {
+    let matrix = transforms.iter().try_fold(
+        Matrix4::eye(),
+        |matrix, transform| -> std::io::Result<Matrix4> { Ok(transform.matrix()? * matrix) },
+    )?;
+    if !matrix.is_invertible() {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "the object transform can not be inverted",
+        ));
+    }
+    Ok(matrix)
+}
+
+fn extension(filename: &str) -> String {
+    Path::new(filename)
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+fn default_max_depth() -> usize {
+    DEFAULT_MAX_DEPTH
+}
+
+fn default_glossy_samples() -> usize {
+    DEFAULT_GLOSSY_SAMPLES
+}
+
+fn default_one() -> usize {
+    1
+}
+
+fn default_one_float() -> Float {
+    1.0
+}
+
+fn point([x, y, z]: [Float; 3]) -> Tuple4D {
+    Tuple4D::new_point(x, y, z)
+}
+
+fn vector([x, y, z]: [Float; 3]) -> Tuple4D {
+    Tuple4D::new_vector(x, y, z)
+}
+
+fn color([r, g, b]: [Float; 3]) -> Color {
+    Color::new(r, g, b)
+}
+
+fn xyz(tuple: Tuple4D) -> [Float; 3] {
+    [tuple.x, tuple.y, tuple.z]
+}
+
+fn rgb(color: Color) -> [Float; 3] {
+    [color.r, color.g, color.b]
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::consts::FRAC_PI_2;
+
+    use super::*;
+    use crate::{hit, scenes, Ray};
+
+    const SCENE_JSON: &str = r#"{
+        "camera": {
+            "width": 100,
+            "height": 50,
+            "field_of_view": 1.0,
+            "view": {"from": [0, 1.5, -5], "to": [0, 1, 0], "up": [0, 1, 0]}
+        },
+        "lights": [{"type": "point", "position": [-10, 10, -10], "intensity": [1, 1, 1]}],
+        "objects": [
+            {"type": "plane", "material": {"color": [1, 0.9, 0.9], "specular": 0}},
+            {
+                "type": "sphere",
+                "transform": [{"scale": [0.5, 0.5, 0.5]}, {"translate": [1.5, 0.5, -0.5]}],
+                "material": {"color": [0.5, 1, 0.1], "reflective": 0.5}
+            },
+            {
+                "type": "group",
+                "transform": [{"rotate_y": 1.5707963267948966}],
+                "children": [{"type": "torus", "major_radius": 1, "minor_radius": 0.25}]
+            }
+        ]
+    }"#;
+
+    const SCENE_TOML: &str = r#"
+        max_depth = 2
+
+        [camera]
+        width = 100
+        height = 50
+        field_of_view = 1.0
+        view = { from = [0, 1.5, -5], to = [0, 1, 0], up = [0, 1, 0] }
+        integrator = { type = "path_tracing", max_bounces = 4 }
+
+        [background]
+        type = "gradient"
+        bottom = [0.0, 0.0, 0.0]
+        top = [0.5, 0.7, 1.0]
+
+        [[lights]]
+        type = "area"
+        corner = [-1, 5, -1]
+        uvec = [2, 0, 0]
+        vvec = [0, 0, 2]
+        intensity = [1, 1, 1]
+
+        [[objects]]
+        type = "cube"
+        transform = [{ translate = [0, 1, 0] }]
+        material = { color = [0.2, 0.4, 0.8] }
+    "#;
+
+    #[test]
+    fn building_scene_from_json() {
+        let scene = parse_scene_json(SCENE_JSON).unwrap();
+        let (world, camera) = scene.build(Path::new("")).unwrap();
+        assert_eq!(camera.hsize(), 100);
+        assert_eq!(
+            camera.transform.matrix(),
+            Matrix4::view_transform(
+                Tuple4D::new_point(0.0, 1.5, -5.0),
+                Tuple4D::new_point(0.0, 1.0, 0.0),
+                Tuple4D::new_vector(0.0, 1.0, 0.0),
+            )
+        );
+        assert_eq!(world.lights.len(), 1);
+        assert_eq!(world.objects.len(), 3);
+        assert_eq!(world.objects[0].material.specular, 0.0);
+        // left out material values keep their defaults
+        assert_eq!(world.objects[0].material.diffuse, 0.9);
+        assert_eq!(
+            world.objects[1].transform.matrix(),
+            Matrix4::translation(1.5, 0.5, -0.5) * Matrix4::scaling(0.5, 0.5, 0.5)
+        );
+        assert_eq!(
+            world.objects[2].transform.matrix(),
+            Matrix4::rotation_y(FRAC_PI_2)
+        );
+    }
+
+    #[test]
+    fn building_scene_from_toml() {
+        let scene = parse_scene_toml(SCENE_TOML).unwrap();
+        let (world, camera) = scene.build(Path::new("")).unwrap();
+        assert_eq!(world.max_depth, 2);
+        assert!(matches!(world.lights[0], Light::Area(_)));
+        assert!(matches!(world.background, Background::Gradient { .. }));
+        assert_eq!(camera.integrator, Integrator::path_tracing(4));
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 5.0, 0.0),
+            Tuple4D::new_vector(0.0, -1.0, 0.0),
+        );
+        let xs = world.intersect_world(&ray);
+        assert_eq!(hit(&xs).unwrap().t, 3.0);
+    }
+
+    #[test]
+    fn named_cameras() {
+        let scene = parse_scene_toml(&format!(
+            "{SCENE_TOML}
+            [cameras.top]
+            width = 20
+            height = 20
+            field_of_view = 1.0
+            view = {{ from = [0, 10, 0], to = [0, 0, 0], up = [0, 0, 1] }}
+
+            [cameras.side]
+            width = 30
+            height = 10
+            field_of_view = 0.5
+            view = {{ from = [10, 1, 0], to = [0, 1, 0], up = [0, 1, 0] }}
+            "
+        ))
+        .unwrap();
+        assert_eq!(scene.cameras.len(), 2);
+        let top = scene.build_camera("top").unwrap();
+        assert_eq!((top.hsize(), top.vsize()), (20, 20));
+        assert_eq!(scene.build_camera("side").unwrap().hsize(), 30);
+        let err = scene.build_camera("front").unwrap_err();
+        assert!(err.ends_with("it has side, top"), "{err}");
+        // every camera renders the same world
+        let world = scene.build_world(Path::new("")).unwrap();
+        // through the center of the image
+        let ray = top.ray_for_pixel_offset(10, 10, 0.0, 0.0);
+        assert_eq!(hit(&world.intersect_world(&ray)).unwrap().t, 8.0);
+        let from_json = parse_scene_json(&scene.to_json().unwrap()).unwrap();
+        assert_eq!(from_json, scene);
+        assert!(!parse_scene_json(SCENE_JSON)
+            .unwrap()
+            .to_json()
+            .unwrap()
+            .contains("cameras"));
+    }
+
+    #[test]
+    fn cameras_without_an_invertible_view_are_errors() {
+        let scene = parse_scene_toml(&format!(
+            "{SCENE_TOML}
+            [cameras.nowhere]
+            width = 20
+            height = 20
+            field_of_view = 1.0
+            view = {{ from = [0, 1, 0], to = [0, 1, 0], up = [0, 1, 0] }}
+
+            [cameras.flat]
+            width = 20
+            height = 20
+            field_of_view = 1.0
+            view = [[1, 0, 0, 0], [0, 0, 0, 0], [0, 0, 1, 0], [0, 0, 0, 1]]
+            "
+        ))
+        .unwrap();
+        for name in ["nowhere", "flat"] {
+            let err = scene.cameras[name].build().unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidData);
+            assert!(scene.build_camera(name).is_err());
+        }
+    }
+
+    #[test]
+    fn meshes_are_split_into_cached_hierarchies() {
+        let directory = std::env::temp_dir().join(format!("scene_bvh_test_{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        // a row of 10 triangles along x
+        let facets: String = (0..10)
+            .map(|x| {
+                format!(
+                    "facet normal 0 0 -1\nouter loop\nvertex {x} 0 0\nvertex {x}.5 1 0\n\
+                     vertex {x}.9 0 0\nendloop\nendfacet\n"
+                )
+            })
+            .collect();
+        std::fs::write(
+            directory.join("row.stl"),
+            format!("solid row\n{facets}endsolid\n"),
+        )
+        .unwrap();
+        let scene = parse_scene_toml(&format!(
+            "bvh_cache = \"cache\"
+            {SCENE_TOML}
+
+            [[objects]]
+            type = \"mesh\"
+            file = \"row.stl\"
+            "
+        ))
+        .unwrap();
+        let world = scene.build_world(&directory).unwrap();
+        let mesh = world.objects[1].shape.as_ref() as &dyn Any;
+        assert_eq!(mesh.downcast_ref::<Group>().unwrap().children().len(), 2);
+        let ray = Ray::new(
+            Tuple4D::new_point(7.5, 0.5, -5.0),
+            Tuple4D::new_vector(0.0, 0.0, 1.0),
+        );
+        assert_eq!(world.objects[1].intersect(&ray).len(), 1);
+        let cached: Vec<_> = std::fs::read_dir(directory.join("cache"))
+            .unwrap()
+            .collect();
+        assert_eq!(cached.len(), 1);
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn axis_rotations_and_look_at_transforms() {
+        let transforms: Vec<TransformDescription> = serde_json::from_str(
+            r#"[
+                {"rotate_axis": {"axis": [0, 2, 0], "angle": 0.5}},
+                {"look_at": {"from": [1, 2, 3], "to": [1, 2, 10], "up": [0, 1, 0]}}
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            transform_matrix(&transforms).unwrap(),
+            Matrix4::translation(1.0, 2.0, 3.0) * Matrix4::rotation_y(0.5)
+        );
+        let look_at = |from: [Float; 3], to: [Float; 3], up: [Float; 3]| {
+            transform_matrix(&[TransformDescription::LookAt { from, to, up }])
+        };
+        assert!(look_at([1.0, 2.0, 3.0], [1.0, 2.0, 3.0], [0.0, 1.0, 0.0]).is_err());
+        assert!(look_at([0.0, 0.0, 0.0], [0.0, 5.0, 0.0], [0.0, 2.0, 0.0]).is_err());
+        assert!(look_at([0.0, 0.0, 0.0], [0.0, 0.0, 1.0], [0.0, 0.0, 0.0]).is_err());
+    }
+
+    #[test]
+    fn objects_without_an_invertible_transform_are_errors() {
+        let flat = format!(
+            "{SCENE_TOML}
+            [[objects]]
+            type = \"sphere\"
+            transform = [{{ scale = [0, 0, 0] }}]
+            "
+        );
+        let moving_flat = format!(
+            "{SCENE_TOML}
+            [[objects]]
+            type = \"sphere\"
+            transform_end = [{{ translate = [1, 0, 0] }}, {{ scale = [1, 0, 1] }}]
+            "
+        );
+        for scene in [flat, moving_flat] {
+            let err = parse_scene_toml(&scene)
+                .unwrap()
+                .build_world(Path::new(""))
+                .unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidData);
+        }
+    }
+
+    #[test]
+    fn invalid_scenes_are_errors() {
+        assert!(parse_scene_json("{}").is_err());
+        assert!(parse_scene_json(&SCENE_JSON.replace("torus", "teapot")).is_err());
+        assert!(parse_scene_toml("camera = 1").is_err());
+    }
+
+    #[test]
+    fn world_round_trips_through_json_and_toml() {
+        let (world, camera) = scenes::cornell_box(20, 20);
+        let scene = SceneFile::from_world(&world, &camera).unwrap();
+        let from_json = parse_scene_json(&scene.to_json().unwrap()).unwrap();
+        assert_eq!(from_json, scene);
+        let from_toml = parse_scene_toml(&scene.to_toml().unwrap()).unwrap();
+        assert_eq!(from_toml, scene);
+        let (loaded, loaded_camera) = from_toml.build(Path::new("")).unwrap();
+        assert_eq!(loaded_camera.transform, camera.transform);
+        assert_eq!(loaded.objects.len(), world.objects.len());
+        for (loaded, original) in loaded.objects.iter().zip(&world.objects) {
+            assert_eq!(loaded.transform, original.transform);
+            assert_eq!(loaded.material.color, original.material.color);
+        }
+        let ray = camera.ray_for_pixel(10, 10);
+        assert_eq!(world.color_at(&ray), loaded.color_at(&ray));
+    }
+
+    #[test]
+    fn objects_without_description_can_not_be_saved() {
+        let (mut world, camera) = scenes::three_spheres(10, 10);
+        world.objects.push(Object::mandelbulb(8.0, 4));
+        assert!(SceneFile::from_world(&world, &camera).is_err());
+    }
+}
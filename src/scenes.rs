@@ -0,0 +1,252 @@
+use std::sync::Arc;
+
+use crate::math::consts::{FRAC_PI_2, FRAC_PI_3};
+use crate::math::{Float, Matrix4, Tuple4D};
+use crate::patterns::CheckerPattern;
+use crate::{AreaLight, Camera, Color, Material, Object, PointLight, World};
+
+// ready to render scenes with a camera looking at them, for examples, tests and comparing the
+// integrators. The cameras use the default settings apart from their placement
+
+// the closed room with a red left and green right wall, a tall and a short box and a square area
+// light below the ceiling. The room spans -1 to 1 on every axis and is open towards the camera.
+// Made for path tracing, the whitted renderer lights it like a point light in the center of the
+// light
+pub fn cornell_box(hsize: usize, vsize: usize) -> (World, Camera) {
+    let white = Color::new(0.73, 0.73, 0.73);
+    let wall = |transform: Matrix4, color: Color| {
+        let mut wall = Object::plane();
+        wall.transform = transform.into();
+        wall.material = Material {
+            color,
+            ambient: 0.0,
+            specular: 0.0,
+            ..Material::default()
+        };
+        wall
+    };
+    let block = |transform: Matrix4| {
+        let mut block = Object::cube();
+        block.transform = transform.into();
+        block.material = Material {
+            color: white,
+            ambient: 0.0,
+            specular: 0.0,
+            ..Material::default()
+        };
+        block
+    };
+    let objects = vec![
+        wall(Matrix4::translation(0.0, -1.0, 0.0), white),
+        wall(Matrix4::translation(0.0, 1.0, 0.0), white),
+        wall(
+            Matrix4::translation(0.0, 0.0, 1.0) * Matrix4::rotation_x(FRAC_PI_2),
+            white,
+        ),
+        wall(
+            Matrix4::translation(-1.0, 0.0, 0.0) * Matrix4::rotation_z(FRAC_PI_2),
+            Color::new(0.65, 0.05, 0.05),
+        ),
+        wall(
+            Matrix4::translation(1.0, 0.0, 0.0) * Matrix4::rotation_z(FRAC_PI_2),
+            Color::new(0.12, 0.45, 0.15),
+        ),
+        block(
+            Matrix4::translation(-0.35, -0.4, 0.3)
+                * Matrix4::rotation_y(0.3)
+                * Matrix4::scaling(0.3, 0.6, 0.3),
+        ),
+        block(
+            Matrix4::translation(0.35, -0.7, -0.3)
+                * Matrix4::rotation_y(-0.3)
+                * Matrix4::scaling(0.3, 0.3, 0.3),
+        ),
+    ];
+    // slightly below the ceiling, so the ceiling does not shadow it
+    let light = AreaLight::new(
+        Tuple4D::new_point(-0.25, 0.99, -0.25),
+        Tuple4D::new_vector(0.5, 0.0, 0.0),
+        Tuple4D::new_vector(0.0, 0.0, 0.5),
+        Color::new(8.0, 8.0, 8.0),
+    );
+    let world = World {
+        objects,
+        lights: vec![light.into()],
+        ..World::default()
+    };
+    // the opening of the room exactly fills the field of view
+    let distance: Float = 3.8;
+    let mut camera = Camera::new(hsize, vsize, 2.0 * (1.0 / (distance - 1.0)).atan());
+    camera.transform = Matrix4::view_transform(
+        Tuple4D::new_point(0.0, 0.0, -distance),
+        Tuple4D::new_point(0.0, 0.0, 0.0),
+        Tuple4D::new_vector(0.0, 1.0, 0.0),
+    )
+    .into();
+    (world, camera)
+}
+
+// rows by columns spheres on a checkered floor. The spheres get more reflective from left to right
+// and rougher from front to back, which shows how the material parameters interact
+pub fn sphere_grid(rows: usize, columns: usize, hsize: usize, vsize: usize) -> (World, Camera) {
+    let mut floor = Object::plane();
+    floor.material = Material {
+        pattern: Some(Arc::new(CheckerPattern::new(
+            Color::new(0.9, 0.9, 0.9),
+            Color::new(0.6, 0.6, 0.6),
+        ))),
+        specular: 0.0,
+        ..Material::default()
+    };
+    let mut objects = vec![floor];
+    let fraction = |idx: usize, count: usize| {
+        if count > 1 {
+            idx as Float / (count - 1) as Float
+        } else {
+            0.5
+        }
+    };
+    for row in 0..rows {
+        for column in 0..columns {
+            let (u, v) = (fraction(column, columns), fraction(row, rows));
+            let mut sphere = Object::sphere();
+            sphere.transform = Matrix4::translation(
+                2.5 * column as Float - 1.25 * (columns as Float - 1.0),
+                1.0,
+                2.5 * row as Float,
+            )
+            .into();
+            sphere.material = Material {
+                color: Color::new(0.8 - 0.6 * u, 0.3 + 0.4 * v, 0.2 + 0.6 * u),
+                reflective: u * 0.8,
+                roughness: v * 0.5,
+                ..Material::default()
+            };
+            objects.push(sphere);
+        }
+    }
+    let world = World {
+        objects,
+        lights: vec![PointLight::new(
+            Tuple4D::new_point(-10.0, 20.0, -20.0),
+            Color::new(1.0, 1.0, 1.0),
+        )
+        .into()],
+        ..World::default()
+    };
+    let size = 2.5 * rows.max(columns) as Float;
+    let center = Tuple4D::new_point(0.0, 0.5, 1.25 * (rows as Float - 1.0));
+    let mut camera = Camera::new(hsize, vsize, FRAC_PI_3);
+    camera.transform = Matrix4::view_transform(
+        center + Tuple4D::new_vector(0.0, size * 0.7, -size * 1.1),
+        center,
+        Tuple4D::new_vector(0.0, 1.0, 0.0),
+    )
+    .into();
+    (world, camera)
+}
+
+// the three spheres on a floor from the cover of the ray tracer challenge
+pub fn three_spheres(hsize: usize, vsize: usize) -> (World, Camera) {
+    let mut floor = Object::plane();
+    floor.material = Material {
+        color: Color::new(1.0, 0.9, 0.9),
+        specular: 0.0,
+        ..Material::default()
+    };
+    let sphere = |transform: Matrix4, color: Color| {
+        let mut sphere = Object::sphere();
+        sphere.transform = transform.into();
+        sphere.material = Material {
+            color,
+            diffuse: 0.7,
+            specular: 0.3,
+            ..Material::default()
+        };
+        sphere
+    };
+    let world = World {
+        objects: vec![
+            floor,
+            sphere(
+                Matrix4::translation(-0.5, 1.0, 0.5),
+                Color::new(0.1, 1.0, 0.5),
+            ),
+            sphere(
+                Matrix4::translation(1.5, 0.5, -0.5) * Matrix4::scaling(0.5, 0.5, 0.5),
+                Color::new(0.5, 1.0, 0.1),
+            ),
+            sphere(
+                Matrix4::translation(-1.5, 0.33, -0.75) * Matrix4::scaling(0.33, 0.33, 0.33),
+                Color::new(1.0, 0.8, 0.1),
+            ),
+        ],
+        lights: vec![PointLight::new(
+            Tuple4D::new_point(-10.0, 10.0, -10.0),
+            Color::new(1.0, 1.0, 1.0),
+        )
+        .into()],
+        ..World::default()
+    };
+    let mut camera = Camera::new(hsize, vsize, FRAC_PI_3);
+    camera.transform = Matrix4::view_transform(
+        Tuple4D::new_point(0.0, 1.5, -5.0),
+        Tuple4D::new_point(0.0, 1.0, 0.0),
+        Tuple4D::new_vector(0.0, 1.0, 0.0),
+    )
+    .into();
+    (world, camera)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{hit, Integrator};
+
+    #[test]
+    fn cornell_box_walls_have_their_colors() {
+        let (world, camera) = cornell_box(11, 11);
+        // the center pixel looks at the back wall
+        let back = world.color_at(&camera.ray_for_pixel(5, 5));
+        assert!(back.r > 0.0 && back.r == back.g && back.g == back.b);
+        let left = world.color_at(&camera.ray_for_pixel(0, 5));
+        assert!(left.r > left.g);
+        let right = world.color_at(&camera.ray_for_pixel(10, 5));
+        assert!(right.g > right.r);
+    }
+
+    #[test]
+    fn cornell_box_renders_with_path_tracing() {
+        let (world, mut camera) = cornell_box(4, 4);
+        camera.integrator = Integrator::path_tracing(2);
+        let canvas = camera.render(&world);
+        assert!((0..4).any(|x| canvas.read_pixel(x, 2) != Color::new(0.0, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn sphere_grid_has_a_sphere_per_cell() {
+        let (world, camera) = sphere_grid(2, 3, 20, 10);
+        assert_eq!(world.objects.len(), 1 + 2 * 3);
+        let canvas = camera.render(&world);
+        assert_eq!(canvas.width(), 20);
+        // the center of the image is between the rows, behind the middle sphere of the front row
+        let xs = world.intersect_world(&camera.ray_for_pixel(10, 5));
+        let hit = hit(&xs).unwrap();
+        assert!(std::ptr::eq(hit.object, &world.objects[2]));
+    }
+
+    #[test]
+    fn three_spheres_center_pixel_hits_middle_sphere() {
+        let (world, camera) = three_spheres(11, 11);
+        let xs = world.intersect_world(&camera.ray_for_pixel(5, 5));
+        let hit = hit(&xs).unwrap();
+        assert!(std::ptr::eq(hit.object, &world.objects[1]));
+    }
+
+    #[test]
+    fn scenes_have_no_warnings() {
+        assert!(cornell_box(4, 4).0.validate().is_empty());
+        assert!(sphere_grid(2, 2, 4, 4).0.validate().is_empty());
+        assert!(three_spheres(4, 4).0.validate().is_empty());
+    }
+}
@@ -0,0 +1,62 @@
+use std::any::Any;
+use std::fmt::Debug;
+
+use crate::math::{Float, Tuple4D};
+use crate::{Bounds, Intersection, Ray};
+
+mod cube;
+pub use cube::Cube;
+mod group;
+pub use group::Group;
+mod heightfield;
+pub use heightfield::Heightfield;
+mod instance;
+pub use instance::Instance;
+mod metaballs;
+pub use metaballs::{Metaball, Metaballs};
+mod plane;
+pub use plane::Plane;
+mod quadric;
+pub use quadric::Quadric;
+mod sdf;
+pub use sdf::{mandelbulb_distance, smooth_min, DistanceFunction, SdfShape};
+mod sphere;
+pub use sphere::Sphere;
+mod torus;
+pub use torus::Torus;
+mod triangle;
+pub use triangle::Triangle;
+
+pub trait Shape: Any + Debug + Send + Sync {
+    // both functions work in object space, the Object wrapping the shape
+    // takes care of converting rays and normals from and to world space
+    fn local_intersect(&self, ray: &Ray) -> Vec<Float>;
+    fn local_normal_at(&self, point: Tuple4D) -> Tuple4D;
+    // box around the shape in object space
+    fn bounds(&self) -> Bounds;
+
+    // shapes made of other objects (like groups) add the intersections with their children here,
+    // they refer to the children instead of the object holding the shape
+    fn local_intersect_children<'a>(
+        &'a self,
+        _ray: &Ray,
+        _intersections: &mut Vec<Intersection<'a>>,
+    ) {
+    }
+
+    // any intersection with the children that blocks the ray before distance, not necessarily the
+    // closest one. Shadow rays stop at the first one that is found
+    fn local_occluding_child<'a>(&'a self, ray: &Ray, distance: Float) -> Option<Intersection<'a>> {
+        let mut intersections = vec![];
+        self.local_intersect_children(ray, &mut intersections);
+        intersections
+            .into_iter()
+            .find(|intersection| intersection.blocks(distance))
+    }
+
+    // name of the type without its module path, for debug output
+    fn type_name(&self) -> &'static str {
+        let name = std::any::type_name::<Self>();
+        name.rsplit("::").next().unwrap_or(name)
+    }
+}
@@ -0,0 +1,113 @@
+use crate::math::{Float, Tuple4D, FLOAT_EQ_EPS};
+use crate::shapes::Shape;
+use crate::{Bounds, Ray};
+
+// axis aligned cube from -1 to 1 on every axis
+#[derive(Debug, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Cube;
+
+// entry and exit t of the ray for the slab between -1 and 1 on one axis
+fn check_axis(origin: Float, direction: Float) -> (Float, Float) {
+    let tmin_numerator = -1.0 - origin;
+    let tmax_numerator = 1.0 - origin;
+    let (tmin, tmax) = if direction.abs() >= FLOAT_EQ_EPS {
+        (tmin_numerator / direction, tmax_numerator / direction)
+    } else {
+        (
+            tmin_numerator * Float::INFINITY,
+            tmax_numerator * Float::INFINITY,
+        )
+    };
+    if tmin > tmax {
+        (tmax, tmin)
+    } else {
+        (tmin, tmax)
+    }
+}
+
+impl Shape for Cube {
+    fn local_intersect(&self, ray: &Ray) -> Vec<Float> {
+        let (xtmin, xtmax) = check_axis(ray.origin.x, ray.direction.x);
+        let (ytmin, ytmax) = check_axis(ray.origin.y, ray.direction.y);
+        let (ztmin, ztmax) = check_axis(ray.origin.z, ray.direction.z);
+        let tmin = xtmin.max(ytmin).max(ztmin);
+        let tmax = xtmax.min(ytmax).min(ztmax);
+        if tmin > tmax {
+            return vec![];
+        }
+        vec![tmin, tmax]
+    }
+
+    fn local_normal_at(&self, point: Tuple4D) -> Tuple4D {
+        let (x, y, z) = (point.x.abs(), point.y.abs(), point.z.abs());
+        let max = x.max(y).max(z);
+        if max == x {
+            Tuple4D::new_vector(point.x, 0.0, 0.0)
+        } else if max == y {
+            Tuple4D::new_vector(0.0, point.y, 0.0)
+        } else {
+            Tuple4D::new_vector(0.0, 0.0, point.z)
+        }
+    }
+
+    fn bounds(&self) -> Bounds {
+        Bounds::new(
+            Tuple4D::new_point(-1.0, -1.0, -1.0),
+            Tuple4D::new_point(1.0, 1.0, 1.0),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ray_intersects_cube() {
+        let cases = [
+            ((5.0, 0.5, 0.0), (-1.0, 0.0, 0.0), 4.0, 6.0),
+            ((-5.0, 0.5, 0.0), (1.0, 0.0, 0.0), 4.0, 6.0),
+            ((0.5, 5.0, 0.0), (0.0, -1.0, 0.0), 4.0, 6.0),
+            ((0.5, 0.0, -5.0), (0.0, 0.0, 1.0), 4.0, 6.0),
+            ((0.0, 0.5, 0.0), (0.0, 0.0, 1.0), -1.0, 1.0),
+        ];
+        for ((ox, oy, oz), (dx, dy, dz), t1, t2) in cases {
+            let ray = Ray::new(
+                Tuple4D::new_point(ox, oy, oz),
+                Tuple4D::new_vector(dx, dy, dz),
+            );
+            assert_eq!(Cube.local_intersect(&ray), vec![t1, t2]);
+        }
+    }
+
+    #[test]
+    fn ray_misses_cube() {
+        let ray = Ray::new(
+            Tuple4D::new_point(-2.0, 0.0, 0.0),
+            Tuple4D::new_vector(0.2673, 0.5345, 0.8018),
+        );
+        assert!(Cube.local_intersect(&ray).is_empty());
+        let ray = Ray::new(
+            Tuple4D::new_point(2.0, 2.0, 0.0),
+            Tuple4D::new_vector(-1.0, 0.0, 0.0),
+        );
+        assert!(Cube.local_intersect(&ray).is_empty());
+    }
+
+    #[test]
+    fn normal_on_cube_surface() {
+        assert_eq!(
+            Cube.local_normal_at(Tuple4D::new_point(1.0, 0.5, -0.8)),
+            Tuple4D::new_vector(1.0, 0.0, 0.0)
+        );
+        assert_eq!(
+            Cube.local_normal_at(Tuple4D::new_point(-0.4, 0.3, -1.0)),
+            Tuple4D::new_vector(0.0, 0.0, -1.0)
+        );
+        assert_eq!(
+            Cube.local_normal_at(Tuple4D::new_point(1.0, 1.0, 1.0)),
+            Tuple4D::new_vector(1.0, 0.0, 0.0)
+        );
+    }
+}
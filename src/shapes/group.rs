@@ -0,0 +1,134 @@
+use crate::math::{Float, Tuple4D};
+use crate::shapes::Shape;
+use crate::{Bounds, Intersection, Object, Ray};
+
+// collection of objects that are transformed together, the transforms of the children are relative
+// to the group. The children are fixed once the group is built, so their bounds can be cached
+#[derive(Debug)]
+pub struct Group {
+    children: Vec<Object>,
+    bounds: Bounds,
+}
+
+impl Group {
+    pub fn new(children: Vec<Object>) -> Group {
+        let bounds = children.iter().fold(Bounds::empty(), |bounds, child| {
+            bounds.union(&child.bounds())
+        });
+        Group { children, bounds }
+    }
+
+    pub fn children(&self) -> &[Object] {
+        &self.children
+    }
+
+    pub fn into_children(self) -> Vec<Object> {
+        self.children
+    }
+}
+
+impl Shape for Group {
+    // the group itself has no surface, only its children do
+    fn local_intersect(&self, _ray: &Ray) -> Vec<Float> {
+        vec![]
+    }
+
+    fn local_normal_at(&self, _point: Tuple4D) -> Tuple4D {
+        panic!("groups have no surface, normals are computed by their children")
+    }
+
+    fn bounds(&self) -> Bounds {
+        self.bounds
+    }
+
+    fn local_intersect_children<'a>(
+        &'a self,
+        ray: &Ray,
+        intersections: &mut Vec<Intersection<'a>>,
+    ) {
+        crate::heatmap::count_bounds_test();
+        if !self.bounds.intersects(ray) {
+            return;
+        }
+        for child in &self.children {
+            child.intersect_into(ray, intersections);
+        }
+    }
+
+    fn local_occluding_child<'a>(&'a self, ray: &Ray, distance: Float) -> Option<Intersection<'a>> {
+        crate::heatmap::count_bounds_test();
+        if !self.bounds.intersects(ray) {
+            return None;
+        }
+        self.children
+            .iter()
+            .find_map(|child| child.occluder(ray, distance))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::Matrix4;
+
+    use super::*;
+
+    #[test]
+    fn intersecting_ray_with_empty_group() {
+        let group = Group::new(vec![]);
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 0.0, 0.0),
+            Tuple4D::new_vector(0.0, 0.0, 1.0),
+        );
+        let mut xs = vec![];
+        group.local_intersect_children(&ray, &mut xs);
+        assert!(xs.is_empty());
+        assert!(group.bounds().is_empty());
+    }
+
+    #[test]
+    fn intersecting_ray_with_nonempty_group() {
+        let s1 = Object::sphere();
+        let mut s2 = Object::sphere();
+        s2.transform = Matrix4::translation(0.0, 0.0, -3.0).into();
+        let mut s3 = Object::sphere();
+        s3.transform = Matrix4::translation(5.0, 0.0, 0.0).into();
+        let group = Group::new(vec![s1, s2, s3]);
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 0.0, -5.0),
+            Tuple4D::new_vector(0.0, 0.0, 1.0),
+        );
+        let mut xs = vec![];
+        group.local_intersect_children(&ray, &mut xs);
+        xs.sort_by(|a, b| a.t.total_cmp(&b.t));
+        assert_eq!(xs.len(), 4);
+        let children = group.children();
+        assert!(std::ptr::eq(xs[0].object, &children[1]));
+        assert!(std::ptr::eq(xs[1].object, &children[1]));
+        assert!(std::ptr::eq(xs[2].object, &children[0]));
+        assert!(std::ptr::eq(xs[3].object, &children[0]));
+    }
+
+    #[test]
+    fn group_bounds_contain_transformed_children() {
+        let mut sphere = Object::sphere();
+        sphere.transform =
+            (Matrix4::translation(2.0, 5.0, -3.0) * Matrix4::scaling(2.0, 2.0, 2.0)).into();
+        let mut cube = Object::cube();
+        cube.transform = Matrix4::translation(-4.0, -1.0, 4.0).into();
+        let group = Group::new(vec![sphere, cube]);
+        assert_eq!(group.bounds().min, Tuple4D::new_point(-5.0, -2.0, -5.0));
+        assert_eq!(group.bounds().max, Tuple4D::new_point(4.0, 7.0, 5.0));
+    }
+
+    #[test]
+    fn ray_missing_bounds_skips_children() {
+        let group = Group::new(vec![Object::sphere()]);
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 5.0, -5.0),
+            Tuple4D::new_vector(0.0, 0.0, 1.0),
+        );
+        let mut xs = vec![];
+        group.local_intersect_children(&ray, &mut xs);
+        assert!(xs.is_empty());
+    }
+}
@@ -0,0 +1,344 @@
+use crate::math::{Float, Tuple4D, FLOAT_EQ_EPS};
+use crate::shapes::{Shape, Triangle};
+use crate::{Bounds, Canvas, Ray};
+
+// terrain from a grid of heights spread over the square from -1 to 1 in x and z, the heights are
+// the y coordinates. Every cell between four samples is split into two triangles, normals are
+// interpolated from the slopes at the samples so the terrain looks smooth
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(try_from = "HeightfieldSamples", into = "HeightfieldSamples")
+)]
+pub struct Heightfield {
+    width: usize,
+    depth: usize,
+    heights: Vec<Float>,
+    normals: Vec<Tuple4D>,
+    bounds: Bounds,
+}
+
+impl Heightfield {
+    // heights are stored row by row, height[z * width + x]. Both sides need at least two samples
+    pub fn new(width: usize, depth: usize, heights: Vec<Float>) -> Heightfield {
+        assert!(
+            width >= 2 && depth >= 2,
+            "heightfield needs at least 2x2 samples"
+        );
+        assert_eq!(heights.len(), width * depth);
+        let (min, max) = heights.iter().fold(
+            (Float::INFINITY, Float::NEG_INFINITY),
+            |(min, max), &height| (min.min(height), max.max(height)),
+        );
+        let mut heightfield = Heightfield {
+            width,
+            depth,
+            heights,
+            normals: vec![],
+            bounds: Bounds::new(
+                Tuple4D::new_point(-1.0, min, -1.0),
+                Tuple4D::new_point(1.0, max, 1.0),
+            ),
+        };
+        heightfield.normals = (0..depth)
+            .flat_map(|z| (0..width).map(move |x| (x, z)))
+            .map(|(x, z)| heightfield.sample_normal(x, z))
+            .collect();
+        heightfield
+    }
+
+    // samples the function at the grid points, it gets x and z in [-1, 1]
+    pub fn from_function(
+        width: usize,
+        depth: usize,
+        height: impl Fn(Float, Float) -> Float,
+    ) -> Heightfield {
+        let mut heights = Vec::with_capacity(width * depth);
+        for z in 0..depth {
+            for x in 0..width {
+                heights.push(height(grid_to_object(x, width), grid_to_object(z, depth)));
+            }
+        }
+        Heightfield::new(width, depth, heights)
+    }
+
+    // grayscale image with one sample per pixel, black is 0 and white is 1. The rows of the image
+    // go along z. Fails for images smaller than 2x2 pixels
+    pub fn from_canvas(canvas: &Canvas) -> Result<Heightfield, String> {
+        if canvas.width() < 2 || canvas.height() < 2 {
+            return Err(format!(
+                "heightfield needs at least 2x2 samples, the image has {}x{} pixels",
+                canvas.width(),
+                canvas.height()
+            ));
+        }
+        let mut heights = Vec::with_capacity(canvas.width() * canvas.height());
+        for z in 0..canvas.height() {
+            for x in 0..canvas.width() {
+                let color = canvas.read_pixel(x, z);
+                heights.push((color.r + color.g + color.b) / 3.0);
+            }
+        }
+        Ok(Heightfield::new(canvas.width(), canvas.height(), heights))
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn height(&self, x: usize, z: usize) -> Float {
+        self.heights[z * self.width + x]
+    }
+
+    fn sample_point(&self, x: usize, z: usize) -> Tuple4D {
+        Tuple4D::new_point(
+            grid_to_object(x, self.width),
+            self.height(x, z),
+            grid_to_object(z, self.depth),
+        )
+    }
+
+    // central differences, one sided at the border
+    fn sample_normal(&self, x: usize, z: usize) -> Tuple4D {
+        let (x0, x1) = (x.saturating_sub(1), (x + 1).min(self.width - 1));
+        let (z0, z1) = (z.saturating_sub(1), (z + 1).min(self.depth - 1));
+        let slope_x = (self.height(x1, z) - self.height(x0, z))
+            / (grid_to_object(x1, self.width) - grid_to_object(x0, self.width));
+        let slope_z = (self.height(x, z1) - self.height(x, z0))
+            / (grid_to_object(z1, self.depth) - grid_to_object(z0, self.depth));
+        Tuple4D::new_vector(-slope_x, 1.0, -slope_z).normalize()
+    }
+
+    // the two triangles of a cell, the first one holds the corner at the smallest x and z
+    fn cell_triangles(&self, x: usize, z: usize) -> [Triangle; 2] {
+        let corners = [(x, z), (x + 1, z), (x, z + 1), (x + 1, z + 1)];
+        let [a, b, c, d] = corners.map(|(x, z)| self.sample_point(x, z));
+        let [na, nb, nc, nd] = corners.map(|(x, z)| self.normals[z * self.width + x]);
+        [
+            Triangle::smooth(a, b, c, na, nb, nc),
+            Triangle::smooth(b, d, c, nb, nd, nc),
+        ]
+    }
+
+    fn cell_size(&self) -> (Float, Float) {
+        (
+            2.0 / (self.width - 1) as Float,
+            2.0 / (self.depth - 1) as Float,
+        )
+    }
+}
+
+impl Shape for Heightfield {
+    // walks through the cells below the ray with a 2d digital differential analyzer, only the two
+    // triangles of each visited cell are tested. Hits on the edges between cells are found in both
+    // cells and only reported once
+    fn local_intersect(&self, ray: &Ray) -> Vec<Float> {
+        let Some((t_min, t_max)) = self.bounds.t_range(ray) else {
+            return vec![];
+        };
+        let (cell_x, cell_z) = self.cell_size();
+        let entry = ray.position(t_min);
+        let mut x = (((entry.x + 1.0) / cell_x).floor().max(0.0) as usize).min(self.width - 2);
+        let mut z = (((entry.z + 1.0) / cell_z).floor().max(0.0) as usize).min(self.depth - 2);
+        // t at which the ray crosses the next cell border on each axis and the t between borders
+        let axis = |origin: Float, direction: Float, cell: usize, size: Float| {
+            if direction == 0.0 {
+                return (Float::INFINITY, Float::INFINITY);
+            }
+            let border = if direction > 0.0 { cell + 1 } else { cell };
+            let next = (border as Float * size - 1.0 - origin) / direction;
+            (next, size / direction.abs())
+        };
+        let (mut next_x, delta_x) = axis(ray.origin.x, ray.direction.x, x, cell_x);
+        let (mut next_z, delta_z) = axis(ray.origin.z, ray.direction.z, z, cell_z);
+        let mut cell_start = t_min;
+        let mut xs = vec![];
+        loop {
+            let cell_end = next_x.min(next_z).min(t_max);
+            for triangle in self.cell_triangles(x, z) {
+                xs.extend(
+                    triangle
+                        .local_intersect(ray)
+                        .into_iter()
+                        .filter(|&t| t > cell_start - FLOAT_EQ_EPS && t < cell_end + FLOAT_EQ_EPS),
+                );
+            }
+            if cell_end >= t_max {
+                break;
+            }
+            if next_x < next_z {
+                if ray.direction.x > 0.0 && x + 2 < self.width {
+                    x += 1;
+                } else if ray.direction.x < 0.0 && x > 0 {
+                    x -= 1;
+                } else {
+                    break;
+                }
+                next_x += delta_x;
+            } else {
+                if ray.direction.z > 0.0 && z + 2 < self.depth {
+                    z += 1;
+                } else if ray.direction.z < 0.0 && z > 0 {
+                    z -= 1;
+                } else {
+                    break;
+                }
+                next_z += delta_z;
+            }
+            cell_start = cell_end;
+        }
+        xs.sort_by(Float::total_cmp);
+        xs.dedup_by(|a, b| (*a - *b).abs() < FLOAT_EQ_EPS);
+        xs
+    }
+
+    fn local_normal_at(&self, point: Tuple4D) -> Tuple4D {
+        let (cell_x, cell_z) = self.cell_size();
+        let grid_x = ((point.x + 1.0) / cell_x).clamp(0.0, (self.width - 1) as Float);
+        let grid_z = ((point.z + 1.0) / cell_z).clamp(0.0, (self.depth - 1) as Float);
+        let x = (grid_x.floor() as usize).min(self.width - 2);
+        let z = (grid_z.floor() as usize).min(self.depth - 2);
+        let [lower, upper] = self.cell_triangles(x, z);
+        let triangle = if grid_x - x as Float + grid_z - z as Float <= 1.0 {
+            lower
+        } else {
+            upper
+        };
+        triangle.local_normal_at(point).normalize()
+    }
+
+    fn bounds(&self) -> Bounds {
+        self.bounds
+    }
+}
+
+// the normals and bounds are derived from the heights when deserializing
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct HeightfieldSamples {
+    width: usize,
+    depth: usize,
+    heights: Vec<Float>,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<HeightfieldSamples> for Heightfield {
+    type Error = String;
+
+    fn try_from(samples: HeightfieldSamples) -> Result<Self, Self::Error> {
+        if samples.width < 2 || samples.depth < 2 {
+            return Err("heightfield needs at least 2x2 samples".to_string());
+        }
+        if samples.heights.len() != samples.width * samples.depth {
+            return Err(format!(
+                "expected {} heights, found {}",
+                samples.width * samples.depth,
+                samples.heights.len()
+            ));
+        }
+        Ok(Heightfield::new(
+            samples.width,
+            samples.depth,
+            samples.heights,
+        ))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<Heightfield> for HeightfieldSamples {
+    fn from(heightfield: Heightfield) -> Self {
+        HeightfieldSamples {
+            width: heightfield.width,
+            depth: heightfield.depth,
+            heights: heightfield.heights,
+        }
+    }
+}
+
+fn grid_to_object(idx: usize, samples: usize) -> Float {
+    idx as Float / (samples - 1) as Float * 2.0 - 1.0
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::float_eq;
+    use crate::Color;
+
+    use super::*;
+
+    // a ridge along z, rising linearly from both sides to a height of 1 at x = 0
+    fn ridge() -> Heightfield {
+        Heightfield::from_function(5, 4, |x, _| 1.0 - x.abs())
+    }
+
+    #[test]
+    fn vertical_ray_hits_terrain_at_its_height() {
+        let ridge = ridge();
+        for (x, height) in [(0.0, 1.0), (-0.25, 0.75), (0.8, 0.2), (1.0, 0.0)] {
+            let ray = Ray::new(
+                Tuple4D::new_point(x, 5.0, 0.3),
+                Tuple4D::new_vector(0.0, -1.0, 0.0),
+            );
+            let xs = ridge.local_intersect(&ray);
+            assert_eq!(xs.len(), 1, "{x}: {xs:?}");
+            assert!(float_eq(xs[0], 5.0 - height, FLOAT_EQ_EPS));
+        }
+    }
+
+    #[test]
+    fn ray_crossing_cells_hits_both_slopes() {
+        let ray = Ray::new(
+            Tuple4D::new_point(-5.0, 0.5, 0.1),
+            Tuple4D::new_vector(1.0, 0.0, 0.0),
+        );
+        let xs = ridge().local_intersect(&ray);
+        assert_eq!(xs.len(), 2);
+        assert!(float_eq(xs[0], 4.5, FLOAT_EQ_EPS));
+        assert!(float_eq(xs[1], 5.5, FLOAT_EQ_EPS));
+        let above = Ray::new(
+            Tuple4D::new_point(-5.0, 1.5, 0.1),
+            Tuple4D::new_vector(1.0, 0.0, 0.2),
+        );
+        assert!(ridge().local_intersect(&above).is_empty());
+    }
+
+    #[test]
+    fn normals_follow_the_slope() {
+        let ridge = ridge();
+        let normal = ridge.local_normal_at(Tuple4D::new_point(-0.6, 0.4, 0.2));
+        assert_eq!(normal, Tuple4D::new_vector(-1.0, 1.0, 0.0).normalize());
+        // the samples on the ridge average both slopes
+        let top = ridge.local_normal_at(Tuple4D::new_point(0.0, 1.0, 0.0));
+        assert_eq!(top, Tuple4D::new_vector(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn heightfield_from_grayscale_canvas() {
+        let mut canvas = Canvas::create_canvas(3, 2);
+        canvas.write_pixel(1, 1, Color::new(1.0, 0.5, 0.0));
+        let heightfield = Heightfield::from_canvas(&canvas).unwrap();
+        assert_eq!(heightfield.width(), 3);
+        assert_eq!(heightfield.depth(), 2);
+        assert_eq!(heightfield.height(1, 1), 0.5);
+        assert_eq!(heightfield.height(0, 1), 0.0);
+        let bounds = heightfield.bounds();
+        assert_eq!(bounds.min, Tuple4D::new_point(-1.0, 0.0, -1.0));
+        assert_eq!(bounds.max, Tuple4D::new_point(1.0, 0.5, 1.0));
+        assert!(Heightfield::from_canvas(&Canvas::create_canvas(1, 5)).is_err());
+        assert!(Heightfield::from_canvas(&Canvas::create_canvas(0, 0)).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serializing_heightfield() {
+        let json = serde_json::to_string(&ridge()).unwrap();
+        assert_eq!(serde_json::from_str::<Heightfield>(&json).unwrap(), ridge());
+        let invalid = r#"{"width": 2, "depth": 2, "heights": [0.0, 1.0]}"#;
+        assert!(serde_json::from_str::<Heightfield>(invalid).is_err());
+    }
+}
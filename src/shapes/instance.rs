@@ -0,0 +1,138 @@
+use std::sync::Arc;
+
+use crate::math::{Float, Tuple4D};
+use crate::shapes::Shape;
+use crate::{Bounds, Intersection, Material, Object, Ray};
+
+// shares the geometry of a prototype (usually a mesh group) with other instances, each one only adds
+// its own transform. If a material is set, it replaces the materials of everything in the prototype,
+// overrides of outer instances win over those of instances nested in the prototype
+#[derive(Debug, Clone)]
+pub struct Instance {
+    pub prototype: Arc<Object>,
+    pub material: Option<Material>,
+}
+
+impl Instance {
+    pub fn new(prototype: Arc<Object>) -> Instance {
+        Instance {
+            prototype,
+            material: None,
+        }
+    }
+
+    pub fn with_material(self, material: Material) -> Instance {
+        Instance {
+            material: Some(material),
+            ..self
+        }
+    }
+}
+
+impl Shape for Instance {
+    // the surface belongs to the prototype
+    fn local_intersect(&self, _ray: &Ray) -> Vec<Float> {
+        vec![]
+    }
+
+    fn local_normal_at(&self, _point: Tuple4D) -> Tuple4D {
+        panic!("instances have no surface, normals are computed by their prototype")
+    }
+
+    fn bounds(&self) -> Bounds {
+        self.prototype.bounds()
+    }
+
+    fn local_intersect_children<'a>(
+        &'a self,
+        ray: &Ray,
+        intersections: &mut Vec<Intersection<'a>>,
+    ) {
+        let start = intersections.len();
+        self.prototype.intersect_into(ray, intersections);
+        if let Some(material) = &self.material {
+            for intersection in &mut intersections[start..] {
+                *intersection = intersection.with_material(material);
+            }
+        }
+    }
+
+    fn local_occluding_child<'a>(&'a self, ray: &Ray, distance: Float) -> Option<Intersection<'a>> {
+        match &self.material {
+            // the materials of the prototype decide which of its surfaces are volumes
+            None => self.prototype.occluder(ray, distance),
+            Some(material) if material.volume.is_some() => None,
+            Some(material) => {
+                let mut intersections = vec![];
+                self.prototype.intersect_into(ray, &mut intersections);
+                intersections
+                    .into_iter()
+                    .find(|intersection| intersection.t >= 0.0 && intersection.t < distance)
+                    .map(|intersection| intersection.with_material(material))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::Matrix4;
+    use crate::Color;
+
+    use super::*;
+
+    fn ray() -> Ray {
+        Ray::new(
+            Tuple4D::new_point(0.0, 0.0, -5.0),
+            Tuple4D::new_vector(0.0, 0.0, 1.0),
+        )
+    }
+
+    #[test]
+    fn instances_share_prototype() {
+        let prototype = Arc::new(Object::group(vec![Object::sphere()]));
+        let mut a = Object::instance(prototype.clone());
+        a.transform = Matrix4::translation(0.0, 0.0, 2.0).into();
+        let b = Object::instance(prototype.clone());
+        let xs_a = a.intersect(&ray());
+        let xs_b = b.intersect(&ray());
+        assert_eq!(xs_a.iter().map(|x| x.t).collect::<Vec<_>>(), vec![6.0, 8.0]);
+        assert_eq!(xs_b.iter().map(|x| x.t).collect::<Vec<_>>(), vec![4.0, 6.0]);
+        // the same sphere object is hit through both instances, but in different places
+        assert!(std::ptr::eq(xs_a[0].object, xs_b[0].object));
+        assert!(!xs_a[0].is_same_object(&xs_b[0]));
+        assert_eq!(Arc::strong_count(&prototype), 3);
+    }
+
+    #[test]
+    fn instance_overrides_material_of_prototype() {
+        let mut sphere = Object::sphere();
+        sphere.material.color = Color::new(1.0, 0.0, 0.0);
+        let prototype = Arc::new(sphere);
+        let plain = Object::instance(prototype.clone());
+        let green = Material {
+            color: Color::new(0.0, 1.0, 0.0),
+            ..Material::default()
+        };
+        let overridden = Object::new(Box::new(
+            Instance::new(prototype).with_material(green.clone()),
+        ));
+        assert_eq!(
+            plain.intersect(&ray())[0].material.color,
+            Color::new(1.0, 0.0, 0.0)
+        );
+        let xs = overridden.intersect(&ray());
+        assert_eq!(xs[0].material.color, green.color);
+        let comps = xs[0].prepare_computations(&ray(), &xs);
+        assert_eq!(comps.material.color, green.color);
+    }
+
+    #[test]
+    fn bounds_of_instance_are_bounds_of_prototype() {
+        let mut sphere = Object::sphere();
+        sphere.transform = Matrix4::translation(1.0, 0.0, 0.0).into();
+        let instance = Instance::new(Arc::new(sphere));
+        assert_eq!(instance.bounds().min, Tuple4D::new_point(0.0, -1.0, -1.0));
+        assert_eq!(instance.bounds().max, Tuple4D::new_point(2.0, 1.0, 1.0));
+    }
+}
@@ -0,0 +1,282 @@
+use crate::math::{solve_quadratic, Float, Tuple4D};
+use crate::shapes::Shape;
+use crate::{Bounds, Ray};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Metaball {
+    pub center: Tuple4D,
+    // has to be positive, balls without a radius are ignored
+    pub radius: Float,
+    // negative weights carve into the other balls
+    pub weight: Float,
+}
+
+impl Metaball {
+    // weight * (1 - d^2 / r^2)^3 within the radius, the field and its gradient fall smoothly to zero
+    // at the radius so every ball only influences its surroundings
+    fn field(&self, point: Tuple4D) -> Float {
+        if !self.has_radius() {
+            return 0.0;
+        }
+        let falloff = 1.0 - (point - self.center).dot(point - self.center) / self.radius.powi(2);
+        if falloff <= 0.0 {
+            return 0.0;
+        }
+        self.weight * falloff.powi(3)
+    }
+
+    fn gradient(&self, point: Tuple4D) -> Tuple4D {
+        if !self.has_radius() {
+            return Tuple4D::new_vector(0.0, 0.0, 0.0);
+        }
+        let offset = point - self.center;
+        let radius2 = self.radius.powi(2);
+        let falloff = 1.0 - offset.dot(offset) / radius2;
+        if falloff <= 0.0 {
+            return Tuple4D::new_vector(0.0, 0.0, 0.0);
+        }
+        offset * (-6.0 * self.weight * falloff.powi(2) / radius2)
+    }
+
+    fn has_radius(&self) -> bool {
+        self.radius > 0.0
+    }
+}
+
+// blobby surface where the summed fields of the balls reach the threshold. Balls closer than their
+// radius melt together, a single ball of weight 1 has its surface at
+// radius * sqrt(1 - threshold^(1/3))
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Metaballs {
+    pub balls: Vec<Metaball>,
+    pub threshold: Float,
+}
+
+impl Metaballs {
+    pub fn new(threshold: Float) -> Metaballs {
+        Metaballs {
+            balls: vec![],
+            threshold,
+        }
+    }
+
+    pub fn with_ball(mut self, center: Tuple4D, radius: Float, weight: Float) -> Metaballs {
+        assert!(
+            radius > 0.0,
+            "metaball radius has to be positive, got {radius}"
+        );
+        self.balls.push(Metaball {
+            center,
+            radius,
+            weight,
+        });
+        self
+    }
+
+    pub fn field(&self, point: Tuple4D) -> Float {
+        self.balls.iter().map(|ball| ball.field(point)).sum()
+    }
+
+    // the parts of the ray inside the radius of any ball, merged where they overlap. Outside of
+    // them the field is zero
+    fn ray_segments(&self, ray: &Ray) -> Vec<(Float, Float)> {
+        let direction2 = ray.direction.dot(ray.direction);
+        let mut segments: Vec<(Float, Float)> = self
+            .balls
+            .iter()
+            .filter(|ball| ball.has_radius())
+            .filter_map(|ball| {
+                let offset = ray.origin - ball.center;
+                let roots = solve_quadratic(
+                    direction2,
+                    2.0 * offset.dot(ray.direction),
+                    offset.dot(offset) - ball.radius.powi(2),
+                );
+                match roots[..] {
+                    [t0, t1] if t0 < t1 => Some((t0, t1)),
+                    _ => None,
+                }
+            })
+            .collect();
+        segments.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let mut merged: Vec<(Float, Float)> = vec![];
+        for (start, stop) in segments {
+            match merged.last_mut() {
+                Some(last) if start <= last.1 => last.1 = last.1.max(stop),
+                _ => merged.push((start, stop)),
+            }
+        }
+        merged
+    }
+
+    // the crossing of the threshold between two samples on different sides of it
+    fn bisect(&self, ray: &Ray, mut outside_t: Float, mut inside_t: Float) -> Float {
+        for _ in 0..48 {
+            let mid = (outside_t + inside_t) / 2.0;
+            if self.field(ray.position(mid)) < self.threshold {
+                outside_t = mid;
+            } else {
+                inside_t = mid;
+            }
+        }
+        (outside_t + inside_t) / 2.0
+    }
+}
+
+impl Shape for Metaballs {
+    // the field is sampled along the segments of the ray near the balls, every crossing of the
+    // threshold is refined by bisection. Features smaller than the sampling step may be missed
+    fn local_intersect(&self, ray: &Ray) -> Vec<Float> {
+        let speed = ray.direction.magnitude();
+        let min_radius = self
+            .balls
+            .iter()
+            .filter(|ball| ball.has_radius())
+            .map(|ball| ball.radius)
+            .fold(Float::INFINITY, Float::min);
+        if speed == 0.0 || !min_radius.is_finite() {
+            return vec![];
+        }
+        let step = min_radius / 32.0 / speed;
+        let mut xs = vec![];
+        for (start, stop) in self.ray_segments(ray) {
+            let steps = ((stop - start) / step).ceil() as usize;
+            let mut t = start;
+            let mut inside = self.field(ray.position(t)) >= self.threshold;
+            for idx in 1..=steps {
+                let next_t = if idx == steps {
+                    stop
+                } else {
+                    start + idx as Float * step
+                };
+                let next_inside = self.field(ray.position(next_t)) >= self.threshold;
+                if next_inside != inside {
+                    xs.push(if inside {
+                        self.bisect(ray, next_t, t)
+                    } else {
+                        self.bisect(ray, t, next_t)
+                    });
+                }
+                t = next_t;
+                inside = next_inside;
+            }
+        }
+        xs
+    }
+
+    // the field grows towards the centers, so the normal points against its gradient
+    fn local_normal_at(&self, point: Tuple4D) -> Tuple4D {
+        let gradient = self
+            .balls
+            .iter()
+            .fold(Tuple4D::new_vector(0.0, 0.0, 0.0), |gradient, ball| {
+                gradient + ball.gradient(point)
+            });
+        (-gradient).normalize()
+    }
+
+    fn bounds(&self) -> Bounds {
+        self.balls
+            .iter()
+            .filter(|ball| ball.weight > 0.0 && ball.has_radius())
+            .fold(Bounds::empty(), |bounds, ball| {
+                let extent = Tuple4D::new_vector(ball.radius, ball.radius, ball.radius);
+                bounds.union(&Bounds::new(ball.center - extent, ball.center + extent))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::{float_eq, FLOAT_EQ_EPS};
+
+    use super::*;
+
+    #[test]
+    fn single_ball_is_a_sphere() {
+        // (1 - d^2)^3 = 0.125 at d^2 = 0.5
+        let metaballs =
+            Metaballs::new(0.125).with_ball(Tuple4D::new_point(0.0, 0.0, 0.0), 1.0, 1.0);
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 0.0, -5.0),
+            Tuple4D::new_vector(0.0, 0.0, 1.0),
+        );
+        let xs = metaballs.local_intersect(&ray);
+        assert_eq!(xs.len(), 2);
+        assert!(float_eq(xs[0], 5.0 - Float::sqrt(0.5), FLOAT_EQ_EPS));
+        assert!(float_eq(xs[1], 5.0 + Float::sqrt(0.5), FLOAT_EQ_EPS));
+        let normal = metaballs.local_normal_at(ray.position(xs[0]));
+        assert_eq!(normal, Tuple4D::new_vector(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn close_balls_blend_together() {
+        let metaballs = Metaballs::new(0.125)
+            .with_ball(Tuple4D::new_point(-0.75, 0.0, 0.0), 1.0, 1.0)
+            .with_ball(Tuple4D::new_point(0.75, 0.0, 0.0), 1.0, 1.0);
+        // on their own neither ball reaches x = 0, their surfaces are sqrt(0.5) from the centers
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 5.0, 0.0),
+            Tuple4D::new_vector(0.0, -1.0, 0.0),
+        );
+        let xs = metaballs.local_intersect(&ray);
+        assert_eq!(xs.len(), 2);
+        assert!(xs[0] < 4.9);
+        let axis = Ray::new(
+            Tuple4D::new_point(-5.0, 0.0, 0.0),
+            Tuple4D::new_vector(1.0, 0.0, 0.0),
+        );
+        assert_eq!(metaballs.local_intersect(&axis).len(), 2);
+    }
+
+    #[test]
+    fn ray_missing_metaballs() {
+        let metaballs =
+            Metaballs::new(0.125).with_ball(Tuple4D::new_point(0.0, 0.0, 0.0), 1.0, 1.0);
+        // passes within the radius of the ball but outside its surface
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 0.8, -5.0),
+            Tuple4D::new_vector(0.0, 0.0, 1.0),
+        );
+        assert!(metaballs.local_intersect(&ray).is_empty());
+        assert!(Metaballs::new(0.5).local_intersect(&ray).is_empty());
+    }
+
+    #[test]
+    fn bounds_of_metaballs_ignore_negative_balls() {
+        let metaballs = Metaballs::new(0.125)
+            .with_ball(Tuple4D::new_point(1.0, 0.0, 0.0), 1.0, 1.0)
+            .with_ball(Tuple4D::new_point(0.0, 2.0, 0.0), 0.5, 1.0)
+            .with_ball(Tuple4D::new_point(0.0, 0.0, 5.0), 1.0, -1.0);
+        let bounds = metaballs.bounds();
+        assert_eq!(bounds.min, Tuple4D::new_point(-0.5, -1.0, -1.0));
+        assert_eq!(bounds.max, Tuple4D::new_point(2.0, 2.5, 1.0));
+    }
+
+    #[test]
+    #[should_panic(expected = "metaball radius has to be positive")]
+    fn balls_need_a_radius() {
+        Metaballs::new(0.125).with_ball(Tuple4D::new_point(0.0, 0.0, 0.0), 0.0, 1.0);
+    }
+
+    #[test]
+    fn balls_without_a_radius_are_ignored() {
+        let mut metaballs =
+            Metaballs::new(0.125).with_ball(Tuple4D::new_point(0.0, 0.0, 0.0), 1.0, 1.0);
+        for radius in [0.0, -1.0, Float::NAN] {
+            metaballs.balls.push(Metaball {
+                center: Tuple4D::new_point(0.0, 0.0, 0.0),
+                radius,
+                weight: 1.0,
+            });
+        }
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 0.0, -5.0),
+            Tuple4D::new_vector(0.0, 0.0, 1.0),
+        );
+        assert_eq!(metaballs.local_intersect(&ray).len(), 2);
+        assert_eq!(metaballs.bounds().max, Tuple4D::new_point(1.0, 1.0, 1.0));
+    }
+}
@@ -0,0 +1,86 @@
+use crate::math::{Float, Tuple4D, FLOAT_EQ_EPS};
+use crate::shapes::Shape;
+use crate::{Bounds, Ray};
+
+// infinite plane spanned by the x and z axes
+#[derive(Debug, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Plane;
+
+impl Shape for Plane {
+    fn local_intersect(&self, ray: &Ray) -> Vec<Float> {
+        if ray.direction.y.abs() < FLOAT_EQ_EPS {
+            return vec![];
+        }
+        vec![-ray.origin.y / ray.direction.y]
+    }
+
+    fn local_normal_at(&self, _point: Tuple4D) -> Tuple4D {
+        Tuple4D::new_vector(0.0, 1.0, 0.0)
+    }
+
+    fn bounds(&self) -> Bounds {
+        Bounds::new(
+            Tuple4D::new_point(Float::NEG_INFINITY, 0.0, Float::NEG_INFINITY),
+            Tuple4D::new_point(Float::INFINITY, 0.0, Float::INFINITY),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normal_is_constant_everywhere() {
+        let normal = Tuple4D::new_vector(0.0, 1.0, 0.0);
+        assert_eq!(
+            Plane.local_normal_at(Tuple4D::new_point(0.0, 0.0, 0.0)),
+            normal
+        );
+        assert_eq!(
+            Plane.local_normal_at(Tuple4D::new_point(10.0, 0.0, -10.0)),
+            normal
+        );
+        assert_eq!(
+            Plane.local_normal_at(Tuple4D::new_point(-5.0, 0.0, 150.0)),
+            normal
+        );
+    }
+
+    #[test]
+    fn intersect_with_parallel_ray() {
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 10.0, 0.0),
+            Tuple4D::new_vector(0.0, 0.0, 1.0),
+        );
+        assert!(Plane.local_intersect(&ray).is_empty());
+    }
+
+    #[test]
+    fn intersect_with_coplanar_ray() {
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 0.0, 0.0),
+            Tuple4D::new_vector(0.0, 0.0, 1.0),
+        );
+        assert!(Plane.local_intersect(&ray).is_empty());
+    }
+
+    #[test]
+    fn ray_intersecting_plane_from_above() {
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 1.0, 0.0),
+            Tuple4D::new_vector(0.0, -1.0, 0.0),
+        );
+        assert_eq!(Plane.local_intersect(&ray), vec![1.0]);
+    }
+
+    #[test]
+    fn ray_intersecting_plane_from_below() {
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, -1.0, 0.0),
+            Tuple4D::new_vector(0.0, 1.0, 0.0),
+        );
+        assert_eq!(Plane.local_intersect(&ray), vec![1.0]);
+    }
+}
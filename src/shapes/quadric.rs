@@ -0,0 +1,193 @@
+use crate::math::{solve_quadratic, Float, Tuple4D, FLOAT_EQ_EPS};
+use crate::shapes::Shape;
+use crate::{Bounds, Ray};
+
+// surface where a x^2 + b y^2 + c z^2 + d xy + e xz + f yz + g x + h y + i z + j = 0, with the
+// coefficients a to j in this order. Most quadrics are unbounded, parts of the surface outside of
+// bounds are cut away
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Quadric {
+    pub coefficients: [Float; 10],
+    pub bounds: Bounds,
+}
+
+impl Quadric {
+    pub fn new(coefficients: [Float; 10]) -> Quadric {
+        Quadric {
+            coefficients,
+            bounds: Bounds::infinite(),
+        }
+    }
+
+    pub fn with_bounds(self, bounds: Bounds) -> Quadric {
+        Quadric { bounds, ..self }
+    }
+
+    pub fn ellipsoid(rx: Float, ry: Float, rz: Float) -> Quadric {
+        Quadric::new([
+            1.0 / (rx * rx),
+            1.0 / (ry * ry),
+            1.0 / (rz * rz),
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            -1.0,
+        ])
+        .with_bounds(Bounds::new(
+            Tuple4D::new_point(-rx, -ry, -rz),
+            Tuple4D::new_point(rx, ry, rz),
+        ))
+    }
+
+    // y = x^2 + z^2, opening upwards
+    pub fn paraboloid() -> Quadric {
+        Quadric::new([1.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, -1.0, 0.0, 0.0])
+    }
+
+    // x^2 + z^2 - y^2 = 1 around the y axis, a single sheet with a waist of radius 1
+    pub fn hyperboloid() -> Quadric {
+        Quadric::new([1.0, -1.0, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, -1.0])
+    }
+
+    fn in_bounds(&self, point: Tuple4D) -> bool {
+        let bounds = self.bounds;
+        (bounds.min.x - FLOAT_EQ_EPS..=bounds.max.x + FLOAT_EQ_EPS).contains(&point.x)
+            && (bounds.min.y - FLOAT_EQ_EPS..=bounds.max.y + FLOAT_EQ_EPS).contains(&point.y)
+            && (bounds.min.z - FLOAT_EQ_EPS..=bounds.max.z + FLOAT_EQ_EPS).contains(&point.z)
+    }
+}
+
+impl Shape for Quadric {
+    fn local_intersect(&self, ray: &Ray) -> Vec<Float> {
+        let [a, b, c, d, e, f, g, h, i, j] = self.coefficients;
+        let (o, dir) = (ray.origin, ray.direction);
+        let quadratic = a * dir.x * dir.x
+            + b * dir.y * dir.y
+            + c * dir.z * dir.z
+            + d * dir.x * dir.y
+            + e * dir.x * dir.z
+            + f * dir.y * dir.z;
+        let linear = 2.0 * (a * o.x * dir.x + b * o.y * dir.y + c * o.z * dir.z)
+            + d * (o.x * dir.y + o.y * dir.x)
+            + e * (o.x * dir.z + o.z * dir.x)
+            + f * (o.y * dir.z + o.z * dir.y)
+            + g * dir.x
+            + h * dir.y
+            + i * dir.z;
+        let constant = a * o.x * o.x
+            + b * o.y * o.y
+            + c * o.z * o.z
+            + d * o.x * o.y
+            + e * o.x * o.z
+            + f * o.y * o.z
+            + g * o.x
+            + h * o.y
+            + i * o.z
+            + j;
+        solve_quadratic(quadratic, linear, constant)
+            .into_iter()
+            .filter(|&t| self.in_bounds(ray.position(t)))
+            .collect()
+    }
+
+    // gradient of the implicit function
+    fn local_normal_at(&self, point: Tuple4D) -> Tuple4D {
+        let [a, b, c, d, e, f, g, h, i, _] = self.coefficients;
+        let (x, y, z) = (point.x, point.y, point.z);
+        Tuple4D::new_vector(
+            2.0 * a * x + d * y + e * z + g,
+            2.0 * b * y + d * x + f * z + h,
+            2.0 * c * z + e * x + f * y + i,
+        )
+        .normalize()
+    }
+
+    fn bounds(&self) -> Bounds {
+        self.bounds
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unit_ellipsoid_is_sphere() {
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 0.0, -5.0),
+            Tuple4D::new_vector(0.0, 0.0, 1.0),
+        );
+        let sphere = Quadric::ellipsoid(1.0, 1.0, 1.0);
+        assert_eq!(sphere.local_intersect(&ray), vec![4.0, 6.0]);
+        assert_eq!(
+            sphere.local_normal_at(Tuple4D::new_point(0.0, 0.0, -1.0)),
+            Tuple4D::new_vector(0.0, 0.0, -1.0)
+        );
+    }
+
+    #[test]
+    fn stretched_ellipsoid() {
+        let ellipsoid = Quadric::ellipsoid(1.0, 2.0, 1.0);
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 5.0, 0.0),
+            Tuple4D::new_vector(0.0, -1.0, 0.0),
+        );
+        assert_eq!(ellipsoid.local_intersect(&ray), vec![3.0, 7.0]);
+        assert_eq!(ellipsoid.bounds().max, Tuple4D::new_point(1.0, 2.0, 1.0));
+    }
+
+    #[test]
+    fn ray_along_axis_of_paraboloid_hits_once() {
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 5.0, 0.0),
+            Tuple4D::new_vector(0.0, -1.0, 0.0),
+        );
+        let paraboloid = Quadric::paraboloid();
+        assert_eq!(paraboloid.local_intersect(&ray), vec![5.0]);
+        assert_eq!(
+            paraboloid.local_normal_at(Tuple4D::new_point(0.0, 0.0, 0.0)),
+            Tuple4D::new_vector(0.0, -1.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn bounds_cut_off_quadric() {
+        let dish = Quadric::paraboloid().with_bounds(Bounds::new(
+            Tuple4D::new_point(-1.0, 0.0, -1.0),
+            Tuple4D::new_point(1.0, 1.0, 1.0),
+        ));
+        let inside = Ray::new(
+            Tuple4D::new_point(-5.0, 0.25, 0.0),
+            Tuple4D::new_vector(1.0, 0.0, 0.0),
+        );
+        assert_eq!(dish.local_intersect(&inside), vec![4.5, 5.5]);
+        let above = Ray::new(
+            Tuple4D::new_point(-5.0, 4.0, 0.0),
+            Tuple4D::new_vector(1.0, 0.0, 0.0),
+        );
+        assert!(dish.local_intersect(&above).is_empty());
+        assert_eq!(
+            Quadric::paraboloid().local_intersect(&above),
+            vec![3.0, 7.0]
+        );
+    }
+
+    #[test]
+    fn hyperboloid_has_waist() {
+        let hyperboloid = Quadric::hyperboloid();
+        let ray = Ray::new(
+            Tuple4D::new_point(-5.0, 0.0, 0.0),
+            Tuple4D::new_vector(1.0, 0.0, 0.0),
+        );
+        assert_eq!(hyperboloid.local_intersect(&ray), vec![4.0, 6.0]);
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 5.0, 0.0),
+            Tuple4D::new_vector(0.0, -1.0, 0.0),
+        );
+        assert!(hyperboloid.local_intersect(&ray).is_empty());
+    }
+}
@@ -0,0 +1,285 @@
+use std::fmt;
+use std::sync::Arc;
+
+use crate::math::{Float, Tuple4D};
+use crate::shapes::Shape;
+use crate::{Bounds, Ray};
+
+pub type DistanceFunction = Arc<dyn Fn(Tuple4D) -> Float + Send + Sync>;
+
+// surface where a signed distance function is zero, negative values are inside. The ray is sphere
+// traced through bounds: it advances by the distance to the closest surface, which can not skip
+// anything as long as the function never overestimates the distance
+#[derive(Clone)]
+pub struct SdfShape {
+    pub distance: DistanceFunction,
+    pub bounds: Bounds,
+    pub max_steps: usize,
+    // the surface is located up to this precision, also the smallest step taken along the ray
+    pub epsilon: Float,
+    // marching stops here for unbounded functions
+    pub max_distance: Float,
+}
+
+impl SdfShape {
+    pub fn new(
+        distance: impl Fn(Tuple4D) -> Float + Send + Sync + 'static,
+        bounds: Bounds,
+    ) -> SdfShape {
+        SdfShape {
+            distance: Arc::new(distance),
+            bounds,
+            max_steps: 512,
+            epsilon: 1e-4,
+            max_distance: 1000.0,
+        }
+    }
+
+    // the mandelbulb fractal, the 3d version of the mandelbrot set with z^power + c in spherical
+    // coordinates. More iterations add finer detail, power 8 gives the classic bulb
+    pub fn mandelbulb(power: Float, iterations: usize) -> SdfShape {
+        SdfShape {
+            epsilon: 1e-3,
+            ..SdfShape::new(
+                move |point| mandelbulb_distance(point, power, iterations),
+                // every point further than 2 away from the origin escapes
+                Bounds::new(
+                    Tuple4D::new_point(-2.0, -2.0, -2.0),
+                    Tuple4D::new_point(2.0, 2.0, 2.0),
+                ),
+            )
+        }
+    }
+
+    fn distance_at(&self, point: Tuple4D) -> Float {
+        (self.distance)(point)
+    }
+
+    // the crossing between two points of the ray on different sides of the surface
+    fn bisect(
+        &self,
+        ray: &Ray,
+        mut outside_t: Float,
+        mut inside_t: Float,
+        outside_is_positive: bool,
+    ) -> Float {
+        for _ in 0..32 {
+            let mid = (outside_t + inside_t) / 2.0;
+            if (self.distance_at(ray.position(mid)) >= 0.0) == outside_is_positive {
+                outside_t = mid;
+            } else {
+                inside_t = mid;
+            }
+            if (inside_t - outside_t).abs() < self.epsilon * 1e-3 {
+                break;
+            }
+        }
+        (outside_t + inside_t) / 2.0
+    }
+}
+
+impl fmt::Debug for SdfShape {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SdfShape")
+            .field("bounds", &self.bounds)
+            .field("max_steps", &self.max_steps)
+            .field("epsilon", &self.epsilon)
+            .field("max_distance", &self.max_distance)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Shape for SdfShape {
+    // every change of sign along the ray is a hit, so rays leaving the inside of the shape are found
+    // as well. Steps never get smaller than epsilon, a crossing within the last step is then refined
+    // by bisection
+    fn local_intersect(&self, ray: &Ray) -> Vec<Float> {
+        let Some((t_min, t_max)) = self.bounds.t_range(ray) else {
+            return vec![];
+        };
+        let speed = ray.direction.magnitude();
+        if speed == 0.0 {
+            return vec![];
+        }
+        let t_max = t_max.min(self.max_distance / speed);
+        let mut xs = vec![];
+        // starting a bit in front of the bounds catches surfaces touching them
+        let mut t = t_min.max(-self.max_distance / speed) - self.epsilon / speed;
+        let mut distance = self.distance_at(ray.position(t));
+        for _ in 0..self.max_steps {
+            let next_t = t + distance.abs().max(self.epsilon) / speed;
+            if next_t > t_max {
+                break;
+            }
+            let next_distance = self.distance_at(ray.position(next_t));
+            if (next_distance >= 0.0) != (distance >= 0.0) {
+                xs.push(self.bisect(ray, t, next_t, distance >= 0.0));
+            }
+            t = next_t;
+            distance = next_distance;
+        }
+        xs
+    }
+
+    // central differences of the distance function
+    fn local_normal_at(&self, point: Tuple4D) -> Tuple4D {
+        let h = self.epsilon;
+        let gradient =
+            |axis: Tuple4D| self.distance_at(point + axis * h) - self.distance_at(point - axis * h);
+        Tuple4D::new_vector(
+            gradient(Tuple4D::new_vector(1.0, 0.0, 0.0)),
+            gradient(Tuple4D::new_vector(0.0, 1.0, 0.0)),
+            gradient(Tuple4D::new_vector(0.0, 0.0, 1.0)),
+        )
+        .normalize()
+    }
+
+    fn bounds(&self) -> Bounds {
+        self.bounds
+    }
+}
+
+// estimated distance to the surface of the mandelbulb from the derivative of the iteration. The
+// estimate itself is unsigned, points that do not escape within the iterations are inside and get a
+// negative distance
+pub fn mandelbulb_distance(point: Tuple4D, power: Float, iterations: usize) -> Float {
+    let c = Tuple4D::new_vector(point.x, point.y, point.z);
+    let mut z = c;
+    let mut derivative = 1.0;
+    let mut radius = z.magnitude();
+    // the estimate is too coarse before the first iteration, but the set lies within radius 2
+    if radius > 2.0 {
+        return radius - 2.0;
+    }
+    for _ in 0..iterations {
+        if radius > 2.0 {
+            return 0.5 * radius.ln() * radius / derivative;
+        }
+        if radius == 0.0 {
+            z = c;
+            radius = z.magnitude();
+            continue;
+        }
+        let theta = (z.z / radius).acos() * power;
+        let phi = z.y.atan2(z.x) * power;
+        derivative = radius.powf(power - 1.0) * power * derivative + 1.0;
+        z = Tuple4D::new_vector(
+            theta.sin() * phi.cos(),
+            theta.sin() * phi.sin(),
+            theta.cos(),
+        ) * radius.powf(power)
+            + c;
+        radius = z.magnitude();
+    }
+    if radius > 2.0 {
+        return 0.5 * radius.ln() * radius / derivative;
+    }
+    // the estimate is undefined at the origin, max ignores the nan
+    -(0.5 * radius.ln() * radius / derivative)
+        .abs()
+        .max(Float::MIN_POSITIVE)
+}
+
+// minimum that blends the two distances within k of each other, unites two shapes with a smooth
+// fillet instead of a crease
+pub fn smooth_min(a: Float, b: Float, k: Float) -> Float {
+    if k <= 0.0 {
+        return a.min(b);
+    }
+    let h = (0.5 + 0.5 * (b - a) / k).clamp(0.0, 1.0);
+    b + (a - b) * h - k * h * (1.0 - h)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::{float_eq, FLOAT_EQ_EPS};
+
+    use super::*;
+
+    fn unit_sphere() -> SdfShape {
+        SdfShape::new(
+            |point| (point - Tuple4D::new_point(0.0, 0.0, 0.0)).magnitude() - 1.0,
+            Bounds::new(
+                Tuple4D::new_point(-1.0, -1.0, -1.0),
+                Tuple4D::new_point(1.0, 1.0, 1.0),
+            ),
+        )
+    }
+
+    #[test]
+    fn sphere_tracing_finds_entry_and_exit() {
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 0.0, -5.0),
+            Tuple4D::new_vector(0.0, 0.0, 2.0),
+        );
+        let xs = unit_sphere().local_intersect(&ray);
+        assert_eq!(xs.len(), 2);
+        assert!(float_eq(xs[0], 2.0, FLOAT_EQ_EPS));
+        assert!(float_eq(xs[1], 3.0, FLOAT_EQ_EPS));
+    }
+
+    #[test]
+    fn ray_missing_sdf_shape() {
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 0.9, -5.0),
+            Tuple4D::new_vector(0.5, 0.0, 1.0),
+        );
+        assert!(unit_sphere().local_intersect(&ray).is_empty());
+    }
+
+    #[test]
+    fn normal_from_gradient() {
+        let normal = unit_sphere().local_normal_at(Tuple4D::new_point(0.0, 0.6, 0.8));
+        assert_eq!(normal, Tuple4D::new_vector(0.0, 0.6, 0.8));
+    }
+
+    #[test]
+    fn unbounded_shape_is_marched_up_to_max_distance() {
+        // the plane y = -1
+        let mut floor = SdfShape::new(|point| point.y + 1.0, Bounds::infinite());
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 1.0, 0.0),
+            Tuple4D::new_vector(0.0, -1.0, 0.0),
+        );
+        let xs = floor.local_intersect(&ray);
+        assert_eq!(xs.len(), 1);
+        assert!(float_eq(xs[0], 2.0, FLOAT_EQ_EPS));
+        floor.max_distance = 1.5;
+        assert!(floor.local_intersect(&ray).is_empty());
+    }
+
+    #[test]
+    fn mandelbulb_inside_and_outside() {
+        assert!(mandelbulb_distance(Tuple4D::new_point(0.0, 0.0, 0.0), 8.0, 10) <= 0.0);
+        assert!(mandelbulb_distance(Tuple4D::new_point(0.1, 0.2, -0.1), 8.0, 10) < 0.0);
+        let far = mandelbulb_distance(Tuple4D::new_point(0.0, 0.0, -5.0), 8.0, 10);
+        // the bulb reaches about 1.1 along the axes, the estimate may be lower than the distance
+        assert_eq!(far, 3.0);
+        let near = mandelbulb_distance(Tuple4D::new_point(0.0, 0.0, -1.5), 8.0, 10);
+        assert!(near > 0.0 && near < 0.5);
+    }
+
+    #[test]
+    fn ray_hits_mandelbulb() {
+        let mandelbulb = SdfShape::mandelbulb(8.0, 10);
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 0.0, -5.0),
+            Tuple4D::new_vector(0.0, 0.0, 1.0),
+        );
+        let xs = mandelbulb.local_intersect(&ray);
+        assert!(!xs.is_empty());
+        assert!(xs[0] > 3.0 && xs[0] < 4.5, "first hit at {}", xs[0]);
+        let miss = Ray::new(
+            Tuple4D::new_point(0.0, 1.9, -5.0),
+            Tuple4D::new_vector(0.0, 0.0, 1.0),
+        );
+        assert!(mandelbulb.local_intersect(&miss).is_empty());
+    }
+
+    #[test]
+    fn smooth_min_blends_close_distances() {
+        assert_eq!(smooth_min(1.0, 3.0, 0.5), 1.0);
+        assert_eq!(smooth_min(1.0, 1.0, 0.5), 0.875);
+        assert_eq!(smooth_min(1.0, 1.0, 0.0), 1.0);
+    }
+}
@@ -0,0 +1,95 @@
+use crate::math::{solve_quadratic, Float, Tuple4D};
+use crate::shapes::Shape;
+use crate::{Bounds, Ray};
+
+#[derive(Debug, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Sphere;
+
+impl Shape for Sphere {
+    fn local_intersect(&self, ray: &Ray) -> Vec<Float> {
+        let sphere_to_ray = ray.origin - Tuple4D::new_point(0.0, 0.0, 0.0);
+        let a = ray.direction.dot(ray.direction);
+        let b = 2.0 * ray.direction.dot(sphere_to_ray);
+        let c = sphere_to_ray.dot(sphere_to_ray) - 1.0;
+        // the textbook formula loses the nearer hit of rays from far away to cancellation
+        solve_quadratic(a, b, c)
+    }
+
+    fn local_normal_at(&self, point: Tuple4D) -> Tuple4D {
+        point - Tuple4D::new_point(0.0, 0.0, 0.0)
+    }
+
+    fn bounds(&self) -> Bounds {
+        Bounds::new(
+            Tuple4D::new_point(-1.0, -1.0, -1.0),
+            Tuple4D::new_point(1.0, 1.0, 1.0),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ray_intersects_sphere_at_two_points() {
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 0.0, -5.0),
+            Tuple4D::new_vector(0.0, 0.0, 1.0),
+        );
+        assert_eq!(Sphere.local_intersect(&ray), vec![4.0, 6.0]);
+    }
+
+    #[test]
+    fn ray_intersects_sphere_at_tangent() {
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 1.0, -5.0),
+            Tuple4D::new_vector(0.0, 0.0, 1.0),
+        );
+        assert_eq!(Sphere.local_intersect(&ray), vec![5.0, 5.0]);
+    }
+
+    #[test]
+    fn ray_misses_sphere() {
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 2.0, -5.0),
+            Tuple4D::new_vector(0.0, 0.0, 1.0),
+        );
+        assert!(Sphere.local_intersect(&ray).is_empty());
+    }
+
+    #[test]
+    fn ray_originates_inside_sphere() {
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 0.0, 0.0),
+            Tuple4D::new_vector(0.0, 0.0, 1.0),
+        );
+        assert_eq!(Sphere.local_intersect(&ray), vec![-1.0, 1.0]);
+    }
+
+    #[test]
+    fn sphere_is_behind_ray() {
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 0.0, 5.0),
+            Tuple4D::new_vector(0.0, 0.0, 1.0),
+        );
+        assert_eq!(Sphere.local_intersect(&ray), vec![-6.0, -4.0]);
+    }
+
+    #[test]
+    fn normal_on_sphere_axes() {
+        assert_eq!(
+            Sphere.local_normal_at(Tuple4D::new_point(1.0, 0.0, 0.0)),
+            Tuple4D::new_vector(1.0, 0.0, 0.0)
+        );
+        assert_eq!(
+            Sphere.local_normal_at(Tuple4D::new_point(0.0, 1.0, 0.0)),
+            Tuple4D::new_vector(0.0, 1.0, 0.0)
+        );
+        assert_eq!(
+            Sphere.local_normal_at(Tuple4D::new_point(0.0, 0.0, 1.0)),
+            Tuple4D::new_vector(0.0, 0.0, 1.0)
+        );
+    }
+}
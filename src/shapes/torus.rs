@@ -0,0 +1,143 @@
+use crate::math::{solve_quartic, Float, Tuple4D};
+use crate::shapes::Shape;
+use crate::{Bounds, Ray};
+
+// ring around the y axis, the center of the tube is major_radius away from the origin and the tube
+// itself is minor_radius thick
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Torus {
+    pub major_radius: Float,
+    pub minor_radius: Float,
+}
+
+impl Torus {
+    pub fn new(major_radius: Float, minor_radius: Float) -> Torus {
+        Torus {
+            major_radius,
+            minor_radius,
+        }
+    }
+}
+
+impl Shape for Torus {
+    // points on the surface satisfy (|p|^2 - R^2 - r^2)^2 = 4 R^2 (r^2 - y^2), inserting the ray gives
+    // a quartic in t
+    fn local_intersect(&self, ray: &Ray) -> Vec<Float> {
+        // the quartic is badly conditioned far away from the torus, rays that cannot hit are
+        // rejected before solving it
+        if !self.bounds().intersects(ray) {
+            return vec![];
+        }
+        let (major2, minor2) = (self.major_radius.powi(2), self.minor_radius.powi(2));
+        let origin = ray.origin - Tuple4D::new_point(0.0, 0.0, 0.0);
+        let direction = ray.direction;
+        let dir_dot_dir = direction.dot(direction);
+        let origin_dot_dir = origin.dot(direction);
+        let e = origin.dot(origin) - major2 - minor2;
+        let four_major2 = 4.0 * major2;
+        solve_quartic(
+            dir_dot_dir * dir_dot_dir,
+            4.0 * dir_dot_dir * origin_dot_dir,
+            2.0 * dir_dot_dir * e
+                + 4.0 * origin_dot_dir * origin_dot_dir
+                + four_major2 * direction.y * direction.y,
+            4.0 * origin_dot_dir * e + 2.0 * four_major2 * origin.y * direction.y,
+            e * e - four_major2 * (minor2 - origin.y * origin.y),
+        )
+    }
+
+    fn local_normal_at(&self, point: Tuple4D) -> Tuple4D {
+        let (major2, minor2) = (self.major_radius.powi(2), self.minor_radius.powi(2));
+        let distance2 = point.x * point.x + point.y * point.y + point.z * point.z;
+        Tuple4D::new_vector(
+            point.x * (distance2 - major2 - minor2),
+            point.y * (distance2 + major2 - minor2),
+            point.z * (distance2 - major2 - minor2),
+        )
+        .normalize()
+    }
+
+    fn bounds(&self) -> Bounds {
+        let outer = self.major_radius + self.minor_radius;
+        Bounds::new(
+            Tuple4D::new_point(-outer, -self.minor_radius, -outer),
+            Tuple4D::new_point(outer, self.minor_radius, outer),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::{float_eq, FLOAT_EQ_EPS};
+
+    use super::*;
+
+    #[test]
+    fn ray_through_both_sides_of_torus() {
+        let torus = Torus::new(1.0, 0.25);
+        let ray = Ray::new(
+            Tuple4D::new_point(-5.0, 0.0, 0.0),
+            Tuple4D::new_vector(1.0, 0.0, 0.0),
+        );
+        let xs = torus.local_intersect(&ray);
+        assert_eq!(xs.len(), 4);
+        for (t, expected) in xs.iter().zip([3.75, 4.25, 5.75, 6.25]) {
+            assert!(float_eq(*t, expected, FLOAT_EQ_EPS));
+        }
+    }
+
+    #[test]
+    fn ray_through_hole_misses_torus() {
+        let torus = Torus::new(1.0, 0.25);
+        for ray in [
+            Ray::new(
+                Tuple4D::new_point(0.0, 5.0, 0.0),
+                Tuple4D::new_vector(0.0, -1.0, 0.0),
+            ),
+            Ray::new(
+                Tuple4D::new_point(-5.0, 0.5, 0.0),
+                Tuple4D::new_vector(1.0, 0.0, 0.0),
+            ),
+        ] {
+            assert!(torus.local_intersect(&ray).is_empty());
+        }
+    }
+
+    #[test]
+    fn ray_hitting_tube_from_above() {
+        let torus = Torus::new(2.0, 0.5);
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 3.0, -2.0),
+            Tuple4D::new_vector(0.0, -1.0, 0.0),
+        );
+        let xs = torus.local_intersect(&ray);
+        assert_eq!(xs.len(), 2);
+        assert!(float_eq(xs[0], 2.5, FLOAT_EQ_EPS));
+        assert!(float_eq(xs[1], 3.5, FLOAT_EQ_EPS));
+    }
+
+    #[test]
+    fn normals_of_torus() {
+        let torus = Torus::new(1.0, 0.25);
+        let cases = [
+            ((1.25, 0.0, 0.0), (1.0, 0.0, 0.0)),
+            ((0.75, 0.0, 0.0), (-1.0, 0.0, 0.0)),
+            ((1.0, 0.25, 0.0), (0.0, 1.0, 0.0)),
+            ((0.0, -0.25, 1.0), (0.0, -1.0, 0.0)),
+        ];
+        for ((px, py, pz), (nx, ny, nz)) in cases {
+            assert_eq!(
+                torus.local_normal_at(Tuple4D::new_point(px, py, pz)),
+                Tuple4D::new_vector(nx, ny, nz)
+            );
+        }
+    }
+
+    #[test]
+    fn bounds_of_torus() {
+        let bounds = Torus::new(1.0, 0.25).bounds();
+        assert_eq!(bounds.min, Tuple4D::new_point(-1.25, -0.25, -1.25));
+        assert_eq!(bounds.max, Tuple4D::new_point(1.25, 0.25, 1.25));
+    }
+}
@@ -0,0 +1,327 @@
+use crate::math::{Float, Tuple4D, FLOAT_EQ_EPS};
+use crate::shapes::Shape;
+use crate::{Bounds, Ray};
+
+// flat triangle, or a smooth one if normals for the corners are given, which are then interpolated
+// across the surface to hide the facets of a mesh
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(from = "TriangleCorners", into = "TriangleCorners")
+)]
+pub struct Triangle {
+    p1: Tuple4D,
+    p2: Tuple4D,
+    p3: Tuple4D,
+    normals: Option<[Tuple4D; 3]>,
+    e1: Tuple4D,
+    e2: Tuple4D,
+    normal: Tuple4D,
+}
+
+impl Triangle {
+    pub fn new(p1: Tuple4D, p2: Tuple4D, p3: Tuple4D) -> Triangle {
+        let e1 = p2 - p1;
+        let e2 = p3 - p1;
+        Triangle {
+            p1,
+            p2,
+            p3,
+            normals: None,
+            e1,
+            e2,
+            normal: e2.cross(e1).normalize(),
+        }
+    }
+
+    pub fn smooth(
+        p1: Tuple4D,
+        p2: Tuple4D,
+        p3: Tuple4D,
+        n1: Tuple4D,
+        n2: Tuple4D,
+        n3: Tuple4D,
+    ) -> Triangle {
+        Triangle {
+            normals: Some([n1, n2, n3]),
+            ..Triangle::new(p1, p2, p3)
+        }
+    }
+
+    pub fn p1(&self) -> Tuple4D {
+        self.p1
+    }
+
+    pub fn p2(&self) -> Tuple4D {
+        self.p2
+    }
+
+    pub fn p3(&self) -> Tuple4D {
+        self.p3
+    }
+
+    // normals at p1, p2 and p3 of a smooth triangle
+    pub fn normals(&self) -> Option<[Tuple4D; 3]> {
+        self.normals
+    }
+
+    pub fn e1(&self) -> Tuple4D {
+        self.e1
+    }
+
+    pub fn e2(&self) -> Tuple4D {
+        self.e2
+    }
+
+    // normal of the plane the triangle lies in
+    pub fn face_normal(&self) -> Tuple4D {
+        self.normal
+    }
+
+    // weights of p2 and p3 for a point on the triangle, p1 gets the rest
+    pub fn barycentric(&self, point: Tuple4D) -> (Float, Float) {
+        let to_point = point - self.p1;
+        let d11 = self.e1.dot(self.e1);
+        let d12 = self.e1.dot(self.e2);
+        let d22 = self.e2.dot(self.e2);
+        let d1p = self.e1.dot(to_point);
+        let d2p = self.e2.dot(to_point);
+        let denominator = d11 * d22 - d12 * d12;
+        let u = (d22 * d1p - d12 * d2p) / denominator;
+        let v = (d11 * d2p - d12 * d1p) / denominator;
+        (u, v)
+    }
+}
+
+impl Shape for Triangle {
+    // watertight test of Woop, Benthin and Wald. The corners are moved into a space where the ray
+    // starts at the origin and points along z, the edge functions are then evaluated in 2d. Edges
+    // are computed the same way for both triangles sharing them, so rays hitting an edge exactly
+    // can not slip through the gap between the triangles
+    fn local_intersect(&self, ray: &Ray) -> Vec<Float> {
+        let direction = ray.direction;
+        let kz = (0..3)
+            .max_by(|&a, &b| direction[a].abs().total_cmp(&direction[b].abs()))
+            .unwrap();
+        let mut kx = (kz + 1) % 3;
+        let mut ky = (kx + 1) % 3;
+        // keeps the winding of the triangle
+        if direction[kz] < 0.0 {
+            std::mem::swap(&mut kx, &mut ky);
+        }
+        let shear_x = direction[kx] / direction[kz];
+        let shear_y = direction[ky] / direction[kz];
+        let shear_z = 1.0 / direction[kz];
+        let [a, b, c] = [self.p1, self.p2, self.p3].map(|point| point - ray.origin);
+        let sheared = |point: Tuple4D| {
+            (
+                point[kx] - shear_x * point[kz],
+                point[ky] - shear_y * point[kz],
+                shear_z * point[kz],
+            )
+        };
+        let (ax, ay, az) = sheared(a);
+        let (bx, by, bz) = sheared(b);
+        let (cx, cy, cz) = sheared(c);
+        let u = cx * by - cy * bx;
+        let v = ax * cy - ay * cx;
+        let w = bx * ay - by * ax;
+        if (u < 0.0 || v < 0.0 || w < 0.0) && (u > 0.0 || v > 0.0 || w > 0.0) {
+            return vec![];
+        }
+        let determinant = u + v + w;
+        // the ray lies in the plane of the triangle
+        if determinant.abs() < FLOAT_EQ_EPS * FLOAT_EQ_EPS {
+            return vec![];
+        }
+        vec![(u * az + v * bz + w * cz) / determinant]
+    }
+
+    fn local_normal_at(&self, point: Tuple4D) -> Tuple4D {
+        match self.normals {
+            Some([n1, n2, n3]) => {
+                let (u, v) = self.barycentric(point);
+                n2 * u + n3 * v + n1 * (1.0 - u - v)
+            }
+            None => self.normal,
+        }
+    }
+
+    fn bounds(&self) -> Bounds {
+        let mut bounds = Bounds::empty();
+        for point in [self.p1, self.p2, self.p3] {
+            bounds.add_point(point);
+        }
+        bounds
+    }
+}
+
+// only the corners and normals are serialized, the edges and the face normal are derived from them
+#[cfg(feature = "serde")]
+#[derive(Clone, Copy, serde::Serialize, serde::Deserialize)]
+struct TriangleCorners {
+    p1: Tuple4D,
+    p2: Tuple4D,
+    p3: Tuple4D,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    normals: Option<[Tuple4D; 3]>,
+}
+
+#[cfg(feature = "serde")]
+impl From<TriangleCorners> for Triangle {
+    fn from(corners: TriangleCorners) -> Self {
+        let triangle = Triangle::new(corners.p1, corners.p2, corners.p3);
+        Triangle {
+            normals: corners.normals,
+            ..triangle
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<Triangle> for TriangleCorners {
+    fn from(triangle: Triangle) -> Self {
+        TriangleCorners {
+            p1: triangle.p1,
+            p2: triangle.p2,
+            p3: triangle.p3,
+            normals: triangle.normals,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn triangle() -> Triangle {
+        Triangle::new(
+            Tuple4D::new_point(0.0, 1.0, 0.0),
+            Tuple4D::new_point(-1.0, 0.0, 0.0),
+            Tuple4D::new_point(1.0, 0.0, 0.0),
+        )
+    }
+
+    fn smooth_triangle() -> Triangle {
+        Triangle::smooth(
+            Tuple4D::new_point(0.0, 1.0, 0.0),
+            Tuple4D::new_point(-1.0, 0.0, 0.0),
+            Tuple4D::new_point(1.0, 0.0, 0.0),
+            Tuple4D::new_vector(0.0, 1.0, 0.0),
+            Tuple4D::new_vector(-1.0, 0.0, 0.0),
+            Tuple4D::new_vector(1.0, 0.0, 0.0),
+        )
+    }
+
+    #[test]
+    fn constructing_triangle() {
+        let triangle = triangle();
+        assert_eq!(triangle.e1(), Tuple4D::new_vector(-1.0, -1.0, 0.0));
+        assert_eq!(triangle.e2(), Tuple4D::new_vector(1.0, -1.0, 0.0));
+        assert_eq!(triangle.face_normal(), Tuple4D::new_vector(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn normal_of_flat_triangle_is_constant() {
+        let triangle = triangle();
+        for point in [
+            Tuple4D::new_point(0.0, 0.5, 0.0),
+            Tuple4D::new_point(-0.5, 0.75, 0.0),
+            Tuple4D::new_point(0.5, 0.25, 0.0),
+        ] {
+            assert_eq!(triangle.local_normal_at(point), triangle.face_normal());
+        }
+    }
+
+    #[test]
+    fn ray_parallel_to_triangle_misses() {
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, -1.0, -2.0),
+            Tuple4D::new_vector(0.0, 1.0, 0.0),
+        );
+        assert!(triangle().local_intersect(&ray).is_empty());
+    }
+
+    #[test]
+    fn ray_misses_triangle_edges() {
+        for origin in [
+            Tuple4D::new_point(1.0, 1.0, -2.0),
+            Tuple4D::new_point(-1.0, 1.0, -2.0),
+            Tuple4D::new_point(0.0, -1.0, -2.0),
+        ] {
+            let ray = Ray::new(origin, Tuple4D::new_vector(0.0, 0.0, 1.0));
+            assert!(triangle().local_intersect(&ray).is_empty());
+        }
+    }
+
+    #[test]
+    fn ray_strikes_triangle() {
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 0.5, -2.0),
+            Tuple4D::new_vector(0.0, 0.0, 1.0),
+        );
+        assert_eq!(triangle().local_intersect(&ray), vec![2.0]);
+    }
+
+    #[test]
+    fn rays_through_shared_edges_and_corners_do_not_leak() {
+        let corners = [
+            Tuple4D::new_point(-1.0, -1.0, 0.0),
+            Tuple4D::new_point(1.0, -1.0, 0.0),
+            Tuple4D::new_point(1.0, 1.0, 0.0),
+            Tuple4D::new_point(-1.0, 1.0, 0.0),
+        ];
+        let quad = [
+            Triangle::new(corners[0], corners[1], corners[2]),
+            Triangle::new(corners[0], corners[2], corners[3]),
+        ];
+        // all rays hit the diagonal or a corner of the quad
+        for (origin, direction) in [
+            ((0.3, 0.3, -2.0), (0.0, 0.0, 1.0)),
+            ((1.0 / 3.0, 1.0 / 3.0, -2.0), (0.0, 0.0, 1.0)),
+            ((-1.0, -1.0, -2.0), (0.0, 0.0, 1.0)),
+            ((0.1, 0.1, -2.0), (0.1, 0.1, 1.0)),
+            ((-0.5, 0.3, -2.0), (0.3, -0.1, 1.0)),
+        ] {
+            let origin = Tuple4D::new_point(origin.0, origin.1, origin.2);
+            let direction = Tuple4D::new_vector(direction.0, direction.1, direction.2);
+            let ray = Ray::new(origin, direction);
+            let hits: usize = quad.iter().map(|t| t.local_intersect(&ray).len()).sum();
+            assert!(hits >= 1, "ray from {origin:?} slipped through");
+        }
+    }
+
+    #[test]
+    fn smooth_triangle_interpolates_normal() {
+        let normal = smooth_triangle().local_normal_at(Tuple4D::new_point(-0.2, 0.3, 0.0));
+        assert_eq!(
+            normal.normalize(),
+            Tuple4D::new_vector(-0.5547, 0.83205, 0.0)
+        );
+    }
+
+    #[test]
+    fn barycentric_coordinates_of_corners() {
+        let triangle = triangle();
+        assert_eq!(triangle.barycentric(triangle.p1()), (0.0, 0.0));
+        assert_eq!(triangle.barycentric(triangle.p2()), (1.0, 0.0));
+        assert_eq!(triangle.barycentric(triangle.p3()), (0.0, 1.0));
+    }
+
+    #[test]
+    fn bounds_of_triangle() {
+        let bounds = triangle().bounds();
+        assert_eq!(bounds.min, Tuple4D::new_point(-1.0, 0.0, 0.0));
+        assert_eq!(bounds.max, Tuple4D::new_point(1.0, 1.0, 0.0));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serializing_triangle_derives_edges() {
+        let json = serde_json::to_string(&smooth_triangle()).unwrap();
+        assert!(!json.contains("e1"));
+        let restored: Triangle = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored, smooth_triangle());
+    }
+}
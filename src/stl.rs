@@ -0,0 +1,195 @@
+use std::fs;
+use std::io::{Error, ErrorKind};
+
+use crate::math::{Float, Tuple4D};
+use crate::mesh::smooth_normals;
+use crate::obj::parse_point;
+use crate::shapes::Triangle;
+use crate::Object;
+
+// size of the header and the triangle count of a binary stl file
+const BINARY_HEADER_LEN: usize = 84;
+// normal, three corners and the attribute byte count
+const BINARY_TRIANGLE_LEN: usize = 50;
+
+// triangles of an ascii or binary stl file. The facet normals stored in the file are ignored, as
+// many exporters leave them at zero, the normals are computed from the corners instead
+#[derive(Debug, Default)]
+pub struct StlFile {
+    pub name: String,
+    pub triangles: Vec<Triangle>,
+}
+
+impl StlFile {
+    pub fn from_file(filename: &str) -> std::io::Result<StlFile> {
+        let bytes = fs::read(filename)?;
+        parse_stl_file(&bytes).map_err(|err| Error::new(ErrorKind::InvalidData, err))
+    }
+
+    // stl files have no vertex normals, see smooth_normals
+    pub fn smooth_normals(&mut self, crease_angle: Float) {
+        self.triangles = smooth_normals(&self.triangles, crease_angle);
+    }
+
+    pub fn into_group(self) -> Object {
+        Object::group(
+            self.triangles
+                .into_iter()
+                .map(|triangle| Object::new(Box::new(triangle)))
+                .collect(),
+        )
+    }
+}
+
+// binary files may start with "solid" as well, so the size is checked against the triangle count
+// before treating the file as text
+pub fn parse_stl_file(bytes: &[u8]) -> Result<StlFile, String> {
+    if let Some(count) = binary_triangle_count(bytes) {
+        if bytes.len() == BINARY_HEADER_LEN + count * BINARY_TRIANGLE_LEN {
+            return parse_stl_binary(bytes);
+        }
+    }
+    match std::str::from_utf8(bytes) {
+        Ok(stl_str) if stl_str.trim_start().starts_with("solid") => parse_stl_ascii(stl_str),
+        _ => parse_stl_binary(bytes),
+    }
+}
+
+fn binary_triangle_count(bytes: &[u8]) -> Option<usize> {
+    let count = bytes.get(80..BINARY_HEADER_LEN)?;
+    Some(u32::from_le_bytes(count.try_into().ok()?) as usize)
+}
+
+pub fn parse_stl_binary(bytes: &[u8]) -> Result<StlFile, String> {
+    let count = binary_triangle_count(bytes).ok_or("stl file too short for header")?;
+    let expected_len = BINARY_HEADER_LEN + count * BINARY_TRIANGLE_LEN;
+    if bytes.len() < expected_len {
+        return Err(format!(
+            "stl file has {} bytes, expected {expected_len} for {count} triangles",
+            bytes.len()
+        ));
+    }
+    let name = String::from_utf8_lossy(&bytes[..80])
+        .trim_end_matches('\0')
+        .trim()
+        .to_string();
+    let float_at =
+        |offset: usize| f32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap()) as Float;
+    let point_at = |offset: usize| {
+        Tuple4D::new_point(float_at(offset), float_at(offset + 4), float_at(offset + 8))
+    };
+    let triangles = (0..count)
+        .map(|idx| {
+            // the corners follow the 12 bytes of the facet normal
+            let start = BINARY_HEADER_LEN + idx * BINARY_TRIANGLE_LEN + 12;
+            Triangle::new(point_at(start), point_at(start + 12), point_at(start + 24))
+        })
+        .collect();
+    Ok(StlFile { name, triangles })
+}
+
+// facets are read as "facet normal .. outer loop vertex .. vertex .. vertex .. endloop endfacet",
+// only the vertex lines matter
+pub fn parse_stl_ascii(stl_str: &str) -> Result<StlFile, String> {
+    let mut stl = StlFile::default();
+    let mut corners = vec![];
+    for (line_idx, line) in stl_str.lines().enumerate() {
+        let line_number = line_idx + 1;
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            Some("solid") => stl.name = tokens.collect::<Vec<_>>().join(" "),
+            Some("facet") => corners.clear(),
+            Some("vertex") => corners.push(parse_point(tokens, line_number)?),
+            Some("endfacet") => {
+                if corners.len() != 3 {
+                    return Err(format!(
+                        "facet with {} vertices ending on line {line_number}",
+                        corners.len()
+                    ));
+                }
+                stl.triangles
+                    .push(Triangle::new(corners[0], corners[1], corners[2]));
+            }
+            // outer loop, endloop and endsolid carry no data
+            _ => {}
+        }
+    }
+    Ok(stl)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Ray;
+
+    const TETRAHEDRON_FACE: &str = "solid face\n\
+        facet normal 0 0 -1\n\
+          outer loop\n\
+            vertex -1 0 0\n\
+            vertex 0 1 0\n\
+            vertex 1 0 0\n\
+          endloop\n\
+        endfacet\n\
+        endsolid face\n";
+
+    fn binary_stl(header: &[u8], triangles: &[[f32; 9]]) -> Vec<u8> {
+        let mut bytes = vec![0; 80];
+        bytes[..header.len()].copy_from_slice(header);
+        bytes.extend((triangles.len() as u32).to_le_bytes());
+        for corners in triangles {
+            bytes.extend([0u8; 12]);
+            for value in corners {
+                bytes.extend(value.to_le_bytes());
+            }
+            bytes.extend([0u8; 2]);
+        }
+        bytes
+    }
+
+    #[test]
+    fn parsing_ascii_stl() {
+        let stl = parse_stl_file(TETRAHEDRON_FACE.as_bytes()).unwrap();
+        assert_eq!(stl.name, "face");
+        assert_eq!(
+            stl.triangles,
+            vec![Triangle::new(
+                Tuple4D::new_point(-1.0, 0.0, 0.0),
+                Tuple4D::new_point(0.0, 1.0, 0.0),
+                Tuple4D::new_point(1.0, 0.0, 0.0),
+            )]
+        );
+    }
+
+    #[test]
+    fn parsing_binary_stl() {
+        let corners = [
+            [-1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0, 1.0, 0.0, 0.0, 0.0, -1.0],
+        ];
+        // header starting with solid must not be mistaken for an ascii file
+        let stl = parse_stl_file(&binary_stl(b"solid binary", &corners)).unwrap();
+        assert_eq!(stl.name, "solid binary");
+        assert_eq!(stl.triangles.len(), 2);
+        assert_eq!(stl.triangles[1].p1(), Tuple4D::new_point(0.0, 0.0, 1.0));
+        assert_eq!(stl.triangles[1].p3(), Tuple4D::new_point(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn invalid_stl_files_are_errors() {
+        assert!(parse_stl_file(&binary_stl(b"", &[[0.0; 9]])[..100]).is_err());
+        assert!(parse_stl_file(b"solid x\nfacet\nouter loop\nvertex 1 2\nendfacet").is_err());
+        assert!(parse_stl_file(b"solid x\nfacet\nouter loop\nvertex 1 2 3\nendfacet").is_err());
+    }
+
+    #[test]
+    fn converting_stl_file_to_group() {
+        let group = parse_stl_ascii(TETRAHEDRON_FACE).unwrap().into_group();
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 0.5, -5.0),
+            Tuple4D::new_vector(0.0, 0.0, 1.0),
+        );
+        let xs = group.intersect(&ray);
+        assert_eq!(xs.len(), 1);
+        assert_eq!(xs[0].t, 5.0);
+    }
+}
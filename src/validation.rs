@@ -0,0 +1,381 @@
+use std::fmt;
+
+use crate::math::Tuple4D;
+use crate::shapes::{Group, Instance, Triangle};
+use crate::{Camera, Light, Material, Object, Ray, Volume, World};
+
+// something in a world that most likely renders differently than intended. Objects are given by
+// their path of indices, the first one into the objects of the world and the following ones into
+// the children of groups
+#[derive(Debug, Clone, PartialEq)]
+pub enum SceneWarning {
+    // the object can not be rendered, its rays can not be transformed into object space
+    NonInvertibleTransform {
+        object: Vec<usize>,
+    },
+    NonFiniteMaterial {
+        object: Vec<usize>,
+        field: &'static str,
+    },
+    // empty groups and shapes that collapsed into a point
+    ZeroExtentBounds {
+        object: Vec<usize>,
+    },
+    // triangles whose corners lie on a line have no surface and no normal
+    DegenerateTriangle {
+        object: Vec<usize>,
+    },
+    // the light is enclosed by an opaque object and lights nothing outside of it
+    LightInsideObject {
+        light: usize,
+        object: Vec<usize>,
+    },
+    // a volume that is marched in steps that are not positive, it is rendered with the default
+    // step size instead. The object is none for the fog of the world
+    InvalidStepSize {
+        object: Option<Vec<usize>>,
+    },
+    // the camera can not turn pixels into rays, it has no view to look through
+    NonInvertibleCamera,
+}
+
+impl fmt::Display for SceneWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneWarning::NonInvertibleTransform { object } => {
+                write!(
+                    f,
+                    "{} has a transform that can not be inverted",
+                    path(object)
+                )
+            }
+            SceneWarning::NonFiniteMaterial { object, field } => {
+                write!(
+                    f,
+                    "{} has a material with a non finite {field}",
+                    path(object)
+                )
+            }
+            SceneWarning::ZeroExtentBounds { object } => {
+                write!(f, "{} has bounds without extent", path(object))
+            }
+            SceneWarning::DegenerateTriangle { object } => {
+                write!(f, "{} is a degenerate triangle", path(object))
+            }
+            SceneWarning::LightInsideObject { light, object } => {
+                write!(f, "light {light} is inside {}", path(object))
+            }
+            SceneWarning::InvalidStepSize {
+                object: Some(object),
+            } => {
+                write!(
+                    f,
+                    "{} has a volume with a step size of 0 or less",
+                    path(object)
+                )
+            }
+            SceneWarning::InvalidStepSize { object: None } => {
+                write!(f, "the fog has a step size of 0 or less")
+            }
+            SceneWarning::NonInvertibleCamera => {
+                write!(f, "the camera has a transform that can not be inverted")
+            }
+        }
+    }
+}
+
+impl World {
+    // looks for problems that do not make rendering fail but lead to black or broken images
+    pub fn validate(&self) -> Vec<SceneWarning> {
+        let mut warnings = vec![];
+        for (idx, object) in self.objects.iter().enumerate() {
+            validate_object(object, &mut vec![idx], &mut warnings);
+        }
+        if self.fog.as_ref().is_some_and(invalid_step_size) {
+            warnings.push(SceneWarning::InvalidStepSize { object: None });
+        }
+        for (light_idx, light) in self.lights.iter().enumerate() {
+            let Some(position) = light_position(light) else {
+                continue;
+            };
+            for (idx, object) in self.objects.iter().enumerate() {
+                if encloses(object, position) {
+                    warnings.push(SceneWarning::LightInsideObject {
+                        light: light_idx,
+                        object: vec![idx],
+                    });
+                }
+            }
+        }
+        warnings
+    }
+}
+
+impl SceneWarning {
+    // the scene would make rendering panic, as opposed to rendering an image that looks wrong
+    pub fn prevents_rendering(&self) -> bool {
+        matches!(
+            self,
+            SceneWarning::NonInvertibleTransform { .. } | SceneWarning::NonInvertibleCamera
+        )
+    }
+}
+
+impl Camera {
+    // the problems of the camera, in the same form as World::validate
+    pub fn validate(&self) -> Vec<SceneWarning> {
+        let transforms = [Some(self.transform.matrix()), self.transform_end];
+        if transforms
+            .iter()
+            .flatten()
+            .any(|transform| !transform.is_invertible())
+        {
+            vec![SceneWarning::NonInvertibleCamera]
+        } else {
+            vec![]
+        }
+    }
+}
+
+fn validate_object(object: &Object, path: &mut Vec<usize>, warnings: &mut Vec<SceneWarning>) {
+    let transforms = [Some(object.transform.matrix()), object.transform_end];
+    if transforms
+        .iter()
+        .flatten()
+        .any(|transform| !transform.is_invertible())
+    {
+        warnings.push(SceneWarning::NonInvertibleTransform {
+            object: path.clone(),
+        });
+    }
+    if let Some(field) = non_finite_field(&object.material) {
+        warnings.push(SceneWarning::NonFiniteMaterial {
+            object: path.clone(),
+            field,
+        });
+    }
+    if object
+        .material
+        .volume
+        .as_ref()
+        .is_some_and(invalid_step_size)
+    {
+        warnings.push(SceneWarning::InvalidStepSize {
+            object: Some(path.clone()),
+        });
+    }
+    let bounds = object.shape.bounds();
+    let extent = bounds.max - bounds.min;
+    if bounds.is_empty() || (extent.x == 0.0 && extent.y == 0.0 && extent.z == 0.0) {
+        warnings.push(SceneWarning::ZeroExtentBounds {
+            object: path.clone(),
+        });
+    }
+    let shape: &dyn std::any::Any = object.shape.as_ref();
+    if let Some(triangle) = shape.downcast_ref::<Triangle>() {
+        if triangle.e1().cross(triangle.e2()).magnitude() == 0.0 {
+            warnings.push(SceneWarning::DegenerateTriangle {
+                object: path.clone(),
+            });
+        }
+    } else if let Some(group) = shape.downcast_ref::<Group>() {
+        for (idx, child) in group.children().iter().enumerate() {
+            path.push(idx);
+            validate_object(child, path, warnings);
+            path.pop();
+        }
+    } else if let Some(instance) = shape.downcast_ref::<Instance>() {
+        if let Some(field) = instance.material.as_ref().and_then(non_finite_field) {
+            warnings.push(SceneWarning::NonFiniteMaterial {
+                object: path.clone(),
+                field,
+            });
+        }
+        // the prototype shows up as the only child of the instance
+        path.push(0);
+        validate_object(&instance.prototype, path, warnings);
+        path.pop();
+    }
+}
+
+fn non_finite_field(material: &Material) -> Option<&'static str> {
+    let colors = [
+        ("color", material.color),
+        ("absorption", material.absorption),
+        ("emissive", material.emissive),
+    ];
+    let values = [
+        ("ambient", material.ambient),
+        ("diffuse", material.diffuse),
+        ("specular", material.specular),
+        ("shininess", material.shininess),
+        ("reflective", material.reflective),
+        ("transparency", material.transparency),
+        ("refractive_index", material.refractive_index),
+        ("roughness", material.roughness),
+    ];
+    colors
+        .into_iter()
+        .find(|(_, color)| ![color.r, color.g, color.b].iter().all(|c| c.is_finite()))
+        .map(|(field, _)| field)
+        .or_else(|| {
+            values
+                .into_iter()
+                .find(|(_, value)| !value.is_finite())
+                .map(|(field, _)| field)
+        })
+}
+
+fn invalid_step_size(volume: &Volume) -> bool {
+    volume.step_size <= 0.0 || volume.step_size.is_nan()
+}
+
+fn light_position(light: &Light) -> Option<Tuple4D> {
+    match light {
+        Light::Point(light) => Some(light.position),
+        Light::Spot(light) => Some(light.position),
+        Light::Area(light) => Some(light.center()),
+        Light::Directional(_) => None,
+    }
+}
+
+// a point is inside of a closed surface if rays leaving it cross the surface an odd number of times.
+// Several directions have to agree, so open surfaces like planes are not mistaken as enclosing the
+// point. Transparent objects and volumes are skipped, a light inside of them still shines through
+fn encloses(object: &Object, point: Tuple4D) -> bool {
+    if object.material.transparency > 0.0 || object.material.volume.is_some() {
+        return false;
+    }
+    if object.transform.inverse().is_none() {
+        return false;
+    }
+    let directions = [
+        Tuple4D::new_vector(0.0, 1.0, 0.0),
+        Tuple4D::new_vector(0.0, -1.0, 0.0),
+        Tuple4D::new_vector(0.6, 0.0, 0.8),
+        Tuple4D::new_vector(-0.48, 0.6, -0.64),
+    ];
+    directions.iter().all(|&direction| {
+        let crossings = object
+            .intersect(&Ray::new(point, direction))
+            .iter()
+            .filter(|intersection| intersection.t > 1e-9)
+            .count();
+        crossings % 2 == 1
+    })
+}
+
+fn path(object: &[usize]) -> String {
+    let mut text = format!("object {}", object[0]);
+    for idx in &object[1..] {
+        text += &format!(" child {idx}");
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::{Float, Matrix4};
+    use crate::{Color, PointLight};
+
+    #[test]
+    fn default_world_has_no_warnings() {
+        assert!(World::default_world().validate().is_empty());
+    }
+
+    #[test]
+    fn broken_objects_are_reported() {
+        let mut world = World::new();
+        let mut flat = Object::sphere();
+        flat.transform = Matrix4::scaling(1.0, 0.0, 1.0).into();
+        world.objects.push(flat);
+        let mut nan = Object::cube();
+        nan.material.reflective = Float::NAN;
+        world.objects.push(nan);
+        let degenerate = Object::triangle(
+            Tuple4D::new_point(0.0, 0.0, 0.0),
+            Tuple4D::new_point(1.0, 1.0, 1.0),
+            Tuple4D::new_point(2.0, 2.0, 2.0),
+        );
+        world
+            .objects
+            .push(Object::group(vec![Object::group(vec![]), degenerate]));
+        assert_eq!(
+            world.validate(),
+            vec![
+                SceneWarning::NonInvertibleTransform { object: vec![0] },
+                SceneWarning::NonFiniteMaterial {
+                    object: vec![1],
+                    field: "reflective"
+                },
+                SceneWarning::ZeroExtentBounds { object: vec![2, 0] },
+                SceneWarning::DegenerateTriangle { object: vec![2, 1] },
+            ]
+        );
+        assert_eq!(
+            world.validate()[3].to_string(),
+            "object 2 child 1 is a degenerate triangle"
+        );
+    }
+
+    #[test]
+    fn light_inside_opaque_object() {
+        let mut world = World::default_world();
+        world.objects.push(Object::plane());
+        world.lights.push(
+            PointLight::new(Tuple4D::new_point(0.0, 0.1, 0.2), Color::new(1.0, 1.0, 1.0)).into(),
+        );
+        // inside both spheres of the default world, the plane does not enclose anything
+        assert_eq!(
+            world.validate(),
+            vec![
+                SceneWarning::LightInsideObject {
+                    light: 1,
+                    object: vec![0]
+                },
+                SceneWarning::LightInsideObject {
+                    light: 1,
+                    object: vec![1]
+                },
+            ]
+        );
+        world.objects[1].material.transparency = 0.9;
+        assert_eq!(world.validate().len(), 1);
+    }
+
+    #[test]
+    fn volumes_need_positive_step_sizes() {
+        let mut world = World::new();
+        let mut volume = Volume::new(0.5, Color::new(1.0, 1.0, 1.0));
+        volume.step_size = 0.0;
+        let mut object = Object::cube();
+        object.material.volume = Some(volume.clone());
+        world.objects.push(object);
+        volume.step_size = Float::NAN;
+        world.fog = Some(volume);
+        assert_eq!(
+            world.validate(),
+            vec![
+                SceneWarning::InvalidStepSize {
+                    object: Some(vec![0])
+                },
+                SceneWarning::InvalidStepSize { object: None },
+            ]
+        );
+        assert_eq!(
+            world.validate()[1].to_string(),
+            "the fog has a step size of 0 or less"
+        );
+    }
+
+    #[test]
+    fn cameras_need_invertible_transforms() {
+        let mut camera = crate::camera::test_camera(4);
+        assert!(camera.validate().is_empty());
+        camera.transform_end = Some(Matrix4::scaling(0.0, 1.0, 1.0));
+        assert_eq!(camera.validate(), vec![SceneWarning::NonInvertibleCamera]);
+        assert!(camera.validate()[0].prevents_rendering());
+        assert!(!SceneWarning::InvalidStepSize { object: None }.prevents_rendering());
+    }
+}
@@ -0,0 +1,227 @@
+use std::io::{BufWriter, Error, ErrorKind, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+
+use crate::math::Float;
+use crate::{Canvas, ColorEncoding};
+
+// receives the frames of an animation one after the other, so they do not have to be written to
+// disk as separate images
+pub trait FrameSink {
+    fn write_frame(&mut self, frame: &Canvas) -> std::io::Result<()>;
+}
+
+// uncompressed yuv4mpeg2 video, which ffmpeg and most video tools read directly. The colors are
+// converted to limited range bt.601 with the chroma at half the resolution (4:2:0). The size of the
+// video is taken from the first frame
+#[derive(Debug)]
+pub struct Y4mWriter<W: Write> {
+    writer: W,
+    frames_per_second: Float,
+    pub encoding: ColorEncoding,
+    size: Option<(usize, usize)>,
+}
+
+impl<W: Write> Y4mWriter<W> {
+    pub fn new(writer: W, frames_per_second: Float) -> Y4mWriter<W> {
+        Y4mWriter {
+            writer,
+            frames_per_second,
+            encoding: ColorEncoding::Srgb,
+            size: None,
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn write_header(&mut self, width: usize, height: usize) -> std::io::Result<()> {
+        // rates like 29.97 are written as fractions with a denominator of 1000
+        let (numerator, denominator) = if self.frames_per_second.fract() == 0.0 {
+            (self.frames_per_second as u64, 1)
+        } else {
+            ((self.frames_per_second * 1000.0).round() as u64, 1000)
+        };
+        writeln!(
+            self.writer,
+            "YUV4MPEG2 W{width} H{height} F{numerator}:{denominator} Ip A1:1 C420jpeg"
+        )
+    }
+}
+
+impl<W: Write> FrameSink for Y4mWriter<W> {
+    fn write_frame(&mut self, frame: &Canvas) -> std::io::Result<()> {
+        let size = (frame.width(), frame.height());
+        match self.size {
+            None => {
+                self.write_header(size.0, size.1)?;
+                self.size = Some(size);
+            }
+            Some(expected) if expected != size => {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!(
+                        "frame of {}x{} pixels in a video of {}x{} pixels",
+                        size.0, size.1, expected.0, expected.1
+                    ),
+                ));
+            }
+            Some(_) => {}
+        }
+        let frame = frame.encode(self.encoding);
+        self.writer.write_all(b"FRAME\n")?;
+        self.writer.write_all(&yuv420(&frame))
+    }
+}
+
+// the y plane followed by the u and v planes, each chroma sample is the average of up to 2x2 pixels
+fn yuv420(frame: &Canvas) -> Vec<u8> {
+    let (width, height) = (frame.width(), frame.height());
+    let (chroma_width, chroma_height) = (width.div_ceil(2), height.div_ceil(2));
+    let mut luma = Vec::with_capacity(width * height);
+    let mut u = vec![0.0; chroma_width * chroma_height];
+    let mut v = vec![0.0; chroma_width * chroma_height];
+    let mut counts = vec![0.0; chroma_width * chroma_height];
+    for y in 0..height {
+        for x in 0..width {
+            let color = frame.read_pixel(x, y);
+            let (r, g, b) = (
+                color.r.clamp(0.0, 1.0),
+                color.g.clamp(0.0, 1.0),
+                color.b.clamp(0.0, 1.0),
+            );
+            luma.push((16.0 + 65.481 * r + 128.553 * g + 24.966 * b).round() as u8);
+            let idx = y / 2 * chroma_width + x / 2;
+            u[idx] += -37.797 * r - 74.203 * g + 112.0 * b;
+            v[idx] += 112.0 * r - 93.786 * g - 18.214 * b;
+            counts[idx] += 1.0;
+        }
+    }
+    let chroma = |sums: Vec<Float>| {
+        sums.into_iter()
+            .zip(&counts)
+            .map(|(sum, count)| (128.0 + sum / count).round() as u8)
+    };
+    luma.into_iter().chain(chroma(u)).chain(chroma(v)).collect()
+}
+
+// streams the frames to an ffmpeg process, which encodes them into a video file
+#[derive(Debug)]
+pub struct FfmpegWriter {
+    child: Child,
+    frames: Y4mWriter<BufWriter<ChildStdin>>,
+}
+
+impl FfmpegWriter {
+    // the format of the video is picked by ffmpeg from the extension of output
+    pub fn new(output: &str, frames_per_second: Float) -> std::io::Result<FfmpegWriter> {
+        let mut command = Command::new("ffmpeg");
+        command
+            .args(["-y", "-loglevel", "error", "-f", "yuv4mpegpipe", "-i", "-"])
+            .args(["-pix_fmt", "yuv420p", output]);
+        FfmpegWriter::spawn(command, frames_per_second)
+    }
+
+    // command gets the frames as y4m on its standard input, for passing other arguments to ffmpeg
+    pub fn spawn(mut command: Command, frames_per_second: Float) -> std::io::Result<FfmpegWriter> {
+        let mut child = command.stdin(Stdio::piped()).spawn()?;
+        let stdin = child
+            .stdin
+            .take()
+            .ok_or_else(|| Error::other("no standard input for the video encoder"))?;
+        Ok(FfmpegWriter {
+            child,
+            frames: Y4mWriter::new(BufWriter::new(stdin), frames_per_second),
+        })
+    }
+
+    pub fn set_encoding(&mut self, encoding: ColorEncoding) {
+        self.frames.encoding = encoding;
+    }
+
+    // closes the input of the encoder and waits for it to write the video
+    pub fn finish(self) -> std::io::Result<()> {
+        let FfmpegWriter { mut child, frames } = self;
+        let mut stdin = frames.into_inner();
+        stdin.flush()?;
+        drop(stdin);
+        let status = child.wait()?;
+        if !status.success() {
+            return Err(Error::other(format!(
+                "the video encoder failed with {status}"
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl FrameSink for FfmpegWriter {
+    fn write_frame(&mut self, frame: &Canvas) -> std::io::Result<()> {
+        self.frames.write_frame(frame)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Color;
+
+    #[test]
+    fn y4m_header_and_frames() {
+        let mut writer = Y4mWriter::new(vec![], 24.0);
+        writer.encoding = ColorEncoding::Linear;
+        let mut frame = Canvas::create_canvas(3, 2);
+        frame.write_pixel(0, 0, Color::new(1.0, 1.0, 1.0));
+        writer.write_frame(&frame).unwrap();
+        writer.write_frame(&frame).unwrap();
+        let bytes = writer.into_inner();
+        let header = b"YUV4MPEG2 W3 H2 F24:1 Ip A1:1 C420jpeg\n";
+        assert!(bytes.starts_with(header));
+        let frame_bytes = &bytes[header.len()..];
+        // 6 luma samples and 2 chroma samples for each of u and v
+        assert_eq!(frame_bytes.len(), 2 * (6 + 6 + 4));
+        assert_eq!(&frame_bytes[..6], b"FRAME\n");
+        assert_eq!(&frame_bytes[6..12], [235, 16, 16, 16, 16, 16]);
+        // gray has no chroma
+        assert_eq!(&frame_bytes[12..16], [128, 128, 128, 128]);
+    }
+
+    #[test]
+    fn chroma_of_red() {
+        let frame = Canvas::new(2, 2, Color::new(1.0, 0.0, 0.0));
+        assert_eq!(yuv420(&frame), [81, 81, 81, 81, 90, 240]);
+    }
+
+    #[test]
+    fn fractional_frame_rates() {
+        let mut writer = Y4mWriter::new(vec![], 29.97);
+        writer.write_frame(&Canvas::create_canvas(1, 1)).unwrap();
+        assert!(writer
+            .into_inner()
+            .starts_with(b"YUV4MPEG2 W1 H1 F29970:1000 "));
+    }
+
+    #[test]
+    fn frames_have_to_keep_the_size() {
+        let mut writer = Y4mWriter::new(vec![], 24.0);
+        writer.write_frame(&Canvas::create_canvas(2, 2)).unwrap();
+        let err = writer
+            .write_frame(&Canvas::create_canvas(4, 2))
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn streaming_to_a_process() {
+        let mut command = Command::new("sh");
+        command.args(["-c", "test \"$(head -c 9)\" = YUV4MPEG2 && cat > /dev/null"]);
+        let mut writer = FfmpegWriter::spawn(command, 24.0).unwrap();
+        writer.write_frame(&Canvas::create_canvas(8, 8)).unwrap();
+        writer.finish().unwrap();
+        let mut command = Command::new("sh");
+        command.args(["-c", "cat > /dev/null; exit 3"]);
+        let writer = FfmpegWriter::spawn(command, 24.0).unwrap();
+        assert!(writer.finish().is_err());
+    }
+}
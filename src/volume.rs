@@ -0,0 +1,257 @@
+use std::fs;
+use std::io::{Error, ErrorKind};
+use std::sync::Arc;
+
+use crate::math::{Float, Tuple4D};
+use crate::Color;
+
+pub const DEFAULT_STEP_SIZE: Float = 0.1;
+
+// participating medium like fog, smoke or clouds. It is either spread over the whole world or
+// fills the inside of an object, whose surface then becomes invisible. Light is scattered towards
+// the camera by the medium, which makes the beams of light sources and their shadows visible
+#[derive(Debug, Clone, PartialEq)]
+pub struct Volume {
+    // extinction per unit distance
+    pub density: Float,
+    // color of the light scattered towards the camera
+    pub color: Color,
+    // distance between the points the ray is sampled at while marching through the volume, steps
+    // that are not positive are replaced by DEFAULT_STEP_SIZE
+    pub step_size: Float,
+    // scales the density per point, the medium is homogeneous without a grid
+    pub grid: Option<Arc<DensityGrid>>,
+}
+
+impl Volume {
+    pub fn new(density: Float, color: Color) -> Volume {
+        Volume {
+            density,
+            color,
+            step_size: DEFAULT_STEP_SIZE,
+            grid: None,
+        }
+    }
+
+    pub fn with_grid(density: Float, color: Color, grid: DensityGrid) -> Volume {
+        Volume {
+            grid: Some(Arc::new(grid)),
+            ..Volume::new(density, color)
+        }
+    }
+
+    // point is given in the space the volume lives in, world space for fog and object space otherwise
+    pub fn density_at(&self, point: Tuple4D) -> Float {
+        match &self.grid {
+            Some(grid) => self.density * grid.sample(point),
+            None => self.density,
+        }
+    }
+}
+
+// voxel grid of densities that fills the cube from -1 to 1 on every axis, which makes it fit
+// exactly into Object::cube(). Voxels are stored x first, then y, then z
+#[derive(Debug, Clone, PartialEq)]
+pub struct DensityGrid {
+    pub width: usize,
+    pub height: usize,
+    pub depth: usize,
+    pub data: Vec<Float>,
+}
+
+impl DensityGrid {
+    // fails unless there is one value per voxel
+    pub fn new(
+        width: usize,
+        height: usize,
+        depth: usize,
+        data: Vec<Float>,
+    ) -> Result<DensityGrid, String> {
+        let voxels = voxel_count(width, height, depth)?;
+        if data.len() != voxels {
+            return Err(format!(
+                "expected {voxels} densities for a {width}x{height}x{depth} grid, found {}",
+                data.len()
+            ));
+        }
+        Ok(DensityGrid {
+            width,
+            height,
+            depth,
+            data,
+        })
+    }
+
+    // ascii format: the dimensions "width height depth" followed by one number per voxel,
+    // separated by any whitespace. Lines starting with # are comments
+    pub fn from_ascii(filename: &str) -> std::io::Result<DensityGrid> {
+        let grid_str = fs::read_to_string(filename)?;
+        DensityGrid::from_ascii_str(&grid_str)
+            .map_err(|err| Error::new(ErrorKind::InvalidData, err))
+    }
+
+    // raw format: one byte per voxel without any header, 255 is mapped to a density of 1
+    pub fn from_raw(
+        filename: &str,
+        width: usize,
+        height: usize,
+        depth: usize,
+    ) -> std::io::Result<DensityGrid> {
+        let bytes = fs::read(filename)?;
+        DensityGrid::from_raw_bytes(&bytes, width, height, depth)
+            .map_err(|err| Error::new(ErrorKind::InvalidData, err))
+    }
+
+    fn from_ascii_str(grid_str: &str) -> Result<DensityGrid, String> {
+        let mut tokens = grid_str
+            .lines()
+            .filter(|line| !line.trim_start().starts_with('#'))
+            .flat_map(str::split_whitespace);
+        let mut next_dimension = || -> Result<usize, String> {
+            let token = tokens.next().ok_or("missing grid dimensions")?;
+            token
+                .parse::<usize>()
+                .map_err(|_| format!("invalid grid dimension {token}"))
+        };
+        let width = next_dimension()?;
+        let height = next_dimension()?;
+        let depth = next_dimension()?;
+        let data = tokens
+            .map(|token| {
+                token
+                    .parse::<Float>()
+                    .map_err(|_| format!("invalid density {token}"))
+            })
+            .collect::<Result<Vec<Float>, String>>()?;
+        DensityGrid::new(width, height, depth, data)
+    }
+
+    fn from_raw_bytes(
+        bytes: &[u8],
+        width: usize,
+        height: usize,
+        depth: usize,
+    ) -> Result<DensityGrid, String> {
+        let voxels = voxel_count(width, height, depth)?;
+        if bytes.len() != voxels {
+            return Err(format!(
+                "expected {voxels} bytes for a {width}x{height}x{depth} grid, found {}",
+                bytes.len()
+            ));
+        }
+        let data = bytes.iter().map(|&byte| byte as Float / 255.0).collect();
+        DensityGrid::new(width, height, depth, data)
+    }
+
+    fn voxel(&self, x: usize, y: usize, z: usize) -> Float {
+        self.data[(z * self.height + y) * self.width + x]
+    }
+
+    // trilinear interpolation between the voxel centers, zero outside of the grid
+    pub fn sample(&self, point: Tuple4D) -> Float {
+        if self.data.is_empty()
+            || [point.x, point.y, point.z]
+                .iter()
+                .any(|value| !(-1.0..=1.0).contains(value))
+        {
+            return 0.0;
+        }
+        // position in voxel units relative to the center of the first voxel
+        let to_grid = |value: Float, size: usize| -> (usize, usize, Float) {
+            let position =
+                ((value + 1.0) / 2.0 * size as Float - 0.5).clamp(0.0, (size - 1) as Float);
+            let lower = position.floor() as usize;
+            let upper = (lower + 1).min(size - 1);
+            (lower, upper, position - lower as Float)
+        };
+        let (x0, x1, tx) = to_grid(point.x, self.width);
+        let (y0, y1, ty) = to_grid(point.y, self.height);
+        let (z0, z1, tz) = to_grid(point.z, self.depth);
+        let lerp = |a: Float, b: Float, t: Float| a + (b - a) * t;
+        let along_x = |y, z| lerp(self.voxel(x0, y, z), self.voxel(x1, y, z), tx);
+        let front = lerp(along_x(y0, z0), along_x(y1, z0), ty);
+        let back = lerp(along_x(y0, z1), along_x(y1, z1), ty);
+        lerp(front, back, tz)
+    }
+}
+
+fn voxel_count(width: usize, height: usize, depth: usize) -> Result<usize, String> {
+    width
+        .checked_mul(height)
+        .and_then(|area| area.checked_mul(depth))
+        .ok_or_else(|| format!("a {width}x{height}x{depth} grid has too many voxels"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::FLOAT_EQ_EPS;
+
+    #[test]
+    fn homogeneous_volume_has_constant_density() {
+        let volume = Volume::new(0.5, Color::new(1.0, 1.0, 1.0));
+        assert_eq!(volume.density_at(Tuple4D::new_point(3.0, -2.0, 7.0)), 0.5);
+    }
+
+    #[test]
+    fn parse_ascii_grid() {
+        let grid_str = "# tiny grid\n2 1 2\n0 0.5\n1 0.25\n";
+        let grid = DensityGrid::from_ascii_str(grid_str).unwrap();
+        assert_eq!((grid.width, grid.height, grid.depth), (2, 1, 2));
+        assert_eq!(grid.voxel(1, 0, 0), 0.5);
+        assert_eq!(grid.voxel(0, 0, 1), 1.0);
+    }
+
+    #[test]
+    fn ascii_grid_with_wrong_number_of_values_fails() {
+        assert!(DensityGrid::from_ascii_str("2 2 2\n1 2 3").is_err());
+        assert!(DensityGrid::from_ascii_str("2 x 2").is_err());
+        assert!(DensityGrid::from_ascii_str("1 1 1\nfoo").is_err());
+    }
+
+    #[test]
+    fn raw_grid_maps_bytes_to_unit_range() {
+        let grid = DensityGrid::from_raw_bytes(&[0, 255, 51, 102], 2, 2, 1).unwrap();
+        assert_eq!(grid.data, vec![0.0, 1.0, 0.2, 0.4]);
+        assert!(DensityGrid::from_raw_bytes(&[0, 255], 2, 2, 1).is_err());
+    }
+
+    #[test]
+    fn grids_need_one_value_per_voxel() {
+        assert!(DensityGrid::new(2, 1, 1, vec![0.0]).is_err());
+        assert!(DensityGrid::new(usize::MAX, 2, 1, vec![]).is_err());
+        assert!(DensityGrid::from_raw_bytes(&[], usize::MAX, usize::MAX, 2).is_err());
+        assert!(DensityGrid::from_ascii_str(&format!("{} 3 1", usize::MAX)).is_err());
+    }
+
+    #[test]
+    fn grid_samples_voxel_centers_exactly() {
+        let grid = DensityGrid::new(2, 1, 1, vec![0.0, 1.0]).unwrap();
+        assert_eq!(grid.sample(Tuple4D::new_point(-0.5, 0.0, 0.0)), 0.0);
+        assert_eq!(grid.sample(Tuple4D::new_point(0.5, 0.0, 0.0)), 1.0);
+    }
+
+    #[test]
+    fn grid_interpolates_between_voxels() {
+        let grid = DensityGrid::new(2, 1, 1, vec![0.0, 1.0]).unwrap();
+        assert!((grid.sample(Tuple4D::new_point(0.0, 0.3, -0.2)) - 0.5).abs() < FLOAT_EQ_EPS);
+        assert!((grid.sample(Tuple4D::new_point(0.25, 0.0, 0.0)) - 0.75).abs() < FLOAT_EQ_EPS);
+        // clamped towards the border of the grid
+        assert_eq!(grid.sample(Tuple4D::new_point(0.9, 0.0, 0.0)), 1.0);
+    }
+
+    #[test]
+    fn grid_is_empty_outside_of_unit_cube() {
+        let grid = DensityGrid::new(1, 1, 1, vec![1.0]).unwrap();
+        assert_eq!(grid.sample(Tuple4D::new_point(0.0, 1.5, 0.0)), 0.0);
+        assert_eq!(grid.sample(Tuple4D::new_point(0.0, 0.0, 0.0)), 1.0);
+    }
+
+    #[test]
+    fn grid_scales_volume_density() {
+        let grid = DensityGrid::new(2, 1, 1, vec![0.0, 1.0]).unwrap();
+        let volume = Volume::with_grid(2.0, Color::new(1.0, 1.0, 1.0), grid);
+        assert_eq!(volume.density_at(Tuple4D::new_point(0.5, 0.0, 0.0)), 2.0);
+        assert_eq!(volume.density_at(Tuple4D::new_point(-0.5, 0.0, 0.0)), 0.0);
+    }
+}
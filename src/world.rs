@@ -0,0 +1,1023 @@
+use crate::intersection::{hit, Computations, IntersectionBuffer};
+use crate::light::lighting;
+use crate::math::{Float, Matrix4, Tuple4D};
+use crate::pixel_debug::{self, TraceEvent};
+use crate::sampler::rng_for_point;
+use crate::volume::DEFAULT_STEP_SIZE;
+use crate::{
+    Background, Color, Intersection, Light, Material, Object, PointLight, Ray, Volume,
+    DEFAULT_HIT_OFFSET,
+};
+
+// limits how often rays bounce between reflective surfaces
+pub const DEFAULT_MAX_DEPTH: usize = 5;
+pub const DEFAULT_GLOSSY_SAMPLES: usize = 8;
+
+#[derive(Debug)]
+pub struct World {
+    pub objects: Vec<Object>,
+    pub lights: Vec<Light>,
+    pub background: Background,
+    pub max_depth: usize,
+    // rays averaged for the reflection of rough materials, only the first reflection along a
+    // camera ray is sampled that often, deeper ones use a single ray
+    pub glossy_samples: usize,
+    // participating medium that fills the whole world
+    pub fog: Option<Volume>,
+    // distance secondary rays start above or below the surface they leave, large scenes need
+    // more to avoid acne and small ones less to avoid gaps at contact points
+    pub hit_offset: Float,
+}
+
+impl Default for World {
+    fn default() -> Self {
+        World {
+            objects: vec![],
+            lights: vec![],
+            background: Background::default(),
+            max_depth: DEFAULT_MAX_DEPTH,
+            glossy_samples: DEFAULT_GLOSSY_SAMPLES,
+            fog: None,
+            hit_offset: DEFAULT_HIT_OFFSET,
+        }
+    }
+}
+
+impl World {
+    pub fn new() -> World {
+        World::default()
+    }
+
+    pub fn default_world() -> World {
+        let light = PointLight::new(
+            Tuple4D::new_point(-10.0, 10.0, -10.0),
+            Color::new(1.0, 1.0, 1.0),
+        );
+
+        let mut outer = Object::sphere();
+        outer.material = Material {
+            color: Color::new(0.8, 1.0, 0.6),
+            diffuse: 0.7,
+            specular: 0.2,
+            ..Material::default()
+        };
+
+        let mut inner = Object::sphere();
+        inner.transform = Matrix4::scaling(0.5, 0.5, 0.5).into();
+
+        World {
+            objects: vec![outer, inner],
+            lights: vec![light.into()],
+            ..World::default()
+        }
+    }
+
+    pub fn intersect_world(&self, ray: &Ray) -> Vec<Intersection<'_>> {
+        let mut intersections = vec![];
+        self.intersect_world_into(ray, &mut intersections);
+        intersections
+    }
+
+    // intersect_world into a list that is reused, whatever it held before is dropped
+    pub fn intersect_world_into<'a>(
+        &'a self,
+        ray: &Ray,
+        intersections: &mut Vec<Intersection<'a>>,
+    ) {
+        intersections.clear();
+        for object in &self.objects {
+            object.intersect_into(ray, intersections);
+        }
+        intersections.sort_by(|a, b| a.t.total_cmp(&b.t));
+    }
+
+    pub fn shade_hit(&self, comps: &Computations, remaining: usize) -> Color {
+        self.shade_hit_with(comps, remaining, &mut IntersectionBuffer::new())
+    }
+
+    fn shade_hit_with<'a>(
+        &'a self,
+        comps: &Computations,
+        remaining: usize,
+        buffer: &mut IntersectionBuffer<'a>,
+    ) -> Color {
+        let surface = self
+            .lights
+            .iter()
+            .map(|light| {
+                lighting(
+                    comps.material,
+                    comps.world_to_object,
+                    light,
+                    comps.over_point,
+                    comps.eyev,
+                    comps.normalv,
+                    self.is_shadowed_at(comps.over_point, light, comps.time),
+                    comps.footprint,
+                )
+            })
+            .fold(comps.material.emissive, |acc, color| acc + color);
+        let reflected = self.reflected_color_with(comps, remaining, buffer);
+        let refracted = self.refracted_color_with(comps, remaining, buffer);
+
+        let material = comps.material;
+        if material.reflective > 0.0 && material.transparency > 0.0 {
+            let reflectance = comps.schlick();
+            return surface + reflected * reflectance + refracted * (1.0 - reflectance);
+        }
+        surface + reflected + refracted
+    }
+
+    pub fn color_at(&self, ray: &Ray) -> Color {
+        self.color_at_with(ray, &mut IntersectionBuffer::new())
+    }
+
+    // color_at that takes the lists for the intersections of all rays it casts from the buffer, so
+    // rendering many rays with the same buffer stops allocating them
+    pub fn color_at_with<'a>(&'a self, ray: &Ray, buffer: &mut IntersectionBuffer<'a>) -> Color {
+        self.trace_with(ray, self.max_depth, buffer)
+    }
+
+    // color_at with an explicit number of remaining bounces
+    pub fn trace(&self, ray: &Ray, remaining: usize) -> Color {
+        self.trace_with(ray, remaining, &mut IntersectionBuffer::new())
+    }
+
+    fn trace_with<'a>(
+        &'a self,
+        ray: &Ray,
+        remaining: usize,
+        buffer: &mut IntersectionBuffer<'a>,
+    ) -> Color {
+        let mut intersections = buffer.take();
+        self.intersect_world_into(ray, &mut intersections);
+        let color = self.trace_intersections(ray, &intersections, remaining, buffer);
+        buffer.give_back(intersections);
+        color
+    }
+
+    // trace with the intersections of the ray already found
+    pub(crate) fn trace_intersections<'a>(
+        &'a self,
+        ray: &Ray,
+        intersections: &[Intersection],
+        remaining: usize,
+        buffer: &mut IntersectionBuffer<'a>,
+    ) -> Color {
+        let depth = self.max_depth.saturating_sub(remaining);
+        pixel_debug::record_ray(depth, ray);
+        let surface_hit = hit(intersections);
+        let color = match surface_hit {
+            Some(hit) => {
+                let comps =
+                    hit.prepare_computations_with_offset(ray, intersections, self.hit_offset);
+                pixel_debug::record_hit(depth, &comps);
+                let color = self.shade_hit_with(&comps, remaining, buffer);
+                match comps.medium {
+                    Some(medium) => color.mul_color(medium.transmittance(comps.t)),
+                    None => color,
+                }
+            }
+            None => {
+                let background = self.background.color_for(ray.direction);
+                pixel_debug::record(|| TraceEvent::Miss { depth, background });
+                background
+            }
+        };
+        let end = surface_hit.map_or(Float::INFINITY, |hit| hit.t);
+        let color = self.march_volumes(ray, intersections, end, color);
+        pixel_debug::record(|| TraceEvent::Shaded { depth, color });
+        color
+    }
+
+    // single scattering through fog and volume objects in front of the surface at distance end.
+    // Light travelling from the light source to the scattering point is not attenuated
+    fn march_volumes(
+        &self,
+        ray: &Ray,
+        intersections: &[Intersection],
+        end: Float,
+        surface: Color,
+    ) -> Color {
+        let segments = volume_segments(intersections, end, ray.time);
+        if self.fog.is_none() && segments.is_empty() {
+            return surface;
+        }
+        let mut march_end = segments
+            .iter()
+            .map(|segment| segment.3)
+            .fold(0.0, Float::max);
+        let mut step_size = segments
+            .iter()
+            .filter_map(|(material, _, _, _)| material.volume.as_ref())
+            .map(|volume| volume.step_size)
+            .filter(|&step_size| step_size > 0.0)
+            .fold(Float::INFINITY, Float::min);
+        if let Some(fog) = &self.fog {
+            // beyond this distance less than 0.1% of the light makes it through
+            let visible_distance = if fog.density > 0.0 {
+                6.9 / fog.density
+            } else {
+                0.0
+            };
+            march_end = march_end.max(end.min(visible_distance));
+            if fog.step_size > 0.0 {
+                step_size = step_size.min(fog.step_size);
+            }
+        }
+        // steps that are not positive would never reach the end, those volumes are marched with
+        // the default step size instead. World::validate warns about them
+        if step_size == Float::INFINITY {
+            step_size = DEFAULT_STEP_SIZE;
+        }
+
+        let black = Color::new(0.0, 0.0, 0.0);
+        let mut transmittance = 1.0;
+        let mut scattered = black;
+        let mut t = 0.0;
+        while t < march_end {
+            let dt = step_size.min(march_end - t);
+            // the medium is sampled in the middle of the step
+            let point = ray.position(t + dt / 2.0);
+            let mut density = 0.0;
+            let mut emitted = black;
+            if let Some(fog) = &self.fog {
+                let fog_density = fog.density_at(point);
+                density += fog_density;
+                emitted = emitted + fog.color * fog_density;
+            }
+            for (material, world_to_object, start, stop) in &segments {
+                if t + dt / 2.0 < *start || t + dt / 2.0 > *stop {
+                    continue;
+                }
+                if let Some(volume) = &material.volume {
+                    let volume_density = volume.density_at(*world_to_object * point);
+                    density += volume_density;
+                    emitted = emitted + volume.color * volume_density;
+                }
+            }
+            if density > 0.0 {
+                let incoming = self
+                    .lights
+                    .iter()
+                    .filter(|light| !self.is_shadowed_at(point, light, ray.time))
+                    .fold(black, |sum, light| sum + light.intensity_at(point));
+                scattered = scattered + emitted.mul_color(incoming) * (transmittance * dt);
+                transmittance *= (-density * dt).exp();
+            }
+            t += dt;
+        }
+        scattered + surface * transmittance
+    }
+
+    pub fn reflected_color(&self, comps: &Computations, remaining: usize) -> Color {
+        self.reflected_color_with(comps, remaining, &mut IntersectionBuffer::new())
+    }
+
+    fn reflected_color_with<'a>(
+        &'a self,
+        comps: &Computations,
+        remaining: usize,
+        buffer: &mut IntersectionBuffer<'a>,
+    ) -> Color {
+        let reflective = comps.material.reflective;
+        if remaining == 0 || reflective == 0.0 {
+            return Color::new(0.0, 0.0, 0.0);
+        }
+        let material = comps.material;
+        if material.roughness <= 0.0 {
+            let reflect_ray = Ray::new(comps.over_point, comps.reflectv)
+                .with_time(comps.time)
+                .with_cone(comps.footprint, comps.spread);
+            return self.trace_with(&reflect_ray, remaining - 1, buffer) * reflective;
+        }
+        let samples = if remaining == self.max_depth {
+            self.glossy_samples.max(1)
+        } else {
+            1
+        };
+        let mut rng = rng_for_point(comps.point);
+        let sum = (0..samples).fold(Color::new(0.0, 0.0, 0.0), |sum, _| {
+            let direction = material.glossy_reflection(comps.reflectv, comps.normalv, &mut rng);
+            let reflect_ray = Ray::new(comps.over_point, direction)
+                .with_time(comps.time)
+                .with_cone(comps.footprint, comps.spread);
+            sum + self.trace_with(&reflect_ray, remaining - 1, buffer)
+        });
+        sum / samples as Float * reflective
+    }
+
+    pub fn refracted_color(&self, comps: &Computations, remaining: usize) -> Color {
+        self.refracted_color_with(comps, remaining, &mut IntersectionBuffer::new())
+    }
+
+    fn refracted_color_with<'a>(
+        &'a self,
+        comps: &Computations,
+        remaining: usize,
+        buffer: &mut IntersectionBuffer<'a>,
+    ) -> Color {
+        let transparency = comps.material.transparency;
+        if remaining == 0 || transparency == 0.0 {
+            return Color::new(0.0, 0.0, 0.0);
+        }
+
+        // Snell's law
+        let n_ratio = comps.n1 / comps.n2;
+        let cos_i = comps.eyev.dot(comps.normalv);
+        let sin2_t = n_ratio * n_ratio * (1.0 - cos_i * cos_i);
+        // total internal reflection
+        if sin2_t > 1.0 {
+            pixel_debug::record(|| TraceEvent::TotalInternalReflection {
+                depth: self.max_depth.saturating_sub(remaining),
+            });
+            return Color::new(0.0, 0.0, 0.0);
+        }
+
+        let cos_t = (1.0 - sin2_t).sqrt();
+        let direction = comps.normalv * (n_ratio * cos_i - cos_t) - comps.eyev * n_ratio;
+        let refract_ray = Ray::new(comps.under_point, direction)
+            .with_time(comps.time)
+            .with_cone(comps.footprint, comps.spread);
+        self.trace_with(&refract_ray, remaining - 1, buffer) * transparency
+    }
+
+    pub fn is_shadowed(&self, point: Tuple4D, light: &Light) -> bool {
+        self.is_shadowed_at(point, light, 0.0)
+    }
+
+    // shadow test against the objects at the given time of a motion blurred exposure
+    pub fn is_shadowed_at(&self, point: Tuple4D, light: &Light, time: Float) -> bool {
+        let (direction, distance) = light.direction_from(point);
+        let ray = Ray::new(point, direction).with_time(time);
+        self.is_occluded(&ray, distance)
+    }
+
+    // whether any surface blocks the ray before distance. Stops at the first blocker that is
+    // found instead of looking for the closest hit, which is all shadow rays need
+    pub fn is_occluded(&self, ray: &Ray, distance: Float) -> bool {
+        let blocker = self
+            .objects
+            .iter()
+            .find_map(|object| object.occluder(ray, distance));
+        pixel_debug::record_shadow_test(ray, distance, blocker);
+        blocker.is_some()
+    }
+}
+
+// (material, world to object transform, start, end) for every stretch of the ray inside a volume
+// object, cut off at end
+fn volume_segments<'a>(
+    intersections: &[Intersection<'a>],
+    end: Float,
+    time: Float,
+) -> Vec<(&'a Material, Matrix4, Float, Float)> {
+    let mut segments = vec![];
+    let mut entered: Vec<&Intersection> = vec![];
+    for intersection in intersections {
+        if intersection.material.volume.is_none() {
+            continue;
+        }
+        match entered
+            .iter()
+            .position(|inside| inside.is_same_object(intersection))
+        {
+            Some(idx) => {
+                let start = entered.remove(idx).t.max(0.0);
+                let stop = intersection.t.min(end);
+                if start < stop {
+                    segments.push((
+                        intersection.material,
+                        intersection.world_to_object(time),
+                        start,
+                        stop,
+                    ));
+                }
+            }
+            None => entered.push(intersection),
+        }
+    }
+    segments
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::consts::FRAC_1_SQRT_2;
+    use crate::shapes::Instance;
+    use crate::DirectionalLight;
+
+    use super::*;
+
+    #[test]
+    fn creating_world() {
+        let world = World::new();
+        assert!(world.objects.is_empty());
+        assert!(world.lights.is_empty());
+    }
+
+    #[test]
+    fn default_world() {
+        let world = World::default_world();
+        assert_eq!(
+            world.lights,
+            vec![Light::Point(PointLight::new(
+                Tuple4D::new_point(-10.0, 10.0, -10.0),
+                Color::new(1.0, 1.0, 1.0)
+            ))]
+        );
+        assert_eq!(world.objects.len(), 2);
+        assert_eq!(world.objects[0].material.color, Color::new(0.8, 1.0, 0.6));
+        assert_eq!(
+            world.objects[1].transform.matrix(),
+            Matrix4::scaling(0.5, 0.5, 0.5)
+        );
+    }
+
+    #[test]
+    fn intersect_world_with_ray() {
+        let world = World::default_world();
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 0.0, -5.0),
+            Tuple4D::new_vector(0.0, 0.0, 1.0),
+        );
+        let xs = world.intersect_world(&ray);
+        let ts: Vec<Float> = xs.iter().map(|x| x.t).collect();
+        assert_eq!(ts, vec![4.0, 4.5, 5.5, 6.0]);
+    }
+
+    #[test]
+    fn shading_intersection() {
+        let world = World::default_world();
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 0.0, -5.0),
+            Tuple4D::new_vector(0.0, 0.0, 1.0),
+        );
+        let intersection = Intersection::new(4.0, &world.objects[0]);
+        let comps = intersection.prepare_computations(&ray, &[intersection]);
+        assert_eq!(
+            world.shade_hit(&comps, DEFAULT_MAX_DEPTH),
+            Color::new(0.38066, 0.47583, 0.2855)
+        );
+    }
+
+    #[test]
+    fn shading_intersection_from_inside() {
+        let mut world = World::default_world();
+        world.lights = vec![Light::Point(PointLight::new(
+            Tuple4D::new_point(0.0, 0.25, 0.0),
+            Color::new(1.0, 1.0, 1.0),
+        ))];
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 0.0, 0.0),
+            Tuple4D::new_vector(0.0, 0.0, 1.0),
+        );
+        let intersection = Intersection::new(0.5, &world.objects[1]);
+        let comps = intersection.prepare_computations(&ray, &[intersection]);
+        assert_eq!(
+            world.shade_hit(&comps, DEFAULT_MAX_DEPTH),
+            Color::new(0.90498, 0.90498, 0.90498)
+        );
+    }
+
+    #[test]
+    fn shade_hit_is_given_intersection_in_shadow() {
+        let mut world = World::new();
+        world.lights = vec![Light::Point(PointLight::new(
+            Tuple4D::new_point(0.0, 0.0, -10.0),
+            Color::new(1.0, 1.0, 1.0),
+        ))];
+        world.objects.push(Object::sphere());
+        let mut second = Object::sphere();
+        second.transform = Matrix4::translation(0.0, 0.0, 10.0).into();
+        world.objects.push(second);
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 0.0, 5.0),
+            Tuple4D::new_vector(0.0, 0.0, 1.0),
+        );
+        let intersection = Intersection::new(4.0, &world.objects[1]);
+        let comps = intersection.prepare_computations(&ray, &[intersection]);
+        assert_eq!(
+            world.shade_hit(&comps, DEFAULT_MAX_DEPTH),
+            Color::new(0.1, 0.1, 0.1)
+        );
+    }
+
+    #[test]
+    fn color_when_ray_misses() {
+        let world = World::default_world();
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 0.0, -5.0),
+            Tuple4D::new_vector(0.0, 1.0, 0.0),
+        );
+        assert_eq!(world.color_at(&ray), Color::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn color_when_ray_misses_is_background() {
+        let mut world = World::default_world();
+        world.background = Background::Solid(Color::new(0.2, 0.3, 0.4));
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 0.0, -5.0),
+            Tuple4D::new_vector(0.0, 1.0, 0.0),
+        );
+        assert_eq!(world.color_at(&ray), Color::new(0.2, 0.3, 0.4));
+    }
+
+    #[test]
+    fn color_when_ray_hits() {
+        let world = World::default_world();
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 0.0, -5.0),
+            Tuple4D::new_vector(0.0, 0.0, 1.0),
+        );
+        assert_eq!(world.color_at(&ray), Color::new(0.38066, 0.47583, 0.2855));
+    }
+
+    #[test]
+    fn color_with_intersection_behind_ray() {
+        let mut world = World::default_world();
+        world.objects[0].material.ambient = 1.0;
+        world.objects[1].material.ambient = 1.0;
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 0.0, 0.75),
+            Tuple4D::new_vector(0.0, 0.0, -1.0),
+        );
+        assert_eq!(world.color_at(&ray), world.objects[1].material.color);
+    }
+
+    fn reflective_plane(reflective: Float) -> Object {
+        let mut plane = Object::plane();
+        plane.material.reflective = reflective;
+        plane.transform = Matrix4::translation(0.0, -1.0, 0.0).into();
+        plane
+    }
+
+    #[test]
+    fn reflected_color_for_nonreflective_material() {
+        let mut world = World::default_world();
+        world.objects[1].material.ambient = 1.0;
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 0.0, 0.0),
+            Tuple4D::new_vector(0.0, 0.0, 1.0),
+        );
+        let comps = Intersection::new(1.0, &world.objects[1]).prepare_computations(&ray, &[]);
+        assert_eq!(
+            world.reflected_color(&comps, DEFAULT_MAX_DEPTH),
+            Color::new(0.0, 0.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn reflected_color_for_reflective_material() {
+        let mut world = World::default_world();
+        world.objects.push(reflective_plane(0.5));
+        let value = Float::sqrt(2.0) / 2.0;
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 0.0, -3.0),
+            Tuple4D::new_vector(0.0, -value, value),
+        );
+        let comps =
+            Intersection::new(Float::sqrt(2.0), &world.objects[2]).prepare_computations(&ray, &[]);
+        assert_eq!(
+            world.reflected_color(&comps, DEFAULT_MAX_DEPTH),
+            Color::new(0.19033, 0.23791, 0.14274)
+        );
+    }
+
+    #[test]
+    fn rough_reflection_blurs_the_mirror_image() {
+        let mut world = World::default_world();
+        let mut plane = reflective_plane(0.5);
+        plane.material.roughness = 0.5;
+        world.objects.push(plane);
+        let value = Float::sqrt(2.0) / 2.0;
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 0.0, -3.0),
+            Tuple4D::new_vector(0.0, -value, value),
+        );
+        let comps =
+            Intersection::new(Float::sqrt(2.0), &world.objects[2]).prepare_computations(&ray, &[]);
+        let color = world.reflected_color(&comps, DEFAULT_MAX_DEPTH);
+        assert_ne!(color, Color::new(0.19033, 0.23791, 0.14274));
+        assert_eq!(color, world.reflected_color(&comps, DEFAULT_MAX_DEPTH));
+    }
+
+    #[test]
+    fn shade_hit_with_reflective_material() {
+        let mut world = World::default_world();
+        world.objects.push(reflective_plane(0.5));
+        let value = Float::sqrt(2.0) / 2.0;
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 0.0, -3.0),
+            Tuple4D::new_vector(0.0, -value, value),
+        );
+        let comps =
+            Intersection::new(Float::sqrt(2.0), &world.objects[2]).prepare_computations(&ray, &[]);
+        assert_eq!(
+            world.shade_hit(&comps, DEFAULT_MAX_DEPTH),
+            Color::new(0.87676, 0.92434, 0.82917)
+        );
+    }
+
+    #[test]
+    fn color_at_with_mutually_reflective_surfaces_terminates() {
+        let mut world = World::new();
+        world.lights = vec![Light::Point(PointLight::new(
+            Tuple4D::new_point(0.0, 0.0, 0.0),
+            Color::new(1.0, 1.0, 1.0),
+        ))];
+        world.objects.push(reflective_plane(1.0));
+        let mut upper = Object::plane();
+        upper.material.reflective = 1.0;
+        upper.transform = Matrix4::translation(0.0, 1.0, 0.0).into();
+        world.objects.push(upper);
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 0.0, 0.0),
+            Tuple4D::new_vector(0.0, 1.0, 0.0),
+        );
+        world.color_at(&ray);
+    }
+
+    #[test]
+    fn reflected_color_at_maximum_recursive_depth() {
+        let mut world = World::default_world();
+        world.objects.push(reflective_plane(0.5));
+        let value = Float::sqrt(2.0) / 2.0;
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 0.0, -3.0),
+            Tuple4D::new_vector(0.0, -value, value),
+        );
+        let comps =
+            Intersection::new(Float::sqrt(2.0), &world.objects[2]).prepare_computations(&ray, &[]);
+        assert_eq!(world.reflected_color(&comps, 0), Color::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn refracted_color_with_opaque_surface() {
+        let world = World::default_world();
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 0.0, -5.0),
+            Tuple4D::new_vector(0.0, 0.0, 1.0),
+        );
+        let xs = [
+            Intersection::new(4.0, &world.objects[0]),
+            Intersection::new(6.0, &world.objects[0]),
+        ];
+        let comps = xs[0].prepare_computations(&ray, &xs);
+        assert_eq!(
+            world.refracted_color(&comps, DEFAULT_MAX_DEPTH),
+            Color::new(0.0, 0.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn refracted_color_at_maximum_recursive_depth() {
+        let mut world = World::default_world();
+        world.objects[0].material.transparency = 1.0;
+        world.objects[0].material.refractive_index = 1.5;
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 0.0, -5.0),
+            Tuple4D::new_vector(0.0, 0.0, 1.0),
+        );
+        let xs = [
+            Intersection::new(4.0, &world.objects[0]),
+            Intersection::new(6.0, &world.objects[0]),
+        ];
+        let comps = xs[0].prepare_computations(&ray, &xs);
+        assert_eq!(world.refracted_color(&comps, 0), Color::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn refracted_color_under_total_internal_reflection() {
+        let mut world = World::default_world();
+        world.objects[0].material.transparency = 1.0;
+        world.objects[0].material.refractive_index = 1.5;
+        let value = Float::sqrt(2.0) / 2.0;
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 0.0, value),
+            Tuple4D::new_vector(0.0, 1.0, 0.0),
+        );
+        let xs = [
+            Intersection::new(-value, &world.objects[0]),
+            Intersection::new(value, &world.objects[0]),
+        ];
+        let comps = xs[1].prepare_computations(&ray, &xs);
+        assert_eq!(
+            world.refracted_color(&comps, DEFAULT_MAX_DEPTH),
+            Color::new(0.0, 0.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn absorption_depends_on_distance_inside_object() {
+        let mut world = World::new();
+        world.background = Background::Solid(Color::new(1.0, 1.0, 1.0));
+        let mut glass = Object::sphere();
+        glass.material = Material {
+            ambient: 0.0,
+            diffuse: 0.0,
+            specular: 0.0,
+            transparency: 1.0,
+            absorption: Color::new(0.0, 1.0, 0.5),
+            ..Material::default()
+        };
+        world.objects.push(glass);
+        let through_center = Ray::new(
+            Tuple4D::new_point(0.0, 0.0, -5.0),
+            Tuple4D::new_vector(0.0, 0.0, 1.0),
+        );
+        assert_eq!(
+            world.color_at(&through_center),
+            Color::new(1.0, Float::exp(-2.0), Float::exp(-1.0))
+        );
+        // a chord further out is shorter, so less light is absorbed
+        let off_center = Ray::new(
+            Tuple4D::new_point(0.0, 0.8, -5.0),
+            Tuple4D::new_vector(0.0, 0.0, 1.0),
+        );
+        assert_eq!(
+            world.color_at(&off_center),
+            Color::new(1.0, Float::exp(-1.2), Float::exp(-0.6))
+        );
+    }
+
+    fn transparent_floor_with_ball(floor_reflective: Float) -> World {
+        let mut world = World::default_world();
+        let mut floor = Object::plane();
+        floor.transform = Matrix4::translation(0.0, -1.0, 0.0).into();
+        floor.material.reflective = floor_reflective;
+        floor.material.transparency = 0.5;
+        floor.material.refractive_index = 1.5;
+        world.objects.push(floor);
+        let mut ball = Object::sphere();
+        ball.material.color = Color::new(1.0, 0.0, 0.0);
+        ball.material.ambient = 0.5;
+        ball.transform = Matrix4::translation(0.0, -3.5, -0.5).into();
+        world.objects.push(ball);
+        world
+    }
+
+    #[test]
+    fn shade_hit_with_transparent_material() {
+        let world = transparent_floor_with_ball(0.0);
+        let value = Float::sqrt(2.0) / 2.0;
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 0.0, -3.0),
+            Tuple4D::new_vector(0.0, -value, value),
+        );
+        let xs = [Intersection::new(Float::sqrt(2.0), &world.objects[2])];
+        let comps = xs[0].prepare_computations(&ray, &xs);
+        assert_eq!(
+            world.shade_hit(&comps, DEFAULT_MAX_DEPTH),
+            Color::new(0.93642, 0.68642, 0.68642)
+        );
+    }
+
+    #[test]
+    fn shade_hit_with_reflective_transparent_material() {
+        let world = transparent_floor_with_ball(0.5);
+        let value = Float::sqrt(2.0) / 2.0;
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 0.0, -3.0),
+            Tuple4D::new_vector(0.0, -value, value),
+        );
+        let xs = [Intersection::new(Float::sqrt(2.0), &world.objects[2])];
+        let comps = xs[0].prepare_computations(&ray, &xs);
+        assert_eq!(
+            world.shade_hit(&comps, DEFAULT_MAX_DEPTH),
+            Color::new(0.93391, 0.69643, 0.69243)
+        );
+    }
+
+    #[test]
+    fn no_shadow_when_nothing_collinear_with_point_and_light() {
+        let world = World::default_world();
+        assert!(!world.is_shadowed(Tuple4D::new_point(0.0, 10.0, 0.0), &world.lights[0]));
+    }
+
+    #[test]
+    fn shadow_when_object_between_point_and_light() {
+        let world = World::default_world();
+        assert!(world.is_shadowed(Tuple4D::new_point(10.0, -10.0, 10.0), &world.lights[0]));
+    }
+
+    #[test]
+    fn no_shadow_when_object_behind_light() {
+        let world = World::default_world();
+        assert!(!world.is_shadowed(Tuple4D::new_point(-20.0, 20.0, -20.0), &world.lights[0]));
+    }
+
+    #[test]
+    fn directional_light_casts_shadows_from_infinity() {
+        let mut world = World::default_world();
+        world.lights = vec![Light::Directional(DirectionalLight::new(
+            Tuple4D::new_vector(0.0, -1.0, 0.0),
+            Color::new(1.0, 1.0, 1.0),
+        ))];
+        assert!(world.is_shadowed(Tuple4D::new_point(0.0, -1000.0, 0.0), &world.lights[0]));
+        assert!(!world.is_shadowed(Tuple4D::new_point(0.0, 2.0, 0.0), &world.lights[0]));
+        assert!(!world.is_shadowed(Tuple4D::new_point(2.0, -1000.0, 0.0), &world.lights[0]));
+    }
+
+    #[test]
+    fn no_shadow_when_object_behind_point() {
+        let world = World::default_world();
+        assert!(!world.is_shadowed(Tuple4D::new_point(-2.0, 2.0, -2.0), &world.lights[0]));
+    }
+
+    #[test]
+    fn occlusion_stops_at_distance_and_ignores_volumes() {
+        let mut fog_ball = Object::sphere();
+        fog_ball.material.volume = Some(Volume::new(1.0, Color::new(1.0, 1.0, 1.0)));
+        let mut blocker = Object::sphere();
+        blocker.transform = Matrix4::translation(0.0, 0.0, 5.0).into();
+        let mut world = World::new();
+        world.objects.push(fog_ball);
+        world.objects.push(Object::group(vec![blocker]));
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 0.0, -5.0),
+            Tuple4D::new_vector(0.0, 0.0, 1.0),
+        );
+        assert!(!world.is_occluded(&ray, 9.0));
+        assert!(world.is_occluded(&ray, 9.5));
+        let behind = Ray::new(Tuple4D::new_point(0.0, 0.0, 7.0), ray.direction);
+        assert!(!world.is_occluded(&behind, Float::INFINITY));
+    }
+
+    #[test]
+    fn instances_with_volume_materials_do_not_occlude() {
+        let prototype = std::sync::Arc::new(Object::sphere());
+        let material = Material {
+            volume: Some(Volume::new(1.0, Color::new(1.0, 1.0, 1.0))),
+            ..Material::default()
+        };
+        let mut world = World::new();
+        world.objects.push(Object::instance(prototype.clone()));
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 0.0, -5.0),
+            Tuple4D::new_vector(0.0, 0.0, 1.0),
+        );
+        assert!(world.is_occluded(&ray, 10.0));
+        world.objects[0] = Object::new(Box::new(Instance::new(prototype).with_material(material)));
+        assert!(!world.is_occluded(&ray, 10.0));
+    }
+
+    #[test]
+    fn shading_accumulates_all_lights() {
+        let mut world = World::default_world();
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 0.0, -5.0),
+            Tuple4D::new_vector(0.0, 0.0, 1.0),
+        );
+        let single = world.color_at(&ray);
+        world.lights.push(world.lights[0]);
+        assert_eq!(world.color_at(&ray), single * 2.0);
+    }
+
+    #[test]
+    fn shadows_are_tested_per_light() {
+        let mut world = World::default_world();
+        world.lights.push(Light::Point(PointLight::new(
+            Tuple4D::new_point(20.0, -20.0, 20.0),
+            Color::new(1.0, 1.0, 1.0),
+        )));
+        let point = Tuple4D::new_point(10.0, -10.0, 10.0);
+        assert!(world.is_shadowed(point, &world.lights[0]));
+        assert!(!world.is_shadowed(point, &world.lights[1]));
+    }
+
+    #[test]
+    fn world_without_lights_is_black() {
+        let mut world = World::default_world();
+        world.lights.clear();
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 0.0, -5.0),
+            Tuple4D::new_vector(0.0, 0.0, 1.0),
+        );
+        assert_eq!(world.color_at(&ray), Color::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn fog_dims_distant_surfaces() {
+        let mut world = World::new();
+        let mut sphere = Object::sphere();
+        sphere.material.emissive = Color::new(1.0, 1.0, 1.0);
+        world.objects.push(sphere);
+        world.fog = Some(Volume::new(0.25, Color::new(1.0, 1.0, 1.0)));
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 0.0, -5.0),
+            Tuple4D::new_vector(0.0, 0.0, 1.0),
+        );
+        let expected = Float::exp(-1.0);
+        assert_eq!(
+            world.color_at(&ray),
+            Color::new(expected, expected, expected)
+        );
+    }
+
+    #[test]
+    fn volumes_without_a_positive_step_size_use_the_default() {
+        let mut world = World::new();
+        world.objects.push(Object::sphere());
+        world.lights.push(
+            PointLight::new(
+                Tuple4D::new_point(-2.0, 3.0, -4.0),
+                Color::new(1.0, 1.0, 1.0),
+            )
+            .into(),
+        );
+        world.fog = Some(Volume::new(0.25, Color::new(1.0, 1.0, 1.0)));
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 0.0, -5.0),
+            Tuple4D::new_vector(0.0, 0.0, 1.0),
+        );
+        let expected = world.color_at(&ray);
+        for step_size in [0.0, -1.0, Float::NAN] {
+            world.fog.as_mut().unwrap().step_size = step_size;
+            assert_eq!(world.color_at(&ray), expected);
+        }
+    }
+
+    #[test]
+    fn volume_objects_have_invisible_surfaces() {
+        let mut world = World::new();
+        world.background = Background::Solid(Color::new(0.2, 0.4, 0.6));
+        let mut container = Object::sphere();
+        container.material.volume = Some(Volume::new(0.0, Color::new(1.0, 1.0, 1.0)));
+        world.objects.push(container);
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 0.0, -5.0),
+            Tuple4D::new_vector(0.0, 0.0, 1.0),
+        );
+        assert_eq!(world.color_at(&ray), Color::new(0.2, 0.4, 0.6));
+    }
+
+    #[test]
+    fn shadows_inside_volumes_are_darker() {
+        // light shines from above into a box of fog, half of it is covered by a plate
+        let mut world = World::new();
+        world.lights.push(
+            PointLight::new(
+                Tuple4D::new_point(0.0, 10.0, 0.0),
+                Color::new(1.0, 1.0, 1.0),
+            )
+            .into(),
+        );
+        let mut fog = Object::sphere();
+        fog.transform = Matrix4::scaling(3.0, 3.0, 3.0).into();
+        fog.material.volume = Some(Volume::new(0.2, Color::new(1.0, 1.0, 1.0)));
+        world.objects.push(fog);
+        let mut plate = Object::sphere();
+        plate.transform =
+            (Matrix4::translation(2.0, 5.0, 0.0) * Matrix4::scaling(2.0, 0.1, 4.0)).into();
+        world.objects.push(plate);
+
+        let lit = Ray::new(
+            Tuple4D::new_point(-1.5, 0.0, -5.0),
+            Tuple4D::new_vector(0.0, 0.0, 1.0),
+        );
+        let shadowed = Ray::new(
+            Tuple4D::new_point(1.5, 0.0, -5.0),
+            Tuple4D::new_vector(0.0, 0.0, 1.0),
+        );
+        let lit_color = world.color_at(&lit);
+        let shadowed_color = world.color_at(&shadowed);
+        assert!(lit_color.r > 0.1, "{lit_color:?}");
+        assert!(shadowed_color.r < lit_color.r / 2.0, "{shadowed_color:?}");
+    }
+
+    #[test]
+    fn reused_buffer_gives_the_same_colors() {
+        let world = transparent_floor_with_ball(0.5);
+        let mut buffer = IntersectionBuffer::new();
+        for x in [-0.5, 0.0, 0.5] {
+            let ray = Ray::new(
+                Tuple4D::new_point(x, 0.0, -3.0),
+                Tuple4D::new_vector(0.0, -FRAC_1_SQRT_2, FRAC_1_SQRT_2),
+            );
+            assert_eq!(world.color_at_with(&ray, &mut buffer), world.color_at(&ray));
+        }
+        // the list is cleared before it is filled again
+        let ray = Ray::new(
+            Tuple4D::new_point(0.0, 0.0, -5.0),
+            Tuple4D::new_vector(0.0, 0.0, 1.0),
+        );
+        let mut xs = world.intersect_world(&ray);
+        world.intersect_world_into(&ray, &mut xs);
+        assert_eq!(xs.len(), 4);
+    }
+
+    #[test]
+    fn volume_segments_start_at_ray_origin() {
+        let mut container = Object::sphere();
+        container.material.volume = Some(Volume::new(1.0, Color::new(1.0, 1.0, 1.0)));
+        let xs = [
+            Intersection::new(-1.0, &container),
+            Intersection::new(1.0, &container),
+        ];
+        let segments = volume_segments(&xs, Float::INFINITY, 0.0);
+        assert_eq!(segments.len(), 1);
+        assert_eq!((segments[0].2, segments[0].3), (0.0, 1.0));
+        assert!(volume_segments(&xs, 0.5, 0.0)[0].3 == 0.5);
+    }
+}